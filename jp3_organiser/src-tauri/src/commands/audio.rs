@@ -2,16 +2,83 @@
 //!
 //! Handles:
 //! - Processing selected audio files
-//! - Extracting ID3 metadata
+//! - Extracting ID3 and APE tag metadata (in parallel, streamed via events)
+//! - Detecting the real format from file headers
 //! - Assigning tracking IDs
 //! - AcoustID lookups with rate limiting (2 requests/second)
+//! - Persisting failed files and retrying them
+//! - Opt-in submission of user-confirmed metadata back to AcoustID
+//! - Grouping just-processed files whose fingerprints match
+use id3::frame::PictureType;
 use id3::{Tag, TagLike};
+use image::ImageReader;
+use std::io::Cursor;
 use std::path::Path;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
 use uuid::Uuid;
 
-use crate::models::{AudioMetadata, MetadataStatus, MetadataSource, ProcessedFilesResult, TrackedAudioFile};
-use crate::services::fingerprint_service::{lookup_acoustid, process_audio_fingerprint, rate_limit_delay};
-use crate::services::metadata_ranking_service::extract_metadata_from_acoustic_json;
+use crate::commands::config::get_acoustid_submission_settings;
+use crate::models::{
+    AcoustIdStatus, AcoustIdSubmissionResult, AudioMetadata, EmbeddedArtwork, ExtendedTags,
+    FailedImport, FingerprintGroup, FingerprintGroupingResult, FingerprintStatus, Id3Status,
+    MetadataSource, MetadataStatus, MusicBrainzStatus, ProcessedFilesResult, TrackedAudioFile,
+};
+use crate::services::failed_import_service::{
+    load_failed_imports, record_outcomes, save_failed_imports,
+};
+use crate::services::fingerprint_match_service::{
+    decode_fingerprint, group_matching_fingerprints, FingerprintCandidate,
+};
+use crate::services::fingerprint_service::{
+    acoustid_api_key, lookup_acoustid, process_audio_fingerprint, rate_limit_delay,
+    submit_acoustid,
+};
+use crate::services::folder_scan_service::SUPPORTED_AUDIO_EXTENSIONS;
+use crate::services::format_sniff_service::apply_detected_format;
+use crate::services::import_session_service::ImportSessions;
+use crate::services::metadata_ranking_service::extract_match_from_acoustic_json;
+use crate::services::parallel_io_service::{io_worker_count, map_ordered};
+use crate::services::power_service::keep_awake;
+use crate::services::tag_reader_service::read_file_tags;
+
+/// Event emitted as each file's tags are read during `process_audio_files`
+pub const EVENT_METADATA_EXTRACTED: &str = "import://metadata-extracted";
+
+/// Payload for `import://metadata-extracted`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataExtractedEvent {
+    /// Position of the file in the submitted list
+    pub index: usize,
+    /// Number of files submitted
+    pub total: usize,
+    /// The file with its tag metadata (before any AcoustID lookup)
+    pub file: TrackedAudioFile,
+}
+
+/// Whether AcoustID lookups can run. Checked once per batch; logs a single
+/// warning when the API key is missing.
+pub(crate) fn acoustid_configured() -> bool {
+    let configured = acoustid_api_key().is_some();
+    if !configured {
+        log::warn!("ACOUSTIC_ID_API_KEY not set; skipping AcoustID lookups for this batch");
+    }
+    configured
+}
+
+/// Summarize a processed batch, flagging it if AcoustID was skipped.
+pub(crate) fn batch_result(
+    files: Vec<TrackedAudioFile>,
+    acoustid_enabled: bool,
+) -> ProcessedFilesResult {
+    let result = ProcessedFilesResult::from_files(files);
+    if acoustid_enabled {
+        result
+    } else {
+        result.without_acoustid_key()
+    }
+}
 
 /// Get audio metadata from AcoustID API for a single file.
 ///
@@ -66,21 +133,45 @@ pub async fn get_audio_metadata_from_acoustic_id(
 ///
 /// For each file:
 /// 1. Assigns a unique tracking ID
-/// 2. Attempts to extract ID3 metadata
+/// 2. Attempts to extract tag metadata (all files in parallel, each result
+///    emitted as an `import://metadata-extracted` event)
 /// 3. Looks up in AcoustID with rate limiting (500ms between API calls)
 /// 4. Determines metadata status (Complete/Incomplete/Error)
 ///
-/// Returns all files with their tracking info and metadata status.
+/// Returns all files with their tracking info and metadata status, in the
+/// order they were given. Without an AcoustID API key the lookups are
+/// skipped and the result carries a single warning with `needsAcoustidKey`.
 ///
 /// **Rate Limiting**: API calls are spaced 500ms apart to stay under
 /// the 3 requests/second limit. With retry logic, this ensures we
 /// don't get rate limited even under heavy use.
 #[tauri::command]
-pub async fn process_audio_files(file_paths: Vec<String>) -> Result<ProcessedFilesResult, String> {
-    let mut tracked_files: Vec<TrackedAudioFile> = Vec::with_capacity(file_paths.len());
+pub async fn process_audio_files(
+    app: AppHandle,
+    file_paths: Vec<String>,
+) -> Result<ProcessedFilesResult, String> {
     let total_files = file_paths.len();
+    let _awake = keep_awake("Processing audio files");
+
+    // Tag reads are local IO, so do them all up front in parallel and
+    // stream each result to the UI; the AcoustID pass below stays serial.
+    let extracted = extract_metadata_parallel(&file_paths, |index, file| {
+        let event = MetadataExtractedEvent {
+            index,
+            total: total_files,
+            file: file.clone(),
+        };
+        if let Err(e) = app.emit(EVENT_METADATA_EXTRACTED, event) {
+            log::warn!("Failed to emit {}: {}", EVENT_METADATA_EXTRACTED, e);
+        }
+    });
 
-    for (index, file_path) in file_paths.into_iter().enumerate() {
+    let acoustid_enabled = acoustid_configured();
+    let mut tracked_files: Vec<TrackedAudioFile> = Vec::with_capacity(total_files);
+
+    for (index, mut tracked_file) in extracted.into_iter().enumerate() {
+        let file_path = tracked_file.file_path.clone();
+        let tracking_id = tracked_file.tracking_id.clone();
         log::info!(
             "Processing file {}/{}: {}",
             index + 1,
@@ -88,38 +179,8 @@ pub async fn process_audio_files(file_paths: Vec<String>) -> Result<ProcessedFil
             file_path
         );
 
-        let tracking_id = Uuid::new_v4().to_string();
-        let mut tracked_file = TrackedAudioFile::new(tracking_id.clone(), file_path.clone());
-
-        log::info!("File extension: {}", tracked_file.file_extension);
-
-        // Extract metadata based on file extension
-        match tracked_file.file_extension.as_str() {
-            "mp3" => {
-                log::info!("Extracting ID3 metadata for MP3 file");
-                extract_id3_metadata(&mut tracked_file);
-                // Mark as ID3 source initially (may be overwritten by AcoustID)
-                if tracked_file.metadata.is_complete() {
-                    tracked_file.metadata_source = MetadataSource::Id3;
-                }
-            }
-            "wav" | "flac" | "m4a" | "ogg" | "opus" => {
-                log::info!(
-                    "Skipping ID3 extraction for {} file (not supported yet)",
-                    tracked_file.file_extension
-                );
-                // Mark as incomplete but don't set error_message - we'll try AcoustID
-                tracked_file.metadata_status = MetadataStatus::Incomplete;
-            }
-            _ => {
-                log::warn!("Unsupported file format: {}", tracked_file.file_extension);
-                tracked_file.metadata_status = MetadataStatus::Error;
-                tracked_file.error_message = Some("Unsupported file format".to_string());
-            }
-        }
-
         // Apply rate limiting before API call (except for first file)
-        if index > 0 {
+        if index > 0 && acoustid_enabled {
             rate_limit_delay().await;
         }
 
@@ -131,12 +192,19 @@ pub async fn process_audio_files(file_paths: Vec<String>) -> Result<ProcessedFil
                 "Fingerprint processing failed for file: {}",
                 file_path
             );
+            tracked_file.providers.fingerprint = FingerprintStatus::Failed;
             if tracked_file.error_message.is_none() {
                 tracked_file.error_message = audio_finger_print.error_message;
             }
             tracked_files.push(tracked_file);
             continue;
         }
+        tracked_file.providers.fingerprint = FingerprintStatus::Ok;
+
+        if !acoustid_enabled {
+            tracked_files.push(tracked_file);
+            continue;
+        }
 
         // Lookup in AcoustID
         log::info!(
@@ -150,14 +218,18 @@ pub async fn process_audio_files(file_paths: Vec<String>) -> Result<ProcessedFil
                 log::info!("Successfully got AcousticID result for file: {}", file_path);
 
                 // Parse and rank the JSON to gather the correct metadata
-                match extract_metadata_from_acoustic_json(&result_json) {
-                    Ok(extracted_metadata) => {
+                match extract_match_from_acoustic_json(&result_json) {
+                    Ok((extracted_metadata, confidence)) => {
                         log::info!(
                             "Extracted metadata from AcousticID JSON for file: {}",
                             file_path
                         );
-                        tracked_file.metadata = extracted_metadata;
+                        tracked_file.metadata =
+                            merge_fingerprint_metadata(&tracked_file.metadata, extracted_metadata);
                         tracked_file.metadata_source = MetadataSource::Fingerprint;
+                        tracked_file.confidence = Some(confidence);
+                        tracked_file.providers.acoustid = AcoustIdStatus::Matched;
+                        tracked_file.providers.musicbrainz = MusicBrainzStatus::Pending;
                         tracked_file.update_status();
                         log::info!("Final metadata: {:?}", tracked_file.metadata);
                     }
@@ -167,6 +239,7 @@ pub async fn process_audio_files(file_paths: Vec<String>) -> Result<ProcessedFil
                             file_path,
                             e
                         );
+                        tracked_file.providers.acoustid = AcoustIdStatus::NoMatch;
                         // Keep ID3 source if we had it, otherwise mark as unknown
                         if tracked_file.error_message.is_none() {
                             tracked_file.error_message =
@@ -181,6 +254,7 @@ pub async fn process_audio_files(file_paths: Vec<String>) -> Result<ProcessedFil
                     file_path,
                     e
                 );
+                tracked_file.providers.acoustid = AcoustIdStatus::Error;
                 // Keep ID3 source if we had it, otherwise mark as unknown
                 if tracked_file.error_message.is_none() {
                     tracked_file.error_message = Some(format!("AcousticID lookup failed: {}", e));
@@ -196,42 +270,133 @@ pub async fn process_audio_files(file_paths: Vec<String>) -> Result<ProcessedFil
         tracked_files.len()
     );
 
-    Ok(ProcessedFilesResult::from_files(tracked_files))
+    record_outcomes(&app, &tracked_files);
+
+    Ok(batch_result(tracked_files, acoustid_enabled))
+}
+
+/// Read tags for a list of files on a bounded worker pool.
+///
+/// Each file gets a fresh tracking ID and goes through the same
+/// per-extension handling as the processing pipeline. `on_complete` is
+/// called with the input index as each file finishes; the returned files
+/// are in input order.
+pub fn extract_metadata_parallel(
+    file_paths: &[String],
+    on_complete: impl Fn(usize, &TrackedAudioFile) + Sync,
+) -> Vec<TrackedAudioFile> {
+    log::info!(
+        "Extracting metadata for {} files with {} workers",
+        file_paths.len(),
+        io_worker_count(file_paths.len())
+    );
+
+    map_ordered(
+        file_paths,
+        |file_path| {
+            let tracking_id = Uuid::new_v4().to_string();
+            let mut tracked_file = TrackedAudioFile::new(tracking_id, file_path.clone());
+            extract_initial_metadata(&mut tracked_file);
+            tracked_file
+        },
+        on_complete,
+    )
+}
+
+/// Extract whatever metadata the file's format allows before fingerprinting.
+///
+/// Returns false if the format is unsupported (the file is marked as an
+/// error and should not be fingerprinted).
+fn extract_initial_metadata(tracked_file: &mut TrackedAudioFile) -> bool {
+    log::info!("File extension: {}", tracked_file.file_extension);
+
+    // Route by what the file really is, not what it is named
+    let format_extension = apply_detected_format(tracked_file);
+    if matches!(
+        tracked_file.metadata_status,
+        MetadataStatus::Error | MetadataStatus::ProtectedFile
+    ) {
+        return false;
+    }
+
+    match format_extension.as_str() {
+        "mp3" | "wav" | "flac" | "m4a" | "aac" | "ogg" | "opus" => {
+            log::info!("Extracting tag metadata for {} file", format_extension);
+            extract_tag_metadata(tracked_file);
+            // Only a broken MP3 tag is fatal; other containers still get fingerprinted
+            if format_extension != "mp3" && tracked_file.metadata_status == MetadataStatus::Error {
+                log::warn!(
+                    "Could not read tags from {}: {:?}",
+                    tracked_file.file_path,
+                    tracked_file.error_message.take()
+                );
+                tracked_file.metadata_status = MetadataStatus::Incomplete;
+            }
+            // Mark as ID3 source initially (may be overwritten by AcoustID)
+            if tracked_file.metadata.is_complete() {
+                tracked_file.metadata_source = MetadataSource::Id3;
+            }
+            true
+        }
+        _ => {
+            log::warn!("Unsupported file format: {}", tracked_file.file_extension);
+            tracked_file.metadata_status = MetadataStatus::Error;
+            tracked_file.error_message = Some("Unsupported file format".to_string());
+            false
+        }
+    }
+}
+
+/// Fingerprint metadata, plus the ID3 fields AcoustID can't provide.
+///
+/// The genre and listening history (rating, play count) are always kept.
+/// The album artist and disc number are kept only when the match is on the
+/// same album, since they say nothing about a different release.
+fn merge_fingerprint_metadata(id3: &AudioMetadata, fingerprint: AudioMetadata) -> AudioMetadata {
+    let same_album = match (&id3.album, &fingerprint.album) {
+        (Some(a), Some(b)) => a.trim().eq_ignore_ascii_case(b.trim()),
+        _ => false,
+    };
+    AudioMetadata {
+        genre: id3.genre.clone(),
+        album_artist: id3.album_artist.clone().filter(|_| same_album),
+        disc_number: id3.disc_number.filter(|_| same_album),
+        rating: id3.rating,
+        play_count: id3.play_count,
+        ..fingerprint
+    }
 }
 
-/// Extract ID3 metadata from an MP3 file.
-fn extract_id3_metadata(tracked_file: &mut TrackedAudioFile) {
+/// Extract tag metadata from any supported audio file.
+///
+/// Every tag in the file (ID3v2, MP4, Vorbis comments, APE, ID3v1...) is read
+/// and merged field by field, so legacy files with only ID3v1 or APE tags
+/// still come out complete.
+pub(crate) fn extract_tag_metadata(tracked_file: &mut TrackedAudioFile) {
     let path = Path::new(&tracked_file.file_path);
 
-    match Tag::read_from_path(path) {
-        Ok(tag) => {
-            tracked_file.metadata = AudioMetadata {
-                title: tag.title().map(|s| s.to_string()),
-                artist: tag.artist().map(|s| s.to_string()),
-                album: tag.album().map(|s| s.to_string()),
-                track_number: tag.track(),
-                year: tag.year(),
-                duration_secs: tag.duration(),
-                release_mbid: None, // ID3 tags don't contain MBID
-                artist_mbid: None, // ID3 tags don't contain Artist MBID
-            };
+    match read_file_tags(path) {
+        Ok(tags) if tags.found.is_empty() => {
+            // File has no tag at all
+            tracked_file.metadata = AudioMetadata::default();
+            tracked_file.metadata_status = MetadataStatus::Incomplete;
+        }
+        Ok(tags) => {
+            tracked_file.metadata = tags.metadata;
+            let metadata = &tracked_file.metadata;
+            if metadata.title.is_some() || metadata.artist.is_some() || metadata.album.is_some() {
+                tracked_file.providers.id3 = Id3Status::Ok;
+            }
             tracked_file.update_status();
             log::info!(
-                "ID3 data extracted: {:?}",
-                tag.artist().map(|s| s.to_string())
+                "Tag data extracted from {:?}: {:?}",
+                tags.found,
+                tracked_file.metadata.artist
             );
         }
-        Err(id3::Error {
-            kind: id3::ErrorKind::NoTag,
-            ..
-        }) => {
-            // File has no ID3 tag at all
-            tracked_file.metadata = AudioMetadata::default();
-            tracked_file.metadata_status = MetadataStatus::Incomplete;
-        }
         Err(e) => {
             tracked_file.metadata_status = MetadataStatus::Error;
-            tracked_file.error_message = Some(format!("Failed to read ID3 tag: {}", e));
+            tracked_file.error_message = Some(e);
         }
     }
 }
@@ -245,37 +410,97 @@ fn extract_id3_metadata(tracked_file: &mut TrackedAudioFile) {
 /// The frontend is responsible for rate limiting by waiting between calls.
 /// Recommended: wait 500ms between calls to stay under AcoustID's 3/sec limit.
 #[tauri::command]
-pub async fn process_single_audio_file(file_path: String) -> Result<TrackedAudioFile, String> {
-    log::info!("Processing single file: {}", file_path);
+pub async fn process_single_audio_file(
+    app: AppHandle,
+    file_path: String,
+) -> Result<TrackedAudioFile, String> {
+    let tracked_file = run_single_file_pipeline(file_path, acoustid_configured()).await;
+    record_outcomes(&app, std::slice::from_ref(&tracked_file));
+    Ok(tracked_file)
+}
 
-    let tracking_id = Uuid::new_v4().to_string();
-    let mut tracked_file = TrackedAudioFile::new(tracking_id.clone(), file_path.clone());
+/// Get the persisted list of files that failed to import.
+#[tauri::command]
+pub fn get_failed_imports(app: AppHandle) -> Result<Vec<FailedImport>, String> {
+    load_failed_imports(&app)
+}
 
-    log::info!("File extension: {}", tracked_file.file_extension);
+/// Clear the persisted failed-imports list.
+#[tauri::command]
+pub fn clear_failed_imports(app: AppHandle) -> Result<(), String> {
+    save_failed_imports(&app, &[])
+}
 
-    // Extract metadata based on file extension
-    match tracked_file.file_extension.as_str() {
-        "mp3" => {
-            log::info!("Extracting ID3 metadata for MP3 file");
-            extract_id3_metadata(&mut tracked_file);
-            // Mark as ID3 source initially (may be overwritten by AcoustID)
-            if tracked_file.metadata.is_complete() {
-                tracked_file.metadata_source = MetadataSource::Id3;
-            }
-        }
-        "wav" | "flac" | "m4a" | "ogg" | "opus" => {
-            log::info!(
-                "Skipping ID3 extraction for {} file (not supported yet)",
-                tracked_file.file_extension
-            );
-            tracked_file.metadata_status = MetadataStatus::Incomplete;
+/// Re-run every previously failed file through the processing pipeline.
+///
+/// Files that now succeed are removed from the failed list; files that
+/// fail again have their reason and attempt count updated. Files that no
+/// longer exist on disk are dropped from the list.
+#[tauri::command]
+pub async fn retry_failed_imports(app: AppHandle) -> Result<ProcessedFilesResult, String> {
+    let failed = load_failed_imports(&app)?;
+    log::info!("Retrying {} failed imports", failed.len());
+
+    let acoustid_enabled = acoustid_configured();
+    let mut retained = Vec::with_capacity(failed.len());
+    let mut tracked_files = Vec::with_capacity(failed.len());
+
+    for (index, entry) in failed.into_iter().enumerate() {
+        if !Path::new(&entry.file_path).exists() {
+            log::warn!("Dropping failed import (file missing): {}", entry.file_path);
+            continue;
         }
-        _ => {
-            log::warn!("Unsupported file format: {}", tracked_file.file_extension);
-            tracked_file.metadata_status = MetadataStatus::Error;
-            tracked_file.error_message = Some("Unsupported file format".to_string());
-            return Ok(tracked_file);
+        if index > 0 && acoustid_enabled {
+            rate_limit_delay().await;
         }
+        tracked_files
+            .push(run_single_file_pipeline(entry.file_path.clone(), acoustid_enabled).await);
+        retained.push(entry);
+    }
+
+    // Missing files were skipped above, so only the retried entries remain
+    save_failed_imports(&app, &retained)?;
+    record_outcomes(&app, &tracked_files);
+
+    Ok(batch_result(tracked_files, acoustid_enabled))
+}
+
+/// Run one file through ID3 extraction, fingerprinting and AcoustID lookup.
+async fn run_single_file_pipeline(file_path: String, use_acoustid: bool) -> TrackedAudioFile {
+    run_file_pipeline(Uuid::new_v4().to_string(), file_path, use_acoustid)
+        .await
+        .file
+}
+
+/// Everything the processing pipeline learned about one file.
+pub(crate) struct PipelineOutput {
+    pub file: TrackedAudioFile,
+    /// (fingerprint, duration) if fpcalc succeeded
+    pub fingerprint: Option<(String, u32)>,
+    /// Raw AcoustID lookup response, if the lookup succeeded
+    pub acoustid_response: Option<serde_json::Value>,
+}
+
+/// Run one file through the pipeline, keeping intermediate results.
+///
+/// With `use_acoustid` off the file is still fingerprinted, but keeps its
+/// tag metadata without a lookup.
+pub(crate) async fn run_file_pipeline(
+    tracking_id: String,
+    file_path: String,
+    use_acoustid: bool,
+) -> PipelineOutput {
+    log::info!("Processing single file: {}", file_path);
+
+    let mut tracked_file = TrackedAudioFile::new(tracking_id.clone(), file_path.clone());
+    let mut acoustid_response = None;
+
+    if !extract_initial_metadata(&mut tracked_file) {
+        return PipelineOutput {
+            file: tracked_file,
+            fingerprint: None,
+            acoustid_response: None,
+        };
     }
 
     // Generate fingerprint for Acoustic ID
@@ -283,10 +508,28 @@ pub async fn process_single_audio_file(file_path: String) -> Result<TrackedAudio
 
     if audio_finger_print.fingerprint_status == MetadataStatus::Failed {
         log::error!("Fingerprint processing failed for file: {}", file_path);
+        tracked_file.providers.fingerprint = FingerprintStatus::Failed;
         if tracked_file.error_message.is_none() {
             tracked_file.error_message = audio_finger_print.error_message;
         }
-        return Ok(tracked_file);
+        return PipelineOutput {
+            file: tracked_file,
+            fingerprint: None,
+            acoustid_response: None,
+        };
+    }
+    tracked_file.providers.fingerprint = FingerprintStatus::Ok;
+    let output_fingerprint = Some((
+        audio_finger_print.fingerprint_id.clone(),
+        audio_finger_print.duration_seconds,
+    ));
+
+    if !use_acoustid {
+        return PipelineOutput {
+            file: tracked_file,
+            fingerprint: output_fingerprint,
+            acoustid_response: None,
+        };
     }
 
     // Lookup in AcoustID
@@ -302,14 +545,18 @@ pub async fn process_single_audio_file(file_path: String) -> Result<TrackedAudio
             log::info!("Successfully got AcousticID result for file: {}", file_path);
 
             // We extract the metatdata from the Ranking System function
-            match extract_metadata_from_acoustic_json(&result_json) {
-                Ok(extracted_metadata) => {
+            match extract_match_from_acoustic_json(&result_json) {
+                Ok((extracted_metadata, confidence)) => {
                     log::info!(
                         "Extracted metadata from AcousticID JSON for file: {}",
                         file_path
                     );
-                    tracked_file.metadata = extracted_metadata;
+                    tracked_file.metadata =
+                        merge_fingerprint_metadata(&tracked_file.metadata, extracted_metadata);
                     tracked_file.metadata_source = MetadataSource::Fingerprint;
+                    tracked_file.confidence = Some(confidence);
+                    tracked_file.providers.acoustid = AcoustIdStatus::Matched;
+                    tracked_file.providers.musicbrainz = MusicBrainzStatus::Pending;
                     tracked_file.update_status();
                     log::info!("Final metadata: {:?}", tracked_file.metadata);
                 }
@@ -319,6 +566,7 @@ pub async fn process_single_audio_file(file_path: String) -> Result<TrackedAudio
                         file_path,
                         e
                     );
+                    tracked_file.providers.acoustid = AcoustIdStatus::NoMatch;
                     // Keep ID3 source if we had it, otherwise mark as unknown
                     if tracked_file.error_message.is_none() {
                         tracked_file.error_message =
@@ -326,6 +574,7 @@ pub async fn process_single_audio_file(file_path: String) -> Result<TrackedAudio
                     }
                 }
             }
+            acoustid_response = Some(result_json);
         }
         Err(e) => {
             log::error!(
@@ -333,6 +582,7 @@ pub async fn process_single_audio_file(file_path: String) -> Result<TrackedAudio
                 file_path,
                 e
             );
+            tracked_file.providers.acoustid = AcoustIdStatus::Error;
             // Keep ID3 source if we had it, otherwise mark as unknown
             if tracked_file.error_message.is_none() {
                 tracked_file.error_message = Some(format!("AcousticID lookup failed: {}", e));
@@ -341,17 +591,29 @@ pub async fn process_single_audio_file(file_path: String) -> Result<TrackedAudio
     }
 
     log::info!("Finished processing file: {}", file_path);
-    Ok(tracked_file)
+    PipelineOutput {
+        file: tracked_file,
+        fingerprint: output_fingerprint,
+        acoustid_response,
+    }
 }
 
-/// Get metadata for a single audio file by its path (ID3 only, no AcoustID).
+/// Get metadata for a single audio file by its path (tags only, no AcoustID).
+///
+/// Alongside the usual fields, MP3s get `extended_tags`: composer, the
+/// compilation flag and any embedded artwork with its dimensions.
 #[tauri::command]
 pub fn get_audio_metadata(file_path: String) -> Result<TrackedAudioFile, String> {
     let tracking_id = Uuid::new_v4().to_string();
     let mut tracked_file = TrackedAudioFile::new(tracking_id, file_path);
 
-    if tracked_file.file_extension == "mp3" {
-        extract_id3_metadata(&mut tracked_file);
+    if SUPPORTED_AUDIO_EXTENSIONS.contains(&tracked_file.file_extension.as_str()) {
+        extract_tag_metadata(&mut tracked_file);
+        if tracked_file.file_extension == "mp3" {
+            if let Ok(tag) = Tag::read_from_path(&tracked_file.file_path) {
+                tracked_file.extended_tags = Some(read_extended_tags(&tag));
+            }
+        }
     } else {
         tracked_file.metadata_status = MetadataStatus::Incomplete;
         tracked_file.error_message = Some(format!(
@@ -362,3 +624,129 @@ pub fn get_audio_metadata(file_path: String) -> Result<TrackedAudioFile, String>
 
     Ok(tracked_file)
 }
+
+/// Composer, compilation flag and embedded artwork from an ID3 tag.
+fn read_extended_tags(tag: &Tag) -> ExtendedTags {
+    let composer = tag
+        .text_for_frame_id("TCOM")
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty());
+    let compilation = tag
+        .text_for_frame_id("TCMP")
+        .is_some_and(|flag| flag.trim() == "1");
+
+    let picture = tag
+        .pictures()
+        .find(|p| p.picture_type == PictureType::CoverFront)
+        .or_else(|| tag.pictures().next());
+    let artwork = picture.map(|picture| {
+        let dimensions = ImageReader::new(Cursor::new(&picture.data))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok());
+        EmbeddedArtwork {
+            mime_type: picture.mime_type.clone(),
+            width: dimensions.map(|(width, _)| width),
+            height: dimensions.map(|(_, height)| height),
+            size_bytes: picture.data.len() as u64,
+        }
+    });
+
+    ExtendedTags {
+        composer,
+        compilation,
+        artwork,
+    }
+}
+
+/// Submit a file's fingerprint with user-entered metadata to AcoustID.
+///
+/// Intended for files AcoustID couldn't identify that the user then filled
+/// in manually. Only runs if the user has opted in and set their AcoustID
+/// user key; title and artist are required so we never submit empty data.
+#[tauri::command]
+pub async fn submit_acoustid_fingerprint(
+    app: AppHandle,
+    file_path: String,
+    metadata: AudioMetadata,
+) -> Result<AcoustIdSubmissionResult, String> {
+    let settings = get_acoustid_submission_settings(app)?;
+    let user_key = match (settings.enabled, settings.user_key) {
+        (true, Some(key)) => key,
+        (false, _) => {
+            return Err("AcoustID submission is disabled. Enable it in settings first.".to_string())
+        }
+        (true, None) => return Err("AcoustID user key not set".to_string()),
+    };
+
+    if metadata.title.is_none() || metadata.artist.is_none() {
+        return Err("Title and artist are required to submit to AcoustID".to_string());
+    }
+
+    let fingerprint = process_audio_fingerprint(&file_path, Uuid::new_v4().to_string());
+    if fingerprint.fingerprint_status == MetadataStatus::Failed {
+        return Err(fingerprint
+            .error_message
+            .unwrap_or_else(|| "Fingerprinting failed".to_string()));
+    }
+
+    let result = submit_acoustid(&fingerprint, &metadata, &user_key)
+        .await
+        .map_err(|e| format!("AcoustID submission failed: {}", e))?;
+
+    log::info!(
+        "Submitted fingerprint for {} to AcoustID (id: {:?}, status: {})",
+        file_path,
+        result.submission_id,
+        result.status
+    );
+    Ok(result)
+}
+
+/// Cluster just-processed files whose fingerprints match each other.
+///
+/// Lets the confirmation screen show the same song dropped twice (e.g. a
+/// FLAC and an MP3 of one track) as a single choice. Uses the fingerprints
+/// the import session kept while processing, so no files are re-read.
+#[tauri::command]
+pub fn group_files_by_fingerprint(
+    sessions: State<'_, ImportSessions>,
+    session_id: String,
+    tracking_ids: Vec<String>,
+) -> Result<FingerprintGroupingResult, String> {
+    let session_fingerprints =
+        sessions.with_session(&session_id, |session| session.fingerprints.clone())?;
+
+    let mut candidates = Vec::with_capacity(tracking_ids.len());
+    let mut missing = Vec::new();
+
+    for tracking_id in tracking_ids {
+        let decoded = session_fingerprints
+            .get(&tracking_id)
+            .cloned()
+            .and_then(|(fingerprint, duration)| {
+                decode_fingerprint(&fingerprint)
+                    .map_err(|e| log::warn!("Bad fingerprint for {}: {}", tracking_id, e))
+                    .ok()
+                    .map(|raw| (raw, duration))
+            });
+        match decoded {
+            Some((raw, duration_secs)) => candidates.push(FingerprintCandidate {
+                id: tracking_id,
+                raw,
+                duration_secs,
+            }),
+            None => missing.push(tracking_id),
+        }
+    }
+
+    let groups = group_matching_fingerprints(&candidates)
+        .into_iter()
+        .map(|(tracking_ids, similarity)| FingerprintGroup {
+            tracking_ids,
+            similarity,
+        })
+        .collect();
+
+    Ok(FingerprintGroupingResult { groups, missing })
+}
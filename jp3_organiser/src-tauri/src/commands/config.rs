@@ -1,12 +1,32 @@
 //! Configuration commands for persistent storage.
 //!
-//! Handles saving/loading the library path using tauri-plugin-store.
+//! Handles saving/loading the library path and app settings using
+//! tauri-plugin-store.
 
 use std::path::Path;
 use tauri_plugin_store::StoreExt;
 
+use crate::models::{
+    AcoustIdSubmissionSettings, CoverImageSettings, EncodeProfile, IoThrottleSettings, KnownDevice,
+    SaveHookSettings, MAX_MP3_VBR_QUALITY,
+};
+use crate::commands::library::ensure_device_marker;
+use crate::services::device_service::{read_device_id, upsert_device};
+use crate::services::parallel_io_service::set_io_throttle;
+use crate::services::save_hook_service::set_save_hooks;
+
 const STORE_FILENAME: &str = "config.json";
 const LIBRARY_PATH_KEY: &str = "library_path";
+const ACOUSTID_SUBMIT_ENABLED_KEY: &str = "acoustid_submit_enabled";
+const ACOUSTID_USER_KEY_KEY: &str = "acoustid_user_key";
+const COVER_JPEG_QUALITY_KEY: &str = "cover_jpeg_quality";
+const COVER_MAX_EDGE_KEY: &str = "cover_max_edge";
+const DEVICES_KEY: &str = "devices";
+const IO_THROTTLE_KEY: &str = "io_throttle";
+const SAVE_HOOKS_KEY: &str = "save_hooks";
+
+/// Smallest allowed cover edge (the device screen cover size)
+const MIN_COVER_EDGE: u32 = 240;
 
 /// Get the saved library path from persistent storage.
 #[tauri::command]
@@ -58,3 +78,296 @@ pub fn clear_library_path(app: tauri::AppHandle) -> Result<(), String> {
 
     Ok(())
 }
+
+/// Get the AcoustID submission settings (opt-in flag and user key).
+#[tauri::command]
+pub fn get_acoustid_submission_settings(
+    app: tauri::AppHandle,
+) -> Result<AcoustIdSubmissionSettings, String> {
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+
+    let enabled = store
+        .get(ACOUSTID_SUBMIT_ENABLED_KEY)
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let user_key = store
+        .get(ACOUSTID_USER_KEY_KEY)
+        .and_then(|v| v.as_str().map(|s| s.to_string()));
+
+    Ok(AcoustIdSubmissionSettings { enabled, user_key })
+}
+
+/// Save the AcoustID submission settings.
+///
+/// Submissions stay disabled unless the user explicitly opts in.
+#[tauri::command]
+pub fn set_acoustid_submission_settings(
+    app: tauri::AppHandle,
+    settings: AcoustIdSubmissionSettings,
+) -> Result<(), String> {
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+
+    store.set(ACOUSTID_SUBMIT_ENABLED_KEY, serde_json::json!(settings.enabled));
+    match settings.user_key.filter(|k| !k.trim().is_empty()) {
+        Some(key) => store.set(ACOUSTID_USER_KEY_KEY, serde_json::json!(key.trim())),
+        None => {
+            store.delete(ACOUSTID_USER_KEY_KEY);
+        }
+    }
+    store.save().map_err(|e| format!("Failed to save store: {}", e))?;
+
+    Ok(())
+}
+
+/// Get the cover image settings (JPEG quality and max edge length).
+#[tauri::command]
+pub fn get_cover_image_settings(app: tauri::AppHandle) -> Result<CoverImageSettings, String> {
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+
+    let defaults = CoverImageSettings::default();
+    let jpeg_quality = store
+        .get(COVER_JPEG_QUALITY_KEY)
+        .and_then(|v| v.as_u64())
+        .map(|q| q as u8)
+        .unwrap_or(defaults.jpeg_quality);
+    let max_edge = store
+        .get(COVER_MAX_EDGE_KEY)
+        .and_then(|v| v.as_u64())
+        .map(|e| e as u32)
+        .unwrap_or(defaults.max_edge);
+
+    Ok(CoverImageSettings {
+        jpeg_quality,
+        max_edge,
+    })
+}
+
+/// Save the cover image settings.
+///
+/// Applies to covers saved from now on; existing covers are not re-encoded.
+#[tauri::command]
+pub fn set_cover_image_settings(
+    app: tauri::AppHandle,
+    settings: CoverImageSettings,
+) -> Result<(), String> {
+    if !(1..=100).contains(&settings.jpeg_quality) {
+        return Err("JPEG quality must be between 1 and 100".to_string());
+    }
+    if settings.max_edge < MIN_COVER_EDGE {
+        return Err(format!("Max edge must be at least {} pixels", MIN_COVER_EDGE));
+    }
+
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+
+    store.set(COVER_JPEG_QUALITY_KEY, serde_json::json!(settings.jpeg_quality));
+    store.set(COVER_MAX_EDGE_KEY, serde_json::json!(settings.max_edge));
+    store.save().map_err(|e| format!("Failed to save store: {}", e))?;
+
+    Ok(())
+}
+
+/// Get the saved copy throughput cap and worker limit.
+#[tauri::command]
+pub fn get_io_throttle_settings(app: tauri::AppHandle) -> Result<IoThrottleSettings, String> {
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+
+    Ok(store
+        .get(IO_THROTTLE_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+/// Save the copy throughput cap and worker limit, and apply them now.
+///
+/// Copies already running slow down or speed up at their next chunk.
+#[tauri::command]
+pub fn set_io_throttle_settings(
+    app: tauri::AppHandle,
+    settings: IoThrottleSettings,
+) -> Result<(), String> {
+    if settings.max_mb_per_sec.is_some_and(|cap| cap.is_nan() || cap <= 0.0) {
+        return Err("Throughput cap must be above 0 MB/s".to_string());
+    }
+    if settings.max_workers == Some(0) {
+        return Err("At least one worker is needed".to_string());
+    }
+
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+
+    store.set(IO_THROTTLE_KEY, serde_json::json!(settings));
+    store.save().map_err(|e| format!("Failed to save store: {}", e))?;
+    set_io_throttle(settings);
+
+    Ok(())
+}
+
+/// Get the programs run before and after saves to the library.
+#[tauri::command]
+pub fn get_save_hook_settings(app: tauri::AppHandle) -> Result<SaveHookSettings, String> {
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+
+    Ok(store
+        .get(SAVE_HOOKS_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default())
+}
+
+/// Save the programs run before and after saves, and apply them now.
+///
+/// See `save_hook_service` for what the programs receive and may return.
+#[tauri::command]
+pub fn set_save_hook_settings(
+    app: tauri::AppHandle,
+    settings: SaveHookSettings,
+) -> Result<(), String> {
+    let hooks = [&settings.pre_save, &settings.post_save];
+    if hooks.into_iter().flatten().any(|hook| hook.program.trim().is_empty()) {
+        return Err("A hook needs a program to run".to_string());
+    }
+    if settings.timeout_secs == Some(0) {
+        return Err("Hook timeout must be at least 1 second".to_string());
+    }
+
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+
+    store.set(SAVE_HOOKS_KEY, serde_json::json!(settings));
+    store.save().map_err(|e| format!("Failed to save store: {}", e))?;
+    set_save_hooks(settings);
+
+    Ok(())
+}
+
+/// Known devices from the stored `devices` value (empty if unset or unreadable).
+fn parse_devices(value: Option<serde_json::Value>) -> Vec<KnownDevice> {
+    value
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default()
+}
+
+/// Register (or rename) an SD card under a friendly name.
+///
+/// `path_or_serial` is either a library path on the card, in which case the
+/// card is identified by its `jp3/metadata/device.json` marker (written if
+/// missing, e.g. for libraries initialized before markers existed), or a volume serial/UUID for a card without a library.
+#[tauri::command]
+pub fn register_device(
+    app: tauri::AppHandle,
+    path_or_serial: String,
+    name: String,
+) -> Result<KnownDevice, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Device name cannot be empty".to_string());
+    }
+    let path_or_serial = path_or_serial.trim();
+    if path_or_serial.is_empty() {
+        return Err("Device path or serial cannot be empty".to_string());
+    }
+
+    let path = Path::new(path_or_serial);
+    let device = if path.is_dir() {
+        if !path.join("jp3").join("metadata").is_dir() {
+            return Err("No JP3 library found at this path".to_string());
+        }
+        KnownDevice {
+            id: ensure_device_marker(path)?.id,
+            name: name.to_string(),
+            last_path: Some(path_or_serial.to_string()),
+            encode_profile: None,
+        }
+    } else {
+        KnownDevice {
+            id: path_or_serial.to_string(),
+            name: name.to_string(),
+            last_path: None,
+            encode_profile: None,
+        }
+    };
+
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+    let mut devices = parse_devices(store.get(DEVICES_KEY));
+    upsert_device(&mut devices, device.clone());
+    store.set(DEVICES_KEY, serde_json::json!(devices));
+    store.save().map_err(|e| format!("Failed to save store: {}", e))?;
+
+    Ok(devices
+        .into_iter()
+        .find(|d| d.id == device.id)
+        .unwrap_or(device))
+}
+
+/// Set how exports to a registered device are re-encoded.
+///
+/// `None` copies the library's files unchanged.
+#[tauri::command]
+pub fn set_device_encode_profile(
+    app: tauri::AppHandle,
+    device_id: String,
+    profile: Option<EncodeProfile>,
+) -> Result<KnownDevice, String> {
+    if profile
+        .as_ref()
+        .is_some_and(|p| p.mp3_vbr_quality > MAX_MP3_VBR_QUALITY)
+    {
+        return Err(format!("VBR quality must be between 0 and {}", MAX_MP3_VBR_QUALITY));
+    }
+
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+    let mut devices = parse_devices(store.get(DEVICES_KEY));
+    let device = devices
+        .iter_mut()
+        .find(|d| d.id == device_id)
+        .ok_or_else(|| format!("Device not registered: {}", device_id))?;
+    device.encode_profile = profile;
+    let device = device.clone();
+    store.set(DEVICES_KEY, serde_json::json!(devices));
+    store.save().map_err(|e| format!("Failed to save store: {}", e))?;
+
+    Ok(device)
+}
+
+/// List the registered devices.
+#[tauri::command]
+pub fn list_devices(app: tauri::AppHandle) -> Result<Vec<KnownDevice>, String> {
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+    Ok(parse_devices(store.get(DEVICES_KEY)))
+}
+
+/// The registered device whose card is mounted at a library path, if any.
+///
+/// Does not write a marker; unregistered cards return None.
+#[tauri::command]
+pub fn identify_device(
+    app: tauri::AppHandle,
+    base_path: String,
+) -> Result<Option<KnownDevice>, String> {
+    let Some(id) = read_device_id(Path::new(&base_path))? else {
+        return Ok(None);
+    };
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+    Ok(parse_devices(store.get(DEVICES_KEY)).into_iter().find(|d| d.id == id))
+}
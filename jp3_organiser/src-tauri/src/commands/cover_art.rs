@@ -3,17 +3,32 @@
 //! Commands for fetching and managing album and artist cover art.
 //! 
 //! Album covers are fetched from Cover Art Archive using MusicBrainz Release IDs.
+//! When a text search finds no release, the release MBIDs AcoustID reported
+//! for the album's recordings are tried instead.
 //! Artist covers are fetched from Deezer API by searching artist name (no API key required).
 //! 
 //! Cover files are named using a hash for stability across library compaction:
 //! - Albums: hash of "artist|||album"
 //! - Artists: hash of "artist|||artist" (uses "artist" as second component)
+//!
+//! Earlier builds named covers by album or artist ID; `migrate_legacy_covers`
+//! renames those once per library.
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
+use tauri::ipc::Response;
+use tauri::State;
 
+use crate::commands::config::get_cover_image_settings;
+use crate::commands::library::{library_generation, load_library};
 use crate::services::cover_art_service;
+use crate::services::library_monitor_service::ensure_library_available;
+use crate::services::library_settings_service::{load_library_settings, save_library_settings};
 use crate::services::musicbrainz_service;
+use crate::services::release_candidate_service;
+use crate::services::task_manager_service::TaskManager;
+use crate::services::thumbnail_service::{self, ThumbnailSize};
 
 /// Result of fetching cover art
 #[derive(Debug, Clone, Serialize)]
@@ -55,6 +70,8 @@ pub struct GetCoverPathResult {
 /// * `fallback_mbid` - Optional AcoustID Release ID (fallback if primary has no cover)
 #[tauri::command]
 pub async fn fetch_album_cover(
+    app: tauri::AppHandle,
+    tasks: State<'_, TaskManager>,
     base_path: String,
     artist: String,
     album: String,
@@ -69,6 +86,7 @@ pub async fn fetch_album_cover(
         fallback_mbid
     );
 
+    ensure_library_available(Path::new(&base_path))?;
     let albums_dir = Path::new(&base_path).join("jp3").join("assets").join("albums");
 
     // Check if already cached (using artist+album hash)
@@ -90,6 +108,18 @@ pub async fn fetch_album_cover(
         })?;
     }
 
+    // A release the user picked beats whatever the caller searched for
+    let index = release_candidate_service::load_release_candidates(Path::new(&base_path))?;
+    let (mbid, fallback_mbid) =
+        match release_candidate_service::get_chosen_release(&index, &artist, &album) {
+            Some(chosen) if chosen != mbid => {
+                log::info!("Using chosen release {} (search gave {})", chosen, mbid);
+                (chosen, Some(mbid))
+            }
+            _ => (mbid, fallback_mbid),
+        };
+
+    let settings = get_cover_image_settings(app)?;
     // Fetch and save album cover from Cover Art Archive
     match cover_art_service::fetch_and_save_album_cover(
         &mbid,
@@ -97,13 +127,17 @@ pub async fn fetch_album_cover(
         &albums_dir,
         &artist,
         &album,
+        &settings,
     ).await {
-        Ok(result) => Ok(FetchCoverResult {
-            success: true,
-            path: Some(result.path),
-            error: None,
-            was_cached: false,
-        }),
+        Ok(result) => {
+            thumbnail_service::queue_album_thumbnails(&tasks, Path::new(&base_path), vec![(artist, album)]);
+            Ok(FetchCoverResult {
+                success: true,
+                path: Some(result.path),
+                error: None,
+                was_cached: false,
+            })
+        }
         Err(cover_art_service::CoverArtError::NotFound) => {
             log::info!("No album cover art available for MBID: {}", mbid);
             Ok(FetchCoverResult {
@@ -137,6 +171,7 @@ pub async fn fetch_album_cover(
 /// * `artist` - Artist name (used for search and for stable filename generation)
 #[tauri::command]
 pub async fn fetch_artist_cover(
+    app: tauri::AppHandle,
     base_path: String,
     artist: String,
 ) -> Result<FetchCoverResult, String> {
@@ -145,6 +180,7 @@ pub async fn fetch_artist_cover(
         artist,
     );
 
+    ensure_library_available(Path::new(&base_path))?;
     let artists_dir = Path::new(&base_path).join("jp3").join("assets").join("artists");
 
     // Check if already cached (using artist hash - we use "artist" as the album component)
@@ -166,8 +202,9 @@ pub async fn fetch_artist_cover(
         })?;
     }
 
+    let settings = get_cover_image_settings(app)?;
     // Fetch and save artist cover from Deezer
-    match cover_art_service::fetch_and_save_artist_cover(&artists_dir, &artist).await {
+    match cover_art_service::fetch_and_save_artist_cover(&artists_dir, &artist, &settings).await {
         Ok(result) => Ok(FetchCoverResult {
             success: true,
             path: Some(result.path),
@@ -206,6 +243,8 @@ pub async fn fetch_artist_cover(
 /// * `album` - Album name
 #[tauri::command]
 pub async fn fetch_deezer_album_cover(
+    app: tauri::AppHandle,
+    tasks: State<'_, TaskManager>,
     base_path: String,
     artist: String,
     album: String,
@@ -216,6 +255,7 @@ pub async fn fetch_deezer_album_cover(
         album
     );
 
+    ensure_library_available(Path::new(&base_path))?;
     let albums_dir = Path::new(&base_path).join("jp3").join("assets").join("albums");
 
     // Check if already cached (using artist+album hash)
@@ -237,18 +277,23 @@ pub async fn fetch_deezer_album_cover(
         })?;
     }
 
+    let settings = get_cover_image_settings(app)?;
     // Fetch from Deezer
     match cover_art_service::fetch_and_save_deezer_album_cover(
         &albums_dir,
         &artist,
         &album,
+        &settings,
     ).await {
-        Ok(result) => Ok(FetchCoverResult {
-            success: true,
-            path: Some(result.path),
-            error: None,
-            was_cached: false,
-        }),
+        Ok(result) => {
+            thumbnail_service::queue_album_thumbnails(&tasks, Path::new(&base_path), vec![(artist, album)]);
+            Ok(FetchCoverResult {
+                success: true,
+                path: Some(result.path),
+                error: None,
+                was_cached: false,
+            })
+        }
         Err(cover_art_service::CoverArtError::NotFound) => {
             log::info!("No Deezer album cover available for: {} - {}", artist, album);
             Ok(FetchCoverResult {
@@ -270,6 +315,162 @@ pub async fn fetch_deezer_album_cover(
     }
 }
 
+/// Fetch and cache album cover art using fingerprint release candidates.
+///
+/// For albums where the artist/album text search found no release. Tries
+/// each release MBID AcoustID reported for the album's recordings (recorded
+/// when the import session was saved) against Cover Art Archive directly.
+///
+/// # Arguments
+/// * `base_path` - Library base path
+/// * `artist` - Artist name
+/// * `album` - Album name
+#[tauri::command]
+pub async fn fetch_album_cover_from_fingerprints(
+    app: tauri::AppHandle,
+    tasks: State<'_, TaskManager>,
+    base_path: String,
+    artist: String,
+    album: String,
+) -> Result<FetchCoverResult, String> {
+    log::info!(
+        "fetch_album_cover_from_fingerprints called: artist=\"{}\", album=\"{}\"",
+        artist,
+        album
+    );
+
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let albums_dir = base.join("jp3").join("assets").join("albums");
+
+    // Check if already cached (using artist+album hash)
+    if let Some(path) = cover_art_service::get_cover_path_by_name(&albums_dir, &artist, &album) {
+        log::info!("Album cover already cached: {}", path);
+        return Ok(FetchCoverResult {
+            success: true,
+            path: Some(path),
+            error: None,
+            was_cached: true,
+        });
+    }
+
+    let index = release_candidate_service::load_release_candidates(base)?;
+    let mut mbids = release_candidate_service::get_release_candidates(&index, &artist, &album);
+    if let Some(chosen) = release_candidate_service::get_chosen_release(&index, &artist, &album) {
+        mbids.retain(|m| *m != chosen);
+        mbids.insert(0, chosen);
+    }
+    if mbids.is_empty() {
+        log::info!("No fingerprint release candidates for: {} - {}", artist, album);
+        return Ok(FetchCoverResult {
+            success: false,
+            path: None,
+            error: Some("No fingerprint releases recorded for this album".to_string()),
+            was_cached: false,
+        });
+    }
+
+    // Ensure albums directory exists
+    if !albums_dir.exists() {
+        std::fs::create_dir_all(&albums_dir).map_err(|e| {
+            log::error!("Failed to create albums directory: {}", e);
+            format!("Failed to create albums directory: {}", e)
+        })?;
+    }
+
+    let settings = get_cover_image_settings(app)?;
+
+    match cover_art_service::fetch_and_save_album_cover_from_releases(
+        &mbids,
+        &albums_dir,
+        &artist,
+        &album,
+        &settings,
+    ).await {
+        Ok((result, mbid)) => {
+            log::info!("Cover found via fingerprint release {}", mbid);
+            thumbnail_service::queue_album_thumbnails(&tasks, base, vec![(artist, album)]);
+            Ok(FetchCoverResult {
+                success: true,
+                path: Some(result.path),
+                error: None,
+                was_cached: false,
+            })
+        }
+        Err(cover_art_service::CoverArtError::NotFound) => {
+            log::info!("No cover art for any fingerprint release of: {} - {}", artist, album);
+            Ok(FetchCoverResult {
+                success: false,
+                path: None,
+                error: Some("No cover art available".to_string()),
+                was_cached: false,
+            })
+        }
+        Err(e) => {
+            log::error!("Failed to fetch album cover from fingerprint releases: {}", e);
+            Ok(FetchCoverResult {
+                success: false,
+                path: None,
+                error: Some(e.to_string()),
+                was_cached: false,
+            })
+        }
+    }
+}
+
+/// Persist the user's choice of release for an album.
+///
+/// Used when a search matched several releases (regional editions,
+/// reissues). Later cover fetches use the chosen release first. If the
+/// choice changes, the cached cover is removed so it is re-fetched from
+/// the chosen release.
+///
+/// # Arguments
+/// * `base_path` - Library base path
+/// * `album_id` - Album ID in the library
+/// * `mbid` - Chosen MusicBrainz Release ID
+#[tauri::command]
+pub fn choose_release_mbid(base_path: String, album_id: u32, mbid: String) -> Result<(), String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+
+    let library = load_library(base_path.clone(), None)?;
+    let album = library
+        .albums
+        .iter()
+        .find(|a| a.id == album_id)
+        .ok_or_else(|| format!("Album with ID {} not found", album_id))?;
+
+    let mut index = release_candidate_service::load_release_candidates(base)?;
+    if !release_candidate_service::set_chosen_release(
+        &mut index,
+        &album.artist_name,
+        &album.name,
+        &mbid,
+    ) {
+        return Ok(());
+    }
+    release_candidate_service::save_release_candidates(base, &index)?;
+
+    log::info!(
+        "Chose release {} for \"{}\" by \"{}\"",
+        mbid,
+        album.name,
+        album.artist_name
+    );
+
+    let albums_dir = base.join("jp3").join("assets").join("albums");
+    if let Some(path) =
+        cover_art_service::get_cover_path_by_name(&albums_dir, &album.artist_name, &album.name)
+    {
+        std::fs::remove_file(&path)
+            .map_err(|e| format!("Failed to remove old cover {}: {}", path, e))?;
+    }
+    thumbnail_service::remove_thumbnails(&albums_dir, &album.artist_name, &album.name);
+
+    Ok(())
+}
+
 /// Get the cached cover path for an album.
 ///
 /// Returns the path if the cover exists in cache, None otherwise.
@@ -306,6 +507,9 @@ pub fn get_album_cover_path(
 /// rather than a file path (e.g., for blob URLs).
 /// Uses artist+album hash for stable filename lookup.
 ///
+/// Bytes are returned as a raw IPC response (an `ArrayBuffer` in the
+/// frontend) rather than a JSON array of numbers.
+///
 /// # Arguments
 /// * `base_path` - Library base path  
 /// * `artist` - Artist name
@@ -315,7 +519,8 @@ pub fn read_album_cover(
     base_path: String,
     artist: String,
     album: String,
-) -> Result<Vec<u8>, String> {
+) -> Result<Response, String> {
+    ensure_library_available(Path::new(&base_path))?;
     let album_dir = Path::new(&base_path).join("jp3/assets").join("albums");
     let filename = cover_art_service::cover_filename(&artist, &album);
     let cover_path = album_dir.join(format!("{}.jpg", filename));
@@ -324,17 +529,275 @@ pub fn read_album_cover(
         return Err("Cover not found".to_string());
     }
 
-    std::fs::read(&cover_path).map_err(|e| {
+    std::fs::read(&cover_path).map(Response::new).map_err(|e| {
         log::error!("Failed to read cover file: {}", e);
         format!("Failed to read cover: {}", e)
     })
 }
 
+/// Read a downscaled album cover for the album grid or device preview.
+///
+/// Thumbnails are normally generated in the background after a save or
+/// cover fetch; if one is missing it is generated now from the full cover.
+///
+/// # Arguments
+/// * `base_path` - Library base path
+/// * `artist` - Artist name
+/// * `album` - Album name
+/// * `size` - `"ui"` or `"device"`
+#[tauri::command]
+pub fn read_album_thumbnail(
+    base_path: String,
+    artist: String,
+    album: String,
+    size: ThumbnailSize,
+) -> Result<Response, String> {
+    ensure_library_available(Path::new(&base_path))?;
+    let album_dir = Path::new(&base_path).join("jp3/assets").join("albums");
+    if cover_art_service::get_cover_path_by_name(&album_dir, &artist, &album).is_none() {
+        return Err("Cover not found".to_string());
+    }
+
+    let path = thumbnail_service::ensure_thumbnail(&album_dir, &artist, &album, size)?;
+    std::fs::read(&path)
+        .map(Response::new)
+        .map_err(|e| format!("Failed to read thumbnail: {}", e))
+}
+
+/// Total cover bytes returned by one `read_album_covers_batch` call
+const COVER_BATCH_MAX_BYTES: u64 = 8 * 1024 * 1024;
+
+/// One album cover requested from `read_album_covers_batch`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumCoverRequest {
+    pub artist: String,
+    pub album: String,
+    /// Thumbnail size, or the full cover if not set
+    pub size: Option<ThumbnailSize>,
+}
+
+/// Result of reading many album covers at once
+#[derive(Debug, Clone, Default)]
+pub struct AlbumCoversBatch {
+    /// Image bytes keyed by cover hash
+    pub covers: HashMap<String, Vec<u8>>,
+    /// Hashes of albums that have no cover, or whose cover couldn't be read
+    pub missing: Vec<String>,
+    /// Hashes not read because the size cap was reached; request them again
+    pub deferred: Vec<String>,
+}
+
+/// Index at the start of a covers frame; images follow in `covers` order
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CoversFrameIndex<'a> {
+    covers: Vec<CoversFrameEntry<'a>>,
+    missing: &'a [String],
+    deferred: &'a [String],
+}
+
+#[derive(Serialize)]
+struct CoversFrameEntry<'a> {
+    hash: &'a str,
+    length: usize,
+}
+
+impl AlbumCoversBatch {
+    /// Encode the batch as one binary frame, so images aren't sent as JSON
+    /// number arrays.
+    ///
+    /// Layout: a u32 (little-endian) index length, the JSON index
+    /// (`covers` as `{hash, length}` in image order, `missing`,
+    /// `deferred`), then the image bytes back to back.
+    pub fn to_frame(&self) -> Vec<u8> {
+        let mut covers: Vec<(&String, &Vec<u8>)> = self.covers.iter().collect();
+        covers.sort_by(|a, b| a.0.cmp(b.0));
+        let index = CoversFrameIndex {
+            covers: covers
+                .iter()
+                .map(|(hash, bytes)| CoversFrameEntry {
+                    hash,
+                    length: bytes.len(),
+                })
+                .collect(),
+            missing: &self.missing,
+            deferred: &self.deferred,
+        };
+        let index = serde_json::to_vec(&index).expect("cover frame index serializes");
+
+        let image_bytes: usize = covers.iter().map(|(_, bytes)| bytes.len()).sum();
+        let mut frame = Vec::with_capacity(4 + index.len() + image_bytes);
+        frame.extend_from_slice(&(index.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&index);
+        for (_, bytes) in covers {
+            frame.extend_from_slice(bytes);
+        }
+        frame
+    }
+}
+
+/// Read many album covers in one call (e.g. a page of the album grid).
+///
+/// Keys are cover hashes (see `cover_filename`), so duplicate requests are
+/// read once. Albums without a cover, or whose cover or thumbnail can't be
+/// read, are listed in `missing` rather than failing the batch. Once
+/// `COVER_BATCH_MAX_BYTES` has been read, remaining covers are listed in
+/// `deferred` for a follow-up call; at least one cover is always returned.
+///
+/// The result is sent as one binary frame (see `AlbumCoversBatch::to_frame`).
+///
+/// # Arguments
+/// * `base_path` - Library base path
+/// * `requests` - Albums to read, each with an optional thumbnail size
+#[tauri::command]
+pub fn read_album_covers_batch(
+    base_path: String,
+    requests: Vec<AlbumCoverRequest>,
+) -> Result<Response, String> {
+    read_album_covers(&base_path, requests).map(|batch| Response::new(batch.to_frame()))
+}
+
+/// Read the covers for `read_album_covers_batch`.
+pub fn read_album_covers(
+    base_path: &str,
+    requests: Vec<AlbumCoverRequest>,
+) -> Result<AlbumCoversBatch, String> {
+    ensure_library_available(Path::new(base_path))?;
+    let album_dir = Path::new(base_path).join("jp3/assets").join("albums");
+
+    let mut batch = AlbumCoversBatch::default();
+    let mut total_bytes: u64 = 0;
+
+    for request in requests {
+        let hash = cover_art_service::cover_filename(&request.artist, &request.album);
+        if batch.covers.contains_key(&hash)
+            || batch.missing.contains(&hash)
+            || batch.deferred.contains(&hash)
+        {
+            continue;
+        }
+
+        if cover_art_service::get_cover_path_by_name(&album_dir, &request.artist, &request.album)
+            .is_none()
+        {
+            batch.missing.push(hash);
+            continue;
+        }
+
+        if total_bytes >= COVER_BATCH_MAX_BYTES {
+            batch.deferred.push(hash);
+            continue;
+        }
+
+        let path = match request.size {
+            Some(size) => thumbnail_service::ensure_thumbnail(
+                &album_dir,
+                &request.artist,
+                &request.album,
+                size,
+            ),
+            None => Ok(album_dir.join(format!("{}.jpg", hash))),
+        };
+        let bytes = match path.and_then(|path| {
+            std::fs::read(&path).map_err(|e| format!("Failed to read cover: {}", e))
+        }) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                // One unreadable cover shouldn't cost the rest of the page
+                log::warn!("Album cover {} unreadable: {}", hash, e);
+                batch.missing.push(hash);
+                continue;
+            }
+        };
+        total_bytes += bytes.len() as u64;
+        batch.covers.insert(hash, bytes);
+    }
+
+    Ok(batch)
+}
+
+/// Fetch an album cover for preview without touching the library.
+///
+/// Used by the import confirmation screen to show artwork before anything
+/// is saved. The cover is downloaded into a temp cache keyed by MBID,
+/// artist and album (see `cover_art_service::preview_covers_dir`) and
+/// reused on later previews.
+/// Without an MBID the best MusicBrainz release is searched for; Deezer is
+/// the fallback either way.
+///
+/// # Arguments
+/// * `artist` - Artist name
+/// * `album` - Album name
+/// * `mbid` - MusicBrainz Release ID, if already known
+#[tauri::command]
+pub async fn preview_album_cover(
+    app: tauri::AppHandle,
+    artist: String,
+    album: String,
+    mbid: Option<String>,
+) -> Result<Response, String> {
+    log::info!(
+        "preview_album_cover called: artist=\"{}\", album=\"{}\", mbid={:?}",
+        artist,
+        album,
+        mbid
+    );
+
+    let preview_dir = cover_art_service::preview_covers_dir(mbid.as_deref());
+    if let Some(path) = cover_art_service::get_cover_path_by_name(&preview_dir, &artist, &album) {
+        return std::fs::read(&path)
+            .map(Response::new)
+            .map_err(|e| format!("Failed to read cover preview: {}", e));
+    }
+
+    std::fs::create_dir_all(&preview_dir)
+        .map_err(|e| format!("Failed to create preview directory: {}", e))?;
+
+    let mbid = match mbid {
+        Some(mbid) => Some(mbid),
+        None => musicbrainz_service::search_release_candidates(&artist, &album)
+            .await
+            .ok()
+            .and_then(|releases| releases.into_iter().next())
+            .map(|release| release.release_mbid),
+    };
+
+    let settings = get_cover_image_settings(app)?;
+    let result = match mbid {
+        Some(mbid) => {
+            cover_art_service::fetch_and_save_album_cover(
+                &mbid,
+                None,
+                &preview_dir,
+                &artist,
+                &album,
+                &settings,
+            )
+            .await
+        }
+        None => {
+            cover_art_service::fetch_and_save_deezer_album_cover(&preview_dir, &artist, &album, &settings)
+                .await
+        }
+    };
+
+    match result {
+        Ok(result) => std::fs::read(&result.path)
+            .map(Response::new)
+            .map_err(|e| format!("Failed to read cover preview: {}", e)),
+        Err(e) => {
+            log::info!("No cover preview for {} - {}: {}", artist, album, e);
+            Err("Cover not found".to_string())
+        }
+    }
+}
+
 /// Read artist cover image bytes for displaying in frontend.
 ///
 /// This is useful when the frontend needs the raw image data
 /// rather than a file path (e.g., for blob URLs).
-/// Uses artist hash for stable filename lookup.
+/// Uses artist hash for stable filename lookup. Returned as a raw IPC response.
 ///
 /// # Arguments
 /// * `base_path` - Library base path  
@@ -343,7 +806,8 @@ pub fn read_album_cover(
 pub fn read_artist_cover(
     base_path: String,
     artist: String,
-) -> Result<Vec<u8>, String> {
+) -> Result<Response, String> {
+    ensure_library_available(Path::new(&base_path))?;
     let artist_dir = Path::new(&base_path).join("jp3/assets").join("artists");
     // Use "artist" as the second component for consistency with fetch_artist_cover
     let filename = cover_art_service::cover_filename(&artist, "artist");
@@ -353,13 +817,33 @@ pub fn read_artist_cover(
         return Err("Artist cover not found".to_string());
     }
 
-    std::fs::read(&cover_path).map_err(|e| {
+    std::fs::read(&cover_path).map(Response::new).map_err(|e| {
         log::error!("Failed to read artist cover file: {}", e);
         format!("Failed to read artist cover: {}", e)
     })
 }
 
 
+/// One MusicBrainz release offered for disambiguation
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseOption {
+    /// MusicBrainz Release ID (MBID)
+    pub mbid: String,
+    /// Release title
+    pub title: String,
+    /// Artist name
+    pub artist: Option<String>,
+    /// Release date (YYYY, YYYY-MM or YYYY-MM-DD)
+    pub date: Option<String>,
+    /// Release country code
+    pub country: Option<String>,
+    /// Total tracks across all media
+    pub track_count: Option<u32>,
+    /// Search score (0-100)
+    pub score: u32,
+}
+
 /// Result of searching for a release MBID
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -374,12 +858,43 @@ pub struct SearchReleaseMbidResult {
     pub artist: Option<String>,
     /// Search score (0-100)
     pub score: Option<u32>,
+    /// Every plausible release, best first (includes the best match).
+    /// More than one means the user may want to pick with `choose_release_mbid`.
+    pub releases: Vec<ReleaseOption>,
+}
+
+impl SearchReleaseMbidResult {
+    /// Build a result from plausible releases, best first.
+    fn from_releases(releases: Vec<musicbrainz_service::ReleaseSearchResult>) -> Self {
+        let best = releases.first().cloned();
+        Self {
+            found: best.is_some(),
+            mbid: best.as_ref().map(|b| b.release_mbid.clone()),
+            title: best.as_ref().map(|b| b.title.clone()),
+            artist: best.as_ref().and_then(|b| b.artist.clone()),
+            score: best.as_ref().map(|b| b.score),
+            releases: releases
+                .into_iter()
+                .map(|r| ReleaseOption {
+                    mbid: r.release_mbid,
+                    title: r.title,
+                    artist: r.artist,
+                    date: r.date,
+                    country: r.country,
+                    track_count: r.track_count,
+                    score: r.score,
+                })
+                .collect(),
+        }
+    }
 }
 
 /// Search for a release MBID using MusicBrainz API.
 ///
 /// This searches the MusicBrainz database by artist and album name,
-/// returning the best matching release MBID for use with Cover Art Archive.
+/// returning the best matching release MBID for use with Cover Art Archive,
+/// plus every other plausible release (other countries, reissues) with its
+/// date and track count so the user can disambiguate.
 ///
 /// This is more accurate than using AcoustID's MBID because it uses
 /// the user-confirmed album metadata rather than fingerprint matching.
@@ -399,42 +914,25 @@ pub async fn search_album_mbid(artist: String, album: String) -> SearchReleaseMb
         album
     );
 
-    match musicbrainz_service::search_release(&artist, &album).await {
-        Ok(Some(result)) => {
-            log::info!(
-                "Found release: \"{}\" by {:?} (MBID: {}, score: {})",
-                result.title,
-                result.artist,
-                result.release_mbid,
-                result.score
-            );
-            SearchReleaseMbidResult {
-                found: true,
-                mbid: Some(result.release_mbid),
-                title: Some(result.title),
-                artist: result.artist,
-                score: Some(result.score),
-            }
-        }
-        Ok(None) => {
-            log::info!("No release found for \"{}\" - \"{}\"", artist, album);
-            SearchReleaseMbidResult {
-                found: false,
-                mbid: None,
-                title: None,
-                artist: None,
-                score: None,
+    match musicbrainz_service::search_release_candidates(&artist, &album).await {
+        Ok(releases) => {
+            if let Some(best) = releases.first() {
+                log::info!(
+                    "Found release: \"{}\" by {:?} (MBID: {}, score: {}), {} plausible",
+                    best.title,
+                    best.artist,
+                    best.release_mbid,
+                    best.score,
+                    releases.len()
+                );
+            } else {
+                log::info!("No release found for \"{}\" - \"{}\"", artist, album);
             }
+            SearchReleaseMbidResult::from_releases(releases)
         }
         Err(e) => {
             log::error!("Search failed: {}", e);
-            SearchReleaseMbidResult {
-                found: false,
-                mbid: None,
-                title: None,
-                artist: None,
-                score: None,
-            }
+            SearchReleaseMbidResult::from_releases(Vec::new())
         }
     }
 }
@@ -468,22 +966,7 @@ pub async fn search_album_mbids_batch(
 
     results
         .into_iter()
-        .map(|opt| match opt {
-            Some(result) => SearchReleaseMbidResult {
-                found: true,
-                mbid: Some(result.release_mbid),
-                title: Some(result.title),
-                artist: result.artist,
-                score: Some(result.score),
-            },
-            None => SearchReleaseMbidResult {
-                found: false,
-                mbid: None,
-                title: None,
-                artist: None,
-                score: None,
-            },
-        })
+        .map(SearchReleaseMbidResult::from_releases)
         .collect()
 }
 
@@ -560,6 +1043,8 @@ pub fn clear_cover_cache(base_path: String) -> ClearCoverCacheResult {
                     }
                 }
                 log::info!("Cleared {} album covers from {:?}", albums_cleared, albums_dir);
+                // Thumbnails are derived from the covers just removed
+                let _ = std::fs::remove_dir_all(albums_dir.join(thumbnail_service::THUMBS_DIR));
             }
             Err(e) => {
                 log::error!("Failed to read albums directory {:?}: {}", albums_dir, e);
@@ -631,3 +1116,140 @@ pub fn clear_cover_cache(base_path: String) -> ClearCoverCacheResult {
         error: None,
     }
 }
+
+/// Result of renaming covers saved under ID-based names
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyCoverMigrationResult {
+    /// Whether the library was migrated before (nothing was checked)
+    pub already_migrated: bool,
+    /// The library was compacted since the legacy covers were written, so
+    /// their IDs may name other albums and artists now (nothing was renamed)
+    pub skipped_after_compaction: bool,
+    /// Album covers renamed to their hash name
+    pub albums_renamed: u32,
+    /// Artist covers renamed to their hash name
+    pub artists_renamed: u32,
+    /// Legacy covers removed because the hash-named cover already existed
+    pub duplicates_removed: u32,
+    /// Legacy covers whose ID matches no album or artist (left in place)
+    pub unmatched: u32,
+    /// Legacy covers that couldn't be renamed or removed
+    pub failed: u32,
+}
+
+/// Rename covers that earlier builds saved as `{album ID}.jpg` and
+/// `{artist ID}.jpg` to their hash names, so they don't show as missing
+/// after upgrading.
+///
+/// Each ID's artist and album names are read from library.bin to recompute
+/// the hash. A hash-named cover that already exists is kept and the legacy
+/// file removed. Completion is recorded in the library settings, so this
+/// only runs once per library (unless a file couldn't be moved); later
+/// calls return `already_migrated`. Run at startup for the saved library.
+///
+/// Legacy covers are older than any compaction this build recorded in the
+/// settings. If library.bin's generation shows such a compaction (one not
+/// undone by restoring an older backup), IDs were renumbered after the
+/// covers were written, so nothing is renamed and every legacy cover is
+/// left in place as unmatched.
+///
+/// # Arguments
+/// * `base_path` - Library base path
+#[tauri::command]
+pub fn migrate_legacy_covers(base_path: String) -> Result<LegacyCoverMigrationResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let mut settings = load_library_settings(base)?;
+    if settings.legacy_covers_migrated {
+        return Ok(LegacyCoverMigrationResult {
+            already_migrated: true,
+            ..Default::default()
+        });
+    }
+
+    let mut result = LegacyCoverMigrationResult::default();
+    let generation = library_generation(base)?;
+    result.skipped_after_compaction = settings
+        .compacted_generation
+        .is_some_and(|compacted| generation >= compacted);
+
+    let library = load_library(base_path.clone(), None)?;
+    let (mut album_names, mut artist_names) = (HashMap::new(), HashMap::new());
+    if !result.skipped_after_compaction {
+        for album in &library.albums {
+            let name = cover_art_service::cover_filename(&album.artist_name, &album.name);
+            album_names.insert(album.id, name);
+        }
+        for artist in &library.artists {
+            let name = cover_art_service::cover_filename(&artist.name, "artist");
+            artist_names.insert(artist.id, name);
+        }
+    }
+
+    let assets_dir = base.join("jp3").join("assets");
+    result.albums_renamed =
+        rename_legacy_covers(&assets_dir.join("albums"), &album_names, &mut result);
+    result.artists_renamed =
+        rename_legacy_covers(&assets_dir.join("artists"), &artist_names, &mut result);
+
+    // Try again next time if anything was left behind
+    if result.failed == 0 {
+        settings.legacy_covers_migrated = true;
+        save_library_settings(base, &settings)?;
+    }
+    log::info!(
+        "Legacy cover migration: {} album and {} artist covers renamed, {} duplicates removed, \
+         {} unmatched, {} failed",
+        result.albums_renamed,
+        result.artists_renamed,
+        result.duplicates_removed,
+        result.unmatched,
+        result.failed
+    );
+    Ok(result)
+}
+
+/// Rename the ID-named covers in `dir` to the hash names in `names` (keyed
+/// by ID). Returns the number renamed.
+fn rename_legacy_covers(
+    dir: &Path,
+    names: &HashMap<u32, String>,
+    result: &mut LegacyCoverMigrationResult,
+) -> u32 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    let mut renamed = 0u32;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "jpg") {
+            continue;
+        }
+        // Hash names are 16 hex digits; IDs are shorter decimal numbers
+        let id = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .filter(|stem| stem.len() < 16)
+            .and_then(|stem| stem.parse::<u32>().ok());
+        let Some(id) = id else {
+            continue;
+        };
+        let Some(name) = names.get(&id) else {
+            result.unmatched += 1;
+            continue;
+        };
+
+        let target = dir.join(format!("{}.jpg", name));
+        let moved = if target.exists() {
+            std::fs::remove_file(&path).map(|_| result.duplicates_removed += 1)
+        } else {
+            std::fs::rename(&path, &target).map(|_| renamed += 1)
+        };
+        if let Err(e) = moved {
+            log::warn!("Failed to migrate legacy cover {:?}: {}", path, e);
+            result.failed += 1;
+        }
+    }
+    renamed
+}
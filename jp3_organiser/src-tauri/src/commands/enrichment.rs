@@ -0,0 +1,184 @@
+//! Library metadata enrichment commands.
+//!
+//! Handles:
+//! - Backfilling missing album years from MusicBrainz
+//! - Romanized search/sort keys for non-Latin names
+use std::path::Path;
+
+use crate::commands::library::{load_library, patch_album_years};
+use crate::models::{
+    AlbumYearUnknown, AlbumYearUpdate, BackfillYearsResult, TransliterationResult,
+};
+use crate::services::library_monitor_service::ensure_library_available;
+use crate::services::release_candidate_service::{get_chosen_release, load_release_candidates};
+use crate::services::transliteration_service::{
+    add_generated_keys, load_transliterations, save_transliterations,
+};
+use crate::services::year_backfill_service::{
+    cache_year, cached_year, load_year_cache, lookup_album_year, save_year_cache,
+};
+
+/// Fill in years for albums saved without one (year 0).
+///
+/// Each album is looked up on MusicBrainz (1 request/second), preferring
+/// the release the user chose for it if any. Answers, including "no year",
+/// are cached so later runs only query new albums; pass `refresh = true`
+/// to ignore the cache. Years are patched into library.bin in place.
+#[tauri::command]
+pub async fn backfill_album_years(
+    base_path: String,
+    refresh: Option<bool>,
+) -> Result<BackfillYearsResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+
+    let refresh = refresh.unwrap_or(false);
+    let library = load_library(base_path.clone(), None)?;
+    let releases = load_release_candidates(base)?;
+    let mut cache = load_year_cache(base)?;
+
+    let missing: Vec<_> = library.albums.iter().filter(|a| a.year == 0).collect();
+    log::info!("Backfilling years for {} albums", missing.len());
+
+    let mut result = BackfillYearsResult::default();
+    let mut found: Vec<(u32, String, String, u16)> = Vec::new();
+
+    for album in missing {
+        let cached = cached_year(&cache, &album.artist_name, &album.name).filter(|_| !refresh);
+        let year = match cached {
+            Some(year) => {
+                result.cached_lookups += 1;
+                year
+            }
+            None => {
+                let chosen = get_chosen_release(&releases, &album.artist_name, &album.name);
+                match lookup_album_year(&album.artist_name, &album.name, chosen.as_deref()).await {
+                    Ok(year) => {
+                        let year = year.unwrap_or(0);
+                        cache_year(&mut cache, &album.artist_name, &album.name, year);
+                        year
+                    }
+                    Err(e) => {
+                        // Not cached: a network error says nothing about the album
+                        log::warn!(
+                            "Year lookup failed for \"{}\" by \"{}\": {}",
+                            album.name,
+                            album.artist_name,
+                            e
+                        );
+                        0
+                    }
+                }
+            }
+        };
+
+        if year > 0 {
+            found.push((album.id, album.name.clone(), album.artist_name.clone(), year));
+        } else {
+            result.unknown.push(AlbumYearUnknown {
+                album_id: album.id,
+                album_name: album.name.clone(),
+                artist_name: album.artist_name.clone(),
+            });
+        }
+    }
+
+    save_year_cache(base, &cache)?;
+
+    // The library may have changed while we were waiting on MusicBrainz;
+    // only patch albums that are still the same album and still missing a year
+    let current = load_library(base_path.clone(), None)?;
+    for (album_id, album_name, artist_name, year) in found {
+        let unchanged = current.albums.iter().any(|a| {
+            a.id == album_id && a.name == album_name && a.artist_name == artist_name && a.year == 0
+        });
+        if unchanged {
+            result.updated.push(AlbumYearUpdate {
+                album_id,
+                album_name,
+                artist_name,
+                year,
+            });
+        }
+    }
+
+    let updates: Vec<(u32, u16)> = result.updated.iter().map(|u| (u.album_id, u.year)).collect();
+    if !updates.is_empty() {
+        patch_album_years(&base_path, &updates)?;
+    }
+
+    log::info!(
+        "Year backfill complete: {} updated, {} unknown ({} from cache)",
+        result.updated.len(),
+        result.unknown.len(),
+        result.cached_lookups
+    );
+
+    Ok(result)
+}
+
+/// Turn romanized keys for non-Latin names on or off for a library.
+///
+/// Enabling generates keys for every title, artist and album name already
+/// in the library; later saves and edits add keys for new names. The
+/// setting is stored with the library so it travels with the SD card.
+#[tauri::command]
+pub fn set_transliteration_enabled(
+    base_path: String,
+    enabled: bool,
+) -> Result<TransliterationResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+
+    let mut index = load_transliterations(base)?;
+    index.enabled = enabled;
+
+    let mut keys_generated = 0;
+    if enabled && base.join("jp3").join("metadata").join("library.bin").exists() {
+        let library = load_library(base_path.clone(), None)?;
+        let names = library
+            .artists
+            .iter()
+            .map(|a| a.name.as_str())
+            .chain(library.albums.iter().map(|a| a.name.as_str()))
+            .chain(library.songs.iter().map(|s| s.title.as_str()));
+        keys_generated = add_generated_keys(&mut index, names);
+    }
+
+    save_transliterations(base, &index)?;
+    log::info!(
+        "Transliteration {} ({} keys generated)",
+        if enabled { "enabled" } else { "disabled" },
+        keys_generated
+    );
+
+    Ok(TransliterationResult {
+        enabled,
+        keys_generated,
+    })
+}
+
+/// Set (or clear, with `None`) the user's romanization for a name.
+///
+/// Needed for Japanese kanji, which automatic romanization reads as
+/// Mandarin. Overrides take precedence over generated keys.
+#[tauri::command]
+pub fn set_transliteration_override(
+    base_path: String,
+    name: String,
+    romanized: Option<String>,
+) -> Result<(), String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+
+    let mut index = load_transliterations(base)?;
+    match romanized.map(|r| r.trim().to_lowercase()).filter(|r| !r.is_empty()) {
+        Some(key) => {
+            index.overrides.insert(name, key);
+        }
+        None => {
+            index.overrides.remove(&name);
+        }
+    }
+    save_transliterations(base, &index)
+}
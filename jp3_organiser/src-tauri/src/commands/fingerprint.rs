@@ -0,0 +1,100 @@
+//! Fingerprint maintenance commands.
+//!
+//! Handles:
+//! - Re-fingerprinting library songs after a Chromaprint/fpcalc upgrade
+//! - Pruning stored fingerprints for songs that no longer exist
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::commands::library::load_library;
+use crate::models::{RefingerprintResult, StoredFingerprint};
+use crate::services::fingerprint_index_service::{
+    is_fingerprint_current, load_fingerprint_index, save_fingerprint_index,
+};
+use crate::services::fingerprint_service::{fingerprint_file, fpcalc_version, CHROMAPRINT_ALGORITHM};
+use crate::services::library_monitor_service::{
+    ensure_library_available, wait_while_library_unavailable,
+};
+
+/// Re-fingerprint library songs whose stored fingerprint is missing or was
+/// produced by a different Chromaprint algorithm or fpcalc version.
+///
+/// Pass `force = true` to recompute every fingerprint regardless.
+/// Entries for songs no longer in the library are removed.
+#[tauri::command]
+pub async fn refingerprint_library(
+    base_path: String,
+    force: Option<bool>,
+) -> Result<RefingerprintResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+
+    let force = force.unwrap_or(false);
+    let library = load_library(base_path.clone(), None)?;
+    let mut index = load_fingerprint_index(base)?;
+    let version = fpcalc_version();
+    let music_path = base.join("jp3").join("music");
+
+    log::info!(
+        "Re-fingerprinting library (algorithm {}, fpcalc {:?}, force: {})",
+        CHROMAPRINT_ALGORITHM,
+        version,
+        force
+    );
+
+    let mut result = RefingerprintResult::default();
+
+    // Drop entries for songs that have been deleted or compacted away
+    let live_paths: HashSet<&str> = library.songs.iter().map(|s| s.path.as_str()).collect();
+    let before = index.entries.len();
+    index.entries.retain(|path, _| live_paths.contains(path.as_str()));
+    result.removed = (before - index.entries.len()) as u32;
+
+    for song in &library.songs {
+        result.songs_checked += 1;
+
+        let current = index
+            .entries
+            .get(&song.path)
+            .map(|e| is_fingerprint_current(e, CHROMAPRINT_ALGORITHM, version.as_deref()))
+            .unwrap_or(false);
+        if current && !force {
+            result.up_to_date += 1;
+            continue;
+        }
+
+        // Pause rather than fail every remaining song if the SD card is pulled
+        wait_while_library_unavailable().await;
+
+        match fingerprint_file(music_path.join(&song.path)) {
+            Ok((fingerprint, duration_secs)) => {
+                index.entries.insert(
+                    song.path.clone(),
+                    StoredFingerprint {
+                        fingerprint,
+                        duration_secs,
+                        algorithm: CHROMAPRINT_ALGORITHM,
+                        fpcalc_version: version.clone(),
+                    },
+                );
+                result.refingerprinted += 1;
+            }
+            Err(e) => {
+                log::error!("Failed to fingerprint {}: {}", song.path, e);
+                result.failed += 1;
+            }
+        }
+    }
+
+    save_fingerprint_index(base, &index)?;
+
+    log::info!(
+        "Re-fingerprint complete: {} refingerprinted, {} up to date, {} failed, {} removed",
+        result.refingerprinted,
+        result.up_to_date,
+        result.failed,
+        result.removed
+    );
+
+    Ok(result)
+}
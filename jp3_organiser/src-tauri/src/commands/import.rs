@@ -0,0 +1,429 @@
+//! Folder import commands.
+//!
+//! Handles:
+//! - Walking an existing music folder (Artist/Album/Track style)
+//! - Inferring metadata from tags plus the folder structure
+//! - Grouping files by inferred album into an import plan
+//! - Incremental rescans that only propose new or changed files
+//! - Preview statistics (new artists/albums, duplicates, size, transcodes)
+//! - Adopting a folder in place, without copying its files
+//!
+//! Scanning does not modify the library; the plan is returned to the
+//! frontend for review and files are saved via `save_to_library`.
+//! `scan_and_adopt` is the exception: it registers the folder's songs
+//! directly.
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::commands::audio::extract_tag_metadata;
+use crate::commands::library::{
+    copy_adopted_songs, initialize_library, load_library, save_songs, FilePlacement, FileToSave,
+};
+use crate::models::{
+    AdoptResult, ImportAlbumGroup, ImportPlan, ImportPreviewStats, MetadataSource,
+    MetadataStatus, ParsedLibrary, TrackedAudioFile,
+};
+use crate::services::folder_scan_service::{
+    capture_file_state, collect_audio_files, fill_missing_from_inferred, has_file_changed,
+    infer_metadata_from_path, load_scan_state, save_scan_state,
+};
+use crate::services::format_sniff_service::apply_detected_format;
+use crate::services::library_monitor_service::ensure_library_available;
+use crate::services::parallel_io_service::map_ordered;
+use crate::services::task_manager_service::TaskManager;
+
+/// Rough transcode throughput used for time estimates (bytes per second).
+const TRANSCODE_BYTES_PER_SEC: u64 = 3 * 1024 * 1024;
+
+/// Scan a music folder and build an import plan grouped by inferred album.
+///
+/// Tags are read first; anything missing is filled in from the folder
+/// structure and filename. Files that still lack an artist or album are
+/// grouped by the folder they live in.
+///
+/// When `base_path` is given, preview stats are predicted against that
+/// library; otherwise against an empty one.
+#[tauri::command]
+pub async fn scan_music_folder(
+    path: String,
+    base_path: Option<String>,
+) -> Result<ImportPlan, String> {
+    build_import_plan(&path, base_path.as_deref())
+}
+
+/// Build an import plan for a folder (blocking).
+///
+/// Separated from the command so it can be called from tests and
+/// other commands without an async runtime.
+pub fn build_import_plan(path: &str, base_path: Option<&str>) -> Result<ImportPlan, String> {
+    let library = match base_path {
+        Some(base) => load_existing_library(base)?,
+        None => None,
+    };
+
+    let root = Path::new(path);
+    if !root.is_dir() {
+        return Err(format!("Folder not found: {}", path));
+    }
+
+    log::info!("Scanning music folder: {}", path);
+
+    let (audio_files, skipped_files) = collect_audio_files(root)?;
+    Ok(plan_from_files(root, audio_files, skipped_files, 0, library.as_ref()))
+}
+
+/// Rescan a previously imported folder, proposing only new or changed files.
+///
+/// Files are compared against the state recorded by `record_folder_import`.
+/// Unchanged files are left out of the plan (and never re-fingerprinted);
+/// their count is reported in `unchanged_count`.
+#[tauri::command]
+pub async fn rescan_music_folder(base_path: String, path: String) -> Result<ImportPlan, String> {
+    build_rescan_plan(&base_path, &path)
+}
+
+/// Build an incremental rescan plan (blocking).
+pub fn build_rescan_plan(base_path: &str, path: &str) -> Result<ImportPlan, String> {
+    let base = Path::new(base_path);
+    ensure_library_available(base)?;
+
+    let root = Path::new(path);
+    if !root.is_dir() {
+        return Err(format!("Folder not found: {}", path));
+    }
+    let root = canonical_root(root);
+
+    let state = load_scan_state(base)?;
+    let recorded = state.folders.get(&root.to_string_lossy().to_string());
+
+    let (audio_files, skipped_files) = collect_audio_files(&root)?;
+    let total = audio_files.len();
+
+    let changed: Vec<PathBuf> = audio_files
+        .into_iter()
+        .filter(|file| {
+            let previous = recorded.and_then(|r| r.get(&file.to_string_lossy().to_string()));
+            has_file_changed(file, previous)
+        })
+        .collect();
+    let unchanged_count = total - changed.len();
+
+    log::info!(
+        "Rescan of {}: {} new or changed, {} unchanged",
+        path,
+        changed.len(),
+        unchanged_count
+    );
+
+    let library = load_existing_library(base_path)?;
+    Ok(plan_from_files(
+        &root,
+        changed,
+        skipped_files,
+        unchanged_count,
+        library.as_ref(),
+    ))
+}
+
+/// Record the state of imported files so later rescans can skip them.
+///
+/// Called after the user confirms an import from a scanned folder.
+/// Returns the number of files recorded.
+#[tauri::command]
+pub fn record_folder_import(
+    base_path: String,
+    path: String,
+    file_paths: Vec<String>,
+) -> Result<usize, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+
+    let root = canonical_root(Path::new(&path));
+    let mut state = load_scan_state(base)?;
+    let folder = state
+        .folders
+        .entry(root.to_string_lossy().to_string())
+        .or_default();
+
+    let mut recorded = 0;
+    for file_path in file_paths {
+        let file = canonical_root(Path::new(&file_path));
+        match capture_file_state(&file) {
+            Ok(file_state) => {
+                folder.insert(file.to_string_lossy().to_string(), file_state);
+                recorded += 1;
+            }
+            Err(e) => log::warn!("Could not record import state: {}", e),
+        }
+    }
+
+    save_scan_state(base, &state)?;
+    log::info!("Recorded import state for {} files under {}", recorded, path);
+    Ok(recorded)
+}
+
+/// Index a music folder into the library, leaving its files where they are.
+///
+/// Metadata is read the way `scan_music_folder` reads it, and every file
+/// with a title, artist and album is saved with its absolute path instead of
+/// a copy in the music buckets, so a large collection is indexed without
+/// copying it. Files lacking any of the three are listed in
+/// `incomplete_files` for the normal import flow.
+///
+/// Adopted songs can't be played on the device until they are copied in.
+/// With `copy_files`, `copy_adopted_songs` is queued as a background task
+/// for the songs just adopted.
+#[tauri::command]
+pub async fn scan_and_adopt(
+    tasks: State<'_, TaskManager>,
+    base_path: String,
+    path: String,
+    copy_files: Option<bool>,
+) -> Result<AdoptResult, String> {
+    let mut result = adopt_music_folder(&base_path, &path)?;
+    if copy_files.unwrap_or(false) && !result.song_ids.is_empty() {
+        let song_ids = result.song_ids.clone();
+        tasks.submit_io("Copying adopted songs into the library", move || {
+            copy_adopted_songs(base_path, Some(song_ids)).map(|_| ())
+        });
+        result.copy_queued = true;
+    }
+    Ok(result)
+}
+
+/// Adopt a music folder in place (blocking). See `scan_and_adopt`.
+pub fn adopt_music_folder(base_path: &str, path: &str) -> Result<AdoptResult, String> {
+    let base = Path::new(base_path);
+    if !base.join("jp3").exists() {
+        initialize_library(base_path.to_string())?;
+    }
+    ensure_library_available(base)?;
+
+    let plan = build_import_plan(path, Some(base_path))?;
+    let (complete, incomplete): (Vec<TrackedAudioFile>, Vec<TrackedAudioFile>) = plan
+        .groups
+        .into_iter()
+        .flat_map(|group| group.files)
+        .partition(|file| file.metadata_status == MetadataStatus::Complete);
+
+    let files = complete
+        .into_iter()
+        .map(|file| FileToSave {
+            source_path: file.file_path,
+            metadata: file.metadata,
+            metadata_source: file.metadata_source,
+            confidence: file.confidence,
+            fingerprint: None,
+        })
+        .collect();
+    let saved = save_songs(base_path, files, FilePlacement::InPlace)?;
+    log::info!(
+        "Adopted {} songs from {} ({} incomplete)",
+        saved.files_saved,
+        path,
+        incomplete.len()
+    );
+
+    Ok(AdoptResult {
+        songs_adopted: saved.files_saved,
+        duplicates_skipped: saved.duplicates_skipped,
+        protected_skipped: saved.protected_skipped,
+        incomplete_files: incomplete.into_iter().map(|file| file.file_path).collect(),
+        skipped_files: plan.skipped_files,
+        song_ids: saved.song_ids,
+        copy_queued: false,
+    })
+}
+
+/// Canonicalize a path so recorded keys match between scans.
+/// Falls back to the path as given if it cannot be resolved.
+fn canonical_root(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Load the library to predict against, or None if it has no songs yet.
+fn load_existing_library(base_path: &str) -> Result<Option<ParsedLibrary>, String> {
+    ensure_library_available(Path::new(base_path))?;
+    // load_library errors when library.bin does not exist yet
+    Ok(load_library(base_path.to_string(), None).ok())
+}
+
+/// Read, infer and group a set of files into an import plan.
+fn plan_from_files(
+    root: &Path,
+    audio_files: Vec<PathBuf>,
+    skipped_files: Vec<String>,
+    unchanged_count: usize,
+    library: Option<&ParsedLibrary>,
+) -> ImportPlan {
+    let total_files = audio_files.len();
+
+    let mut groups: Vec<ImportAlbumGroup> = Vec::new();
+    let mut group_index: HashMap<String, usize> = HashMap::new();
+
+    // Tag reads dominate scan time on large folders
+    let scanned = map_ordered(&audio_files, |file| scan_file(root, file), |_, _| {});
+
+    for (file, tracked_file) in audio_files.into_iter().zip(scanned) {
+
+        let folder = file
+            .parent()
+            .and_then(|p| p.strip_prefix(root).ok())
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let metadata = &tracked_file.metadata;
+        let key = match (metadata.album_artist_name(), &metadata.album) {
+            (Some(artist), Some(album)) => format!(
+                "album:{}:{}",
+                artist.trim().to_lowercase(),
+                album.trim().to_lowercase()
+            ),
+            _ => format!("folder:{}", folder),
+        };
+
+        let index = *group_index.entry(key).or_insert_with(|| {
+            groups.push(ImportAlbumGroup {
+                artist: metadata.album_artist_name().cloned(),
+                album: metadata.album.clone(),
+                folder,
+                files: Vec::new(),
+                complete_count: 0,
+            });
+            groups.len() - 1
+        });
+        groups[index].files.push(tracked_file);
+    }
+
+    for group in &mut groups {
+        group.files.sort_by(|a, b| {
+            a.metadata
+                .disc_track_key()
+                .cmp(&b.metadata.disc_track_key())
+                .then_with(|| a.file_name.cmp(&b.file_name))
+        });
+        group.complete_count = group
+            .files
+            .iter()
+            .filter(|f| f.metadata_status == MetadataStatus::Complete)
+            .count();
+    }
+
+    log::info!(
+        "Folder scan complete: {} files in {} groups ({} skipped)",
+        total_files,
+        groups.len(),
+        skipped_files.len()
+    );
+
+    let stats = compute_preview_stats(&groups, library);
+
+    ImportPlan {
+        root_path: root.to_string_lossy().to_string(),
+        groups,
+        total_files,
+        skipped_files,
+        unchanged_count,
+        stats,
+    }
+}
+
+/// Predict what saving a plan would do to the library.
+///
+/// Matches names exactly, the same way `save_to_library` deduplicates.
+fn compute_preview_stats(
+    groups: &[ImportAlbumGroup],
+    library: Option<&ParsedLibrary>,
+) -> ImportPreviewStats {
+    let mut artists: HashSet<String> = HashSet::new();
+    let mut albums: HashSet<(String, String)> = HashSet::new();
+    let mut songs: HashSet<(String, String, String)> = HashSet::new();
+
+    if let Some(library) = library {
+        artists.extend(library.artists.iter().map(|a| a.name.clone()));
+        albums.extend(
+            library
+                .albums
+                .iter()
+                .map(|a| (a.artist_name.clone(), a.name.clone())),
+        );
+        songs.extend(library.songs.iter().map(|s| {
+            (s.title.clone(), s.artist_name.clone(), s.album_name.clone())
+        }));
+    }
+
+    let mut stats = ImportPreviewStats::default();
+
+    for file in groups.iter().flat_map(|g| g.files.iter()) {
+        stats.total_size_bytes += file.file_size;
+        if file.needs_transcode {
+            stats.transcode_count += 1;
+            stats.estimated_transcode_secs += file.file_size.div_ceil(TRANSCODE_BYTES_PER_SEC);
+        }
+
+        let metadata = &file.metadata;
+        let (title, artist, album) = match (&metadata.title, &metadata.artist, &metadata.album) {
+            (Some(t), Some(ar), Some(al)) => (t.clone(), ar.clone(), al.clone()),
+            _ => continue,
+        };
+
+        let album_artist = metadata.album_artist_name().cloned().unwrap_or_else(|| artist.clone());
+
+        if artists.insert(artist.clone()) {
+            stats.new_artists += 1;
+        }
+        if artists.insert(album_artist.clone()) {
+            stats.new_artists += 1;
+        }
+        if albums.insert((album_artist, album.clone())) {
+            stats.new_albums += 1;
+        }
+        if !songs.insert((title, artist, album)) {
+            stats.expected_duplicates += 1;
+        }
+    }
+
+    stats
+}
+
+/// Read tags for a single file and fill the gaps from its path.
+fn scan_file(root: &Path, file: &Path) -> TrackedAudioFile {
+    let tracking_id = Uuid::new_v4().to_string();
+    let mut tracked_file = TrackedAudioFile::new(tracking_id, file.to_string_lossy().to_string());
+
+    let format_extension = apply_detected_format(&mut tracked_file);
+    if matches!(
+        tracked_file.metadata_status,
+        MetadataStatus::Error | MetadataStatus::ProtectedFile
+    ) {
+        // Empty or copy-protected: nothing to import, keep the error visible
+        return tracked_file;
+    }
+
+    if format_extension == "mp3" {
+        extract_tag_metadata(&mut tracked_file);
+        if tracked_file.metadata.is_complete() {
+            tracked_file.metadata_source = MetadataSource::Id3;
+        }
+    }
+
+    // A file with an unreadable tag can still be imported from its path
+    if tracked_file.metadata_status == MetadataStatus::Error {
+        log::warn!(
+            "Tag read failed for {}: {:?}",
+            tracked_file.file_path,
+            tracked_file.error_message
+        );
+        tracked_file.error_message = None;
+    }
+
+    let inferred = infer_metadata_from_path(root, file);
+    if fill_missing_from_inferred(&mut tracked_file.metadata, &inferred) {
+        tracked_file.metadata_source = MetadataSource::FolderStructure;
+    }
+
+    tracked_file.update_status();
+    tracked_file
+}
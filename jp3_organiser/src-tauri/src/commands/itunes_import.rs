@@ -0,0 +1,172 @@
+//! iTunes / Music.app library import.
+//!
+//! Handles:
+//! - Reading tracks and playlists from a `Library.xml` export
+//! - Copying the tracks' files into the library with their iTunes metadata
+//!   (track and disc numbers, years, ratings, play counts)
+//! - Recreating favorites and playlists
+//!
+//! Unlike a folder scan there is no plan to review: iTunes metadata is
+//! already curated, so files are saved straight away via `save_to_library`.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::commands::library::{
+    initialize_library, load_library, save_to_library, toggle_favorite, FileToSave,
+};
+use crate::commands::playlist::create_playlist;
+use crate::models::{AudioMetadata, ItunesImportResult, MetadataSource};
+use crate::services::itunes_library_service::{
+    file_url_to_path, locate_track_file, read_itunes_library, ItunesTrack,
+};
+use crate::services::library_monitor_service::ensure_library_available;
+use crate::services::tag_reader_service::{fill_missing, read_file_tags};
+
+/// Import the music and playlists of an iTunes `Library.xml` export.
+///
+/// Each track's file is copied from where the export says it is. If the
+/// media folder has moved since (e.g. onto another drive), pass its new
+/// location as `music_folder` and tracks under the old one are looked for
+/// there instead. Songs already in the library are skipped; favorites and
+/// playlists are restored against the IDs the songs get.
+#[tauri::command]
+pub fn import_itunes_library(
+    base_path: String,
+    xml_path: String,
+    music_folder: Option<String>,
+) -> Result<ItunesImportResult, String> {
+    let itunes = read_itunes_library(Path::new(&xml_path))?;
+
+    let base = Path::new(&base_path);
+    if !base
+        .join("jp3")
+        .join("metadata")
+        .join("library.bin")
+        .exists()
+    {
+        initialize_library(base_path.clone())?;
+    }
+    ensure_library_available(base)?;
+
+    let exported_folder = itunes.music_folder.as_deref().and_then(file_url_to_path);
+    let music_folder = music_folder.map(PathBuf::from);
+
+    let mut result = ItunesImportResult::default();
+    let mut tracks: Vec<&ItunesTrack> = itunes.tracks.values().collect();
+    tracks.sort_by_key(|track| track.track_id);
+    let mut found: Vec<(&ItunesTrack, PathBuf, AudioMetadata)> = Vec::new();
+    for track in tracks {
+        if !track.is_importable() {
+            result.tracks_skipped += 1;
+            continue;
+        }
+        match locate_track_file(track, exported_folder.as_deref(), music_folder.as_deref()) {
+            Some(path) => {
+                let metadata = track_metadata(track, &path);
+                if metadata.is_complete() {
+                    found.push((track, path, metadata));
+                } else {
+                    let path = path.to_string_lossy().to_string();
+                    result.incomplete_files.push(path);
+                }
+            }
+            None => result.files_missing.push(
+                track
+                    .file_path()
+                    .map(|path| path.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+
+    log::info!(
+        "[import_itunes_library] {} tracks to import, {} skipped, {} missing, {} incomplete",
+        found.len(),
+        result.tracks_skipped,
+        result.files_missing.len(),
+        result.incomplete_files.len()
+    );
+
+    let files = found
+        .iter()
+        .map(|(_, path, metadata)| FileToSave {
+            source_path: path.to_string_lossy().to_string(),
+            metadata: metadata.clone(),
+            // Curated by the user in iTunes rather than read from tags
+            metadata_source: MetadataSource::Manual,
+            confidence: None,
+            fingerprint: None,
+        })
+        .collect();
+    let saved = save_to_library(base_path.clone(), files)?;
+    result.songs_imported = saved.songs_added;
+    result.duplicates_skipped = saved.duplicates_skipped;
+
+    // Songs are keyed by (title, artist, album) in the library, so the
+    // imported tracks are found again by name
+    let library = load_library(base_path.clone(), None)?;
+    let by_name: HashMap<(&str, &str, &str), (u32, bool)> = library
+        .songs
+        .iter()
+        .map(|song| {
+            let key = (
+                song.title.as_str(),
+                song.artist_name.as_str(),
+                song.album_name.as_str(),
+            );
+            (key, (song.id, song.is_favorite))
+        })
+        .collect();
+    let new_ids: HashMap<u64, (u32, bool)> = found
+        .iter()
+        .filter_map(|(track, _, metadata)| {
+            let key = (
+                metadata.title.as_deref()?,
+                metadata.artist.as_deref()?,
+                metadata.album.as_deref()?,
+            );
+            Some((track.track_id, *by_name.get(&key)?))
+        })
+        .collect();
+
+    for (track, _, _) in found.iter().filter(|(track, _, _)| track.is_favorite()) {
+        if let Some(&(id, false)) = new_ids.get(&track.track_id) {
+            toggle_favorite(base_path.clone(), id)?;
+            result.favorites_restored += 1;
+        }
+    }
+
+    for playlist in itunes.playlists.iter().filter(|p| p.is_user_playlist()) {
+        let song_ids: Vec<u32> = playlist
+            .items
+            .iter()
+            .filter_map(|item| new_ids.get(&item.track_id).map(|(id, _)| *id))
+            .collect();
+        if song_ids.is_empty() {
+            continue;
+        }
+        create_playlist(base_path.clone(), playlist.name.clone(), song_ids)?;
+        result.playlists_created += 1;
+    }
+
+    log::info!(
+        "[import_itunes_library] Imported {} songs ({} already present), {} playlists",
+        result.songs_imported,
+        result.duplicates_skipped,
+        result.playlists_created
+    );
+
+    Ok(result)
+}
+
+/// The track's iTunes metadata, with anything it lacks taken from the
+/// file's own tags.
+fn track_metadata(track: &ItunesTrack, path: &Path) -> AudioMetadata {
+    let mut metadata = track.metadata();
+    if !metadata.is_complete() {
+        if let Ok(tags) = read_file_tags(path) {
+            fill_missing(&mut metadata, tags.metadata);
+        }
+    }
+    metadata
+}
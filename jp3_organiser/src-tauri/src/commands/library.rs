@@ -3,15 +3,102 @@
 //! Handles library initialization, status queries, saving files to library,
 //! soft delete, edit, and compaction operations.
 
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::fs::{self, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tauri::{AppHandle, Emitter, Manager, State};
 
 use crate::models::{
-    song_flags, AlbumEntry, ArtistEntry, AudioMetadata, LibraryHeader, LibraryInfo, ParsedAlbum,
-    ParsedArtist, ParsedLibrary, ParsedSong, SaveToLibraryResult, SongEntry, StringTable,
-    HEADER_SIZE,
+    song_flags, AlbumEntry, ArtistEntry, AudioMetadata, CompactPhase, CompactProgress,
+    CompactionThresholds, EncodeProfile, GenreEntry,
+    LibraryHeader, LibraryInfo, LibrarySort, PendingSync, BucketLayout, MAX_FILES_PER_BUCKET,
+    MatchConfidence, MetadataSource, ParsedAlbum, ParsedArtist, ParsedGenre, ParsedLibrary,
+    ParsedSong, PlaylistEntry, SaveHookSettings, SaveToLibraryResult, SongEntry, StringTable, LibraryPage, LibraryPageKind, Script,
+    ScriptCount, StoredFingerprint, CHECKSUM_OFFSET, CHECKSUM_VERSION, DISC_NUMBERS_VERSION, GENERATION_OFFSET, GENRES_VERSION, HEADER_SIZE,
+    SyncAction, SyncPlanFile, SyncPlanReport, SyncReason, SyncRecord, TABLE_SLACK_VERSION,
+    HEADER_SIZE_V2, MAX_RATING, NO_GENRE, RATING_OFFSET, SORT_NAMES_VERSION,
+};
+use crate::services::audio_info_service::read_stream_info;
+use crate::services::backup_service::{
+    backup_dir, create_backup, create_backup_keeping, delete_backup as delete_backup_dir,
+    list_backups as list_backup_dirs, restore_backup,
+};
+use crate::services::compaction_service::{
+    charge_idle_writes, check_cancelled, idle_budget_remaining, intern_chunk, CompactionControl,
+    CompactionGuard, COMPACT_CHUNK_SIZE, IDLE_STEP_MAX_SONGS,
+};
+use crate::commands::audio::get_audio_metadata;
+use crate::services::device_service::{
+    check_firmware_supports, compatibility_problems, ensure_device_marker as write_device_marker,
+    read_device_id, read_device_marker, writable_library_version,
+    write_device_marker as restore_device_marker, DeviceMarker,
+};
+use crate::services::file_naming_service::{
+    fat_safe_stem, song_file_name, taken_file_names, unique_file_name,
+};
+use crate::services::format_sniff_service::{detect_drm, sniff_audio_format};
+use crate::services::library_events_service::{
+    emit_library_change, EVENT_ALBUM_UPDATED, EVENT_ARTIST_UPDATED, EVENT_LIBRARY_COMPACTED,
+    EVENT_SONGS_ADDED, EVENT_SONGS_DELETED, EVENT_SONGS_UPDATED,
+};
+use crate::services::library_monitor_service::ensure_library_available;
+use crate::services::library_snapshot_service::{
+    diff_libraries, full_delta, remember_snapshot, snapshot_at,
+};
+use crate::services::library_settings_service::{load_library_settings, save_library_settings};
+use crate::services::parallel_io_service::{copy_file, map_ordered};
+use crate::services::power_service::keep_awake;
+use crate::services::report_service::render_library_report;
+use crate::services::song_confidence_service::{
+    load_song_confidence, record_song_confidence, rename_song_confidence, retain_song_confidence,
+};
+use crate::services::cover_art_service::{cover_filename, get_cover_path_by_name};
+use crate::services::export_budget_service::select_within_budget;
+use crate::services::fingerprint_index_service::{
+    current_fingerprints, load_fingerprint_index, record_fingerprints, rename_fingerprints,
+    retain_fingerprints,
+};
+use crate::services::fingerprint_match_service::{
+    decode_fingerprint, fingerprints_match, FingerprintCandidate,
+};
+use crate::services::fingerprint_service::{fpcalc_version, CHROMAPRINT_ALGORITHM};
+use crate::services::folder_scan_service::hash_file;
+use crate::services::task_manager_service::begin_foreground_write;
+use crate::services::thumbnail_service::{
+    ensure_thumbnail, remove_thumbnails, thumbnail_path, ThumbnailSize, THUMBS_DIR,
+};
+use crate::services::transcode_service::{
+    cached_transcode_path, clip_range, encode_clip, needs_transcode,
+    prune_transcode_cache as prune_cached_transcodes, transcode_cache_stats, transcode_cached,
+    DEFAULT_CLIP_SECS,
+};
+use crate::services::save_hook_service::{
+    run_pre_save_hook, save_hooks, spawn_post_save_hook, PendingSave, SavedSong,
+};
+use crate::services::replay_gain_service::{rename_replay_gain, retain_replay_gain};
+use crate::services::song_hash_service::{
+    load_song_hashes, record_song_hashes, rename_song_hashes, retain_song_hashes, songs_by_hash,
+};
+use crate::services::sync_history_service::{load_sync_history, record_sync};
+use crate::services::sync_plan_service::{
+    clear_pending_sync, load_pending_sync, render_sync_plan, save_pending_sync,
+};
+use crate::services::sort_name_service::{collate, compare_names, sort_name, sort_songs};
+use crate::services::trash_service::{
+    empty_trash as empty_trash_before, list_trash as list_trash_entries, move_to_trash,
+    purge_expired, restore_from_trash as restore_trashed_files,
+};
+use crate::services::transliteration_service::{
+    add_generated_keys, detect_script, load_transliterations, romanized_key,
+    save_transliterations,
+};
+use crate::services::write_marker_service::{
+    begin_library_write, clear_interrupted_writes, interrupted_writes,
 };
 
 // JP3 directory structure constants
@@ -88,13 +175,101 @@ pub fn initialize_library(base_path: String) -> Result<String, String> {
             .map_err(|e| format!("Failed to write library.bin header: {}", e))?;
     }
 
+    ensure_device_marker(base)?;
+
     Ok(jp3_path.to_string_lossy().to_string())
 }
 
+/// The card's `jp3/metadata/device.json`, written for the current
+/// library.bin if the card doesn't have one yet.
+pub fn ensure_device_marker(base: &Path) -> Result<DeviceMarker, String> {
+    let library_bin_path = base.join(JP3_DIR).join(METADATA_DIR).join(LIBRARY_BIN);
+    let mut file = fs::File::open(&library_bin_path)
+        .map_err(|e| format!("Failed to open library.bin: {}", e))?;
+    let header = read_header(&mut file)?;
+    write_device_marker(base, header.version, header.generation)
+}
+
+/// Refuse to write a library.bin the card's firmware can't read.
+///
+/// `tables` are laid out for `version` (see `LibraryTables::for_version`).
+fn ensure_firmware_reads(
+    library_bin_path: &Path,
+    tables: &LibraryTables,
+    version: u32,
+) -> Result<(), String> {
+    let song_count = tables.songs.len() as u32 / SongEntry::size_for(version);
+    // {base}/jp3/metadata/library.bin
+    match library_bin_path.ancestors().nth(3) {
+        Some(base) => check_firmware_supports(base, version, song_count),
+        None => Ok(()),
+    }
+}
+
+/// Version to write library.bin at `library_bin_path` in: the newest the
+/// card's firmware reads (see `writable_library_version`).
+fn library_bin_version(library_bin_path: &Path) -> u32 {
+    library_bin_path
+        .ancestors()
+        .nth(3)
+        .map_or(crate::models::LIBRARY_VERSION, writable_library_version)
+}
+
+/// Check the library on a card against the limits its firmware declared.
+///
+/// Reports every problem with the fix for each, rather than failing on the
+/// first. Cards whose firmware declared nothing report no problems.
+#[tauri::command]
+pub fn check_device_compatibility(
+    base_path: String,
+) -> Result<crate::models::DeviceCompatibilityReport, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let Some(marker) = read_device_marker(base)? else {
+        return Ok(Default::default());
+    };
+
+    let jp3_path = base.join(JP3_DIR);
+    let mut file = fs::File::open(jp3_path.join(METADATA_DIR).join(LIBRARY_BIN))
+        .map_err(|e| format!("Failed to open library.bin: {}", e))?;
+    let header = read_header(&mut file)?;
+
+    let mut playlists: Vec<(String, u32)> = Vec::new();
+    if let Ok(entries) = fs::read_dir(jp3_path.join(PLAYLISTS_DIR)) {
+        for entry in entries.flatten() {
+            let Some(playlist_id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(".bin"))
+                .and_then(|id_str| id_str.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            let Ok(data) = fs::read(entry.path()) else {
+                continue;
+            };
+            let Some(playlist_header) = crate::models::PlaylistHeader::from_bytes(&data) else {
+                continue;
+            };
+            let name = crate::commands::playlist::read_playlist_file(&entry.path(), playlist_id)
+                .map(|p| p.name)
+                .unwrap_or_else(|_| format!("{}.bin", playlist_id));
+            playlists.push((name, playlist_header.version));
+        }
+    }
+    playlists.sort();
+
+    Ok(crate::models::DeviceCompatibilityReport {
+        problems: compatibility_problems(&marker, header.version, header.song_count, &playlists),
+        firmware_version: marker.firmware_version,
+    })
+}
+
 /// Get information about the current library structure.
 #[tauri::command]
 pub fn get_library_info(base_path: String) -> Result<LibraryInfo, String> {
     let base = Path::new(&base_path);
+    ensure_library_available(base)?;
     let jp3_path = base.join(JP3_DIR);
 
     if !jp3_path.exists() {
@@ -134,10 +309,156 @@ pub fn get_library_info(base_path: String) -> Result<LibraryInfo, String> {
 pub struct FileToSave {
     pub source_path: String,
     pub metadata: AudioMetadata,
+    /// Where the metadata came from, stored on the song
+    #[serde(default)]
+    pub metadata_source: MetadataSource,
+    /// Confidence of the automatic match, recorded for later review
+    #[serde(default)]
+    pub confidence: Option<MatchConfidence>,
+    /// Acoustic fingerprint and duration from fpcalc, used to spot the same
+    /// recording already in the library and stored for later checks
+    #[serde(default)]
+    pub fingerprint: Option<(String, u32)>,
+}
+
+/// Prefix of the error returned when a page snapshot no longer matches the
+/// library. The frontend matches on this to reload from the first page.
+pub const SNAPSHOT_EXPIRED: &str = "SnapshotExpired";
+
+/// Prefix of the error returned when library.bin fails its checksum, e.g.
+/// after the SD card was pulled mid-write. The frontend matches on this to
+/// offer a rebuild instead of showing a generic load failure.
+pub const LIBRARY_CORRUPTED: &str = "LibraryCorrupted";
+
+/// Prefix of the error returned while a library.bin write from an earlier
+/// run is still marked in progress. The frontend matches on this to run
+/// `recover_interrupted_writes` before loading the library.
+pub const INTERRUPTED_WRITE: &str = "InterruptedWrite";
+
+/// Read just the header of library.bin (40 bytes before v3, 48 in v3/v4,
+/// 52 in v5, 56 from v6).
+fn read_header(file: &mut fs::File) -> Result<LibraryHeader, String> {
+    let mut header_bytes = Vec::with_capacity(HEADER_SIZE as usize);
+    file.take(HEADER_SIZE as u64)
+        .read_to_end(&mut header_bytes)
+        .map_err(|e| format!("Failed to read library.bin header: {}", e))?;
+    LibraryHeader::from_bytes(&header_bytes).ok_or_else(|| "Invalid library.bin header".to_string())
+}
+
+/// Read and checksum the whole of library.bin before editing it in place,
+/// so an edit never stamps a fresh checksum over a damaged file.
+fn read_verified_header(file: &mut fs::File) -> Result<LibraryHeader, String> {
+    let mut data = Vec::new();
+    file.seek(SeekFrom::Start(0))
+        .map_err(|e| format!("Failed to seek to header: {}", e))?;
+    file.read_to_end(&mut data)
+        .map_err(|e| format!("Failed to read library.bin: {}", e))?;
+    let header = LibraryHeader::from_bytes(&data).ok_or("Invalid library.bin header")?;
+    verify_checksum(&data, &header)?;
+    Ok(header)
+}
+
+/// Generation of the library.bin at `base`, read from its header alone.
+pub fn library_generation(base: &Path) -> Result<u32, String> {
+    let path = base.join(JP3_DIR).join(METADATA_DIR).join(LIBRARY_BIN);
+    let mut file = fs::File::open(&path)
+        .map_err(|_| "library.bin not found. Add some songs first.".to_string())?;
+    Ok(read_header(&mut file)?.generation)
+}
+
+/// Emit a `library://` change event about songs of the library at `base`.
+fn announce_change(event: &str, base: &Path, song_ids: Vec<u32>) {
+    announce(
+        event,
+        base,
+        crate::models::LibraryChangeEvent {
+            song_ids,
+            ..Default::default()
+        },
+    );
+}
+
+/// Emit a `library://` change event for the library at `base`, filling in
+/// its path and current generation.
+pub(crate) fn announce(event: &str, base: &Path, change: crate::models::LibraryChangeEvent) {
+    emit_library_change(
+        event,
+        crate::models::LibraryChangeEvent {
+            base_path: base.to_string_lossy().to_string(),
+            generation: library_generation(base).unwrap_or_default(),
+            ..change
+        },
+    );
 }
 
-/// Maximum files per music bucket.
-const MAX_FILES_PER_BUCKET: usize = 256;
+/// Generation for a full rewrite of library.bin: one past the current file's.
+fn next_generation(library_bin_path: &Path) -> u32 {
+    fs::File::open(library_bin_path)
+        .ok()
+        .and_then(|mut file| read_header(&mut file).ok())
+        .map(|header| header.generation.wrapping_add(1))
+        .unwrap_or(1)
+}
+
+/// Record a new generation after editing library.bin in place.
+fn write_generation(file: &mut fs::File, generation: u32) -> Result<(), String> {
+    file.seek(SeekFrom::Start(GENERATION_OFFSET))
+        .map_err(|e| format!("Failed to seek to generation: {}", e))?;
+    file.write_all(&generation.to_le_bytes())
+        .map_err(|e| format!("Failed to write generation: {}", e))
+}
+
+/// CRC32 of a v5+ library.bin's tables.
+///
+/// v5 hashes every byte after the header; from v6 only the used part of
+/// each table is hashed, so writing into table slack leaves it unchanged.
+fn tables_checksum(data: &[u8], header: &LibraryHeader) -> u32 {
+    if header.version < TABLE_SLACK_VERSION {
+        return crc32fast::hash(data.get(header.size() as usize..).unwrap_or_default());
+    }
+    let mut hasher = crc32fast::Hasher::new();
+    for (offset, len) in header.table_extents() {
+        let (start, end) = (offset as usize, offset as usize + len as usize);
+        hasher.update(data.get(start..end).unwrap_or_default());
+    }
+    hasher.finalize()
+}
+
+/// Check a whole library.bin against its header checksum.
+///
+/// Files older than v5 carry no checksum and always pass.
+fn verify_checksum(data: &[u8], header: &LibraryHeader) -> Result<(), String> {
+    if header.version < CHECKSUM_VERSION {
+        return Ok(());
+    }
+    let actual = tables_checksum(data, header);
+    if actual != header.checksum {
+        return Err(format!(
+            "{}: library.bin checksum mismatch (expected {:08x}, found {:08x})",
+            LIBRARY_CORRUPTED, header.checksum, actual
+        ));
+    }
+    Ok(())
+}
+
+/// Recompute the checksum after editing library.bin's tables in place.
+fn write_checksum(file: &mut fs::File, header: &LibraryHeader) -> Result<(), String> {
+    if header.version < CHECKSUM_VERSION {
+        return Ok(());
+    }
+    let mut data = Vec::new();
+    file.seek(SeekFrom::Start(0))
+        .map_err(|e| format!("Failed to seek to header: {}", e))?;
+    file.read_to_end(&mut data)
+        .map_err(|e| format!("Failed to read library.bin: {}", e))?;
+    file.seek(SeekFrom::Start(CHECKSUM_OFFSET))
+        .map_err(|e| format!("Failed to seek to checksum: {}", e))?;
+    file.write_all(&tables_checksum(&data, header).to_le_bytes())
+        .map_err(|e| format!("Failed to write checksum: {}", e))
+}
+
+/// Scratch folder in `jp3/music` for files being renumbered by compaction
+const RENUMBER_STAGING_DIR: &str = ".renumber";
 
 /// Existing library data loaded from library.bin for incremental updates.
 struct ExistingLibraryData {
@@ -145,14 +466,105 @@ struct ExistingLibraryData {
     artists: Vec<ArtistEntry>,
     albums: Vec<AlbumEntry>,
     songs: Vec<SongEntry>,
+    genres: Vec<GenreEntry>,
     artist_map: HashMap<String, u32>,
     album_map: HashMap<String, u32>,
+    genre_map: HashMap<String, u16>,
     /// Set of existing songs keyed by (title, artist_id, album_id) to detect duplicates
     song_set: HashSet<(u32, u32, u32)>,
     /// Map from (title_string_id, artist_id, album_id) to song_id for finding duplicate IDs
     song_id_map: HashMap<(u32, u32, u32), u32>,
 }
 
+/// Create an artist entry, adding its name and sort name to the string table.
+fn new_artist_entry(string_table: &mut StringTable, name: &str) -> ArtistEntry {
+    ArtistEntry {
+        name_string_id: string_table.add(name),
+        sort_name_string_id: string_table.add(&sort_name(name)),
+    }
+}
+
+/// Artist ID for a name, adding the artist to the table if it is new.
+fn get_or_create_artist(
+    string_table: &mut StringTable,
+    artists: &mut Vec<ArtistEntry>,
+    artist_map: &mut HashMap<String, u32>,
+    name: &str,
+) -> u32 {
+    if let Some(&id) = artist_map.get(name) {
+        return id;
+    }
+    let id = artists.len() as u32;
+    artists.push(new_artist_entry(string_table, name));
+    artist_map.insert(name.to_string(), id);
+    id
+}
+
+/// Create an album entry, adding its name and sort name to the string table.
+fn new_album_entry(
+    string_table: &mut StringTable,
+    name: &str,
+    album_artist_id: u32,
+    year: u16,
+) -> AlbumEntry {
+    AlbumEntry {
+        name_string_id: string_table.add(name),
+        album_artist_id,
+        year,
+        sort_name_string_id: string_table.add(&sort_name(name)),
+    }
+}
+
+/// Genre ID for a song, adding the genre to the table if it is new.
+///
+/// Blank genres, and genres past the u16 ID range, are stored as `NO_GENRE`.
+fn get_or_create_genre(
+    string_table: &mut StringTable,
+    genres: &mut Vec<GenreEntry>,
+    genre_map: &mut HashMap<String, u16>,
+    genre: Option<&str>,
+) -> u16 {
+    let Some(name) = genre.map(str::trim).filter(|g| !g.is_empty()) else {
+        return NO_GENRE;
+    };
+    if let Some(&id) = genre_map.get(name) {
+        return id;
+    }
+    if genres.len() >= NO_GENRE as usize {
+        return NO_GENRE;
+    }
+    let id = genres.len() as u16;
+    genres.push(GenreEntry {
+        name_string_id: string_table.add(name),
+    });
+    genre_map.insert(name.to_string(), id);
+    id
+}
+
+/// Imported play count as stored in a song entry (capped at `u16::MAX`).
+fn saturate_play_count(play_count: Option<u32>) -> u16 {
+    play_count.unwrap_or(0).min(u16::MAX as u32) as u16
+}
+
+/// Add romanized keys for new names when the library has transliteration enabled.
+fn record_transliterations<'a>(
+    base_path: &Path,
+    names: impl IntoIterator<Item = &'a str>,
+) -> Result<(), String> {
+    let mut index = load_transliterations(base_path)?;
+    if index.enabled && add_generated_keys(&mut index, names) > 0 {
+        save_transliterations(base_path, &index)?;
+    }
+    Ok(())
+}
+
+/// Resolve a stored sort name, computing it for v1 entries that lack one.
+fn resolve_sort_name(strings: &[String], sort_name_string_id: Option<u32>, name: &str) -> String {
+    sort_name_string_id
+        .and_then(|id| strings.get(id as usize).cloned())
+        .unwrap_or_else(|| sort_name(name))
+}
+
 /// Load existing library data from library.bin for merging with new songs.
 fn load_existing_library_data(
     library_bin_path: &Path,
@@ -169,12 +581,13 @@ fn load_existing_library_data(
         .map_err(|e| format!("Failed to read library.bin: {}", e))?;
 
     // Check if file is too small (just header with no data)
-    if data.len() < HEADER_SIZE as usize {
+    if data.len() < HEADER_SIZE_V2 as usize {
         return Ok(None);
     }
 
     // Parse header
     let header = LibraryHeader::from_bytes(&data).ok_or("Invalid library.bin header")?;
+    verify_checksum(&data, &header)?;
 
     // If no songs exist, return None (fresh library)
     if header.song_count == 0 {
@@ -185,15 +598,16 @@ fn load_existing_library_data(
     let strings = parse_string_table(
         &data,
         header.string_table_offset as usize,
-        header.artist_table_offset as usize,
+        header.string_table_end() as usize,
     )?;
-    let string_table = StringTable::from_vec(strings.clone());
+    let mut string_table = StringTable::from_vec(strings.clone());
 
     // Parse raw artist table and rebuild ArtistEntry vec + map
     let raw_artists = parse_artist_table(
         &data,
         header.artist_table_offset as usize,
         header.artist_count as usize,
+        header.version,
     )?;
     let mut artists: Vec<ArtistEntry> = Vec::with_capacity(raw_artists.len());
     let mut artist_map: HashMap<String, u32> = HashMap::new();
@@ -202,9 +616,14 @@ fn load_existing_library_data(
             .get(raw.name_string_id as usize)
             .cloned()
             .unwrap_or_default();
+        // v1 libraries have no sort names yet; add them so the rewrite is v2
+        let sort_name_string_id = raw
+            .sort_name_string_id
+            .unwrap_or_else(|| string_table.add(&sort_name(&name)));
         artist_map.insert(name, id as u32);
         artists.push(ArtistEntry {
             name_string_id: raw.name_string_id,
+            sort_name_string_id,
         });
     }
 
@@ -213,6 +632,7 @@ fn load_existing_library_data(
         &data,
         header.album_table_offset as usize,
         header.album_count as usize,
+        header.version,
     )?;
     let mut albums: Vec<AlbumEntry> = Vec::with_capacity(raw_albums.len());
     let mut album_map: HashMap<String, u32> = HashMap::new();
@@ -221,20 +641,43 @@ fn load_existing_library_data(
             .get(raw.name_string_id as usize)
             .cloned()
             .unwrap_or_default();
-        let album_key = format!("{}:{}", raw.artist_id, album_name);
+        let sort_name_string_id = raw
+            .sort_name_string_id
+            .unwrap_or_else(|| string_table.add(&sort_name(&album_name)));
+        let album_key = format!("{}:{}", raw.album_artist_id, album_name);
         album_map.insert(album_key, id as u32);
         albums.push(AlbumEntry {
             name_string_id: raw.name_string_id,
-            artist_id: raw.artist_id,
+            album_artist_id: raw.album_artist_id,
             year: raw.year,
+            sort_name_string_id,
         });
     }
 
+    // Parse genre table (empty before v3)
+    let raw_genres = parse_genre_table(
+        &data,
+        header.genre_table_offset as usize,
+        header.genre_count as usize,
+    )?;
+    let mut genre_map: HashMap<String, u16> = HashMap::new();
+    let genres: Vec<GenreEntry> = raw_genres
+        .into_iter()
+        .enumerate()
+        .map(|(id, name_string_id)| {
+            if let Some(name) = strings.get(name_string_id as usize) {
+                genre_map.insert(name.clone(), id as u16);
+            }
+            GenreEntry { name_string_id }
+        })
+        .collect();
+
     // Parse raw song table and rebuild SongEntry vec + song_set for duplicate detection
     let raw_songs = parse_song_table(
         &data,
         header.song_table_offset as usize,
         header.song_count as usize,
+        header.version,
     )?;
     let mut song_set: HashSet<(u32, u32, u32)> = HashSet::new();
     let mut song_id_map: HashMap<(u32, u32, u32), u32> = HashMap::new();
@@ -256,6 +699,12 @@ fn load_existing_library_data(
                 track_number: raw.track_number,
                 duration_sec: raw.duration_sec,
                 flags: raw.flags,
+                metadata_source: raw.metadata_source,
+                genre_id: raw.genre_id,
+                disc_number: raw.disc_number,
+                rating: raw.rating,
+                date_added: raw.date_added,
+                play_count: raw.play_count,
             }
         })
         .collect();
@@ -265,8 +714,10 @@ fn load_existing_library_data(
         artists,
         albums,
         songs,
+        genres,
         artist_map,
         album_map,
+        genre_map,
         song_set,
         song_id_map,
     }))
@@ -280,13 +731,43 @@ fn load_existing_library_data(
 /// 3. Merges new songs with existing library data
 /// 4. Writes updated library.bin with all artists, albums, and songs
 ///
-/// Files are added to existing library data (incremental).
+/// Files are added to existing library data (incremental). Missing and
+/// copy-protected (DRM) source files are skipped.
 #[tauri::command]
 pub fn save_to_library(
     base_path: String,
     files: Vec<FileToSave>,
 ) -> Result<SaveToLibraryResult, String> {
-    let base = Path::new(&base_path);
+    save_songs(&base_path, files, FilePlacement::Copy)
+}
+
+/// Where saved songs' audio lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FilePlacement {
+    /// Copied into the music buckets
+    Copy,
+    /// Left where it is; the song's path is the file's absolute path
+    InPlace,
+    /// Already in the music buckets (see `scan_orphaned_files`)
+    InLibrary,
+}
+
+/// Whether a song path points outside `jp3/music` (a song adopted in
+/// place). Such files belong to the user: they are never moved, renumbered
+/// or trashed, and the device can't play them until they are copied in.
+pub fn is_adopted_path(path: &str) -> bool {
+    Path::new(path).is_absolute()
+}
+
+/// `save_to_library`, with the audio either copied in or left in place.
+pub(crate) fn save_songs(
+    base_path: &str,
+    files: Vec<FileToSave>,
+    placement: FilePlacement,
+) -> Result<SaveToLibraryResult, String> {
+    let base = Path::new(base_path);
+    ensure_library_available(base)?;
+    let _write = begin_foreground_write("Saving songs to the library");
     let jp3_path = base.join(JP3_DIR);
     let music_path = jp3_path.join(MUSIC_DIR);
     let metadata_path = jp3_path.join(METADATA_DIR);
@@ -306,8 +787,10 @@ pub fn save_to_library(
         mut artists,
         mut albums,
         mut songs,
+        mut genres,
         mut artist_map,
         mut album_map,
+        mut genre_map,
         mut song_set,
         song_id_map,
     ) = match existing {
@@ -316,8 +799,10 @@ pub fn save_to_library(
             data.artists,
             data.albums,
             data.songs,
+            data.genres,
             data.artist_map,
             data.album_map,
+            data.genre_map,
             data.song_set,
             data.song_id_map,
         ),
@@ -326,6 +811,8 @@ pub fn save_to_library(
             Vec::new(),
             Vec::new(),
             Vec::new(),
+            Vec::new(),
+            HashMap::new(),
             HashMap::new(),
             HashMap::new(),
             HashSet::new(),
@@ -337,19 +824,77 @@ pub fn save_to_library(
     let existing_artist_count = artists.len() as u32;
     let existing_album_count = albums.len() as u32;
 
-    // Find current bucket and file count
-    let (mut current_bucket, mut files_in_bucket) = get_current_bucket(&music_path)?;
+    let active_song_paths = || {
+        songs.iter().enumerate().filter_map(|(id, song)| {
+            if song.flags & song_flags::DELETED != 0 {
+                return None;
+            }
+            Some((id as u32, string_table.get(song.path_string_id)?))
+        })
+    };
+
+    // Same audio under different tags is a duplicate too (best effort)
+    let hash_index = load_song_hashes(base).unwrap_or_else(|e| {
+        log::warn!("Failed to load song hashes: {}", e);
+        Default::default()
+    });
+    let mut song_ids_by_hash = songs_by_hash(&hash_index, active_song_paths());
+    let mut saved_hashes: Vec<(String, String)> = Vec::new();
+
+    // So is the same recording from another source, e.g. a FLAC rip of a
+    // song saved as MP3 (best effort, only with a current fingerprint)
+    let fingerprint_version = fpcalc_version();
+    let mut song_fingerprints = if files.iter().any(|file| file.fingerprint.is_some()) {
+        let fingerprint_index = load_fingerprint_index(base).unwrap_or_else(|e| {
+            log::warn!("Failed to load fingerprint index: {}", e);
+            Default::default()
+        });
+        current_fingerprints(
+            &fingerprint_index,
+            active_song_paths(),
+            CHROMAPRINT_ALGORITHM,
+            fingerprint_version.as_deref(),
+        )
+    } else {
+        Vec::new()
+    };
+    let mut saved_fingerprints: Vec<(String, StoredFingerprint)> = Vec::new();
+
+    let mut buckets = BucketAllocator::new(base, &music_path)?;
+
+    // Power-user hooks (see `save_hook_service`)
+    let hooks = save_hooks();
+    let (files, file_names) = apply_pre_save_hook(&hooks, base, files)?;
+    let mut hooked_songs: Vec<SavedSong> = Vec::new();
 
     let mut files_saved = 0u32;
     let mut duplicates_skipped = 0u32;
+    let mut protected_skipped = 0u32;
+    let mut missing_skipped = 0u32;
+    let mut saved_files: Vec<u32> = Vec::new();
     let mut saved_song_ids: Vec<u32> = Vec::new();
     let mut duplicate_song_ids: Vec<u32> = Vec::new();
     let mut saved_album_ids: Vec<u32> = Vec::new();
+    let mut saved_confidence: Vec<(String, MatchConfidence)> = Vec::new();
 
-    for file_to_save in files {
+    // Every song in the batch shares one date added
+    let date_added = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as u32)
+        .unwrap_or(0);
+
+    // The pre-save hook keeps files in order, so positions match the request
+    for (index, (file_to_save, file_name)) in files.into_iter().zip(file_names).enumerate() {
         let source = Path::new(&file_to_save.source_path);
         if !source.exists() {
-            continue; // Skip missing files
+            log::warn!("Not saving {}: file not found", source.display());
+            missing_skipped += 1;
+            continue;
+        }
+        if let Ok(Some(scheme)) = detect_drm(source) {
+            log::warn!("Not saving {}: protected by {}", source.display(), scheme.name());
+            protected_skipped += 1;
+            continue;
         }
 
         let metadata = &file_to_save.metadata;
@@ -359,29 +904,63 @@ pub fn save_to_library(
         let artist_name = metadata.artist.as_ref().ok_or("Missing artist")?;
         let album_name = metadata.album.as_ref().ok_or("Missing album")?;
 
-        // Get or create artist
-        let artist_id = if let Some(&id) = artist_map.get(artist_name) {
-            id
-        } else {
-            let id = artists.len() as u32;
-            let name_string_id = string_table.add(artist_name);
-            artists.push(ArtistEntry { name_string_id });
-            artist_map.insert(artist_name.clone(), id);
-            id
+        let content_hash = hash_file(source)
+            .map_err(|e| log::warn!("Failed to hash {}: {}", source.display(), e))
+            .ok();
+        if let Some(&existing_song_id) =
+            content_hash.as_ref().and_then(|hash| song_ids_by_hash.get(hash))
+        {
+            log::info!("Skipping duplicate audio: '{}' matches song {}", title, existing_song_id);
+            duplicate_song_ids.push(existing_song_id);
+            duplicates_skipped += 1;
+            continue;
+        }
+
+        let fingerprint = file_to_save.fingerprint.as_ref().and_then(|(encoded, duration)| {
+            let raw = decode_fingerprint(encoded)
+                .map_err(|e| log::warn!("Bad fingerprint for {}: {}", source.display(), e))
+                .ok()?;
+            Some(FingerprintCandidate { id: String::new(), raw, duration_secs: *duration })
+        });
+        let matching_song = fingerprint.as_ref().and_then(|candidate| {
+            song_fingerprints
+                .iter()
+                .find(|song| fingerprints_match(candidate, song).is_some())
+        });
+        if let Some(existing_song_id) = matching_song.and_then(|song| song.id.parse().ok()) {
+            log::info!(
+                "Skipping duplicate recording: '{}' matches song {}",
+                title,
+                existing_song_id
+            );
+            duplicate_song_ids.push(existing_song_id);
+            duplicates_skipped += 1;
+            continue;
+        }
+
+        // Get or create the track artist and the album artist
+        let artist_id =
+            get_or_create_artist(&mut string_table, &mut artists, &mut artist_map, artist_name);
+        let album_artist_id = match metadata.album_artist_name() {
+            Some(name) if name != artist_name => {
+                get_or_create_artist(&mut string_table, &mut artists, &mut artist_map, name)
+            }
+            _ => artist_id,
         };
 
-        // Get or create album (scoped to artist)
-        let album_key = format!("{}:{}", artist_id, album_name);
+        // Get or create album (scoped to album artist, so a compilation's
+        // tracks by different artists stay one album)
+        let album_key = format!("{}:{}", album_artist_id, album_name);
         let album_id = if let Some(&id) = album_map.get(&album_key) {
             id
         } else {
             let id = albums.len() as u32;
-            let name_string_id = string_table.add(album_name);
-            albums.push(AlbumEntry {
-                name_string_id,
-                artist_id,
-                year: metadata.year.unwrap_or(0) as u16,
-            });
+            albums.push(new_album_entry(
+                &mut string_table,
+                album_name,
+                album_artist_id,
+                metadata.year.unwrap_or(0) as u16,
+            ));
             album_map.insert(album_key, id);
             id
         };
@@ -408,34 +987,31 @@ pub fn save_to_library(
             }
         }
 
-        // Check if we need a new bucket
-        if files_in_bucket >= MAX_FILES_PER_BUCKET {
-            current_bucket += 1;
-            files_in_bucket = 0;
-            let new_bucket_path = music_path.join(format!("{:02}", current_bucket));
-            fs::create_dir_all(&new_bucket_path)
-                .map_err(|e| format!("Failed to create bucket {:02}: {}", current_bucket, e))?;
-        }
-
-        // Get file extension from source
-        let extension = source
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("mp3")
-            .to_lowercase();
-
-        // Generate sequential filename: 001.mp3, 002.mp3, etc.
-        let new_filename = format!("{:03}.{}", files_in_bucket + 1, extension);
-        let relative_path = format!("{:02}/{}", current_bucket, new_filename);
-        let dest_path = music_path.join(&relative_path);
-
-        // Copy file with new name
-        fs::copy(source, &dest_path)
-            .map_err(|e| format!("Failed to copy to {}: {}", relative_path, e))?;
+        let relative_path = match placement {
+            FilePlacement::Copy => buckets.copy_in(
+                source,
+                metadata.track_number.unwrap_or(0) as u16,
+                artist_name,
+                title,
+                file_name.as_deref(),
+            )?,
+            FilePlacement::InPlace => fs::canonicalize(source)
+                .unwrap_or_else(|_| source.to_path_buf())
+                .to_string_lossy()
+                .to_string(),
+            FilePlacement::InLibrary => music_relative_path(&music_path, source)
+                .ok_or_else(|| format!("{} is not in the music folder", source.display()))?,
+        };
 
         // Add song entry
         let title_string_id = string_table.add(title);
         let path_string_id = string_table.add(&relative_path);
+        let genre_id = get_or_create_genre(
+            &mut string_table,
+            &mut genres,
+            &mut genre_map,
+            metadata.genre.as_deref(),
+        );
 
         // Add to song_set to prevent duplicates within the same batch
         let song_key = (title_string_id, artist_id, album_id);
@@ -451,51 +1027,92 @@ pub fn save_to_library(
             path_string_id,
             metadata.track_number.unwrap_or(0) as u16,
             metadata.duration_secs.unwrap_or(0) as u16,
-        ));
+            genre_id,
+        )
+        .with_metadata_source(file_to_save.metadata_source)
+        .with_disc_number(metadata.disc_number.unwrap_or(0).min(255) as u8)
+        .with_rating(metadata.rating.unwrap_or(0).min(MAX_RATING))
+        .with_date_added(date_added)
+        .with_play_count(saturate_play_count(metadata.play_count)));
 
         saved_song_ids.push(new_song_id);
         saved_album_ids.push(album_id);
-        files_in_bucket += 1;
+        if hooks.post_save.is_some() {
+            hooked_songs.push(SavedSong {
+                song_id: new_song_id,
+                path: relative_path.clone(),
+                file_path: music_path.join(&relative_path).to_string_lossy().to_string(),
+                source_path: file_to_save.source_path.clone(),
+                metadata: metadata.clone(),
+            });
+        }
+        if let Some(hash) = content_hash {
+            song_ids_by_hash.insert(hash.clone(), new_song_id);
+            saved_hashes.push((relative_path.clone(), hash));
+        }
+        if let Some(mut candidate) = fingerprint {
+            candidate.id = new_song_id.to_string();
+            song_fingerprints.push(candidate);
+        }
+        if let Some((fingerprint, duration_secs)) = file_to_save.fingerprint {
+            saved_fingerprints.push((
+                relative_path.clone(),
+                StoredFingerprint {
+                    fingerprint,
+                    duration_secs,
+                    algorithm: CHROMAPRINT_ALGORITHM,
+                    fpcalc_version: fingerprint_version.clone(),
+                },
+            ));
+        }
+        if let Some(confidence) = file_to_save.confidence {
+            saved_confidence.push((relative_path, confidence));
+        }
+        saved_files.push(index as u32);
         files_saved += 1;
     }
 
-    // Build library.bin
-    let string_table_bytes = string_table.to_bytes();
-    let artist_table_bytes: Vec<u8> = artists.iter().flat_map(|a| a.to_bytes()).collect();
-    let album_table_bytes: Vec<u8> = albums.iter().flat_map(|a| a.to_bytes()).collect();
-    let song_table_bytes: Vec<u8> = songs.iter().flat_map(|s| s.to_bytes()).collect();
-
-    // Calculate offsets
-    let string_table_offset = HEADER_SIZE;
-    let artist_table_offset = string_table_offset + string_table_bytes.len() as u32;
-    let album_table_offset = artist_table_offset + artist_table_bytes.len() as u32;
-    let song_table_offset = album_table_offset + album_table_bytes.len() as u32;
-
-    let header = LibraryHeader {
-        magic: *crate::models::LIBRARY_MAGIC,
-        version: crate::models::LIBRARY_VERSION,
-        song_count: songs.len() as u32,
-        artist_count: artists.len() as u32,
-        album_count: albums.len() as u32,
-        string_table_offset,
-        artist_table_offset,
-        album_table_offset,
-        song_table_offset,
-    };
-
     // Write library.bin
-    let mut file = fs::File::create(&library_bin_path)
-        .map_err(|e| format!("Failed to create library.bin: {}", e))?;
-    file.write_all(&header.to_bytes())
-        .map_err(|e| format!("Failed to write header: {}", e))?;
-    file.write_all(&string_table_bytes)
-        .map_err(|e| format!("Failed to write string table: {}", e))?;
-    file.write_all(&artist_table_bytes)
-        .map_err(|e| format!("Failed to write artist table: {}", e))?;
-    file.write_all(&album_table_bytes)
-        .map_err(|e| format!("Failed to write album table: {}", e))?;
-    file.write_all(&song_table_bytes)
-        .map_err(|e| format!("Failed to write song table: {}", e))?;
+    write_library_bin(
+        &library_bin_path,
+        &string_table,
+        &artists,
+        &albums,
+        &songs,
+        &genres,
+    )?;
+
+    // Romanize the names this save introduced (best effort)
+    let new_names = artists[existing_artist_count as usize..]
+        .iter()
+        .map(|a| a.name_string_id)
+        .chain(
+            albums[existing_album_count as usize..]
+                .iter()
+                .map(|a| a.name_string_id),
+        )
+        .chain(
+            songs[existing_song_count as usize..]
+                .iter()
+                .map(|s| s.title_string_id),
+        )
+        .filter_map(|id| string_table.get(id));
+    if let Err(e) = record_transliterations(base, new_names) {
+        log::warn!("Failed to record transliterations: {}", e);
+    }
+    if let Err(e) = record_song_confidence(base, saved_confidence) {
+        log::warn!("Failed to record song confidence: {}", e);
+    }
+    if let Err(e) = record_song_hashes(base, saved_hashes) {
+        log::warn!("Failed to record song hashes: {}", e);
+    }
+    if let Err(e) = record_fingerprints(base, saved_fingerprints) {
+        log::warn!("Failed to record fingerprints: {}", e);
+    }
+    if !saved_song_ids.is_empty() {
+        announce_change(EVENT_SONGS_ADDED, base, saved_song_ids.clone());
+    }
+    spawn_post_save_hook(&hooks, base, hooked_songs);
 
     Ok(SaveToLibraryResult {
         files_saved,
@@ -503,61 +1120,337 @@ pub fn save_to_library(
         albums_added: albums.len() as u32 - existing_album_count,
         songs_added: songs.len() as u32 - existing_song_count,
         duplicates_skipped,
+        protected_skipped,
+        missing_skipped,
+        saved_files,
         song_ids: saved_song_ids,
         duplicate_song_ids,
         album_ids: saved_album_ids,
     })
 }
 
-/// Soft delete songs by their IDs.
+/// Run the pre-save hook over `files`. Returns the files it let through and,
+/// for each, the name it gave the file's copy (None = the naming scheme).
+fn apply_pre_save_hook(
+    hooks: &SaveHookSettings,
+    base: &Path,
+    files: Vec<FileToSave>,
+) -> Result<(Vec<FileToSave>, Vec<Option<String>>), String> {
+    if hooks.pre_save.is_none() {
+        let names = vec![None; files.len()];
+        return Ok((files, names));
+    }
+    let confidences: Vec<Option<MatchConfidence>> =
+        files.iter().map(|file| file.confidence).collect();
+    let fingerprints: Vec<Option<(String, u32)>> =
+        files.iter().map(|file| file.fingerprint.clone()).collect();
+    let mut pending: Vec<PendingSave> = files
+        .into_iter()
+        .map(|file| PendingSave {
+            source_path: file.source_path,
+            metadata: file.metadata,
+            metadata_source: file.metadata_source,
+            file_name: None,
+        })
+        .collect();
+    run_pre_save_hook(hooks, base, &mut pending)?;
+    Ok(pending
+        .into_iter()
+        .zip(confidences.into_iter().zip(fingerprints))
+        .map(|(file, (confidence, fingerprint))| {
+            let saved = FileToSave {
+                source_path: file.source_path,
+                metadata: file.metadata,
+                metadata_source: file.metadata_source,
+                confidence,
+                fingerprint,
+            };
+            (saved, file.file_name)
+        })
+        .unzip())
+}
+
+/// Copy the files of adopted songs (see `scan_and_adopt`) into the music
+/// buckets, so the device can play them.
 ///
-/// This modifies the flags byte of each song entry (minimal binary write),
-/// AND deletes the actual audio file from music/ (frees disk space immediately).
-/// Use `compact_library` to reclaim metadata space in library.bin.
+/// `song_ids` limits the copy to those songs (default: every adopted song).
+/// The originals are left where they are. Songs then point at their copy,
+/// and their hashes, fingerprints, confidence and gain follow it. Files
+/// that are gone are reported in `missing_files`.
 #[tauri::command]
-pub fn delete_songs(
+pub fn copy_adopted_songs(
     base_path: String,
-    song_ids: Vec<u32>,
-) -> Result<crate::models::DeleteSongsResult, String> {
+    song_ids: Option<Vec<u32>>,
+) -> Result<crate::models::CopyAdoptedResult, String> {
     let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let _write = begin_foreground_write("Copying adopted songs into the library");
     let jp3_path = base.join(JP3_DIR);
-    let metadata_path = jp3_path.join(METADATA_DIR);
     let music_path = jp3_path.join(MUSIC_DIR);
-    let library_bin_path = metadata_path.join(LIBRARY_BIN);
+    let library_bin_path = jp3_path.join(METADATA_DIR).join(LIBRARY_BIN);
 
-    if !library_bin_path.exists() {
-        return Err("Library not found".to_string());
+    let Some(existing) = load_existing_library_data(&library_bin_path)? else {
+        return Ok(Default::default());
+    };
+    let mut string_table = existing.string_table;
+    let mut songs = existing.songs;
+    let wanted: Option<HashSet<u32>> = song_ids.map(|ids| ids.into_iter().collect());
+
+    // Each adopted file once, with the first song that uses it
+    let mut seen = HashSet::new();
+    let adopted: Vec<(u32, usize)> = songs
+        .iter()
+        .enumerate()
+        .filter(|(id, song)| {
+            song.flags & song_flags::DELETED == 0
+                && wanted.as_ref().is_none_or(|ids| ids.contains(&(*id as u32)))
+                && string_table.get(song.path_string_id).is_some_and(is_adopted_path)
+        })
+        .filter(|(_, song)| seen.insert(song.path_string_id))
+        .map(|(id, song)| (song.path_string_id, id))
+        .collect();
+
+    let mut result = crate::models::CopyAdoptedResult::default();
+    let mut buckets = BucketAllocator::new(base, &music_path)?;
+    let mut new_path_ids: HashMap<u32, u32> = HashMap::new();
+    let mut renamed: HashMap<String, String> = HashMap::new();
+    for (path_string_id, song_id) in adopted {
+        let source = string_table.get(path_string_id).unwrap_or_default().to_string();
+        if !Path::new(&source).is_file() {
+            result.missing_files.push(source);
+            continue;
+        }
+        let song = &songs[song_id];
+        let name = |id: u32| string_table.get(id).unwrap_or_default().to_string();
+        let artist = existing
+            .artists
+            .get(song.artist_id as usize)
+            .map(|artist| name(artist.name_string_id))
+            .unwrap_or_default();
+        let title = name(song.title_string_id);
+        let track_number = song.track_number;
+        match buckets.copy_in(Path::new(&source), track_number, &artist, &title, None) {
+            Ok(relative_path) => {
+                new_path_ids.insert(path_string_id, string_table.add(&relative_path));
+                renamed.insert(source, relative_path);
+                result.files_copied += 1;
+            }
+            Err(e) => {
+                log::warn!("Failed to copy adopted file {}: {}", source, e);
+                result.failed_files.push(source);
+            }
+        }
+    }
+    if renamed.is_empty() {
+        return Ok(result);
     }
 
-    // Read entire file to get string table for path resolution
-    let mut data = Vec::new();
-    {
-        let mut read_file = fs::File::open(&library_bin_path)
-            .map_err(|e| format!("Failed to open library.bin for reading: {}", e))?;
-        read_file
-            .read_to_end(&mut data)
-            .map_err(|e| format!("Failed to read library.bin: {}", e))?;
+    // Every song sharing a copied file moves with it, deleted ones included
+    let mut patched_songs = Vec::new();
+    for (id, song) in songs.iter_mut().enumerate() {
+        if let Some(&new_id) = new_path_ids.get(&song.path_string_id) {
+            song.path_string_id = new_id;
+            patched_songs.push(id as u32);
+            if song.flags & song_flags::DELETED == 0 {
+                result.songs_updated += 1;
+            }
+        }
     }
+    write_library_bin_patched(
+        &library_bin_path,
+        &string_table,
+        &existing.artists,
+        &existing.albums,
+        &songs,
+        &existing.genres,
+        &PatchedEntries {
+            songs: &patched_songs,
+            ..Default::default()
+        },
+    )?;
 
-    let header = LibraryHeader::from_bytes(&data).ok_or("Invalid library.bin header")?;
+    let renames = [
+        rename_fingerprints(base, &renamed),
+        rename_song_confidence(base, &renamed),
+        rename_song_hashes(base, &renamed),
+        rename_replay_gain(base, &renamed),
+    ];
+    for e in renames.into_iter().filter_map(Result::err) {
+        log::warn!("Failed to follow copied files: {}", e);
+    }
+    announce_change(EVENT_SONGS_UPDATED, base, patched_songs);
 
-    // Parse string table to resolve paths
-    let strings = parse_string_table(
-        &data,
-        header.string_table_offset as usize,
-        header.artist_table_offset as usize,
-    )?;
+    Ok(result)
+}
 
-    // Open file for writing flags
-    let mut file = OpenOptions::new()
-        .read(true)
-        .write(true)
+/// `file`'s path relative to `jp3/music` (with `/` separators), or None if
+/// it lies outside.
+fn music_relative_path(music_path: &Path, file: &Path) -> Option<String> {
+    let relative = file.strip_prefix(music_path).ok()?;
+    let parts: Vec<_> = relative
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect();
+    Some(parts.join("/"))
+}
+
+/// Songs whose audio file no longer exists, in `load_library` order.
+///
+/// Files moved or renamed on the card by hand show up here; point each
+/// song at its file again with `relink_song`.
+#[tauri::command]
+pub fn scan_missing_files(base_path: String) -> Result<Vec<ParsedSong>, String> {
+    let music_path = Path::new(&base_path).join(JP3_DIR).join(MUSIC_DIR);
+    let missing: Vec<ParsedSong> = load_library(base_path, None)?
+        .songs
+        .into_iter()
+        .filter(|song| !music_path.join(&song.path).is_file())
+        .collect();
+    log::info!("[scan_missing_files] {} songs missing their file", missing.len());
+    Ok(missing)
+}
+
+/// Point a song at a file that moved, instead of re-importing it.
+///
+/// `new_path` is relative to `jp3/music` or absolute. A file inside
+/// `jp3/music` is stored by its relative path; one outside is adopted in
+/// place (see `scan_and_adopt`). Songs that shared the old path (deleted
+/// ones included) move too, and its fingerprint, confidence and gain
+/// follow it; the content hash is taken from the new file. Returns the
+/// stored path.
+#[tauri::command]
+pub fn relink_song(base_path: String, song_id: u32, new_path: String) -> Result<String, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let jp3_path = base.join(JP3_DIR);
+    let music_path = jp3_path.join(MUSIC_DIR);
+    let library_bin_path = jp3_path.join(METADATA_DIR).join(LIBRARY_BIN);
+
+    let file = music_path.join(&new_path);
+    if !file.is_file() {
+        return Err(format!("File not found: {}", file.display()));
+    }
+    let file = fs::canonicalize(&file).unwrap_or(file);
+    let stored_path = fs::canonicalize(&music_path)
+        .ok()
+        .and_then(|music| music_relative_path(&music, &file))
+        .unwrap_or_else(|| file.to_string_lossy().to_string());
+
+    let not_found = || format!("Song {} not found", song_id);
+    let existing = load_existing_library_data(&library_bin_path)?.ok_or_else(not_found)?;
+    let mut string_table = existing.string_table;
+    let mut songs = existing.songs;
+    let old_path_id = songs
+        .get(song_id as usize)
+        .filter(|song| song.flags & song_flags::DELETED == 0)
+        .ok_or_else(not_found)?
+        .path_string_id;
+    let old_path = string_table.get(old_path_id).unwrap_or_default().to_string();
+    if old_path == stored_path {
+        return Ok(stored_path);
+    }
+    let owner = songs.iter().position(|song| {
+        song.flags & song_flags::DELETED == 0
+            && string_table.get(song.path_string_id) == Some(stored_path.as_str())
+    });
+    if let Some(owner) = owner {
+        return Err(format!("{} already belongs to song {}", stored_path, owner));
+    }
+
+    let new_path_id = string_table.add(&stored_path);
+    let mut patched_songs = Vec::new();
+    for (id, song) in songs.iter_mut().enumerate() {
+        if song.path_string_id == old_path_id {
+            song.path_string_id = new_path_id;
+            patched_songs.push(id as u32);
+        }
+    }
+    write_library_bin_patched(
+        &library_bin_path,
+        &string_table,
+        &existing.artists,
+        &existing.albums,
+        &songs,
+        &existing.genres,
+        &PatchedEntries {
+            songs: &patched_songs,
+            ..Default::default()
+        },
+    )?;
+
+    let renamed = HashMap::from([(old_path.clone(), stored_path.clone())]);
+    let renames = [
+        rename_fingerprints(base, &renamed),
+        rename_song_confidence(base, &renamed),
+        rename_song_hashes(base, &renamed),
+        rename_replay_gain(base, &renamed),
+        // The file may have been re-encoded since it was saved
+        hash_file(&file)
+            .map_err(|e| format!("Failed to hash {}: {}", file.display(), e))
+            .and_then(|hash| record_song_hashes(base, vec![(stored_path.clone(), hash)])),
+    ];
+    for e in renames.into_iter().filter_map(Result::err) {
+        log::warn!("Failed to follow relinked file: {}", e);
+    }
+    log::info!("[relink_song] Song {}: {} -> {}", song_id, old_path, stored_path);
+    announce_change(EVENT_SONGS_UPDATED, base, patched_songs);
+
+    Ok(stored_path)
+}
+
+/// Soft delete songs by their IDs.
+///
+/// This modifies the flags byte of each song entry (minimal binary write),
+/// AND moves the audio file from music/ to the trash (see `trash_service`),
+/// where it stays until the library's retention period runs out.
+/// Use `compact_library` to reclaim metadata space in library.bin.
+#[tauri::command]
+pub fn delete_songs(
+    base_path: String,
+    song_ids: Vec<u32>,
+) -> Result<crate::models::DeleteSongsResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let jp3_path = base.join(JP3_DIR);
+    let metadata_path = jp3_path.join(METADATA_DIR);
+    let music_path = jp3_path.join(MUSIC_DIR);
+    let library_bin_path = metadata_path.join(LIBRARY_BIN);
+
+    if !library_bin_path.exists() {
+        return Err("Library not found".to_string());
+    }
+
+    // Read entire file to get string table for path resolution
+    let mut data = Vec::new();
+    {
+        let mut read_file = fs::File::open(&library_bin_path)
+            .map_err(|e| format!("Failed to open library.bin for reading: {}", e))?;
+        read_file
+            .read_to_end(&mut data)
+            .map_err(|e| format!("Failed to read library.bin: {}", e))?;
+    }
+
+    let header = LibraryHeader::from_bytes(&data).ok_or("Invalid library.bin header")?;
+    verify_checksum(&data, &header)?;
+
+    // Parse string table to resolve paths
+    let strings = parse_string_table(
+        &data,
+        header.string_table_offset as usize,
+        header.string_table_end() as usize,
+    )?;
+
+    // Open file for writing flags
+    let _marker = begin_library_write(&library_bin_path, "delete_songs")?;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
         .open(&library_bin_path)
         .map_err(|e| format!("Failed to open library.bin for writing: {}", e))?;
 
     let mut songs_deleted = 0u32;
     let mut not_found = Vec::new();
-    let mut files_deleted = 0u32;
+    let mut to_trash: Vec<(String, Option<String>)> = Vec::new();
 
     for &song_id in &song_ids {
         if song_id >= header.song_count {
@@ -566,8 +1459,8 @@ pub fn delete_songs(
         }
 
         // Calculate song entry offset
-        let song_offset =
-            header.song_table_offset as usize + (song_id as usize * SongEntry::SIZE as usize);
+        let song_offset = header.song_table_offset as usize
+            + (song_id as usize * SongEntry::size_for(header.version) as usize);
 
         // Read the path_string_id (bytes 12-16 of the song entry)
         let path_string_id = u32::from_le_bytes(
@@ -577,12 +1470,16 @@ pub fn delete_songs(
         );
 
         // Get the audio file path from string table
+        // Adopted files stay where the user keeps them
         if let Some(audio_path_str) = strings.get(path_string_id as usize) {
-            let audio_file_path = music_path.join(audio_path_str);
-            if audio_file_path.exists() {
-                if fs::remove_file(&audio_file_path).is_ok() {
-                    files_deleted += 1;
-                }
+            if !is_adopted_path(audio_path_str) && music_path.join(audio_path_str).exists() {
+                let title_string_id = u32::from_le_bytes(
+                    data[song_offset..song_offset + 4].try_into().map_err(|_| {
+                        format!("Failed to read title_string_id for song {}", song_id)
+                    })?,
+                );
+                let title = strings.get(title_string_id as usize).cloned();
+                to_trash.push((audio_path_str.clone(), title));
             }
         }
 
@@ -591,16 +1488,33 @@ pub fn delete_songs(
         file.seek(SeekFrom::Start(flags_offset))
             .map_err(|e| format!("Failed to seek to song {}: {}", song_id, e))?;
 
-        file.write_all(&[song_flags::DELETED])
+        file.write_all(&[data[song_offset + 20] | song_flags::DELETED])
             .map_err(|e| format!("Failed to mark song {} as deleted: {}", song_id, e))?;
 
         songs_deleted += 1;
     }
 
+    if songs_deleted > 0 {
+        write_generation(&mut file, header.generation.wrapping_add(1))?;
+        write_checksum(&mut file, &header)?;
+    }
+
     // Ensure changes are flushed to disk
     file.sync_all()
         .map_err(|e| format!("Failed to sync changes: {}", e))?;
 
+    // Files move after the flags are written: if this fails the songs are
+    // deleted but their files are still in music/, so restore_songs works.
+    let retention_days = load_library_settings(base)?.trash_retention_days;
+    if let Err(e) = purge_expired(base, retention_days) {
+        log::warn!("Failed to purge expired trash: {}", e);
+    }
+    let files_deleted = move_to_trash(base, &to_trash)?.len() as u32;
+    if songs_deleted > 0 {
+        let deleted = song_ids.into_iter().filter(|id| !not_found.contains(id)).collect();
+        announce_change(EVENT_SONGS_DELETED, base, deleted);
+    }
+
     // Note: Playlists are NOT updated here to minimize SD card writes.
     // Orphaned song IDs in playlists will be cleaned up during compact_library,
     // which also remaps all song IDs. The frontend filters orphaned IDs when displaying.
@@ -612,242 +1526,2542 @@ pub fn delete_songs(
     })
 }
 
-/// Remap a song ID in all playlists.
-///
-/// Scans all playlist files and replaces occurrences of `old_id` with `new_id`.
-/// Returns the number of playlists that were updated.
-fn remap_song_id_in_playlists(jp3_path: &Path, old_id: u32, new_id: u32) -> Result<u32, String> {
-    use crate::commands::playlist::{read_playlist_file, write_playlist_file};
-
-    let playlists_path = jp3_path.join(PLAYLISTS_DIR);
-
-    if !playlists_path.exists() {
-        return Ok(0);
-    }
-
-    let entries = fs::read_dir(&playlists_path)
-        .map_err(|e| format!("Failed to read playlists directory: {}", e))?;
-
-    let mut playlists_updated = 0u32;
+/// Report what `delete_songs` would do with `song_ids`, without changing
+/// anything: the audio files and bytes that would go to the trash, and the
+/// playlists that would lose songs, so the user can be warned first.
+#[tauri::command]
+pub fn preview_delete(
+    base_path: String,
+    song_ids: Vec<u32>,
+) -> Result<crate::models::DeletePreview, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let jp3_path = base.join(JP3_DIR);
+    let music_path = jp3_path.join(MUSIC_DIR);
 
-    for entry in entries.flatten() {
-        let path = entry.path();
+    let library = load_library(base_path.clone(), None)?;
+    let songs_by_id: HashMap<u32, &ParsedSong> =
+        library.songs.iter().map(|song| (song.id, song)).collect();
 
-        // Skip non-.bin files
-        if path.extension().and_then(|e| e.to_str()) != Some("bin") {
-            continue;
+    let mut found = HashSet::new();
+    let mut not_found = Vec::new();
+    let mut paths = HashSet::new();
+    for song_id in song_ids {
+        match songs_by_id.get(&song_id) {
+            Some(song) => {
+                found.insert(song_id);
+                paths.insert(song.path.as_str());
+            }
+            None if !not_found.contains(&song_id) => not_found.push(song_id),
+            None => {}
         }
+    }
 
-        // Extract playlist ID from filename
-        let playlist_id = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .and_then(|s| s.parse::<u32>().ok());
-
-        let playlist_id = match playlist_id {
-            Some(id) => id,
-            None => continue,
-        };
-
-        // Read playlist
-        let playlist = match read_playlist_file(&path, playlist_id) {
-            Ok(p) => p,
-            Err(_) => continue, // Skip corrupted playlists
-        };
-
-        // Check if this playlist contains the old ID
-        if !playlist.song_ids.contains(&old_id) {
-            continue;
+    let (mut files, mut bytes) = (0u32, 0u64);
+    for path in paths.into_iter().filter(|path| !is_adopted_path(path)) {
+        if let Ok(metadata) = fs::metadata(music_path.join(path)) {
+            files += 1;
+            bytes += metadata.len();
         }
-
-        // Remap old_id to new_id
-        let updated_song_ids: Vec<u32> = playlist
-            .song_ids
-            .iter()
-            .map(|&id| if id == old_id { new_id } else { id })
-            .collect();
-
-        // Write updated playlist
-        write_playlist_file(&path, &playlist.name, &updated_song_ids)?;
-        playlists_updated += 1;
     }
 
-    Ok(playlists_updated)
+    let mut playlists: Vec<crate::models::PlaylistImpact> = fs::read_dir(
+        jp3_path.join(PLAYLISTS_DIR),
+    )
+    .map(|entries| {
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                let playlist_id = name.to_str()?.strip_suffix(".bin")?.parse::<u32>().ok()?;
+                crate::commands::playlist::read_playlist_file(&entry.path(), playlist_id).ok()
+            })
+            .filter_map(|playlist| {
+                let songs_affected =
+                    playlist.song_ids.iter().filter(|id| found.contains(id)).count() as u32;
+                (songs_affected > 0).then(|| crate::models::PlaylistImpact {
+                    playlist_id: playlist.id,
+                    name: playlist.name,
+                    songs_affected,
+                    songs_remaining: playlist.song_ids.len() as u32 - songs_affected,
+                })
+            })
+            .collect()
+    })
+    .unwrap_or_default();
+    playlists.sort_by(|a, b| compare_names(&a.name, &b.name));
+
+    Ok(crate::models::DeletePreview {
+        songs: found.len() as u32,
+        not_found,
+        files,
+        bytes,
+        playlists,
+    })
 }
 
-/// Edit a song's metadata by soft-deleting the old entry and appending a new one.
-///
-/// This approach minimizes write cycles by:
-/// 1. Marking the old song entry as deleted (1 byte write) - but keeps the audio file
-/// 2. Appending new strings/entries to the end of the file
+/// Undo a soft delete by clearing the DELETED flag of each song.
 ///
-/// Note: This does require a full file rewrite since we need to update offsets.
-/// For truly minimal writes, use delete_songs + save_to_library separately.
+/// Only possible until `compact_library` drops the entries. Audio files
+/// `delete_songs` moved to the trash are moved back; songs whose file is gone
+/// from both are left deleted and reported in `missing_files`.
 #[tauri::command]
-pub fn edit_song_metadata(
+pub fn restore_songs(
     base_path: String,
-    song_id: u32,
-    new_metadata: AudioMetadata,
-) -> Result<crate::models::EditSongResult, String> {
+    song_ids: Vec<u32>,
+) -> Result<crate::models::RestoreSongsResult, String> {
     let base = Path::new(&base_path);
+    ensure_library_available(base)?;
     let jp3_path = base.join(JP3_DIR);
-    let metadata_path = jp3_path.join(METADATA_DIR);
-    let library_bin_path = metadata_path.join(LIBRARY_BIN);
+    let music_path = jp3_path.join(MUSIC_DIR);
+    let library_bin_path = jp3_path.join(METADATA_DIR).join(LIBRARY_BIN);
 
     if !library_bin_path.exists() {
         return Err("Library not found".to_string());
     }
 
-    // Read the file FIRST to get the old song's path before any modifications
-    let mut file = fs::File::open(&library_bin_path)
-        .map_err(|e| format!("Failed to open library.bin: {}", e))?;
-    let mut data = Vec::new();
-    file.read_to_end(&mut data)
+    let mut data = fs::read(&library_bin_path)
         .map_err(|e| format!("Failed to read library.bin: {}", e))?;
-    drop(file); // Release the file handle
-
     let header = LibraryHeader::from_bytes(&data).ok_or("Invalid library.bin header")?;
-
-    // Validate song_id exists
-    if song_id >= header.song_count {
-        return Err(format!("Song {} not found", song_id));
-    }
-
-    // Parse string table to get the old path
+    verify_checksum(&data, &header)?;
     let strings = parse_string_table(
         &data,
         header.string_table_offset as usize,
-        header.artist_table_offset as usize,
+        header.string_table_end() as usize,
     )?;
 
-    // Get the old song entry to preserve its path
-    let song_offset =
-        header.song_table_offset as usize + (song_id as usize * SongEntry::SIZE as usize);
-    let old_path_string_id = u32::from_le_bytes(
-        data[song_offset + 12..song_offset + 16]
-            .try_into()
-            .map_err(|_| "Failed to read path_string_id")?,
-    );
-    let old_path = strings
-        .get(old_path_string_id as usize)
-        .cloned()
-        .ok_or("Failed to get old song path")?;
+    let _marker = begin_library_write(&library_bin_path, "restore_songs")?;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&library_bin_path)
+        .map_err(|e| format!("Failed to open library.bin for writing: {}", e))?;
 
-    // Also get the old duration if not provided in new_metadata
-    let old_duration_sec = u16::from_le_bytes(
-        data[song_offset + 18..song_offset + 20]
-            .try_into()
-            .map_err(|_| "Failed to read duration_sec")?,
-    );
+    let trash = list_trash_entries(base)?;
+    let mut restored_ids = Vec::new();
+    let mut not_deleted = Vec::new();
+    let mut missing_files = Vec::new();
 
-    // Now soft-delete the old song WITHOUT deleting the audio file
-    // We do this by directly marking the flags byte as DELETED
-    {
-        let mut write_file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .open(&library_bin_path)
-            .map_err(|e| format!("Failed to open library.bin for writing: {}", e))?;
+    for &song_id in &song_ids {
+        if song_id >= header.song_count {
+            not_deleted.push(song_id);
+            continue;
+        }
 
-        let flags_offset = song_offset as u64 + 20;
-        write_file
-            .seek(SeekFrom::Start(flags_offset))
+        let song_offset = header.song_table_offset as usize
+            + (song_id as usize * SongEntry::size_for(header.version) as usize);
+        let flags = data[song_offset + 20];
+        if flags & song_flags::DELETED == 0 {
+            not_deleted.push(song_id);
+            continue;
+        }
+
+        let path_string_id = u32::from_le_bytes(
+            data[song_offset + 12..song_offset + 16]
+                .try_into()
+                .map_err(|_| format!("Failed to read path_string_id for song {}", song_id))?,
+        );
+        let Some(path) = strings.get(path_string_id as usize) else {
+            missing_files.push(song_id);
+            continue;
+        };
+        let file_exists = music_path.join(path).exists() || {
+            // Newest trashed copy of the path, if any
+            match trash.iter().find(|entry| &entry.original_path == path) {
+                Some(entry) => {
+                    !restore_trashed_files(base, std::slice::from_ref(&entry.id))?.is_empty()
+                }
+                None => false,
+            }
+        };
+        if !file_exists {
+            missing_files.push(song_id);
+            continue;
+        }
+
+        file.seek(SeekFrom::Start(song_offset as u64 + 20))
             .map_err(|e| format!("Failed to seek to song {}: {}", song_id, e))?;
+        file.write_all(&[flags & !song_flags::DELETED])
+            .map_err(|e| format!("Failed to restore song {}: {}", song_id, e))?;
+        // So a repeated ID counts as already restored
+        data[song_offset + 20] = flags & !song_flags::DELETED;
 
-        write_file
-            .write_all(&[song_flags::DELETED])
-            .map_err(|e| format!("Failed to mark song {} as deleted: {}", song_id, e))?;
+        restored_ids.push(song_id);
+    }
 
-        write_file
-            .sync_all()
-            .map_err(|e| format!("Failed to sync changes: {}", e))?;
+    let songs_restored = restored_ids.len() as u32;
+    if songs_restored > 0 {
+        write_generation(&mut file, header.generation.wrapping_add(1))?;
+        write_checksum(&mut file, &header)?;
     }
 
-    // Load existing library data for the append
-    let existing = load_existing_library_data(&library_bin_path)?
-        .ok_or("Failed to load existing library data")?;
+    file.sync_all()
+        .map_err(|e| format!("Failed to sync changes: {}", e))?;
+    if songs_restored > 0 {
+        announce_change(EVENT_SONGS_ADDED, base, restored_ids);
+    }
 
-    let mut string_table = existing.string_table;
-    let mut artists = existing.artists;
-    let mut albums = existing.albums;
-    let mut songs = existing.songs;
-    let mut artist_map = existing.artist_map;
-    let mut album_map = existing.album_map;
+    Ok(crate::models::RestoreSongsResult {
+        songs_restored,
+        not_deleted,
+        missing_files,
+    })
+}
 
-    let old_artist_count = artists.len();
-    let old_album_count = albums.len();
+/// Audio files in the trash, most recently deleted first.
+#[tauri::command]
+pub fn list_trash(base_path: String) -> Result<Vec<crate::models::TrashEntry>, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    list_trash_entries(base)
+}
 
-    // Get or create artist
-    let artist_name = new_metadata.artist.as_ref().ok_or("Missing artist")?;
-    let artist_id = if let Some(&id) = artist_map.get(artist_name) {
-        id
-    } else {
-        let id = artists.len() as u32;
-        let name_string_id = string_table.add(artist_name);
-        artists.push(ArtistEntry { name_string_id });
-        artist_map.insert(artist_name.clone(), id);
-        id
-    };
+/// Move trashed audio files back into music/ and undo the delete of the
+/// songs they belonged to (if compaction hasn't dropped them yet).
+#[tauri::command]
+pub fn restore_from_trash(
+    base_path: String,
+    entry_ids: Vec<String>,
+) -> Result<crate::models::RestoreFromTrashResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let restored = restore_trashed_files(base, &entry_ids)?;
+    let not_restored = entry_ids
+        .iter()
+        .filter(|id| !restored.iter().any(|entry| &entry.id == *id))
+        .cloned()
+        .collect();
 
-    // Get or create album
-    let album_name = new_metadata.album.as_ref().ok_or("Missing album")?;
-    let album_key = format!("{}:{}", artist_id, album_name);
-    let album_id = if let Some(&id) = album_map.get(&album_key) {
-        id
+    // Deleted songs pointing at a restored file
+    let restored_paths: HashSet<&str> =
+        restored.iter().map(|entry| entry.original_path.as_str()).collect();
+    let library_bin_path = base.join(JP3_DIR).join(METADATA_DIR).join(LIBRARY_BIN);
+    let data = fs::read(&library_bin_path)
+        .map_err(|e| format!("Failed to read library.bin: {}", e))?;
+    let header = LibraryHeader::from_bytes(&data).ok_or("Invalid library.bin header")?;
+    verify_checksum(&data, &header)?;
+    let strings = parse_string_table(
+        &data,
+        header.string_table_offset as usize,
+        header.string_table_end() as usize,
+    )?;
+    let entry_size = SongEntry::size_for(header.version) as usize;
+    let song_ids: Vec<u32> = (0..header.song_count)
+        .filter(|&song_id| {
+            let offset = header.song_table_offset as usize + song_id as usize * entry_size;
+            let Some(entry) = data.get(offset..offset + 21) else {
+                return false;
+            };
+            let path_string_id = u32::from_le_bytes([entry[12], entry[13], entry[14], entry[15]]);
+            entry[20] & song_flags::DELETED != 0
+                && strings
+                    .get(path_string_id as usize)
+                    .is_some_and(|path| restored_paths.contains(path.as_str()))
+        })
+        .collect();
+    let songs_restored = if song_ids.is_empty() {
+        0
     } else {
-        let id = albums.len() as u32;
-        let name_string_id = string_table.add(album_name);
-        albums.push(AlbumEntry {
-            name_string_id,
-            artist_id,
-            year: new_metadata.year.unwrap_or(0) as u16,
-        });
-        album_map.insert(album_key, id);
-        id
+        restore_songs(base_path.clone(), song_ids)?.songs_restored
     };
 
-    // Create new song entry with same path but new metadata
-    let title = new_metadata.title.as_ref().ok_or("Missing title")?;
-    let title_string_id = string_table.add(title);
-    let path_string_id = string_table.add(&old_path); // Reuse path, dedup handles it
-
-    let new_song_id = songs.len() as u32;
-    // Preserve duration from old song if not provided in new_metadata
-    let duration = new_metadata
-        .duration_secs
-        .map(|d| d as u16)
-        .unwrap_or(old_duration_sec);
-    songs.push(SongEntry::new(
-        title_string_id,
-        artist_id,
-        album_id,
-        path_string_id,
-        new_metadata.track_number.unwrap_or(0) as u16,
-        duration,
-    ));
+    Ok(crate::models::RestoreFromTrashResult {
+        files_restored: restored.len() as u32,
+        songs_restored,
+        not_restored,
+    })
+}
 
-    // Rebuild and write library.bin
-    write_library_bin(&library_bin_path, &string_table, &artists, &albums, &songs)?;
+/// Permanently remove everything in the trash.
+#[tauri::command]
+pub fn empty_trash(base_path: String) -> Result<crate::models::EmptyTrashResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    empty_trash_before(base, None)
+}
 
-    // Remap old song ID to new song ID in all playlists
-    let playlists_updated = remap_song_id_in_playlists(&jp3_path, song_id, new_song_id)?;
+/// Days deleted audio stays in the trash (None = until emptied).
+#[tauri::command]
+pub fn get_trash_retention(base_path: String) -> Result<Option<u32>, String> {
+    Ok(load_library_settings(Path::new(&base_path))?.trash_retention_days)
+}
 
-    Ok(crate::models::EditSongResult {
-        new_song_id,
-        artist_created: artists.len() > old_artist_count,
-        album_created: albums.len() > old_album_count,
-        playlists_updated,
-    })
+/// Set how many days deleted audio stays in the trash (None = until emptied).
+#[tauri::command]
+pub fn set_trash_retention(base_path: String, days: Option<u32>) -> Result<(), String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let mut settings = load_library_settings(base)?;
+    settings.trash_retention_days = days;
+    save_library_settings(base, &settings)
 }
 
-/// Get library statistics including deleted song count.
+/// How audio files are laid out in `jp3/music`.
+#[tauri::command]
+pub fn get_bucket_layout(base_path: String) -> Result<BucketLayout, String> {
+    Ok(load_library_settings(Path::new(&base_path))?.bucket_layout)
+}
+
+/// Set the bucket layout for songs saved from now on.
 ///
-/// Use this to determine if compaction is needed.
+/// Songs already in the library stay where they are until a compaction
+/// with `renumber_files` moves them into the new layout.
 #[tauri::command]
-pub fn get_library_stats(base_path: String) -> Result<crate::models::LibraryStats, String> {
+pub fn set_bucket_layout(base_path: String, layout: BucketLayout) -> Result<(), String> {
     let base = Path::new(&base_path);
-    let jp3_path = base.join(JP3_DIR);
-    let metadata_path = jp3_path.join(METADATA_DIR);
+    ensure_library_available(base)?;
+    if !(1..=MAX_FILES_PER_BUCKET).contains(&layout.files_per_bucket) {
+        return Err(format!(
+            "Files per bucket must be between 1 and {}",
+            MAX_FILES_PER_BUCKET
+        ));
+    }
+    let mut settings = load_library_settings(base)?;
+    settings.bucket_layout = layout;
+    save_library_settings(base, &settings)
+}
+
+/// Patch album years in place in library.bin.
+///
+/// Only the 2-byte year field of each album entry is rewritten, so this is
+/// safe to run without rebuilding the library. Album IDs outside the album
+/// table are skipped. Returns the number of albums patched.
+pub fn patch_album_years(base_path: &str, updates: &[(u32, u16)]) -> Result<u32, String> {
+    let base = Path::new(base_path);
+    ensure_library_available(base)?;
+    let library_bin_path = base.join(JP3_DIR).join(METADATA_DIR).join(LIBRARY_BIN);
+
+    let _marker = begin_library_write(&library_bin_path, "patch_album_years")?;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&library_bin_path)
+        .map_err(|e| format!("Failed to open library.bin for writing: {}", e))?;
+
+    let header = read_verified_header(&mut file)?;
+
+    let mut patched = 0u32;
+    for &(album_id, year) in updates {
+        if album_id >= header.album_count {
+            continue;
+        }
+
+        // Year is at offset 8 within the album entry
+        let year_offset = header.album_table_offset as u64
+            + album_id as u64 * AlbumEntry::SIZE as u64
+            + 8;
+        file.seek(SeekFrom::Start(year_offset))
+            .map_err(|e| format!("Failed to seek to album {}: {}", album_id, e))?;
+        file.write_all(&year.to_le_bytes())
+            .map_err(|e| format!("Failed to write year for album {}: {}", album_id, e))?;
+        patched += 1;
+    }
+
+    if patched > 0 {
+        write_generation(&mut file, header.generation.wrapping_add(1))?;
+        write_checksum(&mut file, &header)?;
+    }
+
+    file.sync_all()
+        .map_err(|e| format!("Failed to sync changes: {}", e))?;
+    if patched > 0 {
+        let album_ids = updates
+            .iter()
+            .map(|&(album_id, _)| album_id)
+            .filter(|&album_id| album_id < header.album_count)
+            .collect();
+        announce_album_change(base, album_ids);
+    }
+
+    Ok(patched)
+}
+
+/// Emit `library://album-updated` for `album_ids`.
+fn announce_album_change(base: &Path, album_ids: Vec<u32>) {
+    announce(
+        EVENT_ALBUM_UPDATED,
+        base,
+        crate::models::LibraryChangeEvent {
+            album_ids,
+            ..Default::default()
+        },
+    );
+}
+
+/// Set a song's star rating (0 clears it).
+///
+/// Only the rating byte of the song entry is rewritten. Libraries older
+/// than v4 have no rating field; `migrate_library` upgrades the format.
+#[tauri::command]
+pub fn rate_song(base_path: String, song_id: u32, rating: u8) -> Result<(), String> {
+    if rating > MAX_RATING {
+        return Err(format!("Rating must be between 0 and {}", MAX_RATING));
+    }
+
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let library_bin_path = base.join(JP3_DIR).join(METADATA_DIR).join(LIBRARY_BIN);
+
+    let _marker = begin_library_write(&library_bin_path, "rate_song")?;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&library_bin_path)
+        .map_err(|e| format!("Failed to open library.bin for writing: {}", e))?;
+
+    let header = read_verified_header(&mut file)?;
+    if header.version < DISC_NUMBERS_VERSION {
+        return Err(format!(
+            "Library format v{} has no ratings; migrate the library to upgrade it",
+            header.version
+        ));
+    }
+    let (song_offset, _) = read_active_song_flags(&mut file, &header, song_id)?;
+
+    file.seek(SeekFrom::Start(song_offset + RATING_OFFSET))
+        .map_err(|e| format!("Failed to seek to song {}: {}", song_id, e))?;
+    file.write_all(&[rating])
+        .map_err(|e| format!("Failed to write rating for song {}: {}", song_id, e))?;
+    write_generation(&mut file, header.generation.wrapping_add(1))?;
+    write_checksum(&mut file, &header)?;
+
+    file.sync_all()
+        .map_err(|e| format!("Failed to sync changes: {}", e))?;
+    announce_change(EVENT_SONGS_UPDATED, base, vec![song_id]);
+    Ok(())
+}
+
+/// Flip a song's favorite flag, returning whether it is now a favorite.
+///
+/// Only the flags byte of the song entry is rewritten.
+#[tauri::command]
+pub fn toggle_favorite(base_path: String, song_id: u32) -> Result<bool, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let library_bin_path = base.join(JP3_DIR).join(METADATA_DIR).join(LIBRARY_BIN);
+
+    let _marker = begin_library_write(&library_bin_path, "toggle_favorite")?;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&library_bin_path)
+        .map_err(|e| format!("Failed to open library.bin for writing: {}", e))?;
+
+    let header = read_verified_header(&mut file)?;
+    let (song_offset, flags) = read_active_song_flags(&mut file, &header, song_id)?;
+    let flags = flags ^ song_flags::FAVORITE;
+
+    file.seek(SeekFrom::Start(song_offset + 20))
+        .map_err(|e| format!("Failed to seek to song {}: {}", song_id, e))?;
+    file.write_all(&[flags])
+        .map_err(|e| format!("Failed to write flags for song {}: {}", song_id, e))?;
+    write_generation(&mut file, header.generation.wrapping_add(1))?;
+    write_checksum(&mut file, &header)?;
+
+    file.sync_all()
+        .map_err(|e| format!("Failed to sync changes: {}", e))?;
+    announce_change(EVENT_SONGS_UPDATED, base, vec![song_id]);
+
+    Ok(flags & song_flags::FAVORITE != 0)
+}
+
+/// Flags byte of every song entry, indexed by song ID.
+///
+/// Lets callers holding song IDs (playlists) tell deleted songs from IDs
+/// past the end of the song table without parsing the whole library.
+pub(crate) fn read_song_flags(base: &Path) -> Result<Vec<u8>, String> {
+    let library_bin_path = base.join(JP3_DIR).join(METADATA_DIR).join(LIBRARY_BIN);
+    if !library_bin_path.exists() {
+        return Err("Library not found".to_string());
+    }
+
+    let data = fs::read(&library_bin_path)
+        .map_err(|e| format!("Failed to read library.bin: {}", e))?;
+    let header = LibraryHeader::from_bytes(&data).ok_or("Invalid library.bin header")?;
+    verify_checksum(&data, &header)?;
+    let entry_size = SongEntry::size_for(header.version) as usize;
+
+    (0..header.song_count as usize)
+        .map(|i| {
+            data.get(header.song_table_offset as usize + i * entry_size + 20)
+                .copied()
+                .ok_or_else(|| "Song table extends beyond file".to_string())
+        })
+        .collect()
+}
+
+/// Offset and flags byte of an active song entry.
+///
+/// Deleted and out-of-range songs are reported as not found.
+fn read_active_song_flags(
+    file: &mut fs::File,
+    header: &LibraryHeader,
+    song_id: u32,
+) -> Result<(u64, u8), String> {
+    if song_id >= header.song_count {
+        return Err(format!("Song {} not found", song_id));
+    }
+
+    let song_offset = header.song_table_offset as u64
+        + song_id as u64 * SongEntry::size_for(header.version) as u64;
+
+    // Flags byte at offset 20
+    let mut flags = [0u8; 1];
+    file.seek(SeekFrom::Start(song_offset + 20))
+        .and_then(|_| file.read_exact(&mut flags))
+        .map_err(|e| format!("Failed to read song {}: {}", song_id, e))?;
+    if flags[0] & song_flags::DELETED != 0 {
+        return Err(format!("Song {} not found", song_id));
+    }
+    Ok((song_offset, flags[0]))
+}
+
+/// Remap song IDs in all playlists.
+///
+/// Scans all playlist files and replaces each old ID in `song_id_map` with
+/// its new one. Returns the IDs of the playlists that were updated.
+fn remap_song_ids_in_playlists(
+    jp3_path: &Path,
+    song_id_map: &HashMap<u32, u32>,
+) -> Result<Vec<u32>, String> {
+    use crate::commands::playlist::{read_playlist_file, write_playlist_file};
+
+    let playlists_path = jp3_path.join(PLAYLISTS_DIR);
+
+    if !playlists_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(&playlists_path)
+        .map_err(|e| format!("Failed to read playlists directory: {}", e))?;
+
+    let mut playlists_updated = Vec::new();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        // Skip non-.bin files
+        if path.extension().and_then(|e| e.to_str()) != Some("bin") {
+            continue;
+        }
+
+        // Extract playlist ID from filename
+        let playlist_id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.parse::<u32>().ok());
+
+        let playlist_id = match playlist_id {
+            Some(id) => id,
+            None => continue,
+        };
+
+        // Read playlist
+        let playlist = match read_playlist_file(&path, playlist_id) {
+            Ok(p) => p,
+            Err(_) => continue, // Skip corrupted playlists
+        };
+
+        // Check if this playlist contains any of the old IDs
+        if !playlist.song_ids.iter().any(|id| song_id_map.contains_key(id)) {
+            continue;
+        }
+
+        // Remap old IDs to new ones, keeping when each song was added
+        let updated_entries: Vec<PlaylistEntry> = playlist
+            .entries()
+            .into_iter()
+            .map(|entry| match song_id_map.get(&entry.song_id) {
+                Some(&new_id) => PlaylistEntry::new(new_id, entry.added_at),
+                None => entry,
+            })
+            .collect();
+
+        // Write updated playlist
+        write_playlist_file(&path, &playlist.name, &updated_entries, playlist.manual_order)?;
+        playlists_updated.push(playlist_id);
+    }
+
+    Ok(playlists_updated)
+}
+
+/// Edit a song's metadata by soft-deleting the old entry and appending a new one.
+///
+/// The old entry is marked deleted (its audio file is kept) and a new entry
+/// with the same path is appended, so playlists are remapped to the new ID.
+///
+/// When only the title changes, the entry is updated in place instead and
+/// keeps its ID: just the entry (and the title, if it is a new string) is
+/// written while the string table has free space.
+///
+/// Note: This does require a full file rewrite since we need to update offsets.
+/// To edit several songs, use `edit_songs_metadata`, which rewrites once.
+#[tauri::command]
+pub fn edit_song_metadata(
+    base_path: String,
+    song_id: u32,
+    new_metadata: AudioMetadata,
+) -> Result<crate::models::EditSongResult, String> {
+    let result = apply_song_edits(Path::new(&base_path), &[(song_id, new_metadata)])?;
+
+    Ok(crate::models::EditSongResult {
+        new_song_id: result.new_song_ids[0],
+        in_place: result.edited_in_place > 0,
+        artist_created: result.artists_created > 0,
+        album_created: result.albums_created > 0,
+        playlists_updated: result.playlists_updated,
+    })
+}
+
+/// Edit several songs' metadata at once.
+///
+/// Each edit works like `edit_song_metadata` (title-only edits keep their
+/// IDs), but library.bin is written once and playlists are remapped once for
+/// the whole batch. A song may only
+/// appear once; nothing is written if any edit is invalid.
+#[tauri::command]
+pub fn edit_songs_metadata(
+    base_path: String,
+    edits: Vec<crate::models::SongEdit>,
+) -> Result<crate::models::EditSongsResult, String> {
+    let edits: Vec<(u32, AudioMetadata)> = edits
+        .into_iter()
+        .map(|edit| (edit.song_id, edit.metadata))
+        .collect();
+    apply_song_edits(Path::new(&base_path), &edits)
+}
+
+/// Whether an edit changes nothing but the title: the artist, album, track,
+/// duration, genre and disc all resolve to what the entry already has.
+fn is_title_only_edit(
+    string_table: &StringTable,
+    albums: &[AlbumEntry],
+    artist_map: &HashMap<String, u32>,
+    album_map: &HashMap<String, u32>,
+    genre_map: &HashMap<String, u16>,
+    old_song: &SongEntry,
+    new_metadata: &AudioMetadata,
+) -> bool {
+    let (Some(_), Some(artist_name), Some(album_name)) =
+        (&new_metadata.title, &new_metadata.artist, &new_metadata.album)
+    else {
+        return false;
+    };
+    let Some(old_album) = albums.get(old_song.album_id as usize) else {
+        return false;
+    };
+
+    let same_artist = artist_map.get(artist_name) == Some(&old_song.artist_id);
+    // Without an album artist the song stays on its album, compilation or not
+    let same_album = string_table.get(old_album.name_string_id) == Some(album_name.as_str())
+        && album_map.get(&format!("{}:{}", old_album.album_artist_id, album_name))
+            == Some(&old_song.album_id)
+        && new_metadata
+            .album_artist
+            .as_ref()
+            .filter(|name| !name.trim().is_empty())
+            .is_none_or(|name| artist_map.get(name) == Some(&old_album.album_artist_id));
+    let same_genre = new_metadata.genre.as_deref().map(str::trim).is_none_or(|genre| {
+        if genre.is_empty() {
+            old_song.genre_id == NO_GENRE
+        } else {
+            genre_map.get(genre) == Some(&old_song.genre_id)
+        }
+    });
+
+    same_artist
+        && same_album
+        && same_genre
+        && new_metadata.track_number.unwrap_or(0) as u16 == old_song.track_number
+        && new_metadata
+            .duration_secs
+            .is_none_or(|d| d as u16 == old_song.duration_sec)
+        && new_metadata
+            .disc_number
+            .is_none_or(|d| d.min(255) as u8 == old_song.disc_number)
+}
+
+/// Soft-delete each edited song and append its replacement, then write
+/// library.bin and remap playlists once. Title-only edits are made in place.
+fn apply_song_edits(
+    base: &Path,
+    edits: &[(u32, AudioMetadata)],
+) -> Result<crate::models::EditSongsResult, String> {
+    ensure_library_available(base)?;
+    let jp3_path = base.join(JP3_DIR);
+    let metadata_path = jp3_path.join(METADATA_DIR);
+    let library_bin_path = metadata_path.join(LIBRARY_BIN);
+
+    if !library_bin_path.exists() {
+        return Err("Library not found".to_string());
+    }
+
+    // Load existing library data for the append
+    let existing = load_existing_library_data(&library_bin_path)?;
+
+    // Validate every song_id before changing anything
+    let song_count = existing.as_ref().map_or(0, |e| e.songs.len());
+    let mut seen = HashSet::new();
+    for (song_id, _) in edits {
+        if *song_id as usize >= song_count {
+            return Err(format!("Song {} not found", song_id));
+        }
+        if !seen.insert(*song_id) {
+            return Err(format!("Song {} is edited more than once", song_id));
+        }
+    }
+    let Some(existing) = existing else {
+        return Ok(crate::models::EditSongsResult::default());
+    };
+
+    let mut string_table = existing.string_table;
+    let mut artists = existing.artists;
+    let mut albums = existing.albums;
+    let mut songs = existing.songs;
+    let mut genres = existing.genres;
+    let mut artist_map = existing.artist_map;
+    let mut album_map = existing.album_map;
+    let mut genre_map = existing.genre_map;
+
+    let old_artist_count = artists.len();
+    let old_album_count = albums.len();
+
+    let mut song_id_map = HashMap::new();
+    let mut new_song_ids = Vec::with_capacity(edits.len());
+    let mut patched_songs = Vec::new();
+    let mut old_paths = HashSet::new();
+    let mut names = Vec::new();
+
+    for (song_id, new_metadata) in edits {
+        let old_song = songs[*song_id as usize].clone();
+        let old_path = string_table
+            .get(old_song.path_string_id)
+            .map(str::to_string)
+            .ok_or("Failed to get old song path")?;
+
+        // Only the title changed: update the entry in place, keeping its ID
+        if is_title_only_edit(
+            &string_table,
+            &albums,
+            &artist_map,
+            &album_map,
+            &genre_map,
+            &old_song,
+            new_metadata,
+        ) {
+            let title = new_metadata.title.as_ref().ok_or("Missing title")?;
+            let song = &mut songs[*song_id as usize];
+            song.title_string_id = string_table.add(title);
+            song.metadata_source = MetadataSource::Manual;
+
+            patched_songs.push(*song_id);
+            new_song_ids.push(*song_id);
+            old_paths.insert(old_path);
+            names.push(title.clone());
+            continue;
+        }
+
+        // Soft-delete the old song WITHOUT deleting the audio file
+        songs[*song_id as usize].flags |= song_flags::DELETED;
+
+        // Get or create artist
+        let artist_name = new_metadata.artist.as_ref().ok_or("Missing artist")?;
+        let artist_id =
+            get_or_create_artist(&mut string_table, &mut artists, &mut artist_map, artist_name);
+
+        // Album artist: an explicit one wins; otherwise a song staying on a
+        // compilation keeps the compilation's album artist
+        let album_name = new_metadata.album.as_ref().ok_or("Missing album")?;
+        let old_album = albums.get(old_song.album_id as usize);
+        let kept_album_artist_id = old_album
+            .filter(|album| album.album_artist_id != old_song.artist_id)
+            .filter(|album| string_table.get(album.name_string_id) == Some(album_name.as_str()))
+            .map(|album| album.album_artist_id);
+        let album_artist_id = match (&new_metadata.album_artist, kept_album_artist_id) {
+            (Some(name), _) if !name.trim().is_empty() => {
+                get_or_create_artist(&mut string_table, &mut artists, &mut artist_map, name)
+            }
+            (_, Some(id)) => id,
+            _ => artist_id,
+        };
+
+        // Get or create album
+        let album_key = format!("{}:{}", album_artist_id, album_name);
+        let album_id = if let Some(&id) = album_map.get(&album_key) {
+            id
+        } else {
+            let id = albums.len() as u32;
+            albums.push(new_album_entry(
+                &mut string_table,
+                album_name,
+                album_artist_id,
+                new_metadata.year.unwrap_or(0) as u16,
+            ));
+            album_map.insert(album_key, id);
+            id
+        };
+
+        // Create new song entry with same path but new metadata
+        let title = new_metadata.title.as_ref().ok_or("Missing title")?;
+        let title_string_id = string_table.add(title);
+
+        let new_song_id = songs.len() as u32;
+        // Preserve duration from old song if not provided in new_metadata
+        let duration = new_metadata
+            .duration_secs
+            .map(|d| d as u16)
+            .unwrap_or(old_song.duration_sec);
+        let genre_id = match new_metadata.genre.as_deref() {
+            Some(genre) => {
+                get_or_create_genre(&mut string_table, &mut genres, &mut genre_map, Some(genre))
+            }
+            None => old_song.genre_id,
+        };
+        songs.push(
+            SongEntry::new(
+                title_string_id,
+                artist_id,
+                album_id,
+                old_song.path_string_id,
+                new_metadata.track_number.unwrap_or(0) as u16,
+                duration,
+                genre_id,
+            )
+            .with_metadata_source(MetadataSource::Manual)
+            .with_disc_number(
+                new_metadata
+                    .disc_number
+                    .map_or(old_song.disc_number, |d| d.min(255) as u8),
+            )
+            .with_rating(old_song.rating)
+            .with_favorite(old_song.flags & song_flags::FAVORITE != 0)
+            .with_date_added(old_song.date_added)
+            .with_play_count(old_song.play_count),
+        );
+
+        song_id_map.insert(*song_id, new_song_id);
+        new_song_ids.push(new_song_id);
+        old_paths.insert(old_path);
+        names.extend([title.clone(), artist_name.clone(), album_name.clone()]);
+    }
+
+    // Rebuild and write library.bin
+    write_library_bin_patched(
+        &library_bin_path,
+        &string_table,
+        &artists,
+        &albums,
+        &songs,
+        &genres,
+        &PatchedEntries {
+            songs: &patched_songs,
+            ..Default::default()
+        },
+    )?;
+
+    if let Err(e) = record_transliterations(base, names.iter().map(String::as_str)) {
+        log::warn!("Failed to record transliterations: {}", e);
+    }
+
+    // User-entered metadata no longer rests on the automatic match
+    if let Err(e) = retain_song_confidence(base, |path| !old_paths.contains(path)) {
+        log::warn!("Failed to update song confidence: {}", e);
+    }
+
+    // Remap old song IDs to new song IDs in all playlists
+    let playlists_updated = remap_song_ids_in_playlists(&jp3_path, &song_id_map)?;
+    crate::commands::playlist::announce_playlist_change(base, playlists_updated.clone());
+    let playlists_updated = playlists_updated.len() as u32;
+
+    // Songs given a new ID are gone under the old one
+    let replaced: Vec<u32> = song_id_map
+        .iter()
+        .filter(|(old, new)| old != new)
+        .map(|(&old, _)| old)
+        .collect();
+    if !replaced.is_empty() {
+        announce_change(EVENT_SONGS_DELETED, base, replaced);
+    }
+    announce_change(EVENT_SONGS_UPDATED, base, new_song_ids.clone());
+
+    Ok(crate::models::EditSongsResult {
+        new_song_ids,
+        edited_in_place: patched_songs.len() as u32,
+        artists_created: (artists.len() - old_artist_count) as u32,
+        albums_created: (albums.len() - old_album_count) as u32,
+        playlists_updated,
+    })
+}
+
+/// Upgrade library.bin to the current format version, keeping every ID.
+///
+/// On a card whose firmware reads only an older version, the library is
+/// rewritten in that version instead, which may be a downgrade. The old
+/// file is copied to `library.v{N}.bak` first. Unlike `compact_library`,
+/// deleted songs and orphaned entries are kept, so playlists need no
+/// remapping. A library already at the target version is left untouched.
+#[tauri::command]
+pub fn migrate_library(base_path: String) -> Result<crate::models::MigrateLibraryResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let metadata_path = base.join(JP3_DIR).join(METADATA_DIR);
+    let library_bin_path = metadata_path.join(LIBRARY_BIN);
+
+    let mut file = fs::File::open(&library_bin_path)
+        .map_err(|e| format!("Failed to open library.bin: {}", e))?;
+    let header = read_verified_header(&mut file)?;
+    drop(file);
+
+    let from_version = header.version;
+    let to_version = library_bin_version(&library_bin_path);
+    if from_version == to_version {
+        return Ok(crate::models::MigrateLibraryResult {
+            from_version,
+            to_version: from_version,
+            backup_path: None,
+        });
+    }
+
+    let backup_path = metadata_path.join(format!("library.v{}.bak", from_version));
+    fs::copy(&library_bin_path, &backup_path)
+        .map_err(|e| format!("Failed to back up library.bin: {}", e))?;
+
+    // Same loader incremental saves use: v1 sort names are filled in, and
+    // fields older versions lack take their defaults
+    let tables = match load_existing_library_data(&library_bin_path)? {
+        Some(existing) => serialize_tables(
+            &existing.string_table,
+            &existing.artists,
+            &existing.albums,
+            &existing.songs,
+            &existing.genres,
+        ),
+        None => serialize_tables(&StringTable::new(), &[], &[], &[], &[]),
+    };
+    rewrite_library_bin(&library_bin_path, &tables)?;
+
+    log::info!(
+        "[migrate_library] Rewrote library.bin from v{} as v{} (backup: {})",
+        from_version,
+        to_version,
+        backup_path.display()
+    );
+
+    Ok(crate::models::MigrateLibraryResult {
+        from_version,
+        to_version,
+        backup_path: Some(backup_path.to_string_lossy().to_string()),
+    })
+}
+
+/// Rebuild a damaged library.bin from whatever can still be read.
+///
+/// Songs are kept when their title, path, artist and album all resolve and
+/// their audio file is still in `jp3/music`; everything else is dropped.
+/// Audio files no remaining song points at are then added back from their
+/// tags (or filename), so a damaged header or string table loses metadata
+/// but not music. Playlists are remapped to the new song IDs.
+///
+/// The damaged files are backed up first (see `backup_library`).
+#[tauri::command]
+pub fn repair_library(base_path: String) -> Result<crate::models::RepairLibraryResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let jp3_path = base.join(JP3_DIR);
+    let music_path = jp3_path.join(MUSIC_DIR);
+    let metadata_path = jp3_path.join(METADATA_DIR);
+    let library_bin_path = metadata_path.join(LIBRARY_BIN);
+    if !metadata_path.is_dir() {
+        return Err("Library not initialized".to_string());
+    }
+
+    let backup_id = if library_bin_path.exists() {
+        Some(create_backup(base)?.id)
+    } else {
+        None
+    };
+    let data = fs::read(&library_bin_path).unwrap_or_default();
+    let old = salvage_library(&data);
+
+    let mut string_table = StringTable::new();
+    let mut artists: Vec<ArtistEntry> = Vec::new();
+    let mut albums: Vec<AlbumEntry> = Vec::new();
+    let mut songs: Vec<SongEntry> = Vec::new();
+    let mut genres: Vec<GenreEntry> = Vec::new();
+    let mut artist_map: HashMap<String, u32> = HashMap::new();
+    let mut album_map: HashMap<String, u32> = HashMap::new();
+    let mut genre_map: HashMap<String, u16> = HashMap::new();
+    let mut song_id_map: HashMap<u32, u32> = HashMap::new();
+    let mut linked_paths: HashSet<String> = HashSet::new();
+    let mut songs_dropped = 0u32;
+
+    let string = |id: u32| old.strings.get(id as usize).and_then(Option::as_deref);
+    let artist_name = |id: u32| string(old.artists.get(id as usize)?.name_string_id);
+
+    for (old_id, song) in old.songs.iter().enumerate() {
+        if song.flags & song_flags::DELETED != 0 {
+            continue;
+        }
+        let resolved = (|| {
+            let title = string(song.title_string_id)?;
+            let path = string(song.path_string_id)?;
+            let artist = artist_name(song.artist_id)?;
+            let album = old.albums.get(song.album_id as usize)?;
+            let album_name = string(album.name_string_id)?;
+            // A compilation whose album artist was lost is filed under the track artist
+            let album_artist = artist_name(album.album_artist_id).unwrap_or(artist);
+            let genre = old
+                .genres
+                .get(song.genre_id as usize)
+                .and_then(|&name_id| string(name_id));
+            let playable = music_path.join(path).is_file() && !linked_paths.contains(path);
+            playable.then_some((title, path, artist, album, album_name, album_artist, genre))
+        })();
+        let Some((title, path, artist, album, album_name, album_artist, genre)) = resolved else {
+            songs_dropped += 1;
+            continue;
+        };
+
+        let artist_id =
+            get_or_create_artist(&mut string_table, &mut artists, &mut artist_map, artist);
+        let album_artist_id =
+            get_or_create_artist(&mut string_table, &mut artists, &mut artist_map, album_artist);
+        let album_key = format!("{}:{}", album_artist_id, album_name);
+        let album_id = *album_map.entry(album_key).or_insert_with(|| {
+            let entry = new_album_entry(&mut string_table, album_name, album_artist_id, album.year);
+            albums.push(entry);
+            albums.len() as u32 - 1
+        });
+        let genre_id = get_or_create_genre(&mut string_table, &mut genres, &mut genre_map, genre);
+
+        song_id_map.insert(old_id as u32, songs.len() as u32);
+        linked_paths.insert(path.to_string());
+        songs.push(
+            SongEntry::new(
+                string_table.add(title),
+                artist_id,
+                album_id,
+                string_table.add(path),
+                song.track_number,
+                song.duration_sec,
+                genre_id,
+            )
+            .with_metadata_source(song.metadata_source)
+            .with_disc_number(song.disc_number)
+            .with_rating(song.rating)
+            .with_favorite(song.flags & song_flags::FAVORITE != 0)
+            .with_date_added(song.date_added)
+            .with_play_count(song.play_count),
+        );
+    }
+    let songs_kept = songs.len() as u32;
+
+    // Relink audio files nothing points at any more
+    let date_added = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as u32)
+        .unwrap_or(0);
+    for relative_path in music_files(&music_path)? {
+        if linked_paths.contains(&relative_path) {
+            continue;
+        }
+        let file_path = music_path.join(&relative_path);
+        if !matches!(sniff_audio_format(&file_path), Ok(Some(_))) {
+            continue;
+        }
+        let tagged = get_audio_metadata(file_path.to_string_lossy().to_string())?;
+        let metadata = tagged.metadata;
+        let has_tags = metadata.title.is_some();
+        let title = metadata.title.clone().unwrap_or_else(|| relative_path.clone());
+        let artist = metadata.artist.as_deref().unwrap_or("Unknown");
+        let album_name = metadata.album.as_deref().unwrap_or("Unknown");
+        let album_artist = metadata.album_artist_name().map_or(artist, String::as_str).to_string();
+
+        let artist_id =
+            get_or_create_artist(&mut string_table, &mut artists, &mut artist_map, artist);
+        let album_artist_id =
+            get_or_create_artist(&mut string_table, &mut artists, &mut artist_map, &album_artist);
+        let album_key = format!("{}:{}", album_artist_id, album_name);
+        let album_id = *album_map.entry(album_key).or_insert_with(|| {
+            let year = metadata.year.unwrap_or(0) as u16;
+            albums.push(new_album_entry(&mut string_table, album_name, album_artist_id, year));
+            albums.len() as u32 - 1
+        });
+        let genre_id = get_or_create_genre(
+            &mut string_table,
+            &mut genres,
+            &mut genre_map,
+            metadata.genre.as_deref(),
+        );
+
+        songs.push(
+            SongEntry::new(
+                string_table.add(&title),
+                artist_id,
+                album_id,
+                string_table.add(&relative_path),
+                metadata.track_number.unwrap_or(0) as u16,
+                metadata.duration_secs.unwrap_or(0) as u16,
+                genre_id,
+            )
+            .with_metadata_source(if has_tags {
+                MetadataSource::Id3
+            } else {
+                MetadataSource::Unknown
+            })
+            .with_disc_number(metadata.disc_number.unwrap_or(0).min(255) as u8)
+            .with_rating(metadata.rating.unwrap_or(0).min(MAX_RATING))
+            .with_date_added(date_added)
+            .with_play_count(saturate_play_count(metadata.play_count)),
+        );
+    }
+    let songs_relinked = songs.len() as u32 - songs_kept;
+
+    let tables = serialize_tables(&string_table, &artists, &albums, &songs, &genres);
+    rewrite_library_bin(&library_bin_path, &tables)?;
+    let playlists_updated = remap_playlists(&jp3_path.join(PLAYLISTS_DIR), &song_id_map, |_, _| {});
+
+    log::info!(
+        "[repair_library] Kept {} songs, dropped {}, relinked {} files. Updated {} playlists.",
+        songs_kept,
+        songs_dropped,
+        songs_relinked,
+        playlists_updated
+    );
+
+    Ok(crate::models::RepairLibraryResult {
+        backup_id,
+        songs_kept,
+        songs_dropped,
+        songs_relinked,
+        playlists_updated,
+    })
+}
+
+/// Tables read from a damaged library.bin, as far as they go.
+#[derive(Default)]
+struct SalvagedTables {
+    /// None for strings that aren't valid UTF-8
+    strings: Vec<Option<String>>,
+    artists: Vec<RawArtist>,
+    albums: Vec<RawAlbum>,
+    songs: Vec<RawSong>,
+    genres: Vec<u32>,
+}
+
+/// Read every table entry that lies within the file.
+///
+/// Nothing is salvaged without a usable header. Entries are returned with
+/// whatever IDs they hold; callers check them.
+fn salvage_library(data: &[u8]) -> SalvagedTables {
+    let Some(header) = LibraryHeader::from_bytes(data)
+        .filter(|h| h.version > 0 && h.version <= crate::models::LIBRARY_VERSION)
+    else {
+        return SalvagedTables::default();
+    };
+    // Entries of a table that fit in the file
+    let fitting = |count: u32, offset: u32, entry_size: u32| {
+        let available = data.len().saturating_sub(offset as usize) / entry_size as usize;
+        (count as usize).min(available)
+    };
+
+    let (strings, _) = walk_string_table(
+        data,
+        header.string_table_offset as usize,
+        header.string_table_end() as usize,
+    );
+    let artist_count = fitting(header.artist_count, header.artist_table_offset, ArtistEntry::SIZE);
+    let album_count = fitting(header.album_count, header.album_table_offset, AlbumEntry::SIZE);
+    let song_count = fitting(
+        header.song_count,
+        header.song_table_offset,
+        SongEntry::size_for(header.version),
+    );
+    let genre_count = fitting(header.genre_count, header.genre_table_offset, GenreEntry::SIZE);
+
+    let version = header.version;
+    let artist_offset = header.artist_table_offset as usize;
+    SalvagedTables {
+        strings,
+        artists: parse_artist_table(data, artist_offset, artist_count, version).unwrap_or_default(),
+        albums: parse_album_table(data, header.album_table_offset as usize, album_count, version)
+            .unwrap_or_default(),
+        songs: parse_song_table(data, header.song_table_offset as usize, song_count, version)
+            .unwrap_or_default(),
+        genres: parse_genre_table(data, header.genre_table_offset as usize, genre_count)
+            .unwrap_or_default(),
+    }
+}
+
+/// Paths of the files in the music buckets, relative to `jp3/music`, in order.
+///
+/// Buckets may be one or two folders deep (see `BucketLayout`).
+fn music_files(music_path: &Path) -> Result<Vec<String>, String> {
+    if !music_path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    let buckets = fs::read_dir(music_path)
+        .map_err(|e| format!("Failed to read music directory: {}", e))?;
+    for bucket in buckets.flatten().filter(|e| e.path().is_dir()) {
+        let bucket_name = bucket.file_name().to_string_lossy().to_string();
+        let entries = fs::read_dir(bucket.path())
+            .map_err(|e| format!("Failed to read bucket {}: {}", bucket_name, e))?;
+        for entry in entries.flatten() {
+            let name = format!("{}/{}", bucket_name, entry.file_name().to_string_lossy());
+            if entry.path().is_file() {
+                files.push(name);
+            } else if entry.path().is_dir() {
+                let inner = fs::read_dir(entry.path())
+                    .map_err(|e| format!("Failed to read bucket {}: {}", name, e))?;
+                for file in inner.flatten().filter(|e| e.path().is_file()) {
+                    files.push(format!("{}/{}", name, file.file_name().to_string_lossy()));
+                }
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Find files in `jp3/music` that no active song points at.
+///
+/// A save cut off between copying files and writing library.bin leaves its
+/// copies behind, taking up space the device never plays. The files are
+/// reported with their total size; with an `action` they are then deleted,
+/// or the audio ones added back as songs from their tags (files whose audio
+/// is already in the library are left for a later delete). Don't run an
+/// action while a save is in progress: its copies aren't referenced until
+/// it writes library.bin.
+#[tauri::command]
+pub fn scan_orphaned_files(
+    base_path: String,
+    action: Option<crate::models::OrphanAction>,
+) -> Result<crate::models::OrphanScanResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let music_path = base.join(JP3_DIR).join(MUSIC_DIR);
+
+    let library = load_library(base_path.clone(), None)?;
+    let referenced: HashSet<&str> = library.songs.iter().map(|s| s.path.as_str()).collect();
+    let mut result = crate::models::OrphanScanResult::default();
+    for relative_path in music_files(&music_path)? {
+        if referenced.contains(relative_path.as_str()) {
+            continue;
+        }
+        let file_path = music_path.join(&relative_path);
+        let size_bytes = fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0);
+        result.wasted_bytes += size_bytes;
+        result.files.push(crate::models::OrphanedFile {
+            path: relative_path,
+            size_bytes,
+            is_audio: matches!(sniff_audio_format(&file_path), Ok(Some(_))),
+        });
+    }
+    log::info!(
+        "[scan_orphaned_files] {} orphaned files, {} bytes",
+        result.files.len(),
+        result.wasted_bytes
+    );
+
+    match action {
+        None => {}
+        Some(crate::models::OrphanAction::Delete) => {
+            for file in &result.files {
+                match fs::remove_file(music_path.join(&file.path)) {
+                    Ok(()) => {
+                        result.files_deleted += 1;
+                        result.bytes_freed += file.size_bytes;
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to delete {}: {}", file.path, e);
+                        result.failed_files.push(file.path.clone());
+                    }
+                }
+            }
+            remove_empty_buckets(&music_path);
+            log::info!(
+                "[scan_orphaned_files] Deleted {} files, freed {} bytes",
+                result.files_deleted,
+                result.bytes_freed
+            );
+        }
+        Some(crate::models::OrphanAction::Adopt) => {
+            let mut files = Vec::new();
+            for file in result.files.iter().filter(|file| file.is_audio) {
+                let file_path = music_path.join(&file.path);
+                match get_audio_metadata(file_path.to_string_lossy().to_string()) {
+                    Ok(tagged) => files.push(orphan_to_save(file, tagged.metadata, &file_path)),
+                    Err(e) => {
+                        log::warn!("Failed to read tags of {}: {}", file.path, e);
+                        result.failed_files.push(file.path.clone());
+                    }
+                }
+            }
+            let saved = save_songs(&base_path, files, FilePlacement::InLibrary)?;
+            result.songs_adopted = saved.files_saved;
+            result.duplicates_skipped = saved.duplicates_skipped;
+            result.song_ids = saved.song_ids;
+            log::info!(
+                "[scan_orphaned_files] Adopted {} songs, {} duplicates left",
+                result.songs_adopted,
+                result.duplicates_skipped
+            );
+        }
+    }
+
+    Ok(result)
+}
+
+/// An orphaned file to save in place, filling missing tags the way
+/// `repair_library` does when it relinks files.
+fn orphan_to_save(
+    file: &crate::models::OrphanedFile,
+    mut metadata: AudioMetadata,
+    file_path: &Path,
+) -> FileToSave {
+    let metadata_source = if metadata.title.is_some() {
+        MetadataSource::Id3
+    } else {
+        MetadataSource::Unknown
+    };
+    metadata.title.get_or_insert_with(|| file.path.clone());
+    metadata.artist.get_or_insert_with(|| "Unknown".to_string());
+    metadata.album.get_or_insert_with(|| "Unknown".to_string());
+    FileToSave {
+        source_path: file_path.to_string_lossy().to_string(),
+        metadata,
+        metadata_source,
+        confidence: None,
+        fingerprint: None,
+    }
+}
+
+/// Size of the cache of device transcodes (see `export_selection`).
+#[tauri::command]
+pub fn get_transcode_cache_stats(
+    base_path: String,
+) -> Result<crate::models::TranscodeCacheStats, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    transcode_cache_stats(base)
+}
+
+/// Free space taken by device transcodes.
+///
+/// Removes transcodes not used for `unused_days`, then the least recently
+/// used ones until the cache fits in `max_bytes`. Removed transcodes are
+/// re-encoded the next time they are exported.
+#[tauri::command]
+pub fn prune_transcode_cache(
+    base_path: String,
+    max_bytes: Option<u64>,
+    unused_days: Option<u32>,
+) -> Result<crate::models::PruneTranscodeCacheResult, String> {
+    if max_bytes.is_none() && unused_days.is_none() {
+        return Err("Give a size limit or a number of unused days".to_string());
+    }
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    prune_cached_transcodes(base, max_bytes, unused_days)
+}
+
+/// Pick songs whose audio fits in `max_bytes`, e.g. to fill a 16GB card
+/// with `export_selection`.
+///
+/// Only audio files are counted; leave some room for covers and
+/// library.bin.
+#[tauri::command]
+pub fn select_songs_for_size(
+    base_path: String,
+    max_bytes: u64,
+    preferences: Option<crate::models::SelectionPreferences>,
+) -> Result<crate::models::SizedSelection, String> {
+    let music_path = Path::new(&base_path).join(JP3_DIR).join(MUSIC_DIR);
+    let library = load_library(base_path, None)?;
+    let sizes: HashMap<u32, u64> = library
+        .songs
+        .iter()
+        .filter_map(|song| {
+            let metadata = fs::metadata(music_path.join(&song.path)).ok()?;
+            Some((song.id, metadata.len()))
+        })
+        .collect();
+    Ok(select_within_budget(
+        &library.songs,
+        &sizes,
+        max_bytes,
+        &preferences.unwrap_or_default(),
+    ))
+}
+
+/// Write a printable catalogue of the library (artists -> albums -> tracks
+/// with durations) to `dest`, as HTML or Markdown.
+#[tauri::command]
+pub fn export_library_report(
+    base_path: String,
+    dest: String,
+    format: crate::models::ReportFormat,
+) -> Result<crate::models::LibraryReportResult, String> {
+    let library = load_library(base_path, None)?;
+    let (report, counts) = render_library_report(&library, format);
+    fs::write(&dest, report).map_err(|e| format!("Failed to write {}: {}", dest, e))?;
+    Ok(crate::models::LibraryReportResult {
+        path: dest,
+        artist_count: counts.artists,
+        album_count: counts.albums,
+        song_count: counts.songs,
+    })
+}
+
+/// Build a small, self-contained library on another drive from a selection.
+///
+/// The chosen songs, plus every song in the chosen playlists, are copied to
+/// `dest_path` under new song, artist and album IDs, with a string table
+/// holding only their names. The chosen playlists come along remapped, and
+/// only the covers their albums and artists use are copied, with their
+/// thumbnails (generated first where missing). The library at `base_path`
+/// is not otherwise changed. The auto-playlists it exports are generated
+/// on the drive from the exported songs.
+///
+/// With an encode `profile` (usually the target device's), songs that
+/// aren't MP3 are transcoded on the way out; see `transcode_service`.
+///
+/// The drive may hold a device marker the firmware wrote (and nothing else
+/// of a library); its limits are checked before anything is copied, and
+/// the marker is kept. library.bin is written in the newest version the
+/// firmware reads. Pass `library_version` for firmware that reads only an
+/// older one but hasn't said so; it is recorded in the marker, so later
+/// saves to the card keep that version.
+///
+/// The export's plan is kept in the source library until it finishes. If it
+/// fails after songs have been copied (card pulled, app closed), the partial
+/// export stays on the drive for `resume_sync`; if it fails before that, the
+/// partial export is removed.
+#[tauri::command]
+pub fn export_selection(
+    base_path: String,
+    song_ids: Vec<u32>,
+    playlist_ids: Vec<u32>,
+    dest_path: String,
+    profile: Option<EncodeProfile>,
+    library_version: Option<u32>,
+) -> Result<crate::models::ExportSelectionResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    check_library_version(library_version)?;
+    if let Some(pending) = load_pending_sync(base)? {
+        return Err(format!(
+            "An interrupted sync to {} is pending; resume or discard it first",
+            pending.dest_path
+        ));
+    }
+    let dest_jp3 = Path::new(&dest_path).join(JP3_DIR);
+    // A card prepared by the firmware holds only its device marker
+    if dest_jp3.join(METADATA_DIR).join(LIBRARY_BIN).exists() {
+        return Err(format!("{} already has a library", dest_path));
+    }
+
+    let mut plan = PendingSync {
+        song_ids,
+        playlist_ids,
+        dest_path,
+        profile,
+        library_version,
+        started_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        copied: HashMap::new(),
+    };
+    save_pending_sync(base, &plan)?;
+    let result = run_sync(base, &mut plan, false);
+    if result.is_err() && plan.copied.is_empty() {
+        // Nothing worth resuming
+        let _ = remove_export(Path::new(&plan.dest_path));
+        clear_pending_sync(base)?;
+    }
+    result
+}
+
+/// Error unless `library_version` is one this app can write.
+fn check_library_version(library_version: Option<u32>) -> Result<(), String> {
+    match library_version {
+        Some(version) if version == 0 || version > crate::models::LIBRARY_VERSION => Err(format!(
+            "Library version must be 1 to {}, not {}",
+            crate::models::LIBRARY_VERSION,
+            version
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Remove an export's `jp3` folder, keeping the device marker if the
+/// card's firmware declared itself in it.
+fn remove_export(dest: &Path) -> Result<(), String> {
+    let dest_jp3 = dest.join(JP3_DIR);
+    if !dest_jp3.exists() {
+        return Ok(());
+    }
+    let firmware_marker = read_device_marker(dest)
+        .ok()
+        .flatten()
+        .filter(DeviceMarker::declares_firmware);
+    fs::remove_dir_all(&dest_jp3)
+        .map_err(|e| format!("Failed to remove {}: {}", dest_jp3.display(), e))?;
+    if let Some(marker) = firmware_marker {
+        fs::create_dir_all(dest_jp3.join(METADATA_DIR))
+            .map_err(|e| format!("Failed to create metadata directory: {}", e))?;
+        restore_device_marker(dest, &marker)?;
+    }
+    Ok(())
+}
+
+/// The export interrupted before it finished, if any.
+#[tauri::command]
+pub fn get_pending_sync(base_path: String) -> Result<Option<PendingSync>, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    load_pending_sync(base)
+}
+
+/// Past exports, most recent first; only those to `device_id` if given.
+#[tauri::command]
+pub fn get_sync_history(
+    base_path: String,
+    device_id: Option<String>,
+) -> Result<Vec<SyncRecord>, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let mut history = load_sync_history(base)?;
+    if let Some(device_id) = device_id {
+        history.retain(|record| record.device_id.as_deref() == Some(device_id.as_str()));
+    }
+    history.reverse();
+    Ok(history)
+}
+
+/// Preview an export without writing anything.
+///
+/// Lists each file `export_selection` would copy, delete or skip, with the
+/// reason, and the totals. If an interrupted export to `dest_path` is
+/// pending, this previews resuming it instead (the selection arguments are
+/// ignored), checking the files it already copied by hash.
+#[tauri::command]
+pub fn plan_device_sync(
+    base_path: String,
+    song_ids: Vec<u32>,
+    playlist_ids: Vec<u32>,
+    dest_path: String,
+    profile: Option<EncodeProfile>,
+    library_version: Option<u32>,
+) -> Result<SyncPlanReport, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    check_library_version(library_version)?;
+    let (mut plan, resumes_pending) = match load_pending_sync(base)? {
+        Some(pending) if pending.dest_path == dest_path => (pending, true),
+        Some(pending) => {
+            return Err(format!(
+                "An interrupted sync to {} is pending; resume or discard it first",
+                pending.dest_path
+            ))
+        }
+        None => {
+            let dest_jp3 = Path::new(&dest_path).join(JP3_DIR);
+            if dest_jp3.join(METADATA_DIR).join(LIBRARY_BIN).exists() {
+                return Err(format!("{} already has a library", dest_path));
+            }
+            let plan = PendingSync {
+                song_ids,
+                playlist_ids,
+                dest_path,
+                profile,
+                library_version,
+                started_at: 0,
+                copied: HashMap::new(),
+            };
+            (plan, false)
+        }
+    };
+
+    let mut files = Vec::new();
+    let result = write_selection(base, &mut plan, Some(&mut files))?;
+    let mut report = SyncPlanReport {
+        dest_path: plan.dest_path,
+        resumes_pending,
+        bytes_to_copy: result.bytes_copied,
+        ..Default::default()
+    };
+    for file in &files {
+        match file.action {
+            SyncAction::Copy => report.files_to_copy += 1,
+            SyncAction::Delete => {
+                report.files_to_delete += 1;
+                report.bytes_to_free += file.bytes;
+            }
+            SyncAction::Skip => report.files_skipped += 1,
+        }
+    }
+    report.files = files;
+    Ok(report)
+}
+
+/// Write a sync plan from `plan_device_sync` to `dest` as plain text.
+#[tauri::command]
+pub fn export_sync_plan(report: SyncPlanReport, dest: String) -> Result<(), String> {
+    fs::write(&dest, render_sync_plan(&report))
+        .map_err(|e| format!("Failed to write {}: {}", dest, e))
+}
+
+/// Finish an interrupted export.
+///
+/// Songs are laid out exactly as the first run would have; files it already
+/// copied are kept if their hash still matches, and everything else
+/// (including library.bin, playlists and covers) is written as usual. Audio
+/// files on the drive that the export doesn't lay out are removed.
+#[tauri::command]
+pub fn resume_sync(base_path: String) -> Result<crate::models::ExportSelectionResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let mut plan = load_pending_sync(base)?.ok_or("No interrupted sync to resume")?;
+    if !Path::new(&plan.dest_path).is_dir() {
+        return Err(format!("{} is not available", plan.dest_path));
+    }
+    log::info!(
+        "Resuming sync to {} ({} songs already copied)",
+        plan.dest_path,
+        plan.copied.len()
+    );
+    run_sync(base, &mut plan, true)
+}
+
+/// Abandon an interrupted export, removing what it copied if the drive is
+/// available. Returns false if there was nothing to discard.
+#[tauri::command]
+pub fn discard_sync(base_path: String) -> Result<bool, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let Some(plan) = load_pending_sync(base)? else {
+        return Ok(false);
+    };
+    remove_export(Path::new(&plan.dest_path))?;
+    clear_pending_sync(base)?;
+    Ok(true)
+}
+
+/// Write the plan's selection, clearing the plan once it has all been written.
+///
+/// The exported library is then verified and the export added to the sync
+/// history; failing to record it is only logged.
+fn run_sync(
+    base: &Path,
+    plan: &mut PendingSync,
+    resumed: bool,
+) -> Result<crate::models::ExportSelectionResult, String> {
+    let _write = begin_foreground_write("Copying songs to another drive");
+    let mut result = write_selection(base, plan, None)?;
+    let dest = Path::new(&plan.dest_path);
+    result.auto_playlists_exported =
+        crate::commands::playlist::export_auto_playlists(base, dest)?;
+    clear_pending_sync(base)?;
+
+    let verified = match verify_library(plan.dest_path.clone()) {
+        Ok(report) => !report.has_issues(),
+        Err(e) => {
+            log::warn!("Failed to verify export to {}: {}", plan.dest_path, e);
+            false
+        }
+    };
+    let record = SyncRecord {
+        device_id: read_device_id(dest).ok().flatten(),
+        dest_path: plan.dest_path.clone(),
+        synced_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        resumed,
+        songs_exported: result.songs_exported,
+        files_copied: result.songs_exported - result.songs_verified,
+        bytes_copied: result.bytes_copied,
+        verified,
+    };
+    if let Err(e) = record_sync(base, record) {
+        log::warn!("Failed to record sync to {}: {}", plan.dest_path, e);
+    }
+    Ok(result)
+}
+
+/// Export the plan's selection to its drive.
+///
+/// With `preview`, nothing is written or encoded: each file the export
+/// would copy, delete or skip is added to it instead, and the counts in the
+/// result are what the export would report.
+fn write_selection(
+    base: &Path,
+    plan: &mut PendingSync,
+    mut preview: Option<&mut Vec<SyncPlanFile>>,
+) -> Result<crate::models::ExportSelectionResult, String> {
+    let jp3_path = base.join(JP3_DIR);
+    let music_path = jp3_path.join(MUSIC_DIR);
+    let library = load_existing_library_data(&jp3_path.join(METADATA_DIR).join(LIBRARY_BIN))?
+        .ok_or("Library not found")?;
+    let profile = plan.profile.clone();
+    let dest_path = plan.dest_path.clone();
+    let playlists = plan
+        .playlist_ids
+        .iter()
+        .map(|&id| {
+            let path = jp3_path.join(PLAYLISTS_DIR).join(format!("{}.bin", id));
+            crate::commands::playlist::read_playlist_file(&path, id)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let selected: HashSet<u32> = plan
+        .song_ids
+        .iter()
+        .chain(playlists.iter().flat_map(|p| p.song_ids.iter()))
+        .copied()
+        .collect();
+
+    // Check the firmware's declared limits before anything is copied; the
+    // marker it left is kept by `initialize_library`
+    let dest = Path::new(&dest_path);
+    let song_count = library
+        .songs
+        .iter()
+        .enumerate()
+        .filter(|(id, song)| {
+            song.flags & song_flags::DELETED == 0 && selected.contains(&(*id as u32))
+        })
+        .count();
+    let library_version = writable_library_version(dest)
+        .min(plan.library_version.unwrap_or(crate::models::LIBRARY_VERSION));
+    check_firmware_supports(dest, library_version, song_count as u32)?;
+    if preview.is_none() {
+        initialize_library(dest_path.clone())?;
+        // Later writes to the card (see `library_bin_version`) keep the version
+        if plan.library_version.is_some() {
+            let mut marker = ensure_device_marker(dest)?;
+            marker.firmware_max_library_version = Some(library_version);
+            restore_device_marker(dest, &marker)?;
+        }
+    }
+    let dest_jp3 = dest.join(JP3_DIR);
+    let dest_music = dest_jp3.join(MUSIC_DIR);
+
+    // The export keeps the library's bucket layout for songs saved to it later
+    let layout = load_library_settings(base)?.bucket_layout;
+    if layout != BucketLayout::default() && preview.is_none() {
+        let mut dest_settings = load_library_settings(dest)?;
+        dest_settings.bucket_layout = layout;
+        save_library_settings(dest, &dest_settings)?;
+    }
+
+    let mut string_table = StringTable::new();
+    let mut artists: Vec<ArtistEntry> = Vec::new();
+    let mut albums: Vec<AlbumEntry> = Vec::new();
+    let mut songs: Vec<SongEntry> = Vec::new();
+    let mut genres: Vec<GenreEntry> = Vec::new();
+    let mut artist_map: HashMap<String, u32> = HashMap::new();
+    let mut album_map: HashMap<String, u32> = HashMap::new();
+    let mut genre_map: HashMap<String, u16> = HashMap::new();
+    let mut song_id_map: HashMap<u32, u32> = HashMap::new();
+    let mut album_covers: HashSet<(&str, &str)> = HashSet::new();
+    let hash_index = load_song_hashes(base).unwrap_or_default();
+    let mut exported_hashes: Vec<(String, String)> = Vec::new();
+    let mut bytes_copied = 0u64;
+    let mut songs_transcoded = 0u32;
+    let mut songs_verified = 0u32;
+    let mut bucket_names: HashMap<u32, HashSet<String>> = HashMap::new();
+    let mut excluded: Vec<u32> = Vec::new();
+
+    let string = |id: u32| library.string_table.get(id);
+    let artist_name = |id: u32| string(library.artists.get(id as usize)?.name_string_id);
+
+    // Songs in library order, so the export keeps the library's ordering
+    for (old_id, song) in library.songs.iter().enumerate() {
+        if !selected.contains(&(old_id as u32)) {
+            continue;
+        }
+        if song.flags & song_flags::DELETED != 0 {
+            excluded.push(old_id as u32);
+            continue;
+        }
+        let resolved = (|| {
+            let path = string(song.path_string_id)?;
+            let album = library.albums.get(song.album_id as usize)?;
+            Some((
+                string(song.title_string_id)?,
+                path,
+                artist_name(song.artist_id)?,
+                album,
+                string(album.name_string_id)?,
+                artist_name(album.album_artist_id)?,
+            ))
+        })();
+        let Some((title, path, artist, album, album_name, album_artist)) = resolved else {
+            excluded.push(old_id as u32);
+            continue;
+        };
+        let source = music_path.join(path);
+        if !source.is_file() {
+            excluded.push(old_id as u32);
+            continue;
+        }
+
+        let source_hash = hash_index.songs.get(path);
+        let transcode = profile.as_ref().filter(|_| needs_transcode(&source));
+        let transcoded = match transcode {
+            Some(profile) => {
+                let source_hash = match source_hash {
+                    Some(hash) => hash.clone(),
+                    None => hash_file(&source)?,
+                };
+                if preview.is_some() {
+                    // Nothing is encoded for a preview; a cached transcode is used if there is one
+                    Some(cached_transcode_path(base, &source_hash, profile))
+                        .filter(|cached| cached.is_file())
+                } else {
+                    Some(transcode_cached(base, &source, &source_hash, profile)?)
+                }
+            }
+            None => None,
+        };
+
+        // Buckets numbered from scratch, as save_to_library would lay them out
+        let extension = match transcode {
+            Some(_) => "mp3",
+            None => Path::new(path)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("mp3"),
+        };
+        let per_bucket = layout.files_per_bucket.max(1) as usize;
+        let bucket = (songs.len() / per_bucket) as u32;
+        let name = song_file_name(
+            layout.file_names,
+            songs.len() % per_bucket + 1,
+            song.track_number,
+            artist,
+            title,
+            extension,
+        );
+        let name = unique_file_name(&name, bucket_names.entry(bucket).or_default());
+        let relative_path = format!("{}/{}", layout.bucket_dir(bucket), name);
+        let dest_file = dest_music.join(&relative_path);
+        let copy_from = transcoded.as_deref().unwrap_or(&source);
+        let content_hash = match (&transcoded, source_hash) {
+            (None, Some(hash)) => hash.clone(),
+            _ => hash_file(copy_from)?,
+        };
+        if transcode.is_some() {
+            songs_transcoded += 1;
+        }
+
+        // Files from an interrupted run are kept only if they hold this song
+        let verified = match plan.copied.get(&relative_path) {
+            Some(copied) if *copied == content_hash && dest_file.is_file() => {
+                hash_file(&dest_file)? == content_hash
+            }
+            _ => false,
+        };
+        let planned = |action, reason, bytes| SyncPlanFile {
+            action,
+            reason,
+            path: format!("{}/{}", MUSIC_DIR, relative_path),
+            song_id: Some(old_id as u32),
+            bytes,
+            transcode: transcode.is_some(),
+        };
+        if verified {
+            songs_verified += 1;
+            if let Some(files) = preview.as_deref_mut() {
+                files.push(planned(SyncAction::Skip, SyncReason::AlreadyCopied, 0));
+            }
+        } else if let Some(files) = preview.as_deref_mut() {
+            let bytes = fs::metadata(copy_from).map(|m| m.len()).unwrap_or(0);
+            let reason = match dest_file.is_file() {
+                true => SyncReason::ChangedHash,
+                false => SyncReason::New,
+            };
+            bytes_copied += bytes;
+            files.push(planned(SyncAction::Copy, reason, bytes));
+        } else {
+            if let Some(bucket) = dest_file.parent() {
+                fs::create_dir_all(bucket)
+                    .map_err(|e| format!("Failed to create {}: {}", bucket.display(), e))?;
+            }
+            bytes_copied += copy_file(copy_from, &dest_file)
+                .map_err(|e| format!("Failed to copy {}: {}", path, e))?;
+            plan.copied.insert(relative_path.clone(), content_hash.clone());
+            save_pending_sync(base, plan)?;
+        }
+        exported_hashes.push((relative_path.clone(), content_hash));
+
+        let artist_id =
+            get_or_create_artist(&mut string_table, &mut artists, &mut artist_map, artist);
+        let album_artist_id =
+            get_or_create_artist(&mut string_table, &mut artists, &mut artist_map, album_artist);
+        let album_key = format!("{}:{}", album_artist_id, album_name);
+        let album_id = *album_map.entry(album_key).or_insert_with(|| {
+            let entry = new_album_entry(&mut string_table, album_name, album_artist_id, album.year);
+            albums.push(entry);
+            albums.len() as u32 - 1
+        });
+        album_covers.insert((album_artist, album_name));
+        let genre = library
+            .genres
+            .get(song.genre_id as usize)
+            .and_then(|genre| string(genre.name_string_id));
+        let genre_id = get_or_create_genre(&mut string_table, &mut genres, &mut genre_map, genre);
+
+        song_id_map.insert(old_id as u32, songs.len() as u32);
+        songs.push(
+            SongEntry::new(
+                string_table.add(title),
+                artist_id,
+                album_id,
+                string_table.add(&relative_path),
+                song.track_number,
+                song.duration_sec,
+                genre_id,
+            )
+            .with_metadata_source(song.metadata_source)
+            .with_disc_number(song.disc_number)
+            .with_rating(song.rating)
+            .with_favorite(song.flags & song_flags::FAVORITE != 0)
+            .with_date_added(song.date_added)
+            .with_play_count(song.play_count),
+        );
+    }
+
+    // Audio on the drive the export doesn't lay out, left by an earlier run
+    let exported: HashSet<&str> = exported_hashes.iter().map(|(path, _)| path.as_str()).collect();
+    for orphan in music_files(&dest_music)? {
+        if exported.contains(orphan.as_str()) {
+            continue;
+        }
+        let orphan_path = dest_music.join(&orphan);
+        match preview.as_deref_mut() {
+            Some(files) => files.push(SyncPlanFile {
+                action: SyncAction::Delete,
+                reason: SyncReason::OrphanedOnDevice,
+                path: format!("{}/{}", MUSIC_DIR, orphan),
+                song_id: None,
+                bytes: fs::metadata(&orphan_path).map(|m| m.len()).unwrap_or(0),
+                transcode: false,
+            }),
+            None => {
+                if let Err(e) = fs::remove_file(&orphan_path) {
+                    log::warn!("Failed to remove {}: {}", orphan_path.display(), e);
+                }
+            }
+        }
+    }
+
+    if let Some(files) = preview.as_deref_mut() {
+        excluded.extend(
+            selected
+                .iter()
+                .copied()
+                .filter(|&id| id as usize >= library.songs.len()),
+        );
+        excluded.sort_unstable();
+        files.extend(excluded.iter().map(|&id| SyncPlanFile {
+            action: SyncAction::Skip,
+            reason: SyncReason::Excluded,
+            path: library
+                .songs
+                .get(id as usize)
+                .and_then(|song| string(song.path_string_id))
+                .unwrap_or_default()
+                .to_string(),
+            song_id: Some(id),
+            bytes: 0,
+            transcode: false,
+        }));
+    } else {
+        let tables = serialize_tables(&string_table, &artists, &albums, &songs, &genres);
+        rewrite_library_bin(&dest_jp3.join(METADATA_DIR).join(LIBRARY_BIN), &tables)?;
+        record_song_hashes(dest, exported_hashes)?;
+
+        for playlist in &playlists {
+            let entries: Vec<PlaylistEntry> = playlist
+                .song_ids
+                .iter()
+                .zip(&playlist.added_at)
+                .filter_map(|(old_id, &added_at)| {
+                    Some(PlaylistEntry::new(*song_id_map.get(old_id)?, added_at))
+                })
+                .collect();
+            let path = dest_jp3.join(PLAYLISTS_DIR).join(format!("{}.bin", playlist.id));
+            crate::commands::playlist::write_playlist_file(
+                &path,
+                &playlist.name,
+                &entries,
+                playlist.manual_order,
+            )?;
+        }
+    }
+
+    // Only the covers (and thumbnails) the exported albums and artists use
+    let mut covers = Vec::new();
+    let albums_dir = jp3_path.join(ASSETS_DIR).join(ALBUMS_DIR);
+    let dest_albums_dir = dest_jp3.join(ASSETS_DIR).join(ALBUMS_DIR);
+    for &(album_artist, album_name) in &album_covers {
+        let name = format!("{}.jpg", cover_filename(album_artist, album_name));
+        let cover = albums_dir.join(&name);
+        // Thumbnails the background task hasn't made yet (or that were
+        // removed with an old cover) are generated now, so the card gets its
+        // device-sized cover; a preview only lists the ones that exist
+        let generate = preview.is_none() && cover.is_file();
+        covers.push((cover, dest_albums_dir.join(&name)));
+        for size in ThumbnailSize::ALL {
+            if generate {
+                if let Err(e) = ensure_thumbnail(&albums_dir, album_artist, album_name, size) {
+                    log::warn!("Failed to make thumbnail for \"{}\": {}", album_name, e);
+                }
+            }
+            covers.push((
+                thumbnail_path(&albums_dir, album_artist, album_name, size),
+                thumbnail_path(&dest_albums_dir, album_artist, album_name, size),
+            ));
+        }
+    }
+    let artists_dir = jp3_path.join(ASSETS_DIR).join(ARTISTS_DIR);
+    let dest_artists_dir = dest_jp3.join(ASSETS_DIR).join(ARTISTS_DIR);
+    for artist in &artists {
+        let Some(artist) = string_table.get(artist.name_string_id) else {
+            continue;
+        };
+        let name = format!("{}.jpg", cover_filename(artist, "artist"));
+        covers.push((artists_dir.join(&name), dest_artists_dir.join(&name)));
+    }
+    let mut covers_exported = 0u32;
+    for (source, target) in covers.iter().filter(|(source, _)| source.is_file()) {
+        if let Some(files) = preview.as_deref_mut() {
+            let bytes = fs::metadata(source).map(|m| m.len()).unwrap_or(0);
+            bytes_copied += bytes;
+            files.push(SyncPlanFile {
+                action: SyncAction::Copy,
+                reason: SyncReason::New,
+                path: target
+                    .strip_prefix(&dest_jp3)
+                    .unwrap_or(target)
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+                song_id: None,
+                bytes,
+                transcode: false,
+            });
+        } else {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+            bytes_copied += copy_file(source, target)
+                .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+        }
+        if !target.starts_with(dest_albums_dir.join(THUMBS_DIR)) {
+            covers_exported += 1;
+        }
+    }
+
+    Ok(crate::models::ExportSelectionResult {
+        songs_exported: songs.len() as u32,
+        songs_skipped: selected.len() as u32 - songs.len() as u32,
+        playlists_exported: playlists.len() as u32,
+        songs_transcoded,
+        covers_exported,
+        bytes_copied,
+        songs_verified,
+        auto_playlists_exported: 0,
+    })
+}
+
+/// Copy library.bin and the playlists into a new backup in `jp3/backups`.
+#[tauri::command]
+pub fn backup_library(base_path: String) -> Result<crate::models::BackupInfo, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    create_backup(base)
+}
+
+/// List the library's backups, newest first.
+#[tauri::command]
+pub fn list_backups(base_path: String) -> Result<Vec<crate::models::BackupInfo>, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    list_backup_dirs(base)
+}
+
+/// Delete a backup.
+#[tauri::command]
+pub fn delete_backup(base_path: String, backup_id: String) -> Result<(), String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    delete_backup_dir(base, &backup_id)
+}
+
+/// How many backups are kept (None = all).
+#[tauri::command]
+pub fn get_backup_retention(base_path: String) -> Result<Option<u32>, String> {
+    Ok(load_library_settings(Path::new(&base_path))?.backup_retention)
+}
+
+/// Set how many backups are kept (None = all).
+///
+/// Older backups are pruned the next time one is taken.
+#[tauri::command]
+pub fn set_backup_retention(base_path: String, count: Option<u32>) -> Result<(), String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    if count == Some(0) {
+        return Err("At least one backup must be kept".to_string());
+    }
+    let mut settings = load_library_settings(base)?;
+    settings.backup_retention = count;
+    save_library_settings(base, &settings)
+}
+
+/// Restore library.bin and the playlists from a backup.
+///
+/// The current state is backed up first, so a restore can itself be undone.
+/// The restored library gets a new generation, so snapshots taken before
+/// the restore expire.
+#[tauri::command]
+pub fn restore_library(
+    base_path: String,
+    backup_id: String,
+) -> Result<crate::models::BackupInfo, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let library_bin_path = base.join(JP3_DIR).join(METADATA_DIR).join(LIBRARY_BIN);
+
+    // Refuse a damaged backup before touching anything
+    let mut backup_file = fs::File::open(backup_dir(base, &backup_id)?.join(LIBRARY_BIN))
+        .map_err(|e| format!("Failed to open backup library.bin: {}", e))?;
+    read_verified_header(&mut backup_file)?;
+    drop(backup_file);
+
+    let generation = next_generation(&library_bin_path);
+    let safety = create_backup_keeping(base, &[&backup_id])?;
+    let _marker = begin_library_write(&library_bin_path, "restore_library")?;
+    let restored = restore_backup(base, &backup_id)?;
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(&library_bin_path)
+        .map_err(|e| format!("Failed to open library.bin: {}", e))?;
+    write_generation(&mut file, generation)?;
+
+    log::info!(
+        "[restore_library] Restored backup {} (previous state saved as {})",
+        restored.id,
+        safety.id
+    );
+    Ok(restored)
+}
+
+/// Writes to library.bin that an earlier run started but never finished.
+///
+/// While any are reported, `load_library` fails with `INTERRUPTED_WRITE`;
+/// check on startup and offer `recover_interrupted_writes`.
+#[tauri::command]
+pub fn get_interrupted_writes(
+    base_path: String,
+) -> Result<crate::models::InterruptedWrites, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let library_bin_path = base.join(JP3_DIR).join(METADATA_DIR).join(LIBRARY_BIN);
+    Ok(crate::models::InterruptedWrites {
+        writes: interrupted_writes(base),
+        library_intact: library_bin_intact(&library_bin_path),
+    })
+}
+
+/// Recover from interrupted library.bin writes, then clear their markers.
+///
+/// With `backup_id` that backup is restored. Otherwise library.bin is kept
+/// if it still passes its checksum (the write finished, or never reached
+/// it), and rebuilt with `repair_library` if not.
+#[tauri::command]
+pub fn recover_interrupted_writes(
+    base_path: String,
+    backup_id: Option<String>,
+) -> Result<crate::models::WriteRecovery, String> {
+    use crate::models::WriteRecovery;
+
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let library_bin_path = base.join(JP3_DIR).join(METADATA_DIR).join(LIBRARY_BIN);
+
+    let recovery = match backup_id {
+        Some(backup_id) => {
+            restore_library(base_path.clone(), backup_id)?;
+            WriteRecovery::Restored
+        }
+        None if library_bin_intact(&library_bin_path) => WriteRecovery::Intact,
+        None => {
+            repair_library(base_path.clone())?;
+            WriteRecovery::Repaired
+        }
+    };
+
+    // A rewrite cut off before its rename leaves the new file behind
+    let _ = fs::remove_file(library_bin_path.with_extension("bin.tmp"));
+    let cleared = clear_interrupted_writes(base)?;
+    log::info!(
+        "[recover_interrupted_writes] {:?} after {} interrupted writes",
+        recovery,
+        cleared
+    );
+    Ok(recovery)
+}
+
+/// Whether library.bin has a valid header and matches its checksum.
+fn library_bin_intact(library_bin_path: &Path) -> bool {
+    fs::read(library_bin_path).is_ok_and(|data| {
+        LibraryHeader::from_bytes(&data)
+            .is_some_and(|header| verify_checksum(&data, &header).is_ok())
+    })
+}
+
+/// Check library.bin for damage without changing it.
+///
+/// Validates the header, table layout and checksum, that every string is
+/// UTF-8 and every ID points inside its table, and that active songs' audio
+/// files exist. Problems are reported rather than returned as errors; only
+/// a missing or unreadable library.bin fails.
+#[tauri::command]
+pub fn verify_library(
+    base_path: String,
+) -> Result<crate::models::LibraryVerificationReport, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let jp3_path = base.join(JP3_DIR);
+    let library_bin_path = jp3_path.join(METADATA_DIR).join(LIBRARY_BIN);
+    let data = fs::read(&library_bin_path)
+        .map_err(|e| format!("Failed to read library.bin: {}", e))?;
+
+    let mut report = crate::models::LibraryVerificationReport {
+        version: data.get(4..8).map(|b| u32::from_le_bytes(b.try_into().unwrap())),
+        ..Default::default()
+    };
+
+    let Some(header) = LibraryHeader::from_bytes(&data) else {
+        report.header_error = Some("Bad magic or header cut short".to_string());
+        return Ok(report);
+    };
+    if header.version == 0 || header.version > crate::models::LIBRARY_VERSION {
+        report.header_error = Some(format!(
+            "Unknown version {} (this app reads up to {})",
+            header.version,
+            crate::models::LIBRARY_VERSION
+        ));
+        return Ok(report);
+    }
+    report.checksum_valid =
+        header.version < CHECKSUM_VERSION || tables_checksum(&data, &header) == header.checksum;
+
+    // Tables in file order; each must start after the header and the
+    // previous table, and end within the file
+    let song_size = SongEntry::size_for(header.version);
+    let song_len = match header.song_count {
+        0 => 0,
+        // Older files may end right after the flags byte of the last song
+        n if header.version < GENRES_VERSION => (n - 1) * song_size + 21,
+        n => n * song_size,
+    };
+    let mut tables = vec![
+        ("string", header.string_table_offset, header.string_table_len),
+        ("artist", header.artist_table_offset, header.artist_count * ArtistEntry::SIZE),
+        ("album", header.album_table_offset, header.album_count * AlbumEntry::SIZE),
+        ("song", header.song_table_offset, song_len),
+    ];
+    if header.version >= GENRES_VERSION {
+        tables.push(("genre", header.genre_table_offset, header.genre_count * GenreEntry::SIZE));
+    }
+    let mut previous: Option<(&str, u64)> = None;
+    for (name, offset, len) in tables {
+        let end = offset as u64 + len as u64;
+        if offset < header.size() {
+            report.layout_errors.push(format!("{} table starts inside the header", name));
+        }
+        if let Some((prev_name, prev_end)) = previous {
+            if (offset as u64) < prev_end {
+                report
+                    .layout_errors
+                    .push(format!("{} table overlaps the {} table", name, prev_name));
+            }
+        }
+        if end > data.len() as u64 {
+            report
+                .layout_errors
+                .push(format!("{} table extends past the end of the file", name));
+        }
+        previous = Some((name, end));
+    }
+    if !report.layout_errors.is_empty() {
+        return Ok(report);
+    }
+
+    let (strings, string_table_error) = walk_string_table(
+        &data,
+        header.string_table_offset as usize,
+        header.string_table_end() as usize,
+    );
+    report.string_table_error = string_table_error;
+    report.invalid_utf8_string_ids = strings
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.is_none())
+        .map(|(id, _)| id as u32)
+        .collect();
+
+    let raw_artists = parse_artist_table(
+        &data,
+        header.artist_table_offset as usize,
+        header.artist_count as usize,
+        header.version,
+    )?;
+    let raw_albums = parse_album_table(
+        &data,
+        header.album_table_offset as usize,
+        header.album_count as usize,
+        header.version,
+    )?;
+    let raw_songs = parse_song_table(
+        &data,
+        header.song_table_offset as usize,
+        header.song_count as usize,
+        header.version,
+    )?;
+    let raw_genres = parse_genre_table(
+        &data,
+        header.genre_table_offset as usize,
+        header.genre_count as usize,
+    )?;
+
+    let string_count = strings.len() as u32;
+    let mut check = |table, entry_id: usize, field, value: u32, limit: u32| {
+        if value >= limit {
+            report.bad_references.push(crate::models::BadReference {
+                table,
+                entry_id: entry_id as u32,
+                field,
+                value,
+            });
+        }
+    };
+    for (id, artist) in raw_artists.iter().enumerate() {
+        check("artist", id, "nameStringId", artist.name_string_id, string_count);
+        if let Some(sort_id) = artist.sort_name_string_id {
+            check("artist", id, "sortNameStringId", sort_id, string_count);
+        }
+    }
+    for (id, album) in raw_albums.iter().enumerate() {
+        check("album", id, "nameStringId", album.name_string_id, string_count);
+        if let Some(sort_id) = album.sort_name_string_id {
+            check("album", id, "sortNameStringId", sort_id, string_count);
+        }
+        check("album", id, "albumArtistId", album.album_artist_id, header.artist_count);
+    }
+    for (id, song) in raw_songs.iter().enumerate() {
+        check("song", id, "titleStringId", song.title_string_id, string_count);
+        check("song", id, "pathStringId", song.path_string_id, string_count);
+        check("song", id, "artistId", song.artist_id, header.artist_count);
+        check("song", id, "albumId", song.album_id, header.album_count);
+        if song.genre_id != NO_GENRE {
+            check("song", id, "genreId", song.genre_id as u32, header.genre_count);
+        }
+    }
+    for (id, &name_string_id) in raw_genres.iter().enumerate() {
+        check("genre", id, "nameStringId", name_string_id, string_count);
+    }
+
+    let music_path = jp3_path.join(MUSIC_DIR);
+    report.missing_file_song_ids = raw_songs
+        .iter()
+        .enumerate()
+        .filter(|(_, song)| song.flags & song_flags::DELETED == 0)
+        .filter_map(|(id, song)| {
+            let path = strings.get(song.path_string_id as usize)?.as_ref()?;
+            (!music_path.join(path).exists()).then_some(id as u32)
+        })
+        .collect();
+
+    // Files that changed since they were saved (songs without a hash are skipped)
+    let hash_index = load_song_hashes(base)?;
+    report.hash_mismatch_song_ids = raw_songs
+        .iter()
+        .enumerate()
+        .filter(|(id, song)| {
+            song.flags & song_flags::DELETED == 0
+                && !report.missing_file_song_ids.contains(&(*id as u32))
+        })
+        .filter_map(|(id, song)| {
+            let path = strings.get(song.path_string_id as usize)?.as_ref()?;
+            let recorded = hash_index.songs.get(path)?;
+            let actual = hash_file(&music_path.join(path)).ok();
+            (actual.as_ref() != Some(recorded)).then_some(id as u32)
+        })
+        .collect();
+
+    Ok(report)
+}
+
+/// Hash the audio files of active songs that have no recorded hash.
+///
+/// Songs saved before hashes were recorded are skipped by the duplicate
+/// check and `verify_library` until this runs. Returns the number hashed.
+#[tauri::command]
+pub fn backfill_song_hashes(base_path: String) -> Result<u32, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let jp3_path = base.join(JP3_DIR);
+    let music_path = jp3_path.join(MUSIC_DIR);
+    let Some(library) = load_existing_library_data(&jp3_path.join(METADATA_DIR).join(LIBRARY_BIN))?
+    else {
+        return Err("Library not found".to_string());
+    };
+
+    let hash_index = load_song_hashes(base)?;
+    let unhashed: Vec<&str> = library
+        .songs
+        .iter()
+        .filter(|song| song.flags & song_flags::DELETED == 0)
+        .filter_map(|song| library.string_table.get(song.path_string_id))
+        .filter(|path| !hash_index.songs.contains_key(*path))
+        .collect();
+    let hashes: Vec<(String, String)> = map_ordered(
+        &unhashed,
+        |path| hash_file(&music_path.join(path)).ok(),
+        |_, _| {},
+    )
+    .into_iter()
+    .zip(&unhashed)
+    .filter_map(|(hash, path)| Some((path.to_string(), hash?)))
+    .collect();
+
+    let hashed = hashes.len() as u32;
+    record_song_hashes(base, hashes)?;
+    Ok(hashed)
+}
+
+/// Inspect a song's audio file for the technical details panel.
+///
+/// Everything is read from the file on disk (container from the header,
+/// codec parameters, size and hash), so it reflects what the device will
+/// actually play even if the library's metadata disagrees.
+#[tauri::command]
+pub fn get_song_file_info(
+    base_path: String,
+    song_id: u32,
+) -> Result<crate::models::SongFileInfo, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let jp3_path = base.join(JP3_DIR);
+    let Some(library) = load_existing_library_data(&jp3_path.join(METADATA_DIR).join(LIBRARY_BIN))?
+    else {
+        return Err("Library not found".to_string());
+    };
+
+    let song = library
+        .songs
+        .get(song_id as usize)
+        .filter(|song| song.flags & song_flags::DELETED == 0)
+        .ok_or_else(|| format!("Song {} not found", song_id))?;
+    let path = library
+        .string_table
+        .get(song.path_string_id)
+        .ok_or_else(|| format!("Song {} has no file path", song_id))?
+        .to_string();
+
+    let file_path = jp3_path.join(MUSIC_DIR).join(&path);
+    let size_bytes = fs::metadata(&file_path)
+        .map_err(|e| format!("Audio file for song {} is missing: {}", song_id, e))?
+        .len();
+    let container = sniff_audio_format(&file_path)?;
+    let stream = read_stream_info(&file_path, container)?;
+    let sha256 = hash_file(&file_path)?;
+    let hash_matches_library = load_song_hashes(base)?
+        .songs
+        .get(&path)
+        .map(|recorded| *recorded == sha256);
+
+    Ok(crate::models::SongFileInfo {
+        song_id,
+        path,
+        container,
+        stream,
+        size_bytes,
+        sha256,
+        hash_matches_library,
+    })
+}
+
+/// Encode a short MP3 clip of a song, for sharing "is this the right
+/// version?" snippets without sending the whole file.
+///
+/// The clip runs `duration` seconds (default 30, at most 60, cut at the end
+/// of the song) from `start` seconds in. `dest` is the clip's file path,
+/// or a folder to write `Artist - Title (preview).mp3` into.
+#[tauri::command]
+pub async fn export_preview_clip(
+    base_path: String,
+    song_id: u32,
+    start: Option<f32>,
+    duration: Option<f32>,
+    dest: String,
+) -> Result<crate::models::PreviewClip, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let library = load_library(base_path.clone(), None)?;
+    let song = library
+        .songs
+        .iter()
+        .find(|song| song.id == song_id)
+        .ok_or_else(|| format!("Song {} not found", song_id))?;
+    let (start, duration) = clip_range(
+        start.unwrap_or(0.0),
+        duration.unwrap_or(DEFAULT_CLIP_SECS),
+        song.duration_sec,
+    )?;
+
+    let dest = Path::new(&dest);
+    let dest = if dest.is_dir() {
+        let stem = format!("{} - {} (preview)", song.artist_name, song.title);
+        dest.join(format!("{}.mp3", fat_safe_stem(&stem)))
+    } else {
+        if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        dest.to_path_buf()
+    };
+
+    let source = base.join(JP3_DIR).join(MUSIC_DIR).join(&song.path);
+    encode_clip(&source, &dest, start, duration)?;
+    let size_bytes = fs::metadata(&dest).map(|m| m.len()).unwrap_or(0);
+
+    Ok(crate::models::PreviewClip {
+        path: dest.to_string_lossy().to_string(),
+        start_secs: start,
+        duration_secs: duration,
+        size_bytes,
+    })
+}
+
+/// Get library statistics including deleted song count.
+///
+/// Use this to determine if compaction is needed.
+#[tauri::command]
+pub fn get_library_stats(base_path: String) -> Result<crate::models::LibraryStats, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let jp3_path = base.join(JP3_DIR);
+    let metadata_path = jp3_path.join(METADATA_DIR);
     let library_bin_path = metadata_path.join(LIBRARY_BIN);
 
     if !library_bin_path.exists() {
@@ -870,7 +4084,7 @@ pub fn get_library_stats(base_path: String) -> Result<crate::models::LibraryStat
     let strings = parse_string_table(
         &data,
         header.string_table_offset as usize,
-        header.artist_table_offset as usize,
+        header.string_table_end() as usize,
     )?;
 
     // Parse songs to count active vs deleted
@@ -878,6 +4092,7 @@ pub fn get_library_stats(base_path: String) -> Result<crate::models::LibraryStat
         &data,
         header.song_table_offset as usize,
         header.song_count as usize,
+        header.version,
     )?;
 
     let deleted_songs = raw_songs
@@ -886,12 +4101,106 @@ pub fn get_library_stats(base_path: String) -> Result<crate::models::LibraryStat
         .count() as u32;
     let active_songs = header.song_count - deleted_songs;
 
+    let raw_artists = parse_artist_table(
+        &data,
+        header.artist_table_offset as usize,
+        header.artist_count as usize,
+        header.version,
+    )?;
+    let raw_albums = parse_album_table(
+        &data,
+        header.album_table_offset as usize,
+        header.album_count as usize,
+        header.version,
+    )?;
+
+    // Count scripts over display strings only (not paths or sort names)
+    let display_string_ids: HashSet<u32> = raw_songs
+        .iter()
+        .filter(|s| s.flags & song_flags::DELETED == 0)
+        .map(|s| s.title_string_id)
+        .chain(raw_artists.iter().map(|a| a.name_string_id))
+        .chain(raw_albums.iter().map(|a| a.name_string_id))
+        .collect();
+    let mut script_counts: HashMap<Script, u32> = HashMap::new();
+    for id in display_string_ids {
+        if let Some(script) = strings.get(id as usize).and_then(|s| detect_script(s)) {
+            *script_counts.entry(script).or_default() += 1;
+        }
+    }
+    let mut script_distribution: Vec<ScriptCount> = script_counts
+        .into_iter()
+        .map(|(script, count)| ScriptCount { script, count })
+        .collect();
+    script_distribution.sort_by(|a, b| b.count.cmp(&a.count).then(a.script.cmp(&b.script)));
+
     let deleted_percentage = if header.song_count > 0 {
         (deleted_songs as f32 / header.song_count as f32) * 100.0
     } else {
         0.0
     };
 
+    // Space a compaction would reclaim: the same entries and strings it drops
+    let raw_genres = parse_genre_table(
+        &data,
+        header.genre_table_offset as usize,
+        header.genre_count as usize,
+    )?;
+    let kept_songs: Vec<&RawSong> = raw_songs
+        .iter()
+        .filter(|s| s.flags & song_flags::DELETED == 0)
+        .collect();
+    let used_album_ids: HashSet<u32> = kept_songs.iter().map(|s| s.album_id).collect();
+    let used_artist_ids: HashSet<u32> = kept_songs
+        .iter()
+        .map(|s| s.artist_id)
+        .chain(
+            used_album_ids
+                .iter()
+                .filter_map(|&id| raw_albums.get(id as usize))
+                .map(|album| album.album_artist_id),
+        )
+        .collect();
+    let used_genre_ids: HashSet<u16> = kept_songs.iter().map(|s| s.genre_id).collect();
+
+    let live_string_ids: HashSet<u32> = kept_songs
+        .iter()
+        .flat_map(|s| [s.title_string_id, s.path_string_id])
+        .chain(
+            used_artist_ids
+                .iter()
+                .filter_map(|&id| raw_artists.get(id as usize))
+                .flat_map(|a| std::iter::once(a.name_string_id).chain(a.sort_name_string_id)),
+        )
+        .chain(
+            used_album_ids
+                .iter()
+                .filter_map(|&id| raw_albums.get(id as usize))
+                .flat_map(|a| std::iter::once(a.name_string_id).chain(a.sort_name_string_id)),
+        )
+        .chain(
+            used_genre_ids
+                .iter()
+                .filter_map(|&id| raw_genres.get(id as usize).copied()),
+        )
+        .collect();
+    let dead_string_bytes: u64 = strings
+        .iter()
+        .enumerate()
+        .filter(|(id, _)| !live_string_ids.contains(&(*id as u32)))
+        .map(|(_, s)| 2 + s.len() as u64)
+        .sum();
+
+    let orphaned_artists = (raw_artists.len() - used_artist_ids.len()) as u64;
+    let orphaned_albums = (raw_albums.len() - used_album_ids.len()) as u64;
+    let wasted_bytes = deleted_songs as u64 * SongEntry::size_for(header.version) as u64
+        + orphaned_artists * ArtistEntry::SIZE as u64
+        + orphaned_albums * AlbumEntry::SIZE as u64
+        + dead_string_bytes;
+
+    let thresholds = load_library_settings(base)?.compaction_thresholds;
+    let compact_reason = thresholds.evaluate(deleted_percentage, dead_string_bytes, wasted_bytes);
+
     Ok(crate::models::LibraryStats {
         total_songs: header.song_count,
         active_songs,
@@ -900,11 +4209,34 @@ pub fn get_library_stats(base_path: String) -> Result<crate::models::LibraryStat
         total_albums: header.album_count,
         total_strings: strings.len() as u32,
         deleted_percentage,
-        should_compact: deleted_percentage > 20.0,
+        dead_string_bytes,
+        wasted_bytes,
+        should_compact: compact_reason.is_some(),
+        compact_reason,
         file_size_bytes,
+        script_distribution,
     })
 }
 
+/// Get the thresholds `get_library_stats` uses to recommend compacting.
+#[tauri::command]
+pub fn get_compaction_thresholds(base_path: String) -> Result<CompactionThresholds, String> {
+    Ok(load_library_settings(Path::new(&base_path))?.compaction_thresholds)
+}
+
+/// Set the thresholds `get_library_stats` uses to recommend compacting.
+#[tauri::command]
+pub fn set_compaction_thresholds(
+    base_path: String,
+    thresholds: CompactionThresholds,
+) -> Result<(), String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let mut settings = load_library_settings(base)?;
+    settings.compaction_thresholds = thresholds;
+    save_library_settings(base, &settings)
+}
+
 /// Compact the library by removing deleted entries and orphaned data.
 ///
 /// This rebuilds the entire library.bin, removing:
@@ -914,9 +4246,106 @@ pub fn get_library_stats(base_path: String) -> Result<crate::models::LibraryStat
 /// - Strings not referenced by any active entry
 ///
 /// This is a full rewrite operation - use sparingly to minimize SD card wear.
+/// library.bin and the playlists are backed up first (see `backup_library`).
+///
+/// With `renumber_files`, audio files are also renumbered from `00/001`,
+/// closing the gaps deleted songs leave and merging underfilled buckets.
+/// That moves every file after the first gap, so it is opt-in.
+///
+/// With `dry_run`, nothing is written: the result gives what would be
+/// removed, the new library.bin size and the files that would move, to
+/// judge whether the SD card writes are worth it.
+///
+/// Fails if another compaction (or idle-time step) is running.
 #[tauri::command]
-pub fn compact_library(base_path: String) -> Result<crate::models::CompactResult, String> {
-    let base = Path::new(&base_path);
+pub fn compact_library(
+    control: State<'_, CompactionControl>,
+    base_path: String,
+    renumber_files: Option<bool>,
+    dry_run: Option<bool>,
+) -> Result<crate::models::CompactResult, String> {
+    let guard = control.begin()?;
+    run_compaction(
+        &base_path,
+        renumber_files.unwrap_or(false),
+        dry_run.unwrap_or(false),
+        &guard,
+        |_| {},
+    )
+}
+
+/// Event emitted as `compact_library_with_progress` advances
+pub const EVENT_COMPACTION_PROGRESS: &str = "library://compaction-progress";
+
+/// Compact the library, reporting progress as `library://compaction-progress`
+/// events.
+///
+/// Can be stopped with `cancel_compaction` up until the new library.bin is
+/// renamed into place; a cancelled compaction returns an error starting
+/// with `COMPACTION_CANCELLED` and leaves the library exactly as it was.
+///
+/// Runs on a blocking thread so the async runtime stays responsive.
+#[tauri::command]
+pub async fn compact_library_with_progress(
+    app: AppHandle,
+    base_path: String,
+    renumber_files: Option<bool>,
+) -> Result<crate::models::CompactResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let control = app.state::<CompactionControl>();
+        let guard = control.begin()?;
+        run_compaction(
+            &base_path,
+            renumber_files.unwrap_or(false),
+            false,
+            &guard,
+            |progress| {
+                if let Err(e) = app.emit(EVENT_COMPACTION_PROGRESS, progress) {
+                    log::warn!("Failed to emit {}: {}", EVENT_COMPACTION_PROGRESS, e);
+                }
+            },
+        )
+    })
+    .await
+    .map_err(|e| format!("Compaction task failed: {}", e))?
+}
+
+/// Ask a running `compact_library_with_progress` to stop.
+///
+/// Returns false if no compaction is running.
+#[tauri::command]
+pub fn cancel_compaction(control: State<'_, CompactionControl>) -> Result<bool, String> {
+    Ok(control.cancel())
+}
+
+/// Compaction shared by `compact_library` and `compact_library_with_progress`.
+///
+/// Callers hold the `CompactionGuard`, so only one compaction runs at a
+/// time. Song strings are re-interned in chunks on worker threads, and the
+/// guard's cancel flag is checked between steps. The rebuilt library is written to a temporary
+/// file and renamed over library.bin, so until that rename nothing on disk
+/// changes; audio files and playlists are only touched afterwards.
+///
+/// `renumber_files` also lays the audio files out again (see
+/// `compact_library`); renumbered paths are written into the new string
+/// table and the files moved once library.bin is in place.
+///
+/// `dry_run` stops before the backup and write, returning what the
+/// compaction would do.
+pub fn run_compaction<F>(
+    base_path: &str,
+    renumber_files: bool,
+    dry_run: bool,
+    compaction: &CompactionGuard<'_>,
+    on_progress: F,
+) -> Result<crate::models::CompactResult, String>
+where
+    F: Fn(CompactProgress) + Sync,
+{
+    let cancel = compaction.cancel_flag();
+    let base = Path::new(base_path);
+    ensure_library_available(base)?;
+    let _awake = (!dry_run).then(|| keep_awake("Compacting the library"));
     let jp3_path = base.join(JP3_DIR);
     let metadata_path = jp3_path.join(METADATA_DIR);
     let music_path = jp3_path.join(MUSIC_DIR);
@@ -926,6 +4355,12 @@ pub fn compact_library(base_path: String) -> Result<crate::models::CompactResult
         return Err("Library not found".to_string());
     }
 
+    // Compaction drops deleted songs and their audio files for good
+    if !dry_run {
+        let backup = create_backup(base)?;
+        log::info!("[compact_library] Backed up library as {}", backup.id);
+    }
+
     let old_size_bytes = fs::metadata(&library_bin_path)
         .map(|m| m.len())
         .unwrap_or(0);
@@ -938,30 +4373,40 @@ pub fn compact_library(base_path: String) -> Result<crate::models::CompactResult
         .map_err(|e| format!("Failed to read library.bin: {}", e))?;
 
     let header = LibraryHeader::from_bytes(&data).ok_or("Invalid library.bin header")?;
+    verify_checksum(&data, &header)?;
 
     // Parse all data
     let old_strings = parse_string_table(
         &data,
         header.string_table_offset as usize,
-        header.artist_table_offset as usize,
+        header.string_table_end() as usize,
     )?;
 
     let old_artists = parse_artist_table(
         &data,
         header.artist_table_offset as usize,
         header.artist_count as usize,
+        header.version,
     )?;
 
     let old_albums = parse_album_table(
         &data,
         header.album_table_offset as usize,
         header.album_count as usize,
+        header.version,
     )?;
 
     let old_songs = parse_song_table(
         &data,
         header.song_table_offset as usize,
         header.song_count as usize,
+        header.version,
+    )?;
+
+    let old_genres = parse_genre_table(
+        &data,
+        header.genre_table_offset as usize,
+        header.genre_count as usize,
     )?;
 
     // Count what we're removing
@@ -970,143 +4415,523 @@ pub fn compact_library(base_path: String) -> Result<crate::models::CompactResult
         .filter(|s| s.flags & song_flags::DELETED != 0)
         .count() as u32;
 
-    // Filter to only active songs
-    let active_songs: Vec<_> = old_songs
+    // Filter to only active songs, keeping their old IDs
+    let active_songs: Vec<(u32, &RawSong)> = old_songs
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.flags & song_flags::DELETED == 0)
+        .map(|(old_id, s)| (old_id as u32, s))
+        .collect();
+    check_cancelled(cancel)?;
+
+    // Old path -> new path for files that move when renumbering
+    let renamed: HashMap<String, String> = if renumber_files {
+        renumbered_paths(
+            active_songs
+                .iter()
+                .filter_map(|(_, s)| old_strings.get(s.path_string_id as usize))
+                .filter(|path| !is_adopted_path(path)),
+            &load_library_settings(base)?.bucket_layout,
+        )
+    } else {
+        HashMap::new()
+    };
+
+    // Find which artists and albums are still referenced (album artists
+    // of compilations may have no songs of their own)
+    let used_album_ids: HashSet<u32> = active_songs.iter().map(|(_, s)| s.album_id).collect();
+    let used_artist_ids: HashSet<u32> = active_songs
+        .iter()
+        .map(|(_, s)| s.artist_id)
+        .chain(
+            used_album_ids
+                .iter()
+                .filter_map(|&id| old_albums.get(id as usize))
+                .map(|album| album.album_artist_id),
+        )
+        .collect();
+
+    // Build new tables with fresh IDs
+    let mut new_string_table = StringTable::new();
+    let mut new_artists: Vec<ArtistEntry> = Vec::new();
+    let mut new_albums: Vec<AlbumEntry> = Vec::new();
+    let mut new_songs: Vec<SongEntry> = Vec::new();
+    let mut new_genres: Vec<GenreEntry> = Vec::new();
+
+    // Map old IDs to new IDs
+    let mut artist_id_map: HashMap<u32, u32> = HashMap::new();
+    let mut album_id_map: HashMap<u32, u32> = HashMap::new();
+
+    // Rebuild artists (only those still used), renumbered in sort order so
+    // the device can list them by walking the table
+    let mut kept_artists: Vec<(u32, String)> = old_artists
+        .iter()
+        .enumerate()
+        .filter(|(old_id, _)| used_artist_ids.contains(&(*old_id as u32)))
+        .map(|(old_id, artist)| {
+            let name = old_strings
+                .get(artist.name_string_id as usize)
+                .cloned()
+                .unwrap_or_default();
+            (old_id as u32, name)
+        })
+        .collect();
+    kept_artists.sort_by(|a, b| compare_names(&a.1, &b.1));
+    for (old_id, name) in kept_artists {
+        let new_id = new_artists.len() as u32;
+        new_artists.push(new_artist_entry(&mut new_string_table, &name));
+        artist_id_map.insert(old_id, new_id);
+    }
+
+    // Rebuild albums (only those still used, with remapped artist IDs), also in sort order
+    let mut kept_albums: Vec<(u32, String)> = old_albums
+        .iter()
+        .enumerate()
+        .filter(|(old_id, _)| used_album_ids.contains(&(*old_id as u32)))
+        .map(|(old_id, album)| {
+            let name = old_strings
+                .get(album.name_string_id as usize)
+                .cloned()
+                .unwrap_or_default();
+            (old_id as u32, name)
+        })
+        .collect();
+    kept_albums.sort_by(|a, b| compare_names(&a.1, &b.1));
+    for (old_id, name) in kept_albums {
+        let album = &old_albums[old_id as usize];
+        let new_id = new_albums.len() as u32;
+        let new_artist_id = *artist_id_map.get(&album.album_artist_id).unwrap_or(&0);
+        new_albums.push(new_album_entry(
+            &mut new_string_table,
+            &name,
+            new_artist_id,
+            album.year,
+        ));
+        album_id_map.insert(old_id, new_id);
+    }
+
+    // Rebuild genres (only those still used), in name order
+    let mut kept_genres: Vec<(u16, String)> = active_songs
+        .iter()
+        .map(|(_, s)| s.genre_id)
+        .collect::<HashSet<u16>>()
+        .into_iter()
+        .filter_map(|old_id| {
+            let name_string_id = *old_genres.get(old_id as usize)?;
+            Some((old_id, old_strings.get(name_string_id as usize)?.clone()))
+        })
+        .collect();
+    kept_genres.sort_by(|a, b| compare_names(&a.1, &b.1));
+    let mut genre_id_map: HashMap<u16, u16> = HashMap::new();
+    for (old_id, name) in kept_genres {
+        genre_id_map.insert(old_id, new_genres.len() as u16);
+        new_genres.push(GenreEntry {
+            name_string_id: new_string_table.add(&name),
+        });
+    }
+
+    check_cancelled(cancel)?;
+
+    // Re-intern song titles and paths chunk by chunk: each chunk's strings
+    // are deduplicated on a worker, then merged in order so string IDs match
+    // a sequential rebuild. Chunks still queued once cancelled are skipped.
+    let chunks: Vec<&[(u32, &RawSong)]> = active_songs.chunks(COMPACT_CHUNK_SIZE).collect();
+    let total_chunks = chunks.len() as u32;
+    let chunks_interned = AtomicU32::new(0);
+    let chunk_strings = map_ordered(
+        &chunks,
+        |chunk| {
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            Some(intern_chunk(chunk.iter().flat_map(|(_, song)| {
+                let string = |id: u32| {
+                    old_strings
+                        .get(id as usize)
+                        .map(String::as_str)
+                        .unwrap_or_default()
+                };
+                let path = string(song.path_string_id);
+                [
+                    string(song.title_string_id),
+                    renamed.get(path).map_or(path, String::as_str),
+                ]
+            })))
+        },
+        |_, _| {
+            let done = chunks_interned.fetch_add(1, Ordering::Relaxed) + 1;
+            on_progress(CompactProgress::new(CompactPhase::Strings, done, total_chunks));
+        },
+    );
+    check_cancelled(cancel)?;
+
+    // Rebuild songs with remapped IDs
+    // Also build a map from old song IDs to new song IDs for playlist remapping
+    // AND collect paths that are still in use by active songs
+    let mut song_id_map: HashMap<u32, u32> = HashMap::new();
+    let mut active_paths: HashSet<u32> = HashSet::new(); // path_string_ids still in use
+    let chunk_strings = chunk_strings.into_iter().flatten();
+    for (index, (chunk, strings)) in chunks.iter().zip(chunk_strings).enumerate() {
+        check_cancelled(cancel)?;
+        let string_ids = strings.merge_into(&mut new_string_table);
+
+        for (&(old_id, song), ids) in chunk.iter().zip(string_ids.chunks(2)) {
+            // Track this path as still in use
+            active_paths.insert(song.path_string_id);
+
+            let new_artist_id = *artist_id_map.get(&song.artist_id).unwrap_or(&0);
+            let new_album_id = *album_id_map.get(&song.album_id).unwrap_or(&0);
+
+            let new_song_id = new_songs.len() as u32;
+            song_id_map.insert(old_id, new_song_id);
+
+            new_songs.push(SongEntry::new(
+                ids[0],
+                new_artist_id,
+                new_album_id,
+                ids[1],
+                song.track_number,
+                song.duration_sec,
+                *genre_id_map.get(&song.genre_id).unwrap_or(&NO_GENRE),
+            )
+            .with_metadata_source(song.metadata_source)
+            .with_disc_number(song.disc_number)
+            .with_rating(song.rating)
+            .with_favorite(song.flags & song_flags::FAVORITE != 0)
+            .with_date_added(song.date_added)
+            .with_play_count(song.play_count));
+        }
+        on_progress(CompactProgress::new(CompactPhase::Songs, index as u32 + 1, total_chunks));
+    }
+
+    // Calculate removed counts
+    let artists_removed = header.artist_count - new_artists.len() as u32;
+    let albums_removed = header.album_count - new_albums.len() as u32;
+    let strings_removed = old_strings.len() as u32 - new_string_table.len() as u32;
+
+    let tables = serialize_tables(
+        &new_string_table,
+        &new_artists,
+        &new_albums,
+        &new_songs,
+        &new_genres,
+    );
+
+    if dry_run {
+        let new_size_bytes = library_image(&library_bin_path, &tables)?.len() as u64;
+        log::info!(
+            "[compact_library] Dry run: would remove {} songs, {} artists, {} albums, {} strings",
+            songs_removed,
+            artists_removed,
+            albums_removed,
+            strings_removed
+        );
+        return Ok(crate::models::CompactResult {
+            songs_removed,
+            artists_removed,
+            albums_removed,
+            strings_removed,
+            playlists_updated: playlists_to_remap(&jp3_path.join(PLAYLISTS_DIR), &song_id_map),
+            old_size_bytes,
+            new_size_bytes,
+            bytes_saved: old_size_bytes.saturating_sub(new_size_bytes),
+            files_renumbered: renamed.len() as u32,
+            buckets_removed: 0,
+            dry_run: true,
+        });
+    }
+
+    // Last chance to cancel: nothing on disk has changed yet
+    check_cancelled(cancel)?;
+    on_progress(CompactProgress::new(CompactPhase::Writing, 0, 1));
+
+    // Write new library.bin
+    rewrite_library_bin(&library_bin_path, &tables)?;
+    on_progress(CompactProgress::new(CompactPhase::Writing, 1, 1));
+
+    let new_size_bytes = fs::metadata(&library_bin_path)
+        .map(|m| m.len())
+        .unwrap_or(0);
+
+    // Trash audio files of deleted songs ONLY if no active song uses the same path
+    // This handles the case where edit_song_metadata marks old entry as deleted
+    // but creates a new entry with the same audio file path
+    let mut to_trash = Vec::new();
+    for song in &old_songs {
+        if song.flags & song_flags::DELETED != 0 {
+            // Only trash if this path is NOT used by any active song
+            if !active_paths.contains(&song.path_string_id) {
+                if let Some(path_str) = old_strings
+                    .get(song.path_string_id as usize)
+                    .filter(|path| !is_adopted_path(path))
+                {
+                    let title = old_strings.get(song.title_string_id as usize).cloned();
+                    to_trash.push((path_str.clone(), title));
+                }
+            }
+        }
+    }
+    // Moving aside, like delete_songs, so a file dropped by mistake can be restored
+    if let Err(e) = move_to_trash(base, &to_trash) {
+        log::warn!("[compact_library] Failed to trash removed audio: {}", e);
+    }
+
+    let (files_renumbered, buckets_removed) =
+        move_renumbered_files(base, &music_path, &renamed, |done, total| {
+            on_progress(CompactProgress::new(CompactPhase::Files, done, total));
+        });
+
+
+    // Remap song IDs in all playlists
+    // This removes orphaned IDs (deleted songs) and updates IDs to new values
+    let playlists_updated =
+        remap_playlists(&jp3_path.join(PLAYLISTS_DIR), &song_id_map, |done, total| {
+            on_progress(CompactProgress::new(CompactPhase::Playlists, done, total));
+        });
+
+    log::info!(
+        "[compact_library] Removed {} songs, {} artists, {} albums. Updated {} playlists.",
+        songs_removed,
+        artists_removed,
+        albums_removed,
+        playlists_updated
+    );
+
+    // Confidence is only kept for songs that still exist (best effort)
+    let active_path_strings: HashSet<&str> = active_songs
         .iter()
-        .filter(|s| s.flags & song_flags::DELETED == 0)
+        .filter_map(|(_, s)| old_strings.get(s.path_string_id as usize))
+        .map(String::as_str)
         .collect();
+    if let Err(e) = retain_song_confidence(base, |path| active_path_strings.contains(path)) {
+        log::warn!("Failed to prune song confidence: {}", e);
+    }
+    if let Err(e) = retain_song_hashes(base, |path| active_path_strings.contains(path)) {
+        log::warn!("Failed to prune song hashes: {}", e);
+    }
+    if let Err(e) = retain_replay_gain(base, |path| active_path_strings.contains(path)) {
+        log::warn!("Failed to prune replay gain: {}", e);
+    }
+    if !renamed.is_empty() {
+        // Stale fingerprints would otherwise land on songs renumbered into their paths
+        let renames = [
+            retain_fingerprints(base, |path| active_path_strings.contains(path))
+                .and_then(|()| rename_fingerprints(base, &renamed)),
+            rename_song_confidence(base, &renamed),
+            rename_song_hashes(base, &renamed),
+            rename_replay_gain(base, &renamed),
+        ];
+        for e in renames.into_iter().filter_map(Result::err) {
+            log::warn!("Failed to follow renumbered files: {}", e);
+        }
+    }
 
-    // Find which artists and albums are still referenced
-    let used_artist_ids: HashSet<u32> = active_songs.iter().map(|s| s.artist_id).collect();
-    let used_album_ids: HashSet<u32> = active_songs.iter().map(|s| s.album_id).collect();
+    // Note: Cover art files are now named using artist+album hash (not album ID),
+    // so they don't need to be renamed when album IDs change during compaction.
+    // Old ID-based cover files no longer match their IDs, so record the
+    // compaction for `migrate_legacy_covers`.
+    let recorded = library_generation(base).and_then(|generation| {
+        let mut settings = load_library_settings(base)?;
+        settings.compacted_generation = Some(generation);
+        save_library_settings(base, &settings)
+    });
+    if let Err(e) = recorded {
+        log::warn!("Failed to record compaction: {}", e);
+    }
 
-    // Build new tables with fresh IDs
-    let mut new_string_table = StringTable::new();
-    let mut new_artists: Vec<ArtistEntry> = Vec::new();
-    let mut new_albums: Vec<AlbumEntry> = Vec::new();
-    let mut new_songs: Vec<SongEntry> = Vec::new();
+    announce_change(EVENT_LIBRARY_COMPACTED, base, Vec::new());
 
-    // Map old IDs to new IDs
-    let mut artist_id_map: HashMap<u32, u32> = HashMap::new();
-    let mut album_id_map: HashMap<u32, u32> = HashMap::new();
+    Ok(crate::models::CompactResult {
+        songs_removed,
+        artists_removed,
+        albums_removed,
+        strings_removed,
+        playlists_updated,
+        old_size_bytes,
+        new_size_bytes,
+        bytes_saved: old_size_bytes.saturating_sub(new_size_bytes),
+        files_renumbered,
+        buckets_removed,
+        dry_run: false,
+    })
+}
 
-    // Rebuild artists (only those still used)
-    for (old_id, artist) in old_artists.iter().enumerate() {
-        if used_artist_ids.contains(&(old_id as u32)) {
-            let new_id = new_artists.len() as u32;
-            let name = old_strings
-                .get(artist.name_string_id as usize)
-                .cloned()
-                .unwrap_or_default();
-            let name_string_id = new_string_table.add(&name);
-            new_artists.push(ArtistEntry { name_string_id });
-            artist_id_map.insert(old_id as u32, new_id);
+/// New paths for `paths` laid out from `00/001` in order, as
+/// `save_to_library` would have numbered them. Files named after their song
+/// (see `file_naming_service`) keep their name and only change bucket. Only
+/// paths that change are returned; each keeps its extension.
+fn renumbered_paths<'a>(
+    paths: impl Iterator<Item = &'a String>,
+    layout: &BucketLayout,
+) -> HashMap<String, String> {
+    let per_bucket = layout.files_per_bucket.max(1) as usize;
+    let mut renamed = HashMap::new();
+    let mut taken: HashMap<u32, HashSet<String>> = HashMap::new();
+    let mut seen = HashSet::new();
+    let unique = paths.filter(|path| seen.insert(path.as_str()));
+    for (index, path) in unique.enumerate() {
+        let file = Path::new(path);
+        let extension = file.extension().and_then(|e| e.to_str()).unwrap_or("mp3");
+        let numbered = file
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| stem.parse::<u32>().is_ok());
+        let new_path = match file.file_name().and_then(|name| name.to_str()) {
+            Some(name) if !numbered => {
+                let bucket = (index / per_bucket) as u32;
+                let name = unique_file_name(name, taken.entry(bucket).or_default());
+                format!("{}/{}", layout.bucket_dir(bucket), name)
+            }
+            _ => layout.file_path(index, extension),
+        };
+        if new_path != *path {
+            renamed.insert(path.clone(), new_path);
         }
     }
+    renamed
+}
 
-    // Rebuild albums (only those still used, with remapped artist IDs)
-    for (old_id, album) in old_albums.iter().enumerate() {
-        if used_album_ids.contains(&(old_id as u32)) {
-            let new_id = new_albums.len() as u32;
-            let name = old_strings
-                .get(album.name_string_id as usize)
-                .cloned()
-                .unwrap_or_default();
-            let name_string_id = new_string_table.add(&name);
-            let new_artist_id = *artist_id_map.get(&album.artist_id).unwrap_or(&0);
-            new_albums.push(AlbumEntry {
-                name_string_id,
-                artist_id: new_artist_id,
-                year: album.year,
-            });
-            album_id_map.insert(old_id as u32, new_id);
-        }
+/// Move audio files to the paths compaction renumbered them to.
+///
+/// Files are staged in a scratch folder first, so one file can take a path
+/// another is leaving. A stray file (not in the library) already at a new
+/// path is moved to the trash rather than overwritten. Bucket folders left
+/// empty are removed, except `00`. `on_file(done, total)` is called as each
+/// file lands. Returns (files moved, buckets removed).
+///
+/// Failures are logged rather than returned: library.bin already points at
+/// the new paths, and `repair_library` relinks any file left behind.
+fn move_renumbered_files(
+    base: &Path,
+    music_path: &Path,
+    renamed: &HashMap<String, String>,
+    on_file: impl Fn(u32, u32),
+) -> (u32, u32) {
+    if renamed.is_empty() {
+        return (0, 0);
+    }
+    let staging = music_path.join(RENUMBER_STAGING_DIR);
+    if let Err(e) = fs::create_dir_all(&staging) {
+        log::error!("[compact_library] Failed to create {}: {}", staging.display(), e);
+        return (0, 0);
     }
 
-    // Rebuild songs with remapped IDs
-    // Also build a map from old song IDs to new song IDs for playlist remapping
-    // AND collect paths that are still in use by active songs
-    let mut song_id_map: HashMap<u32, u32> = HashMap::new();
-    let mut active_paths: HashSet<u32> = HashSet::new(); // path_string_ids still in use
-    for (old_idx, song) in old_songs.iter().enumerate() {
-        // Skip deleted songs
-        if song.flags & song_flags::DELETED != 0 {
-            continue;
+    let mut staged = Vec::with_capacity(renamed.len());
+    for (index, (old_path, new_path)) in renamed.iter().enumerate() {
+        let staged_path = staging.join(index.to_string());
+        match fs::rename(music_path.join(old_path), &staged_path) {
+            Ok(()) => staged.push((staged_path, new_path)),
+            Err(e) => log::error!("[compact_library] Failed to stage {}: {}", old_path, e),
         }
-
-        // Track this path as still in use
-        active_paths.insert(song.path_string_id);
-
-        let title = old_strings
-            .get(song.title_string_id as usize)
-            .cloned()
-            .unwrap_or_default();
-        let path = old_strings
-            .get(song.path_string_id as usize)
-            .cloned()
-            .unwrap_or_default();
-
-        let title_string_id = new_string_table.add(&title);
-        let path_string_id = new_string_table.add(&path);
-        let new_artist_id = *artist_id_map.get(&song.artist_id).unwrap_or(&0);
-        let new_album_id = *album_id_map.get(&song.album_id).unwrap_or(&0);
-
-        let new_song_id = new_songs.len() as u32;
-        song_id_map.insert(old_idx as u32, new_song_id);
-
-        new_songs.push(SongEntry::new(
-            title_string_id,
-            new_artist_id,
-            new_album_id,
-            path_string_id,
-            song.track_number,
-            song.duration_sec,
-        ));
     }
 
-    // Delete audio files for deleted songs ONLY if no active song uses the same path
-    // This handles the case where edit_song_metadata marks old entry as deleted
-    // but creates a new entry with the same audio file path
-    for song in &old_songs {
-        if song.flags & song_flags::DELETED != 0 {
-            // Only delete if this path is NOT used by any active song
-            if !active_paths.contains(&song.path_string_id) {
-                if let Some(path_str) = old_strings.get(song.path_string_id as usize) {
-                    let audio_path = music_path.join(path_str);
-                    if audio_path.exists() {
-                        let _ = fs::remove_file(&audio_path); // Ignore errors
-                    }
-                }
+    let total = staged.len() as u32;
+    let mut files_moved = 0u32;
+    for (index, (staged_path, new_path)) in staged.into_iter().enumerate() {
+        let dest = music_path.join(new_path);
+        if dest.exists() {
+            if let Err(e) = move_to_trash(base, &[(new_path.clone(), None)]) {
+                log::warn!("[compact_library] Failed to trash stray {}: {}", new_path, e);
             }
         }
+        let moved = dest
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::rename(&staged_path, &dest));
+        match moved {
+            Ok(()) => files_moved += 1,
+            Err(e) => log::error!("[compact_library] Failed to move {}: {}", new_path, e),
+        }
+        on_file(index as u32 + 1, total);
     }
+    let _ = fs::remove_dir(&staging);
 
-    // Calculate removed counts
-    let artists_removed = header.artist_count - new_artists.len() as u32;
-    let albums_removed = header.album_count - new_albums.len() as u32;
-    let strings_removed = old_strings.len() as u32 - new_string_table.len() as u32;
+    let buckets_removed = remove_empty_buckets(music_path);
 
-    // Write new library.bin
-    write_library_bin(
-        &library_bin_path,
-        &new_string_table,
-        &new_artists,
-        &new_albums,
-        &new_songs,
-    )?;
+    log::info!(
+        "[compact_library] Renumbered {} files, removed {} empty buckets",
+        files_moved,
+        buckets_removed
+    );
+    (files_moved, buckets_removed)
+}
 
-    let new_size_bytes = fs::metadata(&library_bin_path)
-        .map(|m| m.len())
-        .unwrap_or(0);
+/// Remove bucket folders left empty, except the first (`00` or `00/00`).
+/// Returns the number of buckets removed.
+fn remove_empty_buckets(music_path: &Path) -> u32 {
+    let numbered_dirs = |dir: &Path| -> Vec<(u32, std::path::PathBuf)> {
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|entry| entry.path().is_dir())
+                    .filter_map(|entry| {
+                        let number = entry.file_name().to_str()?.parse::<u32>().ok()?;
+                        Some((number, entry.path()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
 
-    // Remap song IDs in all playlists
-    // This removes orphaned IDs (deleted songs) and updates IDs to new values
-    let playlists_path = jp3_path.join(PLAYLISTS_DIR);
+    // remove_dir only succeeds on empty folders
+    let mut removed = 0u32;
+    for (bucket, path) in numbered_dirs(music_path) {
+        // Buckets grouped two levels deep
+        let inner = numbered_dirs(&path);
+        let inner_removed = inner
+            .iter()
+            .filter(|(inner_bucket, _)| bucket > 0 || *inner_bucket > 0)
+            .filter(|(_, inner_path)| fs::remove_dir(inner_path).is_ok())
+            .count() as u32;
+        removed += inner_removed;
+        if bucket > 0 && fs::remove_dir(&path).is_ok() && inner.is_empty() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// How many playlists `remap_playlists` would change: those holding a song
+/// that is dropped or gets a new ID.
+fn playlists_to_remap(playlists_path: &Path, song_id_map: &HashMap<u32, u32>) -> u32 {
+    let Ok(entries) = fs::read_dir(playlists_path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let playlist_id = name.to_str()?.strip_suffix(".bin")?.parse::<u32>().ok()?;
+            crate::commands::playlist::read_playlist_file(&entry.path(), playlist_id).ok()
+        })
+        .filter(|playlist| {
+            playlist
+                .entries()
+                .iter()
+                .any(|e| song_id_map.get(&e.song_id) != Some(&e.song_id))
+        })
+        .count() as u32
+}
+
+/// Point every playlist at new song IDs after library.bin was rebuilt.
+///
+/// Songs missing from `song_id_map` are dropped. `on_playlist(done, total)`
+/// is called as each playlist is reached. Returns how many were rewritten.
+fn remap_playlists(
+    playlists_path: &Path,
+    song_id_map: &HashMap<u32, u32>,
+    on_playlist: impl Fn(u32, u32),
+) -> u32 {
     let mut playlists_updated = 0u32;
 
     if playlists_path.exists() {
-        if let Ok(entries) = fs::read_dir(&playlists_path) {
-            for entry in entries.flatten() {
+        if let Ok(entries) = fs::read_dir(playlists_path) {
+            let entries: Vec<_> = entries.flatten().collect();
+            let total_playlists = entries.len() as u32;
+            for (index, entry) in entries.into_iter().enumerate() {
+                on_playlist(index as u32 + 1, total_playlists);
+
                 // Parse playlist ID from filename (e.g., "123.bin" -> 123)
                 let Some(playlist_id) = entry
                     .file_name()
@@ -1126,17 +4951,21 @@ pub fn compact_library(base_path: String) -> Result<crate::models::CompactResult
 
                 // Remap song IDs: keep only songs that exist in the new library
                 // and update their IDs to the new values
-                let remapped_ids: Vec<u32> = playlist
-                    .song_ids
-                    .iter()
-                    .filter_map(|old_id| song_id_map.get(old_id).copied())
+                let remapped_entries: Vec<PlaylistEntry> = playlist
+                    .entries()
+                    .into_iter()
+                    .filter_map(|e| {
+                        let new_id = *song_id_map.get(&e.song_id)?;
+                        Some(PlaylistEntry::new(new_id, e.added_at))
+                    })
                     .collect();
 
                 // Always rewrite since IDs may have changed even if count is same
                 if crate::commands::playlist::write_playlist_file(
                     &entry.path(),
                     &playlist.name,
-                    &remapped_ids,
+                    &remapped_entries,
+                    playlist.manual_order,
                 )
                 .is_ok()
                 {
@@ -1146,82 +4975,519 @@ pub fn compact_library(base_path: String) -> Result<crate::models::CompactResult
         }
     }
 
+    playlists_updated
+}
+
+/// Run one bounded step of idle-time compaction.
+///
+/// The UI calls this while the app is idle. Each step trims at most
+/// `IDLE_STEP_MAX_SONGS` deleted songs and is charged against the library's
+/// daily write budget (`IDLE_WRITE_BUDGET`, kept on the card so it survives
+/// restarts); once the budget is spent, steps write nothing until the next
+/// day. A full `compact_library` is still needed to reclaim
+/// deleted songs elsewhere in the table and orphaned strings.
+#[tauri::command]
+pub fn compact_library_idle_step(
+    control: State<'_, CompactionControl>,
+    base_path: String,
+) -> Result<crate::models::IncrementalCompactResult, String> {
+    // Never runs alongside a full compaction
+    let _guard = control.begin()?;
+    let base = Path::new(&base_path);
+    let result = compact_song_tail(&base_path, IDLE_STEP_MAX_SONGS, idle_budget_remaining(base))?;
+    charge_idle_writes(base, result.bytes_written)?;
+    Ok(result)
+}
+
+/// Trim soft-deleted songs off the end of the song table.
+///
+/// Only the tail is removed, so no other song ID changes: the genre table
+/// moves down over the trimmed entries and the header is patched. The
+/// result goes to a temporary file renamed over library.bin, so a step cut
+/// off part way leaves the old file. At most `max_songs` are trimmed, and
+/// nothing is written if the step (the whole new file plus playlists)
+/// would take more than `budget_bytes`. Playlists still listing a trimmed
+/// ID are rewritten without it, so a later save can't hand that ID to a
+/// different song. Audio files of trimmed songs go to the trash.
+pub fn compact_song_tail(
+    base_path: &str,
+    max_songs: usize,
+    budget_bytes: u64,
+) -> Result<crate::models::IncrementalCompactResult, String> {
+    let base = Path::new(base_path);
+    ensure_library_available(base)?;
+    let jp3_path = base.join(JP3_DIR);
+    let library_bin_path = jp3_path.join(METADATA_DIR).join(LIBRARY_BIN);
+
+    let data =
+        fs::read(&library_bin_path).map_err(|e| format!("Failed to read library.bin: {}", e))?;
+    let header = LibraryHeader::from_bytes(&data).ok_or("Invalid library.bin header")?;
+    verify_checksum(&data, &header)?;
+    if header.version < crate::models::LIBRARY_VERSION {
+        return Err(format!(
+            "Library format v{} must be migrated before idle compaction",
+            header.version
+        ));
+    }
+
+    let songs = parse_song_table(
+        &data,
+        header.song_table_offset as usize,
+        header.song_count as usize,
+        header.version,
+    )?;
+    let deleted_tail = songs
+        .iter()
+        .rev()
+        .take_while(|s| s.flags & song_flags::DELETED != 0)
+        .count();
+    let trimmed = deleted_tail.min(max_songs);
+    let mut result = crate::models::IncrementalCompactResult {
+        budget_remaining: budget_bytes,
+        done: deleted_tail == trimmed,
+        ..Default::default()
+    };
+    if trimmed == 0 {
+        return Ok(result);
+    }
+
+    let new_song_count = songs.len() - trimmed;
+    let genre_start = header.genre_table_offset as usize;
+    let genre_bytes = data
+        .get(genre_start..genre_start + header.genre_count as usize * GenreEntry::SIZE as usize)
+        .ok_or("Genre table extends beyond file")?;
+
+    // Playlists that still reference a trimmed song
+    let mut stale_playlists = Vec::new();
+    if let Ok(entries) = fs::read_dir(jp3_path.join(PLAYLISTS_DIR)) {
+        for entry in entries.flatten() {
+            let Some(playlist_id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(".bin"))
+                .and_then(|id_str| id_str.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            let Ok(playlist) =
+                crate::commands::playlist::read_playlist_file(&entry.path(), playlist_id)
+            else {
+                continue;
+            };
+            if playlist.song_ids.iter().any(|&id| id as usize >= new_song_count) {
+                stale_playlists.push((entry.path(), playlist));
+            }
+        }
+    }
+
+    // Move the genre table down over the trimmed entries and patch the header
+    let entry_size = SongEntry::size_for(header.version) as usize;
+    let new_genre_offset = header.song_table_offset as usize + new_song_count * entry_size;
+    let mut new_header = header.clone();
+    new_header.song_count = new_song_count as u32;
+    new_header.genre_table_offset = new_genre_offset as u32;
+    new_header.generation = header.generation.wrapping_add(1);
+    let mut image = data[..new_genre_offset].to_vec();
+    image.extend_from_slice(genre_bytes);
+    new_header.checksum = tables_checksum(&image, &new_header);
+    image[..HEADER_SIZE as usize].copy_from_slice(&new_header.to_bytes());
+
+    let playlist_bytes: u64 = stale_playlists
+        .iter()
+        .map(|(path, _)| fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+        .sum();
+    let bytes_needed = image.len() as u64 + playlist_bytes;
+    if bytes_needed > budget_bytes {
+        result.done = false;
+        return Ok(result);
+    }
+
+    replace_library_bin(&library_bin_path, &image, "compact_song_tail")?;
+
+    // Audio files of trimmed songs go too, unless an active song shares the path
+    let strings = parse_string_table(
+        &data,
+        header.string_table_offset as usize,
+        header.string_table_end() as usize,
+    )?;
+    let active_paths: HashSet<u32> = songs[..new_song_count]
+        .iter()
+        .filter(|s| s.flags & song_flags::DELETED == 0)
+        .map(|s| s.path_string_id)
+        .collect();
+    let mut to_trash = Vec::new();
+    for song in &songs[new_song_count..] {
+        if active_paths.contains(&song.path_string_id) {
+            continue;
+        }
+        if let Some(path_str) = strings
+            .get(song.path_string_id as usize)
+            .filter(|path| !is_adopted_path(path))
+        {
+            let title = strings.get(song.title_string_id as usize).cloned();
+            to_trash.push((path_str.clone(), title));
+        }
+    }
+    if let Err(e) = move_to_trash(base, &to_trash) {
+        log::warn!("[compact_song_tail] Failed to trash removed audio: {}", e);
+    }
+
+    for (path, playlist) in stale_playlists {
+        let kept: Vec<PlaylistEntry> = playlist
+            .entries()
+            .into_iter()
+            .filter(|e| (e.song_id as usize) < new_song_count)
+            .collect();
+        if crate::commands::playlist::write_playlist_file(
+            &path,
+            &playlist.name,
+            &kept,
+            playlist.manual_order,
+        )
+        .is_ok()
+        {
+            result.playlists_updated += 1;
+        }
+    }
+
     log::info!(
-        "[compact_library] Removed {} songs, {} artists, {} albums. Updated {} playlists.",
-        songs_removed,
-        artists_removed,
-        albums_removed,
-        playlists_updated
+        "[compact_song_tail] Trimmed {} deleted songs, updated {} playlists",
+        trimmed,
+        result.playlists_updated
     );
 
-    // Note: Cover art files are now named using artist+album hash (not album ID),
-    // so they don't need to be renamed when album IDs change during compaction.
-    // Old ID-based cover files will become orphaned but harmless.
+    result.songs_trimmed = trimmed as u32;
+    result.bytes_written = bytes_needed;
+    result.budget_remaining = budget_bytes - bytes_needed;
+    Ok(result)
+}
+
+/// Positions of the album and song tables in `LibraryTables::in_file_order`
+const ALBUM_TABLE_INDEX: usize = 2;
+const SONG_TABLE_INDEX: usize = 3;
+
+/// Free space left after each table on a full rewrite, as a fraction of its size
+const TABLE_SLACK_DIVISOR: usize = 8;
+
+/// Minimum free space left after each table on a full rewrite
+const MIN_TABLE_SLACK: usize = 256;
+
+/// Write library.bin from components.
+///
+/// Saves and edits only ever add entries, so this first tries to append:
+/// if every table on disk is a prefix of its new contents and the new
+/// entries fit in the free space after it, only those bytes and the header
+/// are written. Otherwise the whole file is rewritten.
+fn write_library_bin(
+    path: &Path,
+    string_table: &StringTable,
+    artists: &[ArtistEntry],
+    albums: &[AlbumEntry],
+    songs: &[SongEntry],
+    genres: &[GenreEntry],
+) -> Result<(), String> {
+    let patched = PatchedEntries::default();
+    write_library_bin_patched(path, string_table, artists, albums, songs, genres, &patched)
+}
+
+/// Album and song entries changed in place, by ID.
+#[derive(Default)]
+struct PatchedEntries<'a> {
+    albums: &'a [u32],
+    songs: &'a [u32],
+}
+
+/// Write library.bin where the `patched` entries were changed in place.
+///
+/// Like `write_library_bin`, but those album and song entries may differ
+/// from the file on disk; they are overwritten where they are alongside the
+/// append.
+fn write_library_bin_patched(
+    path: &Path,
+    string_table: &StringTable,
+    artists: &[ArtistEntry],
+    albums: &[AlbumEntry],
+    songs: &[SongEntry],
+    genres: &[GenreEntry],
+    patched: &PatchedEntries,
+) -> Result<(), String> {
+    let tables = serialize_tables(string_table, artists, albums, songs, genres);
+    if append_library_bin(path, &tables, patched)? {
+        return Ok(());
+    }
+    rewrite_library_bin(path, &tables)
+}
+
+/// Serialized library tables, in file order.
+#[derive(Clone)]
+struct LibraryTables {
+    strings: Vec<u8>,
+    artists: Vec<u8>,
+    albums: Vec<u8>,
+    songs: Vec<u8>,
+    genres: Vec<u8>,
+}
+
+impl LibraryTables {
+    fn in_file_order(&self) -> [&[u8]; 5] {
+        [&self.strings, &self.artists, &self.albums, &self.songs, &self.genres]
+    }
+
+    /// These (current format) tables laid out for library.bin `version`.
+    ///
+    /// Before v4 song entries are cut to 24 bytes; before v3 the genre table
+    /// and song genre IDs are dropped, and before v2 the sort names.
+    fn for_version(&self, version: u32) -> Cow<'_, LibraryTables> {
+        if version >= DISC_NUMBERS_VERSION {
+            return Cow::Borrowed(self);
+        }
+        let mut tables = self.clone();
+        tables.songs = self
+            .songs
+            .chunks(SongEntry::SIZE as usize)
+            .flat_map(|entry| {
+                let mut entry = entry[..SongEntry::SIZE_V3 as usize].to_vec();
+                if version < GENRES_VERSION {
+                    entry[0x16..0x18].fill(0); // reserved
+                }
+                entry
+            })
+            .collect();
+        if version < GENRES_VERSION {
+            tables.genres.clear();
+        }
+        if version < SORT_NAMES_VERSION {
+            for artist in tables.artists.chunks_mut(ArtistEntry::SIZE as usize) {
+                artist[4..8].fill(0);
+            }
+            for album in tables.albums.chunks_mut(AlbumEntry::SIZE as usize) {
+                album[12..16].fill(0);
+            }
+        }
+        Cow::Owned(tables)
+    }
+}
+
+fn serialize_tables(
+    string_table: &StringTable,
+    artists: &[ArtistEntry],
+    albums: &[AlbumEntry],
+    songs: &[SongEntry],
+    genres: &[GenreEntry],
+) -> LibraryTables {
+    LibraryTables {
+        strings: string_table.to_bytes(),
+        artists: artists.iter().flat_map(|a| a.to_bytes()).collect(),
+        albums: albums.iter().flat_map(|a| a.to_bytes()).collect(),
+        songs: songs.iter().flat_map(|s| s.to_bytes()).collect(),
+        genres: genres.iter().flat_map(|g| g.to_bytes()).collect(),
+    }
+}
+
+/// Header describing `tables` (laid out for `version`), with the offsets and
+/// checksum left to fill in.
+fn header_for_tables(path: &Path, tables: &LibraryTables, version: u32) -> LibraryHeader {
+    LibraryHeader {
+        magic: *crate::models::LIBRARY_MAGIC,
+        version,
+        song_count: tables.songs.len() as u32 / SongEntry::size_for(version),
+        artist_count: tables.artists.len() as u32 / ArtistEntry::SIZE,
+        album_count: tables.albums.len() as u32 / AlbumEntry::SIZE,
+        string_table_offset: HEADER_SIZE,
+        artist_table_offset: HEADER_SIZE,
+        album_table_offset: HEADER_SIZE,
+        song_table_offset: HEADER_SIZE,
+        generation: next_generation(path),
+        genre_count: tables.genres.len() as u32 / GenreEntry::SIZE,
+        genre_table_offset: HEADER_SIZE,
+        checksum: 0,
+        string_table_len: tables.strings.len() as u32,
+    }
+}
+
+/// Write only the new entries into the free space after each table.
+///
+/// Entries in `patched` are also rewritten in place. Returns
+/// false, having written nothing, if the file on disk or the one to write
+/// is not the current version, any other existing entry changed, or a
+/// table has outgrown its free space. The header is written last, so until
+/// then readers still see the previous library.
+fn append_library_bin(
+    path: &Path,
+    tables: &LibraryTables,
+    patched: &PatchedEntries,
+) -> Result<bool, String> {
+    if library_bin_version(path) != crate::models::LIBRARY_VERSION {
+        return Ok(false);
+    }
+    ensure_firmware_reads(path, tables, crate::models::LIBRARY_VERSION)?;
+    let Ok(data) = fs::read(path) else {
+        return Ok(false);
+    };
+    let Some(old_header) = LibraryHeader::from_bytes(&data) else {
+        return Ok(false);
+    };
+    if old_header.version != crate::models::LIBRARY_VERSION
+        || verify_checksum(&data, &old_header).is_err()
+    {
+        return Ok(false);
+    }
+
+    // Each table may grow up to where the next one starts
+    let limits = [
+        old_header.artist_table_offset as usize,
+        old_header.album_table_offset as usize,
+        old_header.song_table_offset as usize,
+        old_header.genre_table_offset as usize,
+        data.len(),
+    ];
+    let mut writes: Vec<(usize, &[u8])> = Vec::new();
+    for (index, (((offset, used), new_bytes), limit)) in old_header
+        .table_extents()
+        .into_iter()
+        .zip(tables.in_file_order())
+        .zip(limits)
+        .enumerate()
+    {
+        let (offset, used) = (offset as usize, used as usize);
+        if new_bytes.len() < used || offset + new_bytes.len() > limit {
+            return Ok(false);
+        }
+        let Some(on_disk) = data.get(offset..offset + used) else {
+            return Ok(false);
+        };
+
+        // Patched entries count as unchanged once their new bytes are written
+        let mut on_disk = Cow::Borrowed(on_disk);
+        let (entry_size, patched_ids) = match index {
+            ALBUM_TABLE_INDEX => (AlbumEntry::SIZE as usize, patched.albums),
+            SONG_TABLE_INDEX => (SongEntry::SIZE as usize, patched.songs),
+            _ => (0, &[][..]),
+        };
+        for &id in patched_ids {
+            let start = id as usize * entry_size;
+            if start + entry_size <= used {
+                let entry = &new_bytes[start..start + entry_size];
+                on_disk.to_mut()[start..start + entry_size].copy_from_slice(entry);
+                writes.push((offset + start, entry));
+            }
+        }
+        if *on_disk != new_bytes[..used] {
+            return Ok(false);
+        }
+        if new_bytes.len() > used {
+            writes.push((offset + used, &new_bytes[used..]));
+        }
+    }
+
+    let mut header = header_for_tables(path, tables, crate::models::LIBRARY_VERSION);
+    header.string_table_offset = old_header.string_table_offset;
+    header.artist_table_offset = old_header.artist_table_offset;
+    header.album_table_offset = old_header.album_table_offset;
+    header.song_table_offset = old_header.song_table_offset;
+    header.genre_table_offset = old_header.genre_table_offset;
+    header.generation = old_header.generation.wrapping_add(1);
+
+    let mut image = data;
+    for &(at, bytes) in &writes {
+        image[at..at + bytes.len()].copy_from_slice(bytes);
+    }
+    header.checksum = tables_checksum(&image, &header);
+
+    let _marker = begin_library_write(path, "append_library_bin")?;
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open library.bin for writing: {}", e))?;
+    for &(at, bytes) in &writes {
+        file.seek(SeekFrom::Start(at as u64))
+            .map_err(|e| format!("Failed to seek in library.bin: {}", e))?;
+        file.write_all(bytes)
+            .map_err(|e| format!("Failed to append to library.bin: {}", e))?;
+    }
+    file.sync_all()
+        .map_err(|e| format!("Failed to sync: {}", e))?;
+    file.seek(SeekFrom::Start(0))
+        .map_err(|e| format!("Failed to seek to header: {}", e))?;
+    file.write_all(&header.to_bytes())
+        .map_err(|e| format!("Failed to write header: {}", e))?;
+    file.sync_all()
+        .map_err(|e| format!("Failed to sync: {}", e))?;
 
-    Ok(crate::models::CompactResult {
-        songs_removed,
-        artists_removed,
-        albums_removed,
-        strings_removed,
-        playlists_updated,
-        old_size_bytes,
-        new_size_bytes,
-        bytes_saved: old_size_bytes.saturating_sub(new_size_bytes),
-    })
+    log::info!(
+        "[write_library_bin] Appended {} bytes in place",
+        writes.iter().map(|(_, bytes)| bytes.len()).sum::<usize>()
+    );
+    Ok(true)
 }
 
-/// Helper function to write library.bin from components.
-fn write_library_bin(
-    path: &Path,
-    string_table: &StringTable,
-    artists: &[ArtistEntry],
-    albums: &[AlbumEntry],
-    songs: &[SongEntry],
-) -> Result<(), String> {
-    let string_table_bytes = string_table.to_bytes();
-    let artist_table_bytes: Vec<u8> = artists.iter().flat_map(|a| a.to_bytes()).collect();
-    let album_table_bytes: Vec<u8> = albums.iter().flat_map(|a| a.to_bytes()).collect();
-    let song_table_bytes: Vec<u8> = songs.iter().flat_map(|s| s.to_bytes()).collect();
-
-    let string_table_offset = HEADER_SIZE;
-    let artist_table_offset = string_table_offset + string_table_bytes.len() as u32;
-    let album_table_offset = artist_table_offset + artist_table_bytes.len() as u32;
-    let song_table_offset = album_table_offset + album_table_bytes.len() as u32;
+/// Rewrite the whole of library.bin, leaving free space after each table
+/// (v6; older versions for firmware that can't read it have none).
+///
+/// The file is written next to library.bin and renamed over it once synced,
+/// so an interrupted write leaves the previous library intact; the write
+/// is marked in progress until the rename (see `write_marker_service`).
+fn rewrite_library_bin(path: &Path, tables: &LibraryTables) -> Result<(), String> {
+    let image = library_image(path, tables)?;
 
-    let header = LibraryHeader {
-        magic: *crate::models::LIBRARY_MAGIC,
-        version: crate::models::LIBRARY_VERSION,
-        song_count: songs.len() as u32,
-        artist_count: artists.len() as u32,
-        album_count: albums.len() as u32,
-        string_table_offset,
-        artist_table_offset,
-        album_table_offset,
-        song_table_offset,
-    };
+    replace_library_bin(path, &image, "rewrite_library_bin")
+}
 
+/// Write `image` to a temporary file and rename it over library.bin, so a
+/// write cut off part way leaves the old file intact.
+fn replace_library_bin(path: &Path, image: &[u8], operation: &str) -> Result<(), String> {
+    let _marker = begin_library_write(path, operation)?;
+    let temp_path = path.with_extension("bin.tmp");
     let mut file =
-        fs::File::create(path).map_err(|e| format!("Failed to create library.bin: {}", e))?;
-    file.write_all(&header.to_bytes())
-        .map_err(|e| format!("Failed to write header: {}", e))?;
-    file.write_all(&string_table_bytes)
-        .map_err(|e| format!("Failed to write string table: {}", e))?;
-    file.write_all(&artist_table_bytes)
-        .map_err(|e| format!("Failed to write artist table: {}", e))?;
-    file.write_all(&album_table_bytes)
-        .map_err(|e| format!("Failed to write album table: {}", e))?;
-    file.write_all(&song_table_bytes)
-        .map_err(|e| format!("Failed to write song table: {}", e))?;
+        fs::File::create(&temp_path).map_err(|e| format!("Failed to create library.bin: {}", e))?;
+    file.write_all(image)
+        .map_err(|e| format!("Failed to write library.bin: {}", e))?;
     file.sync_all()
         .map_err(|e| format!("Failed to sync: {}", e))?;
+    drop(file);
 
-    Ok(())
+    fs::rename(&temp_path, path).map_err(|e| format!("Failed to replace library.bin: {}", e))
+}
+
+/// The bytes `rewrite_library_bin` would write for `tables`, in the
+/// version `library_bin_version` picks for `path`.
+///
+/// The genre table goes after the song table so readers that predate it
+/// (v2 firmware) still find every other table where they expect.
+fn library_image(path: &Path, tables: &LibraryTables) -> Result<Vec<u8>, String> {
+    let version = library_bin_version(path);
+    let tables = tables.for_version(version);
+    ensure_firmware_reads(path, &tables, version)?;
+    let mut header = header_for_tables(path, &tables, version);
+    let header_size = header.size() as usize;
+    let mut image = vec![0u8; header_size];
+    let mut offsets = [0u32; 5];
+    for (offset, table) in offsets.iter_mut().zip(tables.in_file_order()) {
+        *offset = image.len() as u32;
+        image.extend_from_slice(table);
+        // Before v6 each table ends where the next one starts
+        if version >= TABLE_SLACK_VERSION {
+            let slack = (table.len() / TABLE_SLACK_DIVISOR).max(MIN_TABLE_SLACK);
+            image.resize(image.len() + slack, 0);
+        }
+    }
+    [
+        header.string_table_offset,
+        header.artist_table_offset,
+        header.album_table_offset,
+        header.song_table_offset,
+        header.genre_table_offset,
+    ] = offsets;
+    header.checksum = tables_checksum(&image, &header);
+    // Older headers are a prefix of the current one
+    image[..header_size].copy_from_slice(&header.to_bytes()[..header_size]);
+    Ok(image)
 }
 
 /// Delete all songs belonging to an album.
 ///
 /// This finds all songs with the given album_id and soft-deletes them,
-/// also deleting their audio files from music/.
+/// also moving their audio files from music/ to the trash.
 /// Use `compact_library` to clean up orphaned albums/artists afterward.
 #[tauri::command]
 pub fn delete_album(
@@ -1229,7 +5495,7 @@ pub fn delete_album(
     album_id: u32,
 ) -> Result<crate::models::DeleteAlbumResult, String> {
     // First load the library to find all songs in this album
-    let library = load_library(base_path.clone())?;
+    let library = load_library(base_path.clone(), None)?;
 
     // Find the album to get its name
     let album = library
@@ -1272,7 +5538,7 @@ pub fn delete_album(
 /// Delete all songs belonging to an artist.
 ///
 /// This finds all songs with the given artist_id and soft-deletes them,
-/// also deleting their audio files from music/.
+/// also moving their audio files from music/ to the trash.
 /// Use `compact_library` to clean up orphaned albums/artists afterward.
 #[tauri::command]
 pub fn delete_artist(
@@ -1280,7 +5546,7 @@ pub fn delete_artist(
     artist_id: u32,
 ) -> Result<crate::models::DeleteArtistResult, String> {
     // First load the library to find all songs by this artist
-    let library = load_library(base_path.clone())?;
+    let library = load_library(base_path.clone(), None)?;
 
     // Find the artist to get their name
     let artist = library
@@ -1330,18 +5596,20 @@ pub fn delete_artist(
 
 /// Edit an album's metadata (name, year, or artist).
 ///
-/// This updates all songs in the album to reflect the new album metadata.
-/// If the artist changes, a new artist entry is created if needed.
-/// This requires a full library rewrite.
+/// The album entry is patched in place. Songs are only touched when the
+/// album artist changes: songs credited to the old album artist move to the
+/// new one (created if needed), and only those entries are rewritten. With
+/// no `new_artist_name` the album keeps its artist.
 #[tauri::command]
 pub fn edit_album(
     base_path: String,
     album_id: u32,
     new_name: String,
-    new_artist_name: String,
+    new_artist_name: Option<String>,
     new_year: Option<u16>,
 ) -> Result<crate::models::EditAlbumResult, String> {
     let base = Path::new(&base_path);
+    ensure_library_available(base)?;
     let jp3_path = base.join(JP3_DIR);
     let metadata_path = jp3_path.join(METADATA_DIR);
     let library_bin_path = metadata_path.join(LIBRARY_BIN);
@@ -1357,9 +5625,10 @@ pub fn edit_album(
     let mut string_table = existing.string_table;
     let mut artists = existing.artists;
     let mut albums = existing.albums;
-    let songs = existing.songs;
+    let mut songs = existing.songs;
     let mut artist_map = existing.artist_map;
     let mut album_map = existing.album_map;
+    let genres = existing.genres;
 
     // Find the album
     if album_id as usize >= albums.len() {
@@ -1372,7 +5641,14 @@ pub fn edit_album(
         .get(old_name_string_id)
         .map(|s| s.to_string())
         .unwrap_or_default();
-    let old_artist_id = albums[album_id as usize].artist_id;
+    let old_artist_id = albums[album_id as usize].album_artist_id;
+    let new_artist_name = new_artist_name.unwrap_or_else(|| {
+        artists
+            .get(old_artist_id as usize)
+            .and_then(|artist| string_table.get(artist.name_string_id))
+            .unwrap_or_default()
+            .to_string()
+    });
 
     // Get or create the new artist
     let artist_created;
@@ -1382,8 +5658,7 @@ pub fn edit_album(
     } else {
         artist_created = true;
         let id = artists.len() as u32;
-        let name_string_id = string_table.add(&new_artist_name);
-        artists.push(ArtistEntry { name_string_id });
+        artists.push(new_artist_entry(&mut string_table, &new_artist_name));
         artist_map.insert(new_artist_name.clone(), id);
         id
     };
@@ -1396,43 +5671,166 @@ pub fn edit_album(
     album_map.insert(new_album_key, album_id);
 
     // Update the album entry
-    let new_name_string_id = string_table.add(&new_name);
-    albums[album_id as usize] = AlbumEntry {
-        name_string_id: new_name_string_id,
-        artist_id: new_artist_id,
-        year: new_year.unwrap_or(albums[album_id as usize].year),
-    };
+    let year = new_year.unwrap_or(albums[album_id as usize].year);
+    albums[album_id as usize] = new_album_entry(&mut string_table, &new_name, new_artist_id, year);
 
-    // Update all songs in this album to point to the new artist
+    // Songs credited to the old album artist move to the new one; other
+    // artists on a compilation keep their credit
     let mut songs_updated = 0u32;
-    let updated_songs: Vec<SongEntry> = songs
-        .into_iter()
-        .map(|mut song| {
-            if song.album_id == album_id && song.flags & song_flags::DELETED == 0 {
-                song.artist_id = new_artist_id;
-                songs_updated += 1;
-            }
-            song
-        })
-        .collect();
+    let mut patched_songs: Vec<u32> = Vec::new();
+    for (song_id, song) in songs.iter_mut().enumerate() {
+        if song.album_id != album_id || song.flags & song_flags::DELETED != 0 {
+            continue;
+        }
+        if song.artist_id == old_artist_id && old_artist_id != new_artist_id {
+            song.artist_id = new_artist_id;
+            patched_songs.push(song_id as u32);
+        }
+        songs_updated += 1;
+    }
 
     // Write updated library
-    write_library_bin(
+    write_library_bin_patched(
         &library_bin_path,
         &string_table,
         &artists,
         &albums,
-        &updated_songs,
+        &songs,
+        &genres,
+        &PatchedEntries {
+            albums: &[album_id],
+            songs: &patched_songs,
+        },
     )?;
 
+    if let Err(e) = record_transliterations(
+        base,
+        [new_name.as_str(), new_artist_name.as_str()],
+    ) {
+        log::warn!("Failed to record transliterations: {}", e);
+    }
+
+    announce_album_change(base, vec![album_id]);
+
     Ok(crate::models::EditAlbumResult {
         songs_updated,
+        songs_rewritten: patched_songs.len() as u32,
         artist_created,
         old_name,
         new_name,
     })
 }
 
+/// Merge albums split by inconsistent tagging into one.
+///
+/// Every active song on a source album moves to the target; song IDs are
+/// unchanged, so playlists need no remapping. If the target has no cover,
+/// the first source cover is moved over to it, and the other source covers
+/// and thumbnails are removed. The emptied source albums are dropped by the
+/// next compaction.
+#[tauri::command]
+pub fn merge_albums(
+    base_path: String,
+    target_id: u32,
+    source_ids: Vec<u32>,
+) -> Result<crate::models::MergeAlbumsResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let jp3_path = base.join(JP3_DIR);
+    let library_bin_path = jp3_path.join(METADATA_DIR).join(LIBRARY_BIN);
+
+    if !library_bin_path.exists() {
+        return Err("Library not found".to_string());
+    }
+
+    let existing = load_existing_library_data(&library_bin_path)?
+        .ok_or("Failed to load existing library data")?;
+    let string_table = existing.string_table;
+    let artists = existing.artists;
+    let albums = existing.albums;
+    let mut songs = existing.songs;
+    let genres = existing.genres;
+
+    // Validate every album before changing anything
+    let mut sources: Vec<u32> = Vec::with_capacity(source_ids.len());
+    for &album_id in std::iter::once(&target_id).chain(&source_ids) {
+        if album_id as usize >= albums.len() {
+            return Err(format!("Album with ID {} not found", album_id));
+        }
+        if album_id != target_id && !sources.contains(&album_id) {
+            sources.push(album_id);
+        }
+    }
+    if sources.is_empty() {
+        return Err("No albums to merge into the target".to_string());
+    }
+
+    // (album artist, album name) for cover filenames
+    let album_names = |album_id: u32| {
+        let album = &albums[album_id as usize];
+        let artist = artists
+            .get(album.album_artist_id as usize)
+            .and_then(|artist| string_table.get(artist.name_string_id))
+            .unwrap_or_default();
+        let name = string_table.get(album.name_string_id).unwrap_or_default();
+        (artist, name)
+    };
+
+    let mut songs_moved = 0u32;
+    for song in songs.iter_mut() {
+        if sources.contains(&song.album_id) && song.flags & song_flags::DELETED == 0 {
+            song.album_id = target_id;
+            songs_moved += 1;
+        }
+    }
+
+    write_library_bin(
+        &library_bin_path,
+        &string_table,
+        &artists,
+        &albums,
+        &songs,
+        &genres,
+    )?;
+
+    // Consolidate cover art onto the target
+    let albums_dir = jp3_path.join(ASSETS_DIR).join(ALBUMS_DIR);
+    let (target_artist, target_name) = album_names(target_id);
+    let target_cover =
+        albums_dir.join(format!("{}.jpg", cover_filename(target_artist, target_name)));
+    let mut cover_adopted = false;
+    let mut covers_removed = 0u32;
+    for &source_id in &sources {
+        let (artist, name) = album_names(source_id);
+        let cover = albums_dir.join(format!("{}.jpg", cover_filename(artist, name)));
+        // Same artist and name as the target: the cover is the target's own
+        if cover == target_cover || !cover.exists() {
+            continue;
+        }
+        if !target_cover.exists() {
+            fs::rename(&cover, &target_cover)
+                .map_err(|e| format!("Failed to move cover {}: {}", cover.display(), e))?;
+            remove_thumbnails(&albums_dir, target_artist, target_name);
+            cover_adopted = true;
+        } else {
+            fs::remove_file(&cover)
+                .map_err(|e| format!("Failed to remove cover {}: {}", cover.display(), e))?;
+            covers_removed += 1;
+        }
+        remove_thumbnails(&albums_dir, artist, name);
+    }
+
+    let changed = std::iter::once(target_id).chain(sources.iter().copied());
+    announce_album_change(base, changed.collect());
+
+    Ok(crate::models::MergeAlbumsResult {
+        songs_moved,
+        albums_merged: sources.len() as u32,
+        cover_adopted,
+        covers_removed,
+    })
+}
+
 /// Edit an artist's metadata (name only).
 ///
 /// This updates the artist's name in the string table.
@@ -1445,6 +5843,7 @@ pub fn edit_artist(
     new_name: String,
 ) -> Result<crate::models::EditArtistResult, String> {
     let base = Path::new(&base_path);
+    ensure_library_available(base)?;
     let jp3_path = base.join(JP3_DIR);
     let metadata_path = jp3_path.join(METADATA_DIR);
     let library_bin_path = metadata_path.join(LIBRARY_BIN);
@@ -1461,6 +5860,7 @@ pub fn edit_artist(
     let mut artists = existing.artists;
     let albums = existing.albums;
     let songs = existing.songs;
+    let genres = existing.genres;
     let mut artist_map = existing.artist_map;
 
     // Find the artist
@@ -1490,10 +5890,7 @@ pub fn edit_artist(
     artist_map.insert(new_name.clone(), artist_id);
 
     // Update the artist entry with new name
-    let new_name_string_id = string_table.add(&new_name);
-    artists[artist_id as usize] = ArtistEntry {
-        name_string_id: new_name_string_id,
-    };
+    artists[artist_id as usize] = new_artist_entry(&mut string_table, &new_name);
 
     // Count affected songs and albums
     let songs_affected = songs
@@ -1501,10 +5898,33 @@ pub fn edit_artist(
         .filter(|s| s.artist_id == artist_id && s.flags & song_flags::DELETED == 0)
         .count() as u32;
 
-    let albums_affected = albums.iter().filter(|a| a.artist_id == artist_id).count() as u32;
+    let albums_affected = albums
+        .iter()
+        .filter(|a| a.album_artist_id == artist_id)
+        .count() as u32;
 
     // Write updated library
-    write_library_bin(&library_bin_path, &string_table, &artists, &albums, &songs)?;
+    write_library_bin(
+        &library_bin_path,
+        &string_table,
+        &artists,
+        &albums,
+        &songs,
+        &genres,
+    )?;
+
+    if let Err(e) = record_transliterations(base, [new_name.as_str()]) {
+        log::warn!("Failed to record transliterations: {}", e);
+    }
+
+    announce(
+        EVENT_ARTIST_UPDATED,
+        base,
+        crate::models::LibraryChangeEvent {
+            artist_ids: vec![artist_id],
+            ..Default::default()
+        },
+    );
 
     Ok(crate::models::EditArtistResult {
         songs_affected,
@@ -1514,28 +5934,105 @@ pub fn edit_artist(
     })
 }
 
+/// Hands out new file paths in the music buckets, filling the current
+/// bucket before starting the next, as `save_to_library` lays them out.
+struct BucketAllocator<'a> {
+    music_path: &'a Path,
+    layout: BucketLayout,
+    current_bucket: u32,
+    files_in_bucket: usize,
+    /// File names in the current bucket, read when first needed
+    taken_names: Option<(u32, HashSet<String>)>,
+}
+
+impl<'a> BucketAllocator<'a> {
+    fn new(base: &Path, music_path: &'a Path) -> Result<Self, String> {
+        let layout = load_library_settings(base)?.bucket_layout;
+        let (current_bucket, files_in_bucket) = get_current_bucket(music_path, &layout)?;
+        Ok(Self {
+            music_path,
+            layout,
+            current_bucket,
+            files_in_bucket,
+            taken_names: None,
+        })
+    }
+
+    /// Copy `source` into the next free slot, named `file_name` if given
+    /// (a stem) or else by the naming scheme. Returns its path relative to
+    /// `jp3/music`.
+    fn copy_in(
+        &mut self,
+        source: &Path,
+        track_number: u16,
+        artist: &str,
+        title: &str,
+        file_name: Option<&str>,
+    ) -> Result<String, String> {
+        // Check if we need a new bucket
+        if self.files_in_bucket >= self.layout.files_per_bucket.max(1) as usize {
+            self.current_bucket += 1;
+            self.files_in_bucket = 0;
+        }
+        let bucket_dir = self.layout.bucket_dir(self.current_bucket);
+        fs::create_dir_all(self.music_path.join(&bucket_dir))
+            .map_err(|e| format!("Failed to create bucket {}: {}", bucket_dir, e))?;
+
+        // Name the copy after its real format; fall back to the source extension
+        let extension = match sniff_audio_format(source) {
+            Ok(Some(format)) => format.extension().to_string(),
+            _ => source
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("mp3")
+                .to_lowercase(),
+        };
+
+        // Sequential (001.mp3, 002.mp3, ...) or named after the song, never
+        // replacing a file already in the bucket
+        let taken = match &mut self.taken_names {
+            Some((bucket, taken)) if *bucket == self.current_bucket => taken,
+            _ => {
+                let taken = taken_file_names(&self.music_path.join(&bucket_dir));
+                &mut self.taken_names.insert((self.current_bucket, taken)).1
+            }
+        };
+        let name = match file_name.map(fat_safe_stem).filter(|stem| !stem.is_empty()) {
+            Some(stem) => format!("{}.{}", stem, extension),
+            None => song_file_name(
+                self.layout.file_names,
+                self.files_in_bucket + 1,
+                track_number,
+                artist,
+                title,
+                &extension,
+            ),
+        };
+        let new_filename = unique_file_name(&name, taken);
+        let relative_path = format!("{}/{}", bucket_dir, new_filename);
+
+        copy_file(source, &self.music_path.join(&relative_path))
+            .map_err(|e| format!("Failed to copy to {}: {}", relative_path, e))?;
+        self.files_in_bucket += 1;
+        Ok(relative_path)
+    }
+}
+
 /// Get the current bucket index and file count.
-fn get_current_bucket(music_path: &Path) -> Result<(u32, usize), String> {
+fn get_current_bucket(music_path: &Path, layout: &BucketLayout) -> Result<(u32, usize), String> {
     if !music_path.exists() {
         return Ok((0, 0));
     }
 
-    let mut max_bucket = 0u32;
-    let entries =
-        fs::read_dir(music_path).map_err(|e| format!("Failed to read music directory: {}", e))?;
-
-    for entry in entries.flatten() {
-        if entry.path().is_dir() {
-            if let Some(name) = entry.file_name().to_str() {
-                if let Ok(num) = name.parse::<u32>() {
-                    max_bucket = max_bucket.max(num);
-                }
-            }
-        }
+    let mut max_bucket = highest_numbered_dir(music_path)?;
+    if layout.two_level {
+        let group = music_path.join(format!("{:02}", max_bucket));
+        let bucket_in_group = if group.exists() { highest_numbered_dir(&group)? } else { 0 };
+        max_bucket = max_bucket * layout.files_per_bucket.max(1) + bucket_in_group;
     }
 
     // Count files in the current bucket
-    let bucket_path = music_path.join(format!("{:02}", max_bucket));
+    let bucket_path = music_path.join(layout.bucket_dir(max_bucket));
     let file_count = if bucket_path.exists() {
         fs::read_dir(&bucket_path)
             .map(|entries| entries.flatten().filter(|e| e.path().is_file()).count())
@@ -1547,13 +6044,29 @@ fn get_current_bucket(music_path: &Path) -> Result<(u32, usize), String> {
     Ok((max_bucket, file_count))
 }
 
+/// Highest number among the folders in `dir` named with one (0 if none).
+fn highest_numbered_dir(dir: &Path) -> Result<u32, String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    Ok(entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .max()
+        .unwrap_or(0))
+}
+
 /// Load and parse library.bin from the jp3 folder.
 ///
 /// This parses the binary format exactly as the ESP32 would,
 /// reading directly from the file on disk (not from memory).
+///
+/// Songs come back in ID order unless `sort` is given; artists and albums
+/// are always in sort-name order.
 #[tauri::command]
-pub fn load_library(base_path: String) -> Result<ParsedLibrary, String> {
+pub fn load_library(base_path: String, sort: Option<LibrarySort>) -> Result<ParsedLibrary, String> {
     let base = Path::new(&base_path);
+    ensure_library_available(base)?;
     let jp3_path = base.join(JP3_DIR);
     let metadata_path = jp3_path.join(METADATA_DIR);
     let library_bin_path = metadata_path.join(LIBRARY_BIN);
@@ -1561,6 +6074,13 @@ pub fn load_library(base_path: String) -> Result<ParsedLibrary, String> {
     if !library_bin_path.exists() {
         return Err("library.bin not found. Add some songs first.".to_string());
     }
+    // A write cut off mid-way may have left a torn file
+    if let Some(write) = interrupted_writes(base).first() {
+        return Err(format!(
+            "{}: {} was writing library.bin when the app stopped",
+            INTERRUPTED_WRITE, write.operation
+        ));
+    }
 
     // Read entire file into memory (same as ESP32 would read from SD card)
     let mut file = fs::File::open(&library_bin_path)
@@ -1571,12 +6091,13 @@ pub fn load_library(base_path: String) -> Result<ParsedLibrary, String> {
 
     // Parse header
     let header = LibraryHeader::from_bytes(&data).ok_or("Invalid library.bin header")?;
+    verify_checksum(&data, &header)?;
 
     // Parse string table
     let strings = parse_string_table(
         &data,
         header.string_table_offset as usize,
-        header.artist_table_offset as usize,
+        header.string_table_end() as usize,
     )?;
 
     // Parse artist table
@@ -1584,6 +6105,7 @@ pub fn load_library(base_path: String) -> Result<ParsedLibrary, String> {
         &data,
         header.artist_table_offset as usize,
         header.artist_count as usize,
+        header.version,
     )?;
 
     // Parse album table
@@ -1591,6 +6113,7 @@ pub fn load_library(base_path: String) -> Result<ParsedLibrary, String> {
         &data,
         header.album_table_offset as usize,
         header.album_count as usize,
+        header.version,
     )?;
 
     // Parse song table
@@ -1598,18 +6121,44 @@ pub fn load_library(base_path: String) -> Result<ParsedLibrary, String> {
         &data,
         header.song_table_offset as usize,
         header.song_count as usize,
+        header.version,
     )?;
 
+    // Parse genre table (empty before v3)
+    let genre_names: Vec<String> = parse_genre_table(
+        &data,
+        header.genre_table_offset as usize,
+        header.genre_count as usize,
+    )?
+    .into_iter()
+    .map(|id| strings.get(id as usize).cloned().unwrap_or_default())
+    .collect();
+
+    // Romanized keys are optional extras; a bad index shouldn't block loading
+    let transliterations = load_transliterations(base).unwrap_or_else(|e| {
+        log::warn!("Ignoring transliterations: {}", e);
+        Default::default()
+    });
+    let confidence_index = load_song_confidence(base).unwrap_or_else(|e| {
+        log::warn!("Ignoring song confidence: {}", e);
+        Default::default()
+    });
+
     // Build parsed artists with resolved names
     let artists: Vec<ParsedArtist> = raw_artists
         .iter()
         .enumerate()
-        .map(|(i, a)| ParsedArtist {
-            id: i as u32,
-            name: strings
+        .map(|(i, a)| {
+            let name = strings
                 .get(a.name_string_id as usize)
                 .cloned()
-                .unwrap_or_else(|| "Unknown".to_string()),
+                .unwrap_or_else(|| "Unknown".to_string());
+            ParsedArtist {
+                id: i as u32,
+                sort_name: resolve_sort_name(&strings, a.sort_name_string_id, &name),
+                romanized: romanized_key(&transliterations, &name),
+                name,
+            }
         })
         .collect();
 
@@ -1619,16 +6168,19 @@ pub fn load_library(base_path: String) -> Result<ParsedLibrary, String> {
         .enumerate()
         .map(|(i, a)| {
             let artist_name = artists
-                .get(a.artist_id as usize)
+                .get(a.album_artist_id as usize)
                 .map(|ar| ar.name.clone())
                 .unwrap_or_else(|| "Unknown".to_string());
+            let name = strings
+                .get(a.name_string_id as usize)
+                .cloned()
+                .unwrap_or_else(|| "Unknown".to_string());
             ParsedAlbum {
                 id: i as u32,
-                name: strings
-                    .get(a.name_string_id as usize)
-                    .cloned()
-                    .unwrap_or_else(|| "Unknown".to_string()),
-                artist_id: a.artist_id,
+                sort_name: resolve_sort_name(&strings, a.sort_name_string_id, &name),
+                romanized: romanized_key(&transliterations, &name),
+                name,
+                artist_id: a.album_artist_id,
                 artist_name,
                 year: a.year,
             }
@@ -1636,7 +6188,7 @@ pub fn load_library(base_path: String) -> Result<ParsedLibrary, String> {
         .collect();
 
     // Build parsed songs with resolved names (skip deleted entries)
-    let songs: Vec<ParsedSong> = raw_songs
+    let mut songs: Vec<ParsedSong> = raw_songs
         .iter()
         .enumerate()
         .filter(|(_, s)| s.flags & crate::models::song_flags::DELETED == 0)
@@ -1650,51 +6202,350 @@ pub fn load_library(base_path: String) -> Result<ParsedLibrary, String> {
                 .map(|al| al.name.clone())
                 .unwrap_or_else(|| "Unknown".to_string());
             let year = album.map(|al| al.year).unwrap_or(0);
+            let title = strings
+                .get(s.title_string_id as usize)
+                .cloned()
+                .unwrap_or_else(|| "Unknown".to_string());
+            let genre_id = ((s.genre_id as usize) < genre_names.len()).then_some(s.genre_id);
+            let path = strings
+                .get(s.path_string_id as usize)
+                .cloned()
+                .unwrap_or_else(|| "".to_string());
+            let confidence = confidence_index.songs.get(&path).copied();
             ParsedSong {
                 id: i as u32,
-                title: strings
-                    .get(s.title_string_id as usize)
-                    .cloned()
-                    .unwrap_or_else(|| "Unknown".to_string()),
+                romanized_title: romanized_key(&transliterations, &title),
+                title,
                 artist_id: s.artist_id,
                 artist_name,
                 album_id: s.album_id,
                 album_name,
                 year,
-                path: strings
-                    .get(s.path_string_id as usize)
-                    .cloned()
-                    .unwrap_or_else(|| "".to_string()),
+                path,
                 track_number: s.track_number,
+                disc_number: s.disc_number,
+                rating: s.rating,
+                is_favorite: s.flags & song_flags::FAVORITE != 0,
+                date_added: s.date_added,
+                play_count: s.play_count,
                 duration_sec: s.duration_sec,
+                genre: genre_id.map(|id| genre_names[id as usize].clone()),
+                genre_id,
+                metadata_source: s.metadata_source,
+                confidence_score: confidence.and_then(|c| c.overall()),
+                confidence,
             }
         })
         .collect();
 
-    // Collect IDs of artists and albums that have at least one active song
-    let active_artist_ids: HashSet<u32> = songs.iter().map(|s| s.artist_id).collect();
+    // Genres with active songs, by name
+    let mut genre_song_counts: HashMap<u16, u32> = HashMap::new();
+    for id in songs.iter().filter_map(|s| s.genre_id) {
+        *genre_song_counts.entry(id).or_default() += 1;
+    }
+    let mut genres: Vec<ParsedGenre> = genre_song_counts
+        .into_iter()
+        .map(|(id, song_count)| ParsedGenre {
+            id,
+            name: genre_names[id as usize].clone(),
+            song_count,
+        })
+        .collect();
+    genres.sort_by(|a, b| compare_names(&a.name, &b.name).then_with(|| a.id.cmp(&b.id)));
+
+    // Collect IDs of artists and albums that have at least one active song,
+    // plus the album artists of those albums
     let active_album_ids: HashSet<u32> = songs.iter().map(|s| s.album_id).collect();
+    let active_artist_ids: HashSet<u32> = songs
+        .iter()
+        .map(|s| s.artist_id)
+        .chain(
+            active_album_ids
+                .iter()
+                .filter_map(|&id| raw_albums.get(id as usize))
+                .map(|a| a.album_artist_id),
+        )
+        .collect();
 
-    // Filter artists to only those with active songs
-    let filtered_artists: Vec<ParsedArtist> = artists
+    // Filter artists to only those with active songs, in sort order
+    let mut filtered_artists: Vec<ParsedArtist> = artists
         .into_iter()
         .filter(|a| active_artist_ids.contains(&a.id))
         .collect();
+    filtered_artists.sort_by(|a, b| collate(&a.sort_name, &b.sort_name).then(a.id.cmp(&b.id)));
 
-    // Filter albums to only those with active songs
-    let filtered_albums: Vec<ParsedAlbum> = albums
+    // Filter albums to only those with active songs, in sort order
+    let mut filtered_albums: Vec<ParsedAlbum> = albums
         .into_iter()
         .filter(|a| active_album_ids.contains(&a.id))
         .collect();
+    filtered_albums.sort_by(|a, b| collate(&a.sort_name, &b.sort_name).then(a.id.cmp(&b.id)));
 
-    Ok(ParsedLibrary {
+    if let Some(sort) = sort {
+        sort_songs(&mut songs, sort);
+    }
+
+    let library = ParsedLibrary {
         version: header.version,
+        generation: header.generation,
         artists: filtered_artists,
         albums: filtered_albums,
         songs,
+        genres,
+    };
+    remember_snapshot(base, &library);
+    Ok(library)
+}
+
+/// Everything the album page shows: active songs in disc/track order, total
+/// duration, cover and how much space the audio takes.
+#[tauri::command]
+pub fn get_album(base_path: String, album_id: u32) -> Result<crate::models::AlbumDetail, String> {
+    let jp3_path = Path::new(&base_path).join(JP3_DIR);
+    let library = load_library(base_path.clone(), None)?;
+    let album = library
+        .albums
+        .into_iter()
+        .find(|album| album.id == album_id)
+        .ok_or_else(|| format!("Album {} not found", album_id))?;
+
+    let mut songs: Vec<ParsedSong> = library
+        .songs
+        .into_iter()
+        .filter(|song| song.album_id == album_id)
+        .collect();
+    songs.sort_by_key(|song| (song.disc_number, song.track_number, song.id));
+
+    let albums_dir = jp3_path.join(ASSETS_DIR).join(ALBUMS_DIR);
+    Ok(crate::models::AlbumDetail {
+        cover_path: get_cover_path_by_name(&albums_dir, &album.artist_name, &album.name),
+        total_duration_sec: total_duration_sec(&songs),
+        size_bytes: audio_size_bytes(&jp3_path.join(MUSIC_DIR), &songs),
+        album,
+        songs,
+    })
+}
+
+/// Everything the artist page shows: their albums, their songs (including
+/// other artists' tracks on their albums), years active, total duration,
+/// cover and how much space the audio takes.
+#[tauri::command]
+pub fn get_artist(
+    base_path: String,
+    artist_id: u32,
+) -> Result<crate::models::ArtistDetail, String> {
+    let jp3_path = Path::new(&base_path).join(JP3_DIR);
+    let library = load_library(base_path.clone(), None)?;
+    let artist = library
+        .artists
+        .into_iter()
+        .find(|artist| artist.id == artist_id)
+        .ok_or_else(|| format!("Artist {} not found", artist_id))?;
+
+    let mut albums: Vec<ParsedAlbum> = library
+        .albums
+        .into_iter()
+        .filter(|album| album.artist_id == artist_id)
+        .collect();
+    albums.sort_by_key(|album| (album.year, album.id));
+
+    let own_albums: HashSet<u32> = albums.iter().map(|album| album.id).collect();
+    let mut songs: Vec<ParsedSong> = library
+        .songs
+        .into_iter()
+        .filter(|song| song.artist_id == artist_id || own_albums.contains(&song.album_id))
+        .collect();
+    songs.sort_by_key(|song| {
+        (song.year, song.album_id, song.disc_number, song.track_number, song.id)
+    });
+
+    let years = songs.iter().map(|song| song.year).filter(|&year| year > 0);
+    let cover = jp3_path
+        .join(ASSETS_DIR)
+        .join(ARTISTS_DIR)
+        .join(format!("{}.jpg", cover_filename(&artist.name, "artist")));
+    Ok(crate::models::ArtistDetail {
+        first_year: years.clone().min(),
+        last_year: years.max(),
+        cover_path: cover.is_file().then(|| cover.to_string_lossy().to_string()),
+        total_duration_sec: total_duration_sec(&songs),
+        size_bytes: audio_size_bytes(&jp3_path.join(MUSIC_DIR), &songs),
+        artist,
+        albums,
+        songs,
+    })
+}
+
+fn total_duration_sec(songs: &[ParsedSong]) -> u32 {
+    songs.iter().map(|song| song.duration_sec as u32).sum()
+}
+
+/// Combined size of the songs' audio files; missing files count as nothing.
+fn audio_size_bytes(music_path: &Path, songs: &[ParsedSong]) -> u64 {
+    songs
+        .iter()
+        .filter_map(|song| fs::metadata(music_path.join(&song.path)).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Only what changed since `since_generation`, for refreshing after a save
+/// without reloading the whole library.
+///
+/// Works for any generation `load_library` returned recently; older ones get
+/// the whole library back with `full` set.
+#[tauri::command]
+pub fn load_library_delta(
+    base_path: String,
+    since_generation: u32,
+) -> Result<crate::models::LibraryDelta, String> {
+    let library = load_library(base_path.clone(), None)?;
+    Ok(match snapshot_at(Path::new(&base_path), since_generation) {
+        Some(old) => diff_libraries(&old, library),
+        None => full_delta(library),
     })
 }
 
+/// Songs whose automatic match scored below `threshold` (0-100), weakest
+/// first, for review.
+///
+/// Songs without a recorded confidence (tagged, entered or edited by hand)
+/// are never included.
+#[tauri::command]
+pub fn get_low_confidence_songs(base_path: String, threshold: u8) -> Result<Vec<ParsedSong>, String> {
+    let mut songs: Vec<ParsedSong> = load_library(base_path, None)?
+        .songs
+        .into_iter()
+        .filter(|s| s.confidence_score.is_some_and(|score| score < threshold))
+        .collect();
+    songs.sort_by(|a, b| {
+        a.confidence_score
+            .cmp(&b.confidence_score)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    Ok(songs)
+}
+
+/// Favorite songs, in `load_library` order.
+#[tauri::command]
+pub fn get_favorites(base_path: String) -> Result<Vec<ParsedSong>, String> {
+    Ok(load_library(base_path, None)?
+        .songs
+        .into_iter()
+        .filter(|s| s.is_favorite)
+        .collect())
+}
+
+/// Load one page of artists, albums or songs (in `load_library` order, or
+/// newest first for `RecentlyAdded`).
+///
+/// The first call (no `snapshot`) returns a snapshot token tied to the
+/// library's generation counter. Passing it back on later calls keeps all
+/// pages from the same version of the library; if library.bin was written
+/// in between, a `SnapshotExpired` error is returned instead of a page that
+/// could skip or repeat rows.
+///
+/// `filter` narrows albums and songs to one artist or album, so a detail
+/// view can page through just its own rows.
+#[tauri::command]
+pub fn load_library_page(
+    base_path: String,
+    kind: LibraryPageKind,
+    offset: u32,
+    limit: u32,
+    snapshot: Option<String>,
+    filter: Option<crate::models::LibraryPageFilter>,
+) -> Result<LibraryPage, String> {
+    let library = load_library(base_path, None)?;
+    let token = format!("gen-{}", library.generation);
+
+    if let Some(snapshot) = snapshot {
+        if snapshot != token {
+            return Err(format!(
+                "{}: The library changed since this list was loaded ({} -> {}). Refresh to continue.",
+                SNAPSHOT_EXPIRED, snapshot, token
+            ));
+        }
+    }
+
+    let filter = filter.unwrap_or_default();
+    let keep_song = |song: &ParsedSong| {
+        filter.artist_id.is_none_or(|id| song.artist_id == id)
+            && filter.album_id.is_none_or(|id| song.album_id == id)
+    };
+
+    let start = offset as usize;
+    let limit = limit as usize;
+    let mut page = LibraryPage {
+        snapshot: token,
+        offset,
+        total: 0,
+        artists: Vec::new(),
+        albums: Vec::new(),
+        songs: Vec::new(),
+    };
+    match kind {
+        LibraryPageKind::Artists => {
+            page.total = library.artists.len() as u32;
+            page.artists = library.artists.into_iter().skip(start).take(limit).collect();
+        }
+        LibraryPageKind::Albums => {
+            let albums: Vec<_> = library
+                .albums
+                .into_iter()
+                .filter(|album| filter.artist_id.is_none_or(|id| album.artist_id == id))
+                .collect();
+            page.total = albums.len() as u32;
+            page.albums = albums.into_iter().skip(start).take(limit).collect();
+        }
+        LibraryPageKind::Songs => {
+            let songs: Vec<_> = library.songs.into_iter().filter(keep_song).collect();
+            page.total = songs.len() as u32;
+            page.songs = songs.into_iter().skip(start).take(limit).collect();
+        }
+        LibraryPageKind::RecentlyAdded => {
+            let mut songs: Vec<_> = library.songs.into_iter().filter(keep_song).collect();
+            songs.sort_by(|a, b| {
+                b.date_added
+                    .cmp(&a.date_added)
+                    .then_with(|| b.id.cmp(&a.id))
+            });
+            page.total = songs.len() as u32;
+            page.songs = songs.into_iter().skip(start).take(limit).collect();
+        }
+    }
+
+    Ok(page)
+}
+
+/// Read a possibly damaged string table as far as it goes.
+///
+/// Strings that aren't valid UTF-8 come back as None. Returns why the walk
+/// stopped early, if it did.
+fn walk_string_table(
+    data: &[u8],
+    start: usize,
+    end: usize,
+) -> (Vec<Option<String>>, Option<String>) {
+    let end = end.min(data.len());
+    let mut strings = Vec::new();
+    let mut pos = start;
+    while pos < end {
+        if pos + 2 > end {
+            return (strings, Some("String length cut short".to_string()));
+        }
+        let len = u16::from_le_bytes([data[pos], data[pos + 1]]) as usize;
+        pos += 2;
+        if pos + len > end {
+            let error = format!("String {} extends past the string table", strings.len());
+            return (strings, Some(error));
+        }
+        strings.push(String::from_utf8(data[pos..pos + len].to_vec()).ok());
+        pos += len;
+    }
+    (strings, None)
+}
+
 /// Parse the string table from binary data.
 fn parse_string_table(data: &[u8], start: usize, end: usize) -> Result<Vec<String>, String> {
     let mut strings = Vec::new();
@@ -1724,16 +6575,23 @@ fn parse_string_table(data: &[u8], start: usize, end: usize) -> Result<Vec<Strin
 /// Raw artist entry from binary (before name resolution).
 struct RawArtist {
     name_string_id: u32,
+    /// None for libraries written before sort names existed
+    sort_name_string_id: Option<u32>,
 }
 
 /// Parse artist table from binary data.
-fn parse_artist_table(data: &[u8], start: usize, count: usize) -> Result<Vec<RawArtist>, String> {
+fn parse_artist_table(
+    data: &[u8],
+    start: usize,
+    count: usize,
+    version: u32,
+) -> Result<Vec<RawArtist>, String> {
     let mut artists = Vec::with_capacity(count);
     let entry_size = ArtistEntry::SIZE as usize;
 
     for i in 0..count {
         let offset = start + i * entry_size;
-        if offset + 4 > data.len() {
+        if offset + entry_size > data.len() {
             return Err("Artist table extends beyond file".to_string());
         }
         let name_string_id = u32::from_le_bytes(
@@ -1741,7 +6599,19 @@ fn parse_artist_table(data: &[u8], start: usize, count: usize) -> Result<Vec<Raw
                 .try_into()
                 .map_err(|_| "Failed to read artist name_string_id")?,
         );
-        artists.push(RawArtist { name_string_id });
+        let sort_name_string_id = if version >= SORT_NAMES_VERSION {
+            Some(u32::from_le_bytes(
+                data[offset + 4..offset + 8]
+                    .try_into()
+                    .map_err(|_| "Failed to read artist sort_name_string_id")?,
+            ))
+        } else {
+            None
+        };
+        artists.push(RawArtist {
+            name_string_id,
+            sort_name_string_id,
+        });
     }
 
     Ok(artists)
@@ -1750,18 +6620,25 @@ fn parse_artist_table(data: &[u8], start: usize, count: usize) -> Result<Vec<Raw
 /// Raw album entry from binary (before name resolution).
 struct RawAlbum {
     name_string_id: u32,
-    artist_id: u32,
+    album_artist_id: u32,
     year: u16,
+    /// None for libraries written before sort names existed
+    sort_name_string_id: Option<u32>,
 }
 
 /// Parse album table from binary data.
-fn parse_album_table(data: &[u8], start: usize, count: usize) -> Result<Vec<RawAlbum>, String> {
+fn parse_album_table(
+    data: &[u8],
+    start: usize,
+    count: usize,
+    version: u32,
+) -> Result<Vec<RawAlbum>, String> {
     let mut albums = Vec::with_capacity(count);
     let entry_size = AlbumEntry::SIZE as usize;
 
     for i in 0..count {
         let offset = start + i * entry_size;
-        if offset + 10 > data.len() {
+        if offset + entry_size > data.len() {
             return Err("Album table extends beyond file".to_string());
         }
         let name_string_id = u32::from_le_bytes(
@@ -1769,20 +6646,30 @@ fn parse_album_table(data: &[u8], start: usize, count: usize) -> Result<Vec<RawA
                 .try_into()
                 .map_err(|_| "Failed to read album name_string_id")?,
         );
-        let artist_id = u32::from_le_bytes(
+        let album_artist_id = u32::from_le_bytes(
             data[offset + 4..offset + 8]
                 .try_into()
-                .map_err(|_| "Failed to read album artist_id")?,
+                .map_err(|_| "Failed to read album album_artist_id")?,
         );
         let year = u16::from_le_bytes(
             data[offset + 8..offset + 10]
                 .try_into()
                 .map_err(|_| "Failed to read album year")?,
         );
+        let sort_name_string_id = if version >= SORT_NAMES_VERSION {
+            Some(u32::from_le_bytes(
+                data[offset + 12..offset + 16]
+                    .try_into()
+                    .map_err(|_| "Failed to read album sort_name_string_id")?,
+            ))
+        } else {
+            None
+        };
         albums.push(RawAlbum {
             name_string_id,
-            artist_id,
+            album_artist_id,
             year,
+            sort_name_string_id,
         });
     }
 
@@ -1798,16 +6685,35 @@ struct RawSong {
     track_number: u16,
     duration_sec: u16,
     flags: u8,
+    /// Unknown for songs saved before sources were recorded
+    metadata_source: MetadataSource,
+    /// NO_GENRE for libraries written before genres existed
+    genre_id: u16,
+    /// 0 for libraries written before disc numbers existed
+    disc_number: u8,
+    /// 0 (unrated) for libraries written before ratings existed
+    rating: u8,
+    /// 0 (unknown) for libraries written before dates were recorded
+    date_added: u32,
+    /// 0 for libraries written before play counts were imported
+    play_count: u16,
 }
 
 /// Parse song table from binary data.
-fn parse_song_table(data: &[u8], start: usize, count: usize) -> Result<Vec<RawSong>, String> {
+fn parse_song_table(
+    data: &[u8],
+    start: usize,
+    count: usize,
+    version: u32,
+) -> Result<Vec<RawSong>, String> {
     let mut songs = Vec::with_capacity(count);
-    let entry_size = SongEntry::SIZE as usize;
+    let entry_size = SongEntry::size_for(version) as usize;
+    // Older files may end right after the flags byte of the last song
+    let needed = if version >= GENRES_VERSION { entry_size } else { 21 };
 
     for i in 0..count {
         let offset = start + i * entry_size;
-        if offset + 21 > data.len() {
+        if offset + needed > data.len() {
             return Err("Song table extends beyond file".to_string());
         }
         let title_string_id = u32::from_le_bytes(
@@ -1841,6 +6747,33 @@ fn parse_song_table(data: &[u8], start: usize, count: usize) -> Result<Vec<RawSo
                 .map_err(|_| "Failed to read song duration_sec")?,
         );
         let flags = data[offset + 20];
+        let metadata_source = data
+            .get(offset + 21)
+            .map_or(MetadataSource::Unknown, |&b| MetadataSource::from_byte(b));
+        let genre_id = if version >= GENRES_VERSION {
+            u16::from_le_bytes(
+                data[offset + 22..offset + 24]
+                    .try_into()
+                    .map_err(|_| "Failed to read song genre_id")?,
+            )
+        } else {
+            NO_GENRE
+        };
+        let (disc_number, rating, date_added, play_count) = if version >= DISC_NUMBERS_VERSION {
+            let date_added = u32::from_le_bytes(
+                data[offset + 26..offset + 30]
+                    .try_into()
+                    .map_err(|_| "Failed to read song date_added")?,
+            );
+            let play_count = u16::from_le_bytes(
+                data[offset + 30..offset + 32]
+                    .try_into()
+                    .map_err(|_| "Failed to read song play_count")?,
+            );
+            (data[offset + 24], data[offset + 25], date_added, play_count)
+        } else {
+            (0, 0, 0, 0)
+        };
         songs.push(RawSong {
             title_string_id,
             artist_id,
@@ -1849,8 +6782,28 @@ fn parse_song_table(data: &[u8], start: usize, count: usize) -> Result<Vec<RawSo
             track_number,
             duration_sec,
             flags,
+            metadata_source,
+            genre_id,
+            disc_number,
+            rating,
+            date_added,
+            play_count,
         });
     }
 
     Ok(songs)
 }
+
+/// Parse genre table from binary data into genre name string IDs.
+fn parse_genre_table(data: &[u8], start: usize, count: usize) -> Result<Vec<u32>, String> {
+    let entry_size = GenreEntry::SIZE as usize;
+    (0..count)
+        .map(|i| {
+            let offset = start + i * entry_size;
+            data.get(offset..offset + entry_size)
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(u32::from_le_bytes)
+                .ok_or_else(|| "Genre table extends beyond file".to_string())
+        })
+        .collect()
+}
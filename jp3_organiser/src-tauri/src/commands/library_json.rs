@@ -0,0 +1,237 @@
+//! Library export and import as JSON.
+//!
+//! Handles:
+//! - Writing every song (tags, rating, favorite, play count) and playlist
+//!   to a JSON file
+//! - Recreating a library from that file, copying the audio it references
+//!
+//! Songs point at their audio by path relative to `jp3/music`, so the JSON
+//! plus the old library's music folder is enough to move a library to
+//! another machine. IDs in the file only tie playlists to songs; the
+//! importing library assigns its own.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::commands::library::{
+    initialize_library, load_library, save_to_library, toggle_favorite, FileToSave,
+};
+use crate::commands::playlist::{create_playlist, list_playlists, load_playlist};
+use crate::models::{
+    AudioMetadata, ExportLibraryJsonResult, ImportLibraryJsonResult, LibraryJson, PlaylistJson,
+    SongJson, LIBRARY_JSON_VERSION,
+};
+use crate::services::library_monitor_service::ensure_library_available;
+
+/// Write the library's songs and playlists to `dest` as JSON.
+///
+/// Deleted songs are left out, and so are playlist entries pointing at them.
+#[tauri::command]
+pub fn export_library_json(
+    base_path: String,
+    dest: String,
+) -> Result<ExportLibraryJsonResult, String> {
+    let library = load_library(base_path.clone(), None)?;
+    let album_artists: HashMap<u32, (u32, &str)> = library
+        .albums
+        .iter()
+        .map(|album| (album.id, (album.artist_id, album.artist_name.as_str())))
+        .collect();
+
+    let songs: Vec<SongJson> = library
+        .songs
+        .iter()
+        .map(|song| SongJson {
+            id: song.id,
+            title: song.title.clone(),
+            artist: song.artist_name.clone(),
+            album: song.album_name.clone(),
+            album_artist: album_artists
+                .get(&song.album_id)
+                .filter(|(artist_id, _)| *artist_id != song.artist_id)
+                .map(|(_, name)| name.to_string()),
+            year: song.year,
+            track_number: song.track_number,
+            disc_number: song.disc_number,
+            genre: song.genre.clone(),
+            duration_sec: song.duration_sec,
+            rating: song.rating,
+            is_favorite: song.is_favorite,
+            play_count: song.play_count,
+            metadata_source: song.metadata_source,
+            path: song.path.clone(),
+        })
+        .collect();
+
+    let live: HashSet<u32> = songs.iter().map(|song| song.id).collect();
+    let playlists = list_playlists(base_path.clone())?
+        .into_iter()
+        .map(|summary| {
+            let playlist = load_playlist(base_path.clone(), summary.id)?;
+            Ok(PlaylistJson {
+                name: playlist.name,
+                song_ids: playlist
+                    .song_ids
+                    .into_iter()
+                    .filter(|id| live.contains(id))
+                    .collect(),
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let export = LibraryJson {
+        format_version: LIBRARY_JSON_VERSION,
+        exported_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        songs,
+        playlists,
+    };
+    let data = serde_json::to_string_pretty(&export)
+        .map_err(|e| format!("Failed to serialize library: {}", e))?;
+    fs::write(&dest, data).map_err(|e| format!("Failed to write {}: {}", dest, e))?;
+
+    log::info!(
+        "[export_library_json] Exported {} songs and {} playlists to {}",
+        export.songs.len(),
+        export.playlists.len(),
+        dest
+    );
+    Ok(ExportLibraryJsonResult {
+        path: dest,
+        song_count: export.songs.len() as u32,
+        playlist_count: export.playlists.len() as u32,
+    })
+}
+
+/// Recreate a library from a JSON export, copying the audio it references.
+///
+/// Audio is read from `source_path`'s `jp3/music`; by default the folder the
+/// JSON file is in (where an export into the library's own folder lands).
+/// Songs go through `save_to_library`, so a library that already exists is
+/// added to and songs it has are skipped. Favorites and playlists are
+/// restored against the IDs the songs get; songs whose audio is missing
+/// are skipped and listed.
+#[tauri::command]
+pub fn import_library_json(
+    base_path: String,
+    json_path: String,
+    source_path: Option<String>,
+) -> Result<ImportLibraryJsonResult, String> {
+    let data =
+        fs::read_to_string(&json_path).map_err(|e| format!("Failed to read {}: {}", json_path, e))?;
+    let import: LibraryJson =
+        serde_json::from_str(&data).map_err(|e| format!("Failed to parse library JSON: {}", e))?;
+    if import.format_version > LIBRARY_JSON_VERSION {
+        return Err(format!(
+            "Library JSON format v{} is newer than this app supports (v{})",
+            import.format_version, LIBRARY_JSON_VERSION
+        ));
+    }
+
+    let source_base = match source_path {
+        Some(path) => PathBuf::from(path),
+        None => Path::new(&json_path)
+            .parent()
+            .map(Path::to_path_buf)
+            .ok_or("The JSON file has no parent folder; give source_path")?,
+    };
+    let source_music = source_base.join("jp3").join("music");
+
+    let base = Path::new(&base_path);
+    if !base.join("jp3").join("metadata").join("library.bin").exists() {
+        initialize_library(base_path.clone())?;
+    }
+    ensure_library_available(base)?;
+
+    let (present, missing): (Vec<&SongJson>, Vec<&SongJson>) = import
+        .songs
+        .iter()
+        .partition(|song| source_music.join(&song.path).is_file());
+    let files = present
+        .iter()
+        .map(|song| FileToSave {
+            source_path: source_music.join(&song.path).to_string_lossy().to_string(),
+            metadata: song_metadata(song),
+            metadata_source: song.metadata_source,
+            confidence: None,
+            fingerprint: None,
+        })
+        .collect();
+    let saved = save_to_library(base_path.clone(), files)?;
+
+    // Songs are keyed by (title, artist, album) in the library, so the
+    // imported songs are found again by name
+    let library = load_library(base_path.clone(), None)?;
+    let by_name: HashMap<(&str, &str, &str), (u32, bool)> = library
+        .songs
+        .iter()
+        .map(|song| {
+            let key = (song.title.as_str(), song.artist_name.as_str(), song.album_name.as_str());
+            (key, (song.id, song.is_favorite))
+        })
+        .collect();
+    let new_ids: HashMap<u32, (u32, bool)> = present
+        .iter()
+        .filter_map(|song| {
+            let key = (song.title.as_str(), song.artist.as_str(), song.album.as_str());
+            Some((song.id, *by_name.get(&key)?))
+        })
+        .collect();
+
+    let mut favorites_restored = 0u32;
+    for song in present.iter().filter(|song| song.is_favorite) {
+        if let Some(&(id, false)) = new_ids.get(&song.id) {
+            toggle_favorite(base_path.clone(), id)?;
+            favorites_restored += 1;
+        }
+    }
+
+    let mut playlists_created = 0u32;
+    for playlist in &import.playlists {
+        let song_ids: Vec<u32> = playlist
+            .song_ids
+            .iter()
+            .filter_map(|id| new_ids.get(id).map(|(new_id, _)| *new_id))
+            .collect();
+        create_playlist(base_path.clone(), playlist.name.clone(), song_ids)?;
+        playlists_created += 1;
+    }
+
+    let files_missing: Vec<String> = missing.iter().map(|song| song.path.clone()).collect();
+    log::info!(
+        "[import_library_json] Imported {} songs ({} already present, {} missing), {} playlists",
+        saved.songs_added,
+        saved.duplicates_skipped,
+        files_missing.len(),
+        playlists_created
+    );
+    Ok(ImportLibraryJsonResult {
+        songs_imported: saved.songs_added,
+        duplicates_skipped: saved.duplicates_skipped,
+        files_missing,
+        favorites_restored,
+        playlists_created,
+    })
+}
+
+/// Tags `save_to_library` stores, from an exported song.
+fn song_metadata(song: &SongJson) -> AudioMetadata {
+    AudioMetadata {
+        title: Some(song.title.clone()),
+        artist: Some(song.artist.clone()),
+        album: Some(song.album.clone()),
+        track_number: (song.track_number > 0).then_some(song.track_number as u32),
+        year: (song.year > 0).then_some(song.year as i32),
+        duration_secs: Some(song.duration_sec as u32),
+        genre: song.genre.clone(),
+        album_artist: song.album_artist.clone(),
+        disc_number: (song.disc_number > 0).then_some(song.disc_number as u32),
+        rating: (song.rating > 0).then_some(song.rating),
+        play_count: (song.play_count > 0).then_some(song.play_count as u32),
+        ..AudioMetadata::default()
+    }
+}
@@ -3,18 +3,49 @@
 //! Commands are organized by domain:
 //! - `config`: Application configuration (library path storage)
 //! - `library`: Library initialization and management
+//! - `library_json`: Library export and import as JSON
 //! - `audio`: Audio file processing and metadata extraction
 //! - `playlist`: Playlist management
 //! - `cover_art`: Album cover art fetching and caching
+//! - `session`: Import sessions held in managed state
+//! - `fingerprint`: Stored fingerprint maintenance
+//! - `import`: Scanning existing music folders into an import plan, or
+//!   adopting them in place
+//! - `itunes_import`: Importing an iTunes / Music.app Library.xml
+//! - `enrichment`: Filling in missing library metadata and search keys
+//! - `replay_gain`: Track and album loudness analysis
+//! - `tag_cleanup`: Rewriting MP3 tags to a clean ID3v2.4 set
+//! - `search`: Accent- and script-insensitive library search
+//! - `shutdown`: Closing the app safely while background work runs
 
 pub mod audio;
 pub mod config;
 pub mod cover_art;
+pub mod enrichment;
+pub mod fingerprint;
+pub mod import;
+pub mod itunes_import;
 pub mod library;
+pub mod library_json;
 pub mod playlist;
+pub mod replay_gain;
+pub mod search;
+pub mod session;
+pub mod shutdown;
+pub mod tag_cleanup;
 
 pub use audio::*;
 pub use config::*;
 pub use cover_art::*;
+pub use enrichment::*;
+pub use fingerprint::*;
+pub use import::*;
+pub use itunes_import::*;
 pub use library::*;
+pub use library_json::*;
 pub use playlist::*;
+pub use replay_gain::*;
+pub use search::*;
+pub use session::*;
+pub use shutdown::*;
+pub use tag_cleanup::*;
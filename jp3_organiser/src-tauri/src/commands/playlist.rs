@@ -7,16 +7,42 @@ use std::collections::{HashMap, HashSet};
 use std::fs::{self};
 use std::io::{Read, Write};
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::commands::library::{announce, read_song_flags};
 use crate::models::{
-    AudioMetadata, CreatePlaylistResult, DeletePlaylistResult, ParsedPlaylist, PlaylistHeader,
-    PlaylistSummary, SaveToPlaylistResult, PLAYLIST_HEADER_SIZE,
+    song_flags, AudioMetadata, AutoPlaylistKind, AutoPlaylistSettings, CreatePlaylistResult,
+    DeletePlaylistResult, MatchConfidence, MetadataSource, ParsedPlaylist, PlaylistEntry,
+    PlaylistHeader, PlaylistSummary, PlaylistValidationReport, SaveToPlaylistResult,
+    SyncAutoPlaylistsResult, PLAYLIST_VERSION,
 };
+use crate::services::auto_playlist_service::{
+    auto_playlist_song_ids, load_auto_playlists, save_auto_playlists,
+};
+use crate::services::device_service::read_device_marker;
+use crate::services::library_events_service::EVENT_PLAYLIST_CHANGED;
+use crate::services::library_monitor_service::ensure_library_available;
+use crate::services::sort_name_service::compare_names;
 
 // Directory constants
 const JP3_DIR: &str = "jp3";
 const PLAYLISTS_DIR: &str = "playlists";
 
+/// Emit `library://playlist-changed` for `playlist_ids`, if there are any.
+pub(crate) fn announce_playlist_change(base: &Path, playlist_ids: Vec<u32>) {
+    if playlist_ids.is_empty() {
+        return;
+    }
+    announce(
+        EVENT_PLAYLIST_CHANGED,
+        base,
+        crate::models::LibraryChangeEvent {
+            playlist_ids,
+            ..Default::default()
+        },
+    );
+}
+
 /// Get the playlists directory path.
 fn get_playlists_path(base_path: &Path) -> std::path::PathBuf {
     base_path.join(JP3_DIR).join(PLAYLISTS_DIR)
@@ -50,16 +76,36 @@ fn get_next_playlist_id(playlists_path: &Path) -> Result<u32, String> {
     Ok(max_id + 1)
 }
 
+/// Current time in unix seconds, for playlist entry timestamps.
+fn now_unix_secs() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as u32)
+        .unwrap_or(0)
+}
+
 /// Create a new playlist with the given songs.
 ///
-/// The songs must already exist in library.bin.
+/// The songs must already exist in library.bin. The order given is kept
+/// as a manual order.
 #[tauri::command]
 pub fn create_playlist(
     base_path: String,
     name: String,
     song_ids: Vec<u32>,
 ) -> Result<CreatePlaylistResult, String> {
-    let base = Path::new(&base_path);
+    create_playlist_file(&base_path, &name, &song_ids, true)
+}
+
+/// Write a new playlist file, every song added now.
+fn create_playlist_file(
+    base_path: &str,
+    name: &str,
+    song_ids: &[u32],
+    manual_order: bool,
+) -> Result<CreatePlaylistResult, String> {
+    let base = Path::new(base_path);
+    ensure_library_available(base)?;
     let playlists_path = get_playlists_path(base);
 
     // Ensure playlists directory exists
@@ -71,7 +117,13 @@ pub fn create_playlist(
 
     // Write playlist file
     let playlist_file_path = playlists_path.join(format!("{}.bin", playlist_id));
-    write_playlist_file(&playlist_file_path, &name, &song_ids)?;
+    let added_at = now_unix_secs();
+    let entries: Vec<PlaylistEntry> = song_ids
+        .iter()
+        .map(|&song_id| PlaylistEntry::new(song_id, added_at))
+        .collect();
+    write_playlist_file(&playlist_file_path, name, &entries, manual_order)?;
+    announce_playlist_change(base, vec![playlist_id]);
 
     Ok(CreatePlaylistResult {
         playlist_id,
@@ -79,11 +131,61 @@ pub fn create_playlist(
     })
 }
 
+/// Newest playlist version the firmware on the card holding `path`
+/// ({base}/jp3/playlists/{id}.bin) reads, if it declared it or its release
+/// is in the compatibility table.
+fn firmware_max_playlist_version(path: &Path) -> Option<u32> {
+    let base = path.ancestors().nth(3)?;
+    read_device_marker(base)
+        .ok()
+        .flatten()?
+        .max_playlist_version()
+}
+
 /// Write a playlist binary file.
-pub fn write_playlist_file(path: &Path, name: &str, song_ids: &[u32]) -> Result<(), String> {
+///
+/// Uses the current format unless the card's firmware declared it only
+/// reads v1 playlists, in which case the v1 layout is written instead.
+pub fn write_playlist_file(
+    path: &Path,
+    name: &str,
+    entries: &[PlaylistEntry],
+    manual_order: bool,
+) -> Result<(), String> {
+    if firmware_max_playlist_version(path).is_some_and(|max| max < PLAYLIST_VERSION) {
+        let song_ids: Vec<u32> = entries.iter().map(|entry| entry.song_id).collect();
+        return write_playlist_file_v1(path, name, &song_ids);
+    }
+
+    let name_bytes = name.as_bytes();
+    let header = PlaylistHeader::new(entries.len() as u32, name_bytes.len() as u16, manual_order);
+
+    let mut entry_bytes = Vec::with_capacity(entries.len() * PlaylistEntry::SIZE);
+    for entry in entries {
+        entry_bytes.extend_from_slice(&entry.song_id.to_le_bytes());
+        entry_bytes.extend_from_slice(&entry.added_at.to_le_bytes());
+    }
+    write_playlist_bytes(path, &header, name_bytes, &entry_bytes)
+}
+
+/// Write a playlist in the v1 layout (song IDs only, no flags).
+///
+/// For device firmware that only reads v1 playlists; the added timestamps
+/// and order flag are dropped.
+pub fn write_playlist_file_v1(path: &Path, name: &str, song_ids: &[u32]) -> Result<(), String> {
     let name_bytes = name.as_bytes();
-    let header = PlaylistHeader::new(song_ids.len() as u32, name_bytes.len() as u16);
+    let header = PlaylistHeader::new_v1(song_ids.len() as u32, name_bytes.len() as u16);
+
+    let entry_bytes: Vec<u8> = song_ids.iter().flat_map(|id| id.to_le_bytes()).collect();
+    write_playlist_bytes(path, &header, name_bytes, &entry_bytes)
+}
 
+fn write_playlist_bytes(
+    path: &Path,
+    header: &PlaylistHeader,
+    name_bytes: &[u8],
+    entry_bytes: &[u8],
+) -> Result<(), String> {
     let mut file =
         fs::File::create(path).map_err(|e| format!("Failed to create playlist file: {}", e))?;
 
@@ -95,11 +197,9 @@ pub fn write_playlist_file(path: &Path, name: &str, song_ids: &[u32]) -> Result<
     file.write_all(name_bytes)
         .map_err(|e| format!("Failed to write playlist name: {}", e))?;
 
-    // Write song IDs
-    for song_id in song_ids {
-        file.write_all(&song_id.to_le_bytes())
-            .map_err(|e| format!("Failed to write song ID: {}", e))?;
-    }
+    // Write song entries
+    file.write_all(entry_bytes)
+        .map_err(|e| format!("Failed to write song IDs: {}", e))?;
 
     file.sync_all()
         .map_err(|e| format!("Failed to sync playlist file: {}", e))?;
@@ -111,6 +211,7 @@ pub fn write_playlist_file(path: &Path, name: &str, song_ids: &[u32]) -> Result<
 #[tauri::command]
 pub fn load_playlist(base_path: String, playlist_id: u32) -> Result<ParsedPlaylist, String> {
     let base = Path::new(&base_path);
+    ensure_library_available(base)?;
     let playlists_path = get_playlists_path(base);
     let playlist_file_path = playlists_path.join(format!("{}.bin", playlist_id));
 
@@ -123,6 +224,21 @@ pub fn load_playlist(base_path: String, playlist_id: u32) -> Result<ParsedPlayli
 
 /// Read and parse a playlist binary file.
 pub fn read_playlist_file(path: &Path, playlist_id: u32) -> Result<ParsedPlaylist, String> {
+    let (playlist, truncated) = read_playlist_file_lenient(path, playlist_id)?;
+    if truncated {
+        return Err("Playlist file truncated (song IDs)".to_string());
+    }
+    Ok(playlist)
+}
+
+/// Read a playlist, keeping the song IDs before a truncated end.
+///
+/// Returns the playlist and whether its song IDs were cut short. A damaged
+/// header or name is still an error. v1 files are migrated in memory.
+fn read_playlist_file_lenient(
+    path: &Path,
+    playlist_id: u32,
+) -> Result<(ParsedPlaylist, bool), String> {
     let mut file =
         fs::File::open(path).map_err(|e| format!("Failed to open playlist file: {}", e))?;
     let mut data = Vec::new();
@@ -133,7 +249,7 @@ pub fn read_playlist_file(path: &Path, playlist_id: u32) -> Result<ParsedPlaylis
     let header = PlaylistHeader::from_bytes(&data).ok_or("Invalid playlist file header")?;
 
     // Parse name
-    let name_start = PLAYLIST_HEADER_SIZE;
+    let name_start = header.size();
     let name_end = name_start + header.name_length as usize;
     if name_end > data.len() {
         return Err("Playlist file truncated (name)".to_string());
@@ -141,13 +257,17 @@ pub fn read_playlist_file(path: &Path, playlist_id: u32) -> Result<ParsedPlaylis
     let name = String::from_utf8(data[name_start..name_end].to_vec())
         .map_err(|_| "Invalid UTF-8 in playlist name")?;
 
-    // Parse song IDs
+    // Parse song entries (v1 entries have no added_at)
     let songs_start = name_end;
+    let entry_size = header.entry_size();
     let mut song_ids = Vec::with_capacity(header.song_count as usize);
+    let mut added_at = Vec::with_capacity(header.song_count as usize);
+    let mut truncated = false;
     for i in 0..header.song_count as usize {
-        let offset = songs_start + i * 4;
-        if offset + 4 > data.len() {
-            return Err("Playlist file truncated (song IDs)".to_string());
+        let offset = songs_start + i * entry_size;
+        if offset + entry_size > data.len() {
+            truncated = true;
+            break;
         }
         let song_id = u32::from_le_bytes(
             data[offset..offset + 4]
@@ -155,20 +275,77 @@ pub fn read_playlist_file(path: &Path, playlist_id: u32) -> Result<ParsedPlaylis
                 .map_err(|_| "Failed to read song ID")?,
         );
         song_ids.push(song_id);
+        added_at.push(if entry_size >= PlaylistEntry::SIZE {
+            u32::from_le_bytes(
+                data[offset + 4..offset + 8]
+                    .try_into()
+                    .map_err(|_| "Failed to read song added_at")?,
+            )
+        } else {
+            0
+        });
     }
 
-    Ok(ParsedPlaylist {
+    let playlist = ParsedPlaylist {
         id: playlist_id,
         name,
-        song_count: header.song_count,
+        song_count: song_ids.len() as u32,
         song_ids,
-    })
+        added_at,
+        manual_order: header.is_manual_order(),
+    };
+    Ok((playlist, truncated))
+}
+
+/// Rewrite playlists newer than the card's firmware reads in the v1 layout.
+///
+/// Playlists written after the firmware declared its playlist version are
+/// already in the layout it reads; this converts the ones from before.
+/// Returns the number of playlists rewritten.
+#[tauri::command]
+pub fn convert_playlists_for_firmware(base_path: String) -> Result<u32, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let Some(max) = read_device_marker(base)?.and_then(|m| m.max_playlist_version())
+    else {
+        return Ok(0);
+    };
+    let playlists_path = get_playlists_path(base);
+    if !playlists_path.exists() {
+        return Ok(0);
+    }
+
+    let entries = fs::read_dir(&playlists_path)
+        .map_err(|e| format!("Failed to read playlists directory: {}", e))?;
+
+    let mut converted = Vec::new();
+    for entry in entries.flatten() {
+        let Some(playlist_id) = parse_playlist_id(&entry) else {
+            continue;
+        };
+        let path = entry.path();
+        let too_new = fs::read(&path)
+            .ok()
+            .and_then(|data| PlaylistHeader::from_bytes(&data))
+            .is_some_and(|header| header.version > max);
+        if !too_new {
+            continue;
+        }
+        let playlist = read_playlist_file(&path, playlist_id)?;
+        write_playlist_file_v1(&path, &playlist.name, &playlist.song_ids)?;
+        converted.push(playlist_id);
+    }
+
+    let count = converted.len() as u32;
+    announce_playlist_change(base, converted);
+    Ok(count)
 }
 
 /// List all playlists (summaries only, not full song lists).
 #[tauri::command]
 pub fn list_playlists(base_path: String) -> Result<Vec<PlaylistSummary>, String> {
     let base = Path::new(&base_path);
+    ensure_library_available(base)?;
     let playlists_path = get_playlists_path(base);
 
     if !playlists_path.exists() {
@@ -192,7 +369,7 @@ pub fn list_playlists(base_path: String) -> Result<Vec<PlaylistSummary>, String>
         .collect();
 
     // Sort by name for easier lookup
-    playlists.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    playlists.sort_by(|a, b| compare_names(&a.name, &b.name));
 
     Ok(playlists)
 }
@@ -207,6 +384,7 @@ pub fn delete_playlist_by_name(
     playlist_name: String,
 ) -> Result<DeletePlaylistResult, String> {
     let base = Path::new(&base_path);
+    ensure_library_available(base)?;
     let playlists_path = get_playlists_path(base);
 
     if !playlists_path.exists() {
@@ -227,6 +405,7 @@ pub fn delete_playlist_by_name(
         if playlist.name == playlist_name {
             fs::remove_file(&entry.path())
                 .map_err(|e| format!("Failed to delete playlist file: {}", e))?;
+            announce_playlist_change(base, vec![playlist_id]);
             return Ok(DeletePlaylistResult { deleted: true });
         }
     }
@@ -240,6 +419,10 @@ pub fn delete_playlist_by_name(
 pub struct FileToSaveWithPlaylist {
     pub source_path: String,
     pub metadata: AudioMetadata,
+    #[serde(default)]
+    pub metadata_source: MetadataSource,
+    #[serde(default)]
+    pub confidence: Option<MatchConfidence>,
 }
 
 /// Save audio files to the library AND create a playlist with them.
@@ -261,6 +444,9 @@ pub fn save_to_playlist(
         .map(|f| crate::commands::library::FileToSave {
             source_path: f.source_path,
             metadata: f.metadata,
+            metadata_source: f.metadata_source,
+            confidence: f.confidence,
+            fingerprint: None,
         })
         .collect();
 
@@ -269,7 +455,7 @@ pub fn save_to_playlist(
 
     // Now we need to get the song IDs for the playlist
     // Load the library to find the song IDs
-    let library = crate::commands::load_library(base_path.clone())?;
+    let library = crate::commands::load_library(base_path.clone(), None)?;
 
     // Build a lookup map: (title, artist_name, album_name) -> song_id
     let mut song_lookup: HashMap<(String, String, String), u32> = HashMap::new();
@@ -302,8 +488,9 @@ pub fn save_to_playlist(
         }
     }
 
-    // Create the playlist
-    let playlist_result = create_playlist(base_path, playlist_name.clone(), playlist_song_ids)?;
+    // Create the playlist, in import order
+    let playlist_result =
+        create_playlist_file(&base_path, &playlist_name, &playlist_song_ids, false)?;
 
     Ok(SaveToPlaylistResult {
         files_saved: save_result.files_saved,
@@ -325,14 +512,16 @@ pub fn add_songs_to_playlist(
     song_ids: Vec<u32>,
 ) -> Result<CreatePlaylistResult, String> {
     // Load existing playlist
-    let mut playlist = load_playlist(base_path.clone(), playlist_id)?;
+    let playlist = load_playlist(base_path.clone(), playlist_id)?;
+    let mut entries = playlist.entries();
 
     // Add new song IDs (avoiding duplicates)
     let existing_ids: HashSet<u32> = playlist.song_ids.iter().cloned().collect();
+    let added_at = now_unix_secs();
     let mut new_songs_added = 0u32;
     for song_id in song_ids {
         if !existing_ids.contains(&song_id) {
-            playlist.song_ids.push(song_id);
+            entries.push(PlaylistEntry::new(song_id, added_at));
             new_songs_added += 1;
         }
     }
@@ -341,7 +530,13 @@ pub fn add_songs_to_playlist(
     let base = Path::new(&base_path);
     let playlists_path = get_playlists_path(base);
     let playlist_file_path = playlists_path.join(format!("{}.bin", playlist_id));
-    write_playlist_file(&playlist_file_path, &playlist.name, &playlist.song_ids)?;
+    write_playlist_file(
+        &playlist_file_path,
+        &playlist.name,
+        &entries,
+        playlist.manual_order,
+    )?;
+    announce_playlist_change(base, vec![playlist_id]);
 
     Ok(CreatePlaylistResult {
         playlist_id,
@@ -357,19 +552,26 @@ pub fn remove_songs_from_playlist(
     song_ids: Vec<u32>,
 ) -> Result<CreatePlaylistResult, String> {
     // Load existing playlist
-    let mut playlist = load_playlist(base_path.clone(), playlist_id)?;
+    let playlist = load_playlist(base_path.clone(), playlist_id)?;
+    let mut entries = playlist.entries();
 
     // Remove specified song IDs
     let remove_set: HashSet<u32> = song_ids.iter().cloned().collect();
-    let original_count = playlist.song_ids.len();
-    playlist.song_ids.retain(|id| !remove_set.contains(id));
-    let songs_removed = original_count - playlist.song_ids.len();
+    let original_count = entries.len();
+    entries.retain(|entry| !remove_set.contains(&entry.song_id));
+    let songs_removed = original_count - entries.len();
 
     // Write updated playlist
     let base = Path::new(&base_path);
     let playlists_path = get_playlists_path(base);
     let playlist_file_path = playlists_path.join(format!("{}.bin", playlist_id));
-    write_playlist_file(&playlist_file_path, &playlist.name, &playlist.song_ids)?;
+    write_playlist_file(
+        &playlist_file_path,
+        &playlist.name,
+        &entries,
+        playlist.manual_order,
+    )?;
+    announce_playlist_change(base, vec![playlist_id]);
 
     Ok(CreatePlaylistResult {
         playlist_id,
@@ -377,6 +579,199 @@ pub fn remove_songs_from_playlist(
     })
 }
 
+/// Check every playlist for dead or broken song references.
+///
+/// Reports song IDs past the end of the library, soft-deleted songs,
+/// duplicate entries and truncated files, one report per playlist file
+/// (by ID). With `repair`, affected playlists are rewritten keeping only
+/// the first occurrence of each live song; unreadable files are reported
+/// but left alone.
+#[tauri::command]
+pub fn validate_playlists(
+    base_path: String,
+    repair: bool,
+) -> Result<Vec<PlaylistValidationReport>, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let playlists_path = get_playlists_path(base);
+
+    if !playlists_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let song_flags_by_id = read_song_flags(base)?;
+
+    let entries = fs::read_dir(&playlists_path)
+        .map_err(|e| format!("Failed to read playlists directory: {}", e))?;
+    let mut playlist_files: Vec<(u32, std::path::PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| Some((parse_playlist_id(&entry)?, entry.path())))
+        .collect();
+    playlist_files.sort_by_key(|(id, _)| *id);
+
+    let mut reports = Vec::with_capacity(playlist_files.len());
+    for (playlist_id, path) in playlist_files {
+        let mut report = PlaylistValidationReport {
+            playlist_id,
+            ..Default::default()
+        };
+
+        let (playlist, truncated) = match read_playlist_file_lenient(&path, playlist_id) {
+            Ok(result) => result,
+            Err(e) => {
+                report.error = Some(e);
+                reports.push(report);
+                continue;
+            }
+        };
+        report.name = Some(playlist.name.clone());
+        report.truncated = truncated;
+
+        let mut seen = HashSet::new();
+        let mut live_entries = Vec::with_capacity(playlist.song_ids.len());
+        for entry in playlist.entries() {
+            let song_id = entry.song_id;
+            match song_flags_by_id.get(song_id as usize) {
+                None => {
+                    if !report.out_of_range_ids.contains(&song_id) {
+                        report.out_of_range_ids.push(song_id);
+                    }
+                }
+                Some(flags) if flags & song_flags::DELETED != 0 => {
+                    if !report.deleted_ids.contains(&song_id) {
+                        report.deleted_ids.push(song_id);
+                    }
+                }
+                Some(_) => {
+                    if seen.insert(song_id) {
+                        live_entries.push(entry);
+                    } else if !report.duplicate_ids.contains(&song_id) {
+                        report.duplicate_ids.push(song_id);
+                    }
+                }
+            }
+        }
+
+        if repair && report.has_issues() {
+            write_playlist_file(&path, &playlist.name, &live_entries, playlist.manual_order)?;
+            report.repaired = true;
+        }
+        reports.push(report);
+    }
+    let repaired = reports.iter().filter(|r| r.repaired).map(|r| r.playlist_id).collect();
+    announce_playlist_change(base, repaired);
+
+    Ok(reports)
+}
+
+/// Get which auto-playlists are exported on sync.
+#[tauri::command]
+pub fn get_auto_playlist_settings(base_path: String) -> Result<AutoPlaylistSettings, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    Ok(load_auto_playlists(base)?.settings)
+}
+
+/// Set which auto-playlists are exported; applied on the next sync.
+#[tauri::command]
+pub fn set_auto_playlist_settings(
+    base_path: String,
+    settings: AutoPlaylistSettings,
+) -> Result<(), String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let mut index = load_auto_playlists(base)?;
+    index.settings = settings;
+    save_auto_playlists(base, &index)
+}
+
+/// Regenerate the auto-playlists as playlist files on the card.
+///
+/// Run at sync time. Each enabled kind overwrites the file it was written
+/// to last time (a new ID is taken if that file now holds a different
+/// playlist); kinds that were turned off have their file deleted.
+#[tauri::command]
+pub fn sync_auto_playlists(base_path: String) -> Result<SyncAutoPlaylistsResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let playlists_path = get_playlists_path(base);
+    fs::create_dir_all(&playlists_path)
+        .map_err(|e| format!("Failed to create playlists directory: {}", e))?;
+
+    let mut index = load_auto_playlists(base)?;
+    let library = crate::commands::load_library(base_path.clone(), None)?;
+    let added_at = now_unix_secs();
+
+    let mut written = Vec::new();
+    let mut removed = 0u32;
+    let mut removed_ids = Vec::new();
+    for kind in AutoPlaylistKind::ALL {
+        let name = kind.playlist_name();
+        let previous = index.playlist_ids.get(&kind).copied().filter(|&id| {
+            let path = playlists_path.join(format!("{}.bin", id));
+            !path.exists() || read_playlist_file(&path, id).is_ok_and(|p| p.name == name)
+        });
+
+        let Some(song_ids) = auto_playlist_song_ids(&library, &index.settings, kind) else {
+            if let Some(id) = index.playlist_ids.remove(&kind) {
+                if previous.is_some() {
+                    let _ = fs::remove_file(playlists_path.join(format!("{}.bin", id)));
+                    removed += 1;
+                    removed_ids.push(id);
+                }
+            }
+            continue;
+        };
+
+        let playlist_id = match previous {
+            Some(id) => id,
+            None => get_next_playlist_id(&playlists_path)?,
+        };
+        let entries: Vec<PlaylistEntry> = song_ids
+            .iter()
+            .map(|&song_id| PlaylistEntry::new(song_id, added_at))
+            .collect();
+        write_playlist_file(
+            &playlists_path.join(format!("{}.bin", playlist_id)),
+            name,
+            &entries,
+            false,
+        )?;
+        index.playlist_ids.insert(kind, playlist_id);
+        written.push(PlaylistSummary {
+            id: playlist_id,
+            name: name.to_string(),
+            song_count: entries.len() as u32,
+        });
+    }
+
+    save_auto_playlists(base, &index)?;
+    announce_playlist_change(
+        base,
+        written.iter().map(|p| p.id).chain(removed_ids).collect(),
+    );
+    log::info!(
+        "[sync_auto_playlists] Wrote {} auto-playlists, removed {}",
+        written.len(),
+        removed
+    );
+
+    Ok(SyncAutoPlaylistsResult { written, removed })
+}
+
+/// Write the auto-playlists enabled in `base` into the library at `dest`.
+///
+/// Used by exports: `dest` takes `base`'s settings, then its auto-playlists
+/// are generated from the songs exported to it. Returns the number written.
+pub(crate) fn export_auto_playlists(base: &Path, dest: &Path) -> Result<u32, String> {
+    let settings = load_auto_playlists(base)?.settings;
+    let mut index = load_auto_playlists(dest)?;
+    index.settings = settings;
+    save_auto_playlists(dest, &index)?;
+    let result = sync_auto_playlists(dest.to_string_lossy().to_string())?;
+    Ok(result.written.len() as u32)
+}
+
 /// Result of renaming a playlist.
 #[derive(Debug, Clone, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -430,7 +825,13 @@ pub fn rename_playlist(
 
     // Write updated playlist with new name
     let playlist_file_path = playlists_path.join(format!("{}.bin", playlist_id));
-    write_playlist_file(&playlist_file_path, &new_name, &playlist.song_ids)?;
+    write_playlist_file(
+        &playlist_file_path,
+        &new_name,
+        &playlist.entries(),
+        playlist.manual_order,
+    )?;
+    announce_playlist_change(base, vec![playlist_id]);
 
     Ok(RenamePlaylistResult {
         success: true,
@@ -0,0 +1,113 @@
+//! ReplayGain analysis commands.
+//!
+//! Handles:
+//! - Measuring track and album gain for library songs
+//! - Pruning stored gain for songs that no longer exist
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::commands::library::load_library;
+use crate::models::{ParsedSong, ReplayGainResult};
+use crate::services::library_monitor_service::{
+    ensure_library_available, wait_while_library_unavailable,
+};
+use crate::services::replay_gain_service::{
+    load_replay_gain, measure_loudness, save_replay_gain, song_gain,
+};
+
+/// Measure ReplayGain for library songs, album by album.
+///
+/// Each track is measured on its own, then the album's tracks together, and
+/// both gains are stored side by side. Albums where every song already has
+/// album gain are skipped unless `force = true`; adding a song to an album
+/// re-measures the whole album. Entries for songs no longer in the library
+/// are removed.
+#[tauri::command]
+pub async fn analyze_replay_gain(
+    base_path: String,
+    force: Option<bool>,
+) -> Result<ReplayGainResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+
+    let force = force.unwrap_or(false);
+    let library = load_library(base_path.clone(), None)?;
+    let mut index = load_replay_gain(base)?;
+    let music_path = base.join("jp3").join("music");
+
+    // Drop entries for songs that have been deleted or compacted away
+    let live_paths: HashSet<&str> = library.songs.iter().map(|s| s.path.as_str()).collect();
+    index.songs.retain(|path, _| live_paths.contains(path.as_str()));
+
+    let mut albums: BTreeMap<u32, Vec<&ParsedSong>> = BTreeMap::new();
+    for song in &library.songs {
+        albums.entry(song.album_id).or_default().push(song);
+    }
+
+    log::info!(
+        "Analyzing ReplayGain for {} albums (force: {})",
+        albums.len(),
+        force
+    );
+
+    let mut result = ReplayGainResult::default();
+    for songs in albums.values() {
+        let current = songs.iter().all(|song| {
+            index
+                .songs
+                .get(&song.path)
+                .is_some_and(|gain| gain.album_gain_db.is_some())
+        });
+        if current && !force {
+            result.up_to_date += songs.len() as u32;
+            continue;
+        }
+
+        // Pause rather than fail every remaining album if the SD card is pulled
+        wait_while_library_unavailable().await;
+
+        let mut measured = Vec::with_capacity(songs.len());
+        for song in songs {
+            let file = music_path.join(&song.path);
+            match measure_loudness(std::slice::from_ref(&file)) {
+                Ok(loudness) => measured.push((song, file, loudness)),
+                Err(e) => {
+                    log::error!("Failed to measure {}: {}", song.path, e);
+                    result.failed += 1;
+                }
+            }
+        }
+        if measured.is_empty() {
+            continue;
+        }
+
+        let album = match measured.as_slice() {
+            [(_, _, loudness)] => Some(*loudness),
+            _ => {
+                let files: Vec<PathBuf> = measured.iter().map(|(_, f, _)| f.clone()).collect();
+                measure_loudness(&files)
+                    .inspect_err(|e| log::error!("Failed to measure album gain: {}", e))
+                    .ok()
+            }
+        };
+        for (song, _, loudness) in &measured {
+            index
+                .songs
+                .insert(song.path.clone(), song_gain(loudness, album.as_ref()));
+        }
+        result.albums_analyzed += 1;
+        result.songs_analyzed += measured.len() as u32;
+    }
+
+    save_replay_gain(base, &index)?;
+
+    log::info!(
+        "ReplayGain complete: {} songs in {} albums analyzed, {} up to date, {} failed",
+        result.songs_analyzed,
+        result.albums_analyzed,
+        result.up_to_date,
+        result.failed
+    );
+
+    Ok(result)
+}
@@ -0,0 +1,36 @@
+//! Library search commands.
+//!
+//! Search runs in the backend so accent folding and romanized keys
+//! (see `transliteration_service`) apply the same way everywhere, and so
+//! the frontend doesn't have to filter the whole library on every keystroke.
+
+use std::path::Path;
+
+use crate::commands::library::{library_generation, load_library};
+use crate::models::LibrarySearchResults;
+use crate::services::library_monitor_service::ensure_library_available;
+use crate::services::search_service::cached_index;
+
+/// Default number of results per category.
+const DEFAULT_SEARCH_LIMIT: u32 = 5;
+
+/// Search artists, albums and songs.
+///
+/// Matching ignores case and accents ("sigur ros" finds "Sigur Rós") and
+/// includes romanized keys for non-Latin names when transliteration is on.
+/// Songs also match on their artist and album name.
+///
+/// The index is built on the first search after the library changes and
+/// reused until library.bin is written again.
+#[tauri::command]
+pub fn search_library(
+    base_path: String,
+    query: String,
+    limit: Option<u32>,
+) -> Result<LibrarySearchResults, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+    let generation = library_generation(base)?;
+    let index = cached_index(base, generation, || load_library(base_path.clone(), None))?;
+    Ok(index.search(&query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT) as usize))
+}
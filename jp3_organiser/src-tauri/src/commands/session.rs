@@ -0,0 +1,308 @@
+//! Import session commands.
+//!
+//! Handles:
+//! - Opening/closing import sessions held in managed state
+//! - Processing files into a session (ID3, fingerprint, AcoustID)
+//! - Reading, editing and removing session files by tracking ID
+//! - Copying album-level metadata across a folder's files
+//! - Grouping session files into probable albums for confirmation
+//! - Confirming every complete, unambiguous file in one call
+//! - Saving session files to the library without re-passing paths
+//! - Recording AcoustID release candidates for cover art fallbacks
+//! - Queueing cover thumbnail generation after a save
+use std::path::Path;
+
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+
+use crate::commands::audio::{acoustid_configured, batch_result, run_file_pipeline};
+use crate::commands::library::{save_to_library, FileToSave};
+use crate::models::{
+    AudioMetadata, ConfirmAllCompleteResult, ProcessedFilesResult, SaveToLibraryResult,
+    SessionAlbumGroup, TrackedAudioFile,
+};
+use crate::services::failed_import_service::record_outcomes;
+use crate::services::fingerprint_service::rate_limit_delay;
+use crate::services::import_session_service::{ImportSession, ImportSessions};
+use crate::services::metadata_ranking_service::collect_release_mbids;
+use crate::services::release_candidate_service::{
+    add_release_candidates, load_release_candidates, save_release_candidates,
+};
+use crate::services::task_manager_service::TaskManager;
+use crate::services::thumbnail_service::queue_album_thumbnails;
+
+/// Open a new import session and return its ID.
+#[tauri::command]
+pub fn create_import_session(sessions: State<'_, ImportSessions>) -> Result<String, String> {
+    let session_id = sessions.create();
+    log::info!("Opened import session {}", session_id);
+    Ok(session_id)
+}
+
+/// Close an import session, dropping everything it holds.
+#[tauri::command]
+pub fn close_import_session(
+    sessions: State<'_, ImportSessions>,
+    session_id: String,
+) -> Result<bool, String> {
+    Ok(sessions.close(&session_id))
+}
+
+/// Process files into a session.
+///
+/// Runs the same pipeline as `process_audio_files`, but keeps tracked files,
+/// fingerprints and AcoustID candidates in the session for later commands.
+/// Returns only the newly added files.
+#[tauri::command]
+pub async fn add_files_to_session(
+    app: AppHandle,
+    sessions: State<'_, ImportSessions>,
+    session_id: String,
+    file_paths: Vec<String>,
+) -> Result<ProcessedFilesResult, String> {
+    // Fail fast on an unknown session before doing any work
+    sessions.with_session(&session_id, |_| ())?;
+
+    let acoustid_enabled = acoustid_configured();
+    let mut added = Vec::with_capacity(file_paths.len());
+    for (index, file_path) in file_paths.into_iter().enumerate() {
+        if index > 0 && acoustid_enabled {
+            rate_limit_delay().await;
+        }
+        let output =
+            run_file_pipeline(Uuid::new_v4().to_string(), file_path, acoustid_enabled).await;
+
+        // Lock only between awaits
+        sessions.with_session(&session_id, |session| {
+            let tracking_id = output.file.tracking_id.clone();
+            if let Some(fingerprint) = output.fingerprint {
+                session.fingerprints.insert(tracking_id.clone(), fingerprint);
+            }
+            if let Some(response) = output.acoustid_response {
+                session.candidates.insert(tracking_id, response);
+            }
+            session.insert_file(output.file.clone());
+        })?;
+        added.push(output.file);
+    }
+
+    record_outcomes(&app, &added);
+    Ok(batch_result(added, acoustid_enabled))
+}
+
+/// Get files in a session, in the order they were added.
+///
+/// Pass `tracking_ids` to fetch a subset; omit it for the whole session.
+#[tauri::command]
+pub fn get_session_files(
+    sessions: State<'_, ImportSessions>,
+    session_id: String,
+    tracking_ids: Option<Vec<String>>,
+) -> Result<ProcessedFilesResult, String> {
+    let files =
+        sessions.with_session(&session_id, |session| session.ordered_files(tracking_ids.as_deref()))?;
+    Ok(ProcessedFilesResult::from_files(files))
+}
+
+/// Replace a session file's metadata with user-entered values.
+#[tauri::command]
+pub fn update_session_file_metadata(
+    sessions: State<'_, ImportSessions>,
+    session_id: String,
+    tracking_id: String,
+    metadata: AudioMetadata,
+) -> Result<TrackedAudioFile, String> {
+    sessions.with_session(&session_id, |session| {
+        session.update_file_metadata(&tracking_id, metadata)
+    })?
+}
+
+/// Apply one file's album, artist, album artist, year and cover release to other files.
+///
+/// Lets a fix to one track's album name carry over to the rest of its
+/// folder. Titles and track numbers are left alone. Returns the updated files.
+#[tauri::command]
+pub fn propagate_album_metadata(
+    sessions: State<'_, ImportSessions>,
+    session_id: String,
+    template_tracking_id: String,
+    target_tracking_ids: Vec<String>,
+) -> Result<ProcessedFilesResult, String> {
+    let files = sessions.with_session(&session_id, |session| {
+        session.propagate_album_metadata(&template_tracking_id, &target_tracking_ids)
+    })??;
+    Ok(ProcessedFilesResult::from_files(files))
+}
+
+/// Group a session's files into probable albums.
+///
+/// Each group reports how many of its files are complete and which album
+/// fields its files disagree on, so the confirmation screen can show one
+/// row per album and flag only the albums that need attention.
+#[tauri::command]
+pub fn group_import_by_album(
+    sessions: State<'_, ImportSessions>,
+    session_id: String,
+) -> Result<Vec<SessionAlbumGroup>, String> {
+    sessions.with_session(&session_id, |session| session.group_by_album())
+}
+
+/// Confirm every session file that is complete and unambiguous.
+///
+/// Lets a large, clean import be accepted in one step: only files that are
+/// incomplete, in an album with conflicting fields, or flagged with a format
+/// warning are returned for the user to review.
+#[tauri::command]
+pub fn confirm_all_complete(
+    sessions: State<'_, ImportSessions>,
+    session_id: String,
+) -> Result<ConfirmAllCompleteResult, String> {
+    let (confirmed_ids, remaining) =
+        sessions.with_session(&session_id, |session| session.confirm_all_complete())?;
+    log::info!(
+        "Confirmed {} files in session {} ({} left to review)",
+        confirmed_ids.len(),
+        session_id,
+        remaining.len()
+    );
+    Ok(ConfirmAllCompleteResult {
+        confirmed_ids,
+        remaining,
+    })
+}
+
+/// Remove files from a session. Returns how many were removed.
+#[tauri::command]
+pub fn remove_session_files(
+    sessions: State<'_, ImportSessions>,
+    session_id: String,
+    tracking_ids: Vec<String>,
+) -> Result<usize, String> {
+    sessions.with_session(&session_id, |session| {
+        tracking_ids
+            .iter()
+            .filter(|id| session.remove_file(id))
+            .count()
+    })
+}
+
+/// Save session files to the library by tracking ID, or the confirmed files
+/// (see `confirm_all_complete`) when no IDs are given.
+///
+/// Saved files are removed from the session so a second save can't
+/// duplicate them; files that fail validation, or that the save skipped
+/// (missing, copy-protected or duplicate), stay in the session.
+///
+/// Every release MBID AcoustID reported for the saved files is recorded per
+/// album, for `fetch_album_cover_from_fingerprints` to fall back on.
+#[tauri::command]
+pub fn save_session_to_library(
+    sessions: State<'_, ImportSessions>,
+    tasks: State<'_, TaskManager>,
+    session_id: String,
+    base_path: String,
+    tracking_ids: Option<Vec<String>>,
+) -> Result<SaveToLibraryResult, String> {
+    let (files_to_save, saving_ids, release_candidates) =
+        sessions.with_session(&session_id, |session| {
+            let tracking_ids = tracking_ids.unwrap_or_else(|| session.confirmed_ids());
+            let files = session.ordered_files(Some(&tracking_ids));
+            let candidates = album_release_candidates(session, &files);
+            let saving_ids: Vec<String> = files.iter().map(|f| f.tracking_id.clone()).collect();
+            let files_to_save: Vec<FileToSave> = files
+                .into_iter()
+                .map(|file| FileToSave {
+                    fingerprint: session.fingerprints.get(&file.tracking_id).cloned(),
+                    source_path: file.file_path,
+                    metadata: file.metadata,
+                    metadata_source: file.metadata_source,
+                    confidence: file.confidence,
+                })
+                .collect();
+            (files_to_save, saving_ids, candidates)
+        })?;
+
+    let mut saved_albums: Vec<(String, String)> = Vec::new();
+    for file in &files_to_save {
+        let metadata = &file.metadata;
+        if let (Some(artist), Some(album)) = (metadata.album_artist_name(), &metadata.album) {
+            let key = (artist.clone(), album.clone());
+            if !saved_albums.contains(&key) {
+                saved_albums.push(key);
+            }
+        }
+    }
+
+    let result = save_to_library(base_path.clone(), files_to_save)?;
+
+    // Pre-generate thumbnails for covers that already exist (e.g. re-imports);
+    // covers fetched after the save are queued by the fetch commands
+    queue_album_thumbnails(&tasks, Path::new(&base_path), saved_albums);
+
+    // Cover fallbacks are best-effort; never fail a completed save over them
+    if let Err(e) = record_release_candidates(Path::new(&base_path), &release_candidates) {
+        log::warn!("Could not record release candidates: {}", e);
+    }
+
+    // Only what was saved; skipped files stay for the user to deal with
+    sessions.with_session(&session_id, |session| {
+        for &index in &result.saved_files {
+            session.remove_file(&saving_ids[index as usize]);
+        }
+    })?;
+
+    Ok(result)
+}
+
+/// (artist, album, release MBIDs) for each album among `files`.
+///
+/// The MBID already chosen for a file comes first, then every release
+/// AcoustID returned for its recording (same-titled releases first).
+fn album_release_candidates(
+    session: &ImportSession,
+    files: &[TrackedAudioFile],
+) -> Vec<(String, String, Vec<String>)> {
+    let mut albums: Vec<(String, String, Vec<String>)> = Vec::new();
+
+    for file in files {
+        let (artist, album) = match (file.metadata.album_artist_name(), &file.metadata.album) {
+            (Some(artist), Some(album)) => (artist, album),
+            _ => continue,
+        };
+
+        let mut mbids: Vec<String> = file.metadata.release_mbid.iter().cloned().collect();
+        if let Some(response) = session.candidates.get(&file.tracking_id) {
+            mbids.extend(collect_release_mbids(response, Some(album)));
+        }
+        if mbids.is_empty() {
+            continue;
+        }
+
+        match albums.iter_mut().find(|(a, b, _)| a == artist && b == album) {
+            Some((_, _, existing)) => existing.extend(mbids),
+            None => albums.push((artist.clone(), album.clone(), mbids)),
+        }
+    }
+
+    albums
+}
+
+/// Merge per-album release candidates into the library's index.
+fn record_release_candidates(
+    base_path: &Path,
+    albums: &[(String, String, Vec<String>)],
+) -> Result<(), String> {
+    if albums.is_empty() {
+        return Ok(());
+    }
+
+    let mut index = load_release_candidates(base_path)?;
+    let mut changed = false;
+    for (artist, album, mbids) in albums {
+        changed |= add_release_candidates(&mut index, artist, album, mbids);
+    }
+    if changed {
+        save_release_candidates(base_path, &index)?;
+    }
+    Ok(())
+}
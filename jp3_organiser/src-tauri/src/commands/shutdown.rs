@@ -0,0 +1,148 @@
+//! Closing the app while background work is running.
+//!
+//! Handles:
+//! - Keeping the main window open while tasks that write to the library
+//!   are queued, a save or export is writing, or a compaction is running,
+//!   and asking the frontend to confirm (`app://close-blocked`)
+//! - Cancelling and flushing that work before exiting, off the async runtime
+//! - Recording what was cut short, for the next launch
+//!
+//! Exports journal each copied file as they go (see `sync_plan_service`),
+//! so closing during one is recovered with `resume_sync`.
+
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+use crate::models::ShutdownReport;
+use crate::services::compaction_service::CompactionControl;
+use crate::services::task_manager_service::{TaskInfo, TaskManager};
+
+/// Event emitted when a window close was held back by running work
+pub const EVENT_CLOSE_BLOCKED: &str = "app://close-blocked";
+
+const STORE_FILENAME: &str = "shutdown.json";
+const LAST_SHUTDOWN_KEY: &str = "last_shutdown";
+
+/// How long shutdown waits for the running task and compaction to stop
+const SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+
+/// How often shutdown checks whether the compaction has stopped
+const COMPACTION_CHECK_INTERVAL_MS: u64 = 50;
+
+/// Payload for `app://close-blocked`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloseBlockedEvent {
+    /// Running foreground writes, then queued and running background tasks
+    pub tasks: Vec<TaskInfo>,
+    pub compaction_running: bool,
+}
+
+/// Whether closing now would cut library writes short.
+///
+/// If so, emits `app://close-blocked` for the frontend to ask the user, and
+/// returns true so the window is kept open. The frontend then calls
+/// `graceful_shutdown` to close anyway.
+pub fn block_close_if_busy(app: &AppHandle) -> bool {
+    let tasks = app.state::<TaskManager>();
+    let compaction = app.state::<CompactionControl>();
+    if !tasks.has_io_tasks() && !compaction.is_running() {
+        return false;
+    }
+    let event = CloseBlockedEvent {
+        tasks: tasks.active_tasks(),
+        compaction_running: compaction.is_running(),
+    };
+    if let Err(e) = app.emit(EVENT_CLOSE_BLOCKED, event) {
+        log::warn!("Failed to emit {}: {}", EVENT_CLOSE_BLOCKED, e);
+    }
+    true
+}
+
+/// Cancel queued tasks and any compaction, then wait up to `timeout` for
+/// the running task, foreground writes and the compaction to stop.
+pub fn stop_background_work(
+    tasks: &TaskManager,
+    compaction: &CompactionControl,
+    timeout: Duration,
+) -> ShutdownReport {
+    let deadline = Instant::now() + timeout;
+    let compaction_cancelled = compaction.cancel();
+    let cancelled_tasks = tasks.cancel_pending();
+
+    let tasks_finished = tasks.wait_idle(timeout);
+    // A compaction stops at its next checkpoint, or finishes if it was
+    // already replacing library.bin
+    while compaction.is_running() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(COMPACTION_CHECK_INTERVAL_MS));
+    }
+
+    ShutdownReport {
+        shut_down_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        cancelled_tasks,
+        compaction_cancelled,
+        clean: tasks_finished && !compaction.is_running(),
+    }
+}
+
+/// Stop background work, record what was cut short, and exit the app.
+///
+/// Queued tasks are dropped and a running compaction is cancelled; the
+/// task already running and any save or export are given up to 30 seconds
+/// to finish. The wait runs on a blocking thread.
+#[tauri::command]
+pub async fn graceful_shutdown(app: AppHandle) -> Result<ShutdownReport, String> {
+    let handle = app.clone();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let tasks = handle.state::<TaskManager>();
+        let compaction = handle.state::<CompactionControl>();
+        stop_background_work(
+            &tasks,
+            &compaction,
+            Duration::from_secs(SHUTDOWN_TIMEOUT_SECS),
+        )
+    })
+    .await
+    .map_err(|e| format!("Shutdown task failed: {}", e))?;
+    log::info!(
+        "Shutting down: {} tasks cancelled, compaction cancelled: {}, clean: {}",
+        report.cancelled_tasks.len(),
+        report.compaction_cancelled,
+        report.clean
+    );
+    if let Err(e) = save_last_shutdown(&app, &report) {
+        log::error!("Failed to record shutdown: {}", e);
+    }
+    app.exit(0);
+    Ok(report)
+}
+
+/// What the last `graceful_shutdown` stopped, if the app was closed with
+/// work running.
+#[tauri::command]
+pub fn get_last_shutdown(app: AppHandle) -> Result<Option<ShutdownReport>, String> {
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+    store
+        .get(LAST_SHUTDOWN_KEY)
+        .map(|value| {
+            serde_json::from_value(value).map_err(|e| format!("Failed to parse shutdown: {}", e))
+        })
+        .transpose()
+}
+
+fn save_last_shutdown(app: &AppHandle, report: &ShutdownReport) -> Result<(), String> {
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+    store.set(LAST_SHUTDOWN_KEY, serde_json::json!(report));
+    store.save().map_err(|e| format!("Failed to save store: {}", e))
+}
@@ -0,0 +1,104 @@
+//! Tag cleanup commands.
+//!
+//! Handles:
+//! - Rewriting library MP3 tags to a clean ID3v2.4 set
+//! - Updating content hashes of the rewritten files
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::commands::library::{is_adopted_path, load_library};
+use crate::models::{ParsedSong, TagCleanupResult};
+use crate::services::folder_scan_service::hash_file;
+use crate::services::library_monitor_service::ensure_library_available;
+use crate::services::parallel_io_service::map_ordered;
+use crate::services::song_hash_service::record_song_hashes;
+use crate::services::tag_cleanup_service::{clean_file_tags, CleanTagFields, TagCleanupOutcome};
+
+/// Rewrite the tags of library MP3s to a clean ID3v2.4 set.
+///
+/// Title, artist, album, track and year come from library.bin, so edits
+/// made in the app end up in the files too. The embedded front cover is
+/// kept; comments, PRIV and other frames, ID3v1 and APE tags and padding
+/// are dropped. `song_ids` limits cleanup to those songs (default: all).
+/// Songs that aren't MP3s, and adopted songs (see `scan_and_adopt`), are
+/// skipped. Rewritten files get their content
+/// hash updated so `verify_library` doesn't report them as changed.
+#[tauri::command]
+pub fn clean_library_tags(
+    base_path: String,
+    song_ids: Option<Vec<u32>>,
+) -> Result<TagCleanupResult, String> {
+    let base = Path::new(&base_path);
+    ensure_library_available(base)?;
+
+    let library = load_library(base_path.clone(), None)?;
+    let music_path = base.join("jp3").join("music");
+    let wanted: Option<HashSet<u32>> = song_ids.map(|ids| ids.into_iter().collect());
+    let songs: Vec<&ParsedSong> = library
+        .songs
+        .iter()
+        .filter(|song| wanted.as_ref().is_none_or(|ids| ids.contains(&song.id)))
+        .collect();
+
+    let mut result = TagCleanupResult::default();
+    let (mp3s, others): (Vec<&ParsedSong>, Vec<&ParsedSong>) = songs
+        .into_iter()
+        .partition(|song| {
+            // Adopted files are the user's own and are left alone
+            song.path.to_lowercase().ends_with(".mp3") && !is_adopted_path(&song.path)
+        });
+    result.skipped = others.len() as u32;
+
+    log::info!("Cleaning tags of {} MP3s", mp3s.len());
+
+    let outcomes = map_ordered(
+        &mp3s,
+        |song| {
+            let fields = CleanTagFields {
+                title: song.title.clone(),
+                artist: song.artist_name.clone(),
+                album: song.album_name.clone(),
+                track_number: song.track_number as u32,
+                year: song.year as i32,
+            };
+            clean_file_tags(&music_path.join(&song.path), &fields)
+        },
+        |_, _| {},
+    );
+
+    let mut hashes = Vec::new();
+    for (song, outcome) in mp3s.iter().zip(outcomes) {
+        match outcome {
+            Ok(TagCleanupOutcome::AlreadyClean) => result.already_clean += 1,
+            Ok(TagCleanupOutcome::Cleaned {
+                bytes_before,
+                bytes_after,
+            }) => {
+                result.songs_cleaned += 1;
+                result.bytes_before += bytes_before;
+                result.bytes_after += bytes_after;
+                match hash_file(&music_path.join(&song.path)) {
+                    Ok(hash) => hashes.push((song.path.clone(), hash)),
+                    Err(e) => log::warn!("Failed to hash {}: {}", song.path, e),
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to clean tags of {}: {}", song.path, e);
+                result.failed += 1;
+            }
+        }
+    }
+    record_song_hashes(base, hashes)?;
+
+    log::info!(
+        "Tag cleanup complete: {} cleaned ({} -> {} bytes), {} already clean, {} skipped, {} failed",
+        result.songs_cleaned,
+        result.bytes_before,
+        result.bytes_after,
+        result.already_clean,
+        result.skipped,
+        result.failed
+    );
+
+    Ok(result)
+}
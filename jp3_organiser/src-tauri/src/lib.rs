@@ -8,15 +8,53 @@
 //! - `commands/` - Tauri command handlers
 //!   - `audio` - Audio file processing and metadata extraction
 //!   - `config` - Library path persistence
+//!   - `enrichment` - Album year backfill from MusicBrainz, transliteration keys
+//!   - `fingerprint` - Re-fingerprinting after fpcalc upgrades
+//!   - `import` - Folder scanning with structure inference, adoption in place
+//!   - `itunes_import` - iTunes / Music.app Library.xml import with playlists
 //!   - `library` - Library initialization and info
+//!   - `library_json` - Library export/import as JSON, for moving machines
 //!   - `playlist` - Playlist management
+//!   - `replay_gain` - Track and album gain analysis
+//!   - `search` - Accent-insensitive library search with romanized keys
+//!   - `session` - Import sessions keyed by tracking ID
+//!   - `shutdown` - Close protection and graceful shutdown while tasks run
+//!   - `tag_cleanup` - Rewriting library MP3 tags to a clean ID3v2.4 set
 //! - `models/` - Data structures
 //!   - `audio` - TrackedAudioFile, MetadataStatus, AudioMetadata
 //!   - `library` - LibraryHeader, LibraryInfo
 //!   - `playlist` - PlaylistHeader, ParsedPlaylist
 //! - `services/` - Business logic services
+//!   - `audio_info_service` - Codec, bitrate and sample rate read from audio files
+//!   - `failed_import_service` - Persisted failed-imports log for retries
+//!   - `file_naming_service` - FAT-safe song-based audio file names
+//!   - `fingerprint_index_service` - Stored fingerprints with algorithm/version
+//!   - `fingerprint_match_service` - Fingerprint decoding and similarity grouping
 //!   - `fingerprint_service` - Audio fingerprinting with fpcalc
+//!   - `folder_scan_service` - Folder walking and path-based metadata inference
+//!   - `format_sniff_service` - Real audio format detection from file headers
+//!   - `import_session_service` - Managed ImportSession state
+//!   - `itunes_library_service` - Library.xml parsing and track file locations
+//!   - `library_monitor_service` - Detects the library path vanishing (unplugged SD)
 //!   - `metadata_ranking_service` - AcoustID response ranking
+//!   - `parallel_io_service` - Bounded, order-preserving per-file worker pool
+//!   - `power_service` - OS sleep inhibitor held during long IO
+//!   - `release_candidate_service` - Per-album release MBIDs and user release picks
+//!   - `replay_gain_service` - EBU R128 loudness via ffmpeg, stored ReplayGain
+//!   - `report_service` - Printable HTML/Markdown catalogue of the library
+//!   - `resample_service` - Hi-res resampling before fingerprinting
+//!   - `save_hook_service` - External pre-save/post-save hook programs
+//!   - `search_service` - Folded/romanized search index over the library
+//!   - `sort_name_service` - Normalized sort keys for artist/album names and their collation
+//!   - `sync_history_service` - Per-device record of finished exports
+//!   - `sync_plan_service` - Persisted plan of an interrupted export, for resuming
+//!   - `tag_cleanup_service` - Clean ID3v2.4 tag rewrite with junk frames stripped
+//!   - `tag_reader_service` - Merged tag reading (ID3, APE, Vorbis, MP4) via lofty
+//!   - `task_manager_service` - Background task queue (managed TaskManager)
+//!   - `thumbnail_service` - UI and device-size cover thumbnails
+//!   - `transliteration_service` - Script detection and romanized name keys
+//!   - `write_marker_service` - Markers for library.bin writes in progress
+//!   - `year_backfill_service` - Cached MusicBrainz album year lookups
 use tauri::{AppHandle, Manager};
 use dotenv::dotenv;
 use std::env;
@@ -49,45 +87,162 @@ fn splash_screen(app: AppHandle) -> Result<(), String> {
 
 use commands::{
     // Audio commands
+    clear_failed_imports,
     get_audio_metadata,
+    get_failed_imports,
+    group_files_by_fingerprint,
     process_audio_files,
     process_single_audio_file,
+    retry_failed_imports,
+    submit_acoustid_fingerprint,
     // Config commands
     clear_library_path,
+    get_acoustid_submission_settings,
+    get_cover_image_settings,
+    get_io_throttle_settings,
     get_library_path,
+    get_save_hook_settings,
+    identify_device,
+    list_devices,
+    register_device,
+    set_acoustid_submission_settings,
+    set_cover_image_settings,
+    set_device_encode_profile,
+    set_io_throttle_settings,
     set_library_path,
+    set_save_hook_settings,
     // Cover art commands
     clear_cover_cache,
+    choose_release_mbid,
     fetch_album_cover,
+    fetch_album_cover_from_fingerprints,
     fetch_artist_cover,
     fetch_deezer_album_cover,
     get_album_cover_path,
+    migrate_legacy_covers,
+    preview_album_cover,
     read_album_cover,
+    read_album_covers_batch,
+    read_album_thumbnail,
     read_artist_cover,
     search_album_mbid,
     search_album_mbids_batch,
+    // Enrichment commands
+    backfill_album_years,
+    set_transliteration_enabled,
+    set_transliteration_override,
+    // Fingerprint commands
+    refingerprint_library,
+    // Import commands
+    import_itunes_library,
+    record_folder_import,
+    rescan_music_folder,
+    scan_and_adopt,
+    scan_music_folder,
     // Library commands
+    backfill_song_hashes,
+    backup_library,
+    cancel_compaction,
+    check_device_compatibility,
+    copy_adopted_songs,
     compact_library,
+    compact_library_idle_step,
+    compact_library_with_progress,
     delete_album,
     delete_artist,
+    delete_backup,
     delete_songs,
+    discard_sync,
     edit_album,
     edit_artist,
     edit_song_metadata,
+    edit_songs_metadata,
+    empty_trash,
+    export_library_report,
+    export_preview_clip,
+    export_selection,
+    export_sync_plan,
+    get_album,
+    get_artist,
+    get_backup_retention,
+    get_bucket_layout,
+    get_compaction_thresholds,
     get_library_info,
+    get_pending_sync,
+    get_favorites,
+    get_interrupted_writes,
     get_library_stats,
+    get_song_file_info,
+    get_sync_history,
+    get_low_confidence_songs,
+    get_transcode_cache_stats,
+    get_trash_retention,
     initialize_library,
+    list_backups,
+    list_trash,
     load_library,
+    load_library_delta,
+    load_library_page,
+    merge_albums,
+    migrate_library,
+    plan_device_sync,
+    preview_delete,
+    prune_transcode_cache,
+    rate_song,
+    recover_interrupted_writes,
+    relink_song,
+    repair_library,
+    restore_library,
+    restore_from_trash,
+    restore_songs,
+    resume_sync,
     save_to_library,
+    scan_missing_files,
+    scan_orphaned_files,
+    select_songs_for_size,
+    set_backup_retention,
+    set_bucket_layout,
+    set_compaction_thresholds,
+    set_trash_retention,
+    toggle_favorite,
+    verify_library,
+    // Library JSON commands
+    export_library_json,
+    import_library_json,
     // Playlist commands
     add_songs_to_playlist,
+    convert_playlists_for_firmware,
     create_playlist,
     delete_playlist_by_name,
+    get_auto_playlist_settings,
     list_playlists,
     load_playlist,
     remove_songs_from_playlist,
     rename_playlist,
     save_to_playlist,
+    set_auto_playlist_settings,
+    sync_auto_playlists,
+    validate_playlists,
+    // ReplayGain commands
+    analyze_replay_gain,
+    // Search commands
+    search_library,
+    // Shutdown commands
+    get_last_shutdown,
+    graceful_shutdown,
+    // Tag cleanup commands
+    clean_library_tags,
+    // Import session commands
+    add_files_to_session,
+    close_import_session,
+    confirm_all_complete,
+    create_import_session,
+    get_session_files,
+    group_import_by_album,
+    propagate_album_metadata,
+    remove_session_files,
+    save_session_to_library,
+    update_session_file_metadata,
 };
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -100,38 +255,165 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_upload::init())
         .plugin(tauri_plugin_opener::init())
+        .manage(services::import_session_service::ImportSessions::default())
+        .manage(services::task_manager_service::TaskManager::default())
+        .manage(services::compaction_service::CompactionControl::default())
+        .setup(|app| {
+            // Watch for the SD card being removed/reinserted
+            services::library_monitor_service::start_library_monitor(app.handle().clone());
+            // Let library commands announce changes
+            services::library_events_service::init_library_events(app.handle().clone());
+            // Apply the saved copy throttle
+            if let Ok(settings) = commands::config::get_io_throttle_settings(app.handle().clone()) {
+                services::parallel_io_service::set_io_throttle(settings);
+            }
+            // Apply the saved pre/post-save hooks
+            if let Ok(hooks) = commands::config::get_save_hook_settings(app.handle().clone()) {
+                services::save_hook_service::set_save_hooks(hooks);
+            }
+            // Rename covers earlier builds saved under ID-based names (once per library)
+            if let Ok(Some(path)) = commands::config::get_library_path(app.handle().clone()) {
+                std::thread::spawn(move || {
+                    if let Err(e) = commands::cover_art::migrate_legacy_covers(path) {
+                        log::warn!("Legacy cover migration skipped: {}", e);
+                    }
+                });
+            }
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            // Hold the window open while library writes are running; the
+            // frontend confirms and calls graceful_shutdown
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                if commands::shutdown::block_close_if_busy(window.app_handle()) {
+                    api.prevent_close();
+                }
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             // Audio commands
             process_audio_files,
             process_single_audio_file,
             get_audio_metadata,
+            get_failed_imports,
+            clear_failed_imports,
+            retry_failed_imports,
+            submit_acoustid_fingerprint,
+            group_files_by_fingerprint,
             // Config commands
             get_library_path,
             set_library_path,
             clear_library_path,
+            get_acoustid_submission_settings,
+            set_acoustid_submission_settings,
+            get_cover_image_settings,
+            set_cover_image_settings,
+            get_io_throttle_settings,
+            set_io_throttle_settings,
+            get_save_hook_settings,
+            set_save_hook_settings,
+            register_device,
+            list_devices,
+            identify_device,
+            set_device_encode_profile,
             // Cover art commands
             clear_cover_cache,
+            migrate_legacy_covers,
+            choose_release_mbid,
             fetch_album_cover,
+            fetch_album_cover_from_fingerprints,
             fetch_artist_cover,
             fetch_deezer_album_cover,
             get_album_cover_path,
+            preview_album_cover,
             read_album_cover,
+            read_album_covers_batch,
+            read_album_thumbnail,
             read_artist_cover,
             search_album_mbid,
             search_album_mbids_batch,
+            // Enrichment commands
+            backfill_album_years,
+            set_transliteration_enabled,
+            set_transliteration_override,
+            // Fingerprint commands
+            refingerprint_library,
+            // Import commands
+            scan_music_folder,
+            rescan_music_folder,
+            record_folder_import,
+            import_itunes_library,
+            scan_and_adopt,
             // Library commands
             initialize_library,
             get_library_info,
             save_to_library,
+            copy_adopted_songs,
             load_library,
+            load_library_page,
+            load_library_delta,
+            get_album,
+            get_artist,
+            get_low_confidence_songs,
+            preview_delete,
             delete_songs,
+            restore_songs,
+            list_trash,
+            restore_from_trash,
+            empty_trash,
+            get_trash_retention,
+            set_trash_retention,
+            get_bucket_layout,
+            set_bucket_layout,
             delete_album,
             delete_artist,
             edit_song_metadata,
+            edit_songs_metadata,
             edit_album,
+            merge_albums,
             edit_artist,
+            rate_song,
+            toggle_favorite,
+            get_favorites,
             get_library_stats,
+            get_compaction_thresholds,
+            set_compaction_thresholds,
+            migrate_library,
+            verify_library,
+            backfill_song_hashes,
+            get_song_file_info,
+            export_preview_clip,
+            repair_library,
+            scan_orphaned_files,
+            scan_missing_files,
+            relink_song,
+            select_songs_for_size,
+            export_selection,
+            get_pending_sync,
+            resume_sync,
+            get_sync_history,
+            plan_device_sync,
+            export_sync_plan,
+            discard_sync,
+            export_library_report,
+            get_transcode_cache_stats,
+            prune_transcode_cache,
+            check_device_compatibility,
+            backup_library,
+            list_backups,
+            restore_library,
+            delete_backup,
+            get_backup_retention,
+            set_backup_retention,
+            get_interrupted_writes,
+            recover_interrupted_writes,
             compact_library,
+            compact_library_with_progress,
+            cancel_compaction,
+            compact_library_idle_step,
+            // Library JSON commands
+            export_library_json,
+            import_library_json,
             // Playlist commands
             create_playlist,
             load_playlist,
@@ -141,6 +423,31 @@ pub fn run() {
             save_to_playlist,
             add_songs_to_playlist,
             remove_songs_from_playlist,
+            validate_playlists,
+            get_auto_playlist_settings,
+            set_auto_playlist_settings,
+            sync_auto_playlists,
+            convert_playlists_for_firmware,
+            // ReplayGain commands
+            analyze_replay_gain,
+            // Search commands
+            search_library,
+            // Shutdown commands
+            graceful_shutdown,
+            get_last_shutdown,
+            // Tag cleanup commands
+            clean_library_tags,
+            // Import session commands
+            create_import_session,
+            close_import_session,
+            add_files_to_session,
+            get_session_files,
+            group_import_by_album,
+            confirm_all_complete,
+            update_session_file_metadata,
+            propagate_album_metadata,
+            remove_session_files,
+            save_session_to_library,
             splash_screen
         ])
         .run(tauri::generate_context!())
@@ -26,6 +26,8 @@ pub enum MetadataStatus {
     Success,
     /// Failed during processing
     Failed,
+    /// Copy-protected (DRM); can't be played on the device or saved
+    ProtectedFile,
 }
 
 impl Default for MetadataStatus {
@@ -40,12 +42,14 @@ impl Default for MetadataStatus {
 pub enum MetadataSource {
     /// Source not yet determined
     Unknown,
-    /// Metadata extracted from ID3 tags embedded in the file
+    /// Metadata extracted from ID3 or APE tags embedded in the file
     Id3,
     /// Metadata from audio fingerprint matching (Chromaprint -> AcoustID -> MusicBrainz)
     Fingerprint,
     /// Metadata entered manually by user
     Manual,
+    /// Metadata inferred from folder structure and filename
+    FolderStructure,
 }
 
 impl Default for MetadataSource {
@@ -54,6 +58,154 @@ impl Default for MetadataSource {
     }
 }
 
+impl MetadataSource {
+    /// Byte stored in a library.bin song entry (0 in files written before
+    /// sources were recorded, which reads back as Unknown).
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Self::Unknown => 0,
+            Self::Id3 => 1,
+            Self::Fingerprint => 2,
+            Self::Manual => 3,
+            Self::FolderStructure => 4,
+        }
+    }
+
+    /// Inverse of `to_byte`; unrecognised values read as Unknown.
+    pub fn from_byte(byte: u8) -> Self {
+        match byte {
+            1 => Self::Id3,
+            2 => Self::Fingerprint,
+            3 => Self::Manual,
+            4 => Self::FolderStructure,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Whether the file's ID3 or APE tags gave any title, artist or album.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Id3Status {
+    Ok,
+    /// No tag, an empty tag, or a format without ID3 support
+    #[default]
+    None,
+}
+
+/// Outcome of running fpcalc on the file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FingerprintStatus {
+    Ok,
+    Failed,
+    /// Not attempted (unsupported or unreadable file)
+    #[default]
+    Skipped,
+}
+
+/// Outcome of the AcoustID lookup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AcoustIdStatus {
+    /// A recording was matched and its metadata used
+    Matched,
+    /// The lookup worked but found no usable recording
+    NoMatch,
+    /// The request itself failed (network, rate limit, bad response)
+    Error,
+    /// Not attempted (no fingerprint, or no API key configured)
+    #[default]
+    Skipped,
+}
+
+/// Outcome of MusicBrainz release lookups for the file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MusicBrainzStatus {
+    /// Matched; release and cover lookups happen after confirmation
+    Pending,
+    /// Nothing to look up without an AcoustID match
+    #[default]
+    Skipped,
+}
+
+/// How each metadata provider fared for one file, so the confirmation UI
+/// can show why it is incomplete.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderStatus {
+    pub id3: Id3Status,
+    pub fingerprint: FingerprintStatus,
+    pub acoustid: AcoustIdStatus,
+    pub musicbrainz: MusicBrainzStatus,
+}
+
+/// Real container/codec of an audio file, determined from its header bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AudioFormat {
+    /// MPEG-1/2 Layer III
+    Mp3,
+    /// Raw AAC in ADTS frames
+    Aac,
+    /// MP4/M4A container (usually AAC or ALAC)
+    Mp4,
+    Flac,
+    Wav,
+    /// Ogg container with Vorbis (or anything other than Opus)
+    Ogg,
+    /// Ogg container with Opus
+    Opus,
+}
+
+impl AudioFormat {
+    /// Canonical file extension for this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Mp3 => "mp3",
+            Self::Aac => "aac",
+            Self::Mp4 => "m4a",
+            Self::Flac => "flac",
+            Self::Wav => "wav",
+            Self::Ogg => "ogg",
+            Self::Opus => "opus",
+        }
+    }
+
+    /// Whether a (lowercase) file extension is a normal one for this format.
+    pub fn matches_extension(&self, extension: &str) -> bool {
+        match self {
+            Self::Mp3 => extension == "mp3",
+            Self::Aac => extension == "aac",
+            Self::Mp4 => matches!(extension, "m4a" | "mp4" | "aac"),
+            Self::Flac => extension == "flac",
+            Self::Wav => extension == "wav",
+            Self::Ogg => matches!(extension, "ogg" | "oga"),
+            Self::Opus => matches!(extension, "opus" | "ogg"),
+        }
+    }
+}
+
+/// Technical details of an audio stream, read from the file itself.
+///
+/// Fields are `None` when the format isn't one we can parse (MP4, Ogg).
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioStreamInfo {
+    /// Codec name, e.g. "mp3", "flac" or "pcm_s16le"
+    pub codec: Option<String>,
+    /// Bitrate in kbps (the average for VBR files)
+    pub bitrate_kbps: Option<u32>,
+    /// Whether an MP3 is variable bitrate (Xing/VBRI header)
+    pub variable_bitrate: bool,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    /// Bits per sample for lossless formats
+    pub bits_per_sample: Option<u32>,
+    pub duration_secs: Option<u32>,
+}
+
 /// Extracted metadata from an audio file.
 /// All fields are optional since ID3 tags may be partially filled.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -75,7 +227,22 @@ pub struct AudioMetadata {
     pub release_mbid: Option<String>,
     /// MusicBrainz Artist ID ( for fanart tv fetching)
     pub artist_mbid: Option<String>,
-
+    /// Genre (from the ID3 TCON frame)
+    #[serde(default)]
+    pub genre: Option<String>,
+    /// Album artist (ID3 TPE2), when it differs from the track artist
+    /// (e.g. "Various Artists" on a compilation)
+    #[serde(default)]
+    pub album_artist: Option<String>,
+    /// Disc number on a multi-disc album (ID3 TPOS)
+    #[serde(default)]
+    pub disc_number: Option<u32>,
+    /// Star rating (1-5) from an ID3 POPM frame written by another player
+    #[serde(default)]
+    pub rating: Option<u8>,
+    /// Play count from the ID3 PCNT frame or a POPM counter
+    #[serde(default)]
+    pub play_count: Option<u32>,
 }
 
 impl AudioMetadata {
@@ -99,6 +266,83 @@ impl AudioMetadata {
         }
         missing
     }
+
+    /// Artist the album is filed under: the album artist if set, otherwise
+    /// the track artist.
+    pub fn album_artist_name(&self) -> Option<&String> {
+        self.album_artist
+            .as_ref()
+            .filter(|name| !name.trim().is_empty())
+            .or(self.artist.as_ref())
+    }
+
+    /// Album order key: disc (1 when unknown), then track (unknown last).
+    pub fn disc_track_key(&self) -> (u32, u32) {
+        (
+            self.disc_number.unwrap_or(1),
+            self.track_number.unwrap_or(u32::MAX),
+        )
+    }
+}
+
+/// Picture embedded in a file's tag (ID3 APIC).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddedArtwork {
+    /// MIME type recorded in the tag (e.g. "image/jpeg")
+    pub mime_type: String,
+    /// Pixel size, if the image could be decoded (JPEG and PNG)
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub size_bytes: u64,
+}
+
+/// Tag fields beyond `AudioMetadata`, read for the edit dialog.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtendedTags {
+    /// Composer (ID3 TCOM)
+    pub composer: Option<String>,
+    /// Part of a compilation (iTunes TCMP)
+    pub compilation: bool,
+    /// The front cover, or the first picture if there is none
+    pub artwork: Option<EmbeddedArtwork>,
+}
+
+/// How confident the automatic match behind a file's metadata was.
+///
+/// Fields are None when that lookup wasn't used (e.g. no MusicBrainz
+/// search for a fingerprint match).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchConfidence {
+    /// AcoustID fingerprint match score (0.0-1.0)
+    pub acoustid_score: Option<f32>,
+    /// Number of AcoustID submissions linking the fingerprint to the recording
+    pub acoustid_sources: Option<u32>,
+    /// MusicBrainz search score (0-100)
+    pub musicbrainz_score: Option<u32>,
+}
+
+impl MatchConfidence {
+    /// Sources at which a recording counts as fully corroborated
+    const FULL_CONFIDENCE_SOURCES: u32 = 5;
+
+    /// Overall score (0-100): the weakest of the available signals, or None
+    /// if there are none.
+    pub fn overall(&self) -> Option<u8> {
+        [
+            self.acoustid_score
+                .map(|s| (s.clamp(0.0, 1.0) * 100.0).round() as u32),
+            self.acoustid_sources
+                .map(|n| n.min(Self::FULL_CONFIDENCE_SOURCES) * 100 / Self::FULL_CONFIDENCE_SOURCES),
+            self.musicbrainz_score.map(|s| s.min(100)),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .map(|score| score as u8)
+    }
 }
 
 /// A tracked audio file in the upload pipeline.
@@ -126,6 +370,24 @@ pub struct TrackedAudioFile {
     pub metadata: AudioMetadata,
     /// Error message if status is Error
     pub error_message: Option<String>,
+    /// Real format from the file header (None if not recognised)
+    #[serde(default)]
+    pub detected_format: Option<AudioFormat>,
+    /// Set when the extension and the real format disagree
+    #[serde(default)]
+    pub format_warning: Option<String>,
+    /// Whether the file must be transcoded to MP3 for the device
+    #[serde(default)]
+    pub needs_transcode: bool,
+    /// Confidence of the fingerprint match, if metadata came from one
+    #[serde(default)]
+    pub confidence: Option<MatchConfidence>,
+    /// What each metadata provider found
+    #[serde(default)]
+    pub providers: ProviderStatus,
+    /// Composer, compilation flag and embedded artwork (`get_audio_metadata` only)
+    #[serde(default)]
+    pub extended_tags: Option<ExtendedTags>,
 }
 
 impl TrackedAudioFile {
@@ -154,6 +416,12 @@ impl TrackedAudioFile {
             metadata_source: MetadataSource::Unknown,
             metadata: AudioMetadata::default(),
             error_message: None,
+            detected_format: None,
+            format_warning: None,
+            needs_transcode: false,
+            confidence: None,
+            providers: ProviderStatus::default(),
+            extended_tags: None,
         }
     }
 
@@ -198,8 +466,19 @@ pub struct ProcessedFilesResult {
     pub incomplete_count: usize,
     /// Count of files with errors
     pub error_count: usize,
+    /// A problem affecting the whole batch, shown once instead of per file
+    #[serde(default)]
+    pub warning: Option<String>,
+    /// AcoustID was skipped because no API key is configured
+    #[serde(default)]
+    pub needs_acoustid_key: bool,
 }
 
+/// Batch warning when AcoustID lookups were skipped for a missing API key.
+pub const ACOUSTID_KEY_MISSING_WARNING: &str =
+    "AcoustID is not configured (ACOUSTIC_ID_API_KEY is not set), so metadata was read from \
+     file tags only";
+
 impl ProcessedFilesResult {
     pub fn from_files(files: Vec<TrackedAudioFile>) -> Self {
         let complete_count = files
@@ -220,6 +499,15 @@ impl ProcessedFilesResult {
             complete_count,
             incomplete_count,
             error_count,
+            warning: None,
+            needs_acoustid_key: false,
         }
     }
+
+    /// Mark the batch as processed without AcoustID for want of an API key.
+    pub fn without_acoustid_key(mut self) -> Self {
+        self.warning = Some(ACOUSTID_KEY_MISSING_WARNING.to_string());
+        self.needs_acoustid_key = true;
+        self
+    }
 }
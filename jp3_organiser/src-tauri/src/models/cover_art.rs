@@ -1,8 +1,24 @@
-use serde::{Deserialize};
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)] 
 #[serde(rename_all = "PascalCase")]
 pub enum ImageCoverType {
    Artist,
    Album,
-}
\ No newline at end of file
+}
+
+/// Release MBIDs known for each saved album.
+///
+/// Stored in `jp3/metadata/release_candidates.json`, keyed by the album's
+/// cover filename hash (see `cover_art_service::cover_filename`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseCandidateIndex {
+    /// AcoustID release MBIDs, tried directly when a text search finds nothing
+    pub albums: HashMap<String, Vec<String>>,
+    /// Release the user picked when several MusicBrainz releases matched
+    #[serde(default)]
+    pub chosen: HashMap<String, String>,
+}
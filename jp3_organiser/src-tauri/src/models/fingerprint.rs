@@ -0,0 +1,67 @@
+//! Stored fingerprint models.
+//!
+//! Fingerprints for library songs are kept in `jp3/metadata/fingerprints.json`
+//! so duplicate detection doesn't need to re-run fpcalc. Each entry records
+//! the Chromaprint algorithm and fpcalc version it was produced with, since
+//! fingerprints from different algorithms are not comparable.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// A fingerprint stored for one library song.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredFingerprint {
+    /// Compressed Chromaprint fingerprint as output by fpcalc
+    pub fingerprint: String,
+    /// Duration reported by fpcalc, in seconds
+    pub duration_secs: u32,
+    /// Chromaprint algorithm the fingerprint was computed with
+    pub algorithm: u32,
+    /// fpcalc version used, if it could be determined
+    pub fpcalc_version: Option<String>,
+}
+
+/// All stored fingerprints for a library, keyed by song path (e.g. `00/001.mp3`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FingerprintIndex {
+    pub entries: HashMap<String, StoredFingerprint>,
+}
+
+/// Result of re-fingerprinting the library.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefingerprintResult {
+    /// Songs checked
+    pub songs_checked: u32,
+    /// Songs whose fingerprint was (re)computed
+    pub refingerprinted: u32,
+    /// Songs already fingerprinted with the current algorithm/version
+    pub up_to_date: u32,
+    /// Songs that failed to fingerprint
+    pub failed: u32,
+    /// Stale entries removed because the song no longer exists
+    pub removed: u32,
+}
+
+/// Files from one import whose fingerprints match each other.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FingerprintGroup {
+    /// Tracking IDs of the matching files, in input order
+    pub tracking_ids: Vec<String>,
+    /// Best similarity between members (0.0 - 1.0)
+    pub similarity: f32,
+}
+
+/// Result of grouping just-processed files by fingerprint.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FingerprintGroupingResult {
+    /// Groups of two or more files that appear to be the same song
+    pub groups: Vec<FingerprintGroup>,
+    /// Tracking IDs with no usable fingerprint (not processed, or fpcalc failed)
+    pub missing: Vec<String>,
+}
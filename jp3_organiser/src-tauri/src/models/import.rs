@@ -0,0 +1,186 @@
+//! Import planning models.
+//!
+//! Used when onboarding an existing music folder: the folder is walked,
+//! metadata is inferred from tags plus the directory structure, and the
+//! result is returned as a plan grouped by album for the user to confirm
+//! before anything is copied into the library.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::TrackedAudioFile;
+
+/// A group of scanned files that appear to belong to the same album.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportAlbumGroup {
+    /// Inferred artist (from tags, falling back to the folder structure)
+    pub artist: Option<String>,
+    /// Inferred album (from tags, falling back to the folder structure)
+    pub album: Option<String>,
+    /// Folder the group was found in, relative to the scanned root
+    pub folder: String,
+    /// Files in this group, ordered by track number then filename
+    pub files: Vec<TrackedAudioFile>,
+    /// Number of files whose metadata is complete
+    pub complete_count: usize,
+}
+
+/// Album-level field whose values disagree within a session album group.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumFieldConflict {
+    /// Field name ("artist", "album", "year", "releaseMbid" or "trackNumber")
+    pub field: String,
+    /// The differing values (for "trackNumber", the numbers used more than once)
+    pub values: Vec<String>,
+}
+
+/// Tracked files in an import session that appear to belong to one album.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionAlbumGroup {
+    /// Most common artist spelling in the group
+    pub artist: Option<String>,
+    /// Most common album spelling in the group
+    pub album: Option<String>,
+    /// Folder shared by every file in the group, if there is one
+    pub folder: Option<String>,
+    /// Files in this group, ordered by track number then filename
+    pub files: Vec<TrackedAudioFile>,
+    /// Number of files whose metadata is complete
+    pub complete_count: usize,
+    /// Fields the files disagree on
+    pub conflicts: Vec<AlbumFieldConflict>,
+    /// Every file is complete and there are no conflicts
+    pub is_complete: bool,
+}
+
+/// Result of confirming every clean file in an import session at once.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmAllCompleteResult {
+    /// Tracking IDs confirmed by this call
+    pub confirmed_ids: Vec<String>,
+    /// Unconfirmed files that still need the user's attention, in session order
+    pub remaining: Vec<TrackedAudioFile>,
+}
+
+/// Result of scanning a music folder for import.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPlan {
+    /// The folder that was scanned
+    pub root_path: String,
+    /// Files grouped by inferred album
+    pub groups: Vec<ImportAlbumGroup>,
+    /// Total number of audio files found
+    pub total_files: usize,
+    /// Files skipped because their format is not supported
+    pub skipped_files: Vec<String>,
+    /// Files left out of a rescan because they are unchanged since the last import
+    pub unchanged_count: usize,
+    /// Aggregate predictions for the confirmation screen
+    pub stats: ImportPreviewStats,
+}
+
+/// Aggregate predictions for an import, computed before any work starts.
+///
+/// Predictions mirror `save_to_library`: names are matched exactly, and only
+/// files with complete metadata are counted towards new entries/duplicates.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPreviewStats {
+    /// Artists that do not exist in the library yet
+    pub new_artists: usize,
+    /// Albums that do not exist in the library yet
+    pub new_albums: usize,
+    /// Songs expected to be skipped as duplicates (of the library or each other)
+    pub expected_duplicates: usize,
+    /// Combined size of all files in the plan, in bytes
+    pub total_size_bytes: u64,
+    /// Files that are not MP3 and would need transcoding for the ESP32
+    pub transcode_count: usize,
+    /// Rough transcode time estimate, in seconds
+    pub estimated_transcode_secs: u64,
+}
+
+/// Recorded state of a source file at the time it was imported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScannedFileState {
+    /// File size in bytes
+    pub size: u64,
+    /// Last modification time (seconds since the Unix epoch)
+    pub modified_secs: u64,
+    /// SHA-256 of the file contents (hex)
+    pub hash: String,
+}
+
+/// Persisted rescan state, stored at `jp3/metadata/scan_state.json`.
+///
+/// Keyed by scanned root folder, then by absolute file path.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderScanState {
+    pub folders: HashMap<String, HashMap<String, ScannedFileState>>,
+}
+
+/// A file that failed to import, persisted so it can be retried later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedImport {
+    /// Original file path
+    pub file_path: String,
+    /// Just the filename (no directory)
+    pub file_name: String,
+    /// Why the file failed (decode error, network error, ...)
+    pub reason: String,
+    /// How many times this file has failed
+    pub attempts: u32,
+    /// When the file last failed (seconds since the Unix epoch)
+    pub last_failed_at: u64,
+}
+
+/// Result of `import_itunes_library`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItunesImportResult {
+    /// Songs added to the library
+    pub songs_imported: u32,
+    /// Songs the library already had
+    pub duplicates_skipped: u32,
+    /// Podcasts, videos, cloud-only tracks and unsupported formats
+    pub tracks_skipped: u32,
+    /// Files the export points at that could not be found
+    pub files_missing: Vec<String>,
+    /// Files left out because neither iTunes nor their tags give a title,
+    /// artist and album (import these through the normal flow)
+    pub incomplete_files: Vec<String>,
+    /// Loved/favorited tracks marked as favorites
+    pub favorites_restored: u32,
+    /// Playlists recreated
+    pub playlists_created: u32,
+}
+
+/// Result of `scan_and_adopt`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdoptResult {
+    /// Songs registered where their files are
+    pub songs_adopted: u32,
+    /// Songs the library already had
+    pub duplicates_skipped: u32,
+    /// Copy-protected (DRM) files that were not adopted
+    pub protected_skipped: u32,
+    /// Files left out because no title, artist and album could be found
+    /// (import these through the normal flow)
+    pub incomplete_files: Vec<String>,
+    /// Files skipped because their format is not supported
+    pub skipped_files: Vec<String>,
+    /// IDs of the adopted songs
+    pub song_ids: Vec<u32>,
+    /// Whether copying the files into the library was queued
+    pub copy_queued: bool,
+}
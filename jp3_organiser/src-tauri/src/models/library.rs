@@ -6,16 +6,43 @@
 //! - Separate tables for artists, albums, and songs
 //! - All integers are little-endian
 
-use serde::Serialize;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{
+    AudioFormat, AudioMetadata, AudioStreamInfo, EncodeProfile, MatchConfidence, MetadataSource,
+};
 
 // Binary format constants
 pub const LIBRARY_MAGIC: &[u8; 4] = b"LIB1";
-pub const LIBRARY_VERSION: u32 = 1;
-pub const HEADER_SIZE: u32 = 40;
+/// Version 2 stores sort name string IDs in the artist and album tables.
+/// Version 3 adds the genre table and a genre ID on each song.
+/// Version 4 grows song entries to 32 bytes, adding the disc number.
+/// Version 5 adds a CRC32 of the tables to the header.
+/// Version 6 leaves free space after each table so saves can append in place.
+pub const LIBRARY_VERSION: u32 = 6;
+/// First version whose artist and album entries carry sort names.
+pub const SORT_NAMES_VERSION: u32 = 2;
+/// First version with a genre table.
+pub const GENRES_VERSION: u32 = 3;
+/// First version with 32-byte song entries carrying disc numbers.
+pub const DISC_NUMBERS_VERSION: u32 = 4;
+/// First version whose header carries a checksum of the tables.
+pub const CHECKSUM_VERSION: u32 = 5;
+/// First version with free space after each table.
+pub const TABLE_SLACK_VERSION: u32 = 6;
+pub const HEADER_SIZE: u32 = 56;
+/// Header size of files written before table slack (v5).
+pub const HEADER_SIZE_V5: u32 = 52;
+/// Header size of files written before the checksum (v3 and v4).
+pub const HEADER_SIZE_V4: u32 = 48;
+/// Header size of files written before the genre table (v1 and v2).
+pub const HEADER_SIZE_V2: u32 = 40;
 
 /// Library header structure for binary serialization.
 ///
-/// Binary layout (40 bytes total):
+/// Binary layout (56 bytes total; 52 in v5, 48 in v3/v4, 40 before v3):
 /// ```text
 /// Offset  Size  Field
 /// 0x00    4     magic ("LIB1")
@@ -27,8 +54,24 @@ pub const HEADER_SIZE: u32 = 40;
 /// 0x18    4     artist_table_offset
 /// 0x1C    4     album_table_offset
 /// 0x20    4     song_table_offset
-/// 0x24    4     reserved
+/// 0x24    4     generation (reserved/0 in files written before it existed)
+/// 0x28    4     genre_count (v3+)
+/// 0x2C    4     genre_table_offset (v3+)
+/// 0x30    4     checksum (v5+, CRC32 of the tables)
+/// 0x34    4     string_table_len (v6+, bytes of the string table in use)
 /// ```
+///
+/// From v6 each table may be followed by unused space, so a table ends at
+/// its offset plus its used length (entry count times entry size, or
+/// `string_table_len`) rather than where the next table starts. Saves that
+/// only add entries write them into that space and patch the header. The
+/// v6 checksum covers just the used part of each table, in file order;
+/// the v5 checksum covers every byte after the header.
+///
+/// `generation` increases on every write to library.bin, including in-place
+/// edits, so readers can tell whether the library changed between reads.
+/// `checksum` is rewritten with it, so a file cut short or half-written
+/// (SD card pulled mid-write) fails validation instead of parsing garbage.
 #[derive(Debug, Clone)]
 pub struct LibraryHeader {
     pub magic: [u8; 4],
@@ -40,8 +83,19 @@ pub struct LibraryHeader {
     pub artist_table_offset: u32,
     pub album_table_offset: u32,
     pub song_table_offset: u32,
+    pub generation: u32,
+    pub genre_count: u32,
+    pub genre_table_offset: u32,
+    pub checksum: u32,
+    pub string_table_len: u32,
 }
 
+/// Byte offset of the generation counter within the header.
+pub const GENERATION_OFFSET: u64 = 0x24;
+
+/// Byte offset of the table checksum within the header.
+pub const CHECKSUM_OFFSET: u64 = 0x30;
+
 impl LibraryHeader {
     /// Create a new empty library header.
     pub fn new_empty() -> Self {
@@ -55,9 +109,46 @@ impl LibraryHeader {
             artist_table_offset: HEADER_SIZE,
             album_table_offset: HEADER_SIZE,
             song_table_offset: HEADER_SIZE,
+            generation: 0,
+            genre_count: 0,
+            genre_table_offset: HEADER_SIZE,
+            checksum: 0, // CRC32 of no bytes
+            string_table_len: 0,
         }
     }
 
+    /// Size of this header on disk, by version.
+    pub fn size(&self) -> u32 {
+        if self.version >= TABLE_SLACK_VERSION {
+            HEADER_SIZE
+        } else if self.version >= CHECKSUM_VERSION {
+            HEADER_SIZE_V5
+        } else if self.version >= GENRES_VERSION {
+            HEADER_SIZE_V4
+        } else {
+            HEADER_SIZE_V2
+        }
+    }
+
+    /// End of the string table's used bytes.
+    pub fn string_table_end(&self) -> u32 {
+        self.string_table_offset + self.string_table_len
+    }
+
+    /// (offset, length) of the used part of each table, in file order.
+    pub fn table_extents(&self) -> [(u32, u32); 5] {
+        [
+            (self.string_table_offset, self.string_table_len),
+            (self.artist_table_offset, self.artist_count * ArtistEntry::SIZE),
+            (self.album_table_offset, self.album_count * AlbumEntry::SIZE),
+            (
+                self.song_table_offset,
+                self.song_count * SongEntry::size_for(self.version),
+            ),
+            (self.genre_table_offset, self.genre_count * GenreEntry::SIZE),
+        ]
+    }
+
     /// Serialize header to bytes (little-endian).
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::with_capacity(HEADER_SIZE as usize);
@@ -70,15 +161,23 @@ impl LibraryHeader {
         bytes.extend_from_slice(&self.artist_table_offset.to_le_bytes());
         bytes.extend_from_slice(&self.album_table_offset.to_le_bytes());
         bytes.extend_from_slice(&self.song_table_offset.to_le_bytes());
-        // Reserved 4 bytes for future use
-        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&self.generation.to_le_bytes());
+        bytes.extend_from_slice(&self.genre_count.to_le_bytes());
+        bytes.extend_from_slice(&self.genre_table_offset.to_le_bytes());
+        bytes.extend_from_slice(&self.checksum.to_le_bytes());
+        bytes.extend_from_slice(&self.string_table_len.to_le_bytes());
         bytes
     }
 
     /// Parse header from bytes.
+    ///
+    /// Headers older than v3 are 40 bytes and have no genre table; their
+    /// genre fields are returned as empty. Headers older than v5 are at most
+    /// 48 bytes and carry no checksum (returned as 0). Before v6 the string
+    /// table runs up to the artist table, which gives `string_table_len`.
     #[allow(dead_code)]
     pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
-        if bytes.len() < HEADER_SIZE as usize {
+        if bytes.len() < HEADER_SIZE_V2 as usize {
             return None;
         }
 
@@ -87,16 +186,47 @@ impl LibraryHeader {
             return None;
         }
 
+        let version = u32::from_le_bytes(bytes[4..8].try_into().ok()?);
+        let song_table_offset = u32::from_le_bytes(bytes[32..36].try_into().ok()?);
+        let (genre_count, genre_table_offset) = if version >= GENRES_VERSION {
+            if bytes.len() < HEADER_SIZE_V4 as usize {
+                return None;
+            }
+            (
+                u32::from_le_bytes(bytes[40..44].try_into().ok()?),
+                u32::from_le_bytes(bytes[44..48].try_into().ok()?),
+            )
+        } else {
+            (0, song_table_offset)
+        };
+        let checksum = if version >= CHECKSUM_VERSION {
+            u32::from_le_bytes(bytes.get(48..52)?.try_into().ok()?)
+        } else {
+            0
+        };
+        let string_table_offset = u32::from_le_bytes(bytes[20..24].try_into().ok()?);
+        let artist_table_offset = u32::from_le_bytes(bytes[24..28].try_into().ok()?);
+        let string_table_len = if version >= TABLE_SLACK_VERSION {
+            u32::from_le_bytes(bytes.get(52..56)?.try_into().ok()?)
+        } else {
+            artist_table_offset.saturating_sub(string_table_offset)
+        };
+
         Some(Self {
             magic,
-            version: u32::from_le_bytes(bytes[4..8].try_into().ok()?),
+            version,
             song_count: u32::from_le_bytes(bytes[8..12].try_into().ok()?),
             artist_count: u32::from_le_bytes(bytes[12..16].try_into().ok()?),
             album_count: u32::from_le_bytes(bytes[16..20].try_into().ok()?),
-            string_table_offset: u32::from_le_bytes(bytes[20..24].try_into().ok()?),
-            artist_table_offset: u32::from_le_bytes(bytes[24..28].try_into().ok()?),
+            string_table_offset,
+            artist_table_offset,
             album_table_offset: u32::from_le_bytes(bytes[28..32].try_into().ok()?),
-            song_table_offset: u32::from_le_bytes(bytes[32..36].try_into().ok()?),
+            song_table_offset,
+            generation: u32::from_le_bytes(bytes[36..40].try_into().ok()?),
+            genre_count,
+            genre_table_offset,
+            checksum,
+            string_table_len,
         })
     }
 }
@@ -129,11 +259,12 @@ impl LibraryInfo {
 /// ```text
 /// Offset  Size  Field
 /// 0x00    4     name_string_id
-/// 0x04    4     reserved
+/// 0x04    4     sort_name_string_id (v2+, reserved in v1)
 /// ```
 #[derive(Debug, Clone)]
 pub struct ArtistEntry {
     pub name_string_id: u32,
+    pub sort_name_string_id: u32,
 }
 
 impl ArtistEntry {
@@ -142,7 +273,7 @@ impl ArtistEntry {
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::with_capacity(Self::SIZE as usize);
         bytes.extend_from_slice(&self.name_string_id.to_le_bytes());
-        bytes.extend_from_slice(&0u32.to_le_bytes()); // reserved
+        bytes.extend_from_slice(&self.sort_name_string_id.to_le_bytes());
         bytes
     }
 }
@@ -153,15 +284,20 @@ impl ArtistEntry {
 /// ```text
 /// Offset  Size  Field
 /// 0x00    4     name_string_id
-/// 0x04    4     artist_id
+/// 0x04    4     album_artist_id
 /// 0x08    2     year
-/// 0x0A    6     reserved
+/// 0x0A    2     reserved
+/// 0x0C    4     sort_name_string_id (v2+, reserved in v1)
 /// ```
+///
+/// `album_artist_id` is the artist the album is filed under. It differs from
+/// its songs' artists for compilations and collaborations.
 #[derive(Debug, Clone)]
 pub struct AlbumEntry {
     pub name_string_id: u32,
-    pub artist_id: u32,
+    pub album_artist_id: u32,
     pub year: u16,
+    pub sort_name_string_id: u32,
 }
 
 impl AlbumEntry {
@@ -170,13 +306,37 @@ impl AlbumEntry {
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::with_capacity(Self::SIZE as usize);
         bytes.extend_from_slice(&self.name_string_id.to_le_bytes());
-        bytes.extend_from_slice(&self.artist_id.to_le_bytes());
+        bytes.extend_from_slice(&self.album_artist_id.to_le_bytes());
         bytes.extend_from_slice(&self.year.to_le_bytes());
-        bytes.extend_from_slice(&[0u8; 6]); // reserved
+        bytes.extend_from_slice(&[0u8; 2]); // reserved
+        bytes.extend_from_slice(&self.sort_name_string_id.to_le_bytes());
         bytes
     }
 }
 
+/// Genre table entry (4 bytes, v3+).
+///
+/// Binary layout:
+/// ```text
+/// Offset  Size  Field
+/// 0x00    4     name_string_id
+/// ```
+#[derive(Debug, Clone)]
+pub struct GenreEntry {
+    pub name_string_id: u32,
+}
+
+impl GenreEntry {
+    pub const SIZE: u32 = 4;
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.name_string_id.to_le_bytes().to_vec()
+    }
+}
+
+/// Genre ID stored on songs without a genre.
+pub const NO_GENRE: u16 = 0xFFFF;
+
 /// Song entry flags for soft delete support.
 /// Using bitflags allows future expansion (e.g., favorites, hidden, etc.)
 pub mod song_flags {
@@ -184,9 +344,17 @@ pub mod song_flags {
     pub const ACTIVE: u8 = 0x00;
     /// Entry has been soft-deleted (skip during reads)
     pub const DELETED: u8 = 0x01;
+    /// Song is a favorite (kept through edits and compaction)
+    pub const FAVORITE: u8 = 0x02;
 }
 
-/// Song table entry (24 bytes).
+/// Highest star rating a song can have.
+pub const MAX_RATING: u8 = 5;
+
+/// Byte offset of the rating within a v4+ song entry.
+pub const RATING_OFFSET: u64 = 0x19;
+
+/// Song table entry (32 bytes; 24 before v4).
 ///
 /// Binary layout:
 /// ```text
@@ -197,8 +365,13 @@ pub mod song_flags {
 /// 0x0C    4     path_string_id (relative path in library)
 /// 0x10    2     track_number
 /// 0x12    2     duration_sec
-/// 0x14    1     flags (0x00 = active, 0x01 = deleted)
-/// 0x15    3     reserved
+/// 0x14    1     flags (see `song_flags`)
+/// 0x15    1     metadata_source (0 = unknown, as in files written before it)
+/// 0x16    2     genre_id (v3+, 0xFFFF = none; reserved in v1/v2)
+/// 0x18    1     disc_number (v4+, 0 = unknown)
+/// 0x19    1     rating (v4+, 0 = unrated, 1-5 stars)
+/// 0x1A    4     date_added (v4+, unix seconds, 0 = unknown)
+/// 0x1E    2     play_count (v4+, 0 in files written before it; saturates)
 /// ```
 #[derive(Debug, Clone)]
 pub struct SongEntry {
@@ -209,10 +382,32 @@ pub struct SongEntry {
     pub track_number: u16,
     pub duration_sec: u16,
     pub flags: u8,
+    /// Where the song's metadata came from at import (or Manual once edited)
+    pub metadata_source: MetadataSource,
+    pub genre_id: u16,
+    /// Disc of a multi-disc album (0 = unknown)
+    pub disc_number: u8,
+    /// Star rating (0 = unrated, up to `MAX_RATING`)
+    pub rating: u8,
+    /// When the song was saved to the library (unix seconds, 0 = unknown)
+    pub date_added: u32,
+    /// Times played, as imported from the source file's ID3 counters
+    pub play_count: u16,
 }
 
 impl SongEntry {
-    pub const SIZE: u32 = 24;
+    pub const SIZE: u32 = 32;
+    /// Entry size in libraries written before v4.
+    pub const SIZE_V3: u32 = 24;
+
+    /// Entry size for a library of the given version.
+    pub fn size_for(version: u32) -> u32 {
+        if version >= DISC_NUMBERS_VERSION {
+            Self::SIZE
+        } else {
+            Self::SIZE_V3
+        }
+    }
 
     /// Create a new active song entry.
     pub fn new(
@@ -222,6 +417,7 @@ impl SongEntry {
         path_string_id: u32,
         track_number: u16,
         duration_sec: u16,
+        genre_id: u16,
     ) -> Self {
         Self {
             title_string_id,
@@ -231,7 +427,58 @@ impl SongEntry {
             track_number,
             duration_sec,
             flags: song_flags::ACTIVE,
+            metadata_source: MetadataSource::Unknown,
+            genre_id,
+            disc_number: 0,
+            rating: 0,
+            date_added: 0,
+            play_count: 0,
+        }
+    }
+
+    /// Set where the song's metadata came from.
+    pub fn with_metadata_source(mut self, metadata_source: MetadataSource) -> Self {
+        self.metadata_source = metadata_source;
+        self
+    }
+
+    /// Set the disc number (0 = unknown).
+    pub fn with_disc_number(mut self, disc_number: u8) -> Self {
+        self.disc_number = disc_number;
+        self
+    }
+
+    /// Set the star rating (0 = unrated).
+    pub fn with_rating(mut self, rating: u8) -> Self {
+        self.rating = rating;
+        self
+    }
+
+    /// Set when the song was added (unix seconds).
+    pub fn with_date_added(mut self, date_added: u32) -> Self {
+        self.date_added = date_added;
+        self
+    }
+
+    /// Set the play count.
+    pub fn with_play_count(mut self, play_count: u16) -> Self {
+        self.play_count = play_count;
+        self
+    }
+
+    /// Set or clear the favorite flag.
+    pub fn with_favorite(mut self, favorite: bool) -> Self {
+        if favorite {
+            self.flags |= song_flags::FAVORITE;
+        } else {
+            self.flags &= !song_flags::FAVORITE;
         }
+        self
+    }
+
+    /// Check if this entry is a favorite.
+    pub fn is_favorite(&self) -> bool {
+        self.flags & song_flags::FAVORITE != 0
     }
 
     /// Check if this entry is deleted.
@@ -253,11 +500,16 @@ impl SongEntry {
         bytes.extend_from_slice(&self.track_number.to_le_bytes());
         bytes.extend_from_slice(&self.duration_sec.to_le_bytes());
         bytes.push(self.flags);
-        bytes.extend_from_slice(&[0u8; 3]); // reserved
+        bytes.push(self.metadata_source.to_byte());
+        bytes.extend_from_slice(&self.genre_id.to_le_bytes());
+        bytes.push(self.disc_number);
+        bytes.push(self.rating);
+        bytes.extend_from_slice(&self.date_added.to_le_bytes());
+        bytes.extend_from_slice(&self.play_count.to_le_bytes());
         bytes
     }
 
-    /// Parse a song entry from bytes.
+    /// Parse a song entry from a v4+ library.
     pub fn from_bytes(data: &[u8]) -> Option<Self> {
         if data.len() < Self::SIZE as usize {
             return None;
@@ -270,6 +522,12 @@ impl SongEntry {
             track_number: u16::from_le_bytes(data[16..18].try_into().ok()?),
             duration_sec: u16::from_le_bytes(data[18..20].try_into().ok()?),
             flags: data[20],
+            metadata_source: MetadataSource::from_byte(data[21]),
+            genre_id: u16::from_le_bytes(data[22..24].try_into().ok()?),
+            disc_number: data[24],
+            rating: data[25],
+            date_added: u32::from_le_bytes(data[26..30].try_into().ok()?),
+            play_count: u16::from_le_bytes(data[30..32].try_into().ok()?),
         })
     }
 }
@@ -353,6 +611,12 @@ pub struct SaveToLibraryResult {
     pub albums_added: u32,
     pub songs_added: u32,
     pub duplicates_skipped: u32,
+    /// Copy-protected (DRM) files that were not saved
+    pub protected_skipped: u32,
+    /// Files skipped because their source no longer exists
+    pub missing_skipped: u32,
+    /// Positions (in the request's `files`) of the files that were saved
+    pub saved_files: Vec<u32>,
     /// IDs of the newly saved songs (for adding to playlists)
     pub song_ids: Vec<u32>,
     /// IDs of existing songs that were duplicates (for adding to playlists)
@@ -363,31 +627,54 @@ pub struct SaveToLibraryResult {
     pub album_ids: Vec<u32>,
 }
 
+/// Result of `copy_adopted_songs`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyAdoptedResult {
+    /// Adopted files copied into the music buckets
+    pub files_copied: u32,
+    /// Songs now pointing at a copy
+    pub songs_updated: u32,
+    /// Adopted files that no longer exist where they were adopted
+    pub missing_files: Vec<String>,
+    /// Files that could not be copied
+    pub failed_files: Vec<String>,
+}
+
 /// Parsed artist data for frontend display.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ParsedArtist {
     pub id: u32,
     pub name: String,
+    pub sort_name: String,
+    /// Romanized search/sort key for non-Latin names (transliteration enabled)
+    pub romanized: Option<String>,
 }
 
 /// Parsed album data for frontend display.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ParsedAlbum {
     pub id: u32,
     pub name: String,
+    pub sort_name: String,
+    /// Romanized search/sort key for non-Latin names (transliteration enabled)
+    pub romanized: Option<String>,
+    /// Album artist (may differ from the songs' artists)
     pub artist_id: u32,
     pub artist_name: String,
     pub year: u16,
 }
 
 /// Parsed song data for frontend display.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ParsedSong {
     pub id: u32,
     pub title: String,
+    /// Romanized search key for non-Latin titles (transliteration enabled)
+    pub romanized_title: Option<String>,
     pub artist_id: u32,
     pub artist_name: String,
     pub album_id: u32,
@@ -395,7 +682,34 @@ pub struct ParsedSong {
     pub year: u16,
     pub path: String,
     pub track_number: u16,
+    /// Disc of a multi-disc album (0 = unknown)
+    pub disc_number: u8,
+    /// Star rating (0 = unrated, 1-5)
+    pub rating: u8,
+    pub is_favorite: bool,
+    /// When the song was saved to the library (unix seconds, 0 = unknown)
+    pub date_added: u32,
+    /// Times played, imported from ID3 play counters (0 = none recorded)
+    pub play_count: u16,
     pub duration_sec: u16,
+    pub genre_id: Option<u16>,
+    pub genre: Option<String>,
+    /// Where the metadata came from (Unknown for songs saved before this was recorded)
+    pub metadata_source: MetadataSource,
+    /// Confidence of the automatic match the metadata came from, if any
+    pub confidence: Option<MatchConfidence>,
+    /// Overall confidence score (0-100), see `MatchConfidence::overall`
+    pub confidence_score: Option<u8>,
+}
+
+/// Parsed genre data for frontend display.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParsedGenre {
+    pub id: u16,
+    pub name: String,
+    /// Number of active songs with this genre
+    pub song_count: u32,
 }
 
 /// Complete parsed library data for frontend display.
@@ -403,11 +717,162 @@ pub struct ParsedSong {
 #[serde(rename_all = "camelCase")]
 pub struct ParsedLibrary {
     pub version: u32,
+    /// Write counter of the library.bin this was read from
+    pub generation: u32,
+    pub artists: Vec<ParsedArtist>,
+    pub albums: Vec<ParsedAlbum>,
+    pub songs: Vec<ParsedSong>,
+    /// Genres with at least one active song, sorted by name
+    pub genres: Vec<ParsedGenre>,
+}
+
+/// One album with its songs, from `get_album`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumDetail {
+    pub album: ParsedAlbum,
+    /// Active songs in disc/track order
+    pub songs: Vec<ParsedSong>,
+    pub total_duration_sec: u32,
+    /// Cached cover image, if one has been fetched
+    pub cover_path: Option<String>,
+    /// Combined size of the songs' audio files
+    pub size_bytes: u64,
+}
+
+/// One artist with their albums and songs, from `get_artist`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtistDetail {
+    pub artist: ParsedArtist,
+    /// Albums with this album artist, oldest first
+    pub albums: Vec<ParsedAlbum>,
+    /// Songs credited to the artist plus every song on their albums, in
+    /// album then disc/track order
+    pub songs: Vec<ParsedSong>,
+    /// Earliest and latest album year among the songs (`None` if unknown)
+    pub first_year: Option<u16>,
+    pub last_year: Option<u16>,
+    pub total_duration_sec: u32,
+    /// Cached artist image, if one has been fetched
+    pub cover_path: Option<String>,
+    /// Combined size of the songs' audio files
+    pub size_bytes: u64,
+}
+
+/// Which list a paginated library load reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LibraryPageKind {
+    Artists,
+    Albums,
+    Songs,
+    /// Songs, most recently added first
+    RecentlyAdded,
+}
+
+/// Narrows a paginated library load to one artist and/or album.
+///
+/// Artist pages ignore it; album pages use only `artist_id` (the album
+/// artist); song pages match the song's own artist and album.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryPageFilter {
+    pub artist_id: Option<u32>,
+    pub album_id: Option<u32>,
+}
+
+/// What `load_library` orders songs by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SongSortKey {
+    /// Title sort key, then artist and album
+    Title,
+    /// Artist sort key, then album, disc and track
+    Artist,
+    /// Album sort key, then album artist, disc and track
+    Album,
+    /// Album year, then artist, album, disc and track
+    Year,
+    Duration,
+    DateAdded,
+}
+
+/// Song ordering requested from `load_library`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibrarySort {
+    pub key: SongSortKey,
+    /// Reverse the whole order (Z-A, newest/longest first)
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// One page of a library list, tied to a snapshot of the library.
+///
+/// Only the list matching the requested kind is filled.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryPage {
+    /// Pass back when loading the next page
+    pub snapshot: String,
+    pub offset: u32,
+    /// Total items in the list (across all pages, after filtering)
+    pub total: u32,
     pub artists: Vec<ParsedArtist>,
     pub albums: Vec<ParsedAlbum>,
     pub songs: Vec<ParsedSong>,
 }
 
+/// Payload of the `library://` change events (see `library_events_service`).
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryChangeEvent {
+    pub base_path: String,
+    /// Generation of library.bin after the change (for `load_library_delta`)
+    pub generation: u32,
+    /// Songs added, deleted or updated
+    pub song_ids: Vec<u32>,
+    /// Albums updated
+    pub album_ids: Vec<u32>,
+    /// Artists updated
+    pub artist_ids: Vec<u32>,
+    /// Playlists created, changed or deleted
+    pub playlist_ids: Vec<u32>,
+}
+
+/// What changed in the library since a generation the frontend already has.
+///
+/// Entries are matched by ID. When the old generation is no longer known,
+/// `full` is set and the lists hold the whole library instead.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryDelta {
+    /// Generation these changes bring the frontend up to
+    pub generation: u32,
+    /// The lists are the whole library, not changes
+    pub full: bool,
+    /// Added or changed entries
+    pub artists: Vec<ParsedArtist>,
+    pub albums: Vec<ParsedAlbum>,
+    pub songs: Vec<ParsedSong>,
+    pub removed_artist_ids: Vec<u32>,
+    pub removed_album_ids: Vec<u32>,
+    pub removed_song_ids: Vec<u32>,
+    /// Always the full genre list (it is small)
+    pub genres: Vec<ParsedGenre>,
+}
+
+/// Library search matches by category, best first.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibrarySearchResults {
+    pub artists: Vec<ParsedArtist>,
+    pub albums: Vec<ParsedAlbum>,
+    pub songs: Vec<ParsedSong>,
+    pub total_count: u32,
+}
+
 /// Result returned after deleting songs from the library.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -416,16 +881,365 @@ pub struct DeleteSongsResult {
     pub songs_deleted: u32,
     /// Song IDs that were not found
     pub not_found: Vec<u32>,
-    /// Number of audio files deleted from music/
+    /// Number of audio files moved from music/ to the trash
     pub files_deleted: u32,
 }
 
+/// Current format of library JSON exports
+pub const LIBRARY_JSON_VERSION: u32 = 1;
+
+/// A library exported by `export_library_json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryJson {
+    /// See `LIBRARY_JSON_VERSION`
+    pub format_version: u32,
+    /// Unix seconds
+    pub exported_at: u64,
+    pub songs: Vec<SongJson>,
+    #[serde(default)]
+    pub playlists: Vec<PlaylistJson>,
+}
+
+/// One song of a library JSON export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SongJson {
+    /// Song ID in the exported library (ties playlists to songs)
+    pub id: u32,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    /// Set when it differs from the song's artist
+    #[serde(default)]
+    pub album_artist: Option<String>,
+    #[serde(default)]
+    pub year: u16,
+    #[serde(default)]
+    pub track_number: u16,
+    #[serde(default)]
+    pub disc_number: u8,
+    #[serde(default)]
+    pub genre: Option<String>,
+    #[serde(default)]
+    pub duration_sec: u16,
+    #[serde(default)]
+    pub rating: u8,
+    #[serde(default)]
+    pub is_favorite: bool,
+    #[serde(default)]
+    pub play_count: u16,
+    #[serde(default)]
+    pub metadata_source: MetadataSource,
+    /// Audio file, relative to the exported library's `jp3/music`
+    pub path: String,
+}
+
+/// One playlist of a library JSON export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistJson {
+    pub name: String,
+    /// IDs of `SongJson` entries, in playlist order
+    pub song_ids: Vec<u32>,
+}
+
+/// Result returned after `export_library_json`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportLibraryJsonResult {
+    pub path: String,
+    pub song_count: u32,
+    pub playlist_count: u32,
+}
+
+/// Result returned after `import_library_json`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportLibraryJsonResult {
+    /// Songs added to the library
+    pub songs_imported: u32,
+    /// Songs the library already had
+    pub duplicates_skipped: u32,
+    /// Paths of songs whose audio file wasn't found
+    pub files_missing: Vec<String>,
+    pub favorites_restored: u32,
+    pub playlists_created: u32,
+}
+
+/// A preview clip written by `export_preview_clip`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewClip {
+    /// Where the clip was written
+    pub path: String,
+    /// Seconds into the song the clip starts
+    pub start_secs: f32,
+    /// Clip length in seconds (shorter than asked near the end of the song)
+    pub duration_secs: f32,
+    pub size_bytes: u64,
+}
+
+/// A playlist holding songs that are about to be deleted.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistImpact {
+    pub playlist_id: u32,
+    pub name: String,
+    /// Entries that would disappear from the playlist
+    pub songs_affected: u32,
+    /// Entries left afterwards
+    pub songs_remaining: u32,
+}
+
+/// What `delete_songs` would do, reported by `preview_delete`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletePreview {
+    /// Songs that would be marked as deleted
+    pub songs: u32,
+    /// Song IDs not in the library (or already deleted)
+    pub not_found: Vec<u32>,
+    /// Audio files that would move to the trash
+    pub files: u32,
+    /// Size of those files
+    pub bytes: u64,
+    /// Playlists referencing the songs, by name
+    pub playlists: Vec<PlaylistImpact>,
+}
+
+/// Order songs are picked in when filling a size budget.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SelectionPriority {
+    /// As `load_library` lists them
+    #[default]
+    LibraryOrder,
+    /// Highest star rating first
+    HighestRated,
+    RecentlyAdded,
+    /// Highest play count first (imported from ID3 or iTunes; whole albums
+    /// by their total)
+    MostPlayed,
+}
+
+/// How `select_songs_for_size` ranks songs.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectionPreferences {
+    #[serde(default)]
+    pub favorites_first: bool,
+    #[serde(default)]
+    pub priority: SelectionPriority,
+    /// Take or skip albums as a whole instead of song by song
+    #[serde(default)]
+    pub whole_albums: bool,
+}
+
+/// Songs chosen to fill a size budget. `song_ids` can be passed straight
+/// to `export_selection`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizedSelection {
+    /// Included songs, in the order they were picked
+    pub song_ids: Vec<u32>,
+    /// Songs that didn't fit, or whose audio file is missing
+    pub excluded_song_ids: Vec<u32>,
+    /// Audio bytes of the included songs
+    pub selected_bytes: u64,
+    /// Audio bytes of the songs that didn't fit
+    pub excluded_bytes: u64,
+}
+
+/// Result returned after exporting a selection to another drive.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSelectionResult {
+    pub songs_exported: u32,
+    /// Selected songs left out (deleted, unknown ID, or audio file missing)
+    pub songs_skipped: u32,
+    pub playlists_exported: u32,
+    /// Songs re-encoded to MP3 for the encode profile
+    pub songs_transcoded: u32,
+    /// Album and artist covers copied (thumbnails not counted)
+    pub covers_exported: u32,
+    /// Audio and cover bytes copied
+    pub bytes_copied: u64,
+    /// Songs already on the drive from an interrupted run, checked by hash
+    /// instead of copied again
+    pub songs_verified: u32,
+    /// Auto-playlists generated on the drive (see `sync_auto_playlists`)
+    pub auto_playlists_exported: u32,
+}
+
+/// A library.bin write in progress (see `write_marker_service`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteMarker {
+    pub operation_id: String,
+    /// Command or step that was writing, e.g. "delete_songs"
+    pub operation: String,
+    /// Unix seconds
+    pub started_at: u64,
+}
+
+/// Writes to library.bin that were cut off, found when opening the library.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterruptedWrites {
+    pub writes: Vec<WriteMarker>,
+    /// library.bin still passes its header and checksum checks
+    pub library_intact: bool,
+}
+
+/// How `recover_interrupted_writes` dealt with an interrupted write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WriteRecovery {
+    /// library.bin was undamaged and is kept as it is
+    Intact,
+    /// The chosen backup was restored
+    Restored,
+    /// library.bin was damaged and rebuilt with `repair_library`
+    Repaired,
+}
+
+/// What `graceful_shutdown` stopped, kept for the next launch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShutdownReport {
+    /// Unix seconds
+    pub shut_down_at: u64,
+    /// Background tasks dropped before they started
+    pub cancelled_tasks: Vec<String>,
+    /// A compaction was asked to stop (library.bin is left as it was)
+    pub compaction_cancelled: bool,
+    /// Everything running had stopped before the app exited
+    pub clean: bool,
+}
+
+/// An export that was interrupted before it finished.
+///
+/// Stored in `jp3/metadata/pending_sync.json` of the source library until
+/// the export is resumed or discarded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingSync {
+    pub song_ids: Vec<u32>,
+    pub playlist_ids: Vec<u32>,
+    pub dest_path: String,
+    #[serde(default)]
+    pub profile: Option<EncodeProfile>,
+    /// library.bin version to write, for firmware that reads only an older one
+    #[serde(default)]
+    pub library_version: Option<u32>,
+    /// Unix seconds when the export was first started
+    pub started_at: u64,
+    /// Hashes of the song files fully copied so far, by relative path on
+    /// the destination
+    #[serde(default)]
+    pub copied: HashMap<String, String>,
+}
+
+/// What an export would do with one file on the drive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncAction {
+    Copy,
+    Delete,
+    Skip,
+}
+
+/// Why a file in a `SyncPlanReport` gets its action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncReason {
+    /// Not on the drive yet
+    New,
+    /// A file is at its path on the drive, but not with this content
+    ChangedHash,
+    /// Copied by an interrupted run and its hash still matches
+    AlreadyCopied,
+    /// In the drive's music folder but not part of the export
+    OrphanedOnDevice,
+    /// Selected, but deleted, unknown or missing its audio file
+    Excluded,
+}
+
+/// One file in a `SyncPlanReport`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPlanFile {
+    pub action: SyncAction,
+    pub reason: SyncReason,
+    /// Path relative to the drive's `jp3` folder; for excluded songs, the
+    /// song's path in the source library (empty for unknown IDs)
+    pub path: String,
+    /// Source song ID, for audio files of selected songs
+    pub song_id: Option<u32>,
+    /// Bytes to copy or free (estimated from the source for songs whose
+    /// transcode isn't cached yet)
+    pub bytes: u64,
+    /// Re-encoded to MP3 on the way out
+    pub transcode: bool,
+}
+
+/// What an export (or resuming the pending one) would do, without writing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPlanReport {
+    pub dest_path: String,
+    /// Planned as the resumption of the interrupted export to `dest_path`
+    pub resumes_pending: bool,
+    /// Songs in library order, then orphans on the drive, excluded songs and covers
+    pub files: Vec<SyncPlanFile>,
+    pub files_to_copy: u32,
+    pub files_to_delete: u32,
+    pub files_skipped: u32,
+    pub bytes_to_copy: u64,
+    pub bytes_to_free: u64,
+}
+
+/// A finished export, as recorded in the source library's sync history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncRecord {
+    /// ID from the drive's device marker, if it could be read
+    pub device_id: Option<String>,
+    pub dest_path: String,
+    /// Unix seconds when the export finished
+    pub synced_at: u64,
+    /// Whether it was finished by `resume_sync`
+    pub resumed: bool,
+    pub songs_exported: u32,
+    /// Song files copied (those verified from an interrupted run are not counted)
+    pub files_copied: u32,
+    /// Audio and cover bytes copied
+    pub bytes_copied: u64,
+    /// Whether `verify_library` found no issues in the exported library
+    pub verified: bool,
+}
+
+/// Result returned after restoring soft-deleted songs.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreSongsResult {
+    /// Number of songs whose DELETED flag was cleared
+    pub songs_restored: u32,
+    /// Song IDs that don't exist or aren't deleted
+    pub not_deleted: Vec<u32>,
+    /// Deleted songs left as they were because their audio file is gone
+    pub missing_files: Vec<u32>,
+}
+
 /// Result returned after editing a song's metadata.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EditSongResult {
     /// The new song ID after edit (old one is soft-deleted, new one appended)
     pub new_song_id: u32,
+    /// The title-only edit was made in place, so the ID is unchanged
+    pub in_place: bool,
     /// Whether a new artist was created
     pub artist_created: bool,
     /// Whether a new album was created
@@ -434,6 +1248,30 @@ pub struct EditSongResult {
     pub playlists_updated: u32,
 }
 
+/// One song's new metadata in an `edit_songs_metadata` batch.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SongEdit {
+    pub song_id: u32,
+    pub metadata: AudioMetadata,
+}
+
+/// Result returned after editing several songs' metadata at once.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditSongsResult {
+    /// New song IDs, in the same order as the edits
+    pub new_song_ids: Vec<u32>,
+    /// Number of title-only edits made in place (their IDs are unchanged)
+    pub edited_in_place: u32,
+    /// Number of artists created by the batch
+    pub artists_created: u32,
+    /// Number of albums created by the batch
+    pub albums_created: u32,
+    /// Number of playlists updated with new song IDs
+    pub playlists_updated: u32,
+}
+
 /// Library statistics for compaction decision.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -452,10 +1290,230 @@ pub struct LibraryStats {
     pub total_strings: u32,
     /// Percentage of deleted songs (0-100)
     pub deleted_percentage: f32,
-    /// Recommended to compact (deleted > 20%)
+    /// Bytes of strings nothing kept by a compaction refers to
+    pub dead_string_bytes: u64,
+    /// Bytes compaction would reclaim (deleted songs, orphaned entries, dead strings)
+    pub wasted_bytes: u64,
+    /// Recommended to compact (some threshold in `CompactionThresholds` was crossed)
     pub should_compact: bool,
+    /// First threshold crossed, if any
+    pub compact_reason: Option<CompactionRule>,
     /// File size in bytes
     pub file_size_bytes: u64,
+    /// Titles, artist and album names per writing system, most common first
+    pub script_distribution: Vec<ScriptCount>,
+}
+
+/// When `get_library_stats` recommends compacting. A `None` threshold is
+/// not checked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CompactionThresholds {
+    /// Deleted songs as a percentage of all songs (0-100)
+    pub deleted_percentage: Option<f32>,
+    /// Bytes of dead strings
+    pub dead_string_bytes: Option<u64>,
+    /// Total bytes compaction would reclaim
+    pub wasted_bytes: Option<u64>,
+}
+
+impl Default for CompactionThresholds {
+    fn default() -> Self {
+        Self {
+            deleted_percentage: Some(20.0),
+            dead_string_bytes: None,
+            wasted_bytes: None,
+        }
+    }
+}
+
+/// Threshold that made `get_library_stats` recommend compacting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CompactionRule {
+    DeletedPercentage,
+    DeadStringBytes,
+    WastedBytes,
+}
+
+impl CompactionThresholds {
+    /// First rule the given stats cross, checked in declaration order.
+    pub fn evaluate(
+        &self,
+        deleted_percentage: f32,
+        dead_string_bytes: u64,
+        wasted_bytes: u64,
+    ) -> Option<CompactionRule> {
+        if self.deleted_percentage.is_some_and(|max| deleted_percentage > max) {
+            Some(CompactionRule::DeletedPercentage)
+        } else if self.dead_string_bytes.is_some_and(|max| dead_string_bytes > max) {
+            Some(CompactionRule::DeadStringBytes)
+        } else if self.wasted_bytes.is_some_and(|max| wasted_bytes > max) {
+            Some(CompactionRule::WastedBytes)
+        } else {
+            None
+        }
+    }
+}
+
+/// Days deleted audio stays in the trash unless the library sets otherwise.
+pub const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+
+/// Backups kept in `jp3/backups` unless the library sets otherwise.
+pub const DEFAULT_BACKUP_RETENTION: u32 = 10;
+
+/// Audio files per music bucket unless the library sets otherwise.
+pub const DEFAULT_FILES_PER_BUCKET: u32 = 256;
+
+/// Largest bucket size that can be configured.
+pub const MAX_FILES_PER_BUCKET: u32 = 4096;
+
+/// How the audio files saved to the library are named.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileNameScheme {
+    /// `001.mp3`, `002.mp3`, ... in the order songs were saved
+    #[default]
+    Sequential,
+    /// `01 - Title.mp3`
+    TrackTitle,
+    /// `Artist - Title.mp3`
+    ArtistTitle,
+}
+
+/// How audio files are laid out in `jp3/music`.
+///
+/// Files go in buckets of `files_per_bucket`, named by `file_names`.
+/// Buckets are `00/`, `01/`, ...; with `two_level` they are grouped as
+/// `00/00/`, `00/01/`, ..., `files_per_bucket` buckets to a group, for
+/// firmware or file systems that slow down with many entries in one folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BucketLayout {
+    pub files_per_bucket: u32,
+    pub two_level: bool,
+    #[serde(default)]
+    pub file_names: FileNameScheme,
+}
+
+impl Default for BucketLayout {
+    fn default() -> Self {
+        Self {
+            files_per_bucket: DEFAULT_FILES_PER_BUCKET,
+            two_level: false,
+            file_names: FileNameScheme::Sequential,
+        }
+    }
+}
+
+impl BucketLayout {
+    /// Folder of the `bucket`th bucket, relative to `jp3/music`.
+    pub fn bucket_dir(&self, bucket: u32) -> String {
+        if self.two_level {
+            let per_group = self.files_per_bucket.max(1);
+            format!("{:02}/{:02}", bucket / per_group, bucket % per_group)
+        } else {
+            format!("{:02}", bucket)
+        }
+    }
+
+    /// Path of the `index`th audio file, relative to `jp3/music`, when the
+    /// library is numbered from the start (as exports and renumbering
+    /// compaction lay it out). Always a sequential name; see
+    /// `file_naming_service` for the others.
+    pub fn file_path(&self, index: usize, extension: &str) -> String {
+        let per_bucket = self.files_per_bucket.max(1) as usize;
+        format!(
+            "{}/{:03}.{}",
+            self.bucket_dir((index / per_bucket) as u32),
+            index % per_bucket + 1,
+            extension
+        )
+    }
+}
+
+/// Per-library settings kept in `jp3/metadata/library_settings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LibrarySettings {
+    pub compaction_thresholds: CompactionThresholds,
+    /// Days deleted audio stays in `jp3/.trash` (None = until emptied)
+    pub trash_retention_days: Option<u32>,
+    /// Newest backups kept in `jp3/backups` (None = all)
+    pub backup_retention: Option<u32>,
+    /// Layout for audio files saved from now on
+    pub bucket_layout: BucketLayout,
+    /// Covers saved under ID-based names have been renamed (see
+    /// `migrate_legacy_covers`)
+    pub legacy_covers_migrated: bool,
+    /// Generation of the library.bin written by the last compaction, which
+    /// renumbers album and artist IDs (None = not compacted by this build)
+    pub compacted_generation: Option<u32>,
+}
+
+impl Default for LibrarySettings {
+    fn default() -> Self {
+        Self {
+            compaction_thresholds: CompactionThresholds::default(),
+            trash_retention_days: Some(DEFAULT_TRASH_RETENTION_DAYS),
+            backup_retention: Some(DEFAULT_BACKUP_RETENTION),
+            bucket_layout: BucketLayout::default(),
+            legacy_covers_migrated: false,
+            compacted_generation: None,
+        }
+    }
+}
+
+/// An audio file in `jp3/.trash`, waiting to be restored or purged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashEntry {
+    pub id: String,
+    /// Where the file was, relative to `jp3/music`
+    pub original_path: String,
+    /// Title of the song it belonged to, if known
+    pub title: Option<String>,
+    /// When it was deleted (Unix seconds)
+    pub deleted_at: u64,
+    pub size_bytes: u64,
+}
+
+/// Result returned after emptying the trash.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmptyTrashResult {
+    pub files_removed: u32,
+    pub bytes_freed: u64,
+}
+
+/// Size of the transcode cache in `jp3/metadata/transcode_cache`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscodeCacheStats {
+    pub file_count: u32,
+    pub total_bytes: u64,
+    /// When the least recently used transcode was last used (Unix seconds)
+    pub oldest_used_at: Option<u64>,
+}
+
+/// Result returned after pruning the transcode cache.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneTranscodeCacheResult {
+    pub files_removed: u32,
+    pub bytes_freed: u64,
+}
+
+/// Result returned after restoring files from the trash.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreFromTrashResult {
+    /// Files moved back into `jp3/music`
+    pub files_restored: u32,
+    /// Deleted songs brought back with their files
+    pub songs_restored: u32,
+    /// Entry IDs left in the trash (unknown, or the original path is taken)
+    pub not_restored: Vec<String>,
 }
 
 /// Result returned after compacting the library.
@@ -478,6 +1536,212 @@ pub struct CompactResult {
     pub new_size_bytes: u64,
     /// Bytes saved
     pub bytes_saved: u64,
+    /// Audio files moved to close numbering gaps (`renumber_files` only)
+    pub files_renumbered: u32,
+    /// Bucket folders left empty and removed (`renumber_files` only;
+    /// not predicted by a dry run)
+    pub buckets_removed: u32,
+    /// Nothing was written; the counts are what compaction would do
+    pub dry_run: bool,
+}
+
+/// Stage of a running compaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CompactPhase {
+    /// Re-interning song strings (counted in chunks)
+    Strings,
+    /// Rebuilding the song table (counted in chunks)
+    Songs,
+    /// Writing and swapping in the new library.bin
+    Writing,
+    /// Remapping song IDs in playlists (counted in playlists)
+    Playlists,
+    /// Moving renumbered audio files into place (counted in files)
+    Files,
+}
+
+/// Payload for `library://compaction-progress`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactProgress {
+    pub phase: CompactPhase,
+    /// Units of the phase finished so far
+    pub done: u32,
+    /// Units in the phase
+    pub total: u32,
+}
+
+impl CompactProgress {
+    pub fn new(phase: CompactPhase, done: u32, total: u32) -> Self {
+        Self { phase, done, total }
+    }
+}
+
+/// Result returned after migrating library.bin to the current version.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrateLibraryResult {
+    /// Version the file had before
+    pub from_version: u32,
+    /// Version the file has now (same as before if nothing was done)
+    pub to_version: u32,
+    /// Copy of the old file, if one was made
+    pub backup_path: Option<String>,
+}
+
+/// A table entry pointing at a string, artist, album or genre that doesn't exist.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BadReference {
+    /// Table holding the entry ("artist", "album", "song" or "genre")
+    pub table: &'static str,
+    /// Index of the entry in its table
+    pub entry_id: u32,
+    /// Field holding the reference (e.g. "albumId")
+    pub field: &'static str,
+    /// The out-of-range ID
+    pub value: u32,
+}
+
+/// Problems found in library.bin by `verify_library`.
+///
+/// Checks stop early when a later one can't be trusted: nothing past the
+/// header is read if the header is unusable, and no table is parsed if the
+/// tables overlap or run past the end of the file.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryVerificationReport {
+    /// Format version (None if the file is too short to have one)
+    pub version: Option<u32>,
+    /// Why the header couldn't be used (bad magic, too short, unknown version)
+    pub header_error: Option<String>,
+    /// Whether the table checksum matched (always true before v5)
+    pub checksum_valid: bool,
+    /// Tables starting inside the header, overlapping or past the end of the file
+    pub layout_errors: Vec<String>,
+    /// Why the string table couldn't be read to its end
+    pub string_table_error: Option<String>,
+    /// String IDs whose bytes are not valid UTF-8
+    pub invalid_utf8_string_ids: Vec<u32>,
+    /// Entries referring to IDs past the end of another table
+    pub bad_references: Vec<BadReference>,
+    /// Active songs whose audio file is missing from `jp3/music`
+    pub missing_file_song_ids: Vec<u32>,
+    /// Active songs whose audio file no longer matches its recorded hash
+    pub hash_mismatch_song_ids: Vec<u32>,
+}
+
+impl LibraryVerificationReport {
+    /// Whether anything is wrong with the library.
+    pub fn has_issues(&self) -> bool {
+        self.header_error.is_some()
+            || !self.checksum_valid
+            || !self.layout_errors.is_empty()
+            || self.string_table_error.is_some()
+            || !self.invalid_utf8_string_ids.is_empty()
+            || !self.bad_references.is_empty()
+            || !self.missing_file_song_ids.is_empty()
+            || !self.hash_mismatch_song_ids.is_empty()
+    }
+}
+
+/// Result of `repair_library`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairLibraryResult {
+    /// Backup of the damaged files (None if there was no library.bin)
+    pub backup_id: Option<String>,
+    /// Songs carried over from the damaged library.bin
+    pub songs_kept: u32,
+    /// Songs dropped (unreadable, dangling references or missing audio file)
+    pub songs_dropped: u32,
+    /// Audio files in `jp3/music` no song pointed at, added back from their tags
+    pub songs_relinked: u32,
+    /// Playlists rewritten with the new song IDs
+    pub playlists_updated: u32,
+}
+
+/// What `scan_orphaned_files` does with the files it finds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OrphanAction {
+    /// Remove the files from the card
+    Delete,
+    /// Add the audio files back as songs, read from their tags
+    Adopt,
+}
+
+/// A file in `jp3/music` that no active song points at.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanedFile {
+    /// Path relative to `jp3/music`
+    pub path: String,
+    pub size_bytes: u64,
+    /// Whether the file's header is a known audio format
+    pub is_audio: bool,
+}
+
+/// Result of `scan_orphaned_files`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanScanResult {
+    /// Orphaned files found, before any action was taken
+    pub files: Vec<OrphanedFile>,
+    /// Total size of `files`
+    pub wasted_bytes: u64,
+    pub files_deleted: u32,
+    pub bytes_freed: u64,
+    /// Songs added back for orphaned audio files
+    pub songs_adopted: u32,
+    /// Audio files left alone because a song already has the same audio
+    pub duplicates_skipped: u32,
+    /// IDs of the adopted songs
+    pub song_ids: Vec<u32>,
+    /// Files that couldn't be deleted or read
+    pub failed_files: Vec<String>,
+}
+
+/// A backup of library.bin and the playlists in `jp3/backups`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    /// Directory name, passed to `restore_library`
+    pub id: String,
+    /// Creation time (Unix seconds)
+    pub created_at: u64,
+    /// Size of the backed-up library.bin
+    pub library_bytes: u64,
+    /// Number of playlist files in the backup
+    pub playlist_count: u32,
+}
+
+/// Result of one idle-time compaction step.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncrementalCompactResult {
+    /// Deleted songs trimmed off the end of the song table
+    pub songs_trimmed: u32,
+    /// Playlists rewritten to drop the trimmed song IDs
+    pub playlists_updated: u32,
+    /// Bytes written to the card by this step
+    pub bytes_written: u64,
+    /// Write budget left for this library today
+    pub budget_remaining: u64,
+    /// No deleted songs are left at the end of the song table
+    pub done: bool,
+}
+
+/// Idle-time compaction writes charged to a library, stored in
+/// `jp3/metadata/idle_compaction.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdleWriteLog {
+    /// Days since the Unix epoch (UTC) the count is for
+    pub day: u64,
+    /// Bytes idle-time steps wrote that day
+    pub bytes_written: u64,
 }
 
 /// Result returned after deleting an album from the library.
@@ -486,7 +1750,7 @@ pub struct CompactResult {
 pub struct DeleteAlbumResult {
     /// Number of songs deleted
     pub songs_deleted: u32,
-    /// Number of audio files deleted
+    /// Number of audio files moved to the trash
     pub files_deleted: u32,
     /// Name of the deleted album
     pub album_name: String,
@@ -500,7 +1764,7 @@ pub struct DeleteAlbumResult {
 pub struct DeleteArtistResult {
     /// Number of songs deleted
     pub songs_deleted: u32,
-    /// Number of audio files deleted
+    /// Number of audio files moved to the trash
     pub files_deleted: u32,
     /// Number of albums affected (songs deleted from)
     pub albums_affected: u32,
@@ -512,8 +1776,10 @@ pub struct DeleteArtistResult {
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EditAlbumResult {
-    /// Number of songs updated
+    /// Number of songs on the album (all show the new name and year)
     pub songs_updated: u32,
+    /// Song entries rewritten because their artist credit moved
+    pub songs_rewritten: u32,
     /// Whether a new artist was created (if artist name changed)
     pub artist_created: bool,
     /// The old album name
@@ -522,6 +1788,57 @@ pub struct EditAlbumResult {
     pub new_name: String,
 }
 
+/// Result returned after merging albums.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeAlbumsResult {
+    /// Number of songs moved onto the target album
+    pub songs_moved: u32,
+    /// Number of source albums merged (now empty until compaction)
+    pub albums_merged: u32,
+    /// Whether the target took over a source album's cover
+    pub cover_adopted: bool,
+    /// Number of other source covers deleted
+    pub covers_removed: u32,
+}
+
+/// Output format of `export_library_report`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReportFormat {
+    /// Standalone page styled for printing
+    Html,
+    Markdown,
+}
+
+/// Result returned after writing a library report.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryReportResult {
+    pub path: String,
+    pub artist_count: u32,
+    pub album_count: u32,
+    pub song_count: u32,
+}
+
+/// What's on disk for one song, for the technical details panel.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SongFileInfo {
+    pub song_id: u32,
+    /// Path relative to `jp3/music`
+    pub path: String,
+    /// Container detected from the file header, regardless of extension
+    pub container: Option<AudioFormat>,
+    pub stream: AudioStreamInfo,
+    pub size_bytes: u64,
+    /// SHA-256 of the file
+    pub sha256: String,
+    /// Whether the hash matches the one recorded at save time
+    /// (`None` if no hash was recorded)
+    pub hash_matches_library: Option<bool>,
+}
+
 /// Result returned after editing an artist's metadata.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -535,3 +1852,175 @@ pub struct EditArtistResult {
     /// The new artist name
     pub new_name: String,
 }
+
+/// An album whose year was filled in by `backfill_album_years`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumYearUpdate {
+    pub album_id: u32,
+    pub album_name: String,
+    pub artist_name: String,
+    /// The year written to the album entry
+    pub year: u16,
+}
+
+/// An album whose year could not be determined.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumYearUnknown {
+    pub album_id: u32,
+    pub album_name: String,
+    pub artist_name: String,
+}
+
+/// Result returned after backfilling album years from MusicBrainz.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackfillYearsResult {
+    /// Albums that now have a year
+    pub updated: Vec<AlbumYearUpdate>,
+    /// Albums MusicBrainz had no year for
+    pub unknown: Vec<AlbumYearUnknown>,
+    /// Albums answered from the lookup cache without a request
+    pub cached_lookups: u32,
+}
+
+/// Cached MusicBrainz year lookups, stored in `jp3/metadata/year_cache.json`.
+///
+/// Keyed by the album's cover filename hash. A year of 0 records that
+/// MusicBrainz had no year, so the album isn't looked up again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YearLookupCache {
+    pub entries: HashMap<String, u16>,
+}
+
+/// Writing system of a string, by the majority of its letters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Script {
+    Latin,
+    Cyrillic,
+    Greek,
+    Han,
+    /// Hiragana/Katakana (Japanese text mixing kana and kanji counts here)
+    Kana,
+    Hangul,
+    Arabic,
+    Hebrew,
+    Other,
+}
+
+/// Number of library strings written in a script.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptCount {
+    pub script: Script,
+    pub count: u32,
+}
+
+/// Romanized keys for non-Latin names, stored in `jp3/metadata/transliterations.json`.
+///
+/// Keyed by the original string (title, artist or album name). Generated
+/// keys are refreshed on save while `enabled`; user overrides always win,
+/// since automatic romanization can't know how kanji are read.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransliterationIndex {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub generated: HashMap<String, String>,
+    #[serde(default)]
+    pub overrides: HashMap<String, String>,
+}
+
+/// Match confidence per song, stored in `jp3/metadata/song_confidence.json`.
+///
+/// Keyed by the song's relative path, which survives edits and compaction
+/// (song IDs do not). Only songs saved from an automatic match have an entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SongConfidenceIndex {
+    #[serde(default)]
+    pub songs: HashMap<String, MatchConfidence>,
+}
+
+/// SHA-256 of each song's audio file, stored in `jp3/metadata/song_hashes.json`.
+///
+/// Keyed by relative path like `SongConfidenceIndex`. Songs saved before
+/// hashes were recorded have no entry until `backfill_song_hashes` runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SongHashIndex {
+    #[serde(default)]
+    pub songs: HashMap<String, String>,
+}
+
+/// ReplayGain 2.0 values for one song (-18 LUFS reference).
+///
+/// Track values level every song alike; album values keep the loudness
+/// differences within an album, for full-album playback. Peaks are linear
+/// sample peaks (1.0 = full scale).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SongGain {
+    pub track_gain_db: f32,
+    pub track_peak: f32,
+    /// Missing when the rest of the album couldn't be measured
+    #[serde(default)]
+    pub album_gain_db: Option<f32>,
+    #[serde(default)]
+    pub album_peak: Option<f32>,
+}
+
+/// ReplayGain per song, stored in `jp3/metadata/replay_gain.json`.
+///
+/// Keyed by relative path like `SongHashIndex`; read by the firmware.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayGainIndex {
+    #[serde(default)]
+    pub songs: HashMap<String, SongGain>,
+}
+
+/// Result of `analyze_replay_gain`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayGainResult {
+    /// Albums whose tracks were measured
+    pub albums_analyzed: u32,
+    /// Songs given new track and album gain
+    pub songs_analyzed: u32,
+    /// Songs skipped because they already had album gain
+    pub up_to_date: u32,
+    /// Songs that couldn't be measured
+    pub failed: u32,
+}
+
+/// Result of `clean_library_tags`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCleanupResult {
+    /// Songs whose tags were rewritten
+    pub songs_cleaned: u32,
+    /// Songs that already had a clean tag
+    pub already_clean: u32,
+    /// Songs skipped because they aren't MP3s
+    pub skipped: u32,
+    /// Songs whose file couldn't be rewritten
+    pub failed: u32,
+    /// Total size of the rewritten files before cleanup
+    pub bytes_before: u64,
+    /// Total size of the rewritten files after cleanup
+    pub bytes_after: u64,
+}
+
+/// Result returned after toggling transliteration.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransliterationResult {
+    pub enabled: bool,
+    /// Names that now have a generated romanized key
+    pub keys_generated: u32,
+}
@@ -1,11 +1,17 @@
 //! Data models for the JP3 library system.
 
 mod audio;
+mod fingerprint;
+mod import;
 mod library;
 mod playlist;
+mod settings;
 pub mod cover_art; //Make public as I use a type from here
 
 pub use audio::*;
+pub use fingerprint::*;
+pub use import::*;
 pub use library::*;
 pub use playlist::*;
+pub use settings::*;
 pub use cover_art::*;
@@ -5,25 +5,43 @@
 //!
 //! Binary format (per playlist file):
 //! - Header: magic (4 bytes) + version (4 bytes) + song_count (4 bytes) + name_length (2 bytes)
+//!   + flags (1 byte, v2) + reserved (1 byte, v2)
 //! - Name: UTF-8 string (name_length bytes)
-//! - Song IDs: array of u32 song IDs (song_count * 4 bytes)
+//! - Entries: song_count entries of u32 song ID + u32 added_at (v2; song ID only in v1)
+//!
+//! v1 files are migrated in memory when read (added_at 0, manual order) and
+//! rewritten as v2 on their next write. `write_playlist_file_v1` still writes
+//! the old layout for device firmware that only reads v1.
+
+use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
 // Binary format constants
 pub const PLAYLIST_MAGIC: &[u8; 4] = b"PLY1";
-pub const PLAYLIST_VERSION: u32 = 1;
-pub const PLAYLIST_HEADER_SIZE: usize = 14; // 4 + 4 + 4 + 2
+/// Version 2 adds header flags and a per-entry added_at timestamp.
+pub const PLAYLIST_VERSION: u32 = 2;
+pub const PLAYLIST_HEADER_SIZE: usize = 16; // 4 + 4 + 4 + 2 + 1 + 1
+/// Header size of v1 files, which have no flags.
+pub const PLAYLIST_HEADER_SIZE_V1: usize = 14;
+
+/// Playlist header flag bits.
+pub mod playlist_flags {
+    /// Songs are in an order the user chose (not generated, e.g. import order)
+    pub const MANUAL_ORDER: u8 = 0x01;
+}
 
 /// Playlist header structure for binary serialization.
 ///
-/// Binary layout (14 bytes):
+/// Binary layout (16 bytes; 14 in v1):
 /// ```text
 /// Offset  Size  Field
 /// 0x00    4     magic ("PLY1")
 /// 0x04    4     version
 /// 0x08    4     song_count
 /// 0x0C    2     name_length
+/// 0x0E    1     flags (v2+, see `playlist_flags`)
+/// 0x0F    1     reserved (v2+)
 /// ```
 #[derive(Debug, Clone)]
 pub struct PlaylistHeader {
@@ -31,32 +49,75 @@ pub struct PlaylistHeader {
     pub version: u32,
     pub song_count: u32,
     pub name_length: u16,
+    pub flags: u8,
 }
 
 impl PlaylistHeader {
     /// Create a new playlist header.
-    pub fn new(song_count: u32, name_length: u16) -> Self {
+    pub fn new(song_count: u32, name_length: u16, manual_order: bool) -> Self {
         Self {
             magic: *PLAYLIST_MAGIC,
             version: PLAYLIST_VERSION,
             song_count,
             name_length,
+            flags: if manual_order { playlist_flags::MANUAL_ORDER } else { 0 },
+        }
+    }
+
+    /// Create a v1 header (device export).
+    pub fn new_v1(song_count: u32, name_length: u16) -> Self {
+        Self {
+            magic: *PLAYLIST_MAGIC,
+            version: 1,
+            song_count,
+            name_length,
+            flags: 0,
+        }
+    }
+
+    /// Size of this header in bytes.
+    pub fn size(&self) -> usize {
+        if self.version >= 2 {
+            PLAYLIST_HEADER_SIZE
+        } else {
+            PLAYLIST_HEADER_SIZE_V1
         }
     }
 
+    /// Size of each song entry in bytes.
+    pub fn entry_size(&self) -> usize {
+        if self.version >= 2 {
+            PlaylistEntry::SIZE
+        } else {
+            4
+        }
+    }
+
+    /// Whether the songs are in a user-chosen order.
+    pub fn is_manual_order(&self) -> bool {
+        self.flags & playlist_flags::MANUAL_ORDER != 0
+    }
+
     /// Serialize header to bytes (little-endian).
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(PLAYLIST_HEADER_SIZE);
+        let mut bytes = Vec::with_capacity(self.size());
         bytes.extend_from_slice(&self.magic);
         bytes.extend_from_slice(&self.version.to_le_bytes());
         bytes.extend_from_slice(&self.song_count.to_le_bytes());
         bytes.extend_from_slice(&self.name_length.to_le_bytes());
+        if self.version >= 2 {
+            bytes.push(self.flags);
+            bytes.push(0); // reserved
+        }
         bytes
     }
 
     /// Parse header from bytes.
+    ///
+    /// v1 headers have no flags; they are read as manually ordered, since
+    /// every v1 playlist was built by hand.
     pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
-        if bytes.len() < PLAYLIST_HEADER_SIZE {
+        if bytes.len() < PLAYLIST_HEADER_SIZE_V1 {
             return None;
         }
 
@@ -65,15 +126,46 @@ impl PlaylistHeader {
             return None;
         }
 
+        let version = u32::from_le_bytes(bytes[4..8].try_into().ok()?);
+        let flags = if version >= 2 {
+            *bytes.get(14)?
+        } else {
+            playlist_flags::MANUAL_ORDER
+        };
+
         Some(Self {
             magic,
-            version: u32::from_le_bytes(bytes[4..8].try_into().ok()?),
+            version,
             song_count: u32::from_le_bytes(bytes[8..12].try_into().ok()?),
             name_length: u16::from_le_bytes(bytes[12..14].try_into().ok()?),
+            flags,
         })
     }
 }
 
+/// One song in a playlist.
+///
+/// Binary layout (8 bytes, v2+):
+/// ```text
+/// Offset  Size  Field
+/// 0x00    4     song_id
+/// 0x04    4     added_at (unix seconds, 0 = unknown)
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaylistEntry {
+    pub song_id: u32,
+    /// When the song was added (unix seconds, 0 for entries migrated from v1)
+    pub added_at: u32,
+}
+
+impl PlaylistEntry {
+    pub const SIZE: usize = 8;
+
+    pub fn new(song_id: u32, added_at: u32) -> Self {
+        Self { song_id, added_at }
+    }
+}
+
 /// Parsed playlist data for frontend display.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -86,6 +178,21 @@ pub struct ParsedPlaylist {
     pub song_count: u32,
     /// List of song IDs in playlist order
     pub song_ids: Vec<u32>,
+    /// When each song was added (unix seconds, parallel to `song_ids`; 0 = unknown)
+    pub added_at: Vec<u32>,
+    /// Whether the order was chosen by the user rather than generated
+    pub manual_order: bool,
+}
+
+impl ParsedPlaylist {
+    /// Songs paired with their added timestamps, in playlist order.
+    pub fn entries(&self) -> Vec<PlaylistEntry> {
+        self.song_ids
+            .iter()
+            .zip(&self.added_at)
+            .map(|(&song_id, &added_at)| PlaylistEntry::new(song_id, added_at))
+            .collect()
+    }
 }
 
 /// Input for creating a playlist with songs.
@@ -139,6 +246,39 @@ pub struct DeletePlaylistResult {
     pub deleted: bool,
 }
 
+/// Problems found in one playlist file by `validate_playlists`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistValidationReport {
+    /// Playlist ID (derived from filename)
+    pub playlist_id: u32,
+    /// Playlist name (None if the file couldn't be read)
+    pub name: Option<String>,
+    /// Song IDs past the end of the library's song table
+    pub out_of_range_ids: Vec<u32>,
+    /// Song IDs of soft-deleted songs
+    pub deleted_ids: Vec<u32>,
+    /// Song IDs listed more than once (each reported once)
+    pub duplicate_ids: Vec<u32>,
+    /// File ended before the song count in its header
+    pub truncated: bool,
+    /// Why the file couldn't be read at all (damaged header or name)
+    pub error: Option<String>,
+    /// Whether the playlist was rewritten without its dead references
+    pub repaired: bool,
+}
+
+impl PlaylistValidationReport {
+    /// Whether anything is wrong with the playlist.
+    pub fn has_issues(&self) -> bool {
+        !self.out_of_range_ids.is_empty()
+            || !self.deleted_ids.is_empty()
+            || !self.duplicate_ids.is_empty()
+            || self.truncated
+            || self.error.is_some()
+    }
+}
+
 /// Summary of all playlists for the View page.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -150,3 +290,70 @@ pub struct PlaylistSummary {
     /// Number of songs
     pub song_count: u32,
 }
+
+/// Dynamic playlists regenerated as ordinary playlist files on each sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AutoPlaylistKind {
+    /// Newest songs first
+    RecentlyAdded,
+    /// Songs flagged as favorites
+    Favorites,
+    /// Highest play count first
+    MostPlayed,
+}
+
+impl AutoPlaylistKind {
+    pub const ALL: [AutoPlaylistKind; 3] = [
+        AutoPlaylistKind::RecentlyAdded,
+        AutoPlaylistKind::Favorites,
+        AutoPlaylistKind::MostPlayed,
+    ];
+
+    /// Name of the generated playlist.
+    pub fn playlist_name(self) -> &'static str {
+        match self {
+            AutoPlaylistKind::RecentlyAdded => "Recently Added",
+            AutoPlaylistKind::Favorites => "Favorites",
+            AutoPlaylistKind::MostPlayed => "Most Played",
+        }
+    }
+}
+
+/// Which auto-playlists are exported on sync.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoPlaylistSettings {
+    /// Songs in "Recently Added" (0 = not exported)
+    #[serde(default)]
+    pub recently_added_count: u32,
+    /// Export a "Favorites" playlist
+    #[serde(default)]
+    pub favorites: bool,
+    /// Songs in "Most Played" (0 = not exported)
+    #[serde(default)]
+    pub most_played_count: u32,
+}
+
+/// Auto-playlist settings and the playlist file each kind was last written to.
+///
+/// Persisted in `jp3/metadata/auto_playlists.json` so it travels with the
+/// SD card.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoPlaylistIndex {
+    #[serde(default)]
+    pub settings: AutoPlaylistSettings,
+    #[serde(default)]
+    pub playlist_ids: HashMap<AutoPlaylistKind, u32>,
+}
+
+/// Result of regenerating the auto-playlists.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncAutoPlaylistsResult {
+    /// Playlists written this sync
+    pub written: Vec<PlaylistSummary>,
+    /// Playlists deleted because their kind was turned off
+    pub removed: u32,
+}
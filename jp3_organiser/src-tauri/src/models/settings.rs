@@ -0,0 +1,158 @@
+//! Application settings models.
+//!
+//! App-level settings are persisted in `config.json` via tauri-plugin-store
+//! (see `commands::config`).
+
+use serde::{Deserialize, Serialize};
+
+/// Opt-in settings for submitting fingerprints back to AcoustID.
+///
+/// Submission requires the user's own AcoustID user API key
+/// (from https://acoustid.org/api-key), separate from the app key.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcoustIdSubmissionSettings {
+    /// Whether the user has opted in to submissions
+    pub enabled: bool,
+    /// The user's AcoustID user API key
+    pub user_key: Option<String>,
+}
+
+/// Default JPEG quality for re-encoded covers
+pub const DEFAULT_COVER_JPEG_QUALITY: u8 = 85;
+
+/// Default longest cover edge in pixels (providers usually serve 500px)
+pub const DEFAULT_COVER_MAX_EDGE: u32 = 600;
+
+/// How album and artist covers are stored in `jp3/assets`.
+///
+/// Covers larger than `max_edge` are downscaled and re-encoded at
+/// `jpeg_quality` when saved; smaller covers are written unchanged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverImageSettings {
+    /// JPEG quality (1-100) for re-encoded covers
+    pub jpeg_quality: u8,
+    /// Longest edge in pixels
+    pub max_edge: u32,
+}
+
+impl Default for CoverImageSettings {
+    fn default() -> Self {
+        Self {
+            jpeg_quality: DEFAULT_COVER_JPEG_QUALITY,
+            max_edge: DEFAULT_COVER_MAX_EDGE,
+        }
+    }
+}
+
+/// Limits on file copying so big imports leave the disk usable.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IoThrottleSettings {
+    /// Throughput cap for copies in MB/s (None = unlimited)
+    pub max_mb_per_sec: Option<f32>,
+    /// Files read or copied at once (None = up to `MAX_IO_WORKERS`)
+    pub max_workers: Option<u32>,
+}
+
+/// Seconds a save hook may run before it is killed, unless configured
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u32 = 30;
+
+/// An external program run as a save hook.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookCommand {
+    /// Program to run (a path, or a name looked up on PATH)
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// Programs run around every save to the library (see `save_hook_service`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveHookSettings {
+    /// Run before anything is written; may rewrite metadata and file names
+    pub pre_save: Option<HookCommand>,
+    /// Run after the save, with the songs it added
+    pub post_save: Option<HookCommand>,
+    /// Seconds before a hook is killed (None = `DEFAULT_HOOK_TIMEOUT_SECS`)
+    pub timeout_secs: Option<u32>,
+}
+
+/// Default LAME VBR quality for device copies (V2, around 190 kbps)
+pub const DEFAULT_MP3_VBR_QUALITY: u8 = 2;
+
+/// Highest (smallest) LAME VBR quality
+pub const MAX_MP3_VBR_QUALITY: u8 = 9;
+
+/// How audio is re-encoded for a device on export.
+///
+/// The library keeps its originals; anything that isn't MP3 is transcoded
+/// to MP3 at `mp3_vbr_quality` (LAME `-V`, 0 = best, 9 = smallest).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeProfile {
+    pub mp3_vbr_quality: u8,
+}
+
+impl EncodeProfile {
+    /// Names this profile's transcodes in the cache.
+    pub fn cache_key(&self) -> String {
+        format!("mp3v{}", self.mp3_vbr_quality)
+    }
+}
+
+impl Default for EncodeProfile {
+    fn default() -> Self {
+        Self {
+            mp3_vbr_quality: DEFAULT_MP3_VBR_QUALITY,
+        }
+    }
+}
+
+/// A named SD card the app has been told about.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnownDevice {
+    /// ID from the card's `jp3/metadata/device.json`, or a volume serial/UUID
+    pub id: String,
+    /// User-chosen name (e.g. "Car SD")
+    pub name: String,
+    /// Library path the card was last registered or identified at
+    pub last_path: Option<String>,
+    /// How exports to this card are re-encoded (None = copy originals)
+    #[serde(default)]
+    pub encode_profile: Option<EncodeProfile>,
+}
+
+/// Something about the library the card's firmware can't handle.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompatibilityProblem {
+    /// What would break
+    pub problem: String,
+    /// What to do instead
+    pub fix: String,
+}
+
+/// Result of `check_device_compatibility`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceCompatibilityReport {
+    /// Firmware version declared on the card
+    pub firmware_version: Option<String>,
+    /// Empty if the library works on the card (or the firmware declared no limits)
+    pub problems: Vec<CompatibilityProblem>,
+}
+
+/// Result of submitting a fingerprint to AcoustID.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcoustIdSubmissionResult {
+    /// Submission ID assigned by AcoustID (used to check import status)
+    pub submission_id: Option<u64>,
+    /// Submission status reported by AcoustID (usually "pending")
+    pub status: String,
+}
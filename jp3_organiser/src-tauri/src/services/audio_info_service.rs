@@ -0,0 +1,244 @@
+//! Technical details of audio files: codec, bitrate, sample rate, channels.
+//!
+//! Used by the song details panel and when chasing playback problems
+//! reported from the device, so everything is read from the file itself
+//! rather than trusted from the library.
+//!
+//! # Sources
+//! - MP3: the first MPEG frame header after any ID3v2 tag, plus the
+//!   Xing/Info or VBRI header for VBR frame counts (no mp3 decoder needed)
+//! - FLAC/WAV: symphonia's probe and the track's codec parameters
+//! - Everything else: codec only, inferred from the container
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use symphonia::core::codecs::CODEC_TYPE_NULL;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::models::{AudioFormat, AudioStreamInfo};
+use crate::services::format_sniff_service::id3v2_tag_len;
+
+/// Bytes searched for the first MPEG frame after the ID3 tag
+const MPEG_SEARCH_LEN: usize = 64 * 1024;
+
+/// Layer III bitrates in kbps by bitrate index, MPEG-1 then MPEG-2/2.5
+const MPEG1_BITRATES: [u32; 15] = [
+    0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+];
+const MPEG2_BITRATES: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+/// MPEG-1 sample rates; MPEG-2 halves them and MPEG-2.5 quarters them
+const MPEG1_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+/// A decoded MPEG Layer III frame header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MpegFrameHeader {
+    is_mpeg1: bool,
+    bitrate_kbps: u32,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl MpegFrameHeader {
+    /// Decode four header bytes, rejecting anything that isn't Layer III.
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 4 || bytes[0] != 0xFF || bytes[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+        let version = (bytes[1] >> 3) & 0x03; // 00 = 2.5, 01 = reserved, 10 = 2, 11 = 1
+        let layer = (bytes[1] >> 1) & 0x03; // 01 = Layer III
+        let bitrate_index = (bytes[2] >> 4) as usize;
+        let rate_index = ((bytes[2] >> 2) & 0x03) as usize;
+        let reserved = version == 0x01 || bitrate_index == 15 || rate_index == 3;
+        if reserved || layer != 0x01 || bitrate_index == 0 {
+            return None;
+        }
+
+        let is_mpeg1 = version == 0x03;
+        let bitrates = if is_mpeg1 {
+            &MPEG1_BITRATES
+        } else {
+            &MPEG2_BITRATES
+        };
+        let rate_divisor = match version {
+            0x03 => 1,
+            0x02 => 2,
+            _ => 4,
+        };
+        Some(Self {
+            is_mpeg1,
+            bitrate_kbps: bitrates[bitrate_index],
+            sample_rate: MPEG1_SAMPLE_RATES[rate_index] / rate_divisor,
+            channels: if bytes[3] >> 6 == 0x03 { 1 } else { 2 },
+        })
+    }
+
+    fn samples_per_frame(&self) -> u64 {
+        if self.is_mpeg1 {
+            1152
+        } else {
+            576
+        }
+    }
+
+    /// Offset of a Xing/Info header from the frame start (after side info).
+    fn xing_offset(&self) -> usize {
+        4 + match (self.is_mpeg1, self.channels) {
+            (true, 1) => 17,
+            (true, _) => 32,
+            (false, 1) => 9,
+            (false, _) => 17,
+        }
+    }
+}
+
+/// Read technical details from an audio file.
+///
+/// `container` is the format sniffed from the header; when it's `None` the
+/// file is still probed in case symphonia recognises it.
+pub fn read_stream_info(
+    path: &Path,
+    container: Option<AudioFormat>,
+) -> Result<AudioStreamInfo, String> {
+    let size_bytes = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    match container {
+        Some(AudioFormat::Mp3) => read_mp3_info(path, size_bytes),
+        Some(AudioFormat::Aac) => Ok(codec_only("aac")),
+        Some(AudioFormat::Ogg) => Ok(codec_only("vorbis")),
+        Some(AudioFormat::Opus) => Ok(codec_only("opus")),
+        // MP4 may hold AAC or ALAC; we can't tell without a demuxer
+        Some(AudioFormat::Mp4) => Ok(AudioStreamInfo::default()),
+        Some(AudioFormat::Flac) | Some(AudioFormat::Wav) | None => {
+            Ok(probe_stream_info(path, size_bytes).unwrap_or_default())
+        }
+    }
+}
+
+fn codec_only(codec: &str) -> AudioStreamInfo {
+    AudioStreamInfo {
+        codec: Some(codec.to_string()),
+        ..Default::default()
+    }
+}
+
+/// Average bitrate from the file size, for formats without a fixed rate.
+fn average_bitrate_kbps(audio_bytes: u64, duration_secs: f64) -> Option<u32> {
+    (duration_secs > 0.0)
+        .then(|| (audio_bytes as f64 * 8.0 / duration_secs / 1000.0).round() as u32)
+}
+
+fn read_mp3_info(path: &Path, size_bytes: u64) -> Result<AudioStreamInfo, String> {
+    let mut file =
+        File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut head = [0u8; 10];
+    let head_len = file
+        .read(&mut head)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let audio_start = id3v2_tag_len(&head[..head_len]).unwrap_or(0);
+
+    let mut buffer = Vec::with_capacity(MPEG_SEARCH_LEN);
+    file.seek(SeekFrom::Start(audio_start))
+        .and_then(|_| {
+            file.by_ref()
+                .take(MPEG_SEARCH_LEN as u64)
+                .read_to_end(&mut buffer)
+        })
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    // Skip junk/padding before the first frame
+    let Some((offset, header)) = (0..buffer.len())
+        .find_map(|i| MpegFrameHeader::parse(&buffer[i..]).map(|header| (i, header)))
+    else {
+        return Ok(codec_only("mp3"));
+    };
+    let frame = &buffer[offset..];
+    let audio_bytes = size_bytes.saturating_sub(audio_start + offset as u64);
+
+    // A Xing header means VBR; LAME writes the same layout as "Info" for CBR
+    let xing = frame
+        .get(header.xing_offset()..)
+        .filter(|rest| rest.len() >= 12);
+    let (variable_bitrate, frame_count) = match xing {
+        Some(rest) if rest.starts_with(b"Xing") || rest.starts_with(b"Info") => {
+            let has_frames = rest[7] & 0x01 != 0;
+            let frames = u32::from_be_bytes([rest[8], rest[9], rest[10], rest[11]]);
+            (rest.starts_with(b"Xing"), has_frames.then_some(frames))
+        }
+        _ => match frame.get(36..50).filter(|rest| rest.starts_with(b"VBRI")) {
+            Some(rest) => (
+                true,
+                Some(u32::from_be_bytes([rest[10], rest[11], rest[12], rest[13]])),
+            ),
+            None => (false, None),
+        },
+    };
+
+    let duration_secs = match frame_count {
+        Some(frames) => {
+            frames as f64 * header.samples_per_frame() as f64 / header.sample_rate as f64
+        }
+        None => audio_bytes as f64 * 8.0 / (header.bitrate_kbps as f64 * 1000.0),
+    };
+    let bitrate_kbps = if variable_bitrate {
+        average_bitrate_kbps(audio_bytes, duration_secs)
+    } else {
+        Some(header.bitrate_kbps)
+    };
+
+    Ok(AudioStreamInfo {
+        codec: Some("mp3".to_string()),
+        bitrate_kbps,
+        variable_bitrate,
+        sample_rate: Some(header.sample_rate),
+        channels: Some(header.channels),
+        bits_per_sample: None,
+        duration_secs: Some(duration_secs.round() as u32),
+    })
+}
+
+/// Codec parameters from symphonia, for the formats in our feature set.
+fn probe_stream_info(path: &Path, size_bytes: u64) -> Option<AudioStreamInfo> {
+    let file = File::open(path).ok()?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+    let track = probed
+        .format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)?;
+    let params = &track.codec_params;
+
+    let duration_secs = match (params.n_frames, params.sample_rate) {
+        (Some(frames), Some(rate)) if rate > 0 => Some(frames as f64 / rate as f64),
+        _ => None,
+    };
+    Some(AudioStreamInfo {
+        codec: symphonia::default::get_codecs()
+            .get_codec(params.codec)
+            .map(|descriptor| descriptor.short_name.to_string()),
+        bitrate_kbps: duration_secs.and_then(|secs| average_bitrate_kbps(size_bytes, secs)),
+        variable_bitrate: false,
+        sample_rate: params.sample_rate,
+        channels: params.channels.map(|channels| channels.count() as u16),
+        bits_per_sample: params.bits_per_sample,
+        duration_secs: duration_secs.map(|secs| secs.round() as u32),
+    })
+}
@@ -0,0 +1,93 @@
+//! Auto-playlists exported to the SD card.
+//!
+//! The device only understands playlist files, so dynamic lists ("Recently
+//! Added", "Favorites", "Most Played") are materialized into ordinary
+//! `jp3/playlists/*.bin` files whenever the library is synced
+//! (`sync_auto_playlists`) or exported (`export_selection`). Which
+//! lists are exported, and the playlist ID each one was written to, are kept
+//! in `jp3/metadata/auto_playlists.json` so every sync overwrites the same
+//! files instead of piling up copies.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::{AutoPlaylistIndex, AutoPlaylistKind, AutoPlaylistSettings, ParsedLibrary};
+
+const AUTO_PLAYLISTS_FILE: &str = "auto_playlists.json";
+
+fn index_path(base_path: &Path) -> PathBuf {
+    base_path
+        .join("jp3")
+        .join("metadata")
+        .join(AUTO_PLAYLISTS_FILE)
+}
+
+/// Load the auto-playlist index, or an empty index if none exists.
+pub fn load_auto_playlists(base_path: &Path) -> Result<AutoPlaylistIndex, String> {
+    let path = index_path(base_path);
+    if !path.exists() {
+        return Ok(AutoPlaylistIndex::default());
+    }
+    let data = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read auto-playlists: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse auto-playlists: {}", e))
+}
+
+/// Persist the auto-playlist index.
+pub fn save_auto_playlists(base_path: &Path, index: &AutoPlaylistIndex) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(index)
+        .map_err(|e| format!("Failed to serialize auto-playlists: {}", e))?;
+    fs::write(index_path(base_path), data)
+        .map_err(|e| format!("Failed to write auto-playlists: {}", e))
+}
+
+/// Song IDs for an auto-playlist, or None if the settings turn it off.
+pub fn auto_playlist_song_ids(
+    library: &ParsedLibrary,
+    settings: &AutoPlaylistSettings,
+    kind: AutoPlaylistKind,
+) -> Option<Vec<u32>> {
+    match kind {
+        AutoPlaylistKind::RecentlyAdded => {
+            if settings.recently_added_count == 0 {
+                return None;
+            }
+            let mut songs: Vec<_> = library.songs.iter().collect();
+            songs.sort_by(|a, b| {
+                b.date_added
+                    .cmp(&a.date_added)
+                    .then_with(|| b.id.cmp(&a.id))
+            });
+            Some(
+                songs
+                    .into_iter()
+                    .take(settings.recently_added_count as usize)
+                    .map(|s| s.id)
+                    .collect(),
+            )
+        }
+        AutoPlaylistKind::Favorites => settings.favorites.then(|| {
+            library
+                .songs
+                .iter()
+                .filter(|s| s.is_favorite)
+                .map(|s| s.id)
+                .collect()
+        }),
+        AutoPlaylistKind::MostPlayed => {
+            if settings.most_played_count == 0 {
+                return None;
+            }
+            // Songs never played aren't "most played", however short the list
+            let mut songs: Vec<_> = library.songs.iter().filter(|s| s.play_count > 0).collect();
+            songs.sort_by(|a, b| b.play_count.cmp(&a.play_count).then_with(|| a.id.cmp(&b.id)));
+            Some(
+                songs
+                    .into_iter()
+                    .take(settings.most_played_count as usize)
+                    .map(|s| s.id)
+                    .collect(),
+            )
+        }
+    }
+}
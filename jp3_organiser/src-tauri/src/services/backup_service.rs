@@ -0,0 +1,220 @@
+//! Library metadata backups.
+//!
+//! A backup is a copy of library.bin and the playlist files, kept on the
+//! card next to the library:
+//!
+//! ```text
+//! jp3/backups/{id}/library.bin
+//! jp3/backups/{id}/playlists/{playlist_id}.bin
+//! ```
+//!
+//! The ID is the creation time in Unix seconds (with a `-n` suffix if two
+//! backups land in the same second). Audio files are never backed up; a
+//! restore only brings back songs whose files are still in `jp3/music`.
+//!
+//! Each new backup prunes the oldest ones beyond the library's
+//! `backup_retention`, so compactions and restores don't fill the card.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::models::BackupInfo;
+use crate::services::library_settings_service::load_library_settings;
+
+const BACKUPS_DIR: &str = "backups";
+const LIBRARY_BIN: &str = "library.bin";
+const PLAYLISTS_DIR: &str = "playlists";
+
+fn backups_dir(base_path: &Path) -> PathBuf {
+    base_path.join("jp3").join(BACKUPS_DIR)
+}
+
+fn library_bin_path(base_path: &Path) -> PathBuf {
+    base_path.join("jp3").join("metadata").join(LIBRARY_BIN)
+}
+
+fn playlists_dir(base_path: &Path) -> PathBuf {
+    base_path.join("jp3").join(PLAYLISTS_DIR)
+}
+
+/// Playlist files (`*.bin`) in a directory; empty if it doesn't exist.
+fn playlist_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "bin"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Copy every playlist file from one directory into another.
+fn copy_playlists(from: &Path, to: &Path) -> Result<u32, String> {
+    fs::create_dir_all(to).map_err(|e| format!("Failed to create {}: {}", to.display(), e))?;
+    let files = playlist_files(from)?;
+    for file in &files {
+        let name = file.file_name().unwrap_or_default();
+        fs::copy(file, to.join(name))
+            .map_err(|e| format!("Failed to copy {}: {}", file.display(), e))?;
+    }
+    Ok(files.len() as u32)
+}
+
+/// (seconds, suffix) of a backup ID, in creation order.
+fn backup_order(id: &str) -> (u64, u32) {
+    let (secs, suffix) = id.split_once('-').unwrap_or((id, "0"));
+    (secs.parse().unwrap_or(0), suffix.parse().unwrap_or(0))
+}
+
+fn backup_info(id: &str, dir: &Path) -> Result<BackupInfo, String> {
+    let created_at = id
+        .split('-')
+        .next()
+        .and_then(|secs| secs.parse().ok())
+        .ok_or_else(|| format!("Invalid backup ID: {}", id))?;
+    let library_bytes = fs::metadata(dir.join(LIBRARY_BIN))
+        .map_err(|e| format!("Backup {} has no library.bin: {}", id, e))?
+        .len();
+    let playlist_count = playlist_files(&dir.join(PLAYLISTS_DIR))?.len() as u32;
+    Ok(BackupInfo {
+        id: id.to_string(),
+        created_at,
+        library_bytes,
+        playlist_count,
+    })
+}
+
+/// Directory of an existing backup. Rejects IDs that aren't a plain name.
+pub fn backup_dir(base_path: &Path, backup_id: &str) -> Result<PathBuf, String> {
+    let is_plain = !backup_id.is_empty()
+        && backup_id.chars().all(|c| c.is_ascii_digit() || c == '-');
+    let dir = backups_dir(base_path).join(backup_id);
+    if !is_plain || !dir.is_dir() {
+        return Err(format!("Backup not found: {}", backup_id));
+    }
+    Ok(dir)
+}
+
+/// Copy library.bin and the playlists into a new backup, then prune old
+/// backups (see `prune_backups`).
+pub fn create_backup(base_path: &Path) -> Result<BackupInfo, String> {
+    create_backup_keeping(base_path, &[])
+}
+
+/// `create_backup`, never pruning the backups in `keep` (e.g. one about to
+/// be restored).
+pub fn create_backup_keeping(base_path: &Path, keep: &[&str]) -> Result<BackupInfo, String> {
+    let library_bin = library_bin_path(base_path);
+    if !library_bin.exists() {
+        return Err("Library not found".to_string());
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let root = backups_dir(base_path);
+    // Past every backup from this second, even if earlier ones were pruned,
+    // so the new one always sorts newest
+    let latest = list_backups(base_path)?
+        .iter()
+        .map(|b| backup_order(&b.id))
+        .filter(|&(secs, _)| secs == now)
+        .max();
+    let id = match latest {
+        Some((_, suffix)) => format!("{}-{}", now, suffix + 1),
+        None => now.to_string(),
+    };
+
+    let dir = root.join(&id);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    fs::copy(&library_bin, dir.join(LIBRARY_BIN))
+        .map_err(|e| format!("Failed to back up library.bin: {}", e))?;
+    copy_playlists(&playlists_dir(base_path), &dir.join(PLAYLISTS_DIR))?;
+    let info = backup_info(&id, &dir)?;
+
+    // The backup is already safe; a failed prune only leaves extra backups
+    match load_library_settings(base_path) {
+        Ok(settings) => {
+            if let Some(retention) = settings.backup_retention {
+                if let Err(e) = prune_backups(base_path, retention, keep) {
+                    log::warn!("Failed to prune old backups: {}", e);
+                }
+            }
+        }
+        Err(e) => log::warn!("Failed to prune old backups: {}", e),
+    }
+
+    Ok(info)
+}
+
+/// Delete all but the newest `retention` backups (at least one is kept),
+/// except those in `keep`. Returns the IDs deleted.
+pub fn prune_backups(
+    base_path: &Path,
+    retention: u32,
+    keep: &[&str],
+) -> Result<Vec<String>, String> {
+    let mut deleted = Vec::new();
+    for backup in list_backups(base_path)?.into_iter().skip(retention.max(1) as usize) {
+        if keep.contains(&backup.id.as_str()) {
+            continue;
+        }
+        delete_backup(base_path, &backup.id)?;
+        deleted.push(backup.id);
+    }
+    Ok(deleted)
+}
+
+/// Delete a backup.
+pub fn delete_backup(base_path: &Path, backup_id: &str) -> Result<(), String> {
+    let dir = backup_dir(base_path, backup_id)?;
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to delete backup {}: {}", backup_id, e))
+}
+
+/// All backups, newest first. Directories that aren't backups are skipped.
+pub fn list_backups(base_path: &Path) -> Result<Vec<BackupInfo>, String> {
+    let root = backups_dir(base_path);
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups: Vec<BackupInfo> = fs::read_dir(&root)
+        .map_err(|e| format!("Failed to read backups directory: {}", e))?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let id = entry.file_name().to_string_lossy().to_string();
+            backup_info(&id, &entry.path()).ok()
+        })
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(backup_order(&b.id)));
+    Ok(backups)
+}
+
+/// Replace library.bin and the playlists with a backup's copies.
+///
+/// library.bin is swapped in with a rename. Playlists not in the backup are
+/// deleted.
+pub fn restore_backup(base_path: &Path, backup_id: &str) -> Result<BackupInfo, String> {
+    let dir = backup_dir(base_path, backup_id)?;
+    let info = backup_info(backup_id, &dir)?;
+
+    let library_bin = library_bin_path(base_path);
+    let temp_path = library_bin.with_extension("bin.tmp");
+    fs::copy(dir.join(LIBRARY_BIN), &temp_path)
+        .map_err(|e| format!("Failed to copy library.bin from backup: {}", e))?;
+    fs::rename(&temp_path, &library_bin)
+        .map_err(|e| format!("Failed to replace library.bin: {}", e))?;
+
+    let playlists = playlists_dir(base_path);
+    for file in playlist_files(&playlists)? {
+        fs::remove_file(&file)
+            .map_err(|e| format!("Failed to remove {}: {}", file.display(), e))?;
+    }
+    copy_playlists(&dir.join(PLAYLISTS_DIR), &playlists)?;
+
+    Ok(info)
+}
@@ -0,0 +1,185 @@
+//! Support for long-running library compactions.
+//!
+//! Compacting a large library re-interns every string it keeps. Songs are
+//! processed in chunks: each chunk's strings are deduplicated on a worker
+//! thread (`intern_chunk`), then merged into the new string table in chunk
+//! order (`ChunkStrings::merge_into`), so string IDs come out exactly as a
+//! single-threaded rebuild would assign them.
+//!
+//! `CompactionControl` lives in managed state so the UI can cancel a running
+//! compaction. Cancelling is only honoured before the new library.bin is
+//! renamed into place; after that the compaction finishes.
+//!
+//! Between full compactions the UI can run small idle-time steps that only
+//! trim deleted songs off the end of the song table. The bytes those steps
+//! write are capped per library per day. The count is kept on the card in
+//! `jp3/metadata/idle_compaction.json`, next to the write markers, so
+//! restarting the app doesn't hand out a fresh budget.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::models::{IdleWriteLog, StringTable};
+
+/// Songs per chunk when compacting
+pub const COMPACT_CHUNK_SIZE: usize = 2048;
+
+/// Most deleted songs one idle-time step trims
+pub const IDLE_STEP_MAX_SONGS: usize = 256;
+
+/// Bytes idle-time steps may write to one library per day (UTC)
+pub const IDLE_WRITE_BUDGET: u64 = 512 * 1024;
+
+const IDLE_WRITE_LOG_FILE: &str = "idle_compaction.json";
+
+/// Prefix of the error returned when a compaction was cancelled. The
+/// frontend matches on this to show a notice rather than a failure.
+pub const COMPACTION_CANCELLED: &str = "CompactionCancelled";
+
+/// Running/cancel state of the (single) compaction.
+#[derive(Default)]
+pub struct CompactionControl {
+    running: AtomicBool,
+    cancel_requested: AtomicBool,
+}
+
+impl CompactionControl {
+    /// Mark a compaction as started. Fails if one is already running.
+    pub fn begin(&self) -> Result<CompactionGuard<'_>, String> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return Err("A compaction is already running".to_string());
+        }
+        self.cancel_requested.store(false, Ordering::SeqCst);
+        Ok(CompactionGuard { control: self })
+    }
+
+    /// Whether a compaction is running.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Ask the running compaction to stop. Returns false if none is running.
+    pub fn cancel(&self) -> bool {
+        let running = self.running.load(Ordering::SeqCst);
+        if running {
+            self.cancel_requested.store(true, Ordering::SeqCst);
+        }
+        running
+    }
+}
+
+/// Held for the duration of a compaction; clears the running flag on drop.
+pub struct CompactionGuard<'a> {
+    control: &'a CompactionControl,
+}
+
+impl CompactionGuard<'_> {
+    /// Flag to poll between compaction steps.
+    pub fn cancel_flag(&self) -> &AtomicBool {
+        &self.control.cancel_requested
+    }
+}
+
+impl Drop for CompactionGuard<'_> {
+    fn drop(&mut self) {
+        self.control.running.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Error to return if `cancel` has been set.
+pub fn check_cancelled(cancel: &AtomicBool) -> Result<(), String> {
+    if cancel.load(Ordering::Relaxed) {
+        return Err(format!(
+            "{}: compaction cancelled; library.bin was not changed",
+            COMPACTION_CANCELLED
+        ));
+    }
+    Ok(())
+}
+
+fn idle_write_log_path(base_path: &Path) -> PathBuf {
+    base_path.join("jp3").join("metadata").join(IDLE_WRITE_LOG_FILE)
+}
+
+fn current_day() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0)
+}
+
+/// Today's idle-time writes to a library. A log from an earlier day, or
+/// none, counts as nothing written; an unreadable one is logged and
+/// treated the same.
+fn load_idle_write_log(base_path: &Path) -> IdleWriteLog {
+    let today = current_day();
+    let log = match fs::read(idle_write_log_path(base_path)) {
+        Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+            log::warn!("Ignoring unreadable {}: {}", IDLE_WRITE_LOG_FILE, e);
+            IdleWriteLog::default()
+        }),
+        Err(_) => IdleWriteLog::default(),
+    };
+    if log.day == today {
+        log
+    } else {
+        IdleWriteLog {
+            day: today,
+            bytes_written: 0,
+        }
+    }
+}
+
+/// Bytes idle-time steps may still write to this library today.
+pub fn idle_budget_remaining(base_path: &Path) -> u64 {
+    IDLE_WRITE_BUDGET.saturating_sub(load_idle_write_log(base_path).bytes_written)
+}
+
+/// Count bytes written by an idle-time step against today's budget.
+pub fn charge_idle_writes(base_path: &Path, bytes: u64) -> Result<(), String> {
+    if bytes == 0 {
+        return Ok(());
+    }
+    let mut log = load_idle_write_log(base_path);
+    log.bytes_written = log.bytes_written.saturating_add(bytes);
+    let data = serde_json::to_vec(&log)
+        .map_err(|e| format!("Failed to serialize idle compaction budget: {}", e))?;
+    fs::write(idle_write_log_path(base_path), data)
+        .map_err(|e| format!("Failed to write idle compaction budget: {}", e))
+}
+
+/// Strings referenced by one chunk, deduplicated within the chunk.
+pub struct ChunkStrings {
+    /// Unique strings in first-seen order
+    strings: Vec<String>,
+    /// Index into `strings` for each value passed to `intern_chunk`
+    refs: Vec<u32>,
+}
+
+impl ChunkStrings {
+    /// Add the chunk's strings to `table`, returning the table ID of each
+    /// value passed to `intern_chunk`, in order.
+    pub fn merge_into(self, table: &mut StringTable) -> Vec<u32> {
+        let ids: Vec<u32> = self.strings.iter().map(|s| table.add(s)).collect();
+        self.refs.into_iter().map(|r| ids[r as usize]).collect()
+    }
+}
+
+/// Deduplicate a chunk's strings ahead of merging them into a string table.
+pub fn intern_chunk<'a>(values: impl IntoIterator<Item = &'a str>) -> ChunkStrings {
+    let mut lookup: HashMap<&str, u32> = HashMap::new();
+    let mut strings = Vec::new();
+    let refs = values
+        .into_iter()
+        .map(|value| {
+            *lookup.entry(value).or_insert_with(|| {
+                strings.push(value.to_string());
+                strings.len() as u32 - 1
+            })
+        })
+        .collect();
+    ChunkStrings { strings, refs }
+}
@@ -15,18 +15,29 @@
 //! # Rate Limiting
 //! Cover Art Archive recommends being "polite" with requests (1 req/sec).
 //! Images can be cached indefinitely as they're under CC/public domain licenses.
+//!
+//! # Stored Size
+//! Covers wider or taller than `CoverImageSettings::max_edge` are downscaled
+//! and re-encoded as JPEG before saving (see `fit_cover_image`), so a few
+//! 3000px provider images can't fill a small SD card.
 
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+use image::codecs::jpeg::JpegEncoder;
 use serde::Deserialize;
 use tokio::time::sleep;
 
+use crate::models::CoverImageSettings;
+
 /// Delay between API calls to be polite to Cover Art Archive
 const API_CALL_DELAY_MS: u64 = 500;
 
+/// Temp directory for covers previewed before an album is saved
+const PREVIEW_DIR: &str = "jp3_cover_previews";
+
 /// Cover Art Archive API response structures
 #[derive(Debug, Deserialize)]
 pub struct CoverArtAlbumResponse {
@@ -179,6 +190,7 @@ pub fn cover_filename(artist: &str, album: &str) -> String {
 /// * `covers_dir` - Directory to save covers (e.g., `{library}/jp3/assets/albums`)
 /// * `artist` - Artist name (for generating stable filename)
 /// * `album` - Album name (for generating stable filename)
+/// * `settings` - Size limits applied to the saved image
 ///
 /// # Returns
 /// * `Ok(FetchCoverResult)` - Path and size of saved cover
@@ -189,6 +201,7 @@ pub async fn fetch_and_save_album_cover(
     covers_dir: &Path,
     artist: &str,
     album: &str,
+    settings: &CoverImageSettings,
 ) -> Result<FetchCoverResult, CoverArtError> {
     let filename = cover_filename(artist, album);
     
@@ -235,16 +248,60 @@ pub async fn fetch_and_save_album_cover(
     match caa_result {
         Ok(url) => {
              log::info!("[CoverArt] Step 1 complete: Got URL from Cover Art Archive: {}", url);
-             save_cover_image(&url, covers_dir, &filename).await
+             save_cover_image(&url, covers_dir, &filename, settings).await
         },
         Err(e) => {
              // If all CAA attempts failed (or errored), try Deezer fallback
              log::warn!("[CoverArt] Cover Art Archive failed: {}. Attempting Deezer fallback...", e);
-             fetch_and_save_deezer_album_cover(covers_dir, artist, album).await
+             fetch_and_save_deezer_album_cover(covers_dir, artist, album, settings).await
         }
     }
 }
 
+/// Fetch album cover art by trying a list of release MBIDs in order.
+///
+/// Used when no MBID came from a text search: the candidates are release
+/// MBIDs AcoustID reported for the album's recordings. Each is tried against
+/// Cover Art Archive until one has art. No Deezer fallback here; callers
+/// that want one can use `fetch_and_save_deezer_album_cover`.
+///
+/// # Returns
+/// * `Ok((FetchCoverResult, mbid))` - Saved cover and the MBID that had art
+/// * `Err(CoverArtError::NotFound)` - If no candidate has cover art
+pub async fn fetch_and_save_album_cover_from_releases(
+    mbids: &[String],
+    covers_dir: &Path,
+    artist: &str,
+    album: &str,
+    settings: &CoverImageSettings,
+) -> Result<(FetchCoverResult, String), CoverArtError> {
+    let filename = cover_filename(artist, album);
+
+    log::info!(
+        "[CoverArt] Trying {} fingerprint release candidates for {} - {}",
+        mbids.len(),
+        artist,
+        album
+    );
+
+    for mbid in mbids {
+        // Rate limit
+        sleep(Duration::from_millis(API_CALL_DELAY_MS)).await;
+
+        match get_album_cover_url(mbid).await {
+            Ok(url) => {
+                log::info!("[CoverArt] Release {} has cover art: {}", mbid, url);
+                let result = save_cover_image(&url, covers_dir, &filename, settings).await?;
+                return Ok((result, mbid.clone()));
+            }
+            Err(CoverArtError::NotFound) => continue,
+            Err(e) => log::warn!("[CoverArt] Release {} lookup failed: {}", mbid, e),
+        }
+    }
+
+    Err(CoverArtError::NotFound)
+}
+
 /// Fetch artist cover art from Deezer and save it to the covers directory.
 ///
 /// Searches Deezer by artist name — no MBID or API key required.
@@ -252,6 +309,7 @@ pub async fn fetch_and_save_album_cover(
 /// # Arguments
 /// * `covers_dir` - Directory to save covers (e.g., `{library}/jp3/assets/artists`)
 /// * `artist` - Artist name (used for search and for generating stable filename)
+/// * `settings` - Size limits applied to the saved image
 ///
 /// # Returns
 /// * `Ok(FetchCoverResult)` - Path and size of saved cover
@@ -259,6 +317,7 @@ pub async fn fetch_and_save_album_cover(
 pub async fn fetch_and_save_artist_cover(
     covers_dir: &Path,
     artist: &str,
+    settings: &CoverImageSettings,
 ) -> Result<FetchCoverResult, CoverArtError> {
     // Use "artist" as the second component for artist covers
     let filename = cover_filename(artist, "artist");
@@ -278,7 +337,49 @@ pub async fn fetch_and_save_artist_cover(
     log::info!("[Deezer] Step 1 complete: Got URL: {}", cover_url);
 
     // Download and save the image
-    save_cover_image(&cover_url, covers_dir, &filename).await
+    save_cover_image(&cover_url, covers_dir, &filename, settings).await
+}
+
+/// Downscale and re-encode a cover that exceeds `settings.max_edge`.
+///
+/// Covers within the limit are returned unchanged (re-encoding a JPEG
+/// only loses quality). Images that can't be decoded are also kept as-is.
+pub fn fit_cover_image(image_bytes: Vec<u8>, settings: &CoverImageSettings) -> Vec<u8> {
+    let image = match image::load_from_memory(&image_bytes) {
+        Ok(image) => image,
+        Err(e) => {
+            log::warn!("[CoverArt] Could not decode cover, saving as downloaded: {}", e);
+            return image_bytes;
+        }
+    };
+
+    let edge = settings.max_edge;
+    if image.width() <= edge && image.height() <= edge {
+        return image_bytes;
+    }
+
+    let mut resized = Vec::new();
+    let encoded = image
+        .resize(edge, edge, image::imageops::FilterType::Lanczos3)
+        .to_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut resized, settings.jpeg_quality));
+    match encoded {
+        Ok(()) => {
+            log::info!(
+                "[CoverArt] Resized {}x{} cover to fit {}px ({} -> {} bytes)",
+                image.width(),
+                image.height(),
+                edge,
+                image_bytes.len(),
+                resized.len()
+            );
+            resized
+        }
+        Err(e) => {
+            log::warn!("[CoverArt] Could not re-encode cover, saving as downloaded: {}", e);
+            image_bytes
+        }
+    }
 }
 
 /// Download and save a cover image to disk.
@@ -286,11 +387,16 @@ async fn save_cover_image(
     cover_url: &str,
     covers_dir: &Path,
     filename: &str,
+    settings: &CoverImageSettings,
 ) -> Result<FetchCoverResult, CoverArtError> {
     // Download the image
     log::info!("[CoverArt] Step 2: Downloading image...");
     let image_bytes = download_image(cover_url).await?;
     log::info!("[CoverArt] Step 2 complete: Downloaded {} bytes", image_bytes.len());
+    let image_bytes = fit_cover_image(image_bytes, settings);
+
+    // Pause if the SD card was pulled while we were downloading
+    crate::services::library_monitor_service::wait_while_library_unavailable().await;
 
     // Save to file
     log::info!("[CoverArt] Step 3: Saving to disk...");
@@ -505,6 +611,22 @@ async fn download_image(url: &str) -> Result<Vec<u8>, CoverArtError> {
     Ok(bytes.to_vec())
 }
 
+/// Directory for preview covers, outside the library.
+///
+/// Previews use the same hashed filenames as library covers and live in
+/// the OS temp directory, so nothing is written to the SD card before the
+/// user saves. Each release MBID gets its own subfolder (`search` when none
+/// was given), so picking another release for the same artist and album
+/// doesn't show the cover cached for the first one.
+pub fn preview_covers_dir(mbid: Option<&str>) -> PathBuf {
+    let key: String = mbid
+        .unwrap_or("search")
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect();
+    std::env::temp_dir().join(PREVIEW_DIR).join(key)
+}
+
 /// Check if a cover already exists for an album (by artist+album name).
 pub fn cover_exists_by_name(covers_dir: &Path, artist: &str, album: &str) -> bool {
     let filename = cover_filename(artist, album);
@@ -534,6 +656,7 @@ pub async fn fetch_and_save_deezer_album_cover(
     covers_dir: &Path,
     artist: &str,
     album: &str,
+    settings: &CoverImageSettings,
 ) -> Result<FetchCoverResult, CoverArtError> {
     let filename = cover_filename(artist, album);
 
@@ -608,7 +731,7 @@ pub async fn fetch_and_save_deezer_album_cover(
     log::info!("[Deezer] Selected album cover URL: {}", cover_url);
 
     // Download and save the image
-    save_cover_image(cover_url, covers_dir, &filename).await
+    save_cover_image(cover_url, covers_dir, &filename, settings).await
 }
 
 
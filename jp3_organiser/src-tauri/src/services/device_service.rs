@@ -0,0 +1,358 @@
+//! Device (SD card) identity.
+//!
+//! Drive letters and mount points change between sessions, so a card is
+//! recognised by an ID stored on the card itself in
+//! `jp3/metadata/device.json`, written when the library is initialized.
+//! Cards without a library can instead be registered by a volume serial or
+//! UUID supplied by the caller.
+//!
+//! The marker also records which library.bin version the card started with
+//! and which app wrote it. The firmware adds its version and may declare
+//! the newest library.bin and playlist formats and most songs it handles
+//! (`firmwareVersion`, `firmwareMaxLibraryVersion`,
+//! `firmwareMaxPlaylistVersion`, `firmwareMaxSongs`). Limits it doesn't
+//! declare come from `FIRMWARE_SUPPORT`, the table of firmware releases and
+//! the formats they read. `compatibility_problems` checks a card against
+//! them. library.bin is written in the newest format the firmware reads
+//! (see `writable_library_version`); writes that would still break the
+//! card (too many songs) are refused.
+//!
+//! The list of known devices and their names lives in the app store (see
+//! `commands::config`); this module only deals with the card side and with
+//! updating that list.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{
+    CompatibilityProblem, KnownDevice, DISC_NUMBERS_VERSION, GENRES_VERSION, LIBRARY_VERSION,
+    SORT_NAMES_VERSION,
+};
+
+const DEVICE_FILE: &str = "device.json";
+
+/// Formats and limits of one firmware release.
+pub struct FirmwareSupport {
+    /// First firmware version with this support
+    pub version: &'static str,
+    /// Newest library.bin version it reads
+    pub max_library_version: u32,
+    /// Newest playlist file version it reads
+    pub max_playlist_version: u32,
+    /// Most songs it handles, if limited
+    pub max_songs: Option<u32>,
+}
+
+/// Firmware releases and what they read, oldest first.
+///
+/// A release's row applies to every firmware version up to the next row.
+/// Firmware older than the first row is only checked against the limits it
+/// declares.
+pub const FIRMWARE_SUPPORT: &[FirmwareSupport] = &[
+    // First release: sort names, no genres, 24-byte songs, v1 playlists
+    FirmwareSupport {
+        version: "1.0",
+        max_library_version: 2,
+        max_playlist_version: 1,
+        max_songs: None,
+    },
+    // Genres and disc numbers, ratings, dates added and play counts
+    FirmwareSupport {
+        version: "1.1",
+        max_library_version: 4,
+        max_playlist_version: 1,
+        max_songs: None,
+    },
+    // Checksums, table slack and v2 playlists (added dates, manual order)
+    FirmwareSupport {
+        version: "1.2",
+        max_library_version: 6,
+        max_playlist_version: 2,
+        max_songs: None,
+    },
+];
+
+/// The `FIRMWARE_SUPPORT` row for a firmware version, if it is known.
+///
+/// Versions compare numerically by dot-separated parts ("1.10" > "1.9");
+/// a version that doesn't parse has no row.
+pub fn firmware_support(version: &str) -> Option<&'static FirmwareSupport> {
+    let parsed = parse_version(version)?;
+    FIRMWARE_SUPPORT
+        .iter()
+        .rev()
+        .find(|row| parse_version(row.version).is_some_and(|row_version| row_version <= parsed))
+}
+
+fn parse_version(version: &str) -> Option<Vec<u32>> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect()
+}
+
+/// Prefix of the error returned when a write would leave the card with a
+/// library its firmware can't read.
+pub const FIRMWARE_INCOMPATIBLE: &str = "FirmwareIncompatible";
+
+/// Contents of `jp3/metadata/device.json`.
+///
+/// Fields other than `id` default when missing (markers written before
+/// they existed).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceMarker {
+    /// Random ID assigned when the marker was first written
+    pub id: String,
+    /// When the marker was written (Unix seconds)
+    #[serde(default)]
+    pub created_at: u64,
+    /// library.bin generation when the marker was written
+    #[serde(default)]
+    pub library_generation: u32,
+    /// library.bin format version when the marker was written
+    #[serde(default)]
+    pub library_version: u32,
+    /// Version of the app that wrote the marker
+    #[serde(default)]
+    pub app_version: String,
+    /// Firmware version, if the firmware declared one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firmware_version: Option<String>,
+    /// Newest library.bin version the card's firmware reads, if it declared one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firmware_max_library_version: Option<u32>,
+    /// Newest playlist file version the firmware reads, if it declared one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firmware_max_playlist_version: Option<u32>,
+    /// Most songs the firmware handles, if it declared a limit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firmware_max_songs: Option<u32>,
+}
+
+impl DeviceMarker {
+    /// The `FIRMWARE_SUPPORT` row for the firmware's declared version.
+    fn support(&self) -> Option<&'static FirmwareSupport> {
+        firmware_support(self.firmware_version.as_deref()?)
+    }
+
+    /// Newest library.bin version the firmware reads: as declared, else
+    /// from its release's row.
+    pub fn max_library_version(&self) -> Option<u32> {
+        self.firmware_max_library_version
+            .or_else(|| Some(self.support()?.max_library_version))
+    }
+
+    /// Newest playlist version the firmware reads: as declared, else from
+    /// its release's row.
+    pub fn max_playlist_version(&self) -> Option<u32> {
+        self.firmware_max_playlist_version
+            .or_else(|| Some(self.support()?.max_playlist_version))
+    }
+
+    /// Most songs the firmware handles: as declared, else from its
+    /// release's row.
+    pub fn max_songs(&self) -> Option<u32> {
+        self.firmware_max_songs.or_else(|| self.support()?.max_songs)
+    }
+
+    /// Whether the firmware declared its version or any limit.
+    pub fn declares_firmware(&self) -> bool {
+        self.firmware_version.is_some()
+            || self.firmware_max_library_version.is_some()
+            || self.firmware_max_playlist_version.is_some()
+            || self.firmware_max_songs.is_some()
+    }
+}
+
+fn marker_path(base_path: &Path) -> PathBuf {
+    base_path.join("jp3").join("metadata").join(DEVICE_FILE)
+}
+
+/// The card's device marker, or None if none has been written.
+pub fn read_device_marker(base_path: &Path) -> Result<Option<DeviceMarker>, String> {
+    let path = marker_path(base_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read device marker: {}", e))?;
+    serde_json::from_str(&data)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse device marker: {}", e))
+}
+
+/// Write `marker` as the card's device marker.
+pub fn write_device_marker(base_path: &Path, marker: &DeviceMarker) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(marker)
+        .map_err(|e| format!("Failed to serialize device marker: {}", e))?;
+    fs::write(marker_path(base_path), data)
+        .map_err(|e| format!("Failed to write device marker: {}", e))
+}
+
+/// The card's device ID, or None if no marker has been written.
+pub fn read_device_id(base_path: &Path) -> Result<Option<String>, String> {
+    Ok(read_device_marker(base_path)?.map(|marker| marker.id))
+}
+
+/// The card's device marker, writing a new one if there isn't one yet.
+///
+/// The library version and generation are only recorded in a new marker;
+/// an existing marker is returned unchanged.
+pub fn ensure_device_marker(
+    base_path: &Path,
+    library_version: u32,
+    library_generation: u32,
+) -> Result<DeviceMarker, String> {
+    if let Some(marker) = read_device_marker(base_path)? {
+        return Ok(marker);
+    }
+    let marker = DeviceMarker {
+        id: uuid::Uuid::new_v4().to_string(),
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        library_generation,
+        library_version,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        firmware_version: None,
+        firmware_max_library_version: None,
+        firmware_max_playlist_version: None,
+        firmware_max_songs: None,
+    };
+    write_device_marker(base_path, &marker)?;
+    Ok(marker)
+}
+
+/// Version of the library.bin to write to a card: the current format, or
+/// the newest the card's firmware reads if that is older.
+///
+/// Cards without a marker (and libraries on the computer) get the current
+/// format.
+pub fn writable_library_version(base_path: &Path) -> u32 {
+    read_device_marker(base_path)
+        .ok()
+        .flatten()
+        .and_then(|marker| marker.max_library_version())
+        .map_or(LIBRARY_VERSION, |max| max.clamp(1, LIBRARY_VERSION))
+}
+
+/// What a library.bin written as `version` leaves out, compared with the
+/// current format ("" if nothing the app shows).
+pub fn library_version_losses(version: u32) -> String {
+    let mut lost = Vec::new();
+    if version < SORT_NAMES_VERSION {
+        lost.push("sort names");
+    }
+    if version < GENRES_VERSION {
+        lost.push("genres");
+    }
+    if version < DISC_NUMBERS_VERSION {
+        lost.push("disc numbers, ratings, dates added and play counts");
+    }
+    lost.join(", ")
+}
+
+/// What about a library would break on the card's firmware, with the fix
+/// for each.
+///
+/// `playlists` are (name, file version) pairs. Limits the firmware neither
+/// declared nor has a `FIRMWARE_SUPPORT` row for aren't checked.
+pub fn compatibility_problems(
+    marker: &DeviceMarker,
+    library_version: u32,
+    song_count: u32,
+    playlists: &[(String, u32)],
+) -> Vec<CompatibilityProblem> {
+    let mut problems = Vec::new();
+    if let Some(max) = marker.max_library_version().filter(|&max| library_version > max) {
+        let losses = library_version_losses(max);
+        let fix = format!(
+            "Migrate the card's library, or export it again with library version {}, to \
+             write the v{} format{}; or update the firmware",
+            max,
+            max,
+            match losses.is_empty() {
+                true => String::new(),
+                false => format!(" (drops {})", losses),
+            }
+        );
+        problems.push(CompatibilityProblem {
+            problem: format!(
+                "library.bin is v{}, but the firmware reads up to v{}",
+                library_version, max
+            ),
+            fix,
+        });
+    }
+    if let Some(max) = marker.max_songs().filter(|&max| song_count > max) {
+        problems.push(CompatibilityProblem {
+            problem: format!(
+                "{} songs, but the firmware handles up to {}",
+                song_count, max
+            ),
+            fix: "Compact the library or delete songs until it fits".to_string(),
+        });
+    }
+    if let Some(max) = marker.max_playlist_version() {
+        let too_new: Vec<&str> = playlists
+            .iter()
+            .filter(|(_, version)| *version > max)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if !too_new.is_empty() {
+            problems.push(CompatibilityProblem {
+                problem: format!("Playlists newer than v{}: {}", max, too_new.join(", ")),
+                fix: "Convert the playlists to the v1 layout (drops added dates and manual order)"
+                    .to_string(),
+            });
+        }
+    }
+    problems
+}
+
+/// Error if writing a library.bin of this version and size would break the
+/// card's firmware. Cards without a marker pass.
+pub fn check_firmware_supports(
+    base_path: &Path,
+    library_version: u32,
+    song_count: u32,
+) -> Result<(), String> {
+    let Some(marker) = read_device_marker(base_path)? else {
+        return Ok(());
+    };
+    let problems = compatibility_problems(&marker, library_version, song_count, &[]);
+    if problems.is_empty() {
+        return Ok(());
+    }
+    let details: Vec<String> = problems
+        .iter()
+        .map(|p| format!("{} ({})", p.problem, p.fix))
+        .collect();
+    Err(format!(
+        "{}: this card's firmware can't read the library after this change: {}",
+        FIRMWARE_INCOMPATIBLE,
+        details.join("; ")
+    ))
+}
+
+/// Add a device to the known list, or rename it if its ID is already there.
+///
+/// A device seen at a new path keeps its name; the path is updated.
+pub fn upsert_device(devices: &mut Vec<KnownDevice>, device: KnownDevice) {
+    match devices.iter_mut().find(|d| d.id == device.id) {
+        Some(existing) => {
+            existing.name = device.name;
+            if device.last_path.is_some() {
+                existing.last_path = device.last_path;
+            }
+        }
+        None => devices.push(device),
+    }
+}
@@ -0,0 +1,82 @@
+//! Picking songs to fill a size budget for `export_selection`.
+//!
+//! Songs (or whole albums) are ranked by the user's preferences and taken
+//! greedily: each one that still fits is included, the rest are excluded.
+//! A large album that doesn't fit doesn't stop smaller ones after it from
+//! filling the remaining space.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::models::{ParsedSong, SelectionPreferences, SelectionPriority, SizedSelection};
+
+/// Choose songs whose audio files fit in `budget_bytes`.
+///
+/// `sizes` maps song IDs to their audio file size; songs without a size
+/// (file missing) are always excluded. Songs are ranked favorites first (if
+/// asked), then by `preferences.priority`, then in the order given.
+///
+/// Whole albums rank by their best song, except by play count, where the
+/// album's plays are totalled so one much-played single doesn't outrank an
+/// album that is played through.
+pub fn select_within_budget(
+    songs: &[ParsedSong],
+    sizes: &HashMap<u32, u64>,
+    budget_bytes: u64,
+    preferences: &SelectionPreferences,
+) -> SizedSelection {
+    let mut selection = SizedSelection::default();
+    let (available, missing): (Vec<&ParsedSong>, Vec<&ParsedSong>) =
+        songs.iter().partition(|s| sizes.contains_key(&s.id));
+    selection.excluded_song_ids = missing.iter().map(|s| s.id).collect();
+
+    // Each unit is picked or skipped as a whole
+    let mut units: Vec<Vec<&ParsedSong>> = Vec::new();
+    if preferences.whole_albums {
+        let mut album_units: HashMap<u32, usize> = HashMap::new();
+        for song in available {
+            let index = *album_units.entry(song.album_id).or_insert_with(|| {
+                units.push(Vec::new());
+                units.len() - 1
+            });
+            units[index].push(song);
+        }
+        for unit in &mut units {
+            unit.sort_by_key(|s| (s.disc_number, s.track_number, s.id));
+        }
+    } else {
+        units = available.into_iter().map(|song| vec![song]).collect();
+    }
+
+    let rank = |unit: &Vec<&ParsedSong>| {
+        let favorite = preferences.favorites_first && unit.iter().any(|s| s.is_favorite);
+        let priority = match preferences.priority {
+            SelectionPriority::LibraryOrder => 0,
+            SelectionPriority::HighestRated => {
+                unit.iter().map(|s| s.rating as u64).max().unwrap_or(0)
+            }
+            SelectionPriority::RecentlyAdded => {
+                unit.iter().map(|s| s.date_added as u64).max().unwrap_or(0)
+            }
+            SelectionPriority::MostPlayed => unit.iter().map(|s| s.play_count as u64).sum(),
+        };
+        (Reverse(favorite), Reverse(priority))
+    };
+    // Stable, so equal ranks keep the order given
+    units.sort_by_cached_key(rank);
+
+    let mut remaining = budget_bytes;
+    for unit in units {
+        let bytes: u64 = unit.iter().map(|s| sizes[&s.id]).sum();
+        let ids = unit.iter().map(|s| s.id);
+        if bytes <= remaining {
+            remaining -= bytes;
+            selection.selected_bytes += bytes;
+            selection.song_ids.extend(ids);
+        } else {
+            selection.excluded_bytes += bytes;
+            selection.excluded_song_ids.extend(ids);
+        }
+    }
+    selection
+}
@@ -0,0 +1,105 @@
+//! Persistent log of files that failed to import.
+//!
+//! Files that fail processing (decode error, fpcalc failure, network error)
+//! are recorded with their reason in `failed_imports.json` via
+//! tauri-plugin-store, so they survive the session and can be retried with
+//! `retry_failed_imports`. A later successful run clears the entry.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+use crate::models::{FailedImport, MetadataStatus, TrackedAudioFile};
+
+const STORE_FILENAME: &str = "failed_imports.json";
+const FAILED_IMPORTS_KEY: &str = "failed_imports";
+
+/// Whether a processed file should be treated as a failed import.
+///
+/// Files with complete metadata are not failures even if a secondary
+/// step (e.g. the AcoustID lookup) reported an error.
+pub fn is_failed_import(file: &TrackedAudioFile) -> bool {
+    file.metadata_status == MetadataStatus::Error
+        || (file.error_message.is_some() && file.metadata_status != MetadataStatus::Complete)
+}
+
+/// Update a failed-imports list with the outcome of processing a file.
+///
+/// Failures are added (or have their attempt count bumped); successes
+/// remove any previous entry for the same path.
+pub fn apply_outcome(failed: &mut Vec<FailedImport>, file: &TrackedAudioFile, now: u64) {
+    if !is_failed_import(file) {
+        failed.retain(|f| f.file_path != file.file_path);
+        return;
+    }
+
+    let reason = file
+        .error_message
+        .clone()
+        .unwrap_or_else(|| "Unknown error".to_string());
+
+    match failed.iter_mut().find(|f| f.file_path == file.file_path) {
+        Some(existing) => {
+            existing.reason = reason;
+            existing.attempts += 1;
+            existing.last_failed_at = now;
+        }
+        None => failed.push(FailedImport {
+            file_path: file.file_path.clone(),
+            file_name: file.file_name.clone(),
+            reason,
+            attempts: 1,
+            last_failed_at: now,
+        }),
+    }
+}
+
+/// Load the persisted failed-imports list.
+pub fn load_failed_imports(app: &AppHandle) -> Result<Vec<FailedImport>, String> {
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+
+    match store.get(FAILED_IMPORTS_KEY) {
+        Some(value) => serde_json::from_value(value)
+            .map_err(|e| format!("Failed to parse failed imports: {}", e)),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Persist the failed-imports list.
+pub fn save_failed_imports(app: &AppHandle, failed: &[FailedImport]) -> Result<(), String> {
+    let store = app
+        .store(STORE_FILENAME)
+        .map_err(|e| format!("Failed to open store: {}", e))?;
+
+    store.set(FAILED_IMPORTS_KEY, serde_json::json!(failed));
+    store.save().map_err(|e| format!("Failed to save store: {}", e))
+}
+
+/// Record the outcome of processing several files.
+///
+/// Errors are logged rather than returned so a store problem never fails
+/// the processing command itself.
+pub fn record_outcomes(app: &AppHandle, files: &[TrackedAudioFile]) {
+    let mut failed = match load_failed_imports(app) {
+        Ok(f) => f,
+        Err(e) => {
+            log::error!("[FailedImports] {}", e);
+            return;
+        }
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    for file in files {
+        apply_outcome(&mut failed, file, now);
+    }
+
+    if let Err(e) = save_failed_imports(app, &failed) {
+        log::error!("[FailedImports] {}", e);
+    }
+}
@@ -0,0 +1,98 @@
+//! Readable names for audio files in `jp3/music`.
+//!
+//! Files are numbered `001.mp3`, `002.mp3`, ... unless the library's
+//! `BucketLayout` names them after the song (`01 - Title.mp3`,
+//! `Artist - Title.mp3`), which makes the card browsable in a plain file
+//! manager. Names are made safe for FAT: characters FAT rejects are
+//! replaced, trailing dots and spaces dropped, device names (`CON`, `NUL`,
+//! ...) avoided and length capped. FAT also ignores case, so two songs
+//! whose names differ only in case get a ` (2)` suffix like any other
+//! clash.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::models::FileNameScheme;
+
+/// Longest file stem written, in characters. Keeps full paths well inside
+/// what the firmware's FAT driver handles.
+const MAX_STEM_CHARS: usize = 96;
+
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Name for a song's file under `scheme`.
+///
+/// `sequence` is the file's 1-based position in its bucket, used by the
+/// sequential scheme. A track number of 0 (unknown) is left out.
+pub fn song_file_name(
+    scheme: FileNameScheme,
+    sequence: usize,
+    track_number: u16,
+    artist: &str,
+    title: &str,
+    extension: &str,
+) -> String {
+    let stem = match scheme {
+        FileNameScheme::Sequential => return format!("{:03}.{}", sequence, extension),
+        FileNameScheme::TrackTitle if track_number > 0 => {
+            format!("{:02} - {}", track_number, title)
+        }
+        FileNameScheme::TrackTitle => title.to_string(),
+        FileNameScheme::ArtistTitle => format!("{} - {}", artist, title),
+    };
+    format!("{}.{}", fat_safe_stem(&stem), extension)
+}
+
+/// `stem` with everything FAT can't store replaced or removed.
+pub fn fat_safe_stem(stem: &str) -> String {
+    let replaced: String = stem
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_STEM_CHARS)
+        .collect();
+    let trimmed = replaced.trim_start().trim_end_matches(['.', ' ']);
+    if trimmed.is_empty() {
+        return "Untitled".to_string();
+    }
+    let device = trimmed.split('.').next().unwrap_or(trimmed);
+    if RESERVED_NAMES.iter().any(|name| name.eq_ignore_ascii_case(device)) {
+        return format!("{}_", trimmed);
+    }
+    trimmed.to_string()
+}
+
+/// `name`, or `name (2)`, `name (3)`, ... if `taken` (lowercase names
+/// already in the bucket) has it. The returned name is added to `taken`.
+pub fn unique_file_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) => (stem, format!(".{}", extension)),
+        None => (name, String::new()),
+    };
+    let mut candidate = name.to_string();
+    let mut copy = 1;
+    while !taken.insert(candidate.to_lowercase()) {
+        copy += 1;
+        candidate = format!("{} ({}){}", stem, copy, extension);
+    }
+    candidate
+}
+
+/// Lowercase names of the files already in `bucket`, for `unique_file_name`.
+pub fn taken_file_names(bucket: &Path) -> HashSet<String> {
+    fs::read_dir(bucket)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.file_name().to_string_lossy().to_lowercase())
+                .collect()
+        })
+        .unwrap_or_default()
+}
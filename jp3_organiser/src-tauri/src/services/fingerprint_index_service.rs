@@ -0,0 +1,103 @@
+//! Library fingerprint index persistence.
+//!
+//! Reads and writes `jp3/metadata/fingerprints.json` and decides whether a
+//! stored fingerprint is still valid for the current algorithm/fpcalc.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::models::{FingerprintIndex, StoredFingerprint};
+use crate::services::fingerprint_match_service::{decode_fingerprint, FingerprintCandidate};
+use crate::services::path_index_service::{
+    load_index, record_entries, rename_entries, retain_entries, save_index, PathIndex,
+};
+
+impl PathIndex for FingerprintIndex {
+    type Entry = StoredFingerprint;
+    const FILE_NAME: &'static str = "fingerprints.json";
+    const DESCRIPTION: &'static str = "fingerprint index";
+
+    fn entries_mut(&mut self) -> &mut HashMap<String, StoredFingerprint> {
+        &mut self.entries
+    }
+}
+
+/// Load the fingerprint index for a library, or an empty index if none exists.
+pub fn load_fingerprint_index(base_path: &Path) -> Result<FingerprintIndex, String> {
+    load_index(base_path)
+}
+
+/// Persist the fingerprint index for a library.
+pub fn save_fingerprint_index(base_path: &Path, index: &FingerprintIndex) -> Result<(), String> {
+    save_index(base_path, index)
+}
+
+/// Record fingerprints for newly saved songs, by relative path.
+pub fn record_fingerprints(
+    base_path: &Path,
+    songs: Vec<(String, StoredFingerprint)>,
+) -> Result<(), String> {
+    record_entries::<FingerprintIndex>(base_path, songs)
+}
+
+/// Drop fingerprints of songs for which `keep` returns false (deleted songs).
+pub fn retain_fingerprints(base_path: &Path, keep: impl Fn(&str) -> bool) -> Result<(), String> {
+    retain_entries::<FingerprintIndex>(base_path, keep)
+}
+
+/// Re-key fingerprints after compaction renumbered song files.
+pub fn rename_fingerprints(
+    base_path: &Path,
+    renamed: &HashMap<String, String>,
+) -> Result<(), String> {
+    rename_entries::<FingerprintIndex>(base_path, renamed)
+}
+
+/// Whether a stored fingerprint was produced by the given algorithm/version.
+///
+/// An unknown current version (fpcalc missing) only checks the algorithm,
+/// so a missing fpcalc never invalidates the whole index.
+pub fn is_fingerprint_current(
+    entry: &StoredFingerprint,
+    algorithm: u32,
+    fpcalc_version: Option<&str>,
+) -> bool {
+    if entry.algorithm != algorithm {
+        return false;
+    }
+    match fpcalc_version {
+        Some(version) => entry.fpcalc_version.as_deref() == Some(version),
+        None => true,
+    }
+}
+
+/// Decoded fingerprints of songs with a current index entry, ready for
+/// matching. Candidate IDs are song IDs.
+///
+/// `song_paths` are (song ID, relative path) pairs of the songs to include.
+/// Entries from another algorithm/version are skipped, since they can't be
+/// compared with fingerprints made now.
+pub fn current_fingerprints<'a>(
+    index: &FingerprintIndex,
+    song_paths: impl IntoIterator<Item = (u32, &'a str)>,
+    algorithm: u32,
+    fpcalc_version: Option<&str>,
+) -> Vec<FingerprintCandidate> {
+    song_paths
+        .into_iter()
+        .filter_map(|(song_id, path)| {
+            let entry = index.entries.get(path)?;
+            if !is_fingerprint_current(entry, algorithm, fpcalc_version) {
+                return None;
+            }
+            let raw = decode_fingerprint(&entry.fingerprint)
+                .map_err(|e| log::warn!("Bad stored fingerprint for {}: {}", path, e))
+                .ok()?;
+            Some(FingerprintCandidate {
+                id: song_id.to_string(),
+                raw,
+                duration_secs: entry.duration_secs,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,222 @@
+//! Acoustic fingerprint comparison.
+//!
+//! fpcalc outputs fingerprints in Chromaprint's compressed, URL-safe base64
+//! form. To compare two files we decompress them back into the raw 32-bit
+//! sub-fingerprints and measure how many bits agree at the best alignment.
+//!
+//! Identical recordings from different sources (e.g. a FLAC rip and an MP3
+//! download) typically score above 0.9; unrelated songs hover around 0.5.
+
+use std::collections::HashMap;
+
+/// Minimum similarity (fraction of matching bits) to treat two files as the same song
+pub const MATCH_THRESHOLD: f32 = 0.8;
+
+/// Maximum duration difference (seconds) between two matching files
+const MAX_DURATION_DIFF_SECS: u32 = 7;
+
+/// Maximum alignment shift tried, in sub-fingerprints (~8 per second)
+const MAX_OFFSET: isize = 80;
+
+/// Minimum number of overlapping sub-fingerprints for a comparison to count
+const MIN_OVERLAP: usize = 40;
+
+/// Decode a compressed Chromaprint fingerprint into raw sub-fingerprints.
+///
+/// Format: 1 byte algorithm, 3 bytes item count, then delta-coded set-bit
+/// positions packed as 3-bit values, with 5-bit overflow values for deltas
+/// of 7 or more. Each decoded item is XORed with the previous one.
+pub fn decode_fingerprint(encoded: &str) -> Result<Vec<u32>, String> {
+    let data = decode_base64_url(encoded)?;
+    if data.len() < 4 {
+        return Err("Fingerprint too short".to_string());
+    }
+
+    let item_count = ((data[1] as usize) << 16) | ((data[2] as usize) << 8) | data[3] as usize;
+    let body = &data[4..];
+
+    // 3-bit values until we've seen one terminating zero per item
+    let mut values: Vec<u32> = Vec::new();
+    let mut zeros = 0;
+    let mut bit_pos = 0;
+    while zeros < item_count {
+        let value = read_packed(body, bit_pos, 3).ok_or("Truncated fingerprint")?;
+        bit_pos += 3;
+        if value == 0 {
+            zeros += 1;
+        }
+        values.push(value);
+    }
+
+    // 5-bit overflow values start at the next byte boundary
+    let mut overflow_pos = bit_pos.div_ceil(8) * 8;
+    for value in values.iter_mut().filter(|v| **v == 7) {
+        let extra = read_packed(body, overflow_pos, 5).ok_or("Truncated fingerprint")?;
+        overflow_pos += 5;
+        *value += extra;
+    }
+
+    let mut items: Vec<u32> = Vec::with_capacity(item_count);
+    let mut current: u32 = 0;
+    let mut last_bit: u32 = 0;
+    for value in values {
+        if value == 0 {
+            let item = match items.last() {
+                Some(prev) => current ^ prev,
+                None => current,
+            };
+            items.push(item);
+            current = 0;
+            last_bit = 0;
+        } else {
+            last_bit += value;
+            if last_bit > 32 {
+                return Err("Invalid fingerprint bit position".to_string());
+            }
+            current |= 1 << (last_bit - 1);
+        }
+    }
+
+    Ok(items)
+}
+
+/// Similarity of two raw fingerprints at their best alignment (0.0 - 1.0).
+pub fn fingerprint_similarity(a: &[u32], b: &[u32]) -> f32 {
+    let mut best = 0.0f32;
+
+    for offset in -MAX_OFFSET..=MAX_OFFSET {
+        let (a_start, b_start) = if offset >= 0 {
+            (offset as usize, 0)
+        } else {
+            (0, (-offset) as usize)
+        };
+        if a_start >= a.len() || b_start >= b.len() {
+            continue;
+        }
+        let overlap = (a.len() - a_start).min(b.len() - b_start);
+        if overlap < MIN_OVERLAP {
+            continue;
+        }
+
+        let differing_bits: u32 = a[a_start..a_start + overlap]
+            .iter()
+            .zip(&b[b_start..b_start + overlap])
+            .map(|(x, y)| (x ^ y).count_ones())
+            .sum();
+        let score = 1.0 - differing_bits as f32 / (overlap as f32 * 32.0);
+        best = best.max(score);
+    }
+
+    best
+}
+
+/// A decoded fingerprint ready for grouping.
+pub struct FingerprintCandidate {
+    pub id: String,
+    pub raw: Vec<u32>,
+    pub duration_secs: u32,
+}
+
+/// Similarity of two candidates if they match (close durations and a
+/// similarity of at least `MATCH_THRESHOLD`), None otherwise.
+pub fn fingerprints_match(a: &FingerprintCandidate, b: &FingerprintCandidate) -> Option<f32> {
+    if a.duration_secs.abs_diff(b.duration_secs) > MAX_DURATION_DIFF_SECS {
+        return None;
+    }
+    let score = fingerprint_similarity(&a.raw, &b.raw);
+    (score >= MATCH_THRESHOLD).then_some(score)
+}
+
+/// Cluster candidates whose fingerprints match each other.
+///
+/// Matching is transitive (A~B and B~C puts all three together). Returns
+/// only clusters with two or more members, each paired with the best
+/// similarity seen inside it. Input order is preserved within clusters.
+pub fn group_matching_fingerprints(candidates: &[FingerprintCandidate]) -> Vec<(Vec<String>, f32)> {
+    let mut parent: Vec<usize> = (0..candidates.len()).collect();
+    let mut best_score: Vec<f32> = vec![0.0; candidates.len()];
+
+    fn find(parent: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parent[root] != root {
+            root = parent[root];
+        }
+        parent[i] = root;
+        root
+    }
+
+    for i in 0..candidates.len() {
+        for j in (i + 1)..candidates.len() {
+            if let Some(score) = fingerprints_match(&candidates[i], &candidates[j]) {
+                let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                let merged = best_score[ri].max(best_score[rj]).max(score);
+                parent[rj] = ri;
+                best_score[ri] = merged;
+            }
+        }
+    }
+
+    let mut clusters: Vec<(usize, Vec<String>)> = Vec::new();
+    let mut cluster_index: HashMap<usize, usize> = HashMap::new();
+    for (i, candidate) in candidates.iter().enumerate() {
+        let root = find(&mut parent, i);
+        let index = *cluster_index.entry(root).or_insert_with(|| {
+            clusters.push((root, Vec::new()));
+            clusters.len() - 1
+        });
+        clusters[index].1.push(candidate.id.clone());
+    }
+
+    clusters
+        .into_iter()
+        .filter(|(_, ids)| ids.len() > 1)
+        .map(|(root, ids)| (ids, best_score[root]))
+        .collect()
+}
+
+/// Read `width` bits starting at `bit_pos` (LSB-first packing).
+///
+/// The final value may straddle the end of the data; its missing high
+/// bits are zero padding. Returns None only if `bit_pos` itself is past
+/// the end.
+fn read_packed(data: &[u8], bit_pos: usize, width: usize) -> Option<u32> {
+    if bit_pos / 8 >= data.len() {
+        return None;
+    }
+    let mut value = 0u32;
+    for i in 0..width {
+        let pos = bit_pos + i;
+        let byte = data.get(pos / 8).copied().unwrap_or(0);
+        if (byte >> (pos % 8)) & 1 == 1 {
+            value |= 1 << i;
+        }
+    }
+    Some(value)
+}
+
+/// Decode unpadded URL-safe base64, as used by Chromaprint.
+fn decode_base64_url(input: &str) -> Result<Vec<u8>, String> {
+    let mut output = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in input.trim_end_matches('=').bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'-' | b'+' => 62,
+            b'_' | b'/' => 63,
+            _ => return Err(format!("Invalid fingerprint character: {}", c as char)),
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Ok(output)
+}
@@ -4,14 +4,25 @@
 //! - Ubuntu/Debian: sudo apt install fpcalc
 //! - macOS: brew install fpcalc
 //! - Windows: Download from https://acoustid.org/chromaprint
+//!
+//! The Chromaprint algorithm is pinned (`-algorithm`) so fingerprints stay
+//! comparable across fpcalc upgrades; the algorithm and fpcalc version are
+//! recorded with every stored fingerprint.
+//!
+//! Hi-res sources (above 48 kHz) are resampled to Chromaprint's native
+//! 11025 Hz first (see `resample_service`), which improves AcoustID matches.
 use std::path::Path;
 use std::env::var;
 use std::process::Command;
 use std::time::Duration;
 
+use once_cell::sync::Lazy;
 use tokio::time::sleep;
 
-use crate::models::{MetadataStatus, ProcessedAudioFingerprint};
+use crate::models::{
+    AcoustIdSubmissionResult, AudioMetadata, MetadataStatus, ProcessedAudioFingerprint,
+};
+use crate::services::resample_service::resample_for_fingerprint;
 
 /// Delay between API calls to stay under the 3/second rate limit
 /// Using 500ms = 2 requests/second for safety margin
@@ -23,6 +34,17 @@ const RETRY_DELAY_MS: u64 = 1000;
 /// Maximum number of retry attempts
 const MAX_RETRIES: u32 = 1;
 
+/// Chromaprint algorithm passed to fpcalc. 2 is fpcalc's default and the
+/// one AcoustID expects; pinning it means a new fpcalc default can't
+/// silently change what we store.
+pub const CHROMAPRINT_ALGORITHM: u32 = 2;
+
+/// Seconds of audio fingerprinted (fpcalc `-length`)
+const FINGERPRINT_LENGTH_SECS: u32 = 30;
+
+/// fpcalc version string, queried once per run.
+static FPCALC_VERSION: Lazy<Option<String>> = Lazy::new(query_fpcalc_version);
+
 /// Output format from fpcalc command
 #[derive(Debug, serde::Deserialize)]
 struct FpcalcOutput {
@@ -160,6 +182,15 @@ async fn lookup_acoustid_once(
     Ok(json)
 }
 
+/// The AcoustID client key: baked in at build time, or read from the
+/// environment at runtime (development). `None` if neither is set.
+pub fn acoustid_api_key() -> Option<String> {
+    option_env!("ACOUSTIC_ID_API_KEY")
+        .map(str::to_string)
+        .or_else(|| var("ACOUSTIC_ID_API_KEY").ok())
+        .filter(|key| !key.trim().is_empty())
+}
+
 /// Lookup fingerprint in AcoustID database with retry logic.
 ///
 /// - Retries once after 1 second if the API returns a retriable error
@@ -175,14 +206,9 @@ pub async fn lookup_acoustid(
         fingerprint_result.duration_seconds
     );
 
-    // // DeVELOPMENT  
-    // let api_key = var("ACOUSTIC_ID_API_KEY").map_err(|e| {
-    //     log::error!("ACOUSTIC_ID_API_KEY environment variable not set: {}", e);
-    //     AcoustIdLookupError::ConfigError("ACOUSTIC_ID_API_KEY not set".to_string())
-    // })?;
-
-    // PROD
-    let api_key = env!("ACOUSTIC_ID_API_KEY");
+    let api_key = acoustid_api_key().ok_or_else(|| {
+        AcoustIdLookupError::ConfigError("ACOUSTIC_ID_API_KEY not set".to_string())
+    })?;
 
     let client = reqwest::Client::new();
 
@@ -222,6 +248,109 @@ pub async fn lookup_acoustid(
     }))
 }
 
+/// Build the form parameters for an AcoustID submission.
+///
+/// Only fields we actually know are sent; AcoustID treats all metadata
+/// as optional hints alongside the fingerprint and duration.
+pub fn build_submission_params(
+    fingerprint_result: &ProcessedAudioFingerprint,
+    metadata: &AudioMetadata,
+    api_key: &str,
+    user_key: &str,
+) -> Vec<(String, String)> {
+    let mut params: Vec<(String, String)> = vec![
+        ("client".to_string(), api_key.to_string()),
+        ("user".to_string(), user_key.to_string()),
+        ("format".to_string(), "json".to_string()),
+        (
+            "duration.0".to_string(),
+            fingerprint_result.duration_seconds.to_string(),
+        ),
+        (
+            "fingerprint.0".to_string(),
+            fingerprint_result.fingerprint_id.clone(),
+        ),
+    ];
+
+    let optional = [
+        ("track.0", metadata.title.clone()),
+        ("artist.0", metadata.artist.clone()),
+        ("album.0", metadata.album.clone()),
+        ("trackno.0", metadata.track_number.map(|n| n.to_string())),
+        ("year.0", metadata.year.map(|y| y.to_string())),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value.filter(|v| !v.trim().is_empty()) {
+            params.push((key.to_string(), value));
+        }
+    }
+
+    params
+}
+
+/// Submit a fingerprint and user-confirmed metadata to AcoustID.
+///
+/// Requires the user's own AcoustID user key. Submissions are queued by
+/// AcoustID and processed asynchronously, so the status is usually "pending".
+pub async fn submit_acoustid(
+    fingerprint_result: &ProcessedAudioFingerprint,
+    metadata: &AudioMetadata,
+    user_key: &str,
+) -> Result<AcoustIdSubmissionResult, AcoustIdLookupError> {
+    let api_key = acoustid_api_key().ok_or_else(|| {
+        AcoustIdLookupError::ConfigError("ACOUSTIC_ID_API_KEY not set".to_string())
+    })?;
+    let params = build_submission_params(fingerprint_result, metadata, &api_key, user_key);
+
+    log::info!("Sending POST request to https://api.acoustid.org/v2/submit");
+
+    let res = reqwest::Client::new()
+        .post("https://api.acoustid.org/v2/submit")
+        .form(&params)
+        .send()
+        .await
+        .map_err(|e| AcoustIdLookupError::RequestError(e.to_string()))?;
+
+    let status = res.status();
+    let response_text = res
+        .text()
+        .await
+        .map_err(|e| AcoustIdLookupError::RequestError(format!("Failed to read response: {}", e)))?;
+
+    let json: serde_json::Value = serde_json::from_str(&response_text).map_err(|e| {
+        AcoustIdLookupError::ParseError(format!("Failed to parse JSON: {}", e))
+    })?;
+
+    if !status.is_success() || json.get("status").and_then(|s| s.as_str()) == Some("error") {
+        let error_info: AcoustIdErrorResponse =
+            serde_json::from_value(json).unwrap_or(AcoustIdErrorResponse {
+                status: "error".to_string(),
+                error: None,
+            });
+        let code = error_info.error.as_ref().and_then(|e| e.code);
+        let message = error_info
+            .error
+            .and_then(|e| e.message)
+            .unwrap_or_else(|| format!("HTTP {}", status.as_u16()));
+        log::error!("AcoustID submission error: code={:?}, message={}", code, message);
+        return Err(AcoustIdLookupError::ApiError { code, message });
+    }
+
+    let submission = json
+        .get("submissions")
+        .and_then(|s| s.as_array())
+        .and_then(|s| s.first());
+
+    Ok(AcoustIdSubmissionResult {
+        submission_id: submission.and_then(|s| s.get("id")).and_then(|id| id.as_u64()),
+        status: submission
+            .and_then(|s| s.get("status"))
+            .and_then(|s| s.as_str())
+            .unwrap_or("pending")
+            .to_string(),
+    })
+}
+
 /// Enforce rate limiting by sleeping for the configured delay.
 /// Call this before each API request when processing multiple files.
 pub async fn rate_limit_delay() {
@@ -231,12 +360,35 @@ pub async fn rate_limit_delay() {
 
 fn inner_process_audio_fingerprint<P: AsRef<Path>>(path: P) -> anyhow::Result<(String, u32)> {
     let path_ref = path.as_ref();
+
+    // Resampling stage for hi-res sources; any failure falls back to fpcalc
+    match resample_for_fingerprint(path_ref, FINGERPRINT_LENGTH_SECS) {
+        Ok(Some(resampled)) => {
+            let (fingerprint, wav_duration) = run_fpcalc(&resampled.path)?;
+            // The temp WAV only holds the fingerprint window
+            let duration = if resampled.source_duration_secs > 0 {
+                resampled.source_duration_secs
+            } else {
+                wav_duration
+            };
+            return Ok((fingerprint, duration));
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("Resampling failed, fingerprinting source directly: {}", e),
+    }
+
+    run_fpcalc(path_ref)
+}
+
+fn run_fpcalc(path_ref: &Path) -> anyhow::Result<(String, u32)> {
     log::info!("Running fpcalc on file: {:?}", path_ref);
 
     let output = Command::new("fpcalc")
         .arg("-json")
+        .arg("-algorithm")
+        .arg(CHROMAPRINT_ALGORITHM.to_string())
         .arg("-length")
-        .arg("30")
+        .arg(FINGERPRINT_LENGTH_SECS.to_string())
         .arg(path_ref)
         .output()
         .map_err(|e| {
@@ -273,6 +425,32 @@ fn inner_process_audio_fingerprint<P: AsRef<Path>>(path: P) -> anyhow::Result<(S
     Ok((fpcalc_result.fingerprint, fpcalc_result.duration as u32))
 }
 
+/// Fingerprint a file, returning (fingerprint, duration in seconds).
+pub fn fingerprint_file<P: AsRef<Path>>(path: P) -> Result<(String, u32), String> {
+    inner_process_audio_fingerprint(path).map_err(|e| e.to_string())
+}
+
+/// The installed fpcalc version (e.g. "1.5.1"), if fpcalc is available.
+pub fn fpcalc_version() -> Option<String> {
+    FPCALC_VERSION.clone()
+}
+
+/// Run `fpcalc -version` and parse "fpcalc version 1.5.1 (...)".
+fn query_fpcalc_version() -> Option<String> {
+    let output = Command::new("fpcalc").arg("-version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = stdout
+        .split_whitespace()
+        .skip_while(|w| *w != "version")
+        .nth(1)
+        .map(|v| v.to_string());
+    log::info!("Detected fpcalc version: {:?}", version);
+    version
+}
+
 pub fn process_audio_fingerprint<P: AsRef<Path>>(
     path: P,
     tracking_id: String,
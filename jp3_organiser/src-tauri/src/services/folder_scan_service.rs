@@ -0,0 +1,351 @@
+//! Folder scanning and structure inference for bulk imports.
+//!
+//! Most existing collections are organised as `Artist/Album/Track.ext`.
+//! This service walks such a tree and infers metadata from the path so that
+//! files with missing or partial tags can still be imported sensibly.
+//!
+//! # Inference Rules
+//! - `Artist/Album/01 - Title.mp3` -> artist, album, track 1, title
+//! - `Album/01 Title.mp3` -> album, track 1, title
+//! - `Artist/Album/CD1/01 Title.mp3` -> disc folders are skipped over
+//! - `Artist/1979 - The Wall/...` or `The Wall (1979)` -> album + year
+//!
+//! Tags always take precedence; the path only fills fields the tags lack.
+//!
+//! # Incremental Rescans
+//! After an import, the size, mtime and content hash of each imported file
+//! are recorded in `jp3/metadata/scan_state.json`. A rescan compares against
+//! this record: size or hash changes count as changed, and a bumped mtime
+//! with identical contents (e.g. a `touch`) does not.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use sha2::{Digest, Sha256};
+
+use crate::models::{AudioMetadata, FolderScanState, ScannedFileState};
+
+/// Rescan state file name, stored in the library metadata folder
+const SCAN_STATE_FILE: &str = "scan_state.json";
+
+/// Audio extensions the import pipeline can handle.
+pub const SUPPORTED_AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "flac", "m4a", "ogg", "opus"];
+
+/// Recursively collect audio files under `root`.
+///
+/// Returns `(supported, skipped)` where `skipped` lists files with
+/// extensions we cannot import. Hidden files and folders are ignored.
+/// Both lists are sorted so scans are deterministic.
+pub fn collect_audio_files(root: &Path) -> Result<(Vec<PathBuf>, Vec<String>), String> {
+    let mut supported = Vec::new();
+    let mut skipped = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = fs::read_dir(&dir)
+            .map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_hidden = entry
+                .file_name()
+                .to_str()
+                .map(|n| n.starts_with('.'))
+                .unwrap_or(false);
+            if is_hidden {
+                continue;
+            }
+
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.is_file() {
+                if is_supported_audio_file(&path) {
+                    supported.push(path);
+                } else {
+                    skipped.push(path.to_string_lossy().to_string());
+                }
+            }
+        }
+    }
+
+    supported.sort();
+    skipped.sort();
+    Ok((supported, skipped))
+}
+
+/// Check whether a path has a supported audio extension.
+pub fn is_supported_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| SUPPORTED_AUDIO_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Infer metadata from a file's position in the folder tree.
+///
+/// `root` is the scanned folder; only path components below it are used,
+/// so the user's own directory names (e.g. `/home/me/Music`) never leak in.
+pub fn infer_metadata_from_path(root: &Path, file: &Path) -> AudioMetadata {
+    let relative = file.strip_prefix(root).unwrap_or(file);
+
+    let mut dirs: Vec<String> = relative
+        .parent()
+        .map(|p| {
+            p.components()
+                .filter_map(|c| c.as_os_str().to_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Skip over "CD1" / "Disc 2" style folders so the album is the parent
+    if dirs.last().map(|d| is_disc_folder(d)).unwrap_or(false) {
+        dirs.pop();
+    }
+
+    let (album, year) = match dirs.last() {
+        Some(name) => {
+            let (album, year) = parse_album_folder(name);
+            (Some(album), year)
+        }
+        None => (None, None),
+    };
+    let artist = if dirs.len() >= 2 {
+        Some(dirs[dirs.len() - 2].trim().to_string())
+    } else {
+        None
+    };
+
+    let stem = file
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let (track_number, title) = parse_track_filename(stem);
+
+    AudioMetadata {
+        title: non_empty(title),
+        artist: artist.and_then(non_empty),
+        album: album.and_then(non_empty),
+        track_number,
+        year,
+        ..Default::default()
+    }
+}
+
+/// Fill any missing fields in `metadata` from `inferred`.
+///
+/// Returns true if at least one field was filled from the path.
+pub fn fill_missing_from_inferred(metadata: &mut AudioMetadata, inferred: &AudioMetadata) -> bool {
+    let mut filled = false;
+
+    if metadata.title.is_none() && inferred.title.is_some() {
+        metadata.title = inferred.title.clone();
+        filled = true;
+    }
+    if metadata.artist.is_none() && inferred.artist.is_some() {
+        metadata.artist = inferred.artist.clone();
+        filled = true;
+    }
+    if metadata.album.is_none() && inferred.album.is_some() {
+        metadata.album = inferred.album.clone();
+        filled = true;
+    }
+    if metadata.track_number.is_none() && inferred.track_number.is_some() {
+        metadata.track_number = inferred.track_number;
+        filled = true;
+    }
+    if metadata.year.is_none() && inferred.year.is_some() {
+        metadata.year = inferred.year;
+        filled = true;
+    }
+
+    filled
+}
+
+/// Parse a track filename stem into (track number, title).
+///
+/// Handles `01 - Title`, `01. Title`, `01 Title`, `1-01 Title` (disc-track)
+/// and plain `Title`.
+fn parse_track_filename(stem: &str) -> (Option<u32>, String) {
+    let stem = stem.trim();
+    let digits: String = stem.chars().take_while(|c| c.is_ascii_digit()).collect();
+
+    // Only treat short numeric prefixes as track numbers ("1984.mp3" is a title)
+    if digits.is_empty() || digits.len() > 3 || digits.len() == stem.len() {
+        return (None, stem.to_string());
+    }
+
+    let mut rest = &stem[digits.len()..];
+    let mut track: Option<u32> = digits.parse().ok();
+
+    // "1-01 Title": the first number was the disc, the second is the track
+    if let Some(after_dash) = rest.strip_prefix('-') {
+        let second: String = after_dash
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        if !second.is_empty() && second.len() <= 3 {
+            track = second.parse().ok();
+            rest = &after_dash[second.len()..];
+        }
+    }
+
+    // A number glued to letters ("3rd Stone") is part of the title
+    if !rest.starts_with([' ', '-', '.', '_']) {
+        return (None, stem.to_string());
+    }
+
+    let title = rest
+        .trim_start_matches([' ', '-', '.', '_'])
+        .trim()
+        .to_string();
+    (track, title)
+}
+
+/// Parse an album folder name, extracting a year if present.
+///
+/// Recognises `1979 - The Wall`, `The Wall (1979)` and `The Wall [1979]`.
+fn parse_album_folder(name: &str) -> (String, Option<i32>) {
+    let name = name.trim();
+
+    // "1979 - The Wall"
+    if name.len() > 7 && name.is_char_boundary(4) {
+        if let Ok(year) = name[..4].parse::<i32>() {
+            let rest = name[4..].trim_start();
+            if is_plausible_year(year) && rest.starts_with(['-', '.', '_']) {
+                let album = rest.trim_start_matches(['-', '.', '_']).trim();
+                return (album.to_string(), Some(year));
+            }
+        }
+    }
+
+    // "The Wall (1979)" / "The Wall [1979]"
+    for (open, close) in [('(', ')'), ('[', ']')] {
+        if name.ends_with(close) {
+            if let Some(start) = name.rfind(open) {
+                let inner = &name[start + 1..name.len() - 1];
+                if let Ok(year) = inner.trim().parse::<i32>() {
+                    if is_plausible_year(year) {
+                        return (name[..start].trim().to_string(), Some(year));
+                    }
+                }
+            }
+        }
+    }
+
+    (name.to_string(), None)
+}
+
+/// Check whether a folder name looks like a disc subfolder ("CD1", "Disc 2").
+fn is_disc_folder(name: &str) -> bool {
+    let lower = name.trim().to_lowercase();
+    ["cd", "disc", "disk"].iter().any(|prefix| {
+        lower
+            .strip_prefix(prefix)
+            .map(|rest| {
+                let rest = rest.trim_start_matches([' ', '-', '_']);
+                !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit())
+            })
+            .unwrap_or(false)
+    })
+}
+
+fn is_plausible_year(year: i32) -> bool {
+    (1900..=2100).contains(&year)
+}
+
+fn non_empty(s: String) -> Option<String> {
+    if s.trim().is_empty() {
+        None
+    } else {
+        Some(s)
+    }
+}
+
+/// Path to the rescan state file for a library.
+fn scan_state_path(base_path: &Path) -> PathBuf {
+    base_path.join("jp3").join("metadata").join(SCAN_STATE_FILE)
+}
+
+/// Load the rescan state for a library, or an empty state if none exists.
+pub fn load_scan_state(base_path: &Path) -> Result<FolderScanState, String> {
+    let path = scan_state_path(base_path);
+    if !path.exists() {
+        return Ok(FolderScanState::default());
+    }
+    let data = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read scan state: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse scan state: {}", e))
+}
+
+/// Persist the rescan state for a library.
+pub fn save_scan_state(base_path: &Path, state: &FolderScanState) -> Result<(), String> {
+    let path = scan_state_path(base_path);
+    let data = serde_json::to_string_pretty(state)
+        .map_err(|e| format!("Failed to serialize scan state: {}", e))?;
+    fs::write(&path, data).map_err(|e| format!("Failed to write scan state: {}", e))
+}
+
+/// Hash a file's contents with SHA-256, returning a lowercase hex string.
+pub fn hash_file(path: &Path) -> Result<String, String> {
+    let mut file = fs::File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Size and modification time of a file, without hashing it.
+fn file_size_and_mtime(path: &Path) -> Result<(u64, u64), String> {
+    let meta = fs::metadata(path)
+        .map_err(|e| format!("Failed to stat {}: {}", path.display(), e))?;
+    let modified_secs = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok((meta.len(), modified_secs))
+}
+
+/// Capture the current state of a file (size, mtime and content hash).
+pub fn capture_file_state(path: &Path) -> Result<ScannedFileState, String> {
+    let (size, modified_secs) = file_size_and_mtime(path)?;
+    Ok(ScannedFileState {
+        size,
+        modified_secs,
+        hash: hash_file(path)?,
+    })
+}
+
+/// Check whether a file is new or has changed since its recorded state.
+///
+/// Only hashes when the size matches but the mtime moved, so unchanged
+/// files are skipped without reading their contents.
+pub fn has_file_changed(path: &Path, previous: Option<&ScannedFileState>) -> bool {
+    let previous = match previous {
+        Some(p) => p,
+        None => return true,
+    };
+    let (size, modified_secs) = match file_size_and_mtime(path) {
+        Ok(v) => v,
+        Err(_) => return true,
+    };
+    if size != previous.size {
+        return true;
+    }
+    if modified_secs == previous.modified_secs {
+        return false;
+    }
+    hash_file(path).map(|h| h != previous.hash).unwrap_or(true)
+}
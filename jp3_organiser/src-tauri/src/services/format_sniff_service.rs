@@ -0,0 +1,286 @@
+//! Audio format detection from file headers.
+//!
+//! Extensions lie: an AAC file renamed to `.mp3` passes every extension
+//! check and then fails to play on the device. We read the first few bytes
+//! of each file instead and compare the real format with the extension.
+//!
+//! # Recognised Signatures
+//! - `fLaC` -> FLAC
+//! - `RIFF....WAVE` -> WAV
+//! - `OggS` -> Ogg, or Opus if the first page carries `OpusHead`
+//! - `....ftyp` -> MP4/M4A
+//! - MPEG frame sync (`0xFFE`) -> MP3, or AAC when the layer bits are 0 (ADTS)
+//!
+//! A leading ID3v2 tag is skipped before checking, since both MP3 and raw
+//! AAC files commonly carry one.
+//!
+//! # Copy Protection
+//! DRM files decode to garbage or fail late, so they are flagged up front:
+//! - MP4 with an `M4P ` brand or a `drms` sample entry -> FairPlay
+//! - MP4 with an `aavd` sample entry, or the `.aa` magic -> Audible
+//! - MP4 with an `enca` sample entry -> Common Encryption
+//! - ASF (WMA) with a content encryption object -> Windows Media DRM
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::models::{AudioFormat, MetadataStatus, TrackedAudioFile};
+
+/// Bytes read for signature checks (enough for the Ogg `OpusHead` marker)
+const SNIFF_LEN: usize = 64;
+
+/// ID3v2 header length
+const ID3_HEADER_LEN: usize = 10;
+
+/// Largest MP4 `moov` or ASF header read when checking for DRM
+const MAX_DRM_SCAN_LEN: u64 = 16 * 1024 * 1024;
+
+/// Audible `.aa` files carry this magic at offset 4
+const AUDIBLE_AA_MAGIC: [u8; 4] = [0x57, 0x90, 0x75, 0x36];
+
+/// ASF Header Object GUID (starts every WMA/WMV file)
+const ASF_HEADER_GUID: [u8; 16] = [
+    0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11, 0xA6, 0xD9, 0x00, 0xAA, 0x00, 0x62, 0xCE, 0x6C,
+];
+
+/// ASF Content Encryption and Extended Content Encryption Object GUIDs
+const ASF_ENCRYPTION_GUIDS: [[u8; 16]; 2] = [
+    [
+        0xFB, 0xB3, 0x11, 0x22, 0x23, 0xBD, 0xD2, 0x11, 0xB4, 0xB7, 0x00, 0xA0, 0xC9, 0x55, 0xFC,
+        0x6E,
+    ],
+    [
+        0x14, 0xE6, 0x8A, 0x29, 0x22, 0x26, 0x17, 0x4C, 0xB9, 0x35, 0xDA, 0xE0, 0x7E, 0xE9, 0x28,
+        0x9C,
+    ],
+];
+
+/// Copy protection found in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrmScheme {
+    /// iTunes Store purchases before 2009 (`.m4p`)
+    FairPlay,
+    /// Audible audiobooks (`.aa`, `.aax`)
+    Audible,
+    /// Encrypted MP4 (`enca`), e.g. streaming service downloads
+    CommonEncryption,
+    /// Protected WMA
+    WindowsMedia,
+}
+
+impl DrmScheme {
+    pub fn name(self) -> &'static str {
+        match self {
+            DrmScheme::FairPlay => "Apple FairPlay",
+            DrmScheme::Audible => "Audible",
+            DrmScheme::CommonEncryption => "encrypted MP4",
+            DrmScheme::WindowsMedia => "Windows Media DRM",
+        }
+    }
+}
+
+/// Detect the real format of a file from its header.
+///
+/// Returns `Ok(None)` if the header matches no format we know.
+pub fn sniff_audio_format(path: &Path) -> Result<Option<AudioFormat>, String> {
+    let mut file =
+        File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    let mut header = read_up_to(&mut file, SNIFF_LEN)?;
+
+    // Skip a leading ID3v2 tag and look at what follows it
+    if let Some(tag_len) = id3v2_tag_len(&header) {
+        file.seek(SeekFrom::Start(tag_len))
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        header = read_up_to(&mut file, SNIFF_LEN)?;
+    }
+
+    Ok(format_from_header(&header))
+}
+
+/// Total length of a leading ID3v2 tag (header, body and footer), if any.
+pub fn id3v2_tag_len(header: &[u8]) -> Option<u64> {
+    if header.len() < ID3_HEADER_LEN || !header.starts_with(b"ID3") {
+        return None;
+    }
+    let tag_size = header[6..10]
+        .iter()
+        .fold(0u64, |acc, b| (acc << 7) | (*b & 0x7F) as u64);
+    let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+    Some(ID3_HEADER_LEN as u64 + tag_size + footer)
+}
+
+/// Detect copy protection that would stop a file playing on the device.
+pub fn detect_drm(path: &Path) -> Result<Option<DrmScheme>, String> {
+    let mut file =
+        File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let header = read_up_to(&mut file, SNIFF_LEN)?;
+
+    if header.get(4..8) == Some(&AUDIBLE_AA_MAGIC[..]) {
+        return Ok(Some(DrmScheme::Audible));
+    }
+    if header.starts_with(&ASF_HEADER_GUID) {
+        return asf_drm(&mut file, &header)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e));
+    }
+    if header.get(4..8) == Some(b"ftyp") {
+        if header.get(8..12) == Some(b"M4P ") {
+            return Ok(Some(DrmScheme::FairPlay));
+        }
+        return mp4_drm(&mut file).map_err(|e| format!("Failed to read {}: {}", path.display(), e));
+    }
+    Ok(None)
+}
+
+/// Look for a protected sample entry in the MP4 `moov` box.
+fn mp4_drm(file: &mut File) -> std::io::Result<Option<DrmScheme>> {
+    let file_len = file.metadata()?.len();
+    let mut offset = 0u64;
+    while offset + 8 <= file_len {
+        file.seek(SeekFrom::Start(offset))?;
+        let mut box_header = [0u8; 16];
+        let read = file.read(&mut box_header)?;
+        if read < 8 {
+            break;
+        }
+        let (size, header_len) = match u32::from_be_bytes(box_header[..4].try_into().unwrap()) {
+            0 => (file_len - offset, 8),
+            1 if read >= 16 => (u64::from_be_bytes(box_header[8..16].try_into().unwrap()), 16),
+            size => (size as u64, 8),
+        };
+        if size < header_len {
+            break;
+        }
+        if &box_header[4..8] == b"moov" {
+            file.seek(SeekFrom::Start(offset + header_len))?;
+            let mut moov = Vec::new();
+            file.take((size - header_len).min(MAX_DRM_SCAN_LEN))
+                .read_to_end(&mut moov)?;
+            let scheme = [
+                (b"drms", DrmScheme::FairPlay),
+                (b"aavd", DrmScheme::Audible),
+                (b"enca", DrmScheme::CommonEncryption),
+            ]
+            .into_iter()
+            .find(|(entry, _)| contains(&moov, *entry))
+            .map(|(_, scheme)| scheme);
+            return Ok(scheme);
+        }
+        offset += size;
+    }
+    Ok(None)
+}
+
+/// Look for a content encryption object in the ASF header.
+fn asf_drm(file: &mut File, header: &[u8]) -> std::io::Result<Option<DrmScheme>> {
+    let Some(size) = header.get(16..24) else {
+        return Ok(None);
+    };
+    let size = u64::from_le_bytes(size.try_into().unwrap());
+    file.seek(SeekFrom::Start(0))?;
+    let mut asf_header = Vec::new();
+    file.take(size.min(MAX_DRM_SCAN_LEN))
+        .read_to_end(&mut asf_header)?;
+    let protected = ASF_ENCRYPTION_GUIDS
+        .iter()
+        .any(|guid| contains(&asf_header, guid));
+    Ok(protected.then_some(DrmScheme::WindowsMedia))
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+/// Match header bytes against known signatures.
+pub fn format_from_header(header: &[u8]) -> Option<AudioFormat> {
+    if header.starts_with(b"fLaC") {
+        return Some(AudioFormat::Flac);
+    }
+    if header.len() >= 12 && header.starts_with(b"RIFF") && &header[8..12] == b"WAVE" {
+        return Some(AudioFormat::Wav);
+    }
+    if header.starts_with(b"OggS") {
+        let is_opus = header.len() >= 36 && &header[28..36] == b"OpusHead";
+        return Some(if is_opus { AudioFormat::Opus } else { AudioFormat::Ogg });
+    }
+    if header.len() >= 8 && &header[4..8] == b"ftyp" {
+        return Some(AudioFormat::Mp4);
+    }
+    if header.len() >= 2 && header[0] == 0xFF && header[1] & 0xE0 == 0xE0 {
+        // Layer bits 00 are reserved for MPEG audio; ADTS uses them for AAC
+        return match (header[1] >> 1) & 0x03 {
+            0 => Some(AudioFormat::Aac),
+            _ => Some(AudioFormat::Mp3),
+        };
+    }
+    None
+}
+
+/// Sniff a tracked file and record its real format.
+///
+/// Sets `detected_format`, `needs_transcode` and, when the extension
+/// disagrees with the content, `format_warning`. Empty files are marked
+/// as errors and copy-protected ones as `ProtectedFile`. Returns the
+/// extension processing should be routed by: the detected format's if
+/// known, otherwise the file's own.
+pub fn apply_detected_format(tracked_file: &mut TrackedAudioFile) -> String {
+    if tracked_file.file_size == 0 && Path::new(&tracked_file.file_path).is_file() {
+        tracked_file.metadata_status = MetadataStatus::Error;
+        tracked_file.error_message = Some("File is empty".to_string());
+        return tracked_file.file_extension.clone();
+    }
+
+    match detect_drm(Path::new(&tracked_file.file_path)) {
+        Ok(Some(scheme)) => {
+            log::warn!("{}: protected by {}", tracked_file.file_path, scheme.name());
+            tracked_file.metadata_status = MetadataStatus::ProtectedFile;
+            tracked_file.error_message = Some(format!(
+                "This file is copy-protected ({}) and can't be played on the device. \
+                 Import a DRM-free copy instead.",
+                scheme.name()
+            ));
+            return tracked_file.file_extension.clone();
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("Could not check for DRM: {}", e),
+    }
+
+    let detected = match sniff_audio_format(Path::new(&tracked_file.file_path)) {
+        Ok(format) => format,
+        Err(e) => {
+            log::warn!("Could not sniff format: {}", e);
+            None
+        }
+    };
+    tracked_file.detected_format = detected;
+
+    let route_extension = match detected {
+        Some(format) => {
+            if !format.matches_extension(&tracked_file.file_extension) {
+                let warning = format!(
+                    "File has a .{} extension but contains {} audio",
+                    tracked_file.file_extension,
+                    format.extension().to_uppercase()
+                );
+                log::warn!("{}: {}", tracked_file.file_path, warning);
+                tracked_file.format_warning = Some(warning);
+            }
+            format.extension().to_string()
+        }
+        None => tracked_file.file_extension.clone(),
+    };
+
+    tracked_file.needs_transcode = route_extension != "mp3";
+    route_extension
+}
+
+/// Read up to `len` bytes, stopping early at end of file.
+fn read_up_to(file: &mut File, len: usize) -> Result<Vec<u8>, String> {
+    let mut buffer = Vec::with_capacity(len);
+    file.by_ref()
+        .take(len as u64)
+        .read_to_end(&mut buffer)
+        .map_err(|e| format!("Failed to read header: {}", e))?;
+    Ok(buffer)
+}
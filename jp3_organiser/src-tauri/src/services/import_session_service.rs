@@ -0,0 +1,353 @@
+//! Session-scoped import state.
+//!
+//! Processing results used to live only in the frontend, so every follow-up
+//! command (grouping, saving, editing) had to re-pass paths and re-read
+//! files. An `ImportSession` keeps tracked files, fingerprints and AcoustID
+//! candidates in managed Tauri state, keyed by session ID, and commands
+//! address files by tracking ID instead.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+
+use uuid::Uuid;
+
+use crate::models::{
+    AlbumFieldConflict, AudioMetadata, MetadataSource, MetadataStatus, SessionAlbumGroup,
+    TrackedAudioFile,
+};
+use crate::services::sort_name_service::fold_text;
+
+/// Processing state for one import (one upload flow in the UI).
+#[derive(Debug, Default)]
+pub struct ImportSession {
+    /// Tracking IDs in the order files were added
+    pub order: Vec<String>,
+    /// Tracked files by tracking ID
+    pub files: HashMap<String, TrackedAudioFile>,
+    /// (fingerprint, duration) by tracking ID, for files fpcalc succeeded on
+    pub fingerprints: HashMap<String, (String, u32)>,
+    /// Raw AcoustID lookup responses by tracking ID (all candidate recordings)
+    pub candidates: HashMap<String, serde_json::Value>,
+    /// Tracking IDs the user has confirmed for saving (see
+    /// `confirm_all_complete`); editing a file's metadata unconfirms it
+    pub confirmed: HashSet<String>,
+}
+
+impl ImportSession {
+    /// Insert (or replace) a processed file, keeping first-seen order.
+    pub fn insert_file(&mut self, file: TrackedAudioFile) {
+        if !self.files.contains_key(&file.tracking_id) {
+            self.order.push(file.tracking_id.clone());
+        }
+        self.files.insert(file.tracking_id.clone(), file);
+    }
+
+    /// Remove a file and everything known about it. Returns true if it existed.
+    pub fn remove_file(&mut self, tracking_id: &str) -> bool {
+        self.order.retain(|id| id != tracking_id);
+        self.fingerprints.remove(tracking_id);
+        self.candidates.remove(tracking_id);
+        self.confirmed.remove(tracking_id);
+        self.files.remove(tracking_id).is_some()
+    }
+
+    /// Files in insertion order, optionally limited to the given tracking IDs.
+    pub fn ordered_files(&self, tracking_ids: Option<&[String]>) -> Vec<TrackedAudioFile> {
+        self.order
+            .iter()
+            .filter(|id| tracking_ids.map(|ids| ids.contains(id)).unwrap_or(true))
+            .filter_map(|id| self.files.get(id).cloned())
+            .collect()
+    }
+
+    /// Confirmed tracking IDs, in session order.
+    pub fn confirmed_ids(&self) -> Vec<String> {
+        self.order
+            .iter()
+            .filter(|id| self.confirmed.contains(*id))
+            .cloned()
+            .collect()
+    }
+
+    /// Replace a file's metadata with user-entered values.
+    ///
+    /// The file needs confirming again. Returns the updated file.
+    pub fn update_file_metadata(
+        &mut self,
+        tracking_id: &str,
+        metadata: AudioMetadata,
+    ) -> Result<TrackedAudioFile, String> {
+        let file = self
+            .files
+            .get_mut(tracking_id)
+            .ok_or_else(|| format!("File not found in session: {}", tracking_id))?;
+        file.metadata = metadata;
+        file.metadata_source = MetadataSource::Manual;
+        file.confidence = None;
+        file.error_message = None;
+        file.update_status();
+        self.confirmed.remove(tracking_id);
+        Ok(file.clone())
+    }
+
+    /// Copy album-level metadata (artist, album artist, album, year and release MBID used
+    /// for the cover) from one file onto others, keeping each target's title
+    /// and track number.
+    ///
+    /// Fails without changing anything if any tracking ID is unknown. The
+    /// targets need confirming again. Returns them in session order.
+    pub fn propagate_album_metadata(
+        &mut self,
+        template_tracking_id: &str,
+        target_tracking_ids: &[String],
+    ) -> Result<Vec<TrackedAudioFile>, String> {
+        let template = self
+            .files
+            .get(template_tracking_id)
+            .map(|file| file.metadata.clone())
+            .ok_or_else(|| format!("File not found in session: {}", template_tracking_id))?;
+        if let Some(missing) = target_tracking_ids
+            .iter()
+            .find(|id| !self.files.contains_key(id.as_str()))
+        {
+            return Err(format!("File not found in session: {}", missing));
+        }
+
+        for tracking_id in target_tracking_ids {
+            if tracking_id == template_tracking_id {
+                continue;
+            }
+            if let Some(file) = self.files.get_mut(tracking_id) {
+                file.metadata.artist = template.artist.clone();
+                file.metadata.album_artist = template.album_artist.clone();
+                file.metadata.album = template.album.clone();
+                file.metadata.year = template.year;
+                file.metadata.release_mbid = template.release_mbid.clone();
+                file.metadata.artist_mbid = template.artist_mbid.clone();
+                file.metadata_source = MetadataSource::Manual;
+                file.update_status();
+                self.confirmed.remove(tracking_id);
+            }
+        }
+
+        Ok(self.ordered_files(Some(target_tracking_ids)))
+    }
+
+    /// Cluster the session's files into probable albums.
+    ///
+    /// Files with both an album artist (or artist) and album are grouped by those names,
+    /// compared case- and accent-insensitively (so a multi-disc album split
+    /// across folders stays together). Files missing either are grouped by
+    /// folder. Groups are returned in the order their first file was added.
+    pub fn group_by_album(&self) -> Vec<SessionAlbumGroup> {
+        let mut keys: Vec<String> = Vec::new();
+        let mut members: HashMap<String, Vec<TrackedAudioFile>> = HashMap::new();
+
+        for file in self.ordered_files(None) {
+            let key = match (file.metadata.album_artist_name(), &file.metadata.album) {
+                (Some(artist), Some(album)) => {
+                    format!("album:{}:{}", group_key(artist), group_key(album))
+                }
+                _ => format!("folder:{}", parent_folder(&file.file_path)),
+            };
+            if !members.contains_key(&key) {
+                keys.push(key.clone());
+            }
+            members.entry(key).or_default().push(file);
+        }
+
+        keys.into_iter()
+            .filter_map(|key| members.remove(&key))
+            .map(album_group)
+            .collect()
+    }
+
+    /// Confirm every file that is complete and unambiguous.
+    ///
+    /// A file qualifies when its metadata is complete, its extension matches
+    /// its real format, and its album group has no conflicting fields.
+    /// Returns the newly confirmed tracking IDs and the files still left
+    /// unconfirmed, both in session order.
+    pub fn confirm_all_complete(&mut self) -> (Vec<String>, Vec<TrackedAudioFile>) {
+        let clean: HashSet<String> = self
+            .group_by_album()
+            .into_iter()
+            .filter(|group| group.conflicts.is_empty())
+            .flat_map(|group| group.files)
+            .filter(|file| {
+                file.metadata_status == MetadataStatus::Complete && file.format_warning.is_none()
+            })
+            .map(|file| file.tracking_id)
+            .collect();
+
+        let mut confirmed_ids = Vec::new();
+        let mut remaining = Vec::new();
+        for file in self.ordered_files(None) {
+            if self.confirmed.contains(&file.tracking_id) {
+                continue;
+            }
+            if clean.contains(&file.tracking_id) {
+                self.confirmed.insert(file.tracking_id.clone());
+                confirmed_ids.push(file.tracking_id);
+            } else {
+                remaining.push(file);
+            }
+        }
+        (confirmed_ids, remaining)
+    }
+}
+
+/// Normalized name used to match album and artist spellings.
+fn group_key(name: &str) -> String {
+    fold_text(name).split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn parent_folder(file_path: &str) -> String {
+    Path::new(file_path)
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Build a group from its files: display names, completeness and conflicts.
+fn album_group(mut files: Vec<TrackedAudioFile>) -> SessionAlbumGroup {
+    files.sort_by(|a, b| {
+        a.metadata
+            .disc_track_key()
+            .cmp(&b.metadata.disc_track_key())
+            .then_with(|| a.file_name.cmp(&b.file_name))
+    });
+
+    let artists = distinct_values(files.iter().map(|f| f.metadata.album_artist_name().cloned()));
+    let albums = distinct_values(files.iter().map(|f| f.metadata.album.clone()));
+    let years = distinct_values(files.iter().map(|f| f.metadata.year.map(|y| y.to_string())));
+    let releases = distinct_values(files.iter().map(|f| f.metadata.release_mbid.clone()));
+
+    let mut conflicts = Vec::new();
+    for (field, values) in [
+        ("artist", &artists),
+        ("album", &albums),
+        ("year", &years),
+        ("releaseMbid", &releases),
+    ] {
+        if values.len() > 1 {
+            conflicts.push(AlbumFieldConflict {
+                field: field.to_string(),
+                values: values.iter().map(|(value, _)| value.clone()).collect(),
+            });
+        }
+    }
+
+    // Track numbers restart on each disc, so duplicates are per disc
+    let mut track_counts: HashMap<(u32, u32), usize> = HashMap::new();
+    for f in &files {
+        if let Some(track) = f.metadata.track_number {
+            let disc = f.metadata.disc_number.unwrap_or(1);
+            *track_counts.entry((disc, track)).or_default() += 1;
+        }
+    }
+    let mut duplicate_tracks: Vec<(u32, u32)> = track_counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(track, _)| track)
+        .collect();
+    if !duplicate_tracks.is_empty() {
+        duplicate_tracks.sort_unstable();
+        conflicts.push(AlbumFieldConflict {
+            field: "trackNumber".to_string(),
+            values: duplicate_tracks
+                .iter()
+                .map(|&(disc, track)| match disc {
+                    1 => track.to_string(),
+                    _ => format!("{}-{}", disc, track),
+                })
+                .collect(),
+        });
+    }
+
+    let folders = distinct_values(files.iter().map(|f| Some(parent_folder(&f.file_path))));
+    let folder = match folders.as_slice() {
+        [(folder, _)] => Some(folder.clone()),
+        _ => None,
+    };
+
+    let complete_count = files
+        .iter()
+        .filter(|f| f.metadata_status == MetadataStatus::Complete)
+        .count();
+
+    SessionAlbumGroup {
+        artist: most_common(&artists),
+        album: most_common(&albums),
+        folder,
+        is_complete: complete_count == files.len() && conflicts.is_empty(),
+        complete_count,
+        conflicts,
+        files,
+    }
+}
+
+/// Distinct non-empty values with their counts, in first-seen order.
+fn distinct_values(values: impl Iterator<Item = Option<String>>) -> Vec<(String, usize)> {
+    let mut distinct: Vec<(String, usize)> = Vec::new();
+    for value in values.flatten() {
+        match distinct.iter_mut().find(|(existing, _)| *existing == value) {
+            Some((_, count)) => *count += 1,
+            None => distinct.push((value, 1)),
+        }
+    }
+    distinct
+}
+
+/// The most frequent value, preferring the first seen on ties.
+fn most_common(values: &[(String, usize)]) -> Option<String> {
+    values
+        .iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(value, _)| value.clone())
+}
+
+/// All open import sessions. Registered with `tauri::Builder::manage`.
+#[derive(Debug, Default)]
+pub struct ImportSessions {
+    sessions: Mutex<HashMap<String, ImportSession>>,
+}
+
+impl ImportSessions {
+    /// Open a new, empty session and return its ID.
+    pub fn create(&self) -> String {
+        let session_id = Uuid::new_v4().to_string();
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.insert(session_id.clone(), ImportSession::default());
+        }
+        session_id
+    }
+
+    /// Run `f` against a session, or error if the session doesn't exist.
+    ///
+    /// The lock is held for the duration of `f`, so never await inside it.
+    pub fn with_session<T>(
+        &self,
+        session_id: &str,
+        f: impl FnOnce(&mut ImportSession) -> T,
+    ) -> Result<T, String> {
+        let mut sessions = self
+            .sessions
+            .lock()
+            .map_err(|_| "Import session state is poisoned".to_string())?;
+        let session = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| format!("Import session not found: {}", session_id))?;
+        Ok(f(session))
+    }
+
+    /// Close a session, dropping its state. Returns true if it existed.
+    pub fn close(&self, session_id: &str) -> bool {
+        self.sessions
+            .lock()
+            .map(|mut sessions| sessions.remove(session_id).is_some())
+            .unwrap_or(false)
+    }
+}
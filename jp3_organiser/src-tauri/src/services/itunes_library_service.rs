@@ -0,0 +1,202 @@
+//! Reading iTunes / Music.app `Library.xml` exports.
+//!
+//! The export is an XML property list: a `Tracks` dictionary keyed by track
+//! ID, and a `Playlists` array whose items point back at those IDs. Each
+//! track's audio is given by a `file://` URL in `Location`.
+//!
+//! # What Is Imported
+//! Music tracks with a local file. Podcasts, videos and tracks that only
+//! live in the cloud (no `Location`) are skipped, as are the built-in
+//! playlists (the library itself, "Music", "Downloaded", ...) and playlist
+//! folders. Smart playlists are imported as a snapshot of their tracks.
+//!
+//! # Ratings
+//! iTunes stores ratings as 0-100 in steps of 20 per star. Ratings it
+//! derived from the album's rating (`Rating Computed`) are ignored.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::models::AudioMetadata;
+use crate::services::folder_scan_service::SUPPORTED_AUDIO_EXTENSIONS;
+
+/// iTunes rating points per star
+const RATING_PER_STAR: u32 = 20;
+
+/// A parsed `Library.xml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ItunesLibrary {
+    /// Where iTunes kept its media, as a `file://` URL
+    #[serde(rename = "Music Folder")]
+    pub music_folder: Option<String>,
+    #[serde(rename = "Tracks", default)]
+    pub tracks: HashMap<String, ItunesTrack>,
+    #[serde(rename = "Playlists", default)]
+    pub playlists: Vec<ItunesPlaylist>,
+}
+
+/// A track entry; only the fields the importer uses.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ItunesTrack {
+    #[serde(rename = "Track ID")]
+    pub track_id: u64,
+    pub name: Option<String>,
+    pub artist: Option<String>,
+    #[serde(rename = "Album Artist")]
+    pub album_artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    #[serde(rename = "Track Number")]
+    pub track_number: Option<u32>,
+    #[serde(rename = "Disc Number")]
+    pub disc_number: Option<u32>,
+    pub year: Option<i32>,
+    /// Length in milliseconds
+    #[serde(rename = "Total Time")]
+    pub total_time: Option<u64>,
+    #[serde(rename = "Play Count")]
+    pub play_count: Option<u32>,
+    /// 0-100
+    pub rating: Option<u32>,
+    #[serde(rename = "Rating Computed", default)]
+    pub rating_computed: bool,
+    /// "Loved" in iTunes, "Favorited" in Music.app
+    #[serde(default)]
+    pub loved: bool,
+    #[serde(default)]
+    pub favorited: bool,
+    pub location: Option<String>,
+    #[serde(default)]
+    pub podcast: bool,
+    #[serde(rename = "Has Video", default)]
+    pub has_video: bool,
+}
+
+/// A playlist entry.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ItunesPlaylist {
+    #[serde(default)]
+    pub name: String,
+    /// The whole library
+    #[serde(default)]
+    pub master: bool,
+    /// Set on built-in playlists (Music, Movies, Downloaded, ...)
+    #[serde(rename = "Distinguished Kind")]
+    pub distinguished_kind: Option<u32>,
+    #[serde(default)]
+    pub folder: bool,
+    #[serde(rename = "Playlist Items", default)]
+    pub items: Vec<ItunesPlaylistItem>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ItunesPlaylistItem {
+    #[serde(rename = "Track ID")]
+    pub track_id: u64,
+}
+
+impl ItunesTrack {
+    /// Whether this is a music track whose file could be imported.
+    pub fn is_importable(&self) -> bool {
+        if self.podcast || self.has_video {
+            return false;
+        }
+        self.file_path().is_some_and(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| {
+                    SUPPORTED_AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str())
+                })
+        })
+    }
+
+    /// The track's file, decoded from its `Location` URL.
+    pub fn file_path(&self) -> Option<PathBuf> {
+        file_url_to_path(self.location.as_deref()?)
+    }
+
+    pub fn is_favorite(&self) -> bool {
+        self.loved || self.favorited
+    }
+
+    /// The tags `save_to_library` stores.
+    pub fn metadata(&self) -> AudioMetadata {
+        let text = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        let rating = self
+            .rating
+            .filter(|_| !self.rating_computed)
+            .map(|rating| (rating / RATING_PER_STAR).min(5) as u8)
+            .filter(|&stars| stars > 0);
+        AudioMetadata {
+            title: text(&self.name),
+            artist: text(&self.artist),
+            album: text(&self.album),
+            album_artist: text(&self.album_artist),
+            genre: text(&self.genre),
+            track_number: self.track_number.filter(|&n| n > 0),
+            disc_number: self.disc_number.filter(|&n| n > 0),
+            year: self.year.filter(|&y| y > 0),
+            duration_secs: self.total_time.map(|ms| (ms / 1000) as u32),
+            rating,
+            play_count: self.play_count.filter(|&n| n > 0),
+            ..Default::default()
+        }
+    }
+}
+
+impl ItunesPlaylist {
+    /// Whether this is a playlist the user made (not built in, not a folder).
+    pub fn is_user_playlist(&self) -> bool {
+        !self.master && self.distinguished_kind.is_none() && !self.folder
+    }
+}
+
+/// Parse a `Library.xml` export.
+pub fn read_itunes_library(path: &Path) -> Result<ItunesLibrary, String> {
+    plist::from_file(path).map_err(|e| format!("Failed to read iTunes library: {}", e))
+}
+
+/// Decode a `file://` URL into a local path.
+///
+/// Handles `file://localhost/` and Windows drive letters
+/// (`file://localhost/C:/Music/...`). Returns `None` for other schemes.
+pub fn file_url_to_path(url: &str) -> Option<PathBuf> {
+    let rest = url.strip_prefix("file://")?;
+    let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+    let decoded = urlencoding::decode(rest).ok()?;
+    let bytes = decoded.as_bytes();
+    // "/C:/Music" -> "C:/Music"
+    if bytes.len() >= 3 && bytes[0] == b'/' && bytes[1].is_ascii_alphabetic() && bytes[2] == b':' {
+        return Some(PathBuf::from(&decoded[1..]));
+    }
+    Some(PathBuf::from(decoded.as_ref()))
+}
+
+/// Where a track's file is now, trying `music_folder` when the library has
+/// moved since the export.
+///
+/// Paths under the export's own music folder are re-rooted at
+/// `music_folder` if they don't exist where the export says.
+pub fn locate_track_file(
+    track: &ItunesTrack,
+    exported_music_folder: Option<&Path>,
+    music_folder: Option<&Path>,
+) -> Option<PathBuf> {
+    let path = track.file_path()?;
+    if path.is_file() {
+        return Some(path);
+    }
+    let relative = path.strip_prefix(exported_music_folder?).ok()?;
+    let moved = music_folder?.join(relative);
+    moved.is_file().then_some(moved)
+}
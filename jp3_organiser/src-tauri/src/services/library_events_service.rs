@@ -0,0 +1,62 @@
+//! Library change events for the frontend.
+//!
+//! Every command that changes the library announces what it changed, so
+//! every open view can refresh (e.g. with `load_library_delta`) instead of
+//! polling `load_library` after each action:
+//!
+//! - `library://songs-added`: songs saved or restored from a soft delete
+//! - `library://songs-deleted`: songs soft-deleted
+//! - `library://songs-updated`: song metadata, rating, favorite or file
+//!   changed (an edit that gives a song a new ID also deletes the old one)
+//! - `library://album-updated`: album name, artist or year changed, or
+//!   albums merged
+//! - `library://artist-updated`: artist renamed
+//! - `library://playlist-changed`: playlists created, edited, renamed or
+//!   deleted
+//! - `library://compacted`: compaction finished; every ID may have changed
+//!
+//! The app handle is registered once at startup. Without one (the library
+//! commands called directly, as in tests) events are silently dropped.
+
+use once_cell::sync::OnceCell;
+use tauri::{AppHandle, Emitter};
+
+use crate::models::LibraryChangeEvent;
+
+/// Songs were saved or restored
+pub const EVENT_SONGS_ADDED: &str = "library://songs-added";
+
+/// Songs were soft-deleted
+pub const EVENT_SONGS_DELETED: &str = "library://songs-deleted";
+
+/// Songs' metadata, rating, favorite flag or file changed
+pub const EVENT_SONGS_UPDATED: &str = "library://songs-updated";
+
+/// Albums were edited or merged
+pub const EVENT_ALBUM_UPDATED: &str = "library://album-updated";
+
+/// Artists were renamed
+pub const EVENT_ARTIST_UPDATED: &str = "library://artist-updated";
+
+/// Playlists were created, changed or deleted
+pub const EVENT_PLAYLIST_CHANGED: &str = "library://playlist-changed";
+
+/// library.bin was compacted; song, artist and album IDs were renumbered
+pub const EVENT_LIBRARY_COMPACTED: &str = "library://compacted";
+
+static APP: OnceCell<AppHandle> = OnceCell::new();
+
+/// Register the app handle library events are emitted through.
+pub fn init_library_events(app: AppHandle) {
+    let _ = APP.set(app);
+}
+
+/// Emit a library change event, if an app handle is registered.
+pub fn emit_library_change(event: &str, payload: LibraryChangeEvent) {
+    let Some(app) = APP.get() else {
+        return;
+    };
+    if let Err(e) = app.emit(event, payload) {
+        log::warn!("Failed to emit {}: {}", event, e);
+    }
+}
@@ -0,0 +1,126 @@
+//! Library availability monitoring.
+//!
+//! The library usually lives on removable media (an SD card). When the card is
+//! ejected mid-session, commands would otherwise fail with raw IO errors.
+//!
+//! This service:
+//! - Produces a distinct `LibraryUnavailable` error for missing library roots
+//! - Polls the configured library path and emits events when it vanishes/reappears
+//! - Exposes a pause flag that long-running background work waits on
+//!
+//! # Events
+//! - `library://unavailable` - the library path disappeared (payload: `LibraryAvailabilityEvent`)
+//! - `library://available` - the library path came back (payload: `LibraryAvailabilityEvent`)
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+/// Prefix of the error string returned when the library root is missing.
+/// The frontend matches on this to show a "reconnect your SD card" prompt.
+pub const LIBRARY_UNAVAILABLE: &str = "LibraryUnavailable";
+
+/// Event emitted when the library path disappears
+pub const EVENT_LIBRARY_UNAVAILABLE: &str = "library://unavailable";
+
+/// Event emitted when the library path reappears
+pub const EVENT_LIBRARY_AVAILABLE: &str = "library://available";
+
+/// How often the configured library path is checked
+const POLL_INTERVAL_MS: u64 = 2000;
+
+/// How often paused background work re-checks availability
+const PAUSE_CHECK_INTERVAL_MS: u64 = 500;
+
+/// Whether the configured library path currently exists.
+/// Starts as `true` so nothing is paused before the first poll.
+static LIBRARY_AVAILABLE: AtomicBool = AtomicBool::new(true);
+
+/// Payload for library availability events
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryAvailabilityEvent {
+    /// The library base path being monitored
+    pub path: String,
+    /// Whether the path currently exists
+    pub available: bool,
+}
+
+/// Build the `LibraryUnavailable` error string for a base path.
+pub fn library_unavailable_error(base_path: &Path) -> String {
+    format!(
+        "{}: Library path '{}' is not accessible. Was the SD card removed?",
+        LIBRARY_UNAVAILABLE,
+        base_path.display()
+    )
+}
+
+/// Ensure the library base path exists before touching anything inside it.
+///
+/// Returns a `LibraryUnavailable` error (rather than a raw IO error) when the
+/// root has vanished, e.g. because the SD card was unplugged.
+pub fn ensure_library_available(base_path: &Path) -> Result<(), String> {
+    if base_path.as_os_str().is_empty() || !base_path.is_dir() {
+        return Err(library_unavailable_error(base_path));
+    }
+    Ok(())
+}
+
+/// Whether the monitored library path was present at the last poll.
+pub fn is_library_available() -> bool {
+    LIBRARY_AVAILABLE.load(Ordering::SeqCst)
+}
+
+/// Wait while the monitored library is unavailable.
+///
+/// Long-running background work calls this between items so it pauses
+/// automatically when the SD card is pulled and resumes when it returns.
+pub async fn wait_while_library_unavailable() {
+    if is_library_available() {
+        return;
+    }
+    log::warn!("[LibraryMonitor] Library unavailable - pausing background work");
+    while !is_library_available() {
+        tokio::time::sleep(Duration::from_millis(PAUSE_CHECK_INTERVAL_MS)).await;
+    }
+    log::info!("[LibraryMonitor] Library available again - resuming background work");
+}
+
+/// Start polling the configured library path on a background thread.
+///
+/// Emits `library://unavailable` / `library://available` on transitions.
+/// If no library path is configured, the library is treated as available.
+pub fn start_library_monitor(app: AppHandle) {
+    thread::spawn(move || loop {
+        let path = crate::commands::get_library_path(app.clone())
+            .ok()
+            .flatten();
+
+        let available = match &path {
+            Some(p) => Path::new(p).is_dir(),
+            None => true,
+        };
+
+        let was_available = LIBRARY_AVAILABLE.swap(available, Ordering::SeqCst);
+
+        if was_available != available {
+            let path = path.unwrap_or_default();
+            let event = if available {
+                log::info!("[LibraryMonitor] Library path reappeared: {}", path);
+                EVENT_LIBRARY_AVAILABLE
+            } else {
+                log::warn!("[LibraryMonitor] Library path disappeared: {}", path);
+                EVENT_LIBRARY_UNAVAILABLE
+            };
+            if let Err(e) = app.emit(event, LibraryAvailabilityEvent { path, available }) {
+                log::error!("[LibraryMonitor] Failed to emit {}: {}", event, e);
+            }
+        }
+
+        thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+    });
+}
@@ -0,0 +1,38 @@
+//! Per-library settings.
+//!
+//! Settings that belong to a library rather than the app (compaction
+//! thresholds, for example) travel with the SD card in
+//! `jp3/metadata/library_settings.json`. A missing file means defaults.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::LibrarySettings;
+
+const LIBRARY_SETTINGS_FILE: &str = "library_settings.json";
+
+fn settings_path(base_path: &Path) -> PathBuf {
+    base_path
+        .join("jp3")
+        .join("metadata")
+        .join(LIBRARY_SETTINGS_FILE)
+}
+
+/// Load the library's settings, or defaults if none are saved.
+pub fn load_library_settings(base_path: &Path) -> Result<LibrarySettings, String> {
+    let path = settings_path(base_path);
+    if !path.exists() {
+        return Ok(LibrarySettings::default());
+    }
+    let data = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read library settings: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse library settings: {}", e))
+}
+
+/// Persist the library's settings.
+pub fn save_library_settings(base_path: &Path, settings: &LibrarySettings) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize library settings: {}", e))?;
+    fs::write(settings_path(base_path), data)
+        .map_err(|e| format!("Failed to write library settings: {}", e))
+}
@@ -0,0 +1,97 @@
+//! Recent library snapshots for incremental refreshes.
+//!
+//! Each time library.bin is loaded at a new generation, the parsed library
+//! is kept in memory (the last `MAX_SNAPSHOTS` generations per library).
+//! `load_library_delta` diffs the current library against the snapshot for
+//! the generation the frontend already has, so after a save only the
+//! entries that changed cross the IPC bridge.
+//!
+//! Entries are compared by ID, so the diff stays correct even after
+//! compaction renumbers everything; it just comes out larger.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+
+use crate::models::{LibraryDelta, ParsedLibrary};
+
+/// Generations kept per library
+const MAX_SNAPSHOTS: usize = 4;
+
+/// Kept snapshots by library base path, oldest first
+type SnapshotCache = HashMap<PathBuf, Vec<Arc<ParsedLibrary>>>;
+
+static SNAPSHOTS: Lazy<Mutex<SnapshotCache>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Keep `library` as the latest snapshot, unless its generation is already kept.
+pub fn remember_snapshot(base_path: &Path, library: &ParsedLibrary) {
+    let mut snapshots = SNAPSHOTS.lock().unwrap();
+    let kept = snapshots.entry(base_path.to_path_buf()).or_default();
+    if kept.iter().any(|s| s.generation == library.generation) {
+        return;
+    }
+    kept.push(Arc::new(library.clone()));
+    if kept.len() > MAX_SNAPSHOTS {
+        kept.remove(0);
+    }
+}
+
+/// The snapshot for `generation`, if it is still kept.
+pub fn snapshot_at(base_path: &Path, generation: u32) -> Option<Arc<ParsedLibrary>> {
+    SNAPSHOTS
+        .lock()
+        .unwrap()
+        .get(base_path)?
+        .iter()
+        .find(|s| s.generation == generation)
+        .cloned()
+}
+
+/// The whole library, for a frontend whose generation isn't known.
+pub fn full_delta(library: ParsedLibrary) -> LibraryDelta {
+    LibraryDelta {
+        generation: library.generation,
+        full: true,
+        artists: library.artists,
+        albums: library.albums,
+        songs: library.songs,
+        genres: library.genres,
+        ..Default::default()
+    }
+}
+
+/// Entries added or changed in `new`, and IDs gone since `old`.
+pub fn diff_libraries(old: &ParsedLibrary, new: ParsedLibrary) -> LibraryDelta {
+    let (artists, removed_artist_ids) = diff_entries(&old.artists, new.artists, |a| a.id);
+    let (albums, removed_album_ids) = diff_entries(&old.albums, new.albums, |a| a.id);
+    let (songs, removed_song_ids) = diff_entries(&old.songs, new.songs, |s| s.id);
+    LibraryDelta {
+        generation: new.generation,
+        full: false,
+        artists,
+        albums,
+        songs,
+        removed_artist_ids,
+        removed_album_ids,
+        removed_song_ids,
+        genres: new.genres,
+    }
+}
+
+fn diff_entries<T: PartialEq>(
+    old: &[T],
+    new: Vec<T>,
+    id: impl Fn(&T) -> u32,
+) -> (Vec<T>, Vec<u32>) {
+    let new_ids: HashSet<u32> = new.iter().map(&id).collect();
+    let mut removed: Vec<u32> = old.iter().map(&id).filter(|i| !new_ids.contains(i)).collect();
+    removed.sort_unstable();
+    let old_by_id: HashMap<u32, &T> = old.iter().map(|entry| (id(entry), entry)).collect();
+    let changed = new
+        .into_iter()
+        .filter(|entry| old_by_id.get(&id(entry)).is_none_or(|old| *old != entry))
+        .collect();
+    (changed, removed)
+}
@@ -14,7 +14,7 @@
 
 use serde::Deserialize;
 
-use crate::models::AudioMetadata;
+use crate::models::{AudioMetadata, MatchConfidence};
 
 // =============================================================================
 // Ranking Configuration
@@ -99,7 +99,10 @@ pub struct Recording {
 #[derive(Debug, Clone, Deserialize)]
 pub struct AcoustIdResult {
     pub recordings: Option<Vec<Recording>>,
-    // Note: id, score omitted - not used for ranking
+    /// Fingerprint match score (0.0-1.0), kept as the match confidence
+    #[serde(default)]
+    pub score: Option<f32>,
+    // Note: id omitted - not used for ranking
 }
 
 /// Full AcoustID API response.
@@ -130,30 +133,53 @@ pub struct AcoustIdResponse {
 pub fn extract_metadata_from_acoustic_json(
     json: &serde_json::Value,
 ) -> Result<AudioMetadata, String> {
+    extract_match_from_acoustic_json(json).map(|(metadata, _)| metadata)
+}
+
+/// Like `extract_metadata_from_acoustic_json`, also returning how confident
+/// the chosen match is (its AcoustID score and sources count).
+pub fn extract_match_from_acoustic_json(
+    json: &serde_json::Value,
+) -> Result<(AudioMetadata, MatchConfidence), String> {
     let response: AcoustIdResponse = serde_json::from_value(json.clone())
         .map_err(|e| format!("Failed to parse AcoustID response: {}", e))?;
 
-    extract_metadata_from_response(response)
+    extract_match_from_response(response)
 }
 
 /// Extract metadata from a parsed AcoustID response.
 ///
-/// This is the main ranking implementation. Use this if you've already
-/// deserialized the response to avoid double-parsing.
+/// Use this if you've already deserialized the response to avoid
+/// double-parsing.
 pub fn extract_metadata_from_response(
     response: AcoustIdResponse,
 ) -> Result<AudioMetadata, String> {
+    extract_match_from_response(response).map(|(metadata, _)| metadata)
+}
+
+/// Rank a parsed AcoustID response and return the best match with its
+/// confidence. This is the main ranking implementation.
+pub fn extract_match_from_response(
+    response: AcoustIdResponse,
+) -> Result<(AudioMetadata, MatchConfidence), String> {
     if response.status != "ok" {
         return Err(format!("AcoustID API returned status: {}", response.status));
     }
 
-    // Collect all recordings, filtering invalid ones
-    let recordings: Vec<Recording> = response
+    // Collect all recordings with their result's match score, filtering invalid ones
+    let recordings: Vec<(Recording, Option<f32>)> = response
         .results
         .unwrap_or_default()
         .into_iter()
-        .flat_map(|result| result.recordings.unwrap_or_default())
-        .filter(|r| !r.title.is_empty())
+        .flat_map(|result| {
+            let score = result.score;
+            result
+                .recordings
+                .unwrap_or_default()
+                .into_iter()
+                .map(move |recording| (recording, score))
+        })
+        .filter(|(r, _)| !r.title.is_empty())
         .collect();
 
     if recordings.is_empty() {
@@ -165,7 +191,7 @@ pub fn extract_metadata_from_response(
     // Score all recordings
     let mut scored: Vec<ScoredRecording> = recordings
         .into_iter()
-        .map(|rec| ScoredRecording::new(rec))
+        .map(|(rec, acoustid_score)| ScoredRecording::new(rec, acoustid_score))
         .collect();
 
     // Apply ranking criteria
@@ -185,7 +211,47 @@ pub fn extract_metadata_from_response(
     let best = scored.into_iter().next()
         .ok_or("No recordings after ranking")?;
 
-    build_metadata(&best.recording)
+    let confidence = MatchConfidence {
+        acoustid_score: best.acoustid_score,
+        acoustid_sources: best.recording.sources,
+        musicbrainz_score: None,
+    };
+    Ok((build_metadata(&best.recording)?, confidence))
+}
+
+/// Collect every release MBID in an AcoustID response.
+///
+/// Releases from release groups titled like `album` (case-insensitive)
+/// come first, followed by all others, each in response order without
+/// duplicates. Used as Cover Art Archive candidates when the chosen
+/// release has no art or a text search finds nothing.
+pub fn collect_release_mbids(json: &serde_json::Value, album: Option<&str>) -> Vec<String> {
+    let response: AcoustIdResponse = match serde_json::from_value(json.clone()) {
+        Ok(r) => r,
+        Err(_) => return Vec::new(),
+    };
+
+    let album = album.map(|a| a.trim().to_lowercase());
+    let groups: Vec<ReleaseGroup> = response
+        .results
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|result| result.recordings.unwrap_or_default())
+        .flat_map(|recording| recording.releasegroups.unwrap_or_default())
+        .collect();
+    let (matching, others): (Vec<_>, Vec<_>) = groups
+        .iter()
+        .partition(|g| album.as_deref() == Some(g.title.trim().to_lowercase().as_str()));
+
+    let mut mbids: Vec<String> = Vec::new();
+    for group in matching.into_iter().chain(others) {
+        for id in group.releases.iter().flatten().filter_map(|r| r.id.as_ref()) {
+            if !mbids.contains(id) {
+                mbids.push(id.clone());
+            }
+        }
+    }
+    mbids
 }
 
 // =============================================================================
@@ -196,12 +262,18 @@ pub fn extract_metadata_from_response(
 #[derive(Debug)]
 struct ScoredRecording {
     recording: Recording,
+    /// AcoustID score of the result the recording came from
+    acoustid_score: Option<f32>,
     score: u32,
 }
 
 impl ScoredRecording {
-    fn new(recording: Recording) -> Self {
-        Self { recording, score: 0 }
+    fn new(recording: Recording, acoustid_score: Option<f32>) -> Self {
+        Self {
+            recording,
+            acoustid_score,
+            score: 0,
+        }
     }
 }
 
@@ -367,7 +439,12 @@ fn build_metadata(recording: &Recording) -> Result<AudioMetadata, String> {
         track_number: None, 
         duration_secs: None,
         release_mbid,
-        artist_mbid: Some(artist_mbid)
+        artist_mbid: Some(artist_mbid),
+        genre: None,
+        album_artist: None,
+        disc_number: None,
+        rating: None,
+        play_count: None,
     })
 }
 
@@ -1,4 +1,45 @@
+pub mod audio_info_service;
+pub mod auto_playlist_service;
+pub mod backup_service;
+pub mod compaction_service;
 pub mod cover_art_service;
+pub mod device_service;
+pub mod export_budget_service;
+pub mod failed_import_service;
+pub mod file_naming_service;
+pub mod fingerprint_index_service;
+pub mod fingerprint_match_service;
 pub mod fingerprint_service;
+pub mod folder_scan_service;
+pub mod format_sniff_service;
+pub mod import_session_service;
+pub mod itunes_library_service;
+pub mod library_events_service;
+pub mod library_monitor_service;
+pub mod library_snapshot_service;
+pub mod library_settings_service;
 pub mod metadata_ranking_service;
-pub mod musicbrainz_service;
\ No newline at end of file
+pub mod musicbrainz_service;
+pub mod parallel_io_service;
+pub mod path_index_service;
+pub mod power_service;
+pub mod release_candidate_service;
+pub mod replay_gain_service;
+pub mod report_service;
+pub mod resample_service;
+pub mod save_hook_service;
+pub mod search_service;
+pub mod song_confidence_service;
+pub mod song_hash_service;
+pub mod sort_name_service;
+pub mod sync_history_service;
+pub mod sync_plan_service;
+pub mod tag_cleanup_service;
+pub mod tag_reader_service;
+pub mod task_manager_service;
+pub mod thumbnail_service;
+pub mod transliteration_service;
+pub mod trash_service;
+pub mod transcode_service;
+pub mod write_marker_service;
+pub mod year_backfill_service;
\ No newline at end of file
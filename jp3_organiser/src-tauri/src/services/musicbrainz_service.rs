@@ -25,6 +25,13 @@ const MIN_REQUEST_INTERVAL_MS: u64 = 1100; // 1.1 seconds for safety margin
 /// Request timeout
 const REQUEST_TIMEOUT_SECS: u64 = 30;
 
+/// Maximum releases requested per search
+const SEARCH_LIMIT: &str = "10";
+
+/// Releases scoring within this many points of the best match are
+/// returned as alternatives (reissues and regional editions usually tie)
+const PLAUSIBLE_SCORE_MARGIN: u32 = 10;
+
 /// User-Agent string for MusicBrainz API requests
 const USER_AGENT: &str = "JP3Organiser/1.0.0 (https://github.com/jp3-organiser)";
 
@@ -46,6 +53,9 @@ pub struct MusicBrainzRelease {
     #[serde(rename = "artist-credit")]
     pub artist_credit: Option<Vec<ArtistCredit>>,
     pub date: Option<String>,
+    pub country: Option<String>,
+    #[serde(rename = "track-count")]
+    pub track_count: Option<u32>,
     #[serde(rename = "release-group")]
     pub release_group: Option<ReleaseGroup>,
 }
@@ -108,6 +118,10 @@ pub struct ReleaseSearchResult {
     pub score: u32,
     /// Release date if available
     pub date: Option<String>,
+    /// Release country code (e.g. "GB", "XE") if available
+    pub country: Option<String>,
+    /// Total tracks across all media, if available
+    pub track_count: Option<u32>,
 }
 
 /// Enforce rate limiting by waiting if necessary.
@@ -156,8 +170,8 @@ fn build_client() -> Result<reqwest::Client, MusicBrainzError> {
 
 /// Search for a release MBID by artist and album name.
 ///
-/// This searches the MusicBrainz database using Lucene query syntax.
 /// Returns the best matching release MBID for fetching cover art.
+/// See `search_release_candidates` for all plausible matches.
 ///
 /// # Arguments
 /// * `artist` - Artist name
@@ -171,6 +185,26 @@ pub async fn search_release(
     artist: &str,
     album: &str,
 ) -> Result<Option<ReleaseSearchResult>, MusicBrainzError> {
+    Ok(search_release_candidates(artist, album)
+        .await?
+        .into_iter()
+        .next())
+}
+
+/// Search for every plausible release matching an artist and album name.
+///
+/// This searches the MusicBrainz database using Lucene query syntax.
+/// Different countries and reissues of the same album usually score the
+/// same, so every release within `PLAUSIBLE_SCORE_MARGIN` of the best is
+/// returned, best first, for the user to disambiguate.
+///
+/// # Returns
+/// * `Ok(Vec<ReleaseSearchResult>)` - Plausible releases (empty if none)
+/// * `Err(MusicBrainzError)` - If the search fails
+pub async fn search_release_candidates(
+    artist: &str,
+    album: &str,
+) -> Result<Vec<ReleaseSearchResult>, MusicBrainzError> {
     log::info!(
         "[MusicBrainz] Searching for release - artist: \"{}\", album: \"{}\"",
         artist,
@@ -198,7 +232,7 @@ pub async fn search_release(
         .query(&[
             ("query", query.as_str()),
             ("fmt", "json"),
-            ("limit", "5"),
+            ("limit", SEARCH_LIMIT),
         ])
         .send()
         .await
@@ -241,37 +275,116 @@ pub async fn search_release(
         Some(releases) if !releases.is_empty() => releases,
         _ => {
             log::info!("[MusicBrainz] No releases found for query");
-            return Ok(None);
+            return Ok(Vec::new());
         }
     };
 
     log::info!("[MusicBrainz] Found {} releases", releases.len());
 
-    // Get the first (best) result
-    let best = &releases[0];
-    let artist_name = best
-        .artist_credit
-        .as_ref()
-        .and_then(|ac| ac.first())
-        .and_then(|c| c.artist.as_ref().map(|a| a.name.clone()));
-
-    let result = ReleaseSearchResult {
-        release_mbid: best.id.clone(),
-        title: best.title.clone(),
-        artist: artist_name,
-        score: best.score.unwrap_or(0),
-        date: best.date.clone(),
-    };
+    let results = plausible_releases(releases);
 
     log::info!(
-        "[MusicBrainz] Best match: \"{}\" by {:?} (score: {}, MBID: {})",
-        result.title,
-        result.artist,
-        result.score,
-        result.release_mbid
+        "[MusicBrainz] Best match: \"{}\" by {:?} (score: {}, MBID: {}), {} plausible",
+        results[0].title,
+        results[0].artist,
+        results[0].score,
+        results[0].release_mbid,
+        results.len()
     );
 
-    Ok(Some(result))
+    Ok(results)
+}
+
+/// Look up a single release by MBID.
+///
+/// # Returns
+/// * `Ok(Some(ReleaseSearchResult))` - The release (score is always 100)
+/// * `Ok(None)` - No release with that MBID
+/// * `Err(MusicBrainzError)` - If the lookup fails
+pub async fn lookup_release(mbid: &str) -> Result<Option<ReleaseSearchResult>, MusicBrainzError> {
+    log::info!("[MusicBrainz] Looking up release {}", mbid);
+
+    // Enforce rate limiting
+    enforce_rate_limit().await;
+
+    let client = build_client()?;
+
+    let response = client
+        .get(format!("https://musicbrainz.org/ws/2/release/{}", mbid))
+        .query(&[("fmt", "json"), ("inc", "artist-credits")])
+        .send()
+        .await
+        .map_err(|e| {
+            log::error!("[MusicBrainz] Request failed: {}", e);
+            MusicBrainzError::RequestError(e.to_string())
+        })?;
+
+    let status = response.status();
+    log::info!("[MusicBrainz] Response status: {}", status);
+
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    // Handle rate limiting (503)
+    if status == reqwest::StatusCode::SERVICE_UNAVAILABLE {
+        log::error!("[MusicBrainz] Rate limit exceeded (503)");
+        return Err(MusicBrainzError::RateLimitExceeded);
+    }
+
+    if !status.is_success() {
+        log::error!("[MusicBrainz] Request failed with status: {}", status);
+        return Err(MusicBrainzError::RequestError(format!("HTTP {}", status)));
+    }
+
+    let release: MusicBrainzRelease = response.json().await.map_err(|e| {
+        log::error!("[MusicBrainz] Failed to parse release: {}", e);
+        MusicBrainzError::ParseError(e.to_string())
+    })?;
+
+    Ok(plausible_releases(vec![MusicBrainzRelease {
+        score: Some(100),
+        ..release
+    }])
+    .into_iter()
+    .next())
+}
+
+/// Parse the year from a MusicBrainz date ("YYYY", "YYYY-MM" or "YYYY-MM-DD").
+pub fn release_year(date: &str) -> Option<u16> {
+    date.get(0..4)
+        .and_then(|y| y.parse::<u16>().ok())
+        .filter(|y| *y > 0)
+}
+
+/// Keep releases scoring close to the best (first) one.
+///
+/// MusicBrainz returns search results best first, so the first release is
+/// always kept.
+pub fn plausible_releases(releases: Vec<MusicBrainzRelease>) -> Vec<ReleaseSearchResult> {
+    let best_score = releases.first().and_then(|r| r.score).unwrap_or(0);
+
+    releases
+        .into_iter()
+        .enumerate()
+        .filter(|(i, r)| *i == 0 || r.score.unwrap_or(0) + PLAUSIBLE_SCORE_MARGIN >= best_score)
+        .map(|(_, release)| {
+            let artist = release
+                .artist_credit
+                .as_ref()
+                .and_then(|ac| ac.first())
+                .and_then(|c| c.artist.as_ref().map(|a| a.name.clone()));
+            ReleaseSearchResult {
+                release_mbid: release.id,
+                title: release.title,
+                artist,
+                score: release.score.unwrap_or(0),
+                date: release.date,
+                country: release.country,
+                track_count: release.track_count,
+            }
+        })
+        .collect()
 }
 
 /// Search for multiple releases in batch, respecting rate limits.
@@ -283,10 +396,10 @@ pub async fn search_release(
 /// * `queries` - List of (artist, album) tuples to search
 ///
 /// # Returns
-/// * Vector of Optional results (None if not found, Some if found)
+/// * Plausible releases for each query, best first (empty if not found)
 pub async fn search_releases_batch(
     queries: &[(String, String)],
-) -> Vec<Option<ReleaseSearchResult>> {
+) -> Vec<Vec<ReleaseSearchResult>> {
     log::info!(
         "[MusicBrainz] Batch searching {} releases",
         queries.len()
@@ -295,8 +408,8 @@ pub async fn search_releases_batch(
     let mut results = Vec::with_capacity(queries.len());
 
     for (artist, album) in queries {
-        match search_release(artist, album).await {
-            Ok(result) => results.push(result),
+        match search_release_candidates(artist, album).await {
+            Ok(releases) => results.push(releases),
             Err(e) => {
                 log::warn!(
                     "[MusicBrainz] Search failed for \"{}\" - \"{}\": {}",
@@ -304,15 +417,15 @@ pub async fn search_releases_batch(
                     album,
                     e
                 );
-                results.push(None);
+                results.push(Vec::new());
             }
         }
     }
 
     log::info!(
         "[MusicBrainz] Batch complete: {} found, {} not found",
-        results.iter().filter(|r| r.is_some()).count(),
-        results.iter().filter(|r| r.is_none()).count()
+        results.iter().filter(|r| !r.is_empty()).count(),
+        results.iter().filter(|r| r.is_empty()).count()
     );
 
     results
@@ -0,0 +1,137 @@
+//! Bounded parallelism for per-file IO work.
+//!
+//! Reading tags from a thousand-file drop one at a time is slow, but
+//! spawning a thread per file thrashes the disk (especially SD cards and
+//! USB drives). `map_ordered` runs a fixed number of workers that pull
+//! indices from a shared counter, so at most `MAX_IO_WORKERS` files are
+//! being read at once. Results come back in input order regardless of
+//! which worker finished first.
+//!
+//! Users can lower the worker count and cap copy throughput at runtime
+//! (`set_io_throttle`) so a big import doesn't starve the rest of the disk.
+//! The cap is shared by every `copy_file` in flight.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+
+use crate::models::IoThrottleSettings;
+
+/// Upper bound on files read concurrently
+pub const MAX_IO_WORKERS: usize = 4;
+
+/// Bytes copied between throughput checks
+const COPY_CHUNK_SIZE: usize = 256 * 1024;
+
+static IO_THROTTLE: Lazy<Mutex<IoThrottleSettings>> =
+    Lazy::new(|| Mutex::new(IoThrottleSettings::default()));
+
+/// When the next throttled chunk may start, shared across copies
+static NEXT_CHUNK_AT: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+
+/// Current throughput cap and worker limit.
+pub fn io_throttle() -> IoThrottleSettings {
+    IO_THROTTLE.lock().unwrap().clone()
+}
+
+/// Change the throughput cap and worker limit. Copies already running pick
+/// up the new cap at their next chunk.
+pub fn set_io_throttle(settings: IoThrottleSettings) {
+    *IO_THROTTLE.lock().unwrap() = settings;
+}
+
+/// Number of workers to use for `item_count` items.
+pub fn io_worker_count(item_count: usize) -> usize {
+    let cores = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let limit = io_throttle()
+        .max_workers
+        .map_or(MAX_IO_WORKERS, |n| (n as usize).min(MAX_IO_WORKERS));
+    cores.min(limit).min(item_count).max(1)
+}
+
+/// Wait until `bytes` more may be written under the throughput cap.
+fn pace(bytes: usize) {
+    let Some(mb_per_sec) = io_throttle().max_mb_per_sec.filter(|&cap| cap > 0.0) else {
+        return;
+    };
+    let cost = Duration::from_secs_f64(bytes as f64 / (mb_per_sec as f64 * 1024.0 * 1024.0));
+    let wait = {
+        let mut next = NEXT_CHUNK_AT.lock().unwrap();
+        let now = Instant::now();
+        let start = next.map_or(now, |at| at.max(now));
+        *next = Some(start + cost);
+        start - now
+    };
+    thread::sleep(wait);
+}
+
+/// Copy a file, honouring the throughput cap. Returns the bytes copied.
+///
+/// Without a cap this is `fs::copy`.
+pub fn copy_file(source: &Path, dest: &Path) -> io::Result<u64> {
+    if io_throttle().max_mb_per_sec.is_none() {
+        return fs::copy(source, dest);
+    }
+
+    let mut reader = fs::File::open(source)?;
+    let mut writer = fs::File::create(dest)?;
+    let mut buffer = vec![0u8; COPY_CHUNK_SIZE];
+    let mut copied = 0u64;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        pace(read);
+        writer.write_all(&buffer[..read])?;
+        copied += read as u64;
+    }
+    writer.flush()?;
+    Ok(copied)
+}
+
+/// Apply `f` to every item on a bounded worker pool, preserving input order.
+///
+/// `on_complete(index, &result)` is called from the worker thread as soon
+/// as each item finishes, so callers can stream partial results (e.g. as
+/// events) while the rest are still being read. Completion order is not
+/// input order; the returned Vec is.
+pub fn map_ordered<T, R, F, C>(items: &[T], f: F, on_complete: C) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+    C: Fn(usize, &R) + Sync,
+{
+    let next_index = AtomicUsize::new(0);
+    let slots: Mutex<Vec<Option<R>>> = Mutex::new((0..items.len()).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..io_worker_count(items.len()) {
+            scope.spawn(|| loop {
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                let result = f(item);
+                on_complete(index, &result);
+                slots.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    slots
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|slot| slot.expect("every index is processed exactly once"))
+        .collect()
+}
@@ -0,0 +1,99 @@
+//! Per-song indexes in `jp3/metadata/`, keyed by relative path.
+//!
+//! library.bin has no room for side data such as hashes, fingerprints,
+//! match confidence or gain, so each lives in its own JSON file keyed by the
+//! song's relative path (e.g. `00/001.mp3`), which survives edits (song IDs
+//! do not). Loading, saving and keeping those keys in step with deletes and
+//! compaction renumbering is the same for every index; the owning services
+//! wrap these functions for their own index type.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// A JSON index of per-song entries keyed by relative path.
+pub trait PathIndex: Default + Serialize + DeserializeOwned {
+    type Entry;
+
+    /// File name in `jp3/metadata/`
+    const FILE_NAME: &'static str;
+
+    /// What the index holds, for error messages (e.g. "song hashes")
+    const DESCRIPTION: &'static str;
+
+    fn entries_mut(&mut self) -> &mut HashMap<String, Self::Entry>;
+}
+
+fn index_path<I: PathIndex>(base_path: &Path) -> PathBuf {
+    base_path.join("jp3").join("metadata").join(I::FILE_NAME)
+}
+
+/// Load an index, or an empty index if none exists.
+pub fn load_index<I: PathIndex>(base_path: &Path) -> Result<I, String> {
+    let path = index_path::<I>(base_path);
+    if !path.exists() {
+        return Ok(I::default());
+    }
+    let data = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", I::DESCRIPTION, e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse {}: {}", I::DESCRIPTION, e))
+}
+
+/// Persist an index.
+pub fn save_index<I: PathIndex>(base_path: &Path, index: &I) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(index)
+        .map_err(|e| format!("Failed to serialize {}: {}", I::DESCRIPTION, e))?;
+    fs::write(index_path::<I>(base_path), data)
+        .map_err(|e| format!("Failed to write {}: {}", I::DESCRIPTION, e))
+}
+
+/// Add or replace entries for newly saved songs, by relative path.
+pub fn record_entries<I: PathIndex>(
+    base_path: &Path,
+    songs: Vec<(String, I::Entry)>,
+) -> Result<(), String> {
+    if songs.is_empty() {
+        return Ok(());
+    }
+    let mut index: I = load_index(base_path)?;
+    index.entries_mut().extend(songs);
+    save_index(base_path, &index)
+}
+
+/// Keep only entries whose path `keep` returns true for; the file is only
+/// rewritten if something was dropped.
+pub fn retain_entries<I: PathIndex>(
+    base_path: &Path,
+    keep: impl Fn(&str) -> bool,
+) -> Result<(), String> {
+    let mut index: I = load_index(base_path)?;
+    let entries = index.entries_mut();
+    let before = entries.len();
+    entries.retain(|path, _| keep(path));
+    if entries.len() != before {
+        save_index(base_path, &index)?;
+    }
+    Ok(())
+}
+
+/// Move entries to the new paths of songs whose files were renumbered.
+///
+/// `renamed` maps old relative paths to new ones.
+pub fn rename_entries<I: PathIndex>(
+    base_path: &Path,
+    renamed: &HashMap<String, String>,
+) -> Result<(), String> {
+    if renamed.is_empty() {
+        return Ok(());
+    }
+    let mut index: I = load_index(base_path)?;
+    let entries = index.entries_mut();
+    *entries = std::mem::take(entries)
+        .into_iter()
+        .map(|(path, entry)| (renamed.get(&path).cloned().unwrap_or(path), entry))
+        .collect();
+    save_index(base_path, &index)
+}
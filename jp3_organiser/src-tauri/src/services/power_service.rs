@@ -0,0 +1,124 @@
+//! Keeps the computer awake during long IO.
+//!
+//! Big imports and syncs fail if the laptop sleeps halfway through. While
+//! any `SleepGuard` is alive, the OS's own sleep inhibitor runs as a child
+//! process, the same way fpcalc and ffmpeg are used:
+//! - Linux: `systemd-inhibit` (sleep and idle)
+//! - macOS: `caffeinate -i`
+//! - Windows: PowerShell holding `SetThreadExecutionState`
+//!
+//! The inhibitor is started by the first guard and stopped when the last
+//! is dropped. It also exits with the app, so a crash can't leave the
+//! machine unable to sleep. If the tool is missing, work carries on without
+//! it.
+
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+
+struct Inhibitor {
+    holders: usize,
+    process: Option<Child>,
+}
+
+static INHIBITOR: Mutex<Inhibitor> = Mutex::new(Inhibitor {
+    holders: 0,
+    process: None,
+});
+
+/// Keeps the computer awake until dropped.
+#[must_use = "sleep is only prevented while the guard is alive"]
+pub struct SleepGuard {
+    _private: (),
+}
+
+/// Prevent sleep until the returned guard is dropped.
+///
+/// `reason` is shown by the OS (where it shows one) if this is the first
+/// guard.
+pub fn keep_awake(reason: &str) -> SleepGuard {
+    let mut inhibitor = INHIBITOR.lock().unwrap_or_else(|e| e.into_inner());
+    inhibitor.holders += 1;
+    if inhibitor.holders == 1 {
+        inhibitor.process = match inhibit_command(reason).map(|mut command| {
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+        }) {
+            Some(Ok(child)) => {
+                log::info!("[power] Preventing sleep: {}", reason);
+                Some(child)
+            }
+            Some(Err(e)) => {
+                log::warn!("[power] Could not prevent sleep: {}", e);
+                None
+            }
+            None => None,
+        };
+    }
+    SleepGuard { _private: () }
+}
+
+/// Number of live guards (0 when the computer may sleep).
+pub fn sleep_guard_count() -> usize {
+    INHIBITOR.lock().unwrap_or_else(|e| e.into_inner()).holders
+}
+
+impl Drop for SleepGuard {
+    fn drop(&mut self) {
+        let mut inhibitor = INHIBITOR.lock().unwrap_or_else(|e| e.into_inner());
+        inhibitor.holders -= 1;
+        if inhibitor.holders == 0 {
+            if let Some(mut child) = inhibitor.process.take() {
+                let _ = child.kill();
+                let _ = child.wait();
+                log::info!("[power] Sleep allowed again");
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn inhibit_command(reason: &str) -> Option<Command> {
+    let mut command = Command::new("systemd-inhibit");
+    command
+        .args(["--what=sleep:idle", "--who=JP3 Organiser", "--mode=block"])
+        .arg(format!("--why={}", reason))
+        .args(["tail", "-f", "/dev/null"])
+        .arg(format!("--pid={}", std::process::id()));
+    Some(command)
+}
+
+#[cfg(target_os = "macos")]
+fn inhibit_command(_reason: &str) -> Option<Command> {
+    let mut command = Command::new("caffeinate");
+    command.args(["-i", "-w"]).arg(std::process::id().to_string());
+    Some(command)
+}
+
+#[cfg(target_os = "windows")]
+fn inhibit_command(_reason: &str) -> Option<Command> {
+    use std::os::windows::process::CommandExt;
+
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    const SET_STATE: &str = "[DllImport(\"kernel32.dll\")] \
+        public static extern uint SetThreadExecutionState(uint f);";
+    // ES_CONTINUOUS | ES_SYSTEM_REQUIRED, held until the app's process exits
+    let script = format!(
+        "$k = Add-Type -Name Power -Namespace Jp3 -PassThru -MemberDefinition '{}'; \
+         $k::SetThreadExecutionState(0x80000001) | Out-Null; Wait-Process -Id {}",
+        SET_STATE,
+        std::process::id()
+    );
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .creation_flags(CREATE_NO_WINDOW);
+    Some(command)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn inhibit_command(_reason: &str) -> Option<Command> {
+    None
+}
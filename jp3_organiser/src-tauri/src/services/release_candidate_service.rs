@@ -0,0 +1,96 @@
+//! Per-album release candidates and user release picks.
+//!
+//! AcoustID returns many releases for each recording, but only one release
+//! MBID survives into the saved metadata. When the user saves an import
+//! session, every release MBID AcoustID gave for the album's recordings is
+//! recorded in `jp3/metadata/release_candidates.json` so cover lookups can
+//! try them directly when the artist/album text search finds nothing.
+//!
+//! The same file records which release the user picked when a MusicBrainz
+//! search matched several (regional editions, reissues). A pick takes
+//! precedence over search results for later cover and metadata lookups.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::ReleaseCandidateIndex;
+use crate::services::cover_art_service::cover_filename;
+
+const RELEASE_CANDIDATES_FILE: &str = "release_candidates.json";
+
+fn index_path(base_path: &Path) -> PathBuf {
+    base_path
+        .join("jp3")
+        .join("metadata")
+        .join(RELEASE_CANDIDATES_FILE)
+}
+
+/// Load the release candidate index, or an empty index if none exists.
+pub fn load_release_candidates(base_path: &Path) -> Result<ReleaseCandidateIndex, String> {
+    let path = index_path(base_path);
+    if !path.exists() {
+        return Ok(ReleaseCandidateIndex::default());
+    }
+    let data = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read release candidates: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse release candidates: {}", e))
+}
+
+/// Persist the release candidate index.
+pub fn save_release_candidates(
+    base_path: &Path,
+    index: &ReleaseCandidateIndex,
+) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(index)
+        .map_err(|e| format!("Failed to serialize release candidates: {}", e))?;
+    fs::write(index_path(base_path), data)
+        .map_err(|e| format!("Failed to write release candidates: {}", e))
+}
+
+/// Add release MBIDs for an album, keeping existing ones first.
+///
+/// Returns true if anything new was added.
+pub fn add_release_candidates(
+    index: &mut ReleaseCandidateIndex,
+    artist: &str,
+    album: &str,
+    mbids: &[String],
+) -> bool {
+    let entry = index.albums.entry(cover_filename(artist, album)).or_default();
+    let before = entry.len();
+    for mbid in mbids {
+        if !entry.contains(mbid) {
+            entry.push(mbid.clone());
+        }
+    }
+    entry.len() > before
+}
+
+/// Release MBIDs recorded for an album (empty if none).
+pub fn get_release_candidates(index: &ReleaseCandidateIndex, artist: &str, album: &str) -> Vec<String> {
+    index
+        .albums
+        .get(&cover_filename(artist, album))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Record the user's release pick for an album.
+///
+/// Returns true if the pick changed.
+pub fn set_chosen_release(
+    index: &mut ReleaseCandidateIndex,
+    artist: &str,
+    album: &str,
+    mbid: &str,
+) -> bool {
+    let previous = index
+        .chosen
+        .insert(cover_filename(artist, album), mbid.to_string());
+    previous.as_deref() != Some(mbid)
+}
+
+/// The release the user picked for an album, if any.
+pub fn get_chosen_release(index: &ReleaseCandidateIndex, artist: &str, album: &str) -> Option<String> {
+    index.chosen.get(&cover_filename(artist, album)).cloned()
+}
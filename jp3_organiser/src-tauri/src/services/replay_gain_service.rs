@@ -0,0 +1,146 @@
+//! ReplayGain 2.0 loudness analysis.
+//!
+//! Loudness is measured with ffmpeg's `ebur128` filter (EBU R128 integrated
+//! loudness and true peak). Track gain comes from each file on its own;
+//! album gain from the album's tracks played back to back, so quiet and
+//! loud tracks on the same album keep their difference.
+//!
+//! library.bin has no room for gain, so values are stored in
+//! `jp3/metadata/replay_gain.json`, keyed by each song's relative path like
+//! `song_hashes.json`. The firmware picks track or album gain from there.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::models::{ReplayGainIndex, SongGain};
+use crate::services::path_index_service::{
+    load_index, rename_entries, retain_entries, save_index, PathIndex,
+};
+
+/// ReplayGain 2.0 reference loudness
+pub const REFERENCE_LUFS: f64 = -18.0;
+
+/// Gain is clamped to this many dB either way (silence would otherwise
+/// ask for +50 dB)
+const MAX_GAIN_DB: f64 = 24.0;
+
+/// Integrated loudness and true peak from one ebur128 run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    pub integrated_lufs: f64,
+    pub true_peak_dbfs: f64,
+}
+
+impl Loudness {
+    /// Gain in dB that brings this loudness to the reference level.
+    pub fn gain_db(&self) -> f32 {
+        let gain = (REFERENCE_LUFS - self.integrated_lufs).clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+        ((gain * 100.0).round() / 100.0) as f32
+    }
+
+    /// True peak as a linear sample value (1.0 = full scale).
+    pub fn peak(&self) -> f32 {
+        10f64.powf(self.true_peak_dbfs / 20.0) as f32
+    }
+}
+
+/// Track gain from `track`, album gain from `album` when it was measured.
+pub fn song_gain(track: &Loudness, album: Option<&Loudness>) -> SongGain {
+    SongGain {
+        track_gain_db: track.gain_db(),
+        track_peak: track.peak(),
+        album_gain_db: album.map(Loudness::gain_db),
+        album_peak: album.map(Loudness::peak),
+    }
+}
+
+/// Measure the loudness of `files` played back to back.
+///
+/// One file gives its track loudness; an album's files give album loudness.
+pub fn measure_loudness(files: &[PathBuf]) -> Result<Loudness, String> {
+    if files.is_empty() {
+        return Err("No files to measure".to_string());
+    }
+    let inputs: String = (0..files.len()).map(|i| format!("[{}:a:0]", i)).collect();
+    let filter = match files.len() {
+        1 => format!("{}ebur128=peak=true", inputs),
+        n => format!("{}concat=n={}:v=0:a=1,ebur128=peak=true", inputs, n),
+    };
+
+    let mut command = Command::new("ffmpeg");
+    command.args(["-hide_banner", "-nostats"]);
+    for file in files {
+        command.arg("-i").arg(file);
+    }
+    let output = command
+        .args(["-filter_complex", &filter, "-f", "null", "-"])
+        .output()
+        .map_err(|e| {
+            format!(
+                "Failed to run ffmpeg: {}. Ensure ffmpeg is installed \
+                 (apt install ffmpeg or brew install ffmpeg)",
+                e
+            )
+        })?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(format!(
+            "ffmpeg failed measuring {}: {}",
+            files[0].display(),
+            stderr.trim()
+        ));
+    }
+    parse_ebur128_summary(&stderr)
+        .ok_or_else(|| format!("No loudness summary from ffmpeg for {}", files[0].display()))
+}
+
+/// Read integrated loudness and true peak from the ebur128 summary that
+/// ffmpeg logs when the filter finishes.
+pub fn parse_ebur128_summary(stderr: &str) -> Option<Loudness> {
+    let summary = &stderr[stderr.rfind("Summary:")?..];
+    let value = |label: &str, unit: &str| {
+        summary.lines().find_map(|line| {
+            let rest = line.trim().strip_prefix(label)?;
+            rest.trim().strip_suffix(unit)?.trim().parse::<f64>().ok()
+        })
+    };
+    Some(Loudness {
+        integrated_lufs: value("I:", "LUFS")?,
+        true_peak_dbfs: value("Peak:", "dBFS")?,
+    })
+}
+
+impl PathIndex for ReplayGainIndex {
+    type Entry = SongGain;
+    const FILE_NAME: &'static str = "replay_gain.json";
+    const DESCRIPTION: &'static str = "replay gain";
+
+    fn entries_mut(&mut self) -> &mut HashMap<String, SongGain> {
+        &mut self.songs
+    }
+}
+
+/// Load the gain index, or an empty index if none exists.
+pub fn load_replay_gain(base_path: &Path) -> Result<ReplayGainIndex, String> {
+    load_index(base_path)
+}
+
+/// Persist the gain index.
+pub fn save_replay_gain(base_path: &Path, index: &ReplayGainIndex) -> Result<(), String> {
+    save_index(base_path, index)
+}
+
+/// Drop gain of songs for which `keep` returns false (deleted songs).
+pub fn retain_replay_gain(base_path: &Path, keep: impl Fn(&str) -> bool) -> Result<(), String> {
+    retain_entries::<ReplayGainIndex>(base_path, keep)
+}
+
+/// Re-key gain after compaction renumbered song files, so the firmware
+/// still finds it.
+pub fn rename_replay_gain(
+    base_path: &Path,
+    renamed: &HashMap<String, String>,
+) -> Result<(), String> {
+    rename_entries::<ReplayGainIndex>(base_path, renamed)
+}
@@ -0,0 +1,292 @@
+//! Printable catalogue of the library (artists -> albums -> tracks).
+//!
+//! Rendered from `ParsedLibrary` into one of two templates below, as HTML
+//! (styled for printing) or Markdown. Placeholders are `{{name}}`; every
+//! value is escaped for the output format before it is substituted.
+//!
+//! Albums are listed under their album artist, oldest first. Tracks whose
+//! artist differs from the album artist (compilations, features) name it.
+
+use std::collections::HashMap;
+
+use crate::models::{ParsedAlbum, ParsedLibrary, ParsedSong, ReportFormat};
+
+const REPORT_TITLE: &str = "JP3 Library";
+
+const HTML_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{{title}}</title>
+<style>
+body { font-family: sans-serif; margin: 2em; color: #222; }
+h2 { border-bottom: 1px solid #ccc; margin-top: 2em; page-break-after: avoid; }
+h3 { margin-bottom: 0.3em; }
+h3 .details { font-weight: normal; color: #666; font-size: 0.85em; }
+section.album { page-break-inside: avoid; }
+table { border-collapse: collapse; width: 100%; }
+td { padding: 2px 8px; }
+td.number, td.duration { text-align: right; width: 4em; color: #666; }
+td.artist { color: #666; }
+@media print { body { margin: 0; } }
+</style>
+</head>
+<body>
+<h1>{{title}}</h1>
+<p class="summary">{{summary}}</p>
+{{body}}</body>
+</html>
+"#;
+
+const MARKDOWN_TEMPLATE: &str = "# {{title}}
+
+{{summary}}
+
+{{body}}";
+
+/// Counts shown in the report summary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReportCounts {
+    pub artists: u32,
+    pub albums: u32,
+    pub songs: u32,
+}
+
+/// An album with its tracks in disc/track order.
+struct AlbumSection<'a> {
+    album: &'a ParsedAlbum,
+    songs: Vec<&'a ParsedSong>,
+}
+
+impl AlbumSection<'_> {
+    fn multi_disc(&self) -> bool {
+        self.songs.iter().any(|song| song.disc_number > 1)
+    }
+
+    fn duration_secs(&self) -> u32 {
+        self.songs.iter().map(|song| song.duration_sec as u32).sum()
+    }
+
+    /// "2020 · 12 tracks · 45:12", leaving out an unknown year.
+    fn details(&self) -> String {
+        let mut parts = Vec::new();
+        if self.album.year > 0 {
+            parts.push(self.album.year.to_string());
+        }
+        parts.push(count_label(self.songs.len() as u32, "track"));
+        parts.push(format_duration(self.duration_secs()));
+        parts.join(" · ")
+    }
+
+    fn track_label(&self, song: &ParsedSong) -> String {
+        match (self.multi_disc(), song.track_number) {
+            (_, 0) => String::new(),
+            (true, track) => format!("{}-{}", song.disc_number.max(1), track),
+            (false, track) => track.to_string(),
+        }
+    }
+}
+
+/// Render the library as a catalogue in `format`.
+pub fn render_library_report(
+    library: &ParsedLibrary,
+    format: ReportFormat,
+) -> (String, ReportCounts) {
+    let sections = group_by_artist(library);
+    let counts = ReportCounts {
+        artists: sections.len() as u32,
+        albums: sections.iter().map(|(_, albums)| albums.len() as u32).sum(),
+        songs: library.songs.len() as u32,
+    };
+    let total_secs: u32 = library
+        .songs
+        .iter()
+        .map(|song| song.duration_sec as u32)
+        .sum();
+    let summary = [
+        count_label(counts.artists, "artist"),
+        count_label(counts.albums, "album"),
+        count_label(counts.songs, "song"),
+        format_duration(total_secs),
+    ]
+    .join(" · ");
+
+    let report = match format {
+        ReportFormat::Html => fill(
+            HTML_TEMPLATE,
+            &[
+                ("title", escape_html(REPORT_TITLE)),
+                ("summary", escape_html(&summary)),
+                ("body", html_body(&sections)),
+            ],
+        ),
+        ReportFormat::Markdown => fill(
+            MARKDOWN_TEMPLATE,
+            &[
+                ("title", escape_markdown(REPORT_TITLE)),
+                ("summary", escape_markdown(&summary)),
+                ("body", markdown_body(&sections)),
+            ],
+        ),
+    };
+    (report, counts)
+}
+
+/// Album artists in library order, each with their albums (oldest first).
+fn group_by_artist(library: &ParsedLibrary) -> Vec<(&str, Vec<AlbumSection<'_>>)> {
+    let mut songs_by_album: HashMap<u32, Vec<&ParsedSong>> = HashMap::new();
+    for song in &library.songs {
+        songs_by_album.entry(song.album_id).or_default().push(song);
+    }
+
+    library
+        .artists
+        .iter()
+        .filter_map(|artist| {
+            let mut albums: Vec<AlbumSection> = library
+                .albums
+                .iter()
+                .filter(|album| album.artist_id == artist.id)
+                .filter_map(|album| {
+                    let mut songs = songs_by_album.get(&album.id)?.clone();
+                    songs.sort_by_key(|song| (song.disc_number, song.track_number, song.id));
+                    Some(AlbumSection { album, songs })
+                })
+                .collect();
+            if albums.is_empty() {
+                return None;
+            }
+            albums.sort_by_key(|section| section.album.year);
+            Some((artist.name.as_str(), albums))
+        })
+        .collect()
+}
+
+fn html_body(sections: &[(&str, Vec<AlbumSection>)]) -> String {
+    let mut body = String::new();
+    for (artist, albums) in sections {
+        body.push_str(&format!("<h2>{}</h2>\n", escape_html(artist)));
+        for section in albums {
+            body.push_str(&format!(
+                "<section class=\"album\">\n<h3>{} <span class=\"details\">{}</span></h3>\n",
+                escape_html(&section.album.name),
+                escape_html(&section.details())
+            ));
+            body.push_str("<table>\n");
+            for song in &section.songs {
+                let featured = if song.artist_id != section.album.artist_id {
+                    escape_html(&song.artist_name)
+                } else {
+                    String::new()
+                };
+                body.push_str(&format!(
+                    "<tr><td class=\"number\">{}</td><td>{}</td><td class=\"artist\">{}</td>\
+                     <td class=\"duration\">{}</td></tr>\n",
+                    escape_html(&section.track_label(song)),
+                    escape_html(&song.title),
+                    featured,
+                    format_duration(song.duration_sec as u32)
+                ));
+            }
+            body.push_str("</table>\n</section>\n");
+        }
+    }
+    body
+}
+
+fn markdown_body(sections: &[(&str, Vec<AlbumSection>)]) -> String {
+    let mut body = String::new();
+    for (artist, albums) in sections {
+        body.push_str(&format!("## {}\n\n", escape_markdown(artist)));
+        for section in albums {
+            body.push_str(&format!(
+                "### {}\n\n_{}_\n\n| # | Title | Duration |\n|--:|---|--:|\n",
+                escape_markdown(&section.album.name),
+                escape_markdown(&section.details())
+            ));
+            for song in &section.songs {
+                let mut title = escape_markdown(&song.title);
+                if song.artist_id != section.album.artist_id {
+                    title.push_str(&format!(" — {}", escape_markdown(&song.artist_name)));
+                }
+                body.push_str(&format!(
+                    "| {} | {} | {} |\n",
+                    section.track_label(song),
+                    title,
+                    format_duration(song.duration_sec as u32)
+                ));
+            }
+            body.push('\n');
+        }
+    }
+    body
+}
+
+/// Substitute `{{key}}` placeholders in one pass, so substituted values
+/// are never themselves scanned for placeholders.
+fn fill(template: &str, values: &[(&str, String)]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let key = &rest[start + 2..start + len];
+        filled.push_str(&rest[..start]);
+        match values.iter().find(|(name, _)| *name == key) {
+            Some((_, value)) => filled.push_str(value),
+            None => filled.push_str(&rest[start..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// "1 track", "12 tracks".
+fn count_label(count: u32, noun: &str) -> String {
+    match count {
+        1 => format!("1 {}", noun),
+        n => format!("{} {}s", n, noun),
+    }
+}
+
+/// "3:07", or "1:02:03" from an hour up.
+pub fn format_duration(secs: u32) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Backslash-escape characters Markdown (and its tables) would interpret.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '#' | '|' | '<' | '>' | '!' | '~'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
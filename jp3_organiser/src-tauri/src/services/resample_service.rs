@@ -0,0 +1,225 @@
+//! Resampling of hi-res audio before fingerprinting.
+//!
+//! Chromaprint works internally at 11025 Hz mono. fpcalc handles common
+//! rates fine, but hi-res sources (88.2/96/192 kHz FLAC or WAV) match
+//! noticeably worse on AcoustID. For those, we decode the fingerprint
+//! window ourselves, downmix to mono, resample to 11025 Hz with rubato and
+//! hand fpcalc a small temporary WAV instead.
+//!
+//! Sources at or below 48 kHz (and formats we can't decode) are left to
+//! fpcalc unchanged.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use rubato::{FftFixedIn, Resampler};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use uuid::Uuid;
+
+/// Sample rate Chromaprint works at internally
+pub const CHROMAPRINT_SAMPLE_RATE: u32 = 11025;
+
+/// Sources above this rate are resampled before fingerprinting
+pub const MAX_PASSTHROUGH_SAMPLE_RATE: u32 = 48000;
+
+/// Resampler input chunk size (frames)
+const RESAMPLE_CHUNK_FRAMES: usize = 1024;
+
+/// A resampled temporary WAV ready for fpcalc.
+///
+/// The file is deleted when this is dropped.
+#[derive(Debug)]
+pub struct ResampledAudio {
+    /// Path to the temporary 16-bit mono WAV
+    pub path: PathBuf,
+    /// Sample rate of the original source
+    pub source_sample_rate: u32,
+    /// Duration of the full original source, in seconds.
+    /// The WAV only covers the fingerprint window, so fpcalc's own
+    /// duration would be wrong for AcoustID lookups.
+    pub source_duration_secs: u32,
+}
+
+impl Drop for ResampledAudio {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Resample a hi-res source for fingerprinting, if it needs it.
+///
+/// Returns `Ok(None)` when the file should go to fpcalc as-is (rate at or
+/// below 48 kHz, or a format we can't decode). Only the first `max_secs`
+/// seconds are decoded, matching fpcalc's `-length`.
+pub fn resample_for_fingerprint(
+    path: &Path,
+    max_secs: u32,
+) -> Result<Option<ResampledAudio>, String> {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) => return Err(format!("Failed to open {}: {}", path.display(), e)),
+    };
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    // Formats outside our symphonia feature set are fpcalc's problem
+    let probed = match symphonia::default::get_probe().format(
+        &hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    ) {
+        Ok(p) => p,
+        Err(_) => return Ok(None),
+    };
+    let mut format = probed.format;
+
+    let track = match format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+    {
+        Some(t) => t,
+        None => return Ok(None),
+    };
+    let sample_rate = match track.codec_params.sample_rate {
+        Some(rate) if rate > MAX_PASSTHROUGH_SAMPLE_RATE => rate,
+        _ => return Ok(None),
+    };
+    let track_id = track.id;
+    let source_duration_secs = track
+        .codec_params
+        .n_frames
+        .map(|frames| (frames / sample_rate as u64) as u32)
+        .unwrap_or(0);
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| format!("Unsupported codec: {}", e))?;
+
+    // Decode the fingerprint window, downmixing to mono as we go
+    let max_frames = max_secs as usize * sample_rate as usize;
+    let mut mono: Vec<f32> = Vec::with_capacity(max_frames);
+    while mono.len() < max_frames {
+        let packet = match format.next_packet() {
+            Ok(p) => p,
+            Err(SymphoniaError::IoError(_)) => break, // end of stream
+            Err(e) => return Err(format!("Failed to read audio: {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(d) => d,
+            Err(SymphoniaError::DecodeError(e)) => {
+                log::warn!("Skipping undecodable packet: {}", e);
+                continue;
+            }
+            Err(e) => return Err(format!("Failed to decode audio: {}", e)),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        mono.extend(
+            buffer
+                .samples()
+                .chunks(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+        );
+    }
+    mono.truncate(max_frames);
+
+    log::info!(
+        "Resampling {} from {} Hz to {} Hz ({} frames)",
+        path.display(),
+        sample_rate,
+        CHROMAPRINT_SAMPLE_RATE,
+        mono.len()
+    );
+    let resampled = resample_mono(&mono, sample_rate, CHROMAPRINT_SAMPLE_RATE)?;
+
+    let temp_path = std::env::temp_dir().join(format!("jp3_fp_{}.wav", Uuid::new_v4()));
+    write_wav_mono_16(&temp_path, &resampled, CHROMAPRINT_SAMPLE_RATE)?;
+
+    Ok(Some(ResampledAudio {
+        path: temp_path,
+        source_sample_rate: sample_rate,
+        source_duration_secs,
+    }))
+}
+
+/// Resample mono samples between two rates with rubato's FFT resampler.
+fn resample_mono(samples: &[f32], rate_in: u32, rate_out: u32) -> Result<Vec<f32>, String> {
+    let mut resampler = FftFixedIn::<f32>::new(
+        rate_in as usize,
+        rate_out as usize,
+        RESAMPLE_CHUNK_FRAMES,
+        2,
+        1,
+    )
+    .map_err(|e| format!("Failed to create resampler: {}", e))?;
+
+    let mut output: Vec<f32> =
+        Vec::with_capacity(samples.len() * rate_out as usize / rate_in as usize + 1);
+    let mut position = 0;
+
+    while samples.len() - position >= resampler.input_frames_next() {
+        let end = position + resampler.input_frames_next();
+        let chunk = resampler
+            .process(&[&samples[position..end]], None)
+            .map_err(|e| format!("Resampling failed: {}", e))?;
+        output.extend_from_slice(&chunk[0]);
+        position = end;
+    }
+
+    if position < samples.len() {
+        let chunk = resampler
+            .process_partial(Some(&[&samples[position..]]), None)
+            .map_err(|e| format!("Resampling failed: {}", e))?;
+        output.extend_from_slice(&chunk[0]);
+    }
+
+    Ok(output)
+}
+
+/// Write mono f32 samples as a 16-bit PCM WAV file.
+fn write_wav_mono_16(path: &Path, samples: &[f32], sample_rate: u32) -> Result<(), String> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut bytes: Vec<u8> = Vec::with_capacity(44 + data_len as usize);
+
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // mono
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * 2).to_le_bytes()); // byte rate
+    bytes.extend_from_slice(&2u16.to_le_bytes()); // block align
+    bytes.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let mut file = File::create(path)
+        .map_err(|e| format!("Failed to create temp WAV {}: {}", path.display(), e))?;
+    file.write_all(&bytes)
+        .map_err(|e| format!("Failed to write temp WAV: {}", e))
+}
@@ -0,0 +1,231 @@
+//! External programs run around saves to the library.
+//!
+//! Power users can plug custom naming, tagging or notifications into every
+//! save without forking the app. A hook is a program that receives a JSON
+//! document on stdin:
+//!
+//! - **pre-save** runs before anything is written, with the pending files.
+//!   It may print the same document back with changed `metadata`, or a
+//!   `fileName` (stem, no extension) to name the file's copy in `jp3/music`.
+//!   Printing nothing keeps the files as they are. A non-zero exit, or
+//!   output that isn't the document with the same files, cancels the save.
+//! - **post-save** runs in the background once the save is done, with the
+//!   songs it added. Its failures are only logged.
+//!
+//! A hook that runs longer than the configured timeout is killed. Hooks are
+//! app settings (see `commands::config`), never read from the library, so a
+//! library card from elsewhere can't bring programs of its own.
+
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{
+    AudioMetadata, HookCommand, MetadataSource, SaveHookSettings, DEFAULT_HOOK_TIMEOUT_SECS,
+};
+
+static SAVE_HOOKS: Lazy<Mutex<SaveHookSettings>> =
+    Lazy::new(|| Mutex::new(SaveHookSettings::default()));
+
+/// How often a running hook is checked for exit
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Current save hooks.
+pub fn save_hooks() -> SaveHookSettings {
+    SAVE_HOOKS.lock().unwrap().clone()
+}
+
+/// Change the save hooks. Saves already running keep the hooks they started with.
+pub fn set_save_hooks(settings: SaveHookSettings) {
+    *SAVE_HOOKS.lock().unwrap() = settings;
+}
+
+/// A file about to be saved, as the pre-save hook sees it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingSave {
+    pub source_path: String,
+    pub metadata: AudioMetadata,
+    #[serde(default)]
+    pub metadata_source: MetadataSource,
+    /// Name for the copy in `jp3/music`, without extension (None = the
+    /// library's naming scheme)
+    #[serde(default)]
+    pub file_name: Option<String>,
+}
+
+/// A song the save added, as the post-save hook sees it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSong {
+    pub song_id: u32,
+    /// Path as stored in library.bin (relative to `jp3/music` unless adopted)
+    pub path: String,
+    /// Where the audio is on disk
+    pub file_path: String,
+    pub source_path: String,
+    pub metadata: AudioMetadata,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PreSaveInput<'a> {
+    event: &'static str,
+    base_path: String,
+    files: &'a [PendingSave],
+}
+
+#[derive(Deserialize)]
+struct PreSaveOutput {
+    files: Vec<PendingSave>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PostSaveInput {
+    event: &'static str,
+    base_path: String,
+    songs: Vec<SavedSong>,
+}
+
+/// Run the pre-save hook over `files`, applying what it changes.
+///
+/// Does nothing when no pre-save hook is configured.
+pub fn run_pre_save_hook(
+    settings: &SaveHookSettings,
+    base_path: &Path,
+    files: &mut Vec<PendingSave>,
+) -> Result<(), String> {
+    let Some(hook) = &settings.pre_save else {
+        return Ok(());
+    };
+    let input = serde_json::to_vec(&PreSaveInput {
+        event: "pre-save",
+        base_path: base_path.to_string_lossy().to_string(),
+        files,
+    })
+    .map_err(|e| format!("Failed to serialize pre-save input: {}", e))?;
+
+    let output = run_hook(hook, &input, hook_timeout(settings))
+        .map_err(|e| format!("Pre-save hook cancelled the save: {}", e))?;
+    if output.trim().is_empty() {
+        return Ok(());
+    }
+    let changed: PreSaveOutput = serde_json::from_str(&output)
+        .map_err(|e| format!("Pre-save hook printed invalid JSON: {}", e))?;
+    let same_files = changed.files.len() == files.len()
+        && changed
+            .files
+            .iter()
+            .zip(files.iter())
+            .all(|(changed, file)| changed.source_path == file.source_path);
+    if !same_files {
+        return Err("Pre-save hook must print back the same files, in order".to_string());
+    }
+    *files = changed.files;
+    Ok(())
+}
+
+/// Start the post-save hook for `songs` in the background.
+///
+/// Does nothing when no post-save hook is configured or nothing was saved.
+pub fn spawn_post_save_hook(settings: &SaveHookSettings, base_path: &Path, songs: Vec<SavedSong>) {
+    let Some(hook) = settings.post_save.clone() else {
+        return;
+    };
+    if songs.is_empty() {
+        return;
+    }
+    let input = PostSaveInput {
+        event: "post-save",
+        base_path: base_path.to_string_lossy().to_string(),
+        songs,
+    };
+    let timeout = hook_timeout(settings);
+    thread::spawn(move || {
+        let result = serde_json::to_vec(&input)
+            .map_err(|e| e.to_string())
+            .and_then(|input| run_hook(&hook, &input, timeout));
+        if let Err(e) = result {
+            log::warn!("Post-save hook failed: {}", e);
+        }
+    });
+}
+
+fn hook_timeout(settings: &SaveHookSettings) -> Duration {
+    Duration::from_secs(settings.timeout_secs.unwrap_or(DEFAULT_HOOK_TIMEOUT_SECS) as u64)
+}
+
+/// Run `hook` with `input` on stdin and return what it printed.
+///
+/// Fails if it can't be started, exits non-zero (with its stderr) or runs
+/// past `timeout` (it is killed).
+pub fn run_hook(hook: &HookCommand, input: &[u8], timeout: Duration) -> Result<String, String> {
+    let mut child = Command::new(&hook.program)
+        .args(&hook.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", hook.program, e))?;
+
+    // Feed and drain the pipes on their own threads, so a hook that writes
+    // a lot before reading all its input can't deadlock against us
+    let mut stdin = child.stdin.take();
+    let input = input.to_vec();
+    let writer = thread::spawn(move || {
+        if let Some(stdin) = stdin.as_mut() {
+            // A hook may exit without reading its input
+            let _ = stdin.write_all(&input);
+        }
+    });
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "{} did not finish within {}s",
+                    hook.program,
+                    timeout.as_secs()
+                ));
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(format!("Failed to wait for {}: {}", hook.program, e)),
+        }
+    };
+    let _ = writer.join();
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    if !status.success() {
+        return Err(format!(
+            "{} exited with {}: {}",
+            hook.program,
+            status,
+            stderr.trim()
+        ));
+    }
+    Ok(stdout)
+}
+
+fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_string(&mut text);
+        }
+        text
+    })
+}
@@ -0,0 +1,182 @@
+//! Library search with accent folding and romanized keys.
+//!
+//! Each artist, album and song is indexed under its folded display name
+//! ("Beyoncé" -> "beyonce") plus its romanized key when transliteration
+//! is enabled ("椎名林檎" -> "shiina ringo"). Queries are folded the same
+//! way, so "beyonce", "sigur ros" and "shiina" all find their entries.
+//! Songs are also indexed under their artist and album names.
+//!
+//! Building the index means parsing the whole library, so one index per
+//! library is kept in memory and reused until library.bin's generation
+//! changes (or the transliterations are edited).
+//!
+//! # Scoring
+//! The best score over an entry's keys is used:
+//! - 100: exact match
+//! - 80: key starts with the query
+//! - 60: a word in the key starts with the query
+//! - 50: key contains the query
+//!
+//! A song matched only by its artist or album scores half, so songs whose
+//! title matches rank first.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+
+use crate::models::{LibrarySearchResults, ParsedAlbum, ParsedArtist, ParsedLibrary, ParsedSong};
+use crate::services::sort_name_service::fold_text;
+
+/// Built indexes by library base path, with the generation they were built from.
+type IndexCache = HashMap<PathBuf, (u32, Arc<SearchIndex>)>;
+
+static INDEXES: Lazy<Mutex<IndexCache>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Entry paired with its folded search keys.
+struct Indexed<T> {
+    item: T,
+    sort_key: String,
+    keys: Vec<String>,
+    /// Keys from related names (a song's artist and album), scored at half
+    related_keys: Vec<String>,
+}
+
+/// Searchable snapshot of a parsed library.
+pub struct SearchIndex {
+    artists: Vec<Indexed<ParsedArtist>>,
+    albums: Vec<Indexed<ParsedAlbum>>,
+    songs: Vec<Indexed<ParsedSong>>,
+}
+
+impl SearchIndex {
+    /// Build search keys for every entry in the library.
+    pub fn build(library: ParsedLibrary) -> Self {
+        Self {
+            artists: library
+                .artists
+                .into_iter()
+                .map(|a| Indexed {
+                    keys: search_keys(&a.name, a.romanized.as_deref()),
+                    related_keys: Vec::new(),
+                    sort_key: a.sort_name.clone(),
+                    item: a,
+                })
+                .collect(),
+            albums: library
+                .albums
+                .into_iter()
+                .map(|a| Indexed {
+                    keys: search_keys(&a.name, a.romanized.as_deref()),
+                    related_keys: Vec::new(),
+                    sort_key: a.sort_name.clone(),
+                    item: a,
+                })
+                .collect(),
+            songs: library
+                .songs
+                .into_iter()
+                .map(|s| Indexed {
+                    keys: search_keys(&s.title, s.romanized_title.as_deref()),
+                    related_keys: vec![normalize(&s.artist_name), normalize(&s.album_name)],
+                    sort_key: fold_text(&s.title),
+                    item: s,
+                })
+                .collect(),
+        }
+    }
+
+    /// Find up to `limit` matches per category, best matches first.
+    pub fn search(&self, query: &str, limit: usize) -> LibrarySearchResults {
+        let query = normalize(query);
+        if query.is_empty() {
+            return LibrarySearchResults::default();
+        }
+
+        let artists = rank(&self.artists, &query, limit);
+        let albums = rank(&self.albums, &query, limit);
+        let songs = rank(&self.songs, &query, limit);
+        let total_count = (artists.len() + albums.len() + songs.len()) as u32;
+
+        LibrarySearchResults {
+            artists,
+            albums,
+            songs,
+            total_count,
+        }
+    }
+}
+
+/// The search index for a library at `generation`, building it with `load`
+/// if there is none or it was built from an older generation.
+pub fn cached_index(
+    base_path: &Path,
+    generation: u32,
+    load: impl FnOnce() -> Result<ParsedLibrary, String>,
+) -> Result<Arc<SearchIndex>, String> {
+    if let Some((built_from, index)) = INDEXES.lock().unwrap().get(base_path) {
+        if *built_from == generation {
+            return Ok(Arc::clone(index));
+        }
+    }
+
+    let library = load()?;
+    let built_from = library.generation;
+    let index = Arc::new(SearchIndex::build(library));
+    INDEXES
+        .lock()
+        .unwrap()
+        .insert(base_path.to_path_buf(), (built_from, Arc::clone(&index)));
+    Ok(index)
+}
+
+/// Drop a library's cached index, for changes that don't touch library.bin.
+pub fn invalidate_index(base_path: &Path) {
+    INDEXES.lock().unwrap().remove(base_path);
+}
+
+/// Fold and collapse whitespace.
+fn normalize(text: &str) -> String {
+    fold_text(text).split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn search_keys(name: &str, romanized: Option<&str>) -> Vec<String> {
+    let mut keys = vec![normalize(name)];
+    if let Some(romanized) = romanized {
+        keys.push(normalize(romanized));
+    }
+    keys
+}
+
+/// Score a key against a normalized query (0 = no match).
+pub fn match_score(key: &str, query: &str) -> u32 {
+    if key == query {
+        100
+    } else if key.starts_with(query) {
+        80
+    } else if key.split(' ').any(|word| word.starts_with(query)) {
+        60
+    } else if key.contains(query) {
+        50
+    } else {
+        0
+    }
+}
+
+fn rank<T: Clone>(entries: &[Indexed<T>], query: &str, limit: usize) -> Vec<T> {
+    let mut hits: Vec<(u32, &Indexed<T>)> = entries
+        .iter()
+        .filter_map(|entry| {
+            let direct = entry.keys.iter().map(|key| match_score(key, query));
+            let related = entry.related_keys.iter().map(|key| match_score(key, query) / 2);
+            let score = direct.chain(related).max().unwrap_or(0);
+            (score > 0).then_some((score, entry))
+        })
+        .collect();
+    hits.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.sort_key.cmp(&b.1.sort_key)));
+    hits.into_iter()
+        .take(limit)
+        .map(|(_, entry)| entry.item.clone())
+        .collect()
+}
@@ -0,0 +1,63 @@
+//! Per-song match confidence.
+//!
+//! The ranking service knows how strong a fingerprint match was (AcoustID
+//! score and sources count), and MusicBrainz searches report a score of
+//! their own. library.bin has no room for these, so they are stored in
+//! `jp3/metadata/song_confidence.json`, keyed by each song's relative path,
+//! and joined onto songs when the library is loaded. Low scores drive the
+//! review workflow (`get_low_confidence_songs`).
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::models::{MatchConfidence, SongConfidenceIndex};
+use crate::services::path_index_service::{
+    load_index, record_entries, rename_entries, retain_entries, save_index, PathIndex,
+};
+
+impl PathIndex for SongConfidenceIndex {
+    type Entry = MatchConfidence;
+    const FILE_NAME: &'static str = "song_confidence.json";
+    const DESCRIPTION: &'static str = "song confidence";
+
+    fn entries_mut(&mut self) -> &mut HashMap<String, MatchConfidence> {
+        &mut self.songs
+    }
+}
+
+/// Load the confidence index, or an empty index if none exists.
+pub fn load_song_confidence(base_path: &Path) -> Result<SongConfidenceIndex, String> {
+    load_index(base_path)
+}
+
+/// Persist the confidence index.
+pub fn save_song_confidence(base_path: &Path, index: &SongConfidenceIndex) -> Result<(), String> {
+    save_index(base_path, index)
+}
+
+/// Record confidences for newly saved songs, by relative path.
+pub fn record_song_confidence(
+    base_path: &Path,
+    songs: Vec<(String, MatchConfidence)>,
+) -> Result<(), String> {
+    record_entries::<SongConfidenceIndex>(base_path, songs)
+}
+
+/// Drop entries for songs whose metadata no longer comes from a match
+/// (edited by the user) or that no longer exist.
+///
+/// Keeps only paths for which `keep` returns true.
+pub fn retain_song_confidence(
+    base_path: &Path,
+    keep: impl Fn(&str) -> bool,
+) -> Result<(), String> {
+    retain_entries::<SongConfidenceIndex>(base_path, keep)
+}
+
+/// Follow renumbered song files (old → new relative path).
+pub fn rename_song_confidence(
+    base_path: &Path,
+    renamed: &HashMap<String, String>,
+) -> Result<(), String> {
+    rename_entries::<SongConfidenceIndex>(base_path, renamed)
+}
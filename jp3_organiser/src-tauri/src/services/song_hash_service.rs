@@ -0,0 +1,66 @@
+//! Per-song content hashes.
+//!
+//! Each song's audio file is hashed (SHA-256) when it is saved, so later
+//! checks can tell a changed or damaged file from a good one and spot the
+//! same audio being imported twice under different tags. library.bin has no
+//! room for hashes, so they are stored in `jp3/metadata/song_hashes.json`,
+//! keyed by each song's relative path like `song_confidence.json`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::models::SongHashIndex;
+use crate::services::path_index_service::{
+    load_index, record_entries, rename_entries, retain_entries, save_index, PathIndex,
+};
+
+impl PathIndex for SongHashIndex {
+    type Entry = String;
+    const FILE_NAME: &'static str = "song_hashes.json";
+    const DESCRIPTION: &'static str = "song hashes";
+
+    fn entries_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.songs
+    }
+}
+
+/// Load the hash index, or an empty index if none exists.
+pub fn load_song_hashes(base_path: &Path) -> Result<SongHashIndex, String> {
+    load_index(base_path)
+}
+
+/// Persist the hash index.
+pub fn save_song_hashes(base_path: &Path, index: &SongHashIndex) -> Result<(), String> {
+    save_index(base_path, index)
+}
+
+/// Record hashes for newly saved songs, by relative path.
+pub fn record_song_hashes(base_path: &Path, songs: Vec<(String, String)>) -> Result<(), String> {
+    record_entries::<SongHashIndex>(base_path, songs)
+}
+
+/// Drop hashes of songs for which `keep` returns false (deleted songs).
+pub fn retain_song_hashes(base_path: &Path, keep: impl Fn(&str) -> bool) -> Result<(), String> {
+    retain_entries::<SongHashIndex>(base_path, keep)
+}
+
+/// Re-key hashes after compaction renumbered song files.
+pub fn rename_song_hashes(
+    base_path: &Path,
+    renamed: &HashMap<String, String>,
+) -> Result<(), String> {
+    rename_entries::<SongHashIndex>(base_path, renamed)
+}
+
+/// Hash → song ID for songs with a recorded hash.
+///
+/// `song_paths` are (song ID, relative path) pairs of the songs to include.
+pub fn songs_by_hash<'a>(
+    index: &SongHashIndex,
+    song_paths: impl IntoIterator<Item = (u32, &'a str)>,
+) -> HashMap<String, u32> {
+    song_paths
+        .into_iter()
+        .filter_map(|(song_id, path)| Some((index.songs.get(path)?.clone(), song_id)))
+        .collect()
+}
@@ -0,0 +1,138 @@
+//! Sort keys for artist, album and playlist names.
+//!
+//! Names are sorted by a normalized key rather than their display text, so
+//! "The Wall" files under W and "Björk" sits next to "Bjorn" on both the
+//! desktop and the device. Keys are computed once at save time and stored
+//! in library.bin (format v2) so the device never has to normalize text.
+//!
+//! # Normalization
+//! - Accents are folded ("Björk" -> "bjork") and text is lowercased
+//! - Leading punctuation is dropped ("...And Justice" -> "and justice")
+//! - A leading "The", "A" or "An" is dropped when more words follow
+//! - A leading number is zero-padded so "2 Unlimited" sorts before "10cc"
+//!
+//! # Collation
+//! The app orders keys with the Unicode Collation Algorithm (CLDR root
+//! order, via `feruca`) rather than by bytes, so letters that don't
+//! decompose into a base letter and an accent ("Ø", "Æ", "ß") sort next to
+//! their base letters instead of after "z". The firmware can only compare
+//! bytes, so it lists artists and albums in table order, which compaction
+//! writes in this same collated order.
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use feruca::{Collator, Tailoring};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::models::{LibrarySort, ParsedSong, SongSortKey};
+
+/// Leading articles ignored when sorting
+const ARTICLES: [&str; 3] = ["the", "a", "an"];
+
+/// Width leading numbers are padded to
+const NUMBER_WIDTH: usize = 8;
+
+thread_local! {
+    /// Collation needs scratch buffers, so each thread keeps its own collator.
+    /// Spaces and punctuation count ("non-ignorable"), so "Pink Floyd" still
+    /// sorts before "Pinkerton".
+    static COLLATOR: RefCell<Collator> =
+        RefCell::new(Collator::new(Tailoring::default(), false, true));
+}
+
+/// Compare two strings in Unicode collation order (CLDR root).
+///
+/// Strings that collate the same are ordered by their bytes, so this is a
+/// total order.
+pub fn collate(a: &str, b: &str) -> Ordering {
+    COLLATOR.with(|collator| collator.borrow_mut().collate(a, b))
+}
+
+/// Fold accents and case ("Beyoncé" -> "beyonce").
+///
+/// Shared with search so queries typed without accents still match.
+pub fn fold_text(text: &str) -> String {
+    text.nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Compute the sort key for a display name.
+pub fn sort_name(name: &str) -> String {
+    let folded = fold_text(name);
+
+    let words: Vec<&str> = folded
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .split_whitespace()
+        .collect();
+
+    let words = match words.split_first() {
+        Some((first, rest)) if !rest.is_empty() && ARTICLES.contains(first) => rest,
+        _ => &words[..],
+    };
+
+    pad_leading_number(&words.join(" "))
+}
+
+/// Compare two names by collated sort key, falling back to the display text.
+pub fn compare_names(a: &str, b: &str) -> Ordering {
+    collate(&sort_name(a), &sort_name(b)).then_with(|| collate(a, b))
+}
+
+/// Order songs by `sort`.
+///
+/// Text keys go through `sort_name` and are collated, so song titles drop
+/// their articles and order the same way artist and album names do. Ties
+/// fall back to the disc and track, then the song ID.
+pub fn sort_songs(songs: &mut [ParsedSong], sort: LibrarySort) {
+    // [title, artist, album], computed once per song
+    let keys: HashMap<u32, [String; 3]> = songs
+        .iter()
+        .map(|song| {
+            let keys = [&song.title, &song.artist_name, &song.album_name].map(|s| sort_name(s));
+            (song.id, keys)
+        })
+        .collect();
+    songs.sort_by(|a, b| {
+        // Songs saved in the same second stay in ID order
+        if sort.key == SongSortKey::DateAdded {
+            return a.date_added.cmp(&b.date_added).then(a.id.cmp(&b.id));
+        }
+        // A number to compare first (if any), then the text keys in this order
+        let (a_number, b_number, fields) = match sort.key {
+            SongSortKey::Year => (a.year, b.year, [1, 2, 0]),
+            SongSortKey::Duration => (a.duration_sec, b.duration_sec, [0, 1, 2]),
+            SongSortKey::Artist => (0, 0, [1, 2, 0]),
+            SongSortKey::Album => (0, 0, [2, 1, 0]),
+            SongSortKey::Title | SongSortKey::DateAdded => (0, 0, [0, 1, 2]),
+        };
+        let (a_keys, b_keys) = (&keys[&a.id], &keys[&b.id]);
+        let text = || {
+            fields.iter().fold(Ordering::Equal, |order, &i| {
+                order.then_with(|| collate(&a_keys[i], &b_keys[i]))
+            })
+        };
+        let position = |song: &ParsedSong| (song.disc_number, song.track_number);
+        a_number
+            .cmp(&b_number)
+            .then_with(text)
+            .then_with(|| position(a).cmp(&position(b)))
+            .then(a.id.cmp(&b.id))
+    });
+    if sort.descending {
+        songs.reverse();
+    }
+}
+
+/// Zero-pad a leading run of ASCII digits to `NUMBER_WIDTH`.
+fn pad_leading_number(key: &str) -> String {
+    let digits = key.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 || digits >= NUMBER_WIDTH {
+        return key.to_string();
+    }
+    format!("{}{}", "0".repeat(NUMBER_WIDTH - digits), key)
+}
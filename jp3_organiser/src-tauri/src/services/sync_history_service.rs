@@ -0,0 +1,44 @@
+//! History of finished exports, per device.
+//!
+//! Each export that finishes (including one finished by `resume_sync`)
+//! appends a record to `jp3/metadata/sync_history.json` in the source
+//! library: the drive's device ID, when it was written, how much was copied
+//! and whether the exported library passed verification. Only the most
+//! recent `MAX_SYNC_RECORDS` are kept.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::SyncRecord;
+
+const SYNC_HISTORY_FILE: &str = "sync_history.json";
+
+/// Records kept before the oldest are dropped
+pub const MAX_SYNC_RECORDS: usize = 200;
+
+fn history_path(base_path: &Path) -> PathBuf {
+    base_path.join("jp3").join("metadata").join(SYNC_HISTORY_FILE)
+}
+
+/// Every recorded export, oldest first.
+pub fn load_sync_history(base_path: &Path) -> Result<Vec<SyncRecord>, String> {
+    let path = history_path(base_path);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read sync history: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse sync history: {}", e))
+}
+
+/// Append a finished export, dropping the oldest records past the limit.
+pub fn record_sync(base_path: &Path, record: SyncRecord) -> Result<(), String> {
+    let mut history = load_sync_history(base_path)?;
+    history.push(record);
+    let excess = history.len().saturating_sub(MAX_SYNC_RECORDS);
+    history.drain(..excess);
+    let data = serde_json::to_string_pretty(&history)
+        .map_err(|e| format!("Failed to serialize sync history: {}", e))?;
+    fs::write(history_path(base_path), data)
+        .map_err(|e| format!("Failed to write sync history: {}", e))
+}
@@ -0,0 +1,96 @@
+//! Persisted plan for an export that hasn't finished.
+//!
+//! `export_selection` writes its arguments to
+//! `jp3/metadata/pending_sync.json` in the source library before copying,
+//! and adds each song file once it is fully copied. If the copy is
+//! interrupted (card pulled, app closed) the plan survives, and
+//! `resume_sync` carries on from it: files already on the card are checked
+//! by hash instead of being copied again. The plan is removed when the
+//! export finishes or is discarded.
+//!
+//! `plan_device_sync` previews an export (or resuming one) as a
+//! `SyncPlanReport`; `render_sync_plan` turns that into plain text for bug
+//! reports.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::{PendingSync, SyncAction, SyncPlanReport, SyncReason};
+
+const PENDING_SYNC_FILE: &str = "pending_sync.json";
+
+fn plan_path(base_path: &Path) -> PathBuf {
+    base_path.join("jp3").join("metadata").join(PENDING_SYNC_FILE)
+}
+
+/// Load the pending plan, if an export was interrupted.
+pub fn load_pending_sync(base_path: &Path) -> Result<Option<PendingSync>, String> {
+    let path = plan_path(base_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read pending sync: {}", e))?;
+    serde_json::from_str(&data)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse pending sync: {}", e))
+}
+
+/// Persist the plan, replacing any earlier one.
+pub fn save_pending_sync(base_path: &Path, plan: &PendingSync) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(plan)
+        .map_err(|e| format!("Failed to serialize pending sync: {}", e))?;
+    fs::write(plan_path(base_path), data)
+        .map_err(|e| format!("Failed to write pending sync: {}", e))
+}
+
+/// Remove the plan once its export has finished or been discarded.
+pub fn clear_pending_sync(base_path: &Path) -> Result<(), String> {
+    let path = plan_path(base_path);
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove pending sync: {}", e))?;
+    }
+    Ok(())
+}
+
+/// A sync plan as plain text: a summary, then one line per file.
+pub fn render_sync_plan(report: &SyncPlanReport) -> String {
+    let mut text = format!("Sync plan for {}\n", report.dest_path);
+    if report.resumes_pending {
+        text.push_str("Resumes an interrupted export\n");
+    }
+    text.push_str(&format!(
+        "Copy {} files ({} bytes), delete {} files ({} bytes), skip {} files\n\n",
+        report.files_to_copy,
+        report.bytes_to_copy,
+        report.files_to_delete,
+        report.bytes_to_free,
+        report.files_skipped
+    ));
+    for file in &report.files {
+        let action = match file.action {
+            SyncAction::Copy => "COPY",
+            SyncAction::Delete => "DELETE",
+            SyncAction::Skip => "SKIP",
+        };
+        let reason = match file.reason {
+            SyncReason::New => "new",
+            SyncReason::ChangedHash => "changed hash",
+            SyncReason::AlreadyCopied => "already copied",
+            SyncReason::OrphanedOnDevice => "orphaned on device",
+            SyncReason::Excluded => "excluded",
+        };
+        text.push_str(&format!("{:<6} {:<18} {}", action, reason, file.path));
+        if file.bytes > 0 {
+            text.push_str(&format!(" ({} bytes)", file.bytes));
+        }
+        if file.transcode {
+            text.push_str(" [transcode]");
+        }
+        if let Some(song_id) = file.song_id {
+            text.push_str(&format!(" song {}", song_id));
+        }
+        text.push('\n');
+    }
+    text
+}
@@ -0,0 +1,149 @@
+//! Rewriting MP3 tags to a clean ID3v2.4 set.
+//!
+//! Files from some sources carry kilobytes of COMM, PRIV and other junk
+//! frames, often next to stale ID3v1 and APE tags, and the device's tag
+//! parser has tripped over odd frames before. Cleanup replaces every tag in
+//! the file with one unpadded ID3v2.4 tag holding only title, artist, album,
+//! track, year and the front cover. The audio is copied through untouched.
+//!
+//! The file is rewritten to a `.part` copy and renamed over the original,
+//! so an interrupted cleanup never leaves a half-written song.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use id3::frame::{Picture, PictureType};
+use id3::{Encoder, Tag, TagLike, Timestamp, Version};
+
+use crate::services::tag_reader_service::tagged_audio_range;
+
+/// The fields a clean tag keeps (besides the cover, which comes from the
+/// file's existing tag).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CleanTagFields {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    /// 0 = unknown
+    pub track_number: u32,
+    /// 0 = unknown
+    pub year: i32,
+}
+
+/// What cleaning one file did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagCleanupOutcome {
+    /// The file already had exactly the clean tag and nothing else
+    AlreadyClean,
+    Cleaned {
+        bytes_before: u64,
+        bytes_after: u64,
+    },
+}
+
+/// Build the clean tag, keeping the front cover (or else the first
+/// picture) of `existing`.
+pub fn clean_tag(fields: &CleanTagFields, existing: Option<&Tag>) -> Tag {
+    let mut tag = Tag::with_version(Version::Id3v24);
+    if !fields.title.is_empty() {
+        tag.set_title(fields.title.as_str());
+    }
+    if !fields.artist.is_empty() {
+        tag.set_artist(fields.artist.as_str());
+    }
+    if !fields.album.is_empty() {
+        tag.set_album(fields.album.as_str());
+    }
+    if fields.track_number > 0 {
+        tag.set_track(fields.track_number);
+    }
+    if fields.year > 0 {
+        tag.set_date_recorded(Timestamp {
+            year: fields.year,
+            month: None,
+            day: None,
+            hour: None,
+            minute: None,
+            second: None,
+        });
+    }
+
+    let cover = existing.and_then(|existing| {
+        existing
+            .pictures()
+            .find(|p| p.picture_type == PictureType::CoverFront)
+            .or_else(|| existing.pictures().next())
+    });
+    if let Some(cover) = cover {
+        tag.add_frame(Picture {
+            mime_type: cover.mime_type.clone(),
+            picture_type: PictureType::CoverFront,
+            description: String::new(),
+            data: cover.data.clone(),
+        });
+    }
+    tag
+}
+
+/// Replace every tag in the MP3 at `path` with the clean ID3v2.4 tag.
+pub fn clean_file_tags(path: &Path, fields: &CleanTagFields) -> Result<TagCleanupOutcome, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let bytes_before = file
+        .metadata()
+        .map_err(|e| format!("Failed to read file size: {}", e))?
+        .len();
+    // An unreadable tag is replaced like any other
+    let existing = Tag::read_from2(&mut file).ok();
+
+    let mut header = Vec::new();
+    Encoder::new()
+        .version(Version::Id3v24)
+        .padding(0)
+        .encode(&clean_tag(fields, existing.as_ref()), &mut header)
+        .map_err(|e| format!("Failed to encode tag: {}", e))?;
+    let audio = tagged_audio_range(&mut file).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    // Already exactly the clean tag, followed by the audio and nothing else
+    if audio.start == header.len() as u64 && audio.end == bytes_before {
+        let mut current = vec![0u8; header.len()];
+        file.seek(SeekFrom::Start(0))
+            .and_then(|_| file.read_exact(&mut current))
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if current == header {
+            return Ok(TagCleanupOutcome::AlreadyClean);
+        }
+    }
+
+    let part_path = path.with_extension("mp3.part");
+    let written = write_cleaned(&mut file, &part_path, &header, audio.start, audio.end);
+    if let Err(e) = written {
+        let _ = fs::remove_file(&part_path);
+        return Err(format!("Failed to write cleaned file: {}", e));
+    }
+    drop(file);
+    fs::rename(&part_path, path).map_err(|e| {
+        let _ = fs::remove_file(&part_path);
+        format!("Failed to replace file: {}", e)
+    })?;
+
+    Ok(TagCleanupOutcome::Cleaned {
+        bytes_before,
+        bytes_after: header.len() as u64 + (audio.end - audio.start),
+    })
+}
+
+/// Write `header` and then bytes `start..end` of `source` to `dest`.
+fn write_cleaned(
+    source: &mut File,
+    dest: &Path,
+    header: &[u8],
+    start: u64,
+    end: u64,
+) -> io::Result<()> {
+    let mut out = File::create(dest)?;
+    out.write_all(header)?;
+    source.seek(SeekFrom::Start(start))?;
+    io::copy(&mut source.take(end - start), &mut out)?;
+    out.sync_all()
+}
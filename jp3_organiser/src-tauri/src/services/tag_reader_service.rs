@@ -0,0 +1,313 @@
+//! Tag reading for audio files, across every tag type they carry.
+//!
+//! Old MP3 rips often have only an ID3v1 tag, or an APEv2 tag written by
+//! foobar2000 or mp3gain, which reading ID3v2 alone treats as untagged.
+//! Tags are read with the `lofty` crate: ID3v2 (2.2-2.4), APE, ID3v1,
+//! Vorbis comments (FLAC, Ogg, Opus), MP4 atoms (M4A), and RIFF INFO/AIFF
+//! text chunks. Ratings and play counts come from the ID3v2 POPM and PCNT
+//! frames, which lofty only passes through raw, so those two are read with
+//! the `id3` crate.
+//!
+//! # Precedence
+//! When several tags coexist, each field comes from the first tag that has
+//! it, in `TagKind` order: the format's own tag (ID3v2, MP4, Vorbis), then
+//! APE and the WAV/AIFF chunks, then ID3v1. ID3v1 is last because its text
+//! fields are cut off at 30 bytes and its genre is a fixed list.
+//!
+//! # Field Mapping
+//! | Field | ID3v2 | MP4 | Vorbis | APE | ID3v1 |
+//! |-------|-------|-----|--------|-----|-------|
+//! | Title | `TIT2` | `©nam` | `TITLE` | `Title` | title |
+//! | Artist | `TPE1` | `©ART` | `ARTIST` | `Artist` | artist |
+//! | Album | `TALB` | `©alb` | `ALBUM` | `Album` | album |
+//! | Album artist | `TPE2` | `aART` | `ALBUMARTIST` | `Album Artist` | - |
+//! | Track / disc | `TRCK` / `TPOS` | `trkn` / `disk` | `TRACKNUMBER` / `DISCNUMBER` | `Track` / `Disc` | track (v1.1) |
+//! | Year | `TDRC` / `TYER` | `©day` | `DATE` / `YEAR` | `Year` | year |
+//! | Genre | `TCON` | `©gen` | `GENRE` | `Genre` | genre number |
+//! | Duration | `TLEN` | - | - | - | - |
+//! | Rating / play count | `POPM` / `PCNT` | - | - | - | - |
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+use id3::TagLike;
+use lofty::config::ParseOptions;
+use lofty::error::{ErrorKind, LoftyError};
+use lofty::file::TaggedFile;
+use lofty::prelude::{Accessor, ItemKey, TaggedFileExt};
+use lofty::probe::Probe;
+use lofty::tag::{Tag, TagType};
+
+use crate::models::AudioMetadata;
+
+/// APE tag header and footer length
+const APE_FOOTER_LEN: u64 = 32;
+
+/// APE footer flag: the tag also starts with a header
+const APE_HAS_HEADER: u32 = 1 << 31;
+
+/// ID3v2 header (and footer) length
+const ID3V2_HEADER_LEN: u64 = 10;
+
+/// ID3v2 header flag: the tag ends with a copy of the header
+const ID3V2_HAS_FOOTER: u8 = 0x10;
+
+/// ID3v1 tag length; an APE tag ends right before it
+const ID3V1_LEN: u64 = 128;
+
+/// Largest APE tag skipped over (binary items such as cover art can be large)
+const MAX_APE_TAG_LEN: u64 = 16 * 1024 * 1024;
+
+/// A tag type found in a file, in precedence order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TagKind {
+    Id3v2,
+    Mp4,
+    Vorbis,
+    Ape,
+    RiffInfo,
+    AiffText,
+    Id3v1,
+}
+
+impl TagKind {
+    fn from_lofty(tag_type: TagType) -> Option<Self> {
+        match tag_type {
+            TagType::Id3v2 => Some(TagKind::Id3v2),
+            TagType::Mp4Ilst => Some(TagKind::Mp4),
+            TagType::VorbisComments => Some(TagKind::Vorbis),
+            TagType::Ape => Some(TagKind::Ape),
+            TagType::RiffInfo => Some(TagKind::RiffInfo),
+            TagType::AiffText => Some(TagKind::AiffText),
+            TagType::Id3v1 => Some(TagKind::Id3v1),
+            _ => None,
+        }
+    }
+}
+
+/// Metadata merged from every tag in a file.
+#[derive(Debug, Clone, Default)]
+pub struct FileTags {
+    pub metadata: AudioMetadata,
+    /// Tags found, in precedence order
+    pub found: Vec<TagKind>,
+}
+
+/// Read and merge every tag of `path`.
+///
+/// A file with no tags, or whose format isn't recognised, gives an empty
+/// `found`. The audio itself isn't parsed, so only damaged tags are errors.
+pub fn read_file_tags(path: &Path) -> Result<FileTags, String> {
+    let read = || -> Result<TaggedFile, LoftyError> {
+        Probe::open(path)?
+            .guess_file_type()?
+            .options(ParseOptions::new().read_properties(false))
+            .read()
+    };
+    let tagged_file = match read() {
+        Ok(tagged_file) => tagged_file,
+        Err(e) if matches!(e.kind(), ErrorKind::UnknownFormat) => return Ok(FileTags::default()),
+        Err(e) => return Err(format!("Failed to read tags: {}", e)),
+    };
+
+    let mut found: Vec<(TagKind, &Tag)> = tagged_file
+        .tags()
+        .iter()
+        .filter_map(|tag| Some((TagKind::from_lofty(tag.tag_type())?, tag)))
+        .collect();
+    found.sort_by_key(|(kind, _)| *kind);
+
+    let mut tags = FileTags::default();
+    for (kind, tag) in found {
+        tags.found.push(kind);
+        fill_missing(&mut tags.metadata, tag_metadata(tag));
+    }
+    if tags.found.contains(&TagKind::Id3v2) {
+        if let Ok(tag) = id3::Tag::read_from_path(path) {
+            tags.metadata.rating = popm_rating(&tag);
+            tags.metadata.play_count = id3_play_count(&tag);
+        }
+    }
+    Ok(tags)
+}
+
+/// Fields from one tag, through lofty's format-independent view of it.
+fn tag_metadata(tag: &Tag) -> AudioMetadata {
+    let text = |key: &ItemKey| non_empty(tag.get_string(key));
+    AudioMetadata {
+        title: non_empty(tag.title().as_deref()),
+        artist: non_empty(tag.artist().as_deref()),
+        album: non_empty(tag.album().as_deref()),
+        track_number: tag
+            .track()
+            .or_else(|| leading_number(&text(&ItemKey::TrackNumber)?))
+            .filter(|&track| track > 0),
+        // "2004-05-01" and "2004" both give 2004
+        year: text(&ItemKey::Year)
+            .or_else(|| text(&ItemKey::RecordingDate))
+            .and_then(|date| parse_year(&date)),
+        duration_secs: text(&ItemKey::Length).and_then(|length| length.parse().ok()),
+        genre: non_empty(tag.genre().as_deref()),
+        album_artist: text(&ItemKey::AlbumArtist),
+        disc_number: tag
+            .disk()
+            .or_else(|| leading_number(&text(&ItemKey::DiscNumber)?))
+            .filter(|&disc| disc > 0),
+        ..Default::default()
+    }
+}
+
+/// Take each field `metadata` lacks from `fallback`.
+pub fn fill_missing(metadata: &mut AudioMetadata, fallback: AudioMetadata) {
+    let m = metadata;
+    m.title = m.title.take().or(fallback.title);
+    m.artist = m.artist.take().or(fallback.artist);
+    m.album = m.album.take().or(fallback.album);
+    m.track_number = m.track_number.or(fallback.track_number);
+    m.year = m.year.or(fallback.year);
+    m.duration_secs = m.duration_secs.or(fallback.duration_secs);
+    m.genre = m.genre.take().or(fallback.genre);
+    m.album_artist = m.album_artist.take().or(fallback.album_artist);
+    m.disc_number = m.disc_number.or(fallback.disc_number);
+    m.rating = m.rating.or(fallback.rating);
+    m.play_count = m.play_count.or(fallback.play_count);
+}
+
+/// Where a trailing ID3v1 tag starts, or the file length if there is none.
+fn id3v1_start(file: &mut File) -> io::Result<u64> {
+    let file_len = file.seek(SeekFrom::End(0))?;
+    if file_len < ID3V1_LEN {
+        return Ok(file_len);
+    }
+    let mut marker = [0u8; 3];
+    file.seek(SeekFrom::Start(file_len - ID3V1_LEN))?;
+    file.read_exact(&mut marker)?;
+    Ok(if &marker == b"TAG" {
+        file_len - ID3V1_LEN
+    } else {
+        file_len
+    })
+}
+
+/// Where an APE tag whose footer ends at `footer_end` starts, including
+/// its header if it has one.
+fn ape_tag_start(file: &mut File, footer_end: u64) -> Option<u64> {
+    let footer_start = footer_end.checked_sub(APE_FOOTER_LEN)?;
+    let mut footer = [0u8; APE_FOOTER_LEN as usize];
+    file.seek(SeekFrom::Start(footer_start)).ok()?;
+    file.read_exact(&mut footer).ok()?;
+    if &footer[..8] != b"APETAGEX" {
+        return None;
+    }
+    let le_u32 = |at: usize| u32::from_le_bytes(footer[at..at + 4].try_into().unwrap());
+    // Tag size counts the items and the footer, but not the optional header
+    let tag_len = le_u32(12) as u64;
+    let has_header = le_u32(20) & APE_HAS_HEADER != 0;
+    if !(APE_FOOTER_LEN..=MAX_APE_TAG_LEN).contains(&tag_len) {
+        return None;
+    }
+
+    let items_start = footer_end.checked_sub(tag_len)?;
+    match has_header {
+        true => items_start.checked_sub(APE_FOOTER_LEN),
+        false => Some(items_start),
+    }
+}
+
+/// Byte range of the audio, between a leading ID3v2 tag and any trailing
+/// APE and ID3v1 tags.
+pub fn tagged_audio_range(file: &mut File) -> io::Result<Range<u64>> {
+    let mut header = [0u8; ID3V2_HEADER_LEN as usize];
+    file.seek(SeekFrom::Start(0))?;
+    let read = file.read(&mut header)?;
+    let mut start = 0;
+    if read == header.len() && &header[..3] == b"ID3" {
+        // Tag size is syncsafe (7 bits per byte) and leaves out the header
+        let size = header[6..10]
+            .iter()
+            .fold(0u64, |size, &b| (size << 7) | (b & 0x7F) as u64);
+        let footer = if header[5] & ID3V2_HAS_FOOTER != 0 {
+            ID3V2_HEADER_LEN
+        } else {
+            0
+        };
+        start = ID3V2_HEADER_LEN + size + footer;
+    }
+
+    let mut end = id3v1_start(file)?;
+    if let Some(ape_start) = ape_tag_start(file, end) {
+        end = ape_start;
+    }
+    Ok(start.min(end)..end)
+}
+
+/// Trimmed text, or `None` if there is nothing left.
+fn non_empty(text: Option<&str>) -> Option<String> {
+    text.map(|t| t.trim_matches(|c: char| c.is_whitespace() || c == '\0'))
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+}
+
+/// "3" and "3/12" both give 3; 0 means unset.
+fn leading_number(text: &str) -> Option<u32> {
+    let digits: String = text
+        .trim()
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok().filter(|&n| n > 0)
+}
+
+/// Year from "1999" or a date like "1999-05-01".
+fn parse_year(text: &str) -> Option<i32> {
+    let year = text.trim().get(..4)?;
+    year.chars()
+        .all(|c| c.is_ascii_digit())
+        .then(|| year.parse().ok())
+        .flatten()
+        .filter(|&year| year > 0)
+}
+
+/// Star rating (1-5) from the first rated POPM frame.
+///
+/// POPM ratings are 1-255; players disagree on the scale, so this uses the
+/// Windows Media Player steps (1, 64, 128, 196, 255) as boundaries.
+fn popm_rating(tag: &id3::Tag) -> Option<u8> {
+    let rating = tag
+        .frames()
+        .filter_map(|frame| frame.content().popularimeter())
+        .map(|popm| popm.rating)
+        .find(|&rating| rating > 0)?;
+    Some(match rating {
+        1..=63 => 1,
+        64..=127 => 2,
+        128..=195 => 3,
+        196..=254 => 4,
+        _ => 5,
+    })
+}
+
+/// Play count from the PCNT frame or POPM counters, whichever is highest.
+fn id3_play_count(tag: &id3::Tag) -> Option<u32> {
+    // PCNT is a big-endian counter of at least 4 bytes
+    let pcnt = tag
+        .get("PCNT")
+        .and_then(|frame| frame.content().to_unknown().ok())
+        .map(|pcnt| {
+            pcnt.data
+                .iter()
+                .fold(0u64, |count, &b| count.saturating_mul(256) | b as u64)
+        });
+    let popm = tag
+        .frames()
+        .filter_map(|frame| frame.content().popularimeter())
+        .map(|popm| popm.counter)
+        .max();
+    pcnt.into_iter()
+        .chain(popm)
+        .max()
+        .filter(|&count| count > 0)
+        .map(|count| count.min(u32::MAX as u64) as u32)
+}
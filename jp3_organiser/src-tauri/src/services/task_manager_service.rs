@@ -0,0 +1,266 @@
+//! Background task queue.
+//!
+//! Work that can happen after a command has returned (thumbnail generation
+//! after a save, for example) is submitted to the `TaskManager` held in
+//! managed state. Tasks run one at a time, in submission order, on a single
+//! worker thread so they never compete with foreground IO for the SD card.
+//!
+//! The worker pauses while the library is unavailable (SD card pulled) and
+//! resumes when it comes back. Task errors are logged, never surfaced; a
+//! task that panics is logged as failed and the worker moves on to the next.
+//!
+//! Tasks submitted with `submit_io` write to the library and keep the
+//! computer awake from submission until they finish (see `power_service`).
+//! The app asks before closing while any are queued, and
+//! `cancel_pending` drops the ones that haven't started on shutdown.
+//!
+//! Commands that write to the library themselves (saves, exports) hold a
+//! `ForegroundWrite` instead. These are listed alongside the queued tasks,
+//! so the same close protection covers them, and shutdown waits for them.
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::services::library_monitor_service::is_library_available;
+use crate::services::power_service::{keep_awake, SleepGuard};
+
+/// How often a paused worker re-checks library availability
+const PAUSE_CHECK_INTERVAL_MS: u64 = 500;
+
+/// How often `wait_idle` checks whether the queue has drained
+const IDLE_CHECK_INTERVAL_MS: u64 = 50;
+
+/// Library writes running in commands, shared by every `TaskManager`
+static FOREGROUND_WRITES: Mutex<Vec<TaskInfo>> = Mutex::new(Vec::new());
+static NEXT_FOREGROUND_ID: AtomicU64 = AtomicU64::new(0);
+
+type TaskFn = Box<dyn FnOnce() -> Result<(), String> + Send>;
+
+struct Task {
+    id: u64,
+    name: String,
+    run: TaskFn,
+    awake: Option<SleepGuard>,
+    /// `cancel_pending` calls made before this task was submitted
+    generation: u64,
+}
+
+/// A queued or running task, as shown to the user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskInfo {
+    #[serde(skip)]
+    id: u64,
+    pub name: String,
+    /// Submitted with `submit_io` (writes to the library)
+    pub io: bool,
+    pub running: bool,
+}
+
+/// A library write made by a command; keeps the computer awake and counts
+/// as a running IO task until dropped.
+#[must_use = "the write is only tracked while the guard is alive"]
+pub struct ForegroundWrite {
+    id: u64,
+    _awake: SleepGuard,
+}
+
+impl Drop for ForegroundWrite {
+    fn drop(&mut self) {
+        if let Ok(mut writes) = FOREGROUND_WRITES.lock() {
+            writes.retain(|info| info.id != self.id);
+        }
+    }
+}
+
+/// Mark a command's library write as running until the guard is dropped.
+pub fn begin_foreground_write(name: &str) -> ForegroundWrite {
+    let id = NEXT_FOREGROUND_ID.fetch_add(1, Ordering::SeqCst);
+    let awake = keep_awake(name);
+    if let Ok(mut writes) = FOREGROUND_WRITES.lock() {
+        writes.push(TaskInfo {
+            id,
+            name: name.to_string(),
+            io: true,
+            running: true,
+        });
+    }
+    ForegroundWrite { id, _awake: awake }
+}
+
+fn foreground_writes() -> Vec<TaskInfo> {
+    FOREGROUND_WRITES
+        .lock()
+        .map(|writes| writes.clone())
+        .unwrap_or_default()
+}
+
+/// Error text for a task that panicked.
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown cause".to_string());
+    format!("panicked: {}", message)
+}
+
+/// Queue of background tasks with a single worker thread.
+pub struct TaskManager {
+    sender: Mutex<Sender<Task>>,
+    pending: Arc<AtomicUsize>,
+    tasks: Arc<Mutex<Vec<TaskInfo>>>,
+    generation: Arc<AtomicU64>,
+    next_id: AtomicU64,
+}
+
+impl Default for TaskManager {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel::<Task>();
+        let pending = Arc::new(AtomicUsize::new(0));
+        let tasks = Arc::new(Mutex::new(Vec::<TaskInfo>::new()));
+        let generation = Arc::new(AtomicU64::new(0));
+        let worker_pending = Arc::clone(&pending);
+        let worker_tasks = Arc::clone(&tasks);
+        let worker_generation = Arc::clone(&generation);
+
+        thread::Builder::new()
+            .name("jp3-background-tasks".to_string())
+            .spawn(move || {
+                for task in receiver {
+                    let cancelled =
+                        || task.generation < worker_generation.load(Ordering::SeqCst);
+                    while !is_library_available() && !cancelled() {
+                        thread::sleep(Duration::from_millis(PAUSE_CHECK_INTERVAL_MS));
+                    }
+                    // Checked under the lock so cancel_pending reports it accurately
+                    let start = {
+                        let mut tasks = worker_tasks.lock().unwrap();
+                        let info = tasks.iter_mut().find(|info| info.id == task.id);
+                        match info {
+                            Some(info) if !cancelled() => {
+                                info.running = true;
+                                true
+                            }
+                            _ => false,
+                        }
+                    };
+                    if start {
+                        let result = panic::catch_unwind(AssertUnwindSafe(task.run))
+                            .unwrap_or_else(|payload| Err(panic_message(payload)));
+                        if let Err(e) = result {
+                            log::warn!("[TaskManager] {} failed: {}", task.name, e);
+                        }
+                    } else {
+                        log::info!("[TaskManager] {} cancelled", task.name);
+                    }
+                    drop(task.awake);
+                    worker_tasks.lock().unwrap().retain(|info| info.id != task.id);
+                    worker_pending.fetch_sub(1, Ordering::SeqCst);
+                }
+            })
+            .expect("failed to spawn background task worker");
+
+        Self {
+            sender: Mutex::new(sender),
+            pending,
+            tasks,
+            generation,
+            next_id: AtomicU64::new(0),
+        }
+    }
+}
+
+impl TaskManager {
+    /// Queue a task to run after everything submitted before it.
+    pub fn submit<F>(&self, name: impl Into<String>, run: F)
+    where
+        F: FnOnce() -> Result<(), String> + Send + 'static,
+    {
+        self.queue(name.into(), Box::new(run), None);
+    }
+
+    /// Queue an IO-heavy task, keeping the computer awake until it has run.
+    pub fn submit_io<F>(&self, name: impl Into<String>, run: F)
+    where
+        F: FnOnce() -> Result<(), String> + Send + 'static,
+    {
+        let name = name.into();
+        let awake = keep_awake(&name);
+        self.queue(name, Box::new(run), Some(awake));
+    }
+
+    fn queue(&self, name: String, run: TaskFn, awake: Option<SleepGuard>) {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.tasks.lock().unwrap().push(TaskInfo {
+            id,
+            name: name.clone(),
+            io: awake.is_some(),
+            running: false,
+        });
+        let task = Task {
+            id,
+            name,
+            run,
+            awake,
+            generation: self.generation.load(Ordering::SeqCst),
+        };
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        if self.sender.lock().unwrap().send(task).is_err() {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            self.tasks.lock().unwrap().retain(|info| info.id != id);
+            log::error!("[TaskManager] Worker has stopped; task dropped");
+        }
+    }
+
+    /// Number of queued or running tasks.
+    pub fn pending_count(&self) -> usize {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    /// Running foreground writes, then queued and running tasks in the
+    /// order they will run.
+    pub fn active_tasks(&self) -> Vec<TaskInfo> {
+        let mut active = foreground_writes();
+        active.extend(self.tasks.lock().unwrap().iter().cloned());
+        active
+    }
+
+    /// Whether any task that writes to the library is queued or running,
+    /// including foreground writes.
+    pub fn has_io_tasks(&self) -> bool {
+        !foreground_writes().is_empty() || self.tasks.lock().unwrap().iter().any(|info| info.io)
+    }
+
+    /// Drop every task that hasn't started yet; the running one finishes.
+    /// Returns the names of the dropped tasks.
+    pub fn cancel_pending(&self) -> Vec<String> {
+        let tasks = self.tasks.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        tasks
+            .iter()
+            .filter(|info| !info.running)
+            .map(|info| info.name.clone())
+            .collect()
+    }
+
+    /// Wait up to `timeout` for the queue to drain and foreground writes to
+    /// finish. Returns false on timeout.
+    pub fn wait_idle(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.pending_count() > 0 || !foreground_writes().is_empty() {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(IDLE_CHECK_INTERVAL_MS));
+        }
+        true
+    }
+}
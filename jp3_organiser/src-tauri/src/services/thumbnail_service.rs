@@ -0,0 +1,163 @@
+//! Downscaled album cover copies.
+//!
+//! Provider covers are usually 500px or larger. The album grid only needs a
+//! small thumbnail and the device screen a slightly larger one, so both are
+//! generated once and stored next to the full cover:
+//!
+//! ```text
+//! jp3/assets/albums/{hash}.jpg              full cover
+//! jp3/assets/albums/thumbs/{hash}_ui.jpg    album grid thumbnail
+//! jp3/assets/albums/thumbs/{hash}_device.jpg device screen cover
+//! ```
+//!
+//! Generation normally happens in the background after a save (see
+//! `task_manager_service`); `ensure_thumbnail` also creates one on demand.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::ImageReader;
+use serde::{Deserialize, Serialize};
+
+use crate::services::cover_art_service::cover_filename;
+use crate::services::task_manager_service::TaskManager;
+
+/// Subdirectory of the album covers directory holding thumbnails
+pub const THUMBS_DIR: &str = "thumbs";
+
+/// JPEG quality for generated thumbnails
+const THUMBNAIL_QUALITY: u8 = 85;
+
+/// Thumbnail sizes generated for each cover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ThumbnailSize {
+    /// Album grid in the desktop UI
+    Ui,
+    /// ESP32 device screen
+    Device,
+}
+
+impl ThumbnailSize {
+    pub const ALL: [ThumbnailSize; 2] = [ThumbnailSize::Ui, ThumbnailSize::Device];
+
+    /// Longest edge in pixels.
+    pub fn max_edge(self) -> u32 {
+        match self {
+            ThumbnailSize::Ui => 200,
+            ThumbnailSize::Device => 240,
+        }
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            ThumbnailSize::Ui => "ui",
+            ThumbnailSize::Device => "device",
+        }
+    }
+}
+
+/// Path of an album's thumbnail (whether or not it exists yet).
+pub fn thumbnail_path(albums_dir: &Path, artist: &str, album: &str, size: ThumbnailSize) -> PathBuf {
+    albums_dir.join(THUMBS_DIR).join(format!(
+        "{}_{}.jpg",
+        cover_filename(artist, album),
+        size.suffix()
+    ))
+}
+
+/// Generate one thumbnail from the album's full cover.
+///
+/// Covers already smaller than the target are re-encoded without upscaling.
+pub fn generate_thumbnail(
+    albums_dir: &Path,
+    artist: &str,
+    album: &str,
+    size: ThumbnailSize,
+) -> Result<PathBuf, String> {
+    let cover_path = albums_dir.join(format!("{}.jpg", cover_filename(artist, album)));
+    let image = ImageReader::open(&cover_path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| format!("Failed to open cover {}: {}", cover_path.display(), e))?
+        .decode()
+        .map_err(|e| format!("Failed to decode cover {}: {}", cover_path.display(), e))?;
+
+    let edge = size.max_edge();
+    let resized = if image.width() > edge || image.height() > edge {
+        image.thumbnail(edge, edge)
+    } else {
+        image
+    };
+
+    let thumbs_dir = albums_dir.join(THUMBS_DIR);
+    fs::create_dir_all(&thumbs_dir)
+        .map_err(|e| format!("Failed to create thumbnails directory: {}", e))?;
+
+    let mut bytes = Vec::new();
+    resized
+        .to_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, THUMBNAIL_QUALITY))
+        .map_err(|e| format!("Failed to encode thumbnail: {}", e))?;
+
+    let path = thumbnail_path(albums_dir, artist, album, size);
+    fs::write(&path, bytes).map_err(|e| format!("Failed to write thumbnail: {}", e))?;
+    Ok(path)
+}
+
+/// Generate every missing thumbnail for an album that has a cover.
+///
+/// Returns the number generated (0 if there is no cover yet).
+pub fn generate_missing_thumbnails(albums_dir: &Path, artist: &str, album: &str) -> Result<u32, String> {
+    let cover_path = albums_dir.join(format!("{}.jpg", cover_filename(artist, album)));
+    if !cover_path.exists() {
+        return Ok(0);
+    }
+
+    let mut generated = 0;
+    for size in ThumbnailSize::ALL {
+        if !thumbnail_path(albums_dir, artist, album, size).exists() {
+            generate_thumbnail(albums_dir, artist, album, size)?;
+            generated += 1;
+        }
+    }
+    Ok(generated)
+}
+
+/// Thumbnail path, generating it first if needed.
+pub fn ensure_thumbnail(
+    albums_dir: &Path,
+    artist: &str,
+    album: &str,
+    size: ThumbnailSize,
+) -> Result<PathBuf, String> {
+    let path = thumbnail_path(albums_dir, artist, album, size);
+    if path.exists() {
+        return Ok(path);
+    }
+    generate_thumbnail(albums_dir, artist, album, size)
+}
+
+/// Delete an album's thumbnails (e.g. after its cover was replaced).
+pub fn remove_thumbnails(albums_dir: &Path, artist: &str, album: &str) {
+    for size in ThumbnailSize::ALL {
+        let _ = fs::remove_file(thumbnail_path(albums_dir, artist, album, size));
+    }
+}
+
+/// Queue background thumbnail generation for albums.
+///
+/// Albums without a cover yet are skipped when their task runs.
+pub fn queue_album_thumbnails(tasks: &TaskManager, base_path: &Path, albums: Vec<(String, String)>) {
+    let albums_dir = base_path.join("jp3").join("assets").join("albums");
+    for (artist, album) in albums {
+        let albums_dir = albums_dir.clone();
+        tasks.submit_io(format!("Thumbnails for \"{}\" by \"{}\"", album, artist), move || {
+            let generated = generate_missing_thumbnails(&albums_dir, &artist, &album)?;
+            if generated > 0 {
+                log::info!("Generated {} thumbnails for \"{}\" by \"{}\"", generated, album, artist);
+            }
+            Ok(())
+        });
+    }
+}
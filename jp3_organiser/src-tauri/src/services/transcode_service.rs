@@ -0,0 +1,240 @@
+//! Re-encoding audio for a device on export.
+//!
+//! The library keeps its originals; a device with an encode profile gets
+//! MP3 copies of anything that isn't MP3 already. Transcodes are made with
+//! the `ffmpeg` command-line tool and cached:
+//!
+//! ```text
+//! jp3/metadata/transcode_cache/{source hash}-{profile key}.mp3
+//! ```
+//!
+//! so exporting the same songs again (or to a second card with the same
+//! profile) skips the encode. A cache hit bumps the file's modified time,
+//! which is what `prune_transcode_cache` treats as "last used".
+//!
+//! Short MP3 preview clips for sharing are encoded the same way, straight
+//! to their destination.
+//!
+//! Requires ffmpeg built with libmp3lame:
+//! - Ubuntu/Debian: sudo apt install ffmpeg
+//! - macOS: brew install ffmpeg
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::models::{EncodeProfile, PruneTranscodeCacheResult, TranscodeCacheStats};
+
+const TRANSCODE_CACHE_DIR: &str = "transcode_cache";
+
+/// Preview clip length when none is given
+pub const DEFAULT_CLIP_SECS: f32 = 30.0;
+
+/// Longest preview clip, so a "clip" can't be the whole song
+pub const MAX_CLIP_SECS: f32 = 60.0;
+
+/// LAME VBR quality of preview clips (V4, ~165 kbps)
+const CLIP_VBR_QUALITY: u8 = 4;
+
+fn cache_dir(base_path: &Path) -> PathBuf {
+    base_path
+        .join("jp3")
+        .join("metadata")
+        .join(TRANSCODE_CACHE_DIR)
+}
+
+/// Where the transcode of a source with `source_hash` under `profile` is kept.
+pub fn cached_transcode_path(
+    base_path: &Path,
+    source_hash: &str,
+    profile: &EncodeProfile,
+) -> PathBuf {
+    cache_dir(base_path).join(format!("{}-{}.mp3", source_hash, profile.cache_key()))
+}
+
+/// Whether a file is re-encoded for a device (anything but MP3).
+pub fn needs_transcode(path: &Path) -> bool {
+    !path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("mp3"))
+}
+
+/// The MP3 transcode of `source`, encoding it first if it isn't cached.
+pub fn transcode_cached(
+    base_path: &Path,
+    source: &Path,
+    source_hash: &str,
+    profile: &EncodeProfile,
+) -> Result<PathBuf, String> {
+    let cached = cached_transcode_path(base_path, source_hash, profile);
+    if cached.is_file() {
+        let touched = fs::File::options()
+            .write(true)
+            .open(&cached)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        if let Err(e) = touched {
+            log::warn!("Failed to mark {:?} as used: {}", cached, e);
+        }
+        return Ok(cached);
+    }
+
+    fs::create_dir_all(cache_dir(base_path))
+        .map_err(|e| format!("Failed to create transcode cache: {}", e))?;
+    // Encode beside the cache entry so a failed run never leaves a partial file in it
+    let partial = cached.with_extension("mp3.part");
+    let result = run_ffmpeg(source, &partial, profile.mp3_vbr_quality).and_then(|()| {
+        fs::rename(&partial, &cached).map_err(|e| format!("Failed to store transcode: {}", e))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result.map(|()| cached)
+}
+
+/// Cached files with their size and last-used time, least recently used first.
+fn cached_files(base_path: &Path) -> Result<Vec<(PathBuf, u64, u64)>, String> {
+    let dir = cache_dir(base_path);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<(PathBuf, u64, u64)> = fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read transcode cache: {}", e))?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+            let used_at = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs());
+            Some((entry.path(), metadata.len(), used_at))
+        })
+        .collect();
+    files.sort_by_key(|(path, _, used_at)| (*used_at, path.clone()));
+    Ok(files)
+}
+
+/// How many transcodes are cached and how much space they take.
+pub fn transcode_cache_stats(base_path: &Path) -> Result<TranscodeCacheStats, String> {
+    let files = cached_files(base_path)?;
+    Ok(TranscodeCacheStats {
+        file_count: files.len() as u32,
+        total_bytes: files.iter().map(|(_, size, _)| size).sum(),
+        oldest_used_at: files.first().map(|(_, _, used_at)| *used_at),
+    })
+}
+
+/// Remove transcodes not used for `unused_days`, then the least recently
+/// used ones until the cache is at most `max_bytes`.
+pub fn prune_transcode_cache(
+    base_path: &Path,
+    max_bytes: Option<u64>,
+    unused_days: Option<u32>,
+) -> Result<PruneTranscodeCacheResult, String> {
+    let files = cached_files(base_path)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let cutoff = unused_days.map(|days| now.saturating_sub(days as u64 * 24 * 60 * 60));
+    let mut remaining: u64 = files.iter().map(|(_, size, _)| size).sum();
+
+    let mut result = PruneTranscodeCacheResult::default();
+    for (path, size, used_at) in files {
+        let expired = cutoff.is_some_and(|cutoff| used_at < cutoff);
+        let over_budget = max_bytes.is_some_and(|max| remaining > max);
+        if !expired && !over_budget {
+            continue;
+        }
+        fs::remove_file(&path)
+            .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        remaining -= size;
+        result.files_removed += 1;
+        result.bytes_freed += size;
+    }
+    Ok(result)
+}
+
+/// Start and length of a preview clip of a `song_secs` long song.
+///
+/// The length is capped at `MAX_CLIP_SECS` and at the end of the song. A
+/// song length of 0 (unknown) only applies the cap.
+pub fn clip_range(start: f32, duration: f32, song_secs: u16) -> Result<(f32, f32), String> {
+    if !start.is_finite() || start < 0.0 {
+        return Err(format!("Invalid clip start: {}", start));
+    }
+    if !duration.is_finite() || duration <= 0.0 {
+        return Err(format!("Invalid clip length: {}", duration));
+    }
+    let mut duration = duration.min(MAX_CLIP_SECS);
+    if song_secs > 0 {
+        let song_secs = song_secs as f32;
+        if start >= song_secs {
+            return Err(format!(
+                "Clip starts at {}s but the song is only {}s long",
+                start, song_secs
+            ));
+        }
+        duration = duration.min(song_secs - start);
+    }
+    Ok((start, duration))
+}
+
+/// Encode `duration` seconds of `source` from `start` to an MP3 at `dest`.
+///
+/// Tags are carried over. The clip is written beside `dest` and renamed
+/// into place, so a failed encode leaves nothing behind.
+pub fn encode_clip(source: &Path, dest: &Path, start: f32, duration: f32) -> Result<(), String> {
+    log::info!("Encoding {}s clip of {:?} from {}s", duration, source, start);
+    let partial = dest.with_extension("mp3.part");
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-y", "-ss", &start.to_string(), "-t", &duration.to_string()])
+        .arg("-i")
+        .arg(source)
+        .args(["-map", "0:a:0", "-codec:a", "libmp3lame", "-q:a"])
+        .arg(CLIP_VBR_QUALITY.to_string())
+        .args(["-map_metadata", "0", "-id3v2_version", "3", "-f", "mp3"])
+        .arg(&partial)
+        .output()
+        .map_err(|e| {
+            format!(
+                "Failed to run ffmpeg: {}. Ensure ffmpeg is installed \
+                 (apt install ffmpeg or brew install ffmpeg)",
+                e
+            )
+        })?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&partial);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffmpeg failed on {}: {}", source.display(), stderr.trim()));
+    }
+    fs::rename(&partial, dest).map_err(|e| {
+        let _ = fs::remove_file(&partial);
+        format!("Failed to write clip: {}", e)
+    })
+}
+
+fn run_ffmpeg(source: &Path, dest: &Path, vbr_quality: u8) -> Result<(), String> {
+    log::info!("Transcoding {:?} at V{}", source, vbr_quality);
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-y", "-i"])
+        .arg(source)
+        .args(["-map", "0:a:0", "-codec:a", "libmp3lame", "-q:a"])
+        .arg(vbr_quality.to_string())
+        .args(["-map_metadata", "0", "-id3v2_version", "3", "-f", "mp3"])
+        .arg(dest)
+        .output()
+        .map_err(|e| {
+            format!(
+                "Failed to run ffmpeg: {}. Ensure ffmpeg is installed \
+                 (apt install ffmpeg or brew install ffmpeg)",
+                e
+            )
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffmpeg failed on {}: {}", source.display(), stderr.trim()));
+    }
+    Ok(())
+}
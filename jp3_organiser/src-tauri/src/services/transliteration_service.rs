@@ -0,0 +1,241 @@
+//! Script detection and romanized keys for non-Latin names.
+//!
+//! Libraries with CJK or Cyrillic tags can't be searched from a Latin
+//! keyboard. When transliteration is enabled for a library, every non-Latin
+//! title, artist and album name gets a romanized key ("Кино" -> "kino",
+//! "しいな" -> "shiina") stored in `jp3/metadata/transliterations.json`.
+//!
+//! Kana use Hepburn romanization (what people type: "shiina", not
+//! "siina"); everything else goes through `deunicode`.
+//!
+//! # Limitations
+//! Automatic romanization reads Han characters with their Mandarin
+//! readings, which is wrong for Japanese kanji. Users can set an override
+//! for such names (e.g. "椎名林檎" -> "shiina ringo"); overrides are never
+//! replaced by generated keys.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::{Script, TransliterationIndex};
+use crate::services::search_service::invalidate_index;
+
+const TRANSLITERATIONS_FILE: &str = "transliterations.json";
+
+/// Hepburn romaji for hiragana U+3041..=U+3096 (katakana are shifted down).
+/// Small kana used in combinations are handled separately.
+const HIRAGANA_ROMAJI: [&str; 86] = [
+    "a", "a", "i", "i", "u", "u", "e", "e", "o", "o", // ぁ-お
+    "ka", "ga", "ki", "gi", "ku", "gu", "ke", "ge", "ko", "go", // か-ご
+    "sa", "za", "shi", "ji", "su", "zu", "se", "ze", "so", "zo", // さ-ぞ
+    "ta", "da", "chi", "ji", "", "tsu", "zu", "te", "de", "to", "do", // た-ど (っ is "")
+    "na", "ni", "nu", "ne", "no", // な-の
+    "ha", "ba", "pa", "hi", "bi", "pi", "fu", "bu", "pu", // は-ぷ
+    "he", "be", "pe", "ho", "bo", "po", // へ-ぽ
+    "ma", "mi", "mu", "me", "mo", // ま-も
+    "ya", "ya", "yu", "yu", "yo", "yo", // ゃ-よ
+    "ra", "ri", "ru", "re", "ro", // ら-ろ
+    "wa", "wa", "wi", "we", "wo", "n", "vu", "ka", "ke", // ゎ-ゖ
+];
+
+fn index_path(base_path: &Path) -> PathBuf {
+    base_path
+        .join("jp3")
+        .join("metadata")
+        .join(TRANSLITERATIONS_FILE)
+}
+
+/// Script of a single character, or None for digits, punctuation and spaces.
+pub fn char_script(c: char) -> Option<Script> {
+    if !c.is_alphabetic() {
+        return None;
+    }
+    let script = match c as u32 {
+        0x0000..=0x024F | 0x1E00..=0x1EFF => Script::Latin,
+        0x0370..=0x03FF | 0x1F00..=0x1FFF => Script::Greek,
+        0x0400..=0x052F => Script::Cyrillic,
+        0x0590..=0x05FF => Script::Hebrew,
+        0x0600..=0x06FF | 0x0750..=0x077F => Script::Arabic,
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+        0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => Script::Kana,
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF => Script::Han,
+        _ => Script::Other,
+    };
+    Some(script)
+}
+
+/// Dominant script of a string, or None if it has no letters.
+///
+/// Han text containing any kana is treated as Japanese (`Kana`).
+pub fn detect_script(s: &str) -> Option<Script> {
+    let mut counts: HashMap<Script, usize> = HashMap::new();
+    for script in s.chars().filter_map(char_script) {
+        *counts.entry(script).or_default() += 1;
+    }
+    if counts.contains_key(&Script::Kana) && counts.contains_key(&Script::Han) {
+        let han = counts.remove(&Script::Han).unwrap_or(0);
+        *counts.entry(Script::Kana).or_default() += han;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(script, count)| (*count, *script == Script::Latin))
+        .map(|(script, _)| script)
+}
+
+/// Romanize a name, or None if it is already Latin (or has no letters).
+pub fn romanize(s: &str) -> Option<String> {
+    let has_non_latin = s
+        .chars()
+        .filter_map(char_script)
+        .any(|script| script != Script::Latin);
+    if !has_non_latin {
+        return None;
+    }
+    let romanized = romanize_mixed(s)
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!romanized.is_empty()).then_some(romanized)
+}
+
+/// Romanize kana runs with Hepburn and everything else with `deunicode`.
+fn romanize_mixed(s: &str) -> String {
+    let mut out = String::new();
+    let mut other = String::new();
+    let mut kana: Vec<char> = Vec::new();
+    for c in s.chars() {
+        if hiragana_index(c).is_some() || c == 'ー' {
+            if !other.is_empty() {
+                out.push_str(&deunicode::deunicode(&other));
+                other.clear();
+            }
+            kana.push(c);
+        } else {
+            if !kana.is_empty() {
+                out.push_str(&romanize_kana(&kana));
+                kana.clear();
+            }
+            other.push(c);
+        }
+    }
+    out.push_str(&romanize_kana(&kana));
+    out.push_str(&deunicode::deunicode(&other));
+    out
+}
+
+/// Index into `HIRAGANA_ROMAJI` for a hiragana or katakana character.
+fn hiragana_index(c: char) -> Option<usize> {
+    let code = c as u32;
+    let hiragana = match code {
+        0x3041..=0x3096 => code,
+        0x30A1..=0x30F6 => code - 0x60,
+        _ => return None,
+    };
+    Some((hiragana - 0x3041) as usize)
+}
+
+/// Hepburn romanization of a run of kana.
+fn romanize_kana(kana: &[char]) -> String {
+    let mut out = String::new();
+    let mut double_next = false;
+    for (i, &c) in kana.iter().enumerate() {
+        // Long vowel mark: dropped, as in common Hepburn spellings
+        let Some(index) = hiragana_index(c) else {
+            continue;
+        };
+        let small_y = matches!(c, 'ゃ' | 'ゅ' | 'ょ' | 'ャ' | 'ュ' | 'ョ');
+        let small_vowel = matches!(c, 'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ' | 'ァ' | 'ィ' | 'ゥ' | 'ェ' | 'ォ');
+        let mut romaji = HIRAGANA_ROMAJI[index].to_string();
+
+        if romaji.is_empty() {
+            // Small tsu doubles the following consonant
+            double_next = true;
+            continue;
+        }
+        if (small_y || small_vowel) && i > 0 {
+            // Combine with the previous syllable: ki + ya -> kya, shi + ya -> sha, fu + a -> fa
+            if out.ends_with("shi") || out.ends_with("chi") || (small_y && out.ends_with("ji")) {
+                out.pop();
+                if small_y {
+                    romaji.remove(0);
+                }
+            } else if small_y && out.ends_with('i')
+                || !small_y && (out.ends_with('u') || out.ends_with('e') || out.ends_with('o'))
+            {
+                out.pop();
+            }
+        }
+        if double_next {
+            let first = romaji.chars().next().unwrap_or_default();
+            if romaji.starts_with("ch") {
+                out.push('t');
+            } else if !"aiueon".contains(first) {
+                out.push(first);
+            }
+            double_next = false;
+        }
+        out.push_str(&romaji);
+    }
+    out
+}
+
+/// Load the transliteration index, or an empty (disabled) index if none exists.
+pub fn load_transliterations(base_path: &Path) -> Result<TransliterationIndex, String> {
+    let path = index_path(base_path);
+    if !path.exists() {
+        return Ok(TransliterationIndex::default());
+    }
+    let data = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read transliterations: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse transliterations: {}", e))
+}
+
+/// Persist the transliteration index.
+pub fn save_transliterations(
+    base_path: &Path,
+    index: &TransliterationIndex,
+) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(index)
+        .map_err(|e| format!("Failed to serialize transliterations: {}", e))?;
+    fs::write(index_path(base_path), data)
+        .map_err(|e| format!("Failed to write transliterations: {}", e))?;
+    // Romanized keys are part of the search index
+    invalidate_index(base_path);
+    Ok(())
+}
+
+/// Generate keys for names that don't have one yet.
+///
+/// Returns the number of keys added.
+pub fn add_generated_keys<'a>(
+    index: &mut TransliterationIndex,
+    names: impl IntoIterator<Item = &'a str>,
+) -> u32 {
+    let mut added = 0;
+    for name in names {
+        if index.generated.contains_key(name) {
+            continue;
+        }
+        if let Some(key) = romanize(name) {
+            index.generated.insert(name.to_string(), key);
+            added += 1;
+        }
+    }
+    added
+}
+
+/// The romanized key for a name: the user's override, else the generated key.
+///
+/// Always None while transliteration is disabled.
+pub fn romanized_key(index: &TransliterationIndex, name: &str) -> Option<String> {
+    if !index.enabled {
+        return None;
+    }
+    index
+        .overrides
+        .get(name)
+        .or_else(|| index.generated.get(name))
+        .cloned()
+}
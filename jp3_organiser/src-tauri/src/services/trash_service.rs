@@ -0,0 +1,185 @@
+//! Trash for audio files removed by `delete_songs`.
+//!
+//! Deleted audio is moved aside rather than removed, so a mis-click can be
+//! undone:
+//!
+//! ```text
+//! jp3/.trash/manifest.json
+//! jp3/.trash/{id}.{ext}
+//! ```
+//!
+//! The manifest records where each file came from, relative to `jp3/music`.
+//! Files older than the library's retention period are removed the next time
+//! something is trashed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::models::{EmptyTrashResult, TrashEntry};
+
+const TRASH_DIR: &str = ".trash";
+const MANIFEST_FILE: &str = "manifest.json";
+
+fn trash_dir(base_path: &Path) -> PathBuf {
+    base_path.join("jp3").join(TRASH_DIR)
+}
+
+fn music_dir(base_path: &Path) -> PathBuf {
+    base_path.join("jp3").join("music")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Where a trashed file is kept: its ID plus the original extension.
+fn trashed_file_path(base_path: &Path, entry: &TrashEntry) -> PathBuf {
+    let name = match Path::new(&entry.original_path).extension() {
+        Some(ext) => format!("{}.{}", entry.id, ext.to_string_lossy()),
+        None => entry.id.clone(),
+    };
+    trash_dir(base_path).join(name)
+}
+
+fn load_manifest(base_path: &Path) -> Result<Vec<TrashEntry>, String> {
+    let path = trash_dir(base_path).join(MANIFEST_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read trash manifest: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse trash manifest: {}", e))
+}
+
+fn save_manifest(base_path: &Path, entries: &[TrashEntry]) -> Result<(), String> {
+    let dir = trash_dir(base_path);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create trash directory: {}", e))?;
+    let data = serde_json::to_string_pretty(entries)
+        .map_err(|e| format!("Failed to serialize trash manifest: {}", e))?;
+    fs::write(dir.join(MANIFEST_FILE), data)
+        .map_err(|e| format!("Failed to write trash manifest: {}", e))
+}
+
+/// Move audio files from `jp3/music` into the trash.
+///
+/// `files` are (path relative to `jp3/music`, song title) pairs. Files that
+/// don't exist or can't be moved are skipped. Returns the entries trashed.
+pub fn move_to_trash(
+    base_path: &Path,
+    files: &[(String, Option<String>)],
+) -> Result<Vec<TrashEntry>, String> {
+    let mut manifest = load_manifest(base_path)?;
+    fs::create_dir_all(trash_dir(base_path))
+        .map_err(|e| format!("Failed to create trash directory: {}", e))?;
+
+    let music = music_dir(base_path);
+    let deleted_at = now_secs();
+    let mut trashed = Vec::new();
+    for (original_path, title) in files {
+        let source = music.join(original_path);
+        let Ok(metadata) = fs::metadata(&source) else {
+            continue;
+        };
+        let entry = TrashEntry {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            original_path: original_path.clone(),
+            title: title.clone(),
+            deleted_at,
+            size_bytes: metadata.len(),
+        };
+        if fs::rename(&source, trashed_file_path(base_path, &entry)).is_ok() {
+            trashed.push(entry);
+        }
+    }
+
+    if !trashed.is_empty() {
+        manifest.extend(trashed.iter().cloned());
+        save_manifest(base_path, &manifest)?;
+    }
+    Ok(trashed)
+}
+
+/// Everything in the trash, most recently deleted first.
+pub fn list_trash(base_path: &Path) -> Result<Vec<TrashEntry>, String> {
+    let mut entries = load_manifest(base_path)?;
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_at));
+    Ok(entries)
+}
+
+/// Move trashed files back to where they were deleted from.
+///
+/// Entries that are unknown, whose file is gone, or whose original path is
+/// taken again are left alone. Returns the entries restored.
+pub fn restore_from_trash(base_path: &Path, ids: &[String]) -> Result<Vec<TrashEntry>, String> {
+    let manifest = load_manifest(base_path)?;
+    let music = music_dir(base_path);
+
+    let mut kept = Vec::with_capacity(manifest.len());
+    let mut restored = Vec::new();
+    for entry in manifest {
+        let destination = music.join(&entry.original_path);
+        let restorable = ids.contains(&entry.id) && !destination.exists();
+        let moved = restorable
+            && destination
+                .parent()
+                .is_none_or(|parent| fs::create_dir_all(parent).is_ok())
+            && fs::rename(trashed_file_path(base_path, &entry), &destination).is_ok();
+        if moved {
+            restored.push(entry);
+        } else {
+            kept.push(entry);
+        }
+    }
+
+    if !restored.is_empty() {
+        save_manifest(base_path, &kept)?;
+    }
+    Ok(restored)
+}
+
+/// Permanently remove trashed files deleted before `cutoff` (Unix seconds),
+/// or everything if `cutoff` is `None`.
+pub fn empty_trash(base_path: &Path, cutoff: Option<u64>) -> Result<EmptyTrashResult, String> {
+    let manifest = load_manifest(base_path)?;
+    let total = manifest.len();
+    let mut result = EmptyTrashResult::default();
+
+    let mut kept = Vec::with_capacity(manifest.len());
+    for entry in manifest {
+        if cutoff.is_some_and(|cutoff| entry.deleted_at >= cutoff) {
+            kept.push(entry);
+            continue;
+        }
+        let path = trashed_file_path(base_path, &entry);
+        if path.exists() {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+            result.files_removed += 1;
+            result.bytes_freed += entry.size_bytes;
+        }
+    }
+
+    if kept.len() != total {
+        save_manifest(base_path, &kept)?;
+    }
+    Ok(result)
+}
+
+/// Remove trashed files older than `retention_days`. `None` keeps them until
+/// the trash is emptied.
+pub fn purge_expired(
+    base_path: &Path,
+    retention_days: Option<u32>,
+) -> Result<EmptyTrashResult, String> {
+    match retention_days {
+        Some(days) => {
+            let cutoff = now_secs().saturating_sub(days as u64 * 24 * 60 * 60);
+            empty_trash(base_path, Some(cutoff))
+        }
+        None => Ok(EmptyTrashResult::default()),
+    }
+}
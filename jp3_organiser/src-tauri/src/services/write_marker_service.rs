@@ -0,0 +1,133 @@
+//! Markers for library.bin writes in progress.
+//!
+//! Before library.bin is edited in place or rewritten, a small marker is
+//! written to `jp3/metadata/writes/{operation_id}.json` and synced; it is
+//! removed once the write has been synced (and renamed into place). A
+//! marker still there when the app next opens the library means a write
+//! was cut off (crash, power loss, card pulled), so `load_library` refuses
+//! the file until `recover_interrupted_writes` has checked it.
+//!
+//! Each write has its own marker, so concurrent writes don't clear each
+//! other's. Markers of writes still running in this process are never
+//! reported as interrupted.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::models::WriteMarker;
+
+const WRITES_DIR: &str = "writes";
+
+/// Operation IDs of the writes running in this process
+static ACTIVE_WRITES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Removes its marker when dropped, once the write is on disk.
+///
+/// Dropped on error returns too: a failed write is reported to the caller,
+/// and the checksum catches any damage it left. Only a write the process
+/// never returned from leaves its marker behind.
+#[must_use = "the marker is removed as soon as the guard is dropped"]
+pub struct WriteMarkerGuard {
+    path: PathBuf,
+    operation_id: String,
+}
+
+impl Drop for WriteMarkerGuard {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            log::warn!("Failed to remove write marker {}: {}", self.path.display(), e);
+        }
+        if let Ok(mut active) = ACTIVE_WRITES.lock() {
+            active.retain(|id| *id != self.operation_id);
+        }
+    }
+}
+
+fn writes_dir(base_path: &Path) -> PathBuf {
+    base_path.join("jp3").join("metadata").join(WRITES_DIR)
+}
+
+/// Mark a write to `library_bin_path` as started; keep the guard until the
+/// file is synced.
+pub fn begin_library_write(
+    library_bin_path: &Path,
+    operation: &str,
+) -> Result<WriteMarkerGuard, String> {
+    let dir = library_bin_path
+        .parent()
+        .ok_or("library.bin has no parent folder")?
+        .join(WRITES_DIR);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create write markers: {}", e))?;
+
+    let marker = WriteMarker {
+        operation_id: uuid::Uuid::new_v4().to_string(),
+        operation: operation.to_string(),
+        started_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    };
+    let path = dir.join(format!("{}.json", marker.operation_id));
+    let data = serde_json::to_vec(&marker)
+        .map_err(|e| format!("Failed to serialize write marker: {}", e))?;
+    let mut file =
+        fs::File::create(&path).map_err(|e| format!("Failed to create write marker: {}", e))?;
+    file.write_all(&data)
+        .and_then(|()| file.sync_all())
+        .map_err(|e| format!("Failed to write write marker: {}", e))?;
+
+    if let Ok(mut active) = ACTIVE_WRITES.lock() {
+        active.push(marker.operation_id.clone());
+    }
+    Ok(WriteMarkerGuard {
+        path,
+        operation_id: marker.operation_id,
+    })
+}
+
+/// Markers left by writes that never finished, oldest first.
+///
+/// Unreadable markers are still reported, as an unknown operation.
+pub fn interrupted_writes(base_path: &Path) -> Vec<WriteMarker> {
+    let Ok(entries) = fs::read_dir(writes_dir(base_path)) else {
+        return Vec::new();
+    };
+    let active = ACTIVE_WRITES.lock().map(|a| a.clone()).unwrap_or_default();
+    let mut markers: Vec<WriteMarker> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let operation_id = path.file_stem()?.to_str()?.to_string();
+            if path.extension()? != "json" || active.contains(&operation_id) {
+                return None;
+            }
+            let marker = fs::read(&path)
+                .ok()
+                .and_then(|data| serde_json::from_slice(&data).ok())
+                .unwrap_or(WriteMarker {
+                    operation_id,
+                    operation: "unknown".to_string(),
+                    started_at: 0,
+                });
+            Some(marker)
+        })
+        .collect();
+    markers.sort_by_key(|marker| marker.started_at);
+    markers
+}
+
+/// Remove the markers of interrupted writes once the library was checked.
+/// Returns how many were removed.
+pub fn clear_interrupted_writes(base_path: &Path) -> Result<u32, String> {
+    let dir = writes_dir(base_path);
+    let mut cleared = 0u32;
+    for marker in interrupted_writes(base_path) {
+        fs::remove_file(dir.join(format!("{}.json", marker.operation_id)))
+            .map_err(|e| format!("Failed to remove write marker: {}", e))?;
+        cleared += 1;
+    }
+    Ok(cleared)
+}
@@ -0,0 +1,83 @@
+//! Album year lookups for backfilling missing years.
+//!
+//! Albums saved without a year (no tag, no AcoustID date) are stored with
+//! year 0. This service asks MusicBrainz for a year and caches every answer,
+//! including "unknown", in `jp3/metadata/year_cache.json` so repeated
+//! backfills don't re-query the same albums.
+//!
+//! # Year Selection
+//! - If the user chose a release for the album (`choose_release_mbid`),
+//!   that release's date is used.
+//! - Otherwise the earliest year among plausible search matches is used,
+//!   since reissues and regional editions come later than the original.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::YearLookupCache;
+use crate::services::cover_art_service::cover_filename;
+use crate::services::musicbrainz_service::{
+    lookup_release, release_year, search_release_candidates, MusicBrainzError,
+    ReleaseSearchResult,
+};
+
+const YEAR_CACHE_FILE: &str = "year_cache.json";
+
+fn cache_path(base_path: &Path) -> PathBuf {
+    base_path.join("jp3").join("metadata").join(YEAR_CACHE_FILE)
+}
+
+/// Load the year lookup cache, or an empty cache if none exists.
+pub fn load_year_cache(base_path: &Path) -> Result<YearLookupCache, String> {
+    let path = cache_path(base_path);
+    if !path.exists() {
+        return Ok(YearLookupCache::default());
+    }
+    let data =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read year cache: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse year cache: {}", e))
+}
+
+/// Persist the year lookup cache.
+pub fn save_year_cache(base_path: &Path, cache: &YearLookupCache) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(cache)
+        .map_err(|e| format!("Failed to serialize year cache: {}", e))?;
+    fs::write(cache_path(base_path), data)
+        .map_err(|e| format!("Failed to write year cache: {}", e))
+}
+
+/// Cached year for an album: `Some(0)` means known to be unknown.
+pub fn cached_year(cache: &YearLookupCache, artist: &str, album: &str) -> Option<u16> {
+    cache.entries.get(&cover_filename(artist, album)).copied()
+}
+
+/// Record a lookup result (0 for unknown).
+pub fn cache_year(cache: &mut YearLookupCache, artist: &str, album: &str, year: u16) {
+    cache.entries.insert(cover_filename(artist, album), year);
+}
+
+/// Earliest year among releases, ignoring ones without a date.
+pub fn earliest_year(releases: &[ReleaseSearchResult]) -> Option<u16> {
+    releases
+        .iter()
+        .filter_map(|r| r.date.as_deref().and_then(release_year))
+        .min()
+}
+
+/// Ask MusicBrainz for an album's year (rate limited by the MusicBrainz service).
+pub async fn lookup_album_year(
+    artist: &str,
+    album: &str,
+    chosen_mbid: Option<&str>,
+) -> Result<Option<u16>, MusicBrainzError> {
+    if let Some(mbid) = chosen_mbid {
+        if let Some(release) = lookup_release(mbid).await? {
+            if let Some(year) = release.date.as_deref().and_then(release_year) {
+                return Ok(Some(year));
+            }
+        }
+    }
+
+    let releases = search_release_candidates(artist, album).await?;
+    Ok(earliest_year(&releases))
+}
@@ -0,0 +1,137 @@
+//! Tests for single-file tag reads (`get_audio_metadata`).
+//!
+//! Tests cover:
+//! - Album artist, disc number and genre from ID3
+//! - Composer, compilation flag and embedded artwork with its dimensions
+//! - Files without a tag or artwork
+//! - Ratings and play counts from POPM/PCNT frames
+
+use std::io::Cursor;
+
+use id3::frame::{Content, Frame, Picture, PictureType, Popularimeter, Unknown};
+use id3::{Tag, TagLike};
+use jp3_organiser_lib::commands::audio::get_audio_metadata;
+
+/// One silent 128 kbps MPEG-1 Layer III frame, enough to be recognised as an MP3.
+const MP3_FRAME: [u8; 417] = mp3_frame();
+
+const fn mp3_frame() -> [u8; 417] {
+    let mut frame = [0; 417];
+    frame[0] = 0xFF;
+    frame[1] = 0xFB;
+    frame[2] = 0x90;
+    frame[3] = 0x64;
+    frame
+}
+
+fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    image::RgbImage::new(width, height)
+        .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+        .unwrap();
+    bytes
+}
+
+#[test]
+fn test_get_audio_metadata_extended_tags() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("song.mp3");
+    std::fs::write(&path, MP3_FRAME).unwrap();
+
+    let cover = png_bytes(4, 3);
+    let mut tag = Tag::new();
+    tag.set_title("Song");
+    tag.set_artist("Artist");
+    tag.set_album("Album");
+    tag.set_album_artist("Various Artists");
+    tag.set_disc(2);
+    tag.set_genre("Jazz");
+    tag.set_text("TCOM", "Composer");
+    tag.set_text("TCMP", "1");
+    tag.add_frame(Picture {
+        mime_type: "image/png".to_string(),
+        picture_type: PictureType::CoverFront,
+        description: String::new(),
+        data: cover.clone(),
+    });
+    tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+    let file = get_audio_metadata(path.to_string_lossy().to_string()).unwrap();
+    assert_eq!(file.metadata.album_artist.as_deref(), Some("Various Artists"));
+    assert_eq!(file.metadata.disc_number, Some(2));
+    assert_eq!(file.metadata.genre.as_deref(), Some("Jazz"));
+
+    let extended = file.extended_tags.expect("MP3s get extended tags");
+    assert_eq!(extended.composer.as_deref(), Some("Composer"));
+    assert!(extended.compilation);
+    let artwork = extended.artwork.expect("embedded cover");
+    assert_eq!(artwork.mime_type, "image/png");
+    assert_eq!((artwork.width, artwork.height), (Some(4), Some(3)));
+    assert_eq!(artwork.size_bytes, cover.len() as u64);
+}
+
+#[test]
+fn test_get_audio_metadata_without_artwork() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("song.mp3");
+    std::fs::write(&path, MP3_FRAME).unwrap();
+
+    let mut tag = Tag::new();
+    tag.set_title("Song");
+    tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+    let extended = get_audio_metadata(path.to_string_lossy().to_string())
+        .unwrap()
+        .extended_tags
+        .unwrap();
+    assert_eq!(extended.composer, None);
+    assert!(!extended.compilation);
+    assert_eq!(extended.artwork, None);
+
+    // No tag at all: nothing extended to report
+    let untagged = temp_dir.path().join("untagged.mp3");
+    std::fs::write(&untagged, MP3_FRAME).unwrap();
+    let file = get_audio_metadata(untagged.to_string_lossy().to_string()).unwrap();
+    assert!(file.extended_tags.is_none());
+}
+
+#[test]
+fn test_get_audio_metadata_listening_history() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("song.mp3");
+    std::fs::write(&path, MP3_FRAME).unwrap();
+
+    let mut tag = Tag::new();
+    tag.set_title("Song");
+    tag.add_frame(Popularimeter {
+        user: "player@example.com".to_string(),
+        rating: 196,
+        counter: 7,
+    });
+    tag.add_frame(Frame::with_content(
+        "PCNT",
+        Content::Unknown(Unknown {
+            data: vec![0, 0, 1, 2],
+            version: id3::Version::Id3v24,
+        }),
+    ));
+    tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+
+    // POPM 196 is four stars; the PCNT counter (258) beats the POPM one
+    let metadata = get_audio_metadata(path.to_string_lossy().to_string()).unwrap().metadata;
+    assert_eq!(metadata.rating, Some(4));
+    assert_eq!(metadata.play_count, Some(258));
+
+    // Unrated and never played: nothing to import
+    let mut tag = Tag::new();
+    tag.set_title("Song");
+    tag.add_frame(Popularimeter {
+        user: String::new(),
+        rating: 0,
+        counter: 0,
+    });
+    tag.write_to_path(&path, id3::Version::Id3v24).unwrap();
+    let metadata = get_audio_metadata(path.to_string_lossy().to_string()).unwrap().metadata;
+    assert_eq!(metadata.rating, None);
+    assert_eq!(metadata.play_count, None);
+}
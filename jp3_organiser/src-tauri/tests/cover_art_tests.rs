@@ -0,0 +1,311 @@
+//! Tests for fingerprint-driven cover art fallbacks.
+//!
+//! Tests cover:
+//! - Collecting every release MBID from an AcoustID response
+//! - Recording per-album release candidates without duplicates
+//! - Keeping every plausible MusicBrainz release for disambiguation
+//! - Persisting the user's release pick
+//! - Reading many covers in one batch, with missing or unreadable covers listed, sent as one
+//!   binary frame
+//! - Downscaling oversized covers before they are saved
+//! - Preview covers cached per release MBID
+//! - Renaming covers saved under legacy ID-based names, once per library, and not at all once a
+//!   compaction has renumbered the IDs
+
+use std::path::Path;
+
+use jp3_organiser_lib::commands::cover_art::{
+    migrate_legacy_covers, read_album_covers, AlbumCoverRequest,
+};
+use jp3_organiser_lib::services::thumbnail_service::ThumbnailSize;
+use jp3_organiser_lib::commands::library::{
+    delete_songs, initialize_library, load_library, run_compaction, save_to_library, FileToSave,
+};
+use jp3_organiser_lib::models::{AudioMetadata, CoverImageSettings, MetadataSource};
+use jp3_organiser_lib::services::compaction_service::CompactionControl;
+use jp3_organiser_lib::services::cover_art_service::{
+    cover_filename, fit_cover_image, preview_covers_dir,
+};
+use jp3_organiser_lib::services::metadata_ranking_service::collect_release_mbids;
+use jp3_organiser_lib::services::musicbrainz_service::{plausible_releases, MusicBrainzSearchResponse};
+use jp3_organiser_lib::services::release_candidate_service::{
+    add_release_candidates, get_chosen_release, get_release_candidates, load_release_candidates,
+    save_release_candidates, set_chosen_release,
+};
+
+#[test]
+fn test_collect_release_mbids_prefers_matching_album() {
+    let json = serde_json::json!({
+        "status": "ok",
+        "results": [{
+            "recordings": [
+                {
+                    "title": "Song",
+                    "releasegroups": [
+                        { "title": "Greatest Hits", "releases": [{ "id": "hits-1" }] },
+                        { "title": "Obscure EP", "releases": [{ "id": "ep-1" }, { "id": "ep-2" }] }
+                    ]
+                },
+                {
+                    "title": "Song",
+                    "releasegroups": [
+                        { "title": "obscure ep", "releases": [{ "id": "ep-2" }, { "id": "ep-3" }] }
+                    ]
+                }
+            ]
+        }]
+    });
+
+    assert_eq!(
+        collect_release_mbids(&json, Some("Obscure EP")),
+        vec!["ep-1", "ep-2", "ep-3", "hits-1"]
+    );
+    assert_eq!(
+        collect_release_mbids(&json, None),
+        vec!["hits-1", "ep-1", "ep-2", "ep-3"]
+    );
+    assert!(collect_release_mbids(&serde_json::json!({"bad": true}), None).is_empty());
+}
+
+#[test]
+fn test_release_candidates_roundtrip() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+    let base = Path::new(&base_path);
+
+    let mut index = load_release_candidates(base).unwrap();
+    let mbids = vec!["a".to_string(), "b".to_string()];
+    assert!(add_release_candidates(&mut index, "Artist", "Album", &mbids));
+    // Same MBIDs again add nothing; lookup is case-insensitive like cover names
+    assert!(!add_release_candidates(&mut index, "artist", "ALBUM", &mbids));
+    assert!(add_release_candidates(&mut index, "Artist", "Album", &["c".to_string()]));
+    save_release_candidates(base, &index).unwrap();
+
+    let loaded = load_release_candidates(base).unwrap();
+    assert_eq!(get_release_candidates(&loaded, "Artist", "Album"), vec!["a", "b", "c"]);
+    assert!(get_release_candidates(&loaded, "Artist", "Other").is_empty());
+}
+
+#[test]
+fn test_plausible_releases_keep_close_scores() {
+    let response: MusicBrainzSearchResponse = serde_json::from_value(serde_json::json!({
+        "count": 3,
+        "releases": [
+            { "id": "uk", "title": "Album", "score": 100, "date": "1979-11-30", "country": "GB", "track-count": 26 },
+            { "id": "us", "title": "Album", "score": 95, "date": "1979-12-08", "country": "US", "track-count": 26 },
+            { "id": "live", "title": "Album Live", "score": 60, "country": "XE" }
+        ]
+    }))
+    .unwrap();
+
+    let releases = plausible_releases(response.releases.unwrap());
+
+    assert_eq!(releases.len(), 2);
+    assert_eq!(releases[0].release_mbid, "uk");
+    assert_eq!(releases[1].country.as_deref(), Some("US"));
+    assert_eq!(releases[1].track_count, Some(26));
+    assert_eq!(releases[1].date.as_deref(), Some("1979-12-08"));
+}
+
+#[test]
+fn test_chosen_release_persists() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+    let base = Path::new(&base_path);
+
+    let mut index = load_release_candidates(base).unwrap();
+    assert!(set_chosen_release(&mut index, "Artist", "Album", "us"));
+    assert!(!set_chosen_release(&mut index, "Artist", "Album", "us"));
+    save_release_candidates(base, &index).unwrap();
+
+    let loaded = load_release_candidates(base).unwrap();
+    assert_eq!(get_chosen_release(&loaded, "Artist", "Album").as_deref(), Some("us"));
+    assert_eq!(get_chosen_release(&loaded, "Artist", "Other"), None);
+}
+
+#[test]
+fn test_read_album_covers_batch() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+    let albums_dir = temp_dir.path().join("jp3").join("assets").join("albums");
+    std::fs::create_dir_all(&albums_dir).unwrap();
+    std::fs::write(albums_dir.join(format!("{}.jpg", cover_filename("Artist", "Album"))), b"cover").unwrap();
+
+    let request = |album: &str| AlbumCoverRequest {
+        artist: "Artist".to_string(),
+        album: album.to_string(),
+        size: None,
+    };
+    let batch = read_album_covers(
+        &base_path,
+        vec![request("Album"), request("No Cover"), request("Album")],
+    )
+    .unwrap();
+
+    assert_eq!(batch.covers.len(), 1);
+    assert_eq!(batch.covers[&cover_filename("Artist", "Album")], b"cover");
+    assert_eq!(batch.missing, vec![cover_filename("Artist", "No Cover")]);
+    assert!(batch.deferred.is_empty());
+
+    // Sent as: index length, JSON index, then the images in index order
+    let frame = batch.to_frame();
+    let index_len = u32::from_le_bytes(frame[..4].try_into().unwrap()) as usize;
+    let index: serde_json::Value = serde_json::from_slice(&frame[4..4 + index_len]).unwrap();
+    assert_eq!(index["covers"][0]["hash"], cover_filename("Artist", "Album"));
+    assert_eq!(index["covers"][0]["length"], 5);
+    assert_eq!(index["missing"][0], cover_filename("Artist", "No Cover"));
+    assert_eq!(&frame[4 + index_len..], b"cover");
+
+    // A cover no thumbnail can be made from is missing; the rest still load
+    let thumbnail = |album: &str| AlbumCoverRequest {
+        size: Some(ThumbnailSize::Ui),
+        ..request(album)
+    };
+    std::fs::write(albums_dir.join(format!("{}.jpg", cover_filename("Artist", "Other"))), b"other")
+        .unwrap();
+    let batch = read_album_covers(&base_path, vec![thumbnail("Album"), request("Other")]).unwrap();
+    assert_eq!(batch.missing, vec![cover_filename("Artist", "Album")]);
+    assert_eq!(batch.covers[&cover_filename("Artist", "Other")], b"other");
+}
+
+#[test]
+fn test_fit_cover_image_downscales_oversized_covers() {
+    let encode = |width, height| {
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        image::RgbImage::from_pixel(width, height, image::Rgb([10, 120, 200]))
+            .write_to(&mut bytes, image::ImageFormat::Png)
+            .unwrap();
+        bytes.into_inner()
+    };
+    let settings = CoverImageSettings {
+        jpeg_quality: 80,
+        max_edge: 300,
+    };
+
+    let large = encode(900, 600);
+    let fitted = image::load_from_memory(&fit_cover_image(large, &settings)).unwrap();
+    assert_eq!((fitted.width(), fitted.height()), (300, 200));
+
+    // Within the limit: bytes are kept exactly
+    let small = encode(300, 300);
+    assert_eq!(fit_cover_image(small.clone(), &settings), small);
+
+    // Not an image: kept as downloaded
+    assert_eq!(fit_cover_image(b"not an image".to_vec(), &settings), b"not an image");
+}
+
+/// A library with one song on each of the albums "First" and "Second", by "Artist".
+fn save_two_albums(temp_dir: &Path) -> String {
+    let base_path = temp_dir.to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+    let files = ["First", "Second"]
+        .iter()
+        .map(|album| {
+            let source = temp_dir.join(format!("{}.mp3", album));
+            std::fs::write(&source, format!("audio for {}", album)).unwrap();
+            FileToSave {
+                source_path: source.to_string_lossy().to_string(),
+                metadata: AudioMetadata {
+                    title: Some("Song".to_string()),
+                    artist: Some("Artist".to_string()),
+                    album: Some(album.to_string()),
+                    ..Default::default()
+                },
+                metadata_source: MetadataSource::Unknown,
+                confidence: None,
+                fingerprint: None,
+            }
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    base_path
+}
+
+#[test]
+fn test_preview_covers_keyed_by_mbid() {
+    let first = preview_covers_dir(Some("b1a9c0e9-d987-4042-ae91-78d6a3267d69"));
+    let second = preview_covers_dir(Some("f5093c06-23e3-404f-aeaa-40f72885ee3a"));
+    let searched = preview_covers_dir(None);
+
+    assert_ne!(first, second);
+    assert_ne!(first, searched);
+    assert_eq!(first, preview_covers_dir(Some("b1a9c0e9-d987-4042-ae91-78d6a3267d69")));
+    // An MBID can't reach outside the preview cache
+    assert_eq!(preview_covers_dir(Some("../x")).parent(), searched.parent());
+}
+
+#[test]
+fn test_migrate_legacy_covers() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = save_two_albums(temp_dir.path());
+
+    // Covers as an earlier build saved them, by album and artist ID
+    let assets = temp_dir.path().join("jp3").join("assets");
+    let albums_dir = assets.join("albums");
+    let artists_dir = assets.join("artists");
+    std::fs::create_dir_all(&albums_dir).unwrap();
+    std::fs::create_dir_all(&artists_dir).unwrap();
+    std::fs::write(albums_dir.join("0.jpg"), b"first").unwrap();
+    std::fs::write(albums_dir.join("1.jpg"), b"old second").unwrap();
+    std::fs::write(albums_dir.join("9.jpg"), b"gone").unwrap();
+    std::fs::write(artists_dir.join("0.jpg"), b"artist").unwrap();
+    let second = albums_dir.join(format!("{}.jpg", cover_filename("Artist", "Second")));
+    std::fs::write(&second, b"new second").unwrap();
+
+    let result = migrate_legacy_covers(base_path.clone()).unwrap();
+    assert!(!result.already_migrated);
+    assert_eq!(result.albums_renamed, 1);
+    assert_eq!(result.artists_renamed, 1);
+    assert_eq!(result.duplicates_removed, 1);
+    assert_eq!(result.unmatched, 1);
+    assert_eq!(result.failed, 0);
+
+    let first = albums_dir.join(format!("{}.jpg", cover_filename("Artist", "First")));
+    assert_eq!(std::fs::read(first).unwrap(), b"first");
+    assert_eq!(std::fs::read(&second).unwrap(), b"new second");
+    let artist = artists_dir.join(format!("{}.jpg", cover_filename("Artist", "artist")));
+    assert!(artist.exists());
+    assert!(!albums_dir.join("1.jpg").exists());
+    assert!(albums_dir.join("9.jpg").exists());
+
+    // Recorded as done
+    std::fs::write(albums_dir.join("0.jpg"), b"first").unwrap();
+    assert!(migrate_legacy_covers(base_path).unwrap().already_migrated);
+    assert!(albums_dir.join("0.jpg").exists());
+}
+
+#[test]
+fn test_migrate_legacy_covers_after_compaction() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = save_two_albums(temp_dir.path());
+
+    // Saved by an earlier build while "Second" was album 1
+    let albums_dir = temp_dir.path().join("jp3").join("assets").join("albums");
+    std::fs::create_dir_all(&albums_dir).unwrap();
+    std::fs::write(albums_dir.join("0.jpg"), b"first").unwrap();
+    std::fs::write(albums_dir.join("1.jpg"), b"second").unwrap();
+
+    // Dropping "First" renumbers "Second" to album 0
+    let library = load_library(base_path.clone(), None).unwrap();
+    let first = library.songs.iter().find(|s| s.album_name == "First").unwrap();
+    delete_songs(base_path.clone(), vec![first.id]).unwrap();
+    let control = CompactionControl::default();
+    run_compaction(&base_path, false, false, &control.begin().unwrap(), |_| {}).unwrap();
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.albums.len(), 1);
+    assert_eq!(library.albums[0].id, 0);
+
+    let result = migrate_legacy_covers(base_path).unwrap();
+    assert!(result.skipped_after_compaction);
+    assert_eq!((result.albums_renamed, result.duplicates_removed), (0, 0));
+    assert_eq!(result.unmatched, 2);
+
+    // "First"'s cover was not handed to "Second"
+    let second = albums_dir.join(format!("{}.jpg", cover_filename("Artist", "Second")));
+    assert!(!second.exists());
+    assert!(albums_dir.join("0.jpg").exists());
+    assert!(albums_dir.join("1.jpg").exists());
+}
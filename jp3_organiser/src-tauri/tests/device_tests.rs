@@ -0,0 +1,326 @@
+//! Tests for SD card identity.
+//!
+//! Tests cover:
+//! - Device marker written at initialization and read back unchanged
+//! - Older markers (ID only) still read, and rewritten only when missing
+//! - Saves written in the older library format the card's firmware reads, and
+//!   refused when it handles fewer songs than the library would hold
+//! - Compatibility report listing each problem with its fix
+//! - Firmware limits looked up by release when not declared, and migrating a
+//!   card's library down to the format its firmware reads
+//! - Exports to a card the firmware prepared checked against its limits, keeping its marker
+//! - Exports written in an older library format on request
+//! - Known device list: registering, renaming and path updates
+
+use jp3_organiser_lib::commands::library::{
+    check_device_compatibility, ensure_device_marker, export_selection, initialize_library,
+    load_library, migrate_library, save_to_library, FileToSave,
+};
+use jp3_organiser_lib::commands::playlist::create_playlist;
+use jp3_organiser_lib::models::{AudioMetadata, KnownDevice, MetadataSource, LIBRARY_VERSION};
+use jp3_organiser_lib::services::device_service::{
+    firmware_support, read_device_id, read_device_marker, upsert_device, FIRMWARE_INCOMPATIBLE,
+};
+
+fn device(id: &str, name: &str, last_path: Option<&str>) -> KnownDevice {
+    KnownDevice {
+        id: id.to_string(),
+        name: name.to_string(),
+        last_path: last_path.map(str::to_string),
+        encode_profile: None,
+    }
+}
+
+fn marker_path(temp_dir: &tempfile::TempDir) -> std::path::PathBuf {
+    temp_dir
+        .path()
+        .join("jp3")
+        .join("metadata")
+        .join("device.json")
+}
+
+#[test]
+fn test_device_marker_written_at_initialization() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+
+    let marker = read_device_marker(temp_dir.path()).unwrap().unwrap();
+    assert!(!marker.id.is_empty());
+    assert!(marker.created_at > 0);
+    assert_eq!(marker.library_version, LIBRARY_VERSION);
+    assert_eq!(marker.app_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(marker.firmware_max_library_version, None);
+
+    // Re-initializing keeps the card's identity
+    initialize_library(base_path).unwrap();
+    assert_eq!(ensure_device_marker(temp_dir.path()).unwrap().id, marker.id);
+
+    // A marker from before the extra fields existed
+    std::fs::write(marker_path(&temp_dir), r#"{"id":"old-card"}"#).unwrap();
+    assert_eq!(
+        read_device_id(temp_dir.path()).unwrap().as_deref(),
+        Some("old-card")
+    );
+    assert_eq!(
+        ensure_device_marker(temp_dir.path())
+            .unwrap()
+            .library_version,
+        0
+    );
+
+    // Libraries initialized before markers existed get one on demand
+    std::fs::remove_file(marker_path(&temp_dir)).unwrap();
+    assert_eq!(read_device_id(temp_dir.path()).unwrap(), None);
+    let id = ensure_device_marker(temp_dir.path()).unwrap().id;
+    assert_ne!(id, marker.id);
+    assert_eq!(read_device_id(temp_dir.path()).unwrap(), Some(id));
+}
+
+#[test]
+fn test_save_written_for_older_firmware() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+
+    let source_path = temp_dir.path().join("song.mp3");
+    std::fs::write(&source_path, "fake audio data").unwrap();
+    let file = FileToSave {
+        source_path: source_path.to_string_lossy().to_string(),
+        metadata: AudioMetadata {
+            title: Some("Song".to_string()),
+            artist: Some("Artist".to_string()),
+            album: Some("Album".to_string()),
+            ..Default::default()
+        },
+        metadata_source: MetadataSource::Unknown,
+        confidence: None,
+        fingerprint: None,
+    };
+
+    // Firmware declares it only reads v1 libraries
+    let mut marker = read_device_marker(temp_dir.path()).unwrap().unwrap();
+    marker.firmware_max_library_version = Some(1);
+    std::fs::write(
+        marker_path(&temp_dir),
+        serde_json::to_string(&marker).unwrap(),
+    )
+    .unwrap();
+
+    save_to_library(base_path.clone(), vec![file]).unwrap();
+    save_to_library(base_path.clone(), vec![song_file(&temp_dir, "Two")]).unwrap();
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.version, 1);
+    assert_eq!(library.songs.len(), 2);
+    assert_eq!(library.songs[1].title, "Two");
+    assert!(check_device_compatibility(base_path).unwrap().problems.is_empty());
+}
+
+fn song_file(temp_dir: &tempfile::TempDir, title: &str) -> FileToSave {
+    let source_path = temp_dir.path().join(format!("{}.mp3", title));
+    std::fs::write(&source_path, format!("fake audio data {}", title)).unwrap();
+    FileToSave {
+        source_path: source_path.to_string_lossy().to_string(),
+        metadata: AudioMetadata {
+            title: Some(title.to_string()),
+            artist: Some("Artist".to_string()),
+            album: Some("Album".to_string()),
+            ..Default::default()
+        },
+        metadata_source: MetadataSource::Unknown,
+        confidence: None,
+        fingerprint: None,
+    }
+}
+
+#[test]
+fn test_save_refused_over_firmware_song_limit() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+
+    let mut marker = read_device_marker(temp_dir.path()).unwrap().unwrap();
+    marker.firmware_max_songs = Some(1);
+    std::fs::write(
+        marker_path(&temp_dir),
+        serde_json::to_string(&marker).unwrap(),
+    )
+    .unwrap();
+
+    save_to_library(base_path.clone(), vec![song_file(&temp_dir, "One")]).unwrap();
+    let err = save_to_library(base_path, vec![song_file(&temp_dir, "Two")]).unwrap_err();
+    assert!(
+        err.starts_with(FIRMWARE_INCOMPATIBLE),
+        "unexpected error: {}",
+        err
+    );
+    assert!(err.contains("2 songs"), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_check_device_compatibility() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+    let saved = save_to_library(
+        base_path.clone(),
+        vec![song_file(&temp_dir, "One"), song_file(&temp_dir, "Two")],
+    )
+    .unwrap();
+    create_playlist(base_path.clone(), "Road Trip".to_string(), saved.song_ids).unwrap();
+
+    // Firmware that declared nothing is never flagged
+    let report = check_device_compatibility(base_path.clone()).unwrap();
+    assert!(report.problems.is_empty());
+
+    let mut marker = read_device_marker(temp_dir.path()).unwrap().unwrap();
+    marker.firmware_version = Some("1.2.0".to_string());
+    marker.firmware_max_library_version = Some(1);
+    marker.firmware_max_playlist_version = Some(1);
+    marker.firmware_max_songs = Some(1);
+    std::fs::write(
+        marker_path(&temp_dir),
+        serde_json::to_string(&marker).unwrap(),
+    )
+    .unwrap();
+
+    let report = check_device_compatibility(base_path).unwrap();
+    assert_eq!(report.firmware_version.as_deref(), Some("1.2.0"));
+    assert_eq!(report.problems.len(), 3);
+    assert!(report.problems[0].problem.contains("library.bin"));
+    assert!(report.problems[1].problem.contains("2 songs"));
+    assert!(report.problems[2].problem.contains("Road Trip"));
+    assert!(report.problems[2].fix.contains("v1"));
+}
+
+#[test]
+fn test_firmware_limits_from_release_table() {
+    assert!(firmware_support("0.9").is_none());
+    assert_eq!(firmware_support("1.1.5").unwrap().max_library_version, 4);
+    assert_eq!(firmware_support("1.10").unwrap().version, "1.2");
+    assert!(firmware_support("beta").is_none());
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+    save_to_library(base_path.clone(), vec![song_file(&temp_dir, "One")]).unwrap();
+
+    // Firmware that only states its version gets its release's limits
+    let mut marker = read_device_marker(temp_dir.path()).unwrap().unwrap();
+    marker.firmware_version = Some("1.1.5".to_string());
+    std::fs::write(
+        marker_path(&temp_dir),
+        serde_json::to_string(&marker).unwrap(),
+    )
+    .unwrap();
+    let report = check_device_compatibility(base_path.clone()).unwrap();
+    assert_eq!(report.problems.len(), 1);
+    assert!(report.problems[0].problem.contains("up to v4"));
+    assert!(report.problems[0].fix.contains("library version 4"));
+
+    // Migrating rewrites the card's library in the format it reads
+    let migrated = migrate_library(base_path.clone()).unwrap();
+    assert_eq!(migrated.from_version, LIBRARY_VERSION);
+    assert_eq!(migrated.to_version, 4);
+    assert!(check_device_compatibility(base_path.clone()).unwrap().problems.is_empty());
+    assert_eq!(load_library(base_path, None).unwrap().songs[0].title, "One");
+}
+
+#[test]
+fn test_export_to_card_with_firmware_marker() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+    let saved = save_to_library(
+        base_path.clone(),
+        vec![song_file(&temp_dir, "One"), song_file(&temp_dir, "Two")],
+    )
+    .unwrap();
+
+    // A blank card the firmware has declared itself on
+    let card = tempfile::TempDir::new().unwrap();
+    let card_path = card.path().to_string_lossy().to_string();
+    std::fs::create_dir_all(marker_path(&card).parent().unwrap()).unwrap();
+    std::fs::write(
+        marker_path(&card),
+        r#"{"id":"card-1","firmwareVersion":"0.9","firmwareMaxSongs":1}"#,
+    )
+    .unwrap();
+
+    let err = export_selection(
+        base_path.clone(),
+        saved.song_ids.clone(),
+        vec![],
+        card_path.clone(),
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert!(err.starts_with(FIRMWARE_INCOMPATIBLE), "unexpected error: {}", err);
+    assert!(!card.path().join("jp3").join("music").exists());
+    let marker = read_device_marker(card.path()).unwrap().unwrap();
+    assert_eq!(marker.firmware_max_songs, Some(1));
+
+    // Within the limit the export goes through and the marker is kept
+    export_selection(base_path, vec![saved.song_ids[0]], vec![], card_path.clone(), None, None)
+        .unwrap();
+    assert_eq!(load_library(card_path, None).unwrap().songs.len(), 1);
+    let marker = read_device_marker(card.path()).unwrap().unwrap();
+    assert_eq!(marker.id, "card-1");
+    assert_eq!(marker.firmware_version.as_deref(), Some("0.9"));
+}
+
+#[test]
+fn test_export_in_older_library_version() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+    let saved = save_to_library(
+        base_path.clone(),
+        vec![song_file(&temp_dir, "One"), song_file(&temp_dir, "Two")],
+    )
+    .unwrap();
+
+    let card = tempfile::TempDir::new().unwrap();
+    let card_path = card.path().to_string_lossy().to_string();
+    let err = export_selection(
+        base_path.clone(),
+        saved.song_ids.clone(),
+        vec![],
+        card_path.clone(),
+        None,
+        Some(LIBRARY_VERSION + 1),
+    )
+    .unwrap_err();
+    assert!(err.contains("Library version"), "unexpected error: {}", err);
+
+    export_selection(base_path, saved.song_ids, vec![], card_path.clone(), None, Some(3))
+        .unwrap();
+    let library = load_library(card_path.clone(), None).unwrap();
+    assert_eq!(library.version, 3);
+    assert_eq!(library.songs.len(), 2);
+
+    // The marker remembers the version, so later saves keep it
+    let marker = read_device_marker(card.path()).unwrap().unwrap();
+    assert_eq!(marker.firmware_max_library_version, Some(3));
+    save_to_library(card_path.clone(), vec![song_file(&temp_dir, "Three")]).unwrap();
+    assert_eq!(load_library(card_path, None).unwrap().version, 3);
+}
+
+#[test]
+fn test_upsert_device() {
+    let mut devices = Vec::new();
+    upsert_device(&mut devices, device("abc", "Car SD", Some("E:\\")));
+    upsert_device(&mut devices, device("1234-ABCD", "Spare", None));
+    assert_eq!(devices.len(), 2);
+
+    // Same card at a new drive letter under a new name
+    upsert_device(&mut devices, device("abc", "Car", Some("F:\\")));
+    assert_eq!(devices.len(), 2);
+    assert_eq!(devices[0].name, "Car");
+    assert_eq!(devices[0].last_path.as_deref(), Some("F:\\"));
+
+    // Registering by serial keeps the last known path
+    upsert_device(&mut devices, device("abc", "Car SD", None));
+    assert_eq!(devices[0].last_path.as_deref(), Some("F:\\"));
+}
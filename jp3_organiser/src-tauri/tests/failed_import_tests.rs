@@ -0,0 +1,61 @@
+//! Tests for the persisted failed-imports log.
+//!
+//! Tests cover:
+//! - Recording failures with reasons
+//! - Bumping attempts on repeated failures
+//! - Clearing entries once a retry succeeds
+
+use jp3_organiser_lib::models::{AudioMetadata, MetadataStatus, TrackedAudioFile};
+use jp3_organiser_lib::services::failed_import_service::apply_outcome;
+
+/// Helper to create a tracked file that failed with the given reason.
+fn failed_file(path: &str, reason: &str) -> TrackedAudioFile {
+    let mut file = TrackedAudioFile::new("id".to_string(), path.to_string());
+    file.metadata_status = MetadataStatus::Error;
+    file.error_message = Some(reason.to_string());
+    file
+}
+
+#[test]
+fn test_failures_are_recorded_and_attempts_bumped() {
+    let mut failed = Vec::new();
+
+    apply_outcome(&mut failed, &failed_file("/music/a.flac", "fpcalc failed"), 100);
+    apply_outcome(&mut failed, &failed_file("/music/b.mp3", "Network error"), 100);
+    apply_outcome(&mut failed, &failed_file("/music/a.flac", "Decode error"), 200);
+
+    assert_eq!(failed.len(), 2);
+    assert_eq!(failed[0].file_name, "a.flac");
+    assert_eq!(failed[0].attempts, 2);
+    assert_eq!(failed[0].reason, "Decode error");
+    assert_eq!(failed[0].last_failed_at, 200);
+    assert_eq!(failed[1].attempts, 1);
+}
+
+#[test]
+fn test_successful_retry_clears_entry() {
+    let mut failed = Vec::new();
+    apply_outcome(&mut failed, &failed_file("/music/a.mp3", "AcousticID lookup failed"), 100);
+
+    // Retry succeeded: complete metadata, even if a secondary step errored
+    let mut retried = failed_file("/music/a.mp3", "AcousticID lookup failed");
+    retried.metadata = AudioMetadata {
+        title: Some("Title".to_string()),
+        artist: Some("Artist".to_string()),
+        album: Some("Album".to_string()),
+        track_number: None,
+        year: None,
+        duration_secs: None,
+        release_mbid: None,
+        artist_mbid: None,
+        genre: None,
+        album_artist: None,
+        disc_number: None,
+        rating: None,
+        play_count: None,
+    };
+    retried.metadata_status = MetadataStatus::Complete;
+    apply_outcome(&mut failed, &retried, 200);
+
+    assert!(failed.is_empty());
+}
@@ -0,0 +1,48 @@
+//! Tests for song-based audio file names.
+//!
+//! Tests cover:
+//! - Sequential, track-title and artist-title names
+//! - FAT-safe names: rejected characters, trailing dots, device names, length
+//! - Case-insensitive clashes resolved with a numbered suffix
+
+use std::collections::HashSet;
+
+use jp3_organiser_lib::models::FileNameScheme;
+use jp3_organiser_lib::services::file_naming_service::{
+    fat_safe_stem, song_file_name, unique_file_name,
+};
+
+#[test]
+fn test_song_file_name_schemes() {
+    let name = |scheme, track| song_file_name(scheme, 7, track, "Artist", "Title", "flac");
+    assert_eq!(name(FileNameScheme::Sequential, 3), "007.flac");
+    assert_eq!(name(FileNameScheme::TrackTitle, 3), "03 - Title.flac");
+    assert_eq!(name(FileNameScheme::TrackTitle, 0), "Title.flac");
+    assert_eq!(name(FileNameScheme::ArtistTitle, 3), "Artist - Title.flac");
+
+    let name = song_file_name(FileNameScheme::ArtistTitle, 1, 1, "AC/DC", "T.N.T.", "mp3");
+    assert_eq!(name, "AC_DC - T.N.T.mp3");
+}
+
+#[test]
+fn test_fat_safe_stem() {
+    assert_eq!(fat_safe_stem("What? <Live> \"Edit\": 1|2*"), "What_ _Live_ _Edit__ 1_2_");
+    assert_eq!(fat_safe_stem("  Tab\there ..."), "Tab_here");
+    assert_eq!(fat_safe_stem("..."), "Untitled");
+    assert_eq!(fat_safe_stem("con"), "con_");
+    assert_eq!(fat_safe_stem("Nul.remix"), "Nul.remix_");
+    assert_eq!(fat_safe_stem("Console"), "Console");
+
+    // Capped in characters, not bytes
+    let long = "ü".repeat(200);
+    assert_eq!(fat_safe_stem(&long).chars().count(), 96);
+}
+
+#[test]
+fn test_unique_file_name() {
+    let mut taken: HashSet<String> = ["title.mp3".to_string()].into();
+    assert_eq!(unique_file_name("Title.mp3", &mut taken), "Title (2).mp3");
+    assert_eq!(unique_file_name("TITLE.mp3", &mut taken), "TITLE (3).mp3");
+    assert_eq!(unique_file_name("Other.mp3", &mut taken), "Other.mp3");
+    assert!(taken.contains("title (2).mp3"));
+}
@@ -0,0 +1,187 @@
+//! Tests for the stored fingerprint index.
+//!
+//! Tests cover:
+//! - Persisting fingerprints with algorithm/version metadata
+//! - Keeping index keys in step with deleted and renumbered songs
+//! - Detecting fingerprints made by another algorithm or fpcalc version
+//! - AcoustID submission parameters
+//! - Batches processed without an AcoustID API key carrying one warning
+//! - Fingerprint decoding and duplicate grouping
+
+use std::path::Path;
+
+use jp3_organiser_lib::commands::library::initialize_library;
+use jp3_organiser_lib::models::{
+    AudioMetadata, FingerprintIndex, MetadataStatus, ProcessedAudioFingerprint,
+    ProcessedFilesResult, StoredFingerprint, TrackedAudioFile, ACOUSTID_KEY_MISSING_WARNING,
+};
+use jp3_organiser_lib::services::fingerprint_match_service::{
+    decode_fingerprint, group_matching_fingerprints, FingerprintCandidate,
+};
+use jp3_organiser_lib::services::fingerprint_service::build_submission_params;
+use jp3_organiser_lib::services::fingerprint_index_service::{
+    is_fingerprint_current, load_fingerprint_index, record_fingerprints, rename_fingerprints,
+    retain_fingerprints, save_fingerprint_index,
+};
+
+fn stored(algorithm: u32, version: Option<&str>) -> StoredFingerprint {
+    StoredFingerprint {
+        fingerprint: "AQADtEqUZElIJMkS".to_string(),
+        duration_secs: 215,
+        algorithm,
+        fpcalc_version: version.map(|v| v.to_string()),
+    }
+}
+
+#[test]
+fn test_fingerprint_index_roundtrip() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+    let base = Path::new(&base_path);
+
+    // Missing index loads as empty
+    assert!(load_fingerprint_index(base).unwrap().entries.is_empty());
+
+    let mut index = FingerprintIndex::default();
+    index
+        .entries
+        .insert("00/001.mp3".to_string(), stored(2, Some("1.5.1")));
+    save_fingerprint_index(base, &index).unwrap();
+
+    let loaded = load_fingerprint_index(base).unwrap();
+    assert_eq!(loaded.entries.get("00/001.mp3"), Some(&stored(2, Some("1.5.1"))));
+
+    record_fingerprints(
+        base,
+        vec![
+            ("00/002.mp3".to_string(), stored(2, None)),
+            ("00/003.mp3".to_string(), stored(1, None)),
+        ],
+    )
+    .unwrap();
+    retain_fingerprints(base, |path| path != "00/002.mp3").unwrap();
+    let renamed = [("00/003.mp3".to_string(), "00/002.mp3".to_string())].into();
+    rename_fingerprints(base, &renamed).unwrap();
+
+    let mut paths: Vec<_> = load_fingerprint_index(base).unwrap().entries.into_iter().collect();
+    paths.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        paths,
+        vec![
+            ("00/001.mp3".to_string(), stored(2, Some("1.5.1"))),
+            ("00/002.mp3".to_string(), stored(1, None)),
+        ]
+    );
+}
+
+#[test]
+fn test_fingerprint_staleness_detection() {
+    let entry = stored(2, Some("1.5.1"));
+
+    assert!(is_fingerprint_current(&entry, 2, Some("1.5.1")));
+    assert!(!is_fingerprint_current(&entry, 2, Some("1.6.0")));
+    assert!(!is_fingerprint_current(&entry, 1, Some("1.5.1")));
+    // fpcalc not installed: only the algorithm can be checked
+    assert!(is_fingerprint_current(&entry, 2, None));
+}
+
+#[test]
+fn test_submission_params_include_only_known_metadata() {
+    let fingerprint = ProcessedAudioFingerprint {
+        fingerprint_id: "AQADtEqUZElIJMkS".to_string(),
+        tracking_id: "id".to_string(),
+        fingerprint_status: MetadataStatus::Success,
+        error_message: None,
+        duration_seconds: 215,
+    };
+    let metadata = AudioMetadata {
+        title: Some("Song".to_string()),
+        artist: Some("Artist".to_string()),
+        album: None,
+        track_number: Some(4),
+        year: None,
+        duration_secs: None,
+        release_mbid: None,
+        artist_mbid: None,
+        genre: None,
+        album_artist: None,
+        disc_number: None,
+        rating: None,
+        play_count: None,
+    };
+
+    let params = build_submission_params(&fingerprint, &metadata, "app-key", "user-key");
+    let get = |key: &str| {
+        params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+
+    assert_eq!(get("client"), Some("app-key"));
+    assert_eq!(get("user"), Some("user-key"));
+    assert_eq!(get("duration.0"), Some("215"));
+    assert_eq!(get("fingerprint.0"), Some("AQADtEqUZElIJMkS"));
+    assert_eq!(get("track.0"), Some("Song"));
+    assert_eq!(get("trackno.0"), Some("4"));
+    assert_eq!(get("album.0"), None);
+    assert_eq!(get("year.0"), None);
+}
+
+#[test]
+fn test_batch_without_acoustid_key_warns_once() {
+    let files = vec![
+        TrackedAudioFile::new("a".to_string(), "/music/a.mp3".to_string()),
+        TrackedAudioFile::new("b".to_string(), "/music/b.mp3".to_string()),
+    ];
+
+    let result = ProcessedFilesResult::from_files(files.clone());
+    assert!(!result.needs_acoustid_key);
+    assert_eq!(result.warning, None);
+
+    let result = ProcessedFilesResult::from_files(files).without_acoustid_key();
+    assert!(result.needs_acoustid_key);
+    assert_eq!(result.warning.as_deref(), Some(ACOUSTID_KEY_MISSING_WARNING));
+    // The batch carries the warning; no file gets a per-file error
+    assert!(result.files.iter().all(|f| f.error_message.is_none()));
+    assert_eq!(result.error_count, 0);
+}
+
+#[test]
+fn test_decode_compressed_fingerprint() {
+    // One item with bits 1 and 3 set
+    assert_eq!(decode_fingerprint("AQAAARE").unwrap(), vec![0b101]);
+    // Second item is XORed with the first
+    assert_eq!(decode_fingerprint("AQAAAhEC").unwrap(), vec![0b101, 0b100]);
+    // Bit delta >= 7 uses a 5-bit overflow value
+    assert_eq!(decode_fingerprint("AQAAAQcC").unwrap(), vec![1 << 8]);
+}
+
+#[test]
+fn test_group_matching_fingerprints() {
+    // Simple xorshift so the test is deterministic without extra crates
+    let mut state = 0x2545_f491u32;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+
+    let song: Vec<u32> = (0..200).map(|_| next()).collect();
+    let other: Vec<u32> = (0..200).map(|_| next()).collect();
+    // Same song from another source: shifted by a few frames, a couple of bits flipped
+    let reencoded: Vec<u32> = song[3..].iter().map(|v| v ^ 0b1000_0001).collect();
+
+    let candidates = vec![
+        FingerprintCandidate { id: "flac".to_string(), raw: song, duration_secs: 200 },
+        FingerprintCandidate { id: "other".to_string(), raw: other, duration_secs: 201 },
+        FingerprintCandidate { id: "mp3".to_string(), raw: reencoded, duration_secs: 199 },
+    ];
+
+    let groups = group_matching_fingerprints(&candidates);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].0, vec!["flac".to_string(), "mp3".to_string()]);
+    assert!(groups[0].1 > 0.9);
+}
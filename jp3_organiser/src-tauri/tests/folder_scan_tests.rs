@@ -0,0 +1,343 @@
+//! Integration tests for folder import scanning.
+//!
+//! Tests cover:
+//! - Artist/Album/Track structure inference
+//! - Year and disc folder handling
+//! - Grouping by inferred album
+//! - Skipping unsupported files
+//! - Incremental rescans with change detection
+//! - Import preview statistics against an existing library
+//! - Adopting a folder in place and copying adopted songs in later
+
+use std::path::Path;
+
+use jp3_organiser_lib::commands::import::{
+    adopt_music_folder, build_import_plan, build_rescan_plan, record_folder_import,
+};
+use jp3_organiser_lib::commands::library::{
+    copy_adopted_songs, delete_songs, initialize_library, load_library, run_compaction,
+    save_to_library, FileToSave,
+};
+use jp3_organiser_lib::models::{AudioMetadata, MetadataSource, MetadataStatus};
+use jp3_organiser_lib::services::compaction_service::CompactionControl;
+use jp3_organiser_lib::services::folder_scan_service::infer_metadata_from_path;
+use jp3_organiser_lib::services::song_hash_service::load_song_hashes;
+
+/// Helper to create a dummy file (and its parent folders) under a root.
+fn create_dummy_file(root: &Path, relative: &str) {
+    let file_path = root.join(relative);
+    std::fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+    std::fs::write(&file_path, format!("fake audio data for {}", relative)).unwrap();
+}
+
+// =============================================================================
+// Path Inference Tests
+// =============================================================================
+
+#[test]
+fn test_infer_artist_album_track_from_path() {
+    let root = Path::new("/music");
+    let file = root.join("Pink Floyd/1979 - The Wall/03 - Another Brick in the Wall.mp3");
+
+    let metadata = infer_metadata_from_path(root, &file);
+
+    assert_eq!(metadata.artist.as_deref(), Some("Pink Floyd"));
+    assert_eq!(metadata.album.as_deref(), Some("The Wall"));
+    assert_eq!(metadata.year, Some(1979));
+    assert_eq!(metadata.track_number, Some(3));
+    assert_eq!(metadata.title.as_deref(), Some("Another Brick in the Wall"));
+}
+
+#[test]
+fn test_infer_skips_disc_folders_and_parses_disc_track() {
+    let root = Path::new("/music");
+    let file = root.join("Artist/Double Album (2001)/CD2/2-05 Song.flac");
+
+    let metadata = infer_metadata_from_path(root, &file);
+
+    assert_eq!(metadata.artist.as_deref(), Some("Artist"));
+    assert_eq!(metadata.album.as_deref(), Some("Double Album"));
+    assert_eq!(metadata.year, Some(2001));
+    assert_eq!(metadata.track_number, Some(5));
+    assert_eq!(metadata.title.as_deref(), Some("Song"));
+}
+
+#[test]
+fn test_infer_does_not_treat_numeric_title_as_track() {
+    let root = Path::new("/music");
+    let file = root.join("1984.mp3");
+
+    let metadata = infer_metadata_from_path(root, &file);
+
+    assert_eq!(metadata.title.as_deref(), Some("1984"));
+    assert_eq!(metadata.track_number, None);
+    assert_eq!(metadata.artist, None);
+    assert_eq!(metadata.album, None);
+}
+
+// =============================================================================
+// Import Plan Tests
+// =============================================================================
+
+#[test]
+fn test_scan_groups_files_by_inferred_album() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+
+    create_dummy_file(root, "Artist A/Album One/02 - Second.mp3");
+    create_dummy_file(root, "Artist A/Album One/01 - First.mp3");
+    create_dummy_file(root, "Artist B/Album Two/01 Only.wav");
+    create_dummy_file(root, "Artist A/Album One/cover.jpg");
+    create_dummy_file(root, ".hidden/01 - Ignored.mp3");
+
+    let plan = build_import_plan(&root.to_string_lossy(), None).unwrap();
+
+    assert_eq!(plan.total_files, 3);
+    assert_eq!(plan.groups.len(), 2);
+    assert_eq!(plan.skipped_files.len(), 1);
+    assert!(plan.skipped_files[0].ends_with("cover.jpg"));
+
+    let album_one = plan
+        .groups
+        .iter()
+        .find(|g| g.album.as_deref() == Some("Album One"))
+        .unwrap();
+    assert_eq!(album_one.artist.as_deref(), Some("Artist A"));
+    assert_eq!(album_one.complete_count, 2);
+
+    // Files ordered by track number
+    let titles: Vec<_> = album_one
+        .files
+        .iter()
+        .map(|f| f.metadata.title.clone().unwrap())
+        .collect();
+    assert_eq!(titles, vec!["First", "Second"]);
+
+    for file in &album_one.files {
+        assert_eq!(file.metadata_status, MetadataStatus::Complete);
+        assert_eq!(file.metadata_source, MetadataSource::FolderStructure);
+    }
+}
+
+#[test]
+fn test_scan_loose_files_are_incomplete() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+
+    create_dummy_file(root, "01 - Loose Track.mp3");
+
+    let plan = build_import_plan(&root.to_string_lossy(), None).unwrap();
+
+    assert_eq!(plan.groups.len(), 1);
+    let file = &plan.groups[0].files[0];
+    assert_eq!(file.metadata.title.as_deref(), Some("Loose Track"));
+    assert_eq!(file.metadata_status, MetadataStatus::Incomplete);
+    assert_eq!(plan.groups[0].complete_count, 0);
+}
+
+#[test]
+fn test_scan_missing_folder_returns_error() {
+    let result = build_import_plan("/definitely/not/a/real/folder", None);
+    assert!(result.is_err());
+}
+
+// =============================================================================
+// Incremental Rescan Tests
+// =============================================================================
+
+#[test]
+fn test_rescan_only_proposes_new_or_changed_files() {
+    let library_dir = tempfile::TempDir::new().unwrap();
+    let base_path = library_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+
+    let music_dir = tempfile::TempDir::new().unwrap();
+    let root = music_dir.path();
+    let music_path = root.to_string_lossy().to_string();
+
+    create_dummy_file(root, "Artist/Album/01 - One.mp3");
+    create_dummy_file(root, "Artist/Album/02 - Two.mp3");
+    create_dummy_file(root, "Artist/Album/03 - Three.mp3");
+
+    // Nothing recorded yet: everything is new
+    let plan = build_rescan_plan(&base_path, &music_path).unwrap();
+    assert_eq!(plan.total_files, 3);
+    assert_eq!(plan.unchanged_count, 0);
+
+    let imported: Vec<String> = plan
+        .groups
+        .iter()
+        .flat_map(|g| g.files.iter().map(|f| f.file_path.clone()))
+        .collect();
+    assert_eq!(
+        record_folder_import(base_path.clone(), music_path.clone(), imported).unwrap(),
+        3
+    );
+
+    // Touch one file without changing contents, modify another, add a new one
+    let touched = root.join("Artist/Album/01 - One.mp3");
+    std::fs::File::options()
+        .write(true)
+        .open(&touched)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(120))
+        .unwrap();
+    std::fs::write(root.join("Artist/Album/02 - Two.mp3"), "re-encoded audio data").unwrap();
+    create_dummy_file(root, "Artist/Album/04 - Four.mp3");
+
+    let plan = build_rescan_plan(&base_path, &music_path).unwrap();
+    assert_eq!(plan.unchanged_count, 2);
+    assert_eq!(plan.total_files, 2);
+
+    let mut titles: Vec<_> = plan
+        .groups
+        .iter()
+        .flat_map(|g| g.files.iter().map(|f| f.metadata.title.clone().unwrap()))
+        .collect();
+    titles.sort();
+    assert_eq!(titles, vec!["Four", "Two"]);
+}
+
+// =============================================================================
+// Import Preview Stats Tests
+// =============================================================================
+
+#[test]
+fn test_preview_stats_against_existing_library() {
+    let library_dir = tempfile::TempDir::new().unwrap();
+    let base_path = library_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+
+    let music_dir = tempfile::TempDir::new().unwrap();
+    let root = music_dir.path();
+    create_dummy_file(root, "Artist/Old Album/01 - Known.mp3");
+    create_dummy_file(root, "Artist/New Album/01 - Fresh.flac");
+    create_dummy_file(root, "Other/Debut/01 - Start.wav");
+
+    // Existing library already has "Known" by Artist on Old Album
+    save_to_library(
+        base_path.clone(),
+        vec![FileToSave {
+            source_path: root
+                .join("Artist/Old Album/01 - Known.mp3")
+                .to_string_lossy()
+                .to_string(),
+            metadata: AudioMetadata {
+                title: Some("Known".to_string()),
+                artist: Some("Artist".to_string()),
+                album: Some("Old Album".to_string()),
+                track_number: Some(1),
+                year: None,
+                duration_secs: None,
+                release_mbid: None,
+                artist_mbid: None,
+                genre: None,
+                album_artist: None,
+                disc_number: None,
+                rating: None,
+                play_count: None,
+            },
+            metadata_source: MetadataSource::Unknown,
+            confidence: None,
+            fingerprint: None,
+        }],
+    )
+    .unwrap();
+
+    let plan = build_import_plan(&root.to_string_lossy(), Some(&base_path)).unwrap();
+    let stats = &plan.stats;
+
+    assert_eq!(plan.total_files, 3);
+    assert_eq!(stats.new_artists, 1); // Other
+    assert_eq!(stats.new_albums, 2); // New Album, Debut
+    assert_eq!(stats.expected_duplicates, 1); // Known
+    assert_eq!(stats.transcode_count, 2); // flac + wav
+    assert!(stats.estimated_transcode_secs >= 2);
+    assert!(stats.total_size_bytes > 0);
+}
+
+// =============================================================================
+// Adopt In Place Tests
+// =============================================================================
+
+#[test]
+fn test_adopt_leaves_files_in_place() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base = temp_dir.path().join("library");
+    std::fs::create_dir(&base).unwrap();
+    let base_path = base.to_string_lossy().to_string();
+    let root = temp_dir.path().join("collection");
+    create_dummy_file(&root, "Artist/Debut/01 - First.mp3");
+    create_dummy_file(&root, "Artist/Debut/02 - Second.mp3");
+    create_dummy_file(&root, "loose.mp3");
+
+    let result = adopt_music_folder(&base_path, &root.to_string_lossy()).unwrap();
+    assert_eq!(result.songs_adopted, 2);
+    assert_eq!(result.incomplete_files.len(), 1);
+    assert!(!result.copy_queued);
+
+    // Songs point at the originals; nothing was copied into music/
+    let library = load_library(base_path.clone(), None).unwrap();
+    let root = std::fs::canonicalize(&root).unwrap();
+    let first = library.songs.iter().find(|s| s.title == "First").unwrap();
+    assert_eq!(
+        Path::new(&first.path),
+        root.join("Artist/Debut/01 - First.mp3")
+    );
+    let copied = std::fs::read_dir(base.join("jp3/music/00")).unwrap().count();
+    assert_eq!(copied, 0);
+
+    // Adopting again finds the same audio
+    let again = adopt_music_folder(&base_path, &root.to_string_lossy()).unwrap();
+    assert_eq!(again.songs_adopted, 0);
+    assert_eq!(again.duplicates_skipped, 2);
+
+    // Deleting and compacting never touch the user's files
+    delete_songs(base_path.clone(), vec![first.id]).unwrap();
+    let control = CompactionControl::default();
+    run_compaction(&base_path, true, false, &control.begin().unwrap(), |_| {}).unwrap();
+    assert!(root.join("Artist/Debut/01 - First.mp3").exists());
+    let library = load_library(base_path, None).unwrap();
+    assert_eq!(library.songs.len(), 1);
+    assert_eq!(
+        Path::new(&library.songs[0].path),
+        root.join("Artist/Debut/02 - Second.mp3")
+    );
+}
+
+#[test]
+fn test_copy_adopted_songs_into_library() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base = temp_dir.path().join("library");
+    std::fs::create_dir(&base).unwrap();
+    let base_path = base.to_string_lossy().to_string();
+    let root = temp_dir.path().join("collection");
+    create_dummy_file(&root, "Artist/Debut/01 - First.mp3");
+    create_dummy_file(&root, "Artist/Debut/02 - Second.mp3");
+
+    let adopted = adopt_music_folder(&base_path, &root.to_string_lossy()).unwrap();
+    std::fs::remove_file(root.join("Artist/Debut/02 - Second.mp3")).unwrap();
+
+    let result = copy_adopted_songs(base_path.clone(), None).unwrap();
+    assert_eq!(result.files_copied, 1);
+    assert_eq!(result.songs_updated, 1);
+    assert_eq!(result.missing_files.len(), 1);
+
+    // The copy is in music/ and the original stays
+    let library = load_library(base_path.clone(), None).unwrap();
+    let first = library.songs.iter().find(|s| s.title == "First").unwrap();
+    assert_eq!(first.id, adopted.song_ids[0]);
+    assert_eq!(first.path, "00/001.mp3");
+    assert!(base.join("jp3/music/00/001.mp3").exists());
+    assert!(root.join("Artist/Debut/01 - First.mp3").exists());
+
+    // Its hash follows the copy
+    let hashes = load_song_hashes(&base).unwrap();
+    assert!(hashes.songs.contains_key("00/001.mp3"));
+    assert_eq!(hashes.songs.len(), 2);
+
+    // Nothing adopted is left to copy but the missing file
+    let again = copy_adopted_songs(base_path, None).unwrap();
+    assert_eq!(again.files_copied, 0);
+    assert_eq!(again.missing_files.len(), 1);
+}
@@ -0,0 +1,181 @@
+//! Tests for header-based audio format detection.
+//!
+//! Tests cover:
+//! - Recognising common container and frame signatures
+//! - Skipping a leading ID3v2 tag
+//! - Flagging files whose extension disagrees with their content
+//! - Rejecting empty files
+//! - Detecting FairPlay, Audible, encrypted MP4 and WMA copy protection
+
+use std::path::Path;
+
+use jp3_organiser_lib::models::{AudioFormat, MetadataStatus, TrackedAudioFile};
+use jp3_organiser_lib::services::format_sniff_service::{
+    apply_detected_format, detect_drm, format_from_header, sniff_audio_format, DrmScheme,
+};
+
+/// ID3v2.4 header for an empty 16-byte tag body, followed by the body.
+fn id3_prefix() -> Vec<u8> {
+    let mut bytes = b"ID3\x04\x00\x00\x00\x00\x00\x10".to_vec();
+    bytes.extend_from_slice(&[0u8; 16]);
+    bytes
+}
+
+fn tracked(dir: &Path, name: &str, contents: &[u8]) -> TrackedAudioFile {
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    TrackedAudioFile::new("id".to_string(), path.to_string_lossy().to_string())
+}
+
+#[test]
+fn test_format_from_header_signatures() {
+    assert_eq!(format_from_header(b"fLaC\x00\x00\x00\x22"), Some(AudioFormat::Flac));
+    assert_eq!(format_from_header(b"RIFF\x24\x00\x00\x00WAVEfmt "), Some(AudioFormat::Wav));
+    assert_eq!(format_from_header(b"\x00\x00\x00\x20ftypM4A "), Some(AudioFormat::Mp4));
+    assert_eq!(format_from_header(&[0xFF, 0xFB, 0x90, 0x64]), Some(AudioFormat::Mp3));
+    assert_eq!(format_from_header(&[0xFF, 0xF1, 0x50, 0x80]), Some(AudioFormat::Aac));
+    assert_eq!(format_from_header(b"not audio at all"), None);
+
+    let mut opus = b"OggS".to_vec();
+    opus.resize(28, 0);
+    opus.extend_from_slice(b"OpusHead");
+    assert_eq!(format_from_header(&opus), Some(AudioFormat::Opus));
+    assert_eq!(format_from_header(&opus[..28]), Some(AudioFormat::Ogg));
+}
+
+#[test]
+fn test_sniff_skips_id3_tag() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("tagged.mp3");
+    let mut bytes = id3_prefix();
+    bytes.extend_from_slice(&[0xFF, 0xFB, 0x90, 0x64, 0x00]);
+    std::fs::write(&path, bytes).unwrap();
+
+    assert_eq!(sniff_audio_format(&path).unwrap(), Some(AudioFormat::Mp3));
+}
+
+#[test]
+fn test_renamed_aac_is_flagged_for_transcoding() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let mut bytes = id3_prefix();
+    bytes.extend_from_slice(&[0xFF, 0xF1, 0x50, 0x80, 0x00]);
+    let mut file = tracked(temp_dir.path(), "renamed.mp3", &bytes);
+
+    let route = apply_detected_format(&mut file);
+
+    assert_eq!(route, "aac");
+    assert_eq!(file.detected_format, Some(AudioFormat::Aac));
+    assert!(file.needs_transcode);
+    assert!(file.format_warning.unwrap().contains(".mp3"));
+}
+
+#[test]
+fn test_matching_and_unknown_files_are_not_flagged() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+
+    let mut mp3 = tracked(temp_dir.path(), "real.mp3", &[0xFF, 0xFB, 0x90, 0x64]);
+    assert_eq!(apply_detected_format(&mut mp3), "mp3");
+    assert!(mp3.format_warning.is_none());
+    assert!(!mp3.needs_transcode);
+
+    // Unrecognised content falls back to the extension
+    let mut unknown = tracked(temp_dir.path(), "mystery.flac", b"fake audio data");
+    assert_eq!(apply_detected_format(&mut unknown), "flac");
+    assert_eq!(unknown.detected_format, None);
+    assert!(unknown.needs_transcode);
+
+    let mut empty = tracked(temp_dir.path(), "empty.mp3", b"");
+    apply_detected_format(&mut empty);
+    assert_eq!(empty.metadata_status, MetadataStatus::Error);
+}
+
+/// An MP4 box: big-endian size, type, body.
+fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut bytes = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+    bytes.extend_from_slice(kind);
+    bytes.extend_from_slice(body);
+    bytes
+}
+
+/// An MP4 file whose only track uses the `entry` sample entry.
+fn mp4_with_sample_entry(brand: &[u8; 4], entry: &[u8; 4]) -> Vec<u8> {
+    let mut ftyp = brand.to_vec();
+    ftyp.extend_from_slice(&[0, 0, 0, 0]);
+    let stsd = mp4_box(b"stsd", &mp4_box(entry, &[0u8; 28]));
+    let trak = mp4_box(b"trak", &mp4_box(b"mdia", &mp4_box(b"stbl", &stsd)));
+    let mut bytes = mp4_box(b"ftyp", &ftyp);
+    bytes.extend(mp4_box(b"mdat", &[0u8; 64]));
+    bytes.extend(mp4_box(b"moov", &trak));
+    bytes
+}
+
+#[test]
+fn test_detect_drm() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let detect = |name: &str, contents: &[u8]| {
+        let path = temp_dir.path().join(name);
+        std::fs::write(&path, contents).unwrap();
+        detect_drm(&path).unwrap()
+    };
+
+    assert_eq!(detect("plain.m4a", &mp4_with_sample_entry(b"M4A ", b"mp4a")), None);
+    assert_eq!(
+        detect("store.m4a", &mp4_with_sample_entry(b"M4A ", b"drms")),
+        Some(DrmScheme::FairPlay)
+    );
+    assert_eq!(
+        detect("store.m4p", &mp4_with_sample_entry(b"M4P ", b"mp4a")),
+        Some(DrmScheme::FairPlay)
+    );
+    assert_eq!(
+        detect("book.aax", &mp4_with_sample_entry(b"aax ", b"aavd")),
+        Some(DrmScheme::Audible)
+    );
+    assert_eq!(
+        detect("stream.m4a", &mp4_with_sample_entry(b"M4A ", b"enca")),
+        Some(DrmScheme::CommonEncryption)
+    );
+    assert_eq!(
+        detect("book.aa", &[0x00, 0x00, 0x10, 0x00, 0x57, 0x90, 0x75, 0x36]),
+        Some(DrmScheme::Audible)
+    );
+
+    // WMA: ASF header object holding a content encryption object
+    let asf_guid = [
+        0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11, 0xA6, 0xD9, 0x00, 0xAA, 0x00, 0x62, 0xCE,
+        0x6C,
+    ];
+    let encryption_guid = [
+        0xFB, 0xB3, 0x11, 0x22, 0x23, 0xBD, 0xD2, 0x11, 0xB4, 0xB7, 0x00, 0xA0, 0xC9, 0x55, 0xFC,
+        0x6E,
+    ];
+    let wma = |objects: &[u8]| {
+        let mut bytes = asf_guid.to_vec();
+        bytes.extend_from_slice(&((30 + objects.len()) as u64).to_le_bytes());
+        bytes.extend_from_slice(&[1, 0, 0, 0, 1, 2]);
+        bytes.extend_from_slice(objects);
+        bytes
+    };
+    assert_eq!(detect("plain.wma", &wma(&[0u8; 24])), None);
+    assert_eq!(
+        detect("protected.wma", &wma(&encryption_guid)),
+        Some(DrmScheme::WindowsMedia)
+    );
+
+    assert_eq!(detect("song.mp3", &[0xFF, 0xFB, 0x90, 0x64]), None);
+}
+
+#[test]
+fn test_protected_file_is_not_processed() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let mut file = tracked(
+        temp_dir.path(),
+        "store.m4p",
+        &mp4_with_sample_entry(b"M4P ", b"drms"),
+    );
+
+    apply_detected_format(&mut file);
+
+    assert_eq!(file.metadata_status, MetadataStatus::ProtectedFile);
+    assert!(file.error_message.unwrap().contains("Apple FairPlay"));
+}
@@ -0,0 +1,269 @@
+//! Tests for session-scoped import state.
+//!
+//! Tests cover:
+//! - Creating and closing sessions
+//! - Insertion order and subset lookup by tracking ID
+//! - Removing files drops their fingerprints and candidates
+//! - Propagating album metadata from one file to the rest of its album
+//! - Grouping session files into albums with completeness and conflicts
+//! - Ordering multi-disc albums by disc, then track
+//! - Confirming all complete, unambiguous files at once
+//! - Editing or propagating metadata unconfirms the file
+
+use jp3_organiser_lib::models::{MetadataStatus, TrackedAudioFile};
+use jp3_organiser_lib::services::import_session_service::ImportSessions;
+
+fn tracked(tracking_id: &str) -> TrackedAudioFile {
+    TrackedAudioFile::new(tracking_id.to_string(), format!("/music/{}.mp3", tracking_id))
+}
+
+#[test]
+fn test_session_keeps_files_in_order() {
+    let sessions = ImportSessions::default();
+    let session_id = sessions.create();
+
+    sessions
+        .with_session(&session_id, |session| {
+            session.insert_file(tracked("b"));
+            session.insert_file(tracked("a"));
+            session.insert_file(tracked("c"));
+            // Re-inserting keeps the original position
+            session.insert_file(tracked("b"));
+        })
+        .unwrap();
+
+    let all = sessions
+        .with_session(&session_id, |session| session.ordered_files(None))
+        .unwrap();
+    let ids: Vec<_> = all.iter().map(|f| f.tracking_id.as_str()).collect();
+    assert_eq!(ids, vec!["b", "a", "c"]);
+
+    let subset = sessions
+        .with_session(&session_id, |session| {
+            session.ordered_files(Some(&["c".to_string(), "b".to_string()]))
+        })
+        .unwrap();
+    let ids: Vec<_> = subset.iter().map(|f| f.tracking_id.as_str()).collect();
+    assert_eq!(ids, vec!["b", "c"]);
+}
+
+#[test]
+fn test_remove_file_and_close_session() {
+    let sessions = ImportSessions::default();
+    let session_id = sessions.create();
+
+    sessions
+        .with_session(&session_id, |session| {
+            session.insert_file(tracked("a"));
+            session
+                .fingerprints
+                .insert("a".to_string(), ("AQAAARE".to_string(), 200));
+            session
+                .candidates
+                .insert("a".to_string(), serde_json::json!({"status": "ok"}));
+
+            assert!(session.remove_file("a"));
+            assert!(!session.remove_file("a"));
+            assert!(session.fingerprints.is_empty());
+            assert!(session.candidates.is_empty());
+        })
+        .unwrap();
+
+    assert!(sessions.close(&session_id));
+    assert!(sessions.with_session(&session_id, |_| ()).is_err());
+}
+
+#[test]
+fn test_propagate_album_metadata() {
+    let sessions = ImportSessions::default();
+    let session_id = sessions.create();
+
+    let updated = sessions
+        .with_session(&session_id, |session| {
+            let mut template = tracked("a");
+            template.metadata.title = Some("Intro".to_string());
+            template.metadata.artist = Some("Artist".to_string());
+            template.metadata.album = Some("Fixed Album".to_string());
+            template.metadata.year = Some(2001);
+            template.metadata.release_mbid = Some("release-1".to_string());
+            session.insert_file(template);
+
+            let mut other = tracked("b");
+            other.metadata.title = Some("Second".to_string());
+            other.metadata.track_number = Some(2);
+            other.metadata.album = Some("Fixd Album".to_string());
+            other.update_status();
+            session.insert_file(other);
+            session.insert_file(tracked("c"));
+
+            // Unknown targets fail without changing anything
+            assert!(session
+                .propagate_album_metadata("a", &["b".to_string(), "missing".to_string()])
+                .is_err());
+            assert_eq!(session.files["b"].metadata.album.as_deref(), Some("Fixd Album"));
+
+            session.propagate_album_metadata("a", &["b".to_string()])
+        })
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(updated.len(), 1);
+    let b = &updated[0];
+    assert_eq!(b.metadata.album.as_deref(), Some("Fixed Album"));
+    assert_eq!(b.metadata.artist.as_deref(), Some("Artist"));
+    assert_eq!(b.metadata.year, Some(2001));
+    assert_eq!(b.metadata.release_mbid.as_deref(), Some("release-1"));
+    assert_eq!(b.metadata.title.as_deref(), Some("Second"));
+    assert_eq!(b.metadata.track_number, Some(2));
+    assert_eq!(b.metadata_status, MetadataStatus::Complete);
+
+    let c = sessions
+        .with_session(&session_id, |session| session.files["c"].clone())
+        .unwrap();
+    assert_eq!(c.metadata.album, None);
+}
+
+fn tagged(tracking_id: &str, folder: &str, artist: &str, album: &str, track: u32) -> TrackedAudioFile {
+    let mut file = TrackedAudioFile::new(
+        tracking_id.to_string(),
+        format!("/music/{}/{}.mp3", folder, tracking_id),
+    );
+    file.metadata.title = Some(format!("Song {}", tracking_id));
+    file.metadata.artist = Some(artist.to_string());
+    file.metadata.album = Some(album.to_string());
+    file.metadata.track_number = Some(track);
+    file.update_status();
+    file
+}
+
+#[test]
+fn test_group_by_album() {
+    let sessions = ImportSessions::default();
+    let session_id = sessions.create();
+
+    let groups = sessions
+        .with_session(&session_id, |session| {
+            // Multi-disc album across two folders, with an accent variant
+            session.insert_file(tagged("d1", "Album/CD1", "Björk", "Homogenic", 2));
+            session.insert_file(tagged("d2", "Album/CD2", "Bjork", "homogenic", 1));
+            session.insert_file(tagged("d3", "Album/CD2", "Björk", "Homogenic", 1));
+            // Untagged files group by folder
+            session.insert_file(TrackedAudioFile::new(
+                "u1".to_string(),
+                "/music/Unknown/u1.mp3".to_string(),
+            ));
+            session.insert_file(tagged("c1", "Clean", "Artist", "Clean Album", 1));
+            session.group_by_album()
+        })
+        .unwrap();
+
+    assert_eq!(groups.len(), 3);
+
+    let album = &groups[0];
+    let ids: Vec<_> = album.files.iter().map(|f| f.tracking_id.as_str()).collect();
+    assert_eq!(ids, vec!["d2", "d3", "d1"]);
+    assert_eq!(album.artist.as_deref(), Some("Björk"));
+    assert_eq!(album.album.as_deref(), Some("Homogenic"));
+    assert_eq!(album.folder, None);
+    assert_eq!(album.complete_count, 3);
+    let fields: Vec<_> = album.conflicts.iter().map(|c| c.field.as_str()).collect();
+    assert_eq!(fields, vec!["artist", "album", "trackNumber"]);
+    assert_eq!(album.conflicts[2].values, vec!["1"]);
+    assert!(!album.is_complete);
+
+    let unknown = &groups[1];
+    assert_eq!(unknown.folder.as_deref(), Some("/music/Unknown"));
+    assert_eq!(unknown.complete_count, 0);
+    assert!(!unknown.is_complete);
+
+    let clean = &groups[2];
+    assert!(clean.conflicts.is_empty());
+    assert!(clean.is_complete);
+}
+
+#[test]
+fn test_group_by_album_orders_discs() {
+    let sessions = ImportSessions::default();
+    let session_id = sessions.create();
+
+    let on_disc = |tracking_id: &str, disc: u32, track: u32| {
+        let mut file = tagged(tracking_id, "Album", "Artist", "Double", track);
+        file.metadata.disc_number = Some(disc);
+        file
+    };
+    let groups = sessions
+        .with_session(&session_id, |session| {
+            session.insert_file(on_disc("d2t1", 2, 1));
+            session.insert_file(on_disc("d1t2", 1, 2));
+            session.insert_file(on_disc("d1t1", 1, 1));
+            session.insert_file(on_disc("d2t2", 2, 2));
+            session.group_by_album()
+        })
+        .unwrap();
+
+    // Track 1 on each disc is not a duplicate
+    assert_eq!(groups.len(), 1);
+    let ids: Vec<_> = groups[0].files.iter().map(|f| f.tracking_id.as_str()).collect();
+    assert_eq!(ids, vec!["d1t1", "d1t2", "d2t1", "d2t2"]);
+    assert!(groups[0].conflicts.is_empty());
+}
+
+#[test]
+fn test_confirm_all_complete() {
+    let sessions = ImportSessions::default();
+    let session_id = sessions.create();
+
+    let (confirmed, remaining) = sessions
+        .with_session(&session_id, |session| {
+            session.insert_file(tagged("a1", "A", "Artist", "Album", 1));
+            session.insert_file(tagged("a2", "A", "Artist", "Album", 2));
+            // Same track number twice: the whole album needs review
+            session.insert_file(tagged("b1", "B", "Other", "Record", 1));
+            session.insert_file(tagged("b2", "B", "Other", "Record", 1));
+            session.insert_file(tracked("u1"));
+            let mut mislabeled = tagged("w1", "W", "Solo", "Single", 1);
+            mislabeled.format_warning = Some("File is FLAC but named .mp3".to_string());
+            session.insert_file(mislabeled);
+            session.confirm_all_complete()
+        })
+        .unwrap();
+
+    assert_eq!(confirmed, vec!["a1", "a2"]);
+    let ids: Vec<_> = remaining.iter().map(|f| f.tracking_id.as_str()).collect();
+    assert_eq!(ids, vec!["b1", "b2", "u1", "w1"]);
+
+    // Already-confirmed files are neither re-confirmed nor returned
+    let (confirmed, remaining) = sessions
+        .with_session(&session_id, |session| {
+            session.remove_file("b2");
+            session.confirm_all_complete()
+        })
+        .unwrap();
+    assert_eq!(confirmed, vec!["b1"]);
+    assert_eq!(remaining.len(), 2);
+}
+
+#[test]
+fn test_editing_unconfirms_file() {
+    let sessions = ImportSessions::default();
+    let session_id = sessions.create();
+
+    sessions
+        .with_session(&session_id, |session| {
+            session.insert_file(tagged("a1", "A", "Artist", "Album", 1));
+            session.insert_file(tagged("a2", "A", "Artist", "Album", 2));
+            session.insert_file(tagged("a3", "A", "Artist", "Album", 3));
+            session.confirm_all_complete();
+            assert_eq!(session.confirmed_ids(), vec!["a1", "a2", "a3"]);
+
+            let mut metadata = session.files["a2"].metadata.clone();
+            metadata.title = Some("Renamed".to_string());
+            let updated = session.update_file_metadata("a2", metadata).unwrap();
+            assert_eq!(updated.metadata.title.as_deref(), Some("Renamed"));
+            assert!(session.update_file_metadata("missing", Default::default()).is_err());
+
+            session.propagate_album_metadata("a1", &["a3".to_string()]).unwrap();
+            assert_eq!(session.confirmed_ids(), vec!["a1"]);
+        })
+        .unwrap();
+}
@@ -0,0 +1,202 @@
+//! Tests for importing an iTunes / Music.app Library.xml.
+//!
+//! Tests cover:
+//! - Decoding `file://` locations (percent-encoding, Windows drives)
+//! - Track metadata, ratings, play counts and favorites
+//! - Skipping podcasts and built-in playlists, reporting missing files
+//! - Finding files after the media folder moved
+
+use std::path::{Path, PathBuf};
+
+use jp3_organiser_lib::commands::itunes_import::import_itunes_library;
+use jp3_organiser_lib::commands::library::load_library;
+use jp3_organiser_lib::commands::playlist::{list_playlists, load_playlist};
+use jp3_organiser_lib::services::itunes_library_service::file_url_to_path;
+
+fn file_url(path: &Path) -> String {
+    format!(
+        "file://{}",
+        path.to_string_lossy()
+            .split('/')
+            .map(|part| urlencoding::encode(part).into_owned())
+            .collect::<Vec<_>>()
+            .join("/")
+    )
+}
+
+fn track_xml(id: u32, fields: &str, location: &str) -> String {
+    format!(
+        "<key>{id}</key><dict><key>Track ID</key><integer>{id}</integer>{fields}\
+         <key>Location</key><string>{location}</string></dict>"
+    )
+}
+
+fn playlist_xml(name: &str, extra: &str, track_ids: &[u32]) -> String {
+    let items: String = track_ids
+        .iter()
+        .map(|id| format!("<dict><key>Track ID</key><integer>{}</integer></dict>", id))
+        .collect();
+    format!(
+        "<dict><key>Name</key><string>{name}</string>{extra}\
+         <key>Playlist Items</key><array>{items}</array></dict>"
+    )
+}
+
+fn library_xml(music_folder: &str, tracks: &[String], playlists: &[String]) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+<key>Major Version</key><integer>1</integer>
+<key>Music Folder</key><string>{}</string>
+<key>Tracks</key><dict>{}</dict>
+<key>Playlists</key><array>{}</array>
+</dict>
+</plist>
+"#,
+        music_folder,
+        tracks.concat(),
+        playlists.concat()
+    )
+}
+
+fn write_audio(path: &PathBuf) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, format!("fake audio data for {}", path.display())).unwrap();
+}
+
+#[test]
+fn test_file_url_to_path() {
+    assert_eq!(
+        file_url_to_path("file:///Users/me/Music/AC%2FDC/Back%20in%20Black.mp3"),
+        Some(PathBuf::from("/Users/me/Music/AC/DC/Back in Black.mp3"))
+    );
+    assert_eq!(
+        file_url_to_path("file://localhost/C:/Users/me/Music/song.mp3"),
+        Some(PathBuf::from("C:/Users/me/Music/song.mp3"))
+    );
+    assert_eq!(file_url_to_path("http://example.com/song.mp3"), None);
+}
+
+#[test]
+fn test_import_itunes_library() {
+    let source = tempfile::TempDir::new().unwrap();
+    let media = source.path().join("iTunes Media");
+    let first = media.join("Artist").join("Album").join("01 First Song.mp3");
+    let second = media
+        .join("Artist")
+        .join("Album")
+        .join("02 Second Song.mp3");
+    let podcast = media.join("Podcasts").join("episode.mp3");
+    write_audio(&first);
+    write_audio(&second);
+    write_audio(&podcast);
+    let loose = media.join("loose.mp3");
+    write_audio(&loose);
+
+    let album = "<key>Artist</key><string>Artist</string>\
+                 <key>Album</key><string>Album</string>\
+                 <key>Year</key><integer>1999</integer>";
+    let tracks = [
+        track_xml(
+            101,
+            &format!(
+                "<key>Name</key><string>First Song</string>{album}\
+                 <key>Track Number</key><integer>1</integer>\
+                 <key>Play Count</key><integer>42</integer>\
+                 <key>Rating</key><integer>80</integer><key>Loved</key><true/>"
+            ),
+            &file_url(&first),
+        ),
+        track_xml(
+            102,
+            &format!(
+                "<key>Name</key><string>Second Song</string>{album}\
+                 <key>Track Number</key><integer>2</integer>\
+                 <key>Rating</key><integer>60</integer><key>Rating Computed</key><true/>"
+            ),
+            &file_url(&second),
+        ),
+        track_xml(
+            103,
+            "<key>Name</key><string>Episode</string><key>Podcast</key><true/>",
+            &file_url(&podcast),
+        ),
+        track_xml(
+            104,
+            &format!("<key>Name</key><string>Gone</string>{album}"),
+            &file_url(&media.join("gone.mp3")),
+        ),
+        // No album in iTunes or in the (untagged) file
+        track_xml(
+            105,
+            "<key>Name</key><string>Loose</string><key>Artist</key><string>Artist</string>",
+            &file_url(&loose),
+        ),
+    ];
+    let playlists = [
+        playlist_xml("Library", "<key>Master</key><true/>", &[101, 102, 103, 104]),
+        playlist_xml(
+            "Music",
+            "<key>Distinguished Kind</key><integer>4</integer>",
+            &[101],
+        ),
+        playlist_xml("Road Trip", "", &[102, 104, 101]),
+    ];
+    let xml_path = source.path().join("Library.xml");
+    std::fs::write(
+        &xml_path,
+        library_xml(&file_url(&media), &tracks, &playlists),
+    )
+    .unwrap();
+
+    let library_dir = tempfile::TempDir::new().unwrap();
+    let base_path = library_dir.path().to_string_lossy().to_string();
+    let xml = xml_path.to_string_lossy().to_string();
+    let result = import_itunes_library(base_path.clone(), xml.clone(), None).unwrap();
+    assert_eq!(result.songs_imported, 2);
+    assert_eq!(result.tracks_skipped, 1);
+    assert_eq!(
+        result.files_missing,
+        vec![media.join("gone.mp3").to_string_lossy().to_string()]
+    );
+    assert_eq!(
+        result.incomplete_files,
+        vec![loose.to_string_lossy().to_string()]
+    );
+    assert_eq!(
+        (result.favorites_restored, result.playlists_created),
+        (1, 1)
+    );
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    let song = |title: &str| library.songs.iter().find(|s| s.title == title).unwrap();
+    let (first_song, second_song) = (song("First Song"), song("Second Song"));
+    assert_eq!((first_song.track_number, first_song.year), (1, 1999));
+    assert_eq!((first_song.rating, first_song.play_count), (4, 42));
+    assert!(first_song.is_favorite);
+    // Computed from the album rating, not the user's own
+    assert_eq!(second_song.rating, 0);
+
+    let playlists = list_playlists(base_path.clone()).unwrap();
+    assert_eq!(playlists.len(), 1);
+    let road_trip = load_playlist(base_path.clone(), playlists[0].id).unwrap();
+    assert_eq!(road_trip.name, "Road Trip");
+    assert_eq!(road_trip.song_ids, vec![second_song.id, first_song.id]);
+
+    // Media moved to another drive: found under the new folder instead
+    let moved = tempfile::TempDir::new().unwrap();
+    let moved_file = moved.path().join("gone.mp3");
+    write_audio(&moved_file);
+    let result = import_itunes_library(
+        base_path.clone(),
+        xml,
+        Some(moved.path().to_string_lossy().to_string()),
+    )
+    .unwrap();
+    assert_eq!((result.songs_imported, result.duplicates_skipped), (1, 2));
+    assert!(result.files_missing.is_empty());
+    assert_eq!(result.incomplete_files.len(), 1);
+    assert_eq!(load_library(base_path, None).unwrap().songs.len(), 3);
+}
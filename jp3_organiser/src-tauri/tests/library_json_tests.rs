@@ -0,0 +1,105 @@
+//! Tests for library export and import as JSON.
+//!
+//! Tests cover:
+//! - Round trip to a new library: tags, ratings, favorites and playlists
+//! - Songs whose audio is missing are skipped and listed
+//! - Importing again skips songs the library already has
+
+use jp3_organiser_lib::commands::library::{
+    delete_songs, initialize_library, load_library, rate_song, save_to_library, toggle_favorite,
+    FileToSave,
+};
+use jp3_organiser_lib::commands::library_json::{export_library_json, import_library_json};
+use jp3_organiser_lib::commands::playlist::{create_playlist, list_playlists, load_playlist};
+use jp3_organiser_lib::models::{AudioMetadata, MetadataSource};
+
+/// A library with songs "Song 1".."Song {count}", on two albums.
+fn setup_library_with_songs(count: u32) -> (tempfile::TempDir, String) {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+
+    let files = (1..=count)
+        .map(|track| {
+            let source_path = temp_dir.path().join(format!("{}.mp3", track));
+            std::fs::write(&source_path, format!("fake audio data {}", track)).unwrap();
+            FileToSave {
+                source_path: source_path.to_string_lossy().to_string(),
+                metadata: AudioMetadata {
+                    title: Some(format!("Song {}", track)),
+                    artist: Some("Artist".to_string()),
+                    album: Some(format!("Album {}", track % 2)),
+                    track_number: Some(track),
+                    year: Some(2001),
+                    genre: Some("Jazz".to_string()),
+                    ..Default::default()
+                },
+                metadata_source: MetadataSource::Fingerprint,
+                confidence: None,
+                fingerprint: None,
+            }
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    (temp_dir, base_path)
+}
+
+#[test]
+fn test_library_json_round_trip() {
+    let (old_dir, old_base) = setup_library_with_songs(4);
+    rate_song(old_base.clone(), 0, 5).unwrap();
+    toggle_favorite(old_base.clone(), 2).unwrap();
+    delete_songs(old_base.clone(), vec![3]).unwrap();
+    create_playlist(old_base.clone(), "Mix".to_string(), vec![2, 3, 0]).unwrap();
+
+    let json_path = old_dir.path().join("library.json");
+    let export =
+        export_library_json(old_base.clone(), json_path.to_string_lossy().to_string()).unwrap();
+    assert_eq!((export.song_count, export.playlist_count), (3, 1));
+
+    // Song 2's audio went missing before the move
+    let old_library = load_library(old_base.clone(), None).unwrap();
+    let song_2 = old_library.songs.iter().find(|s| s.title == "Song 2").unwrap();
+    let music_dir = old_dir.path().join("jp3").join("music");
+    std::fs::remove_file(music_dir.join(&song_2.path)).unwrap();
+
+    let new_dir = tempfile::TempDir::new().unwrap();
+    let new_base = new_dir.path().to_string_lossy().to_string();
+    let json = json_path.to_string_lossy().to_string();
+    let result = import_library_json(new_base.clone(), json.clone(), None).unwrap();
+    assert_eq!(result.songs_imported, 2);
+    assert_eq!(result.files_missing, vec![song_2.path.clone()]);
+    assert_eq!(result.favorites_restored, 1);
+    assert_eq!(result.playlists_created, 1);
+
+    let library = load_library(new_base.clone(), None).unwrap();
+    let song = |title: &str| library.songs.iter().find(|s| s.title == title).unwrap();
+    let (song_1, song_3) = (song("Song 1"), song("Song 3"));
+    assert_eq!(song_1.rating, 5);
+    assert!(song_3.is_favorite && !song_1.is_favorite);
+    assert_eq!((song_1.album_name.as_str(), song_1.year), ("Album 1", 2001));
+    assert_eq!(song_1.genre.as_deref(), Some("Jazz"));
+    assert_eq!(song_1.metadata_source, MetadataSource::Fingerprint);
+    let new_music = new_dir.path().join("jp3").join("music");
+    assert_eq!(
+        std::fs::read_to_string(new_music.join(&song_3.path)).unwrap(),
+        "fake audio data 3"
+    );
+
+    // The playlist keeps its order, minus the deleted and missing songs
+    let playlists = list_playlists(new_base.clone()).unwrap();
+    let mix = load_playlist(new_base.clone(), playlists[0].id).unwrap();
+    assert_eq!(mix.name, "Mix");
+    assert_eq!(mix.song_ids, vec![song_3.id, song_1.id]);
+
+    // Importing again adds nothing
+    let result = import_library_json(
+        new_base.clone(),
+        json,
+        Some(old_dir.path().to_string_lossy().to_string()),
+    )
+    .unwrap();
+    assert_eq!((result.songs_imported, result.duplicates_skipped), (0, 2));
+    assert_eq!(load_library(new_base, None).unwrap().songs.len(), 2);
+}
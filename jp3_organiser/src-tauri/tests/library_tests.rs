@@ -4,17 +4,112 @@
 //! - Library initialization and structure
 //! - Saving files with string deduplication
 //! - Duplicate song detection (across batches and within batch)
-//! - Soft delete operations
+//! - Duplicate recordings spotted by stored fingerprints of the current version
+//! - Soft delete operations, and restoring soft-deleted songs
+//! - Delete previews: files, bytes and the playlists that would lose songs
+//! - Trash for deleted audio: restoring, emptying and retention
 //! - Metadata editing
-//! - Library compaction
-//! - Edit with playlist remapping
+//! - Library compaction, and dry runs that predict it without writing
+//! - Edit with playlist remapping, and batch edits written once
+//! - Title-only edits made in place, keeping the song ID
+//! - Missing library root (unplugged SD card)
+//! - Sort names stored at save and used for ordering (with v1 fallback)
+//! - Song sort options on load (title, artist, album, year, duration, date added), collated so
+//!   accented and mixed-case titles interleave
+//! - Paginated loads (optionally filtered by artist or album) and snapshot expiry when the
+//!   library changes
+//! - Incremental loads returning only entries changed since a generation
+//! - Genre table: saved genres, compaction, and v2 files without genres
+//! - Compilations filed under their album artist
+//! - Metadata source stored per song and set to Manual on edit
+//! - Match confidence stored per song and low-confidence review
+//! - Disc numbers stored per song (and read as unknown from v3 files)
+//! - Star ratings set in place and kept through edits and compaction
+//! - Ratings and play counts imported from source tags at save
+//! - Favorite flag toggled in place and kept through edits and compaction
+//! - Date added recorded at save and used for the recently added page
+//! - Table checksum kept valid by in-place edits and rejecting damaged files
+//! - Compaction progress, and cancellation leaving library.bin untouched
+//! - Idle-time compaction trimming the song table tail within a write budget, trashing
+//!   the trimmed songs' audio; the daily budget kept on the card across restarts
+//! - Migrating an older library.bin in place, with a backup and unchanged IDs
+//! - Incremental saves appended into table free space instead of a rewrite
+//! - Compaction thresholds read from library settings, reporting the rule crossed
+//! - Backups of library.bin and playlists, taken before compaction and restored
+//! - Backup retention pruning the oldest backups, and deleting a backup
+//! - Integrity check reporting bad references, missing files and layout damage
+//! - Content hashes recorded at save: duplicate audio, changed files, backfill
+//! - Repair rebuilding a damaged library.bin and relinking unreferenced audio files
+//! - Orphaned files in the music buckets: reported, adopted back or deleted
+//! - Songs whose file went missing, relinked to where the file moved
+//! - Exporting selected songs and playlists as a separate library, transcoding to MP3 for
+//!   an encode profile from the transcode cache, with thumbnails made for its covers
+//! - Resuming an interrupted export, keeping files already copied when their hash matches
+//! - Picking songs to fill a size budget (favorites, rating, play count, whole albums)
+//! - Transcode cache size and pruning by age and size
+//! - Album name and year edited in place, rewriting only re-credited songs
+//! - Merging albums split by inconsistent tagging, consolidating covers
+//! - Song file info read from disk: codec parameters, size and hash check
+//! - Printable HTML/Markdown library report grouped by album artist
+//! - Album and artist detail queries with duration, cover and disk usage
+//! - Copy-protected (DRM) and missing files skipped on save, with the saved files reported
+//! - Opt-in compaction renumbering audio files and merging buckets
+//! - Configurable bucket size and two-level bucket layout
+//! - Files named after their song, kept through renumbering compaction
+//! - Write-in-progress markers, and recovering from writes that were cut off
+//! - Sync history recorded for finished exports, filtered by device
+//! - Dry-run sync plans listing each file's action and reason, as data and as text
 
 use jp3_organiser_lib::commands::library::{
-    compact_library, delete_songs, edit_song_metadata, get_library_stats, initialize_library,
-    load_library, save_to_library, FileToSave,
+    backfill_song_hashes, backup_library, compact_song_tail, delete_backup, delete_songs,
+    discard_sync, edit_album, edit_song_metadata, edit_songs_metadata, empty_trash,
+    export_library_report, export_selection, export_sync_plan, get_album, get_artist,
+    get_backup_retention, get_bucket_layout, get_compaction_thresholds, get_favorites,
+    get_interrupted_writes, get_library_info, get_library_stats, get_low_confidence_songs,
+    get_pending_sync, get_song_file_info, get_sync_history, get_transcode_cache_stats,
+    get_trash_retention, initialize_library, list_backups, list_trash, load_library,
+    load_library_delta, load_library_page, merge_albums, migrate_library, plan_device_sync,
+    preview_delete, prune_transcode_cache, rate_song, recover_interrupted_writes, relink_song,
+    repair_library, restore_from_trash, restore_library, restore_songs, resume_sync,
+    run_compaction, save_to_library, scan_missing_files, scan_orphaned_files,
+    select_songs_for_size, set_backup_retention, set_bucket_layout, set_compaction_thresholds,
+    set_trash_retention, toggle_favorite, verify_library, FileToSave, INTERRUPTED_WRITE,
+    LIBRARY_CORRUPTED, SNAPSHOT_EXPIRED,
 };
 use jp3_organiser_lib::commands::playlist::{create_playlist, load_playlist};
-use jp3_organiser_lib::models::AudioMetadata;
+use jp3_organiser_lib::models::{
+    AudioFormat, AudioMetadata, BucketLayout, CompactPhase, CompactionRule, CompactionThresholds,
+    EncodeProfile, FileNameScheme, LibraryPageFilter, LibraryPageKind, LibrarySort,
+    MatchConfidence, MetadataSource, OrphanAction, PendingSync, ReportFormat, SelectionPreferences,
+    SelectionPriority, SongEdit, SongSortKey, SyncAction, SyncReason, WriteRecovery,
+};
+use jp3_organiser_lib::services::compaction_service::{
+    charge_idle_writes, idle_budget_remaining, CompactionControl, COMPACTION_CANCELLED,
+    IDLE_WRITE_BUDGET,
+};
+use jp3_organiser_lib::services::cover_art_service::cover_filename;
+use jp3_organiser_lib::services::device_service::read_device_id;
+use jp3_organiser_lib::services::fingerprint_index_service::{
+    load_fingerprint_index, save_fingerprint_index,
+};
+use jp3_organiser_lib::services::fingerprint_service::CHROMAPRINT_ALGORITHM;
+use jp3_organiser_lib::services::folder_scan_service::hash_file;
+use jp3_organiser_lib::services::sync_plan_service::save_pending_sync;
+use jp3_organiser_lib::services::thumbnail_service::{thumbnail_path, ThumbnailSize};
+use jp3_organiser_lib::services::transcode_service::cached_transcode_path;
+use std::sync::Mutex;
+
+/// Compact the way the `compact_library` command does, holding the
+/// compaction control for the duration.
+fn compact(
+    base_path: &str,
+    renumber_files: bool,
+    dry_run: bool,
+) -> Result<jp3_organiser_lib::models::CompactResult, String> {
+    let control = CompactionControl::default();
+    let guard = control.begin()?;
+    run_compaction(base_path, renumber_files, dry_run, &guard, |_| {})
+}
 
 /// Helper to create a test environment with initialized library.
 fn setup_test_library() -> (tempfile::TempDir, String) {
@@ -31,6 +126,28 @@ fn create_dummy_audio_file(temp_dir: &tempfile::TempDir, name: &str) -> String {
     file_path.to_string_lossy().to_string()
 }
 
+/// Helper to rewrite library.bin as an older format version.
+///
+/// Song entries are cut back to their pre-v4 24 bytes; the genre table that
+/// follows them is kept (pre-v3 readers ignore it).
+fn downgrade_library_bin(library_bin: &std::path::Path, version: u32) {
+    let bytes = std::fs::read(library_bin).unwrap();
+    let read_u32 = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+    let song_count = read_u32(8) as usize;
+    let song_table_offset = read_u32(32) as usize;
+    let song_table_end = song_table_offset + song_count * 32;
+
+    let mut downgraded = bytes[..song_table_offset].to_vec();
+    for song in bytes[song_table_offset..song_table_end].chunks(32) {
+        downgraded.extend_from_slice(&song[..24]);
+    }
+    downgraded.extend_from_slice(&bytes[song_table_end..]);
+    downgraded[4..8].copy_from_slice(&version.to_le_bytes());
+    let genre_table_offset = (read_u32(44) as usize - song_count * 8) as u32;
+    downgraded[44..48].copy_from_slice(&genre_table_offset.to_le_bytes());
+    std::fs::write(library_bin, downgraded).unwrap();
+}
+
 /// Helper to create a FileToSave with common defaults.
 fn create_file_to_save(
     source_path: String,
@@ -51,7 +168,15 @@ fn create_file_to_save(
             duration_secs: Some(180),
             release_mbid: None,
             artist_mbid: None,
+            genre: None,
+            album_artist: None,
+            disc_number: None,
+            rating: None,
+            play_count: None,
         },
+        metadata_source: MetadataSource::Unknown,
+        confidence: None,
+        fingerprint: None,
     }
 }
 
@@ -97,7 +222,7 @@ fn test_string_deduplication_across_batches() {
     assert_eq!(result2.albums_added, 0, "Should add 0 new albums");
 
     // Verify library state
-    let library = load_library(base_path).unwrap();
+    let library = load_library(base_path, None).unwrap();
     assert_eq!(library.songs.len(), 2, "Should have 2 songs");
     assert_eq!(
         library.artists.len(),
@@ -164,7 +289,7 @@ fn test_duplicate_song_detection_across_batches() {
     );
 
     // Verify library still has only 1 song
-    let library = load_library(base_path.clone()).unwrap();
+    let library = load_library(base_path.clone(), None).unwrap();
     assert_eq!(library.songs.len(), 1, "Library should have exactly 1 song");
 
     // Verify only 1 file in music directory
@@ -199,10 +324,89 @@ fn test_duplicate_detection_within_same_batch() {
     );
 
     // Verify library has only 1 song
-    let library = load_library(base_path).unwrap();
+    let library = load_library(base_path, None).unwrap();
     assert_eq!(library.songs.len(), 1, "Library should have exactly 1 song");
 }
 
+#[test]
+fn test_duplicate_detection_by_content() {
+    let (temp_dir, base_path) = setup_test_library();
+    let file = create_dummy_audio_file(&temp_dir, "test.mp3");
+    save_to_library(
+        base_path.clone(),
+        vec![create_file_to_save(file, "Song", "Artist", "Album", 2020, 1)],
+    )
+    .unwrap();
+
+    // The same audio again under different tags, plus a copy within the batch
+    let copy = temp_dir.path().join("copy.mp3");
+    std::fs::copy(temp_dir.path().join("test.mp3"), &copy).unwrap();
+    let other = create_dummy_audio_file(&temp_dir, "other.mp3");
+    let other_copy = temp_dir.path().join("other_copy.mp3");
+    std::fs::copy(&other, &other_copy).unwrap();
+    let copy = copy.to_string_lossy().to_string();
+    let other_copy = other_copy.to_string_lossy().to_string();
+    let files = vec![
+        create_file_to_save(copy, "Renamed", "Someone Else", "Other Album", 2021, 1),
+        create_file_to_save(other, "Other", "Artist", "Album", 2020, 2),
+        create_file_to_save(other_copy, "Other Again", "Artist", "Album", 2020, 3),
+    ];
+
+    let result = save_to_library(base_path.clone(), files).unwrap();
+    assert_eq!(result.files_saved, 1);
+    assert_eq!(result.duplicates_skipped, 2);
+    assert_eq!(result.duplicate_song_ids, vec![0, 1]);
+    assert_eq!(result.artists_added, 0, "Skipped audio adds no artists");
+    assert_eq!(load_library(base_path, None).unwrap().songs.len(), 2);
+}
+
+#[test]
+fn test_duplicate_detection_by_fingerprint() {
+    // 48 silent sub-fingerprints, compressed as fpcalc outputs them
+    let fingerprint = || Some(("AgAAMAAAAAAAAAAAAAAAAAAAAAAAAA".to_string(), 180));
+    let (temp_dir, base_path) = setup_test_library();
+    let mut file = create_file_to_save(
+        create_dummy_audio_file(&temp_dir, "song.mp3"),
+        "Song",
+        "Artist",
+        "Album",
+        2020,
+        1,
+    );
+    file.fingerprint = fingerprint();
+    save_to_library(base_path.clone(), vec![file]).unwrap();
+
+    let index = load_fingerprint_index(temp_dir.path()).unwrap();
+    let stored = index.entries.values().next().expect("Fingerprint recorded on save");
+    assert_eq!(stored.algorithm, CHROMAPRINT_ALGORITHM);
+    assert_eq!(stored.duration_secs, 180);
+
+    // A different file (e.g. a FLAC rip) of the same recording, other tags
+    let mut rip = create_file_to_save(
+        create_dummy_audio_file(&temp_dir, "rip.flac"),
+        "Song (Remastered)",
+        "Artist",
+        "Album",
+        2020,
+        1,
+    );
+    rip.fingerprint = fingerprint();
+    let result = save_to_library(base_path.clone(), vec![rip.clone()]).unwrap();
+    assert_eq!(result.files_saved, 0);
+    assert_eq!(result.duplicates_skipped, 1);
+    assert_eq!(result.duplicate_song_ids, vec![0]);
+
+    // Fingerprints from another algorithm can't be compared, so are ignored
+    let mut index = load_fingerprint_index(temp_dir.path()).unwrap();
+    for entry in index.entries.values_mut() {
+        entry.algorithm = CHROMAPRINT_ALGORITHM + 1;
+    }
+    save_fingerprint_index(temp_dir.path(), &index).unwrap();
+    let result = save_to_library(base_path, vec![rip]).unwrap();
+    assert_eq!(result.files_saved, 1);
+    assert_eq!(result.duplicates_skipped, 0);
+}
+
 // =============================================================================
 // Soft Delete Tests
 // =============================================================================
@@ -223,7 +427,7 @@ fn test_soft_delete_songs() {
     save_to_library(base_path.clone(), files).unwrap();
 
     // Verify we have 2 songs
-    let library = load_library(base_path.clone()).unwrap();
+    let library = load_library(base_path.clone(), None).unwrap();
     assert_eq!(library.songs.len(), 2, "Should have 2 songs before delete");
 
     // Verify audio files exist before delete
@@ -253,7 +457,7 @@ fn test_soft_delete_songs() {
     assert!(audio_file_2.exists(), "Audio file 2 should still exist");
 
     // Verify we now have 1 song (deleted one is filtered out)
-    let library = load_library(base_path.clone()).unwrap();
+    let library = load_library(base_path.clone(), None).unwrap();
     assert_eq!(library.songs.len(), 1, "Should have 1 song after delete");
     assert_eq!(
         library.songs[0].title, "Song Two",
@@ -267,6 +471,50 @@ fn test_soft_delete_songs() {
     assert_eq!(stats.deleted_songs, 1, "Deleted songs should be 1");
 }
 
+#[test]
+fn test_preview_delete() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = (0..3)
+        .map(|i| {
+            create_file_to_save(
+                create_dummy_audio_file(&temp_dir, &format!("{}.mp3", i)),
+                &format!("Song {}", i),
+                "Artist",
+                "Album",
+                2020,
+                i + 1,
+            )
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    create_playlist(base_path.clone(), "Road".to_string(), vec![0, 1, 2]).unwrap();
+    create_playlist(base_path.clone(), "Gym".to_string(), vec![2]).unwrap();
+    create_playlist(base_path.clone(), "Calm".to_string(), vec![1]).unwrap();
+    delete_songs(base_path.clone(), vec![2]).unwrap();
+
+    // Song 2 is already deleted; 9 never existed
+    let preview = preview_delete(base_path.clone(), vec![0, 1, 2, 9]).unwrap();
+    assert_eq!(preview.songs, 2);
+    assert_eq!(preview.not_found, vec![2, 9]);
+    assert_eq!(preview.files, 2);
+    assert_eq!(
+        preview.bytes,
+        ["0.mp3", "1.mp3"]
+            .iter()
+            .map(|name| format!("fake audio data for {}", name).len() as u64)
+            .sum::<u64>()
+    );
+    let playlists: Vec<_> = preview
+        .playlists
+        .iter()
+        .map(|p| (p.name.as_str(), p.songs_affected, p.songs_remaining))
+        .collect();
+    assert_eq!(playlists, vec![("Calm", 1, 0), ("Road", 2, 1)]);
+
+    // Nothing was deleted
+    assert_eq!(load_library(base_path.clone(), None).unwrap().songs.len(), 2);
+}
+
 #[test]
 fn test_delete_nonexistent_song() {
     let (temp_dir, base_path) = setup_test_library();
@@ -284,6 +532,116 @@ fn test_delete_nonexistent_song() {
     assert_eq!(delete_result.not_found.len(), 3, "Should have 3 not_found");
 }
 
+#[test]
+fn test_restore_songs() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let files = vec![
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "one.mp3"),
+            "Song One", "Artist", "Album", 2020, 1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "two.mp3"),
+            "Song Two", "Artist", "Album", 2020, 2,
+        ),
+    ];
+    save_to_library(base_path.clone(), files).unwrap();
+    delete_songs(base_path.clone(), vec![0, 1]).unwrap();
+
+    // Song 0's file comes back out of the trash
+    let result = restore_songs(base_path.clone(), vec![0, 0, 7]).unwrap();
+    assert_eq!(result.songs_restored, 1);
+    assert_eq!(result.not_deleted, vec![0, 7], "Already restored and unknown IDs");
+    assert!(result.missing_files.is_empty());
+
+    // Song 1's file is gone for good once the trash is emptied
+    empty_trash(base_path.clone()).unwrap();
+    let result = restore_songs(base_path.clone(), vec![1]).unwrap();
+    assert_eq!(result.songs_restored, 0);
+    assert_eq!(result.missing_files, vec![1]);
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.songs.len(), 1);
+    assert_eq!(library.songs[0].title, "Song One");
+    let music_path = temp_dir.path().join("jp3").join("music");
+    assert!(music_path.join(&library.songs[0].path).exists());
+}
+
+#[test]
+fn test_deleted_audio_goes_to_trash() {
+    let (temp_dir, base_path) = setup_test_library();
+    let file = create_dummy_audio_file(&temp_dir, "test.mp3");
+    save_to_library(
+        base_path.clone(),
+        vec![create_file_to_save(file, "Song One", "Artist", "Album", 2020, 1)],
+    )
+    .unwrap();
+    let song_path = load_library(base_path.clone(), None).unwrap().songs[0].path.clone();
+    let music_file = temp_dir.path().join("jp3").join("music").join(&song_path);
+
+    let result = delete_songs(base_path.clone(), vec![0]).unwrap();
+    assert_eq!(result.files_deleted, 1);
+    assert!(!music_file.exists());
+
+    let trash = list_trash(base_path.clone()).unwrap();
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].original_path, song_path);
+    assert_eq!(trash[0].title.as_deref(), Some("Song One"));
+
+    let result =
+        restore_from_trash(base_path.clone(), vec![trash[0].id.clone(), "nope".to_string()])
+            .unwrap();
+    assert_eq!(result.files_restored, 1);
+    assert_eq!(result.songs_restored, 1);
+    assert_eq!(result.not_restored, vec!["nope".to_string()]);
+    assert!(music_file.exists());
+    assert!(list_trash(base_path.clone()).unwrap().is_empty());
+    assert_eq!(load_library(base_path, None).unwrap().songs.len(), 1);
+}
+
+#[test]
+fn test_trash_retention() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = vec![
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "one.mp3"),
+            "Song One", "Artist", "Album", 2020, 1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "two.mp3"),
+            "Song Two", "Artist", "Album", 2020, 2,
+        ),
+    ];
+    save_to_library(base_path.clone(), files).unwrap();
+    assert_eq!(get_trash_retention(base_path.clone()).unwrap(), Some(30));
+
+    delete_songs(base_path.clone(), vec![0]).unwrap();
+
+    // Backdate the first delete past the retention period
+    let manifest_path = temp_dir.path().join("jp3").join(".trash").join("manifest.json");
+    let manifest = std::fs::read_to_string(&manifest_path).unwrap();
+    let mut entries: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    entries[0]["deletedAt"] = serde_json::json!(1);
+    std::fs::write(&manifest_path, entries.to_string()).unwrap();
+
+    // Keeping files forever leaves it alone
+    set_trash_retention(base_path.clone(), None).unwrap();
+    delete_songs(base_path.clone(), vec![1]).unwrap();
+    assert_eq!(list_trash(base_path.clone()).unwrap().len(), 2);
+
+    // The next delete purges it
+    set_trash_retention(base_path.clone(), Some(7)).unwrap();
+    delete_songs(base_path.clone(), vec![]).unwrap();
+    let trash = list_trash(base_path.clone()).unwrap();
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].title.as_deref(), Some("Song Two"));
+
+    let result = empty_trash(base_path.clone()).unwrap();
+    assert_eq!(result.files_removed, 1);
+    assert!(list_trash(base_path).unwrap().is_empty());
+}
+
 // =============================================================================
 // Edit Metadata Tests
 // =============================================================================
@@ -314,6 +672,11 @@ fn test_edit_song_metadata() {
         duration_secs: Some(180),
         release_mbid: None,
         artist_mbid: None,
+        genre: None,
+        album_artist: None,
+        disc_number: None,
+        rating: None,
+        play_count: None,
     };
 
     let edit_result = edit_song_metadata(base_path.clone(), 0, new_metadata).unwrap();
@@ -322,7 +685,7 @@ fn test_edit_song_metadata() {
     assert!(edit_result.album_created, "Should create new album");
 
     // Verify the library now shows the corrected metadata
-    let library = load_library(base_path.clone()).unwrap();
+    let library = load_library(base_path.clone(), None).unwrap();
     assert_eq!(library.songs.len(), 1, "Should have 1 active song");
     assert_eq!(library.songs[0].title, "Correct Title");
     assert_eq!(library.songs[0].artist_name, "Correct Artist");
@@ -373,7 +736,7 @@ fn test_compact_library() {
     );
 
     // Compact
-    let compact_result = compact_library(base_path.clone()).unwrap();
+    let compact_result = compact(&base_path, false, false).unwrap();
 
     assert_eq!(compact_result.songs_removed, 1, "Should remove 1 song");
     assert_eq!(
@@ -394,7 +757,7 @@ fn test_compact_library() {
     assert_eq!(stats_after.total_albums, 1, "Should have 1 album");
 
     // Verify the remaining songs are correct
-    let library = load_library(base_path).unwrap();
+    let library = load_library(base_path, None).unwrap();
     assert_eq!(library.songs.len(), 2);
     let titles: Vec<_> = library.songs.iter().map(|s| s.title.as_str()).collect();
     assert!(titles.contains(&"Song One"));
@@ -402,6 +765,201 @@ fn test_compact_library() {
     assert!(!titles.contains(&"Song Two")); // This was deleted
 }
 
+#[test]
+fn test_compact_library_dry_run() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = (0..4)
+        .map(|i| {
+            create_file_to_save(
+                create_dummy_audio_file(&temp_dir, &format!("{}.mp3", i)),
+                &format!("Song {}", i),
+                &format!("Artist {}", i % 2),
+                &format!("Album {}", i % 2),
+                2020,
+                i + 1,
+            )
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    delete_songs(base_path.clone(), vec![1, 3]).unwrap();
+    create_playlist(base_path.clone(), "Mix".to_string(), vec![2, 1]).unwrap();
+
+    let library_bin = temp_dir.path().join("jp3/metadata/library.bin");
+    let before = std::fs::read(&library_bin).unwrap();
+
+    // A preview writes nothing and takes no backup
+    let preview = compact(&base_path, true, true).unwrap();
+    assert!(preview.dry_run);
+    assert_eq!(std::fs::read(&library_bin).unwrap(), before);
+    assert!(list_backups(base_path.clone()).unwrap().is_empty());
+    assert!(temp_dir.path().join("jp3/music/00/003.mp3").exists());
+    assert_eq!(preview.files_renumbered, 1);
+
+    // ...and predicts what the real compaction does
+    let result = compact(&base_path, false, false).unwrap();
+    assert!(!result.dry_run);
+    assert_eq!(
+        (preview.songs_removed, preview.artists_removed, preview.albums_removed),
+        (2, 1, 1)
+    );
+    assert_eq!(
+        (preview.songs_removed, preview.artists_removed, preview.albums_removed),
+        (result.songs_removed, result.artists_removed, result.albums_removed)
+    );
+    assert_eq!(preview.strings_removed, result.strings_removed);
+    assert_eq!(preview.playlists_updated, result.playlists_updated);
+    assert_eq!(preview.new_size_bytes, result.new_size_bytes);
+    assert_eq!(preview.bytes_saved, result.bytes_saved);
+}
+
+#[test]
+fn test_compact_library_renumbers_files() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    // 258 songs: a full 00 bucket and two files in 01
+    let files = (0..258)
+        .map(|i| {
+            create_file_to_save(
+                create_dummy_audio_file(&temp_dir, &format!("{}.mp3", i)),
+                &format!("Song {}", i),
+                "Artist",
+                "Album",
+                2020,
+                i + 1,
+            )
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    delete_songs(base_path.clone(), vec![0, 10]).unwrap();
+
+    // Without the option files stay where they are
+    let result = compact(&base_path, false, false).unwrap();
+    assert_eq!((result.files_renumbered, result.buckets_removed), (0, 0));
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.songs[0].path, "00/002.mp3");
+
+    let result = compact(&base_path, true, false).unwrap();
+    assert_eq!(result.files_renumbered, 256);
+    assert_eq!(result.buckets_removed, 1);
+
+    let music_dir = temp_dir.path().join("jp3").join("music");
+    let library = load_library(base_path.clone(), None).unwrap();
+    for (index, song) in library.songs.iter().enumerate() {
+        assert_eq!(song.path, format!("00/{:03}.mp3", index + 1));
+        let number: u32 = song.title.trim_start_matches("Song ").parse().unwrap();
+        let contents = std::fs::read_to_string(music_dir.join(&song.path)).unwrap();
+        assert_eq!(contents, format!("fake audio data for {}.mp3", number));
+    }
+    assert!(!music_dir.join("01").exists());
+    assert!(!music_dir.join(".renumber").exists());
+
+    // Hashes followed the files
+    assert!(!verify_library(base_path).unwrap().has_issues());
+}
+
+#[test]
+fn test_bucket_layout_setting() {
+    let (temp_dir, base_path) = setup_test_library();
+    let save = |names: &[&str]| {
+        let files = names
+            .iter()
+            .map(|name| {
+                let path = create_dummy_audio_file(&temp_dir, &format!("{}.mp3", name));
+                create_file_to_save(path, name, "Artist", "Album", 2020, 1)
+            })
+            .collect();
+        save_to_library(base_path.clone(), files).unwrap();
+    };
+    save(&["A", "B"]);
+
+    assert_eq!(get_bucket_layout(base_path.clone()).unwrap(), BucketLayout::default());
+    let layout = BucketLayout {
+        files_per_bucket: 2,
+        two_level: true,
+        ..BucketLayout::default()
+    };
+    let empty = BucketLayout {
+        files_per_bucket: 0,
+        ..layout
+    };
+    assert!(set_bucket_layout(base_path.clone(), empty).is_err());
+    set_bucket_layout(base_path.clone(), layout).unwrap();
+    assert_eq!(get_bucket_layout(base_path.clone()).unwrap(), layout);
+
+    // Songs already saved stay put; new ones use the new layout
+    save(&["C", "D", "E"]);
+    let paths = |base_path: &String| -> Vec<String> {
+        let library = load_library(base_path.clone(), None).unwrap();
+        library.songs.into_iter().map(|song| song.path).collect()
+    };
+    assert_eq!(
+        paths(&base_path),
+        vec!["00/001.mp3", "00/002.mp3", "00/00/001.mp3", "00/00/002.mp3", "00/01/001.mp3"]
+    );
+    assert!(!verify_library(base_path.clone()).unwrap().has_issues());
+
+    // Renumbering compaction moves everything into the layout
+    compact(&base_path, true, false).unwrap();
+    save(&["F"]);
+    assert_eq!(
+        paths(&base_path),
+        vec![
+            "00/00/001.mp3",
+            "00/00/002.mp3",
+            "00/01/001.mp3",
+            "00/01/002.mp3",
+            "01/00/001.mp3",
+            "01/00/002.mp3",
+        ]
+    );
+    let music_dir = temp_dir.path().join("jp3").join("music");
+    let contents = std::fs::read_to_string(music_dir.join("01").join("00").join("001.mp3"));
+    assert_eq!(contents.unwrap(), "fake audio data for E.mp3");
+    assert!(!verify_library(base_path).unwrap().has_issues());
+}
+
+#[test]
+fn test_song_named_files() {
+    let (temp_dir, base_path) = setup_test_library();
+    let layout = BucketLayout {
+        files_per_bucket: 2,
+        file_names: FileNameScheme::TrackTitle,
+        ..BucketLayout::default()
+    };
+    set_bucket_layout(base_path.clone(), layout).unwrap();
+
+    // The same title on two albums can't share a name
+    let files = vec![
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "a.mp3"), "Intro", "Artist", "First", 2020, 1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "b.mp3"), "Intro", "Artist", "Second", 2021, 1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "c.mp3"), "What?", "Artist", "Second", 2021, 2,
+        ),
+    ];
+    save_to_library(base_path.clone(), files).unwrap();
+    let paths = |base_path: &String| -> Vec<String> {
+        let library = load_library(base_path.clone(), None).unwrap();
+        library.songs.into_iter().map(|song| song.path).collect()
+    };
+    assert_eq!(
+        paths(&base_path),
+        vec!["00/01 - Intro.mp3", "00/01 - Intro (2).mp3", "01/02 - What_.mp3"]
+    );
+
+    // Renumbering merges buckets but keeps the names
+    delete_songs(base_path.clone(), vec![0]).unwrap();
+    compact(&base_path, true, false).unwrap();
+    assert_eq!(paths(&base_path), vec!["00/01 - Intro (2).mp3", "00/02 - What_.mp3"]);
+    let music_dir = temp_dir.path().join("jp3").join("music");
+    let contents = std::fs::read_to_string(music_dir.join("00").join("02 - What_.mp3"));
+    assert_eq!(contents.unwrap(), "fake audio data for c.mp3");
+    assert!(!verify_library(base_path).unwrap().has_issues());
+}
+
 // =============================================================================
 // Edit with Playlist Remapping Tests
 // =============================================================================
@@ -433,16 +991,22 @@ fn test_edit_song_remaps_playlist_ids() {
     let playlist_before = load_playlist(base_path.clone(), playlist_id).unwrap();
     assert_eq!(playlist_before.song_ids, vec![0, 1, 2]);
 
-    // Edit song 1 (Song Two) - this should create a new song ID (3) and remap playlist
+    // Edit song 1 (Song Two) beyond its title - this should create a new song ID (3)
+    // and remap playlist
     let new_metadata = AudioMetadata {
         title: Some("Song Two (Edited)".to_string()),
         artist: Some("Artist".to_string()),
         album: Some("Album".to_string()),
         year: Some(2020),
-        track_number: Some(2),
+        track_number: Some(4),
         duration_secs: Some(180),
         release_mbid: None,
         artist_mbid: None,
+        genre: None,
+        album_artist: None,
+        disc_number: None,
+        rating: None,
+        play_count: None,
     };
 
     let edit_result = edit_song_metadata(base_path.clone(), 1, new_metadata).unwrap();
@@ -461,7 +1025,7 @@ fn test_edit_song_remaps_playlist_ids() {
     );
 
     // Verify the library shows the edited song
-    let library = load_library(base_path).unwrap();
+    let library = load_library(base_path, None).unwrap();
     assert_eq!(library.songs.len(), 3, "Should have 3 active songs");
 
     let edited_song = library
@@ -472,6 +1036,164 @@ fn test_edit_song_remaps_playlist_ids() {
     assert_eq!(edited_song.title, "Song Two (Edited)");
 }
 
+#[test]
+fn test_edit_song_title_in_place() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let files: Vec<FileToSave> = (1..=2)
+        .map(|track| {
+            let file = create_dummy_audio_file(&temp_dir, &format!("song{}.mp3", track));
+            create_file_to_save(
+                file,
+                &format!("Song {}", track),
+                "Artist",
+                "Album",
+                2020,
+                track,
+            )
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    rate_song(base_path.clone(), 0, 4).unwrap();
+    let playlist_id = create_playlist(base_path.clone(), "Mix".to_string(), vec![1, 0])
+        .unwrap()
+        .playlist_id;
+    let library_bin = temp_dir.path().join("jp3").join("metadata").join("library.bin");
+    let size_before = std::fs::metadata(&library_bin).unwrap().len();
+
+    let retitled = |title: &str| AudioMetadata {
+        title: Some(title.to_string()),
+        artist: Some("Artist".to_string()),
+        album: Some("Album".to_string()),
+        year: None,
+        track_number: Some(1),
+        duration_secs: None,
+        release_mbid: None,
+        artist_mbid: None,
+        genre: None,
+        album_artist: None,
+        disc_number: None,
+        rating: None,
+        play_count: None,
+    };
+
+    // A new title string keeps the ID and rewrites nothing else
+    let result = edit_song_metadata(base_path.clone(), 0, retitled("Song One")).unwrap();
+    assert_eq!(result.new_song_id, 0);
+    assert!(result.in_place);
+    assert_eq!(result.playlists_updated, 0);
+
+    // So does a title already in the string table
+    let result = edit_song_metadata(base_path.clone(), 0, retitled("Album")).unwrap();
+    assert_eq!(result.new_song_id, 0);
+    assert!(result.in_place);
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.songs.len(), 2);
+    let song = library.songs.iter().find(|s| s.id == 0).unwrap();
+    assert_eq!(song.title, "Album");
+    assert_eq!(song.metadata_source, MetadataSource::Manual);
+    assert_eq!(song.rating, 4);
+    assert_eq!(std::fs::metadata(&library_bin).unwrap().len(), size_before);
+    assert!(!verify_library(base_path.clone()).unwrap().has_issues());
+    assert_eq!(load_playlist(base_path.clone(), playlist_id).unwrap().song_ids, vec![1, 0]);
+
+    // Changing anything else still appends a new entry
+    let mut moved = retitled("Song One");
+    moved.track_number = Some(3);
+    let result = edit_song_metadata(base_path.clone(), 0, moved).unwrap();
+    assert_eq!(result.new_song_id, 2);
+    assert!(!result.in_place);
+}
+
+#[test]
+fn test_edit_songs_metadata_batch() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let files: Vec<FileToSave> = (1..=3)
+        .map(|track| {
+            let file = create_dummy_audio_file(&temp_dir, &format!("song{}.mp3", track));
+            create_file_to_save(
+                file,
+                &format!("Song {}", track),
+                "Artist",
+                "Album",
+                2020,
+                track,
+            )
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    let playlist_id = create_playlist(base_path.clone(), "Mix".to_string(), vec![2, 0, 1])
+        .unwrap()
+        .playlist_id;
+
+    let retagged = |track: u32| AudioMetadata {
+        title: Some(format!("Song {}", track)),
+        artist: Some("Artist".to_string()),
+        album: Some("Album (Remastered)".to_string()),
+        year: Some(2021),
+        track_number: Some(track),
+        duration_secs: None,
+        release_mbid: None,
+        artist_mbid: None,
+        genre: None,
+        album_artist: None,
+        disc_number: None,
+        rating: None,
+        play_count: None,
+    };
+    let edits = vec![
+        SongEdit {
+            song_id: 0,
+            metadata: retagged(1),
+        },
+        SongEdit {
+            song_id: 2,
+            metadata: retagged(3),
+        },
+    ];
+
+    let result = edit_songs_metadata(base_path.clone(), edits).unwrap();
+    assert_eq!(result.new_song_ids, vec![3, 4]);
+    assert_eq!(result.edited_in_place, 0);
+    assert_eq!(result.artists_created, 0);
+    assert_eq!(result.albums_created, 1, "Both edits share the new album");
+    assert_eq!(result.playlists_updated, 1);
+
+    let playlist = load_playlist(base_path.clone(), playlist_id).unwrap();
+    assert_eq!(playlist.song_ids, vec![4, 3, 1]);
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.songs.len(), 3);
+    let remastered: Vec<u32> = library
+        .songs
+        .iter()
+        .filter(|s| s.album_name == "Album (Remastered)")
+        .map(|s| s.id)
+        .collect();
+    assert_eq!(remastered, vec![3, 4]);
+
+    // A song edited twice rejects the whole batch
+    let edits = vec![
+        SongEdit {
+            song_id: 1,
+            metadata: retagged(2),
+        },
+        SongEdit {
+            song_id: 1,
+            metadata: retagged(2),
+        },
+    ];
+    assert!(edit_songs_metadata(base_path.clone(), edits).is_err());
+    let missing = vec![SongEdit {
+        song_id: 99,
+        metadata: retagged(1),
+    }];
+    assert!(edit_songs_metadata(base_path.clone(), missing).is_err());
+    assert_eq!(load_library(base_path, None).unwrap().songs.len(), 3);
+}
+
 #[test]
 fn test_edit_song_no_playlists_affected() {
     let (temp_dir, base_path) = setup_test_library();
@@ -513,6 +1235,11 @@ fn test_edit_song_no_playlists_affected() {
         duration_secs: Some(180),
         release_mbid: None,
         artist_mbid: None,
+        genre: None,
+        album_artist: None,
+        disc_number: None,
+        rating: None,
+        play_count: None,
     };
 
     let edit_result = edit_song_metadata(base_path, 1, new_metadata).unwrap();
@@ -554,7 +1281,7 @@ fn test_edit_then_compact_preserves_audio_file() {
     assert_eq!(save_result.songs_added, 1);
 
     // Verify the audio file was copied to the library
-    let library = load_library(base_path.clone()).unwrap();
+    let library = load_library(base_path.clone(), None).unwrap();
     let song_path = &library.songs[0].path;
     let music_path = std::path::Path::new(&base_path)
         .join("jp3")
@@ -572,6 +1299,11 @@ fn test_edit_then_compact_preserves_audio_file() {
         duration_secs: Some(180),
         release_mbid: None,
         artist_mbid: None,
+        genre: None,
+        album_artist: None,
+        disc_number: None,
+        rating: None,
+        play_count: None,
     };
     let edit_result = edit_song_metadata(base_path.clone(), 0, new_metadata).unwrap();
     assert!(edit_result.album_created, "Should create new album");
@@ -589,7 +1321,7 @@ fn test_edit_then_compact_preserves_audio_file() {
     assert_eq!(stats_before_compact.active_songs, 1);
 
     // Now compact the library
-    let compact_result = compact_library(base_path.clone()).unwrap();
+    let compact_result = compact(&base_path, false, false).unwrap();
     assert_eq!(compact_result.songs_removed, 1, "Should remove 1 deleted song");
 
     // CRITICAL: Audio file should STILL exist because the new song uses the same path
@@ -599,8 +1331,2431 @@ fn test_edit_then_compact_preserves_audio_file() {
     );
 
     // Verify the new song still has the correct path and can be loaded
-    let library_after = load_library(base_path.clone()).unwrap();
+    let library_after = load_library(base_path.clone(), None).unwrap();
     assert_eq!(library_after.songs.len(), 1);
     assert_eq!(library_after.songs[0].path, *song_path);
     assert_eq!(library_after.songs[0].album_name, "New Album");
 }
+
+// =============================================================================
+// Library Availability Tests
+// =============================================================================
+
+#[test]
+fn test_missing_library_root_returns_library_unavailable() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    // Simulate the SD card being pulled
+    drop(temp_dir);
+
+    let err = load_library(base_path.clone(), None).unwrap_err();
+    assert!(
+        err.starts_with("LibraryUnavailable"),
+        "Expected LibraryUnavailable error, got: {}",
+        err
+    );
+
+    let err = get_library_info(base_path).unwrap_err();
+    assert!(err.starts_with("LibraryUnavailable"));
+}
+
+// =============================================================================
+// Sort Name Tests
+// =============================================================================
+
+#[test]
+fn test_load_library_orders_by_sort_name() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let files = vec![
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "a.mp3"),
+            "Another Brick",
+            "Pink Floyd",
+            "The Wall",
+            1979,
+            1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "b.mp3"),
+            "Hyperballad",
+            "Björk",
+            "Post",
+            1995,
+            1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "c.mp3"),
+            "Come Together",
+            "The Beatles",
+            "Abbey Road",
+            1969,
+            1,
+        ),
+    ];
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.version, 6);
+    let artists: Vec<_> = library.artists.iter().map(|a| a.name.as_str()).collect();
+    assert_eq!(artists, vec!["The Beatles", "Björk", "Pink Floyd"]);
+    let albums: Vec<_> = library.albums.iter().map(|a| a.sort_name.as_str()).collect();
+    assert_eq!(albums, vec!["abbey road", "post", "wall"]);
+
+    // A v1 library has no stored sort names; they are computed on load
+    let library_bin = temp_dir
+        .path()
+        .join("jp3")
+        .join("metadata")
+        .join("library.bin");
+    downgrade_library_bin(&library_bin, 1);
+
+    let library = load_library(base_path, None).unwrap();
+    assert_eq!(library.version, 1);
+    let artists: Vec<_> = library.artists.iter().map(|a| a.sort_name.as_str()).collect();
+    assert_eq!(artists, vec!["beatles", "bjork", "pink floyd"]);
+}
+
+#[test]
+fn test_load_library_sorted_songs() {
+    let (temp_dir, base_path) = setup_test_library();
+    let mut files = vec![
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "a.mp3"), "The Zoo", "Écho", "Beta", 2001, 2,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "b.mp3"), "apple", "The Band", "Alpha", 1999, 1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "c.mp3"), "Mango", "Écho", "Beta", 2001, 1,
+        ),
+    ];
+    files[0].metadata.duration_secs = Some(60);
+    files[2].metadata.duration_secs = Some(300);
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let titles = |key: SongSortKey, descending: bool| -> Vec<String> {
+        let sort = Some(LibrarySort { key, descending });
+        load_library(base_path.clone(), sort)
+            .unwrap()
+            .songs
+            .into_iter()
+            .map(|s| s.title)
+            .collect()
+    };
+
+    // Articles, case and accents don't affect the order
+    assert_eq!(titles(SongSortKey::Title, false), vec!["apple", "Mango", "The Zoo"]);
+    assert_eq!(titles(SongSortKey::Title, true), vec!["The Zoo", "Mango", "apple"]);
+    assert_eq!(titles(SongSortKey::Artist, false), vec!["apple", "Mango", "The Zoo"]);
+    assert_eq!(titles(SongSortKey::Album, true), vec!["The Zoo", "Mango", "apple"]);
+    assert_eq!(titles(SongSortKey::Year, false), vec!["apple", "Mango", "The Zoo"]);
+    assert_eq!(titles(SongSortKey::Duration, false), vec!["The Zoo", "apple", "Mango"]);
+    assert_eq!(titles(SongSortKey::DateAdded, true), vec!["Mango", "apple", "The Zoo"]);
+}
+
+#[test]
+fn test_load_library_sorted_songs_collates_titles() {
+    let (temp_dir, base_path) = setup_test_library();
+    let titles = ["zulu", "Øre", "apple", "Émile", "ärger", "ORANGE"];
+    let files = titles
+        .iter()
+        .enumerate()
+        .map(|(i, title)| {
+            create_file_to_save(
+                create_dummy_audio_file(&temp_dir, &format!("{}.mp3", i)),
+                title,
+                "Artist",
+                "Album",
+                2020,
+                i as u32 + 1,
+            )
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let sort = Some(LibrarySort {
+        key: SongSortKey::Title,
+        descending: false,
+    });
+    let library = load_library(base_path, sort).unwrap();
+    let sorted: Vec<_> = library.songs.iter().map(|s| s.title.as_str()).collect();
+    // Accents and case don't split the alphabet, including letters like "Ø"
+    assert_eq!(sorted, vec!["apple", "ärger", "Émile", "ORANGE", "Øre", "zulu"]);
+}
+
+// =============================================================================
+// Pagination Snapshot Tests
+// =============================================================================
+
+#[test]
+fn test_library_pages_share_a_snapshot() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let files = (1..=5)
+        .map(|i| {
+            create_file_to_save(
+                create_dummy_audio_file(&temp_dir, &format!("{}.mp3", i)),
+                &format!("Song {}", i),
+                "Artist",
+                "Album",
+                2020,
+                i,
+            )
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let first =
+        load_library_page(base_path.clone(), LibraryPageKind::Songs, 0, 2, None, None).unwrap();
+    assert_eq!(first.total, 5);
+    assert_eq!(first.songs.len(), 2);
+
+    let second = load_library_page(
+        base_path.clone(),
+        LibraryPageKind::Songs,
+        2,
+        2,
+        Some(first.snapshot.clone()),
+        None,
+    )
+    .unwrap();
+    assert_eq!(second.snapshot, first.snapshot);
+    assert_eq!(second.songs[0].title, "Song 3");
+
+    // An in-place delete changes the generation and expires the snapshot
+    delete_songs(base_path.clone(), vec![0]).unwrap();
+    let err = load_library_page(
+        base_path.clone(),
+        LibraryPageKind::Songs,
+        4,
+        2,
+        Some(first.snapshot.clone()),
+        None,
+    )
+    .unwrap_err();
+    assert!(err.starts_with(SNAPSHOT_EXPIRED), "got: {}", err);
+
+    // Starting over gives a fresh snapshot
+    let fresh = load_library_page(base_path, LibraryPageKind::Songs, 0, 10, None, None).unwrap();
+    assert_ne!(fresh.snapshot, first.snapshot);
+    assert_eq!(fresh.total, 4);
+}
+
+#[test]
+fn test_load_library_page_filtered() {
+    let (temp_dir, base_path) = setup_test_library();
+    let songs = [
+        ("A1", "Artist A", "Album X"),
+        ("A2", "Artist A", "Album Y"),
+        ("B1", "Artist B", "Album Z"),
+    ];
+    let files = songs
+        .iter()
+        .enumerate()
+        .map(|(i, (title, artist, album))| {
+            let file = create_dummy_audio_file(&temp_dir, &format!("{}.mp3", title));
+            create_file_to_save(file, title, artist, album, 2020, i as u32 + 1)
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    let library = load_library(base_path.clone(), None).unwrap();
+    let artist_a = library.artists.iter().find(|a| a.name == "Artist A").unwrap().id;
+    let album_y = library.albums.iter().find(|a| a.name == "Album Y").unwrap().id;
+
+    let by_artist = LibraryPageFilter {
+        artist_id: Some(artist_a),
+        album_id: None,
+    };
+    let page = load_library_page(
+        base_path.clone(),
+        LibraryPageKind::Songs,
+        1,
+        10,
+        None,
+        Some(by_artist.clone()),
+    )
+    .unwrap();
+    assert_eq!(page.total, 2);
+    assert_eq!(page.songs.len(), 1);
+    assert_eq!(page.songs[0].title, "A2");
+
+    let page = load_library_page(
+        base_path.clone(),
+        LibraryPageKind::Albums,
+        0,
+        10,
+        None,
+        Some(by_artist),
+    )
+    .unwrap();
+    let names: Vec<_> = page.albums.iter().map(|a| a.name.as_str()).collect();
+    assert_eq!(names, vec!["Album X", "Album Y"]);
+
+    let by_album = LibraryPageFilter {
+        artist_id: None,
+        album_id: Some(album_y),
+    };
+    let page =
+        load_library_page(base_path, LibraryPageKind::RecentlyAdded, 0, 10, None, Some(by_album))
+            .unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.songs[0].title, "A2");
+}
+
+#[test]
+fn test_load_library_delta() {
+    let (temp_dir, base_path) = setup_test_library();
+    let save = |name: &str, album: &str| {
+        let file = create_dummy_audio_file(&temp_dir, &format!("{}.mp3", name));
+        let files = vec![create_file_to_save(file, name, "Artist", album, 2020, 1)];
+        save_to_library(base_path.clone(), files).unwrap();
+    };
+    save("One", "First");
+    save("Two", "First");
+    let generation = load_library(base_path.clone(), None).unwrap().generation;
+
+    // Only the new song and its album come back
+    save("Three", "Second");
+    let delta = load_library_delta(base_path.clone(), generation).unwrap();
+    assert!(!delta.full);
+    assert_eq!(delta.songs.iter().map(|s| s.title.as_str()).collect::<Vec<_>>(), vec!["Three"]);
+    assert_eq!(delta.albums.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(), vec!["Second"]);
+    assert!(delta.artists.is_empty());
+    assert!(delta.removed_song_ids.is_empty());
+
+    // Edits in place and deletions since the last delta
+    rate_song(base_path.clone(), 1, 4).unwrap();
+    delete_songs(base_path.clone(), vec![0]).unwrap();
+    let delta = load_library_delta(base_path.clone(), delta.generation).unwrap();
+    assert_eq!(delta.songs.len(), 1);
+    assert_eq!(delta.songs[0].rating, 4);
+    assert_eq!(delta.removed_song_ids, vec![0]);
+
+    // Unknown generations get the whole library
+    let delta = load_library_delta(base_path, 9999).unwrap();
+    assert!(delta.full);
+    assert_eq!(delta.songs.len(), 2);
+}
+
+// =============================================================================
+// Genre Tests
+// =============================================================================
+
+#[test]
+fn test_genres_saved_and_compacted() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let with_genre = |name: &str, title: &str, genre: Option<&str>| {
+        let mut file = create_file_to_save(
+            create_dummy_audio_file(&temp_dir, name),
+            title,
+            "Artist",
+            "Album",
+            2020,
+            1,
+        );
+        file.metadata.genre = genre.map(str::to_string);
+        file
+    };
+    let files = vec![
+        with_genre("a.mp3", "Song A", Some("Rock")),
+        with_genre("b.mp3", "Song B", Some("Jazz")),
+        with_genre("c.mp3", "Song C", Some(" Rock ")),
+        with_genre("d.mp3", "Song D", None),
+    ];
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    let genres: Vec<_> = library
+        .genres
+        .iter()
+        .map(|g| (g.name.as_str(), g.song_count))
+        .collect();
+    assert_eq!(genres, vec![("Jazz", 1), ("Rock", 2)]);
+    let song_d = library.songs.iter().find(|s| s.title == "Song D").unwrap();
+    assert_eq!(song_d.genre, None);
+
+    // Compaction drops genres no active song uses
+    let song_b = library.songs.iter().find(|s| s.title == "Song B").unwrap();
+    delete_songs(base_path.clone(), vec![song_b.id]).unwrap();
+    compact(&base_path, false, false).unwrap();
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.genres.len(), 1);
+    assert_eq!(library.genres[0].name, "Rock");
+    let song_a = library.songs.iter().find(|s| s.title == "Song A").unwrap();
+    assert_eq!(song_a.genre.as_deref(), Some("Rock"));
+
+    // A v2 file has no genre table; its songs load without genres
+    let library_bin = temp_dir
+        .path()
+        .join("jp3")
+        .join("metadata")
+        .join("library.bin");
+    downgrade_library_bin(&library_bin, 2);
+
+    let library = load_library(base_path, None).unwrap();
+    assert_eq!(library.version, 2);
+    assert!(library.genres.is_empty());
+    assert!(library.songs.iter().all(|s| s.genre.is_none()));
+}
+
+// =============================================================================
+// Album Artist Tests
+// =============================================================================
+
+#[test]
+fn test_compilation_stays_one_album() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let files = [("a.mp3", "Song A", "Artist A"), ("b.mp3", "Song B", "Artist B")]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (name, title, artist))| {
+            let mut file = create_file_to_save(
+                create_dummy_audio_file(&temp_dir, name),
+                title,
+                artist,
+                "Greatest Hits",
+                2001,
+                i as u32 + 1,
+            );
+            file.metadata.album_artist = Some("Various Artists".to_string());
+            file
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let check = |library: &jp3_organiser_lib::models::ParsedLibrary| {
+        assert_eq!(library.albums.len(), 1);
+        assert_eq!(library.albums[0].artist_name, "Various Artists");
+        let artists: Vec<_> = library.artists.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(artists, vec!["Artist A", "Artist B", "Various Artists"]);
+        let song_b = library.songs.iter().find(|s| s.title == "Song B").unwrap();
+        assert_eq!(song_b.artist_name, "Artist B");
+        assert_eq!(song_b.album_name, "Greatest Hits");
+    };
+    check(&load_library(base_path.clone(), None).unwrap());
+
+    // The album artist has no songs of its own but survives compaction
+    compact(&base_path, false, false).unwrap();
+    check(&load_library(base_path, None).unwrap());
+}
+
+// =============================================================================
+// Metadata Source Tests
+// =============================================================================
+
+#[test]
+fn test_metadata_source_persisted_per_song() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let mut guessed = create_file_to_save(
+        create_dummy_audio_file(&temp_dir, "guessed.mp3"),
+        "Guessed",
+        "Artist",
+        "Album",
+        2020,
+        1,
+    );
+    guessed.metadata_source = MetadataSource::FolderStructure;
+    let mut tagged = create_file_to_save(
+        create_dummy_audio_file(&temp_dir, "tagged.mp3"),
+        "Tagged",
+        "Artist",
+        "Album",
+        2020,
+        2,
+    );
+    tagged.metadata_source = MetadataSource::Id3;
+    save_to_library(base_path.clone(), vec![guessed, tagged]).unwrap();
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    let source_of = |title: &str| {
+        library
+            .songs
+            .iter()
+            .find(|s| s.title == title)
+            .map(|s| s.metadata_source)
+            .unwrap()
+    };
+    assert_eq!(source_of("Guessed"), MetadataSource::FolderStructure);
+    assert_eq!(source_of("Tagged"), MetadataSource::Id3);
+
+    // Editing a song marks its metadata as user-entered
+    let fixed = create_file_to_save(String::new(), "Fixed", "Artist", "Album", 2020, 1);
+    edit_song_metadata(base_path.clone(), 0, fixed.metadata).unwrap();
+    compact(&base_path, false, false).unwrap();
+
+    let library = load_library(base_path, None).unwrap();
+    let fixed = library.songs.iter().find(|s| s.title == "Fixed").unwrap();
+    assert_eq!(fixed.metadata_source, MetadataSource::Manual);
+}
+
+// =============================================================================
+// Confidence Tests
+// =============================================================================
+
+#[test]
+fn test_low_confidence_songs() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let scores = [
+        ("Sure", Some(0.98)),
+        ("Unsure", Some(0.55)),
+        ("Doubtful", Some(0.7)),
+        ("Tagged", None),
+    ];
+    let files = scores
+        .into_iter()
+        .enumerate()
+        .map(|(i, (title, score))| {
+            let mut file = create_file_to_save(
+                create_dummy_audio_file(&temp_dir, &format!("{}.mp3", title)),
+                title,
+                "Artist",
+                "Album",
+                2020,
+                i as u32 + 1,
+            );
+            file.confidence = score.map(|s| MatchConfidence {
+                acoustid_score: Some(s),
+                acoustid_sources: Some(10),
+                musicbrainz_score: None,
+            });
+            file
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let low = get_low_confidence_songs(base_path.clone(), 80).unwrap();
+    let titles: Vec<_> = low.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(titles, vec!["Unsure", "Doubtful"]);
+    assert_eq!(low[0].confidence_score, Some(55));
+
+    // Editing a song clears its confidence, and compaction keeps the rest
+    let unsure_id = low[0].id;
+    let fixed = create_file_to_save(String::new(), "Unsure", "Artist", "Album", 2020, 2);
+    edit_song_metadata(base_path.clone(), unsure_id, fixed.metadata).unwrap();
+    compact(&base_path, false, false).unwrap();
+
+    let low = get_low_confidence_songs(base_path, 80).unwrap();
+    let titles: Vec<_> = low.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(titles, vec!["Doubtful"]);
+}
+
+// =============================================================================
+// Disc Number Tests
+// =============================================================================
+
+#[test]
+fn test_disc_numbers_persisted_per_song() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let on_disc = |name: &str, title: &str, disc: Option<u32>| {
+        let mut file = create_file_to_save(
+            create_dummy_audio_file(&temp_dir, name),
+            title,
+            "Artist",
+            "Double Album",
+            2001,
+            1,
+        );
+        file.metadata.disc_number = disc;
+        file
+    };
+    let files = vec![
+        on_disc("a.mp3", "Disc One Opener", Some(1)),
+        on_disc("b.mp3", "Disc Two Opener", Some(2)),
+        on_disc("c.mp3", "Untagged", None),
+    ];
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    let disc_of = |title: &str| {
+        library
+            .songs
+            .iter()
+            .find(|s| s.title == title)
+            .unwrap()
+            .disc_number
+    };
+    assert_eq!(disc_of("Disc One Opener"), 1);
+    assert_eq!(disc_of("Disc Two Opener"), 2);
+    assert_eq!(disc_of("Untagged"), 0);
+
+    // Editing without a disc number keeps the stored one
+    let song = library
+        .songs
+        .iter()
+        .find(|s| s.title == "Disc Two Opener")
+        .unwrap();
+    let metadata = AudioMetadata {
+        title: Some("Disc Two Opener (Remastered)".to_string()),
+        artist: Some("Artist".to_string()),
+        album: Some("Double Album".to_string()),
+        track_number: Some(1),
+        ..Default::default()
+    };
+    edit_song_metadata(base_path.clone(), song.id, metadata).unwrap();
+    compact(&base_path, false, false).unwrap();
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    let edited = library
+        .songs
+        .iter()
+        .find(|s| s.title == "Disc Two Opener (Remastered)")
+        .unwrap();
+    assert_eq!(edited.disc_number, 2);
+
+    // A v3 file has 24-byte song entries and no disc numbers
+    let library_bin = temp_dir
+        .path()
+        .join("jp3")
+        .join("metadata")
+        .join("library.bin");
+    downgrade_library_bin(&library_bin, 3);
+
+    let library = load_library(base_path, None).unwrap();
+    assert_eq!(library.version, 3);
+    assert_eq!(library.songs.len(), 3);
+    assert!(library.songs.iter().all(|s| s.disc_number == 0));
+}
+
+// =============================================================================
+// Rating Tests
+// =============================================================================
+
+#[test]
+fn test_rate_song() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let files = vec![
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "a.mp3"),
+            "Song A",
+            "Artist",
+            "Album",
+            2020,
+            1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "b.mp3"),
+            "Song B",
+            "Artist",
+            "Album",
+            2020,
+            2,
+        ),
+    ];
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let before = load_library(base_path.clone(), None).unwrap();
+    assert!(before.songs.iter().all(|s| s.rating == 0));
+
+    rate_song(base_path.clone(), 1, 4).unwrap();
+    assert!(rate_song(base_path.clone(), 0, 6).is_err());
+    assert!(rate_song(base_path.clone(), 99, 3).is_err());
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert!(library.generation > before.generation);
+    let ratings: Vec<_> = library.songs.iter().map(|s| (s.title.as_str(), s.rating)).collect();
+    assert_eq!(ratings, vec![("Song A", 0), ("Song B", 4)]);
+
+    // The rating survives an edit and compaction
+    let metadata = AudioMetadata {
+        title: Some("Song B (Live)".to_string()),
+        artist: Some("Artist".to_string()),
+        album: Some("Album".to_string()),
+        track_number: Some(2),
+        ..Default::default()
+    };
+    edit_song_metadata(base_path.clone(), 1, metadata).unwrap();
+    compact(&base_path, false, false).unwrap();
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    let edited = library.songs.iter().find(|s| s.title == "Song B (Live)").unwrap();
+    assert_eq!(edited.rating, 4);
+
+    // Deleted songs can't be rated
+    let song_a = library.songs.iter().find(|s| s.title == "Song A").unwrap();
+    delete_songs(base_path.clone(), vec![song_a.id]).unwrap();
+    assert!(rate_song(base_path, song_a.id, 5).is_err());
+}
+
+#[test]
+fn test_save_imports_rating_and_play_count() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let mut played = create_file_to_save(
+        create_dummy_audio_file(&temp_dir, "a.mp3"),
+        "Song A",
+        "Artist",
+        "Album",
+        2020,
+        1,
+    );
+    played.metadata.rating = Some(4);
+    played.metadata.play_count = Some(42);
+    let mut worn_out = create_file_to_save(
+        create_dummy_audio_file(&temp_dir, "b.mp3"),
+        "Song B",
+        "Artist",
+        "Album",
+        2020,
+        2,
+    );
+    worn_out.metadata.play_count = Some(100_000);
+    save_to_library(base_path.clone(), vec![played, worn_out]).unwrap();
+
+    let history = |base_path: &str| -> Vec<(String, u8, u16)> {
+        load_library(base_path.to_string(), None)
+            .unwrap()
+            .songs
+            .into_iter()
+            .map(|s| (s.title, s.rating, s.play_count))
+            .collect()
+    };
+    let expected = vec![
+        ("Song A".to_string(), 4, 42),
+        ("Song B".to_string(), 0, u16::MAX), // saturates
+    ];
+    assert_eq!(history(&base_path), expected);
+
+    // Kept through an edit and compaction
+    let metadata = AudioMetadata {
+        title: Some("Song A".to_string()),
+        artist: Some("Artist".to_string()),
+        album: Some("Album".to_string()),
+        track_number: Some(3),
+        ..Default::default()
+    };
+    edit_song_metadata(base_path.clone(), 0, metadata).unwrap();
+    compact(&base_path, false, false).unwrap();
+    let mut after = history(&base_path);
+    after.sort();
+    assert_eq!(after, expected);
+}
+
+// =============================================================================
+// Favorite Tests
+// =============================================================================
+
+#[test]
+fn test_toggle_favorite() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let files = (1..=3)
+        .map(|track| {
+            create_file_to_save(
+                create_dummy_audio_file(&temp_dir, &format!("{}.mp3", track)),
+                &format!("Song {}", track),
+                "Artist",
+                "Album",
+                2020,
+                track,
+            )
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    assert!(get_favorites(base_path.clone()).unwrap().is_empty());
+
+    assert!(toggle_favorite(base_path.clone(), 0).unwrap());
+    assert!(toggle_favorite(base_path.clone(), 2).unwrap());
+    assert!(toggle_favorite(base_path.clone(), 99).is_err());
+
+    let favorites: Vec<_> = get_favorites(base_path.clone())
+        .unwrap()
+        .into_iter()
+        .map(|s| s.title)
+        .collect();
+    assert_eq!(favorites, vec!["Song 1", "Song 3"]);
+
+    // Toggling again clears it
+    assert!(!toggle_favorite(base_path.clone(), 2).unwrap());
+    let library = load_library(base_path.clone(), None).unwrap();
+    let flags: Vec<_> = library.songs.iter().map(|s| s.is_favorite).collect();
+    assert_eq!(flags, vec![true, false, false]);
+
+    // The flag survives an edit and compaction; deleting leaves no favorite
+    let metadata = AudioMetadata {
+        title: Some("Song 1 (Remix)".to_string()),
+        artist: Some("Artist".to_string()),
+        album: Some("Album".to_string()),
+        track_number: Some(1),
+        ..Default::default()
+    };
+    edit_song_metadata(base_path.clone(), 0, metadata).unwrap();
+    compact(&base_path, false, false).unwrap();
+
+    let favorites = get_favorites(base_path.clone()).unwrap();
+    assert_eq!(favorites.len(), 1);
+    assert_eq!(favorites[0].title, "Song 1 (Remix)");
+
+    delete_songs(base_path.clone(), vec![favorites[0].id]).unwrap();
+    assert!(get_favorites(base_path.clone()).unwrap().is_empty());
+    assert!(toggle_favorite(base_path, favorites[0].id).is_err());
+}
+
+// =============================================================================
+// Date Added Tests
+// =============================================================================
+
+#[test]
+fn test_date_added_and_recently_added_page() {
+    let (temp_dir, base_path) = setup_test_library();
+    let now = || {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as u32
+    };
+
+    let before = now();
+    let first_batch = vec![
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "a.mp3"),
+            "Old A",
+            "Artist",
+            "Album",
+            2020,
+            1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "b.mp3"),
+            "Old B",
+            "Artist",
+            "Album",
+            2020,
+            2,
+        ),
+    ];
+    save_to_library(base_path.clone(), first_batch).unwrap();
+    let second_batch = vec![create_file_to_save(
+        create_dummy_audio_file(&temp_dir, "c.mp3"),
+        "New C",
+        "Artist",
+        "Album",
+        2020,
+        3,
+    )];
+    save_to_library(base_path.clone(), second_batch).unwrap();
+    let after = now();
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert!(library
+        .songs
+        .iter()
+        .all(|s| s.date_added >= before && s.date_added <= after));
+
+    // Newest first; songs saved in the same second fall back to newest ID
+    let page = load_library_page(
+        base_path.clone(),
+        LibraryPageKind::RecentlyAdded,
+        0,
+        2,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(page.total, 3);
+    let titles: Vec<_> = page.songs.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(titles, vec!["New C", "Old B"]);
+
+    // Editing keeps the original date
+    let old_a = library.songs.iter().find(|s| s.title == "Old A").unwrap();
+    let metadata = AudioMetadata {
+        title: Some("Old A (Edited)".to_string()),
+        artist: Some("Artist".to_string()),
+        album: Some("Album".to_string()),
+        track_number: Some(1),
+        ..Default::default()
+    };
+    edit_song_metadata(base_path.clone(), old_a.id, metadata).unwrap();
+    compact(&base_path, false, false).unwrap();
+
+    let library = load_library(base_path, None).unwrap();
+    let edited = library
+        .songs
+        .iter()
+        .find(|s| s.title == "Old A (Edited)")
+        .unwrap();
+    assert_eq!(edited.date_added, old_a.date_added);
+}
+
+// =============================================================================
+// Checksum Tests
+// =============================================================================
+
+#[test]
+fn test_checksum_detects_damaged_library() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let files = (1..=3)
+        .map(|i| {
+            create_file_to_save(
+                create_dummy_audio_file(&temp_dir, &format!("{}.mp3", i)),
+                &format!("Song {}", i),
+                "Artist",
+                "Album",
+                2020,
+                i,
+            )
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    // In-place edits keep the checksum valid
+    rate_song(base_path.clone(), 0, 4).unwrap();
+    toggle_favorite(base_path.clone(), 1).unwrap();
+    delete_songs(base_path.clone(), vec![2]).unwrap();
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.songs.len(), 2);
+
+    let library_bin = temp_dir
+        .path()
+        .join("jp3")
+        .join("metadata")
+        .join("library.bin");
+    let bytes = std::fs::read(&library_bin).unwrap();
+
+    // A file cut short mid-write, partway into the song table
+    let song_table_offset = u32::from_le_bytes(bytes[32..36].try_into().unwrap()) as usize;
+    std::fs::write(&library_bin, &bytes[..song_table_offset + 10]).unwrap();
+    let err = load_library(base_path.clone(), None).unwrap_err();
+    assert!(err.starts_with(LIBRARY_CORRUPTED), "unexpected error: {}", err);
+
+    // A flipped byte in the string table; in-place edits refuse it too
+    let mut damaged = bytes.clone();
+    damaged[60] ^= 0xFF;
+    std::fs::write(&library_bin, &damaged).unwrap();
+    assert!(load_library(base_path.clone(), None).unwrap_err().starts_with(LIBRARY_CORRUPTED));
+    assert!(rate_song(base_path.clone(), 0, 1).unwrap_err().starts_with(LIBRARY_CORRUPTED));
+
+    // Files older than v5 carry no checksum and still load
+    std::fs::write(&library_bin, &bytes).unwrap();
+    downgrade_library_bin(&library_bin, 3);
+    assert_eq!(load_library(base_path, None).unwrap().version, 3);
+}
+
+#[test]
+fn test_interrupted_write_recovery() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = (1..=3)
+        .map(|i| {
+            create_file_to_save(
+                create_dummy_audio_file(&temp_dir, &format!("{}.mp3", i)),
+                &format!("Song {}", i),
+                "Artist",
+                "Album",
+                2020,
+                i,
+            )
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    rate_song(base_path.clone(), 0, 4).unwrap();
+    delete_songs(base_path.clone(), vec![2]).unwrap();
+
+    // Finished writes leave no markers behind
+    let metadata = temp_dir.path().join("jp3").join("metadata");
+    let writes_dir = metadata.join("writes");
+    assert_eq!(std::fs::read_dir(&writes_dir).unwrap().count(), 0);
+    assert!(get_interrupted_writes(base_path.clone()).unwrap().writes.is_empty());
+
+    // A marker left by a run that stopped mid-write blocks loading
+    let leave_marker = || {
+        std::fs::write(
+            writes_dir.join("abc.json"),
+            r#"{"operationId":"abc","operation":"rate_song","startedAt":5}"#,
+        )
+        .unwrap()
+    };
+    leave_marker();
+    let err = load_library(base_path.clone(), None).unwrap_err();
+    assert!(err.starts_with(INTERRUPTED_WRITE), "unexpected error: {}", err);
+    let interrupted = get_interrupted_writes(base_path.clone()).unwrap();
+    assert_eq!(interrupted.writes.len(), 1);
+    assert_eq!(interrupted.writes[0].operation, "rate_song");
+    assert!(interrupted.library_intact);
+
+    // An undamaged library is kept as it is
+    let recovery = recover_interrupted_writes(base_path.clone(), None).unwrap();
+    assert_eq!(recovery, WriteRecovery::Intact);
+    assert_eq!(load_library(base_path.clone(), None).unwrap().songs.len(), 2);
+
+    // A torn library is rebuilt
+    leave_marker();
+    let library_bin = metadata.join("library.bin");
+    let mut damaged = std::fs::read(&library_bin).unwrap();
+    damaged[60] ^= 0xFF;
+    std::fs::write(&library_bin, &damaged).unwrap();
+    assert!(!get_interrupted_writes(base_path.clone()).unwrap().library_intact);
+    let recovery = recover_interrupted_writes(base_path.clone(), None).unwrap();
+    assert_eq!(recovery, WriteRecovery::Repaired);
+    assert!(get_interrupted_writes(base_path.clone()).unwrap().writes.is_empty());
+    assert!(load_library(base_path.clone(), None).is_ok());
+}
+
+// =============================================================================
+// Compaction Progress Tests
+// =============================================================================
+
+#[test]
+fn test_compaction_progress_and_cancel() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let files = (1..=4)
+        .map(|i| {
+            create_file_to_save(
+                create_dummy_audio_file(&temp_dir, &format!("{}.mp3", i)),
+                &format!("Song {}", i),
+                "Artist",
+                "Album",
+                2020,
+                i,
+            )
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    let library = load_library(base_path.clone(), None).unwrap();
+    let deleted = library.songs.iter().find(|s| s.title == "Song 2").unwrap();
+    delete_songs(base_path.clone(), vec![deleted.id]).unwrap();
+    create_playlist(base_path.clone(), "Mix".to_string(), vec![3, 0]).unwrap();
+
+    let library_bin = temp_dir
+        .path()
+        .join("jp3")
+        .join("metadata")
+        .join("library.bin");
+    let before = std::fs::read(&library_bin).unwrap();
+
+    // A cancelled compaction leaves library.bin exactly as it was
+    let control = CompactionControl::default();
+    let guard = control.begin().unwrap();
+    control.cancel();
+    let err = run_compaction(&base_path, false, false, &guard, |_| {}).unwrap_err();
+    assert!(err.starts_with(COMPACTION_CANCELLED), "unexpected error: {}", err);
+    assert_eq!(std::fs::read(&library_bin).unwrap(), before);
+    drop(guard);
+
+    let progress = Mutex::new(Vec::new());
+    let guard = control.begin().unwrap();
+    let result = run_compaction(&base_path, false, false, &guard, |p| {
+        progress.lock().unwrap().push(p)
+    })
+    .unwrap();
+    drop(guard);
+    assert_eq!(result.songs_removed, 1);
+    assert_eq!(result.playlists_updated, 1);
+    assert!(!library_bin.with_extension("bin.tmp").exists());
+
+    let progress = progress.into_inner().unwrap();
+    let mut phases: Vec<_> = progress.iter().map(|p| p.phase).collect();
+    phases.dedup();
+    assert_eq!(
+        phases,
+        vec![
+            CompactPhase::Strings,
+            CompactPhase::Songs,
+            CompactPhase::Writing,
+            CompactPhase::Playlists
+        ]
+    );
+    assert!(progress.iter().all(|p| p.done <= p.total));
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    let titles: Vec<_> = library.songs.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(titles, vec!["Song 1", "Song 3", "Song 4"]);
+    assert_eq!(load_playlist(base_path, 1).unwrap().song_ids, vec![2, 0]);
+
+    // Only one compaction runs at a time
+    let control = CompactionControl::default();
+    assert!(!control.cancel());
+    let guard = control.begin().unwrap();
+    assert!(control.begin().is_err());
+    assert!(control.cancel());
+    assert!(guard.cancel_flag().load(std::sync::atomic::Ordering::SeqCst));
+    drop(guard);
+    assert!(control.begin().is_ok());
+}
+
+#[test]
+fn test_idle_write_budget_persists() {
+    let (temp_dir, base_path) = setup_test_library();
+    let base = std::path::Path::new(&base_path);
+
+    assert_eq!(idle_budget_remaining(base), IDLE_WRITE_BUDGET);
+    charge_idle_writes(base, 1000).unwrap();
+    charge_idle_writes(base, IDLE_WRITE_BUDGET).unwrap();
+    // Read back from the card, as after a restart
+    assert_eq!(idle_budget_remaining(base), 0);
+
+    // A count from an earlier day no longer applies
+    let log_path = temp_dir.path().join("jp3").join("metadata").join("idle_compaction.json");
+    std::fs::write(&log_path, r#"{"day":1,"bytesWritten":1000}"#).unwrap();
+    assert_eq!(idle_budget_remaining(base), IDLE_WRITE_BUDGET);
+}
+
+#[test]
+fn test_idle_compaction_trims_song_tail() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let files = (1..=4)
+        .map(|i| {
+            create_file_to_save(
+                create_dummy_audio_file(&temp_dir, &format!("{}.mp3", i)),
+                &format!("Song {}", i),
+                "Artist",
+                "Album",
+                2020,
+                i,
+            )
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    create_playlist(base_path.clone(), "Mix".to_string(), vec![3, 0, 1]).unwrap();
+    delete_songs(base_path.clone(), vec![0, 2, 3]).unwrap();
+
+    let library_bin = temp_dir
+        .path()
+        .join("jp3")
+        .join("metadata")
+        .join("library.bin");
+    let size_before = std::fs::metadata(&library_bin).unwrap().len();
+
+    // Song 4's audio reappears, as if an older version had left it behind
+    let song_4_path = list_trash(base_path.clone())
+        .unwrap()
+        .into_iter()
+        .find(|e| e.title.as_deref() == Some("Song 4"))
+        .unwrap()
+        .original_path;
+    let left_behind = temp_dir.path().join("jp3").join("music").join(song_4_path);
+    std::fs::write(&left_behind, "fake audio data").unwrap();
+
+    // Too small a budget writes nothing
+    let result = compact_song_tail(&base_path, 10, 16).unwrap();
+    assert_eq!(result.songs_trimmed, 0);
+    assert_eq!(result.bytes_written, 0);
+    assert!(!result.done);
+    assert_eq!(std::fs::metadata(&library_bin).unwrap().len(), size_before);
+
+    // One song per step; the playlist drops the trimmed ID
+    let result = compact_song_tail(&base_path, 1, 1 << 20).unwrap();
+    assert_eq!(result.songs_trimmed, 1);
+    assert_eq!(result.playlists_updated, 1);
+    assert!(!result.done);
+    assert_eq!(load_playlist(base_path.clone(), 1).unwrap().song_ids, vec![0, 1]);
+
+    let result = compact_song_tail(&base_path, 1, 1 << 20).unwrap();
+    assert_eq!(result.songs_trimmed, 1);
+    assert!(result.done);
+    assert!(std::fs::metadata(&library_bin).unwrap().len() < size_before);
+
+    // A file left behind for a trimmed song went to the trash, not straight to deletion
+    let titles: Vec<_> = list_trash(base_path.clone())
+        .unwrap()
+        .into_iter()
+        .filter_map(|e| e.title)
+        .filter(|t| t == "Song 4")
+        .collect();
+    assert_eq!(titles.len(), 2);
+    assert!(!left_behind.exists());
+    assert!(!library_bin.with_extension("bin.tmp").exists());
+
+    // The deleted song in the middle stays for a full compaction
+    let result = compact_song_tail(&base_path, 1, 1 << 20).unwrap();
+    assert_eq!(result.songs_trimmed, 0);
+    assert!(result.done);
+
+    let stats = get_library_stats(base_path.clone()).unwrap();
+    assert_eq!(stats.deleted_songs, 1);
+
+    // The freed IDs go to new songs without stale playlist references
+    let files = vec![create_file_to_save(
+        create_dummy_audio_file(&temp_dir, "5.mp3"),
+        "Song 5",
+        "Artist",
+        "Album",
+        2020,
+        5,
+    )];
+    save_to_library(base_path.clone(), files).unwrap();
+    let library = load_library(base_path.clone(), None).unwrap();
+    let titles: Vec<_> = library.songs.iter().map(|s| (s.id, s.title.as_str())).collect();
+    assert_eq!(titles, vec![(1, "Song 2"), (2, "Song 5")]);
+    assert_eq!(load_playlist(base_path, 1).unwrap().song_ids, vec![0, 1]);
+}
+
+// =============================================================================
+// Migration Tests
+// =============================================================================
+
+#[test]
+fn test_migrate_library_upgrades_in_place() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let files = (1..=3)
+        .map(|i| {
+            create_file_to_save(
+                create_dummy_audio_file(&temp_dir, &format!("{}.mp3", i)),
+                &format!("Song {}", i),
+                "Artist",
+                "Album",
+                2020,
+                i,
+            )
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    delete_songs(base_path.clone(), vec![1]).unwrap();
+
+    let library_bin = temp_dir
+        .path()
+        .join("jp3")
+        .join("metadata")
+        .join("library.bin");
+    downgrade_library_bin(&library_bin, 1);
+    let old_bytes = std::fs::read(&library_bin).unwrap();
+    assert!(rate_song(base_path.clone(), 0, 3).is_err());
+
+    let result = migrate_library(base_path.clone()).unwrap();
+    assert_eq!(result.from_version, 1);
+    assert_eq!(result.to_version, 6);
+    let backup = result.backup_path.unwrap();
+    assert!(backup.ends_with("library.v1.bak"));
+    assert_eq!(std::fs::read(&backup).unwrap(), old_bytes);
+
+    // Deleted songs keep their slot, so every ID is unchanged
+    let stats = get_library_stats(base_path.clone()).unwrap();
+    assert_eq!(stats.total_songs, 3);
+    assert_eq!(stats.deleted_songs, 1);
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.version, 6);
+    let songs: Vec<_> = library.songs.iter().map(|s| (s.id, s.title.as_str())).collect();
+    assert_eq!(songs, vec![(0, "Song 1"), (2, "Song 3")]);
+    assert_eq!(library.artists[0].sort_name, "artist");
+    rate_song(base_path.clone(), 0, 3).unwrap();
+
+    // Nothing to do the second time
+    let result = migrate_library(base_path).unwrap();
+    assert_eq!(result.from_version, 6);
+    assert!(result.backup_path.is_none());
+}
+
+// =============================================================================
+// Append-Only Save Tests
+// =============================================================================
+
+#[test]
+fn test_incremental_save_appends_in_place() {
+    let (temp_dir, base_path) = setup_test_library();
+    let song_files = |range: std::ops::RangeInclusive<u32>| -> Vec<FileToSave> {
+        range
+            .map(|i| {
+                create_file_to_save(
+                    create_dummy_audio_file(&temp_dir, &format!("{}.mp3", i)),
+                    &format!("Song {}", i),
+                    "Artist",
+                    "Album",
+                    2020,
+                    i,
+                )
+            })
+            .collect()
+    };
+    save_to_library(base_path.clone(), song_files(1..=2)).unwrap();
+
+    let library_bin = temp_dir
+        .path()
+        .join("jp3")
+        .join("metadata")
+        .join("library.bin");
+    let before = std::fs::read(&library_bin).unwrap();
+    let read_u32 = |at: usize| u32::from_le_bytes(before[at..at + 4].try_into().unwrap()) as usize;
+    let strings = 56..56 + read_u32(52);
+    let songs = read_u32(32)..read_u32(32) + 2 * 32;
+
+    // One more song fits in the free space: same size, existing entries untouched
+    save_to_library(base_path.clone(), song_files(3..=3)).unwrap();
+    let after = std::fs::read(&library_bin).unwrap();
+    assert_eq!(after.len(), before.len());
+    assert_eq!(after[strings.clone()], before[strings]);
+    assert_eq!(after[songs.clone()], before[songs]);
+    assert_ne!(after[..56], before[..56]);
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.songs.len(), 3);
+    assert_eq!(library.songs[2].title, "Song 3");
+
+    // A batch too big for the free space rewrites the file with more room
+    save_to_library(base_path.clone(), song_files(4..=40)).unwrap();
+    assert!(std::fs::read(&library_bin).unwrap().len() > after.len());
+    let library = load_library(base_path, None).unwrap();
+    assert_eq!(library.songs.len(), 40);
+}
+
+// ============================================================================
+// Compaction thresholds
+// ============================================================================
+
+#[test]
+fn test_compaction_thresholds_from_library_settings() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = (1..=5)
+        .map(|n| {
+            let file = create_dummy_audio_file(&temp_dir, &format!("song{}.mp3", n));
+            let artist = if n == 2 { "Artist Two" } else { "Artist One" };
+            let album = if n == 2 { "Album Two" } else { "Album One" };
+            create_file_to_save(file, &format!("Song {}", n), artist, album, 2020, n)
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let stats = get_library_stats(base_path.clone()).unwrap();
+    assert_eq!(stats.dead_string_bytes, 0);
+    assert_eq!(stats.wasted_bytes, 0);
+    assert!(stats.compact_reason.is_none());
+
+    // 20% deleted is not over the default 20% rule, but leaves dead strings
+    delete_songs(base_path.clone(), vec![1]).unwrap();
+    let stats = get_library_stats(base_path.clone()).unwrap();
+    assert!(stats.dead_string_bytes > 0);
+    assert!(stats.wasted_bytes > stats.dead_string_bytes);
+    assert!(!stats.should_compact);
+
+    let thresholds = CompactionThresholds {
+        deleted_percentage: None,
+        dead_string_bytes: Some(stats.dead_string_bytes - 1),
+        wasted_bytes: Some(0),
+    };
+    set_compaction_thresholds(base_path.clone(), thresholds).unwrap();
+    let saved = get_compaction_thresholds(base_path.clone()).unwrap();
+    assert_eq!(saved.deleted_percentage, None);
+
+    // Rules are checked in order; the first one crossed is reported
+    let stats = get_library_stats(base_path.clone()).unwrap();
+    assert!(stats.should_compact);
+    assert_eq!(stats.compact_reason, Some(CompactionRule::DeadStringBytes));
+
+    compact(&base_path, false, false).unwrap();
+    let stats = get_library_stats(base_path).unwrap();
+    assert_eq!(stats.dead_string_bytes, 0);
+    assert_eq!(stats.wasted_bytes, 0);
+    assert!(stats.compact_reason.is_none());
+}
+
+// ============================================================================
+// Backups
+// ============================================================================
+
+#[test]
+fn test_backup_and_restore_library() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = (1..=3)
+        .map(|n| {
+            let file = create_dummy_audio_file(&temp_dir, &format!("song{}.mp3", n));
+            create_file_to_save(file, &format!("Song {}", n), "Artist", "Album", 2020, n)
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    create_playlist(base_path.clone(), "Mix".to_string(), vec![0, 1]).unwrap();
+
+    let backup = backup_library(base_path.clone()).unwrap();
+    assert_eq!(backup.playlist_count, 1);
+    assert!(backup.library_bytes > 0);
+
+    // Compaction backs up on its own before rewriting
+    delete_songs(base_path.clone(), vec![1]).unwrap();
+    create_playlist(base_path.clone(), "Later".to_string(), vec![2]).unwrap();
+    compact(&base_path, false, false).unwrap();
+    let backups = list_backups(base_path.clone()).unwrap();
+    assert_eq!(backups.len(), 2);
+    assert_eq!(backups[0].playlist_count, 2);
+    assert_eq!(load_library(base_path.clone(), None).unwrap().songs.len(), 2);
+
+    let restored = restore_library(base_path.clone(), backup.id.clone()).unwrap();
+    assert_eq!(restored.id, backup.id);
+    assert_eq!(load_library(base_path.clone(), None).unwrap().songs.len(), 3);
+    assert_eq!(load_playlist(base_path.clone(), 1).unwrap().song_ids, vec![0, 1]);
+    assert!(load_playlist(base_path.clone(), 2).is_err());
+
+    // The state replaced by the restore was kept as a third backup
+    assert_eq!(list_backups(base_path.clone()).unwrap().len(), 3);
+    assert!(restore_library(base_path.clone(), "../metadata".to_string()).is_err());
+    assert!(restore_library(base_path, "12345".to_string()).is_err());
+}
+
+#[test]
+fn test_backup_retention() {
+    let (temp_dir, base_path) = setup_test_library();
+    let file = create_dummy_audio_file(&temp_dir, "song.mp3");
+    save_to_library(
+        base_path.clone(),
+        vec![create_file_to_save(file, "Song", "Artist", "Album", 2020, 1)],
+    )
+    .unwrap();
+    assert_eq!(get_backup_retention(base_path.clone()).unwrap(), Some(10));
+    assert!(set_backup_retention(base_path.clone(), Some(0)).is_err());
+    set_backup_retention(base_path.clone(), Some(2)).unwrap();
+
+    // Each backup prunes the oldest beyond the newest two
+    let ids: Vec<_> = (0..3)
+        .map(|_| backup_library(base_path.clone()).unwrap().id)
+        .collect();
+    let kept: Vec<_> = list_backups(base_path.clone())
+        .unwrap()
+        .into_iter()
+        .map(|b| b.id)
+        .collect();
+    assert_eq!(kept, vec![ids[2].clone(), ids[1].clone()]);
+
+    // The backup being restored survives the restore's own backup
+    restore_library(base_path.clone(), ids[1].clone()).unwrap();
+    let kept: Vec<_> = list_backups(base_path.clone())
+        .unwrap()
+        .into_iter()
+        .map(|b| b.id)
+        .collect();
+    assert_eq!(kept.len(), 3);
+    assert!(kept.contains(&ids[1]));
+
+    delete_backup(base_path.clone(), ids[1].clone()).unwrap();
+    assert_eq!(list_backups(base_path.clone()).unwrap().len(), 2);
+    assert!(delete_backup(base_path.clone(), ids[1].clone()).is_err());
+    assert!(delete_backup(base_path.clone(), "../metadata".to_string()).is_err());
+
+    // None keeps every backup
+    set_backup_retention(base_path.clone(), None).unwrap();
+    backup_library(base_path.clone()).unwrap();
+    assert_eq!(list_backups(base_path).unwrap().len(), 3);
+}
+
+// ============================================================================
+// Integrity check
+// ============================================================================
+
+#[test]
+fn test_verify_library_reports_damage() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = (1..=3)
+        .map(|n| {
+            let file = create_dummy_audio_file(&temp_dir, &format!("song{}.mp3", n));
+            create_file_to_save(file, &format!("Song {}", n), "Artist", "Album", 2020, n)
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let report = verify_library(base_path.clone()).unwrap();
+    assert!(!report.has_issues(), "unexpected issues: {:?}", report);
+    assert_eq!(report.version, Some(6));
+
+    // An audio file removed behind the library's back
+    let jp3 = temp_dir.path().join("jp3");
+    let song = &load_library(base_path.clone(), None).unwrap().songs[1];
+    std::fs::remove_file(jp3.join("music").join(&song.path)).unwrap();
+    let report = verify_library(base_path.clone()).unwrap();
+    assert_eq!(report.missing_file_song_ids, vec![1]);
+    assert!(report.checksum_valid);
+
+    // Song 2 pointing at an album that doesn't exist
+    let library_bin = jp3.join("metadata").join("library.bin");
+    let bytes = std::fs::read(&library_bin).unwrap();
+    let song_table_offset = u32::from_le_bytes(bytes[32..36].try_into().unwrap()) as usize;
+    let mut damaged = bytes.clone();
+    let album_id = song_table_offset + 2 * 32 + 8;
+    damaged[album_id..album_id + 4].copy_from_slice(&9u32.to_le_bytes());
+    std::fs::write(&library_bin, &damaged).unwrap();
+    let report = verify_library(base_path.clone()).unwrap();
+    assert!(!report.checksum_valid);
+    assert_eq!(report.bad_references.len(), 1);
+    let bad = &report.bad_references[0];
+    assert_eq!((bad.table, bad.entry_id, bad.field, bad.value), ("song", 2, "albumId", 9));
+
+    // A file cut short stops at the layout check
+    std::fs::write(&library_bin, &bytes[..song_table_offset + 10]).unwrap();
+    let report = verify_library(base_path.clone()).unwrap();
+    assert_eq!(report.layout_errors[0], "song table extends past the end of the file");
+    assert!(report.bad_references.is_empty());
+
+    std::fs::write(&library_bin, b"not a library").unwrap();
+    let report = verify_library(base_path).unwrap();
+    assert!(report.header_error.is_some());
+}
+
+#[test]
+fn test_verify_library_reports_changed_audio() {
+    let (temp_dir, base_path) = setup_test_library();
+    let file = create_dummy_audio_file(&temp_dir, "song.mp3");
+    save_to_library(
+        base_path.clone(),
+        vec![create_file_to_save(file, "Song", "Artist", "Album", 2020, 1)],
+    )
+    .unwrap();
+    assert!(!verify_library(base_path.clone()).unwrap().has_issues());
+
+    let song_path = load_library(base_path.clone(), None).unwrap().songs[0].path.clone();
+    let music_file = temp_dir.path().join("jp3").join("music").join(song_path);
+    std::fs::write(music_file, "damaged audio data").unwrap();
+
+    let report = verify_library(base_path).unwrap();
+    assert_eq!(report.hash_mismatch_song_ids, vec![0]);
+    assert!(report.has_issues());
+}
+
+#[test]
+fn test_backfill_song_hashes() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = (1..=2)
+        .map(|n| {
+            let file = create_dummy_audio_file(&temp_dir, &format!("song{}.mp3", n));
+            create_file_to_save(file, &format!("Song {}", n), "Artist", "Album", 2020, n)
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    // As if saved before hashes were recorded
+    let hashes_path = temp_dir.path().join("jp3").join("metadata").join("song_hashes.json");
+    std::fs::remove_file(&hashes_path).unwrap();
+    assert_eq!(backfill_song_hashes(base_path.clone()).unwrap(), 2);
+    assert_eq!(backfill_song_hashes(base_path.clone()).unwrap(), 0);
+
+    // Backfilled hashes catch a re-import of the same audio
+    let copy = temp_dir.path().join("copy.mp3");
+    std::fs::copy(temp_dir.path().join("song1.mp3"), &copy).unwrap();
+    let result = save_to_library(
+        base_path,
+        vec![create_file_to_save(
+            copy.to_string_lossy().to_string(),
+            "Other Title", "Artist", "Album", 2020, 3,
+        )],
+    )
+    .unwrap();
+    assert_eq!(result.duplicate_song_ids, vec![0]);
+}
+
+#[test]
+fn test_repair_library_salvages_damaged_file() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = (1..=3)
+        .map(|n| {
+            let file = create_dummy_audio_file(&temp_dir, &format!("song{}.mp3", n));
+            create_file_to_save(file, &format!("Song {}", n), "Artist", "Album", 2020, n)
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    create_playlist(base_path.clone(), "Mix".to_string(), vec![2, 0, 1]).unwrap();
+
+    // An audio file no song points at (MPEG frame sync, no tags)
+    let jp3 = temp_dir.path().join("jp3");
+    let orphan = jp3.join("music").join("00").join("099.mp3");
+    std::fs::write(orphan, [0xFF, 0xFB, 0x90, 0x00]).unwrap();
+
+    // Song 1 pointing at an album that doesn't exist
+    let library_bin = jp3.join("metadata").join("library.bin");
+    let mut bytes = std::fs::read(&library_bin).unwrap();
+    let song_table_offset = u32::from_le_bytes(bytes[32..36].try_into().unwrap()) as usize;
+    let album_id = song_table_offset + 32 + 8;
+    bytes[album_id..album_id + 4].copy_from_slice(&9u32.to_le_bytes());
+    std::fs::write(&library_bin, &bytes).unwrap();
+    assert!(load_library(base_path.clone(), None).is_err());
+
+    let result = repair_library(base_path.clone()).unwrap();
+    assert!(result.backup_id.is_some());
+    assert_eq!(result.songs_kept, 2);
+    assert_eq!(result.songs_dropped, 1);
+    assert_eq!(result.songs_relinked, 1);
+    assert_eq!(result.playlists_updated, 1);
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    let titles: Vec<_> = library.songs.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(titles, vec!["Song 1", "Song 3", "00/099.mp3"]);
+    assert_eq!(load_playlist(base_path.clone(), 1).unwrap().song_ids, vec![1, 0]);
+    assert!(!verify_library(base_path.clone()).unwrap().has_issues());
+
+    // With the header gone only the audio files are left to go on
+    std::fs::write(&library_bin, b"garbage").unwrap();
+    let result = repair_library(base_path.clone()).unwrap();
+    assert_eq!(result.songs_kept, 0);
+    assert_eq!(result.songs_relinked, 1);
+    assert_eq!(load_library(base_path, None).unwrap().songs.len(), 1);
+}
+
+#[test]
+fn test_scan_orphaned_files() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = (1..=2)
+        .map(|n| {
+            let file = create_dummy_audio_file(&temp_dir, &format!("song{}.mp3", n));
+            create_file_to_save(file, &format!("Song {}", n), "Artist", "Album", 2020, n)
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    // Left behind by saves that never wrote library.bin (MPEG frame sync, no tags)
+    let bucket = temp_dir.path().join("jp3").join("music").join("00");
+    std::fs::write(bucket.join("096.mp3"), [0xFF, 0xFB, 0x90, 0x01, 0x02]).unwrap();
+    std::fs::write(bucket.join("097.mp3"), [0xFF, 0xFB, 0x90, 0x00]).unwrap();
+    std::fs::write(bucket.join("098.mp3"), [0xFF, 0xFB, 0x90, 0x00]).unwrap();
+    std::fs::write(bucket.join("099.mp3.part"), "half a copy").unwrap();
+
+    // Reporting changes nothing
+    let result = scan_orphaned_files(base_path.clone(), None).unwrap();
+    let paths: Vec<_> = result.files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, vec!["00/096.mp3", "00/097.mp3", "00/098.mp3", "00/099.mp3.part"]);
+    assert_eq!(result.wasted_bytes, 5 + 4 + 4 + 11);
+    assert!(result.files[0].is_audio);
+    assert!(!result.files[3].is_audio);
+    assert_eq!(load_library(base_path.clone(), None).unwrap().songs.len(), 2);
+
+    // Audio is adopted where it lies, once per distinct audio
+    let result = scan_orphaned_files(base_path.clone(), Some(OrphanAction::Adopt)).unwrap();
+    assert_eq!(result.songs_adopted, 2);
+    assert_eq!(result.duplicates_skipped, 1);
+    assert_eq!(result.song_ids, vec![2, 3]);
+    let library = load_library(base_path.clone(), None).unwrap();
+    let adopted: Vec<_> = library.songs[2..].iter().map(|s| s.path.as_str()).collect();
+    assert_eq!(adopted, vec!["00/096.mp3", "00/097.mp3"]);
+    assert_eq!(library.songs[3].title, "00/097.mp3");
+
+    // The duplicate and the partial copy are what's left to delete
+    let result = scan_orphaned_files(base_path.clone(), Some(OrphanAction::Delete)).unwrap();
+    assert_eq!(result.files_deleted, 2);
+    assert_eq!(result.bytes_freed, 4 + 11);
+    assert!(!bucket.join("098.mp3").exists());
+    assert!(bucket.join("097.mp3").exists());
+    assert!(scan_orphaned_files(base_path, None).unwrap().files.is_empty());
+}
+
+#[test]
+fn test_relink_moved_song() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = (1..=2)
+        .map(|n| {
+            let file = create_dummy_audio_file(&temp_dir, &format!("song{}.mp3", n));
+            create_file_to_save(file, &format!("Song {}", n), "Artist", "Album", 2020, n)
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    assert!(scan_missing_files(base_path.clone()).unwrap().is_empty());
+
+    // Moved into another bucket by hand
+    let music = temp_dir.path().join("jp3").join("music");
+    let old_path = load_library(base_path.clone(), None).unwrap().songs[0].path.clone();
+    std::fs::create_dir(music.join("07")).unwrap();
+    std::fs::rename(music.join(&old_path), music.join("07").join("moved.mp3")).unwrap();
+    let missing = scan_missing_files(base_path.clone()).unwrap();
+    assert_eq!(missing.iter().map(|s| s.id).collect::<Vec<_>>(), vec![0]);
+
+    let stored = relink_song(base_path.clone(), 0, "07/moved.mp3".to_string()).unwrap();
+    assert_eq!(stored, "07/moved.mp3");
+    assert!(scan_missing_files(base_path.clone()).unwrap().is_empty());
+    assert!(!verify_library(base_path.clone()).unwrap().has_issues());
+
+    // A file another song owns, or one that isn't there, is refused
+    let other = load_library(base_path.clone(), None).unwrap().songs[1].path.clone();
+    assert!(relink_song(base_path.clone(), 0, other).is_err());
+    assert!(relink_song(base_path.clone(), 0, "07/nothing.mp3".to_string()).is_err());
+
+    // Outside jp3/music the file is adopted in place
+    let outside = temp_dir.path().join("elsewhere.mp3");
+    std::fs::rename(music.join("07").join("moved.mp3"), &outside).unwrap();
+    let stored = relink_song(base_path.clone(), 0, outside.to_string_lossy().to_string()).unwrap();
+    assert_eq!(std::path::Path::new(&stored), std::fs::canonicalize(&outside).unwrap());
+    assert!(scan_missing_files(base_path).unwrap().is_empty());
+}
+
+// ============================================================================
+// Selection export
+// ============================================================================
+
+#[test]
+fn test_export_selection() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = vec![
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "a1.mp3"), "A1", "Artist", "First", 2020, 1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "a2.mp3"), "A2", "Artist", "First", 2020, 2,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "b1.mp3"), "B1", "Other", "Second", 2021, 1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "c1.mp3"), "C1", "Third", "Last", 2022, 1,
+        ),
+    ];
+    save_to_library(base_path.clone(), files).unwrap();
+    create_playlist(base_path.clone(), "Mix".to_string(), vec![1, 0]).unwrap();
+
+    // Only the first album has a cover, and no thumbnails yet
+    let albums_dir = temp_dir.path().join("jp3").join("assets").join("albums");
+    std::fs::create_dir_all(&albums_dir).unwrap();
+    let cover = format!("{}.jpg", cover_filename("Artist", "First"));
+    image::RgbImage::from_pixel(600, 600, image::Rgb([200, 30, 30]))
+        .save_with_format(albums_dir.join(&cover), image::ImageFormat::Jpeg)
+        .unwrap();
+
+    let dest_dir = tempfile::TempDir::new().unwrap();
+    let dest_path = dest_dir.path().to_string_lossy().to_string();
+    let result =
+        export_selection(base_path.clone(), vec![3, 99], vec![1], dest_path.clone(), None, None)
+            .unwrap();
+    assert_eq!(result.songs_exported, 3);
+    assert_eq!(result.songs_skipped, 1);
+    assert_eq!(result.playlists_exported, 1);
+    assert_eq!(result.covers_exported, 1);
+
+    // Renumbered in library order, with the playlist remapped
+    let exported = load_library(dest_path.clone(), None).unwrap();
+    let titles: Vec<_> = exported.songs.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(titles, vec!["A1", "A2", "C1"]);
+    assert_eq!(exported.artists.len(), 2);
+    assert_eq!(exported.albums.len(), 2);
+    assert_eq!(exported.songs[2].path, "00/003.mp3");
+    assert_eq!(load_playlist(dest_path.clone(), 1).unwrap().song_ids, vec![1, 0]);
+    let dest_jp3 = dest_dir.path().join("jp3");
+    assert!(dest_jp3.join("music").join("00").join("003.mp3").exists());
+    let dest_albums = dest_jp3.join("assets").join("albums");
+    assert!(dest_albums.join(&cover).exists());
+    let device_thumb = thumbnail_path(&dest_albums, "Artist", "First", ThumbnailSize::Device);
+    assert_eq!(image::open(device_thumb).unwrap().width(), 240);
+    assert!(!verify_library(dest_path.clone()).unwrap().has_issues());
+
+    // The source library is untouched, and an existing export isn't overwritten
+    assert_eq!(load_library(base_path.clone(), None).unwrap().songs.len(), 4);
+    assert!(export_selection(base_path, vec![0], vec![], dest_path, None, None).is_err());
+}
+
+#[test]
+fn test_select_songs_for_size() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = [("a1", "A", 1), ("a2", "A", 2), ("b1", "B", 1), ("c1", "C", 1)]
+        .iter()
+        .map(|&(name, album, track)| {
+            let file = create_dummy_audio_file(&temp_dir, &format!("{}.mp3", name));
+            create_file_to_save(file, name, "Artist", album, 2020, track)
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    // Audio sizes: a1 400, a2 400, b1 500, c1 100
+    let music = temp_dir.path().join("jp3").join("music");
+    let library = load_library(base_path.clone(), None).unwrap();
+    for (song, size) in library.songs.iter().zip([400, 400, 500, 100]) {
+        std::fs::write(music.join(&song.path), vec![0u8; size]).unwrap();
+    }
+
+    // Greedy in library order: b1 doesn't fit, c1 still does
+    let selection = select_songs_for_size(base_path.clone(), 900, None).unwrap();
+    assert_eq!(selection.song_ids, vec![0, 1, 3]);
+    assert_eq!(selection.excluded_song_ids, vec![2]);
+    assert_eq!(selection.selected_bytes, 900);
+    assert_eq!(selection.excluded_bytes, 500);
+
+    toggle_favorite(base_path.clone(), 2).unwrap();
+    let favorites_first = SelectionPreferences {
+        favorites_first: true,
+        ..Default::default()
+    };
+    let selection = select_songs_for_size(base_path.clone(), 900, Some(favorites_first)).unwrap();
+    assert_eq!(selection.song_ids, vec![2, 0]);
+
+    // Album A (800 bytes) is skipped whole, making room for B
+    rate_song(base_path.clone(), 3, 5).unwrap();
+    rate_song(base_path.clone(), 1, 3).unwrap();
+    let whole_albums = SelectionPreferences {
+        priority: SelectionPriority::HighestRated,
+        whole_albums: true,
+        ..Default::default()
+    };
+    let selection =
+        select_songs_for_size(base_path.clone(), 850, Some(whole_albums.clone())).unwrap();
+    assert_eq!(selection.song_ids, vec![3, 2]);
+    assert_eq!(selection.excluded_song_ids, vec![0, 1]);
+
+    // Songs whose file is missing are never picked
+    std::fs::remove_file(music.join(&library.songs[3].path)).unwrap();
+    let selection = select_songs_for_size(base_path, 850, Some(whole_albums)).unwrap();
+    assert_eq!(selection.song_ids, vec![0, 1]);
+    assert_eq!(selection.excluded_song_ids, vec![3, 2]);
+}
+
+#[test]
+fn test_select_songs_most_played() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = [("a", "X", 5), ("b", "Y", 9), ("c", "X", 0), ("d", "X", 5)]
+        .iter()
+        .enumerate()
+        .map(|(i, &(name, album, play_count))| {
+            let file = create_dummy_audio_file(&temp_dir, &format!("{}.mp3", name));
+            let mut file = create_file_to_save(file, name, "Artist", album, 2020, i as u32 + 1);
+            file.metadata.play_count = Some(play_count);
+            file
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let music = temp_dir.path().join("jp3").join("music");
+    for song in load_library(base_path.clone(), None).unwrap().songs {
+        std::fs::write(music.join(&song.path), vec![0u8; 100]).unwrap();
+    }
+
+    // Imported play counts rank the songs; ties keep library order
+    let most_played = SelectionPreferences {
+        priority: SelectionPriority::MostPlayed,
+        ..Default::default()
+    };
+    let selection = select_songs_for_size(base_path.clone(), 300, Some(most_played)).unwrap();
+    assert_eq!(selection.song_ids, vec![1, 0, 3]);
+    assert_eq!(selection.excluded_song_ids, vec![2]);
+
+    // Album X's 10 plays in total outrank album Y's single
+    let whole_albums = SelectionPreferences {
+        priority: SelectionPriority::MostPlayed,
+        whole_albums: true,
+        ..Default::default()
+    };
+    let selection = select_songs_for_size(base_path, 300, Some(whole_albums)).unwrap();
+    assert_eq!(selection.song_ids, vec![0, 2, 3]);
+    assert_eq!(selection.excluded_song_ids, vec![1]);
+}
+
+#[test]
+fn test_export_selection_transcodes_with_profile() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = vec![
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "a.flac"), "Lossless", "Artist", "Album", 2020, 1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "b.mp3"), "Lossy", "Artist", "Album", 2020, 2,
+        ),
+    ];
+    save_to_library(base_path.clone(), files).unwrap();
+
+    // A transcode left in the cache by an earlier export
+    let music = temp_dir.path().join("jp3").join("music");
+    let flac_hash = hash_file(&music.join("00").join("001.flac")).unwrap();
+    let profile = EncodeProfile::default();
+    let cached = cached_transcode_path(temp_dir.path(), &flac_hash, &profile);
+    std::fs::create_dir_all(cached.parent().unwrap()).unwrap();
+    std::fs::write(&cached, b"mp3 from cache").unwrap();
+
+    let dest_dir = tempfile::TempDir::new().unwrap();
+    let dest_path = dest_dir.path().to_string_lossy().to_string();
+    let result = export_selection(
+        base_path.clone(),
+        vec![0, 1],
+        vec![],
+        dest_path.clone(),
+        Some(profile),
+        None,
+    )
+    .unwrap();
+    assert_eq!(result.songs_exported, 2);
+    assert_eq!(result.songs_transcoded, 1);
+
+    // The card gets MP3s; the library keeps its original
+    let exported = load_library(dest_path, None).unwrap();
+    let paths: Vec<_> = exported.songs.iter().map(|s| s.path.as_str()).collect();
+    assert_eq!(paths, vec!["00/001.mp3", "00/002.mp3"]);
+    let dest_music = dest_dir.path().join("jp3").join("music").join("00");
+    assert_eq!(std::fs::read(dest_music.join("001.mp3")).unwrap(), b"mp3 from cache");
+    assert_eq!(load_library(base_path, None).unwrap().songs[0].path, "00/001.flac");
+}
+
+#[test]
+fn test_resume_interrupted_sync() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = (1..=3)
+        .map(|i| {
+            let path = create_dummy_audio_file(&temp_dir, &format!("s{}.mp3", i));
+            create_file_to_save(path, &format!("S{}", i), "Artist", "Album", 2020, i)
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    let music = temp_dir.path().join("jp3").join("music").join("00");
+
+    // An export that failed before copying anything leaves nothing behind
+    let dest_dir = tempfile::TempDir::new().unwrap();
+    let dest_path = dest_dir.path().to_string_lossy().to_string();
+    let result =
+        export_selection(base_path.clone(), vec![0], vec![7], dest_path.clone(), None, None);
+    assert!(result.is_err());
+    assert_eq!(get_pending_sync(base_path.clone()).unwrap(), None);
+    assert!(!dest_dir.path().join("jp3").exists());
+
+    // The card was pulled after two songs; the second was cut short
+    initialize_library(dest_path.clone()).unwrap();
+    let dest_music = dest_dir.path().join("jp3").join("music").join("00");
+    std::fs::copy(music.join("001.mp3"), dest_music.join("001.mp3")).unwrap();
+    std::fs::write(dest_music.join("002.mp3"), b"fake au").unwrap();
+    let mut plan = PendingSync {
+        song_ids: vec![0, 1, 2],
+        playlist_ids: vec![],
+        dest_path: dest_path.clone(),
+        profile: None,
+        library_version: None,
+        started_at: 1,
+        copied: Default::default(),
+    };
+    for name in ["001.mp3", "002.mp3"] {
+        let hash = hash_file(&music.join(name)).unwrap();
+        plan.copied.insert(format!("00/{}", name), hash);
+    }
+    save_pending_sync(temp_dir.path(), &plan).unwrap();
+    assert_eq!(get_pending_sync(base_path.clone()).unwrap(), Some(plan));
+
+    // No new export until the pending one is dealt with
+    let other_dir = tempfile::TempDir::new().unwrap();
+    let other_path = other_dir.path().to_string_lossy().to_string();
+    assert!(export_selection(base_path.clone(), vec![0], vec![], other_path, None, None).is_err());
+    assert!(get_sync_history(base_path.clone(), None).unwrap().is_empty());
+
+    let result = resume_sync(base_path.clone()).unwrap();
+    assert_eq!(result.songs_exported, 3);
+    assert_eq!(result.songs_verified, 1);
+    for name in ["001.mp3", "002.mp3", "003.mp3"] {
+        assert_eq!(
+            std::fs::read(dest_music.join(name)).unwrap(),
+            std::fs::read(music.join(name)).unwrap()
+        );
+    }
+    assert_eq!(get_pending_sync(base_path.clone()).unwrap(), None);
+    assert!(!verify_library(dest_path.clone()).unwrap().has_issues());
+    assert!(resume_sync(base_path.clone()).is_err());
+
+    // The finished export is in the sync history; the failed one is not
+    let device_id = read_device_id(dest_dir.path()).unwrap();
+    assert!(device_id.is_some());
+    let history = get_sync_history(base_path.clone(), None).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].device_id, device_id);
+    assert!(history[0].resumed);
+    assert_eq!(history[0].songs_exported, 3);
+    assert_eq!(history[0].files_copied, 2);
+    assert!(history[0].bytes_copied > 0);
+    assert!(history[0].verified);
+
+    // Discarding removes the partial export along with the plan
+    let dest_path_before_discard = dest_path.clone();
+    assert!(!discard_sync(base_path.clone()).unwrap());
+    let plan = PendingSync {
+        song_ids: vec![0],
+        playlist_ids: vec![],
+        dest_path,
+        profile: None,
+        library_version: None,
+        started_at: 1,
+        copied: [("00/001.mp3".to_string(), "hash".to_string())].into(),
+    };
+    save_pending_sync(temp_dir.path(), &plan).unwrap();
+    assert!(discard_sync(base_path.clone()).unwrap());
+    assert!(!dest_dir.path().join("jp3").exists());
+    assert_eq!(get_pending_sync(base_path.clone()).unwrap(), None);
+
+    // History can be narrowed to one device, most recent first
+    let other_path = other_dir.path().to_string_lossy().to_string();
+    export_selection(base_path.clone(), vec![0], vec![], other_path.clone(), None, None).unwrap();
+    let history = get_sync_history(base_path.clone(), None).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].dest_path, other_path);
+    assert!(!history[0].resumed);
+    assert_eq!(history[0].files_copied, 1);
+    let history = get_sync_history(base_path, device_id).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].dest_path, dest_path_before_discard);
+}
+
+#[test]
+fn test_plan_device_sync() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = (1..=4)
+        .map(|i| {
+            let path = create_dummy_audio_file(&temp_dir, &format!("s{}.mp3", i));
+            create_file_to_save(path, &format!("S{}", i), "Artist", "Album", 2020, i)
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    delete_songs(base_path.clone(), vec![3]).unwrap();
+    let music = temp_dir.path().join("jp3").join("music").join("00");
+
+    // A fresh export: everything is new and nothing is written
+    let dest_dir = tempfile::TempDir::new().unwrap();
+    let dest_path = dest_dir.path().to_string_lossy().to_string();
+    let report =
+        plan_device_sync(base_path.clone(), vec![0, 1, 3, 9], vec![], dest_path.clone(), None, None)
+            .unwrap();
+    assert!(!report.resumes_pending);
+    assert_eq!(report.files_to_copy, 2);
+    assert_eq!(report.files_skipped, 2);
+    assert!(report.bytes_to_copy > 0);
+    let excluded: Vec<_> = report
+        .files
+        .iter()
+        .filter(|f| f.reason == SyncReason::Excluded)
+        .map(|f| f.song_id)
+        .collect();
+    assert_eq!(excluded, vec![Some(3), Some(9)]);
+    assert!(!dest_dir.path().join("jp3").exists());
+
+    // Interrupted after one good copy and one cut short, with a stray file
+    initialize_library(dest_path.clone()).unwrap();
+    let dest_music = dest_dir.path().join("jp3").join("music").join("00");
+    std::fs::copy(music.join("001.mp3"), dest_music.join("001.mp3")).unwrap();
+    std::fs::write(dest_music.join("002.mp3"), b"fake au").unwrap();
+    std::fs::write(dest_music.join("099.mp3"), b"stray").unwrap();
+    let mut plan = PendingSync {
+        song_ids: vec![0, 1, 2],
+        playlist_ids: vec![],
+        dest_path: dest_path.clone(),
+        profile: None,
+        library_version: None,
+        started_at: 1,
+        copied: Default::default(),
+    };
+    for name in ["001.mp3", "002.mp3"] {
+        let hash = hash_file(&music.join(name)).unwrap();
+        plan.copied.insert(format!("00/{}", name), hash);
+    }
+    save_pending_sync(temp_dir.path(), &plan).unwrap();
+
+    let report = plan_device_sync(base_path.clone(), vec![], vec![], dest_path.clone(), None, None)
+        .unwrap();
+    assert!(report.resumes_pending);
+    let actions: Vec<_> = report
+        .files
+        .iter()
+        .map(|f| (f.action, f.reason, f.path.as_str()))
+        .collect();
+    assert_eq!(
+        actions,
+        vec![
+            (SyncAction::Skip, SyncReason::AlreadyCopied, "music/00/001.mp3"),
+            (SyncAction::Copy, SyncReason::ChangedHash, "music/00/002.mp3"),
+            (SyncAction::Copy, SyncReason::New, "music/00/003.mp3"),
+            (SyncAction::Delete, SyncReason::OrphanedOnDevice, "music/00/099.mp3"),
+        ]
+    );
+    assert_eq!(report.bytes_to_free, 5);
+    assert_eq!(std::fs::read(dest_music.join("002.mp3")).unwrap(), b"fake au");
+
+    // Only a pending export's own drive can be planned
+    let other_dir = tempfile::TempDir::new().unwrap();
+    let other_path = other_dir.path().to_string_lossy().to_string();
+    assert!(plan_device_sync(base_path.clone(), vec![0], vec![], other_path, None, None).is_err());
+
+    let text_path = temp_dir.path().join("plan.txt");
+    export_sync_plan(report.clone(), text_path.to_string_lossy().to_string()).unwrap();
+    let text = std::fs::read_to_string(&text_path).unwrap();
+    assert!(text.contains("Resumes an interrupted export"));
+    assert!(text.contains("Copy 2 files"));
+    assert!(text.contains("orphaned on device"));
+
+    // Resuming does what was planned
+    let result = resume_sync(base_path).unwrap();
+    assert_eq!(result.songs_verified, 1);
+    assert_eq!(result.bytes_copied, report.bytes_to_copy);
+    assert!(!dest_music.join("099.mp3").exists());
+}
+
+#[test]
+fn test_transcode_cache_stats_and_prune() {
+    let (temp_dir, base_path) = setup_test_library();
+    assert_eq!(get_transcode_cache_stats(base_path.clone()).unwrap().file_count, 0);
+
+    // Transcodes last used 40 days, 2 days and 0 days ago
+    let profile = EncodeProfile::default();
+    let now = std::time::SystemTime::now();
+    let day = std::time::Duration::from_secs(24 * 60 * 60);
+    for (hash, size, age_days) in [("old", 100, 40), ("mid", 200, 2), ("new", 300, 0)] {
+        let path = cached_transcode_path(temp_dir.path(), hash, &profile);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, vec![0u8; size]).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(now - day * age_days).unwrap();
+    }
+
+    let stats = get_transcode_cache_stats(base_path.clone()).unwrap();
+    assert_eq!(stats.file_count, 3);
+    assert_eq!(stats.total_bytes, 600);
+    let forty_days_ago = now - day * 40;
+    let forty_days_ago = forty_days_ago.duration_since(std::time::UNIX_EPOCH).unwrap();
+    assert_eq!(stats.oldest_used_at, Some(forty_days_ago.as_secs()));
+
+    assert!(prune_transcode_cache(base_path.clone(), None, None).is_err());
+
+    let result = prune_transcode_cache(base_path.clone(), None, Some(30)).unwrap();
+    assert_eq!((result.files_removed, result.bytes_freed), (1, 100));
+
+    // Least recently used goes first
+    let result = prune_transcode_cache(base_path.clone(), Some(300), None).unwrap();
+    assert_eq!((result.files_removed, result.bytes_freed), (1, 200));
+    assert!(cached_transcode_path(temp_dir.path(), "new", &profile).exists());
+    assert_eq!(get_transcode_cache_stats(base_path).unwrap().total_bytes, 300);
+}
+
+// ============================================================================
+// Editing albums
+// ============================================================================
+
+#[test]
+fn test_edit_album_name_and_year_in_place() {
+    let (temp_dir, base_path) = setup_test_library();
+    let mut guest_track = create_file_to_save(
+        create_dummy_audio_file(&temp_dir, "c.mp3"), "Feature", "Guest", "Debut", 2010, 3,
+    );
+    guest_track.metadata.album_artist = Some("Artist".to_string());
+    let files = vec![
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "a.mp3"), "One", "Artist", "Debut", 2010, 1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "b.mp3"), "Two", "Artist", "Debut", 2010, 2,
+        ),
+        guest_track,
+    ];
+    save_to_library(base_path.clone(), files).unwrap();
+    let library_bin = temp_dir.path().join("jp3").join("metadata").join("library.bin");
+    let size_before = std::fs::metadata(&library_bin).unwrap().len();
+
+    // Name and year only: the album entry is patched, no song is rewritten
+    let result =
+        edit_album(base_path.clone(), 0, "Debut (Remastered)".to_string(), None, Some(2012))
+            .unwrap();
+    assert_eq!(result.songs_updated, 3);
+    assert_eq!(result.songs_rewritten, 0);
+    assert!(!result.artist_created);
+    assert_eq!(result.old_name, "Debut");
+    assert_eq!(std::fs::metadata(&library_bin).unwrap().len(), size_before);
+    assert!(!verify_library(base_path.clone()).unwrap().has_issues());
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.albums.len(), 1);
+    assert_eq!(library.albums[0].name, "Debut (Remastered)");
+    assert_eq!(library.albums[0].year, 2012);
+    assert!(library
+        .songs
+        .iter()
+        .all(|s| s.album_name == "Debut (Remastered)" && s.year == 2012));
+
+    // A new album artist rewrites only the songs credited to the old one
+    let result =
+        edit_album(base_path.clone(), 0, "Debut".to_string(), Some("New".to_string()), None)
+            .unwrap();
+    assert!(result.artist_created);
+    assert_eq!(result.songs_rewritten, 2);
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.albums[0].artist_name, "New");
+    assert_eq!(library.albums[0].year, 2012);
+    let credits: Vec<_> = library.songs.iter().map(|s| s.artist_name.as_str()).collect();
+    assert_eq!(credits, vec!["New", "New", "Guest"]);
+    assert!(!verify_library(base_path.clone()).unwrap().has_issues());
+
+    assert!(edit_album(base_path, 9, "Missing".to_string(), None, None).is_err());
+}
+
+// ============================================================================
+// Merging albums
+// ============================================================================
+
+#[test]
+fn test_merge_albums() {
+    let (temp_dir, base_path) = setup_test_library();
+    let files = vec![
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "a1.mp3"), "A1", "Artist", "Album", 2020, 1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "d1.mp3"), "D1", "Artist", "Album (Deluxe)", 2020, 2,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "r1.mp3"), "R1", "Artist", "Album (Remaster)", 2020,
+            3,
+        ),
+    ];
+    save_to_library(base_path.clone(), files).unwrap();
+
+    // The target has no cover; both sources do
+    let albums_dir = temp_dir.path().join("jp3").join("assets").join("albums");
+    std::fs::create_dir_all(&albums_dir).unwrap();
+    let cover = |album: &str| albums_dir.join(format!("{}.jpg", cover_filename("Artist", album)));
+    std::fs::write(cover("Album (Deluxe)"), b"deluxe").unwrap();
+    std::fs::write(cover("Album (Remaster)"), b"remaster").unwrap();
+
+    let result = merge_albums(base_path.clone(), 0, vec![1, 2, 1]).unwrap();
+    assert_eq!(result.songs_moved, 2);
+    assert_eq!(result.albums_merged, 2);
+    assert!(result.cover_adopted);
+    assert_eq!(result.covers_removed, 1);
+
+    assert_eq!(std::fs::read(cover("Album")).unwrap(), b"deluxe");
+    assert!(!cover("Album (Deluxe)").exists());
+    assert!(!cover("Album (Remaster)").exists());
+
+    // Song IDs are unchanged; every song is now on the target album
+    let library = load_library(base_path.clone(), None).unwrap();
+    let ids: Vec<u32> = library.songs.iter().map(|s| s.id).collect();
+    assert_eq!(ids, vec![0, 1, 2]);
+    assert!(library.songs.iter().all(|s| s.album_id == 0 && s.album_name == "Album"));
+
+    assert!(merge_albums(base_path.clone(), 0, vec![0]).is_err());
+    assert!(merge_albums(base_path, 0, vec![9]).is_err());
+}
+
+// ============================================================================
+// Song file info
+// ============================================================================
+
+#[test]
+fn test_get_song_file_info() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    // 50 CBR frames: MPEG-1 Layer III, 128 kbps, 44.1 kHz, joint stereo (417 bytes each)
+    let mut frame = vec![0u8; 417];
+    frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x64]);
+    let source = temp_dir.path().join("cbr.mp3");
+    std::fs::write(&source, frame.repeat(50)).unwrap();
+    let files = vec![create_file_to_save(
+        source.to_string_lossy().to_string(),
+        "Song",
+        "Artist",
+        "Album",
+        2020,
+        1,
+    )];
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let info = get_song_file_info(base_path.clone(), 0).unwrap();
+    assert_eq!(info.container, Some(AudioFormat::Mp3));
+    assert_eq!(info.stream.codec.as_deref(), Some("mp3"));
+    assert_eq!(info.stream.bitrate_kbps, Some(128));
+    assert!(!info.stream.variable_bitrate);
+    assert_eq!(info.stream.sample_rate, Some(44100));
+    assert_eq!(info.stream.channels, Some(2));
+    assert_eq!(info.stream.duration_secs, Some(1)); // 20850 bytes at 128 kbps
+
+    let stored = temp_dir.path().join("jp3").join("music").join(&info.path);
+    assert_eq!(info.size_bytes, std::fs::metadata(&stored).unwrap().len());
+    assert_eq!(info.sha256, hash_file(&stored).unwrap());
+    assert_eq!(info.hash_matches_library, Some(true));
+
+    // A file changed after saving no longer matches its recorded hash
+    let mut bytes = std::fs::read(&stored).unwrap();
+    bytes.extend_from_slice(&frame);
+    std::fs::write(&stored, bytes).unwrap();
+    let info = get_song_file_info(base_path.clone(), 0).unwrap();
+    assert_eq!(info.hash_matches_library, Some(false));
+
+    delete_songs(base_path.clone(), vec![0]).unwrap();
+    assert!(get_song_file_info(base_path.clone(), 0).is_err());
+    assert!(get_song_file_info(base_path, 5).is_err());
+}
+
+// ============================================================================
+// Library report
+// ============================================================================
+
+#[test]
+fn test_export_library_report() {
+    let (temp_dir, base_path) = setup_test_library();
+    let mut compilation_track = create_file_to_save(
+        create_dummy_audio_file(&temp_dir, "c1.mp3"), "Guest Song", "Guest", "Hits", 2015, 1,
+    );
+    compilation_track.metadata.album_artist = Some("Artist".to_string());
+    let files = vec![
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "b2.mp3"), "Second", "Artist", "Debut", 2010, 2,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "b1.mp3"), "Rock & <Roll>", "Artist", "Debut", 2010,
+            1,
+        ),
+        compilation_track,
+    ];
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let html_path = temp_dir.path().join("report.html").to_string_lossy().to_string();
+    let result = export_library_report(base_path.clone(), html_path.clone(), ReportFormat::Html)
+        .unwrap();
+    assert_eq!((result.artist_count, result.album_count, result.song_count), (1, 2, 3));
+    let html = std::fs::read_to_string(&html_path).unwrap();
+    assert!(html.contains("1 artist · 2 albums · 3 songs · 9:00"));
+    assert!(html.contains("<h2>Artist</h2>"));
+    assert!(html.contains("Rock &amp; &lt;Roll&gt;"));
+    // Older album first, tracks in order, the guest credited on the compilation
+    let debut = html.find("Debut").unwrap();
+    assert!(debut < html.find("Hits").unwrap());
+    assert!(html.find("Rock &amp;").unwrap() < html.find("Second").unwrap());
+    assert!(html.contains("<td class=\"artist\">Guest</td>"));
+
+    let md_path = temp_dir.path().join("report.md").to_string_lossy().to_string();
+    export_library_report(base_path.clone(), md_path.clone(), ReportFormat::Markdown).unwrap();
+    let markdown = std::fs::read_to_string(&md_path).unwrap();
+    assert!(markdown.starts_with("# JP3 Library\n"));
+    assert!(markdown.contains("### Debut\n\n_2010 · 2 tracks · 6:00_"));
+    assert!(markdown.contains("| 1 | Rock & \\<Roll\\> | 3:00 |"));
+    assert!(markdown.contains("| 1 | Guest Song — Guest | 3:00 |"));
+
+    let missing_dir = temp_dir.path().join("missing").join("report.md");
+    let missing_dir = missing_dir.to_string_lossy().to_string();
+    assert!(export_library_report(base_path, missing_dir, ReportFormat::Markdown).is_err());
+}
+
+// ============================================================================
+// Album and artist details
+// ============================================================================
+
+#[test]
+fn test_get_album_and_artist_details() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    let mut files = vec![
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "b.mp3"),
+            "Second Track",
+            "Band",
+            "Later",
+            2010,
+            2,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "a.mp3"),
+            "First Track",
+            "Band",
+            "Later",
+            2010,
+            1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "c.mp3"),
+            "Debut",
+            "Band",
+            "Early",
+            2005,
+            1,
+        ),
+    ];
+    let mut guest = create_file_to_save(
+        create_dummy_audio_file(&temp_dir, "d.mp3"),
+        "Feature",
+        "Guest",
+        "Later",
+        2010,
+        3,
+    );
+    guest.metadata.album_artist = Some("Band".to_string());
+    files.push(guest);
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    let album_id = |name: &str| library.albums.iter().find(|a| a.name == name).unwrap().id;
+    let artist_id = |name: &str| library.artists.iter().find(|a| a.name == name).unwrap().id;
+
+    // Only the early album has a cover
+    let albums_dir = temp_dir.path().join("jp3").join("assets").join("albums");
+    std::fs::write(albums_dir.join(format!("{}.jpg", cover_filename("Band", "Early"))), b"jpg")
+        .unwrap();
+
+    let later = get_album(base_path.clone(), album_id("Later")).unwrap();
+    assert_eq!(later.album.year, 2010);
+    let titles: Vec<_> = later.songs.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(titles, vec!["First Track", "Second Track", "Feature"]);
+    assert_eq!(later.total_duration_sec, 540);
+    let music_dir = temp_dir.path().join("jp3").join("music");
+    let size: u64 = later
+        .songs
+        .iter()
+        .map(|s| std::fs::metadata(music_dir.join(&s.path)).unwrap().len())
+        .sum();
+    assert_eq!(later.size_bytes, size);
+    assert_eq!(later.cover_path, None);
+    assert!(get_album(base_path.clone(), album_id("Early"))
+        .unwrap()
+        .cover_path
+        .is_some());
+
+    // The artist page includes guests on their albums, oldest album first
+    let band = get_artist(base_path.clone(), artist_id("Band")).unwrap();
+    let albums: Vec<_> = band.albums.iter().map(|a| a.name.as_str()).collect();
+    assert_eq!(albums, vec!["Early", "Later"]);
+    let titles: Vec<_> = band.songs.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(titles, vec!["Debut", "First Track", "Second Track", "Feature"]);
+    assert_eq!((band.first_year, band.last_year), (Some(2005), Some(2010)));
+    assert_eq!(band.total_duration_sec, 720);
+    assert_eq!(band.cover_path, None);
+
+    let guest = get_artist(base_path.clone(), artist_id("Guest")).unwrap();
+    assert!(guest.albums.is_empty());
+    assert_eq!(guest.songs.len(), 1);
+
+    assert!(get_album(base_path.clone(), 99).is_err());
+    assert!(get_artist(base_path, 99).is_err());
+}
+
+#[test]
+fn test_save_reports_missing_sources() {
+    let (temp_dir, base_path) = setup_test_library();
+    let gone = temp_dir.path().join("gone.mp3").to_string_lossy().to_string();
+    let files = vec![
+        create_file_to_save(gone, "Gone", "Artist", "Album", 2004, 1),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "here.mp3"),
+            "Here",
+            "Artist",
+            "Album",
+            2004,
+            2,
+        ),
+    ];
+
+    let result = save_to_library(base_path, files).unwrap();
+    assert_eq!(result.files_saved, 1);
+    assert_eq!(result.missing_skipped, 1);
+    // Only the second file was saved, so only it can leave an import session
+    assert_eq!(result.saved_files, vec![1]);
+}
+
+// ============================================================================
+// Copy-protected files
+// ============================================================================
+
+#[test]
+fn test_save_skips_protected_files() {
+    let (temp_dir, base_path) = setup_test_library();
+
+    // An iTunes Store .m4p: the ftyp brand alone marks it as FairPlay
+    let protected = temp_dir.path().join("store.m4p");
+    std::fs::write(&protected, b"\x00\x00\x00\x10ftypM4P \x00\x00\x00\x00").unwrap();
+    let files = vec![
+        create_file_to_save(
+            protected.to_string_lossy().to_string(),
+            "Protected",
+            "Artist",
+            "Album",
+            2004,
+            1,
+        ),
+        create_file_to_save(
+            create_dummy_audio_file(&temp_dir, "free.mp3"),
+            "Free",
+            "Artist",
+            "Album",
+            2004,
+            2,
+        ),
+    ];
+
+    let result = save_to_library(base_path.clone(), files).unwrap();
+    assert_eq!(result.files_saved, 1);
+    assert_eq!(result.protected_skipped, 1);
+    assert_eq!(result.saved_files, vec![1]);
+    let library = load_library(base_path, None).unwrap();
+    let titles: Vec<_> = library.songs.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(titles, vec!["Free"]);
+}
@@ -4,8 +4,12 @@
 //! - Basic metadata extraction from AcoustID JSON responses
 //! - Ranking algorithm (sources, dates, album type bonus)
 //! - Edge cases (missing fields, empty recordings, etc.)
+//! - Match confidence of the chosen recording
 
-use jp3_organiser_lib::services::metadata_ranking_service::extract_metadata_from_acoustic_json;
+use jp3_organiser_lib::models::MatchConfidence;
+use jp3_organiser_lib::services::metadata_ranking_service::{
+    extract_match_from_acoustic_json, extract_metadata_from_acoustic_json,
+};
 use serde_json::json;
 
 // =============================================================================
@@ -293,3 +297,44 @@ fn test_multiple_results_all_recordings_considered() {
     assert_eq!(result.title, Some("Song From Result 2".to_string()));
     assert_eq!(result.album, Some("Album 2".to_string()));
 }
+
+// =============================================================================
+// Confidence Tests
+// =============================================================================
+
+#[test]
+fn test_match_confidence_of_chosen_recording() {
+    let recording = |title: &str, sources: u32, year: i32| {
+        json!({
+            "title": title,
+            "sources": sources,
+            "artists": [{ "id": "artist-mbid", "name": "Artist" }],
+            "releasegroups": [{
+                "type": "Album",
+                "title": "Album",
+                "releases": [{ "id": "release-mbid", "date": { "year": year } }]
+            }]
+        })
+    };
+    let json = json!({
+        "status": "ok",
+        "results": [
+            { "score": 0.62, "recordings": [recording("Weak", 1, 2005)] },
+            { "score": 0.97, "recordings": [recording("Strong", 40, 2000)] }
+        ]
+    });
+
+    let (metadata, confidence) = extract_match_from_acoustic_json(&json).unwrap();
+    assert_eq!(metadata.title.as_deref(), Some("Strong"));
+    assert_eq!(confidence.acoustid_score, Some(0.97));
+    assert_eq!(confidence.acoustid_sources, Some(40));
+    assert_eq!(confidence.overall(), Some(97));
+
+    // The weakest signal sets the overall score
+    let few_sources = MatchConfidence {
+        acoustid_sources: Some(2),
+        ..confidence
+    };
+    assert_eq!(few_sources.overall(), Some(40));
+    assert_eq!(MatchConfidence::default().overall(), None);
+}
@@ -0,0 +1,120 @@
+//! Tests for bounded parallel metadata extraction.
+//!
+//! Tests cover:
+//! - Results returned in input order
+//! - Every file reported exactly once as it completes
+//! - Per-provider status after the tag read
+//! - Copy throughput cap and worker limit
+
+use std::sync::Mutex;
+
+use jp3_organiser_lib::commands::audio::extract_metadata_parallel;
+use id3::TagLike;
+use jp3_organiser_lib::models::{
+    AcoustIdStatus, FingerprintStatus, Id3Status, IoThrottleSettings, MetadataStatus,
+    MusicBrainzStatus,
+};
+use jp3_organiser_lib::services::parallel_io_service::{
+    copy_file, io_worker_count, map_ordered, set_io_throttle,
+};
+
+#[test]
+fn test_map_ordered_preserves_input_order() {
+    let items: Vec<u64> = (0..200).collect();
+
+    // Later items finish first, so completion order differs from input order
+    let results = map_ordered(
+        &items,
+        |n| {
+            std::thread::sleep(std::time::Duration::from_micros(200 - n));
+            n * 2
+        },
+        |_, _| {},
+    );
+
+    assert_eq!(results, items.iter().map(|n| n * 2).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_extract_metadata_parallel_reports_each_file() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let mut file_paths = Vec::new();
+    for i in 0..50 {
+        let ext = if i % 2 == 0 { "wav" } else { "txt" };
+        let path = temp_dir.path().join(format!("{:02}.{}", i, ext));
+        std::fs::write(&path, "fake audio data").unwrap();
+        file_paths.push(path.to_string_lossy().to_string());
+    }
+
+    let completed = Mutex::new(Vec::new());
+    let files = extract_metadata_parallel(&file_paths, |index, file| {
+        assert_eq!(file.file_path, file_paths[index]);
+        completed.lock().unwrap().push(index);
+    });
+
+    let paths: Vec<_> = files.iter().map(|f| f.file_path.clone()).collect();
+    assert_eq!(paths, file_paths);
+
+    let mut completed = completed.into_inner().unwrap();
+    completed.sort();
+    assert_eq!(completed, (0..50).collect::<Vec<_>>());
+
+    assert_eq!(files[0].metadata_status, MetadataStatus::Incomplete);
+    assert_eq!(files[1].metadata_status, MetadataStatus::Error);
+}
+
+#[test]
+fn test_extract_metadata_parallel_provider_status() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let mp3_frame = [0xFF, 0xFB, 0x90, 0x64, 0, 0, 0, 0];
+    let tagged = temp_dir.path().join("tagged.mp3");
+    let untagged = temp_dir.path().join("untagged.mp3");
+    std::fs::write(&tagged, mp3_frame).unwrap();
+    std::fs::write(&untagged, mp3_frame).unwrap();
+    let mut tag = id3::Tag::new();
+    tag.set_title("Song");
+    tag.set_artist("Artist");
+    tag.write_to_path(&tagged, id3::Version::Id3v24).unwrap();
+
+    let file_paths: Vec<String> = [&tagged, &untagged]
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    let files = extract_metadata_parallel(&file_paths, |_, _| {});
+
+    assert_eq!(files[0].providers.id3, Id3Status::Ok);
+    assert_eq!(files[1].providers.id3, Id3Status::None);
+    // Nothing beyond the tag read has run yet
+    for file in &files {
+        assert_eq!(file.providers.fingerprint, FingerprintStatus::Skipped);
+        assert_eq!(file.providers.acoustid, AcoustIdStatus::Skipped);
+        assert_eq!(file.providers.musicbrainz, MusicBrainzStatus::Skipped);
+    }
+}
+
+#[test]
+fn test_io_throttle() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let source = temp_dir.path().join("source.bin");
+    let data: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&source, &data).unwrap();
+
+    // 1 MB at 4 MB/s takes at least a quarter second (less the first chunk)
+    set_io_throttle(IoThrottleSettings {
+        max_mb_per_sec: Some(4.0),
+        max_workers: Some(1),
+    });
+    assert_eq!(io_worker_count(100), 1);
+    let started = std::time::Instant::now();
+    let dest = temp_dir.path().join("throttled.bin");
+    assert_eq!(copy_file(&source, &dest).unwrap(), data.len() as u64);
+    let elapsed = started.elapsed();
+
+    set_io_throttle(IoThrottleSettings::default());
+    assert!(elapsed >= std::time::Duration::from_millis(180), "took {:?}", elapsed);
+    assert_eq!(std::fs::read(&dest).unwrap(), data);
+
+    let dest = temp_dir.path().join("unthrottled.bin");
+    copy_file(&source, &dest).unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), data);
+}
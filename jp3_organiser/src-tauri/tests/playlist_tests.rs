@@ -0,0 +1,368 @@
+//! Integration tests for playlist commands.
+//!
+//! Tests cover:
+//! - Validation of dead references (out of range, deleted, duplicated)
+//! - Truncated and unreadable playlist files
+//! - Repair mode rewriting only the affected playlists
+//! - v2 format: per-entry added timestamps and the manual order flag
+//! - v1 files migrated on read and written for device export
+//! - v1 written on cards whose firmware only reads v1, and older files converted
+//! - Auto-playlists regenerated in place on sync and removed when turned off
+//! - Most played auto-playlist by play count, generated on export drives too
+
+use jp3_organiser_lib::commands::library::{
+    delete_songs, export_selection, initialize_library, load_library, save_to_library,
+    toggle_favorite, FileToSave,
+};
+use jp3_organiser_lib::commands::playlist::{
+    add_songs_to_playlist, convert_playlists_for_firmware, create_playlist,
+    get_auto_playlist_settings, list_playlists,
+    load_playlist, remove_songs_from_playlist, save_to_playlist, set_auto_playlist_settings,
+    sync_auto_playlists, validate_playlists, write_playlist_file, write_playlist_file_v1,
+    FileToSaveWithPlaylist,
+};
+use jp3_organiser_lib::models::{
+    AudioMetadata, AutoPlaylistSettings, MetadataSource, PlaylistEntry,
+};
+
+/// Helper to create a library with `count` songs (IDs 0..count).
+fn setup_library_with_songs(count: u32) -> (tempfile::TempDir, String) {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+
+    let files = (1..=count)
+        .map(|track| {
+            let source_path = temp_dir.path().join(format!("{}.mp3", track));
+            std::fs::write(&source_path, format!("fake audio data {}", track)).unwrap();
+            FileToSave {
+                source_path: source_path.to_string_lossy().to_string(),
+                metadata: AudioMetadata {
+                    title: Some(format!("Song {}", track)),
+                    artist: Some("Artist".to_string()),
+                    album: Some("Album".to_string()),
+                    track_number: Some(track),
+                    ..Default::default()
+                },
+                metadata_source: MetadataSource::Unknown,
+                confidence: None,
+                fingerprint: None,
+            }
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    (temp_dir, base_path)
+}
+
+fn entries(song_ids: &[u32]) -> Vec<PlaylistEntry> {
+    song_ids.iter().map(|&id| PlaylistEntry::new(id, 1_700_000_000)).collect()
+}
+
+fn playlist_path(temp_dir: &tempfile::TempDir, playlist_id: u32) -> std::path::PathBuf {
+    temp_dir
+        .path()
+        .join("jp3")
+        .join("playlists")
+        .join(format!("{}.bin", playlist_id))
+}
+
+#[test]
+fn test_validate_playlists_reports_dead_references() {
+    let (temp_dir, base_path) = setup_library_with_songs(3);
+    delete_songs(base_path.clone(), vec![1]).unwrap();
+
+    create_playlist(base_path.clone(), "Clean".to_string(), vec![0, 2]).unwrap();
+    create_playlist(base_path.clone(), "Dirty".to_string(), vec![0, 1, 7, 2, 0, 7]).unwrap();
+
+    // Truncated: header claims 3 songs but only 2 are present
+    let truncated_path = playlist_path(&temp_dir, 3);
+    write_playlist_file(&truncated_path, "Cut Short", &entries(&[2, 0, 1]), true).unwrap();
+    let bytes = std::fs::read(&truncated_path).unwrap();
+    std::fs::write(&truncated_path, &bytes[..bytes.len() - 2]).unwrap();
+
+    // Unreadable: not a playlist file at all
+    std::fs::write(playlist_path(&temp_dir, 4), b"garbage").unwrap();
+
+    let reports = validate_playlists(base_path.clone(), false).unwrap();
+    assert_eq!(reports.len(), 4);
+
+    let clean = &reports[0];
+    assert_eq!(clean.name.as_deref(), Some("Clean"));
+    assert!(!clean.has_issues());
+
+    let dirty = &reports[1];
+    assert_eq!(dirty.out_of_range_ids, vec![7]);
+    assert_eq!(dirty.deleted_ids, vec![1]);
+    assert_eq!(dirty.duplicate_ids, vec![0]);
+    assert!(!dirty.truncated);
+    assert!(!dirty.repaired);
+
+    let cut_short = &reports[2];
+    assert!(cut_short.truncated);
+    assert!(cut_short.deleted_ids.is_empty());
+
+    let garbage = &reports[3];
+    assert!(garbage.name.is_none());
+    assert!(garbage.error.is_some());
+
+    // Validation alone leaves files untouched
+    assert_eq!(load_playlist(base_path, 2).unwrap().song_ids, vec![0, 1, 7, 2, 0, 7]);
+}
+
+#[test]
+fn test_validate_playlists_repair() {
+    let (temp_dir, base_path) = setup_library_with_songs(3);
+    delete_songs(base_path.clone(), vec![1]).unwrap();
+
+    create_playlist(base_path.clone(), "Clean".to_string(), vec![2, 0]).unwrap();
+    create_playlist(base_path.clone(), "Dirty".to_string(), vec![0, 1, 7, 2, 0]).unwrap();
+    let truncated_path = playlist_path(&temp_dir, 3);
+    write_playlist_file(&truncated_path, "Cut Short", &entries(&[2, 0, 1]), true).unwrap();
+    let bytes = std::fs::read(&truncated_path).unwrap();
+    std::fs::write(&truncated_path, &bytes[..bytes.len() - 4]).unwrap();
+    let clean_modified = std::fs::metadata(playlist_path(&temp_dir, 1))
+        .unwrap()
+        .modified()
+        .unwrap();
+
+    let reports = validate_playlists(base_path.clone(), true).unwrap();
+    let repaired: Vec<_> = reports.iter().map(|r| r.repaired).collect();
+    assert_eq!(repaired, vec![false, true, true]);
+
+    assert_eq!(load_playlist(base_path.clone(), 2).unwrap().song_ids, vec![0, 2]);
+    assert_eq!(load_playlist(base_path.clone(), 3).unwrap().song_ids, vec![2, 0]);
+    let clean_after = std::fs::metadata(playlist_path(&temp_dir, 1))
+        .unwrap()
+        .modified()
+        .unwrap();
+    assert_eq!(clean_after, clean_modified);
+
+    // A second pass finds nothing left to fix
+    let reports = validate_playlists(base_path, true).unwrap();
+    assert!(reports.iter().all(|r| !r.has_issues() && !r.repaired));
+}
+
+#[test]
+fn test_playlist_entries_keep_added_at() {
+    let (temp_dir, base_path) = setup_library_with_songs(4);
+
+    // Two songs added at a known time
+    write_playlist_file(&playlist_path(&temp_dir, 1), "Mix", &entries(&[0, 1]), true).unwrap();
+
+    add_songs_to_playlist(base_path.clone(), 1, vec![2, 3]).unwrap();
+    remove_songs_from_playlist(base_path.clone(), 1, vec![1]).unwrap();
+
+    let playlist = load_playlist(base_path.clone(), 1).unwrap();
+    assert_eq!(playlist.song_ids, vec![0, 2, 3]);
+    assert_eq!(playlist.added_at[0], 1_700_000_000);
+    assert!(playlist.added_at[1] > 1_700_000_000);
+    assert_eq!(playlist.added_at[1], playlist.added_at[2]);
+    assert!(playlist.manual_order);
+
+    // A playlist built from an import keeps the import order, flagged as generated
+    let source_path = temp_dir.path().join("new.mp3");
+    std::fs::write(&source_path, "fake audio data new").unwrap();
+    let result = save_to_playlist(
+        base_path.clone(),
+        "Imported".to_string(),
+        vec![FileToSaveWithPlaylist {
+            source_path: source_path.to_string_lossy().to_string(),
+            metadata: AudioMetadata {
+                title: Some("New Song".to_string()),
+                artist: Some("Artist".to_string()),
+                album: Some("Album".to_string()),
+                ..Default::default()
+            },
+            metadata_source: MetadataSource::Unknown,
+            confidence: None,
+        }],
+    )
+    .unwrap();
+    let imported = load_playlist(base_path, result.playlist_id).unwrap();
+    assert!(!imported.manual_order);
+    assert_eq!(imported.song_ids.len(), 1);
+}
+
+#[test]
+fn test_v1_playlist_migrated_on_read() {
+    let (temp_dir, base_path) = setup_library_with_songs(3);
+    let path = playlist_path(&temp_dir, 1);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+    // The device export writer produces the 14-byte header and 4-byte entries
+    write_playlist_file_v1(&path, "Old", &[2, 0]).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 1);
+    assert_eq!(bytes.len(), 14 + "Old".len() + 2 * 4);
+
+    let playlist = load_playlist(base_path.clone(), 1).unwrap();
+    assert_eq!(playlist.name, "Old");
+    assert_eq!(playlist.song_ids, vec![2, 0]);
+    assert_eq!(playlist.added_at, vec![0, 0]);
+    assert!(playlist.manual_order);
+
+    // The next write upgrades the file to v2
+    add_songs_to_playlist(base_path.clone(), 1, vec![1]).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 2);
+    let playlist = load_playlist(base_path, 1).unwrap();
+    assert_eq!(playlist.song_ids, vec![2, 0, 1]);
+    assert_eq!(&playlist.added_at[..2], &[0, 0]);
+}
+
+fn playlist_version(path: &std::path::Path) -> u32 {
+    let bytes = std::fs::read(path).unwrap();
+    u32::from_le_bytes(bytes[4..8].try_into().unwrap())
+}
+
+fn declare_firmware_playlist_version(base: &std::path::Path, version: u32) {
+    let marker_path = base.join("jp3").join("metadata").join("device.json");
+    std::fs::create_dir_all(marker_path.parent().unwrap()).unwrap();
+    std::fs::write(
+        marker_path,
+        format!(r#"{{"id":"card","firmwareMaxPlaylistVersion":{}}}"#, version),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_v1_written_for_firmware_that_only_reads_v1() {
+    let (temp_dir, base_path) = setup_library_with_songs(3);
+    create_playlist(base_path.clone(), "Before".to_string(), vec![0, 1]).unwrap();
+    assert_eq!(playlist_version(&playlist_path(&temp_dir, 1)), 2);
+
+    // Nothing to convert while the firmware reads v2
+    declare_firmware_playlist_version(temp_dir.path(), 2);
+    assert_eq!(convert_playlists_for_firmware(base_path.clone()).unwrap(), 0);
+
+    declare_firmware_playlist_version(temp_dir.path(), 1);
+    create_playlist(base_path.clone(), "After".to_string(), vec![2]).unwrap();
+    add_songs_to_playlist(base_path.clone(), 2, vec![0]).unwrap();
+    assert_eq!(playlist_version(&playlist_path(&temp_dir, 2)), 1);
+    assert_eq!(load_playlist(base_path.clone(), 2).unwrap().song_ids, vec![2, 0]);
+
+    // The playlist from before is rewritten once
+    assert_eq!(convert_playlists_for_firmware(base_path.clone()).unwrap(), 1);
+    assert_eq!(playlist_version(&playlist_path(&temp_dir, 1)), 1);
+    assert_eq!(load_playlist(base_path.clone(), 1).unwrap().song_ids, vec![0, 1]);
+    assert_eq!(convert_playlists_for_firmware(base_path.clone()).unwrap(), 0);
+
+    // Exports to such a card get v1 playlists too
+    let card = tempfile::TempDir::new().unwrap();
+    declare_firmware_playlist_version(card.path(), 1);
+    let card_path = card.path().to_string_lossy().to_string();
+    export_selection(base_path, vec![], vec![1], card_path.clone(), None, None).unwrap();
+    let exported = list_playlists(card_path).unwrap();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(playlist_version(&playlist_path(&card, exported[0].id)), 1);
+}
+
+#[test]
+fn test_sync_auto_playlists() {
+    let (_temp_dir, base_path) = setup_library_with_songs(4);
+    create_playlist(base_path.clone(), "Mine".to_string(), vec![0]).unwrap();
+    toggle_favorite(base_path.clone(), 1).unwrap();
+    toggle_favorite(base_path.clone(), 3).unwrap();
+
+    // Nothing is exported until turned on
+    let result = sync_auto_playlists(base_path.clone()).unwrap();
+    assert!(result.written.is_empty());
+
+    let settings = AutoPlaylistSettings {
+        recently_added_count: 2,
+        favorites: true,
+        ..Default::default()
+    };
+    set_auto_playlist_settings(base_path.clone(), settings).unwrap();
+    assert_eq!(get_auto_playlist_settings(base_path.clone()).unwrap().recently_added_count, 2);
+
+    let result = sync_auto_playlists(base_path.clone()).unwrap();
+    let written: Vec<_> = result.written.iter().map(|p| (p.name.as_str(), p.id)).collect();
+    assert_eq!(written, vec![("Recently Added", 2), ("Favorites", 3)]);
+    assert_eq!(load_playlist(base_path.clone(), 2).unwrap().song_ids, vec![3, 2]);
+    let favorites = load_playlist(base_path.clone(), 3).unwrap();
+    assert_eq!(favorites.song_ids, vec![1, 3]);
+    assert!(!favorites.manual_order);
+
+    // A later sync rewrites the same files
+    toggle_favorite(base_path.clone(), 3).unwrap();
+    sync_auto_playlists(base_path.clone()).unwrap();
+    assert_eq!(list_playlists(base_path.clone()).unwrap().len(), 3);
+    assert_eq!(load_playlist(base_path.clone(), 3).unwrap().song_ids, vec![1]);
+
+    // Turning a kind off deletes its file
+    let settings = AutoPlaylistSettings {
+        recently_added_count: 2,
+        favorites: false,
+        ..Default::default()
+    };
+    set_auto_playlist_settings(base_path.clone(), settings).unwrap();
+    let result = sync_auto_playlists(base_path.clone()).unwrap();
+    assert_eq!(result.removed, 1);
+    let names: Vec<_> = list_playlists(base_path)
+        .unwrap()
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+    assert_eq!(names, vec!["Mine", "Recently Added"]);
+}
+
+#[test]
+fn test_most_played_auto_playlist() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+    let files = [3, 0, 7, 3]
+        .into_iter()
+        .enumerate()
+        .map(|(i, play_count)| {
+            let source_path = temp_dir.path().join(format!("{}.mp3", i));
+            std::fs::write(&source_path, format!("fake audio data {}", i)).unwrap();
+            FileToSave {
+                source_path: source_path.to_string_lossy().to_string(),
+                metadata: AudioMetadata {
+                    title: Some(format!("Song {}", i)),
+                    artist: Some("Artist".to_string()),
+                    album: Some("Album".to_string()),
+                    play_count: Some(play_count),
+                    ..Default::default()
+                },
+                metadata_source: MetadataSource::Unknown,
+                confidence: None,
+                fingerprint: None,
+            }
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let settings = AutoPlaylistSettings {
+        most_played_count: 3,
+        ..Default::default()
+    };
+    set_auto_playlist_settings(base_path.clone(), settings).unwrap();
+    let result = sync_auto_playlists(base_path.clone()).unwrap();
+    assert_eq!(result.written.len(), 1);
+    assert_eq!(result.written[0].name, "Most Played");
+
+    // Ties keep library order; the unplayed song is left out
+    let most_played = load_playlist(base_path.clone(), result.written[0].id).unwrap();
+    assert_eq!(most_played.song_ids, vec![2, 0, 3]);
+
+    // An export gets its own, built from the songs it holds
+    let card = tempfile::TempDir::new().unwrap();
+    let card_path = card.path().to_string_lossy().to_string();
+    let exported = export_selection(base_path, vec![0, 1, 2], vec![], card_path.clone(), None, None)
+        .unwrap();
+    assert_eq!(exported.auto_playlists_exported, 1);
+    let playlists = list_playlists(card_path.clone()).unwrap();
+    assert_eq!(playlists.len(), 1);
+    let card_library = load_library(card_path.clone(), None).unwrap();
+    let titles: Vec<_> = load_playlist(card_path, playlists[0].id)
+        .unwrap()
+        .song_ids
+        .iter()
+        .map(|&id| card_library.songs.iter().find(|s| s.id == id).unwrap().title.clone())
+        .collect();
+    assert_eq!(titles, vec!["Song 2", "Song 0"]);
+}
@@ -0,0 +1,43 @@
+//! Tests for keeping the computer awake during long IO.
+//!
+//! Tests cover:
+//! - Nested sleep guards released only when the last is dropped
+//! - IO tasks holding a guard from submission until they have run
+
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use jp3_organiser_lib::services::power_service::{keep_awake, sleep_guard_count};
+use jp3_organiser_lib::services::task_manager_service::TaskManager;
+
+#[test]
+fn test_sleep_guards_and_io_tasks() {
+    assert_eq!(sleep_guard_count(), 0);
+    let first = keep_awake("First");
+    let second = keep_awake("Second");
+    assert_eq!(sleep_guard_count(), 2);
+    drop(first);
+    assert_eq!(sleep_guard_count(), 1);
+    drop(second);
+    assert_eq!(sleep_guard_count(), 0);
+
+    // The guard outlives submit and is dropped once the task has run
+    let tasks = TaskManager::default();
+    let (started, wait_started) = mpsc::channel();
+    let (finish, wait_finish) = mpsc::channel::<()>();
+    tasks.submit_io("Copy", move || {
+        started.send(sleep_guard_count()).unwrap();
+        wait_finish.recv().unwrap();
+        Ok(())
+    });
+    tasks.submit("Quick", || Ok(()));
+    assert_eq!(wait_started.recv().unwrap(), 1);
+    finish.send(()).unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while tasks.pending_count() > 0 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(tasks.pending_count(), 0);
+    assert_eq!(sleep_guard_count(), 0);
+}
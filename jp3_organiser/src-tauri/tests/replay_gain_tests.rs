@@ -0,0 +1,99 @@
+//! Tests for ReplayGain analysis.
+//!
+//! Tests cover:
+//! - Reading loudness from ffmpeg's ebur128 summary
+//! - Track and album gain against the -18 LUFS reference
+//! - Persisting and pruning the gain index
+
+use jp3_organiser_lib::commands::library::initialize_library;
+use jp3_organiser_lib::models::{ReplayGainIndex, SongGain};
+use jp3_organiser_lib::services::replay_gain_service::{
+    load_replay_gain, parse_ebur128_summary, retain_replay_gain, save_replay_gain, song_gain,
+    Loudness,
+};
+
+/// The tail of `ffmpeg -af ebur128=peak=true` output.
+const EBUR128_OUTPUT: &str = "\
+[Parsed_ebur128_0 @ 0x5581] Summary:
+
+  Integrated loudness:
+    I:         -11.2 LUFS
+    Threshold: -21.4 LUFS
+
+  Loudness range:
+    LRA:         5.1 LU
+    Threshold: -31.4 LUFS
+    LRA low:   -15.0 LUFS
+    LRA high:   -9.9 LUFS
+
+  True peak:
+    Peak:        0.4 dBFS
+";
+
+#[test]
+fn test_parse_ebur128_summary() {
+    let loudness = parse_ebur128_summary(EBUR128_OUTPUT).expect("summary");
+    assert_eq!(loudness.integrated_lufs, -11.2);
+    assert_eq!(loudness.true_peak_dbfs, 0.4);
+
+    // Silence: no peak at all
+    let silent = EBUR128_OUTPUT
+        .replace("-11.2 LUFS", "-70.0 LUFS")
+        .replace("0.4 dBFS", "-inf dBFS");
+    let loudness = parse_ebur128_summary(&silent).unwrap();
+    assert_eq!(loudness.true_peak_dbfs, f64::NEG_INFINITY);
+
+    assert_eq!(parse_ebur128_summary("Output #0, null, to 'pipe:':"), None);
+}
+
+#[test]
+fn test_song_gain_track_and_album() {
+    let track = Loudness {
+        integrated_lufs: -11.2,
+        true_peak_dbfs: 0.0,
+    };
+    let album = Loudness {
+        integrated_lufs: -14.0,
+        true_peak_dbfs: -6.0,
+    };
+
+    let gain = song_gain(&track, Some(&album));
+    assert_eq!(gain.track_gain_db, -6.8);
+    assert_eq!(gain.track_peak, 1.0);
+    assert_eq!(gain.album_gain_db, Some(-4.0));
+    assert!((gain.album_peak.unwrap() - 0.501).abs() < 0.001);
+
+    // Silence is clamped rather than boosted by +52 dB
+    let silent = Loudness {
+        integrated_lufs: -70.0,
+        true_peak_dbfs: f64::NEG_INFINITY,
+    };
+    let gain = song_gain(&silent, None);
+    assert_eq!(gain.track_gain_db, 24.0);
+    assert_eq!(gain.track_peak, 0.0);
+    assert_eq!(gain.album_gain_db, None);
+}
+
+#[test]
+fn test_replay_gain_index_round_trip() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base = temp_dir.path();
+    initialize_library(base.to_string_lossy().to_string()).unwrap();
+    assert!(load_replay_gain(base).unwrap().songs.is_empty());
+
+    let gain = SongGain {
+        track_gain_db: -3.5,
+        track_peak: 0.9,
+        album_gain_db: Some(-4.25),
+        album_peak: Some(0.98),
+    };
+    let mut index = ReplayGainIndex::default();
+    index.songs.insert("00/a.mp3".to_string(), gain);
+    index.songs.insert("00/b.mp3".to_string(), SongGain::default());
+    save_replay_gain(base, &index).unwrap();
+
+    retain_replay_gain(base, |path| path == "00/a.mp3").unwrap();
+    let index = load_replay_gain(base).unwrap();
+    assert_eq!(index.songs.len(), 1);
+    assert_eq!(index.songs["00/a.mp3"], gain);
+}
@@ -0,0 +1,71 @@
+//! Tests for resampling hi-res audio before fingerprinting.
+//!
+//! Tests cover:
+//! - Hi-res WAV resampled to Chromaprint's 11025 Hz mono
+//! - Standard-rate sources passed through untouched
+
+use std::path::Path;
+
+use jp3_organiser_lib::services::resample_service::{
+    resample_for_fingerprint, CHROMAPRINT_SAMPLE_RATE,
+};
+
+/// Helper to write a stereo 16-bit sine-wave WAV.
+fn write_test_wav(path: &Path, sample_rate: u32, secs: u32) {
+    let frames = sample_rate * secs;
+    let data_len = frames * 4;
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * 4).to_le_bytes());
+    bytes.extend_from_slice(&4u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for i in 0..frames {
+        let t = i as f32 / sample_rate as f32;
+        let value = ((t * 440.0 * std::f32::consts::TAU).sin() * 8000.0) as i16;
+        bytes.extend_from_slice(&value.to_le_bytes());
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    std::fs::write(path, bytes).unwrap();
+}
+
+#[test]
+fn test_hi_res_wav_is_resampled() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let source = temp_dir.path().join("hires.wav");
+    write_test_wav(&source, 96000, 2);
+
+    let resampled = resample_for_fingerprint(&source, 30).unwrap().unwrap();
+    assert_eq!(resampled.source_sample_rate, 96000);
+    assert_eq!(resampled.source_duration_secs, 2);
+
+    let wav = std::fs::read(&resampled.path).unwrap();
+    let rate = u32::from_le_bytes(wav[24..28].try_into().unwrap());
+    let channels = u16::from_le_bytes(wav[22..24].try_into().unwrap());
+    let frames = (wav.len() - 44) / 2;
+    assert_eq!(rate, CHROMAPRINT_SAMPLE_RATE);
+    assert_eq!(channels, 1);
+    // Two seconds at 11025 Hz, allowing for resampler edge frames
+    assert!((frames as i64 - 2 * 11025).abs() < 1100, "frames = {}", frames);
+
+    // Temp file is removed on drop
+    let temp_path = resampled.path.clone();
+    drop(resampled);
+    assert!(!temp_path.exists());
+}
+
+#[test]
+fn test_standard_rate_is_passed_through() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let source = temp_dir.path().join("cd.wav");
+    write_test_wav(&source, 44100, 1);
+
+    assert!(resample_for_fingerprint(&source, 30).unwrap().is_none());
+}
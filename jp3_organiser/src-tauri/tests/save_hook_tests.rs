@@ -0,0 +1,120 @@
+//! Tests for pre-save and post-save hooks.
+//!
+//! Tests cover:
+//! - Running a hook program with input, output, failures and a timeout
+//! - Pre-save hooks rewriting metadata and file names, or cancelling a save
+//! - Post-save hooks receiving the saved songs
+//!
+//! Hooks are `sh` scripts, so these tests only run on Unix.
+#![cfg(unix)]
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use jp3_organiser_lib::commands::library::{
+    initialize_library, load_library, save_to_library, FileToSave,
+};
+use jp3_organiser_lib::models::{AudioMetadata, HookCommand, MetadataSource, SaveHookSettings};
+use jp3_organiser_lib::services::save_hook_service::{run_hook, set_save_hooks};
+
+fn sh(script: &str) -> HookCommand {
+    HookCommand {
+        program: "sh".to_string(),
+        args: vec!["-c".to_string(), script.to_string()],
+    }
+}
+
+fn file_to_save(dir: &Path, title: &str) -> FileToSave {
+    let path = dir.join(format!("{}.mp3", title));
+    std::fs::write(&path, format!("fake audio data for {}", title)).unwrap();
+    FileToSave {
+        source_path: path.to_string_lossy().to_string(),
+        metadata: AudioMetadata {
+            title: Some(title.to_string()),
+            artist: Some("Artist".to_string()),
+            album: Some("Album".to_string()),
+            ..Default::default()
+        },
+        metadata_source: MetadataSource::Manual,
+        confidence: None,
+        fingerprint: None,
+    }
+}
+
+#[test]
+fn test_run_hook() {
+    let timeout = Duration::from_secs(5);
+    let output = run_hook(&sh("tr a-z A-Z"), b"hello", timeout).unwrap();
+    assert_eq!(output, "HELLO");
+
+    let error = run_hook(&sh("echo broken >&2; exit 3"), b"", timeout).unwrap_err();
+    assert!(error.contains("broken"), "{}", error);
+
+    let started = Instant::now();
+    let error = run_hook(&sh("sleep 10"), b"", Duration::from_secs(1)).unwrap_err();
+    assert!(error.contains("did not finish"), "{}", error);
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    let missing = HookCommand {
+        program: "/nonexistent/hook".to_string(),
+        args: Vec::new(),
+    };
+    assert!(run_hook(&missing, b"", timeout).is_err());
+}
+
+// One test, because hooks are app-wide and tests run in parallel
+#[test]
+fn test_save_hooks() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base = temp_dir.path();
+    let base_path = base.to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+
+    // A failing pre-save hook cancels the save
+    set_save_hooks(SaveHookSettings {
+        pre_save: Some(sh("cat > /dev/null; exit 1")),
+        ..Default::default()
+    });
+    let files = vec![file_to_save(base, "first")];
+    assert!(save_to_library(base_path.clone(), files).is_err());
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert!(library.songs.is_empty());
+    assert!(!base.join("jp3/music/00/001.mp3").exists());
+
+    // Pre-save renames the title and the copy; post-save records the songs
+    let log = base.join("post-save.json");
+    set_save_hooks(SaveHookSettings {
+        pre_save: Some(sh(
+            r#"sed -e 's/"title":"first"/"title":"First!"/' -e 's/"fileName":null/"fileName":"custom"/'"#,
+        )),
+        post_save: Some(HookCommand {
+            program: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                r#"cat > "$1.part" && mv "$1.part" "$1""#.to_string(),
+                "sh".to_string(),
+                log.to_string_lossy().to_string(),
+            ],
+        }),
+        timeout_secs: Some(10),
+    });
+    let files = vec![file_to_save(base, "first")];
+    let result = save_to_library(base_path.clone(), files).unwrap();
+    set_save_hooks(SaveHookSettings::default());
+
+    let library = load_library(base_path, None).unwrap();
+    assert_eq!(library.songs[0].title, "First!");
+    assert_eq!(library.songs[0].path, "00/custom.mp3");
+    assert!(base.join("jp3/music/00/custom.mp3").exists());
+
+    let started = Instant::now();
+    while !log.exists() && started.elapsed() < Duration::from_secs(10) {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let input: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&log).unwrap()).unwrap();
+    assert_eq!(input["event"], "post-save");
+    assert_eq!(input["songs"][0]["songId"], result.song_ids[0]);
+    assert_eq!(input["songs"][0]["path"], "00/custom.mp3");
+    assert_eq!(input["songs"][0]["metadata"]["title"], "First!");
+}
@@ -0,0 +1,152 @@
+//! Tests for backend library search.
+//!
+//! Tests cover:
+//! - Accent- and case-insensitive matching
+//! - Romanized keys for non-Latin names
+//! - Ranking (exact > prefix > word prefix > contains) and per-category limits
+//! - Songs found through their artist or album, and the cached index refreshing
+//!   after a save
+
+use jp3_organiser_lib::commands::enrichment::{
+    set_transliteration_enabled, set_transliteration_override,
+};
+use jp3_organiser_lib::commands::library::{initialize_library, save_to_library, FileToSave};
+use jp3_organiser_lib::commands::search::search_library;
+use jp3_organiser_lib::models::{AudioMetadata, MetadataSource};
+use jp3_organiser_lib::services::search_service::match_score;
+
+fn setup_library_with(songs: &[(&str, &str, &str)]) -> (tempfile::TempDir, String) {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+
+    let files = songs
+        .iter()
+        .enumerate()
+        .map(|(i, (title, artist, album))| {
+            let source = temp_dir.path().join(format!("{}.mp3", i));
+            std::fs::write(&source, format!("fake audio {}", i)).unwrap();
+            FileToSave {
+                source_path: source.to_string_lossy().to_string(),
+                metadata: AudioMetadata {
+                    title: Some(title.to_string()),
+                    artist: Some(artist.to_string()),
+                    album: Some(album.to_string()),
+                    year: Some(2000),
+                    track_number: Some(1),
+                    duration_secs: Some(200),
+                    release_mbid: None,
+                    artist_mbid: None,
+                    genre: None,
+                    album_artist: None,
+                    disc_number: None,
+                    rating: None,
+                    play_count: None,
+                },
+                metadata_source: MetadataSource::Unknown,
+                confidence: None,
+                fingerprint: None,
+            }
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    (temp_dir, base_path)
+}
+
+#[test]
+fn test_search_ignores_accents() {
+    let (_temp_dir, base_path) = setup_library_with(&[
+        ("Halo", "Beyoncé", "I Am... Sasha Fierce"),
+        ("Hoppípolla", "Sigur Rós", "Takk..."),
+    ]);
+
+    let results = search_library(base_path.clone(), "beyonce".to_string(), None).unwrap();
+    assert_eq!(results.artists.len(), 1);
+    assert_eq!(results.artists[0].name, "Beyoncé");
+
+    let results = search_library(base_path.clone(), "SIGUR ROS".to_string(), None).unwrap();
+    assert_eq!(results.artists[0].name, "Sigur Rós");
+
+    let results = search_library(base_path, "hoppipolla".to_string(), None).unwrap();
+    assert_eq!(results.songs[0].title, "Hoppípolla");
+    assert_eq!(results.total_count, 1);
+}
+
+#[test]
+fn test_search_uses_romanized_keys() {
+    let (_temp_dir, base_path) =
+        setup_library_with(&[("ここでキスして。", "椎名林檎", "無罪モラトリアム")]);
+
+    // Nothing to match until transliteration is on
+    let results = search_library(base_path.clone(), "shiina".to_string(), None).unwrap();
+    assert_eq!(results.total_count, 0);
+
+    set_transliteration_enabled(base_path.clone(), true).unwrap();
+    set_transliteration_override(
+        base_path.clone(),
+        "椎名林檎".to_string(),
+        Some("shiina ringo".to_string()),
+    )
+    .unwrap();
+
+    let results = search_library(base_path.clone(), "shiina".to_string(), None).unwrap();
+    assert_eq!(results.artists[0].name, "椎名林檎");
+
+    // Kana titles are romanized automatically
+    let results = search_library(base_path, "kokode".to_string(), None).unwrap();
+    assert_eq!(results.songs[0].title, "ここでキスして。");
+}
+
+#[test]
+fn test_search_ranking_and_limit() {
+    assert_eq!(match_score("low", "low"), 100);
+    assert_eq!(match_score("lower", "low"), 80);
+    assert_eq!(match_score("the lowdown", "low"), 60);
+    assert_eq!(match_score("yellow", "low"), 50);
+    assert_eq!(match_score("high", "low"), 0);
+
+    let (_temp_dir, base_path) = setup_library_with(&[
+        ("Yellow", "Coldplay", "Parachutes"),
+        ("Low", "Flo Rida", "Mail on Sunday"),
+        ("Lower", "Someone", "Something"),
+    ]);
+
+    let results = search_library(base_path.clone(), "low".to_string(), None).unwrap();
+    let titles: Vec<_> = results.songs.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(titles, vec!["Low", "Lower", "Yellow"]);
+
+    let results = search_library(base_path, "low".to_string(), Some(1)).unwrap();
+    assert_eq!(results.songs.len(), 1);
+}
+
+#[test]
+fn test_search_songs_by_artist_and_album() {
+    let (temp_dir, base_path) = setup_library_with(&[
+        ("Apples", "Lowell", "Fruit"),
+        ("Low", "Flo Rida", "Mail on Sunday"),
+        ("Pears", "Someone", "Low Tide"),
+    ]);
+
+    // Title matches rank above songs found through their artist or album
+    let results = search_library(base_path.clone(), "low".to_string(), None).unwrap();
+    let titles: Vec<_> = results.songs.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(titles, vec!["Low", "Apples", "Pears"]);
+
+    // The cached index is rebuilt once library.bin changes
+    let source = temp_dir.path().join("new.mp3");
+    std::fs::write(&source, "fake audio new").unwrap();
+    let mut file = FileToSave {
+        source_path: source.to_string_lossy().to_string(),
+        metadata: AudioMetadata::default(),
+        metadata_source: MetadataSource::Unknown,
+        confidence: None,
+        fingerprint: None,
+    };
+    file.metadata.title = Some("Lowlands".to_string());
+    file.metadata.artist = Some("Someone".to_string());
+    file.metadata.album = Some("Fields".to_string());
+    save_to_library(base_path.clone(), vec![file]).unwrap();
+
+    let results = search_library(base_path, "lowlands".to_string(), None).unwrap();
+    assert_eq!(results.songs[0].title, "Lowlands");
+}
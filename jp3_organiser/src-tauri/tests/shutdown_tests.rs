@@ -0,0 +1,109 @@
+//! Tests for stopping background work on shutdown.
+//!
+//! Tests cover:
+//! - Queued tasks are dropped while the running one finishes
+//! - A panicking task fails on its own, and later tasks still run
+//! - Active task listing and the IO-task check used by close protection
+//! - Foreground writes (saves, exports) listed as IO tasks and waited for
+//! - Cancelling a compaction that is not running
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use jp3_organiser_lib::commands::shutdown::stop_background_work;
+use jp3_organiser_lib::services::compaction_service::CompactionControl;
+use jp3_organiser_lib::services::task_manager_service::{begin_foreground_write, TaskManager};
+
+#[test]
+fn test_stop_background_work_drops_queued_tasks() {
+    let tasks = TaskManager::default();
+    let compaction = CompactionControl::default();
+    let ran = Arc::new(AtomicU32::new(0));
+    let (started_tx, started_rx) = mpsc::channel();
+
+    tasks.submit_io("Copy files", move || {
+        started_tx.send(()).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        Ok(())
+    });
+    for name in ["Thumbnail A", "Thumbnail B"] {
+        let ran = ran.clone();
+        tasks.submit(name, move || {
+            ran.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        });
+    }
+    started_rx.recv().unwrap();
+
+    let active = tasks.active_tasks();
+    assert_eq!(active.len(), 3);
+    assert!(active[0].running && active[0].io);
+    assert!(tasks.has_io_tasks());
+
+    let report = stop_background_work(&tasks, &compaction, Duration::from_secs(5));
+    assert_eq!(report.cancelled_tasks, vec!["Thumbnail A", "Thumbnail B"]);
+    assert!(!report.compaction_cancelled);
+    assert!(report.clean);
+
+    // The running copy finished; the queued thumbnails never started
+    assert_eq!(ran.load(Ordering::SeqCst), 0);
+    assert_eq!(tasks.pending_count(), 0);
+    assert!(tasks.active_tasks().is_empty());
+    assert!(!tasks.has_io_tasks());
+
+    // The queue still accepts work after a shutdown attempt
+    let ran_after = ran.clone();
+    tasks.submit("Later", move || {
+        ran_after.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    });
+    assert!(tasks.wait_idle(Duration::from_secs(5)));
+    assert_eq!(ran.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_panicking_task_does_not_stop_worker() {
+    let tasks = TaskManager::default();
+    let ran = Arc::new(AtomicU32::new(0));
+
+    tasks.submit("Broken task", || panic!("bad cover data"));
+    let after = ran.clone();
+    tasks.submit("Thumbnail A", move || {
+        after.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    });
+
+    assert!(tasks.wait_idle(Duration::from_secs(5)));
+    assert_eq!(ran.load(Ordering::SeqCst), 1);
+    assert!(tasks.active_tasks().is_empty());
+}
+
+#[test]
+fn test_stop_background_work_waits_for_foreground_writes() {
+    let tasks = TaskManager::default();
+    let compaction = CompactionControl::default();
+
+    let write = begin_foreground_write("Saving songs to the library");
+    let active = tasks.active_tasks();
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].name, "Saving songs to the library");
+    assert!(active[0].running && active[0].io);
+    assert!(tasks.has_io_tasks());
+    assert!(!tasks.wait_idle(Duration::from_millis(20)));
+
+    // A write that doesn't finish in time leaves the shutdown unclean
+    let report = stop_background_work(&tasks, &compaction, Duration::from_millis(20));
+    assert!(report.cancelled_tasks.is_empty());
+    assert!(!report.clean);
+
+    // Otherwise shutdown waits for it to finish
+    let writer = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(100));
+        drop(write);
+    });
+    let report = stop_background_work(&tasks, &compaction, Duration::from_secs(5));
+    assert!(report.clean);
+    assert!(!tasks.has_io_tasks());
+    writer.join().unwrap();
+}
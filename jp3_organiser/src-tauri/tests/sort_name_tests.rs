@@ -0,0 +1,52 @@
+//! Tests for library sort names.
+//!
+//! Tests cover:
+//! - Leading articles, punctuation and accents
+//! - Natural order for leading numbers
+//! - Names that are only an article
+//! - Collation of accented and mixed-case names
+
+use std::cmp::Ordering;
+
+use jp3_organiser_lib::services::sort_name_service::{collate, compare_names, sort_name};
+
+#[test]
+fn test_sort_name_normalizes_names() {
+    assert_eq!(sort_name("The Wall"), "wall");
+    assert_eq!(sort_name("A Night at the Opera"), "night at the opera");
+    assert_eq!(sort_name("An Awesome Wave"), "awesome wave");
+    assert_eq!(sort_name("Björk"), "bjork");
+    assert_eq!(sort_name("...And Justice for All"), "and justice for all");
+    assert_eq!(sort_name("  The   Beatles "), "beatles");
+    // Only strip articles that are whole words
+    assert_eq!(sort_name("Theory of a Deadman"), "theory of a deadman");
+    // A lone article is the name itself
+    assert_eq!(sort_name("The"), "the");
+}
+
+#[test]
+fn test_leading_numbers_sort_naturally() {
+    assert_eq!(compare_names("2 Unlimited", "10cc"), Ordering::Less);
+    assert_eq!(compare_names("The Wall", "Weezer"), Ordering::Less);
+    assert_eq!(compare_names("Abba", "The Beatles"), Ordering::Less);
+
+    let mut names = vec!["The Wall", "10cc", "Animals", "2 Unlimited", "Élan"];
+    names.sort_by(|a, b| compare_names(a, b));
+    assert_eq!(names, vec!["2 Unlimited", "10cc", "Animals", "Élan", "The Wall"]);
+}
+
+#[test]
+fn test_names_collate_across_accents_and_case() {
+    // "Ø" doesn't decompose, so byte order would put it after "z"
+    assert_eq!(collate("øyvind", "zebra"), Ordering::Less);
+    assert_eq!(collate("oasis", "øyvind"), Ordering::Less);
+    // Pink Floyd before Pinkerton: spaces aren't ignored
+    assert_eq!(compare_names("Pink Floyd", "Pinkerton"), Ordering::Less);
+
+    let mut names = vec!["zebra", "Øyvind", "Apple", "Émile", "apple", "ÄRGER", "Oasis", "bob"];
+    names.sort_by(|a, b| compare_names(a, b));
+    assert_eq!(
+        names,
+        vec!["apple", "Apple", "ÄRGER", "bob", "Émile", "Oasis", "Øyvind", "zebra"]
+    );
+}
@@ -0,0 +1,186 @@
+//! Tests for rewriting MP3 tags to a clean ID3v2.4 set.
+//!
+//! Tests cover:
+//! - Junk frames, ID3v1 and APE tags and padding are dropped
+//! - Library metadata and the front cover are written, audio is untouched
+//! - Clean files are left alone on a second run
+//! - Content hashes follow the rewritten files
+
+use id3::frame::{Comment, Picture, PictureType, Private};
+use id3::{Tag, TagLike, Version};
+use jp3_organiser_lib::commands::library::{
+    initialize_library, load_library, save_to_library, verify_library, FileToSave,
+};
+use jp3_organiser_lib::commands::tag_cleanup::clean_library_tags;
+use jp3_organiser_lib::models::{AudioMetadata, MetadataSource};
+use jp3_organiser_lib::services::tag_cleanup_service::{
+    clean_file_tags, CleanTagFields, TagCleanupOutcome,
+};
+use jp3_organiser_lib::services::tag_reader_service::{read_file_tags, TagKind};
+
+/// One silent 128 kbps MPEG-1 Layer III frame, enough to be recognised as an MP3.
+const MP3_FRAME: [u8; 417] = mp3_frame();
+
+const fn mp3_frame() -> [u8; 417] {
+    let mut frame = [0; 417];
+    frame[0] = 0xFF;
+    frame[1] = 0xFB;
+    frame[2] = 0x90;
+    frame[3] = 0x64;
+    frame
+}
+
+/// An MP3 with a bloated ID3v2.3 tag, an APE tag and an ID3v1 tag.
+fn write_messy_mp3(path: &std::path::Path) {
+    std::fs::write(path, MP3_FRAME).unwrap();
+    let mut tag = Tag::new();
+    tag.set_title("Old Title");
+    tag.set_artist("Old Artist");
+    tag.set_genre("Junk");
+    tag.add_frame(Comment {
+        lang: "eng".to_string(),
+        description: String::new(),
+        text: "Downloaded from somewhere".repeat(100),
+    });
+    tag.add_frame(Private {
+        owner_identifier: "www.example.com".to_string(),
+        private_data: vec![0xAB; 4096],
+    });
+    tag.add_frame(Picture {
+        mime_type: "image/jpeg".to_string(),
+        picture_type: PictureType::Other,
+        description: "other".to_string(),
+        data: vec![1, 2, 3],
+    });
+    tag.add_frame(Picture {
+        mime_type: "image/png".to_string(),
+        picture_type: PictureType::CoverFront,
+        description: "front".to_string(),
+        data: vec![4, 5, 6],
+    });
+    tag.write_to_path(path, Version::Id3v23).unwrap();
+
+    let mut data = std::fs::read(path).unwrap();
+    // APEv2 footer with no items, then an ID3v1 tag
+    data.extend(b"APETAGEX");
+    data.extend(2000u32.to_le_bytes());
+    data.extend(32u32.to_le_bytes());
+    data.extend([0; 16]);
+    let mut id3v1 = b"TAG".to_vec();
+    id3v1.extend(b"V1 Title");
+    id3v1.resize(128, 0);
+    data.extend(id3v1);
+    std::fs::write(path, data).unwrap();
+}
+
+#[test]
+fn test_clean_file_tags() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("song.mp3");
+    write_messy_mp3(&path);
+
+    let fields = CleanTagFields {
+        title: "Title".to_string(),
+        artist: "Artist".to_string(),
+        album: "Album".to_string(),
+        track_number: 3,
+        year: 1999,
+    };
+    let outcome = clean_file_tags(&path, &fields).unwrap();
+    let TagCleanupOutcome::Cleaned {
+        bytes_before,
+        bytes_after,
+    } = outcome
+    else {
+        panic!("expected the file to be rewritten, got {:?}", outcome);
+    };
+    let data = std::fs::read(&path).unwrap();
+    assert_eq!(bytes_after, data.len() as u64);
+    assert!(bytes_after < bytes_before);
+    assert!(data.ends_with(&MP3_FRAME));
+
+    let tags = read_file_tags(&path).unwrap();
+    assert_eq!(tags.found, vec![TagKind::Id3v2]);
+    assert_eq!(tags.metadata.title.as_deref(), Some("Title"));
+    assert_eq!(
+        (tags.metadata.track_number, tags.metadata.year),
+        (Some(3), Some(1999))
+    );
+
+    let tag = Tag::read_from_path(&path).unwrap();
+    assert_eq!(tag.version(), Version::Id3v24);
+    let mut ids: Vec<&str> = tag.frames().map(|frame| frame.id()).collect();
+    ids.sort();
+    assert_eq!(ids, vec!["APIC", "TALB", "TDRC", "TIT2", "TPE1", "TRCK"]);
+    let cover = tag.pictures().next().unwrap();
+    assert_eq!(
+        (cover.picture_type, cover.data.as_slice()),
+        (PictureType::CoverFront, &[4, 5, 6][..])
+    );
+
+    // A second pass has nothing to do
+    assert_eq!(
+        clean_file_tags(&path, &fields).unwrap(),
+        TagCleanupOutcome::AlreadyClean
+    );
+    assert_eq!(std::fs::read(&path).unwrap(), data);
+}
+
+#[test]
+fn test_clean_library_tags() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+
+    let source = temp_dir.path().join("source.mp3");
+    write_messy_mp3(&source);
+    let other = temp_dir.path().join("other.flac");
+    std::fs::write(&other, b"fLaC").unwrap();
+    let files = [(&source, "Library Title"), (&other, "Lossless")]
+        .into_iter()
+        .map(|(path, title)| FileToSave {
+            source_path: path.to_string_lossy().to_string(),
+            metadata: AudioMetadata {
+                title: Some(title.to_string()),
+                artist: Some("Artist".to_string()),
+                album: Some("Album".to_string()),
+                track_number: Some(1),
+                year: Some(2010),
+                ..Default::default()
+            },
+            metadata_source: MetadataSource::Manual,
+            confidence: None,
+            fingerprint: None,
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let result = clean_library_tags(base_path.clone(), None).unwrap();
+    assert_eq!(
+        (result.songs_cleaned, result.skipped, result.failed),
+        (1, 1, 0)
+    );
+    assert!(result.bytes_after < result.bytes_before);
+
+    // The file now carries the library's metadata, and its hash still matches
+    let library = load_library(base_path.clone(), None).unwrap();
+    let song = library
+        .songs
+        .iter()
+        .find(|s| s.title == "Library Title")
+        .unwrap();
+    let path = temp_dir.path().join("jp3").join("music").join(&song.path);
+    let tags = read_file_tags(&path).unwrap();
+    assert_eq!(tags.metadata.title.as_deref(), Some("Library Title"));
+    assert_eq!(tags.metadata.year, Some(2010));
+    assert!(verify_library(base_path.clone())
+        .unwrap()
+        .hash_mismatch_song_ids
+        .is_empty());
+
+    let result = clean_library_tags(base_path, Some(vec![song.id])).unwrap();
+    assert_eq!(
+        (result.songs_cleaned, result.already_clean, result.skipped),
+        (0, 1, 0)
+    );
+}
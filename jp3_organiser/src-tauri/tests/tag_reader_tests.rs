@@ -0,0 +1,243 @@
+//! Tests for reading ID3v2, APE, ID3v1 and Vorbis comment tags.
+//!
+//! Tests cover:
+//! - Files with only an ID3v1 or only an APE tag
+//! - FLAC files with Vorbis comments
+//! - ID3v2.2 tags
+//! - Field precedence when several tags coexist
+//! - Untagged files and legacy-tagged imports coming out complete
+
+use std::path::{Path, PathBuf};
+
+use id3::TagLike;
+use jp3_organiser_lib::commands::audio::get_audio_metadata;
+use jp3_organiser_lib::models::MetadataStatus;
+use jp3_organiser_lib::services::tag_reader_service::{read_file_tags, TagKind};
+
+/// One silent 128 kbps MPEG-1 Layer III frame, enough to be recognised as an MP3.
+const MP3_FRAME: [u8; 417] = mp3_frame();
+
+const fn mp3_frame() -> [u8; 417] {
+    let mut frame = [0; 417];
+    frame[0] = 0xFF;
+    frame[1] = 0xFB;
+    frame[2] = 0x90;
+    frame[3] = 0x64;
+    frame
+}
+
+fn write_mp3(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, MP3_FRAME).unwrap();
+    path
+}
+
+fn append(path: &Path, bytes: &[u8]) {
+    let mut data = std::fs::read(path).unwrap();
+    data.extend_from_slice(bytes);
+    std::fs::write(path, data).unwrap();
+}
+
+/// A 128-byte ID3v1.1 tag.
+fn id3v1_tag(title: &str, artist: &str, album: &str, year: &str, track: u8, genre: u8) -> Vec<u8> {
+    let field = |text: &str, len: usize| {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.resize(len, 0);
+        bytes
+    };
+    let mut tag = b"TAG".to_vec();
+    tag.extend(field(title, 30));
+    tag.extend(field(artist, 30));
+    tag.extend(field(album, 30));
+    tag.extend(field(year, 4));
+    tag.extend(field("comment", 28));
+    tag.extend([0, track, genre]);
+    tag
+}
+
+/// An APEv2 tag (items and footer, no header) with text items.
+fn ape_tag(items: &[(&str, &str)]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (key, value) in items {
+        body.extend((value.len() as u32).to_le_bytes());
+        body.extend(0u32.to_le_bytes());
+        body.extend(key.as_bytes());
+        body.push(0);
+        body.extend(value.as_bytes());
+    }
+    let mut footer = b"APETAGEX".to_vec();
+    footer.extend(2000u32.to_le_bytes());
+    footer.extend((body.len() as u32 + 32).to_le_bytes());
+    footer.extend((items.len() as u32).to_le_bytes());
+    footer.extend(0u32.to_le_bytes());
+    footer.extend([0; 8]);
+    body.extend(footer);
+    body
+}
+
+/// A FLAC stream header with a Vorbis comment block and no audio frames.
+fn flac_with_comments(comments: &[&str]) -> Vec<u8> {
+    let mut data = b"fLaC".to_vec();
+
+    // STREAMINFO: 4096-sample blocks, 44.1 kHz, stereo, 16-bit
+    let mut info = Vec::new();
+    info.extend(4096u16.to_be_bytes());
+    info.extend(4096u16.to_be_bytes());
+    info.extend([0; 6]);
+    info.extend(((44_100u64 << 44) | (1 << 41) | (15 << 36)).to_be_bytes());
+    info.extend([0; 16]);
+    data.push(0);
+    data.extend(&(info.len() as u32).to_be_bytes()[1..]);
+    data.extend(info);
+
+    let mut block = Vec::new();
+    block.extend(4u32.to_le_bytes());
+    block.extend(b"test");
+    block.extend((comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        block.extend((comment.len() as u32).to_le_bytes());
+        block.extend(comment.as_bytes());
+    }
+    // Last block, type 4 (VORBIS_COMMENT)
+    data.push(0x84);
+    data.extend(&(block.len() as u32).to_be_bytes()[1..]);
+    data.extend(block);
+    data
+}
+
+#[test]
+fn test_read_id3v1_only() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = write_mp3(temp_dir.path(), "old.mp3");
+    append(&path, &id3v1_tag("Title", "Artist", "Album", "1998", 7, 17));
+
+    let tags = read_file_tags(&path).unwrap();
+    assert_eq!(tags.found, vec![TagKind::Id3v1]);
+    let metadata = tags.metadata;
+    assert_eq!(metadata.title.as_deref(), Some("Title"));
+    assert_eq!(metadata.artist.as_deref(), Some("Artist"));
+    assert_eq!(metadata.album.as_deref(), Some("Album"));
+    assert_eq!(
+        (metadata.year, metadata.track_number),
+        (Some(1998), Some(7))
+    );
+    assert_eq!(metadata.genre.as_deref(), Some("Rock"));
+
+    // Imported the same way as an ID3v2-tagged file
+    let file = get_audio_metadata(path.to_string_lossy().to_string()).unwrap();
+    assert_eq!(file.metadata_status, MetadataStatus::Complete);
+}
+
+#[test]
+fn test_read_ape_only() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = write_mp3(temp_dir.path(), "ape.mp3");
+    append(
+        &path,
+        &ape_tag(&[
+            ("TITLE", "Title"),
+            ("Artist", "Artist"),
+            ("Album", "Album"),
+            ("Album Artist", "Various Artists"),
+            ("Track", "3/12"),
+            ("Disc", "2/2"),
+            ("Year", "2004-05-01"),
+            ("Genre", "Jazz"),
+        ]),
+    );
+
+    let tags = read_file_tags(&path).unwrap();
+    assert_eq!(tags.found, vec![TagKind::Ape]);
+    let metadata = tags.metadata;
+    assert_eq!(metadata.title.as_deref(), Some("Title"));
+    assert_eq!(metadata.album_artist.as_deref(), Some("Various Artists"));
+    assert_eq!(
+        (metadata.track_number, metadata.disc_number),
+        (Some(3), Some(2))
+    );
+    assert_eq!(metadata.year, Some(2004));
+    assert_eq!(metadata.genre.as_deref(), Some("Jazz"));
+}
+
+#[test]
+fn test_read_flac_vorbis_comments() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("track.flac");
+    std::fs::write(
+        &path,
+        flac_with_comments(&[
+            "TITLE=Title",
+            "ARTIST=Artist",
+            "ALBUM=Album",
+            "ALBUMARTIST=Various Artists",
+            "TRACKNUMBER=4",
+            "DISCNUMBER=1",
+            "DATE=2011-03-02",
+            "GENRE=Ambient",
+        ]),
+    )
+    .unwrap();
+
+    let tags = read_file_tags(&path).unwrap();
+    assert_eq!(tags.found, vec![TagKind::Vorbis]);
+    let metadata = tags.metadata;
+    assert_eq!(metadata.title.as_deref(), Some("Title"));
+    assert_eq!(metadata.artist.as_deref(), Some("Artist"));
+    assert_eq!(metadata.album_artist.as_deref(), Some("Various Artists"));
+    assert_eq!(
+        (metadata.track_number, metadata.disc_number),
+        (Some(4), Some(1))
+    );
+    assert_eq!(metadata.year, Some(2011));
+    assert_eq!(metadata.genre.as_deref(), Some("Ambient"));
+
+    // No longer skipped on import
+    let file = get_audio_metadata(path.to_string_lossy().to_string()).unwrap();
+    assert_eq!(file.metadata_status, MetadataStatus::Complete);
+}
+
+#[test]
+fn test_tag_precedence() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = write_mp3(temp_dir.path(), "all.mp3");
+
+    // ID3v2.2 with just a title
+    let mut tag = id3::Tag::new();
+    tag.set_title("V2 Title");
+    tag.write_to_path(&path, id3::Version::Id3v22).unwrap();
+    append(
+        &path,
+        &ape_tag(&[("Title", "APE Title"), ("Artist", "APE Artist")]),
+    );
+    append(
+        &path,
+        &id3v1_tag("V1 Title", "V1 Artist", "V1 Album", "1990", 0, 255),
+    );
+
+    let tags = read_file_tags(&path).unwrap();
+    assert_eq!(
+        tags.found,
+        vec![TagKind::Id3v2, TagKind::Ape, TagKind::Id3v1]
+    );
+    let metadata = tags.metadata;
+    assert_eq!(metadata.title.as_deref(), Some("V2 Title"));
+    assert_eq!(metadata.artist.as_deref(), Some("APE Artist"));
+    assert_eq!(metadata.album.as_deref(), Some("V1 Album"));
+    assert_eq!(metadata.year, Some(1990));
+    // Track 0 and genre 255 mean unset in ID3v1
+    assert_eq!((metadata.track_number, metadata.genre), (None, None));
+}
+
+#[test]
+fn test_read_untagged_file() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = write_mp3(temp_dir.path(), "untagged.mp3");
+
+    let tags = read_file_tags(&path).unwrap();
+    assert!(tags.found.is_empty());
+    assert!(tags.metadata.title.is_none());
+
+    let file = get_audio_metadata(path.to_string_lossy().to_string()).unwrap();
+    assert_eq!(file.metadata_status, MetadataStatus::Incomplete);
+    assert!(file.error_message.is_none());
+}
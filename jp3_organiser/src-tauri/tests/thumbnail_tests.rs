@@ -0,0 +1,73 @@
+//! Tests for cover thumbnails and the background task queue.
+//!
+//! Tests cover:
+//! - Generating UI and device thumbnails without upscaling
+//! - Skipping albums with no cover
+//! - Background tasks running in submission order
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+use jp3_organiser_lib::services::cover_art_service::cover_filename;
+use jp3_organiser_lib::services::task_manager_service::TaskManager;
+use jp3_organiser_lib::services::thumbnail_service::{
+    generate_missing_thumbnails, queue_album_thumbnails, thumbnail_path, ThumbnailSize,
+};
+
+fn write_cover(albums_dir: &std::path::Path, artist: &str, album: &str, width: u32, height: u32) {
+    std::fs::create_dir_all(albums_dir).unwrap();
+    let image = image::RgbImage::from_pixel(width, height, image::Rgb([200, 30, 30]));
+    let path = albums_dir.join(format!("{}.jpg", cover_filename(artist, album)));
+    image.save_with_format(path, image::ImageFormat::Jpeg).unwrap();
+}
+
+#[test]
+fn test_generate_missing_thumbnails() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let albums_dir = temp_dir.path().join("albums");
+    write_cover(&albums_dir, "Artist", "Album", 600, 400);
+
+    assert_eq!(generate_missing_thumbnails(&albums_dir, "Artist", "Album").unwrap(), 2);
+    // Already generated
+    assert_eq!(generate_missing_thumbnails(&albums_dir, "Artist", "Album").unwrap(), 0);
+    // No cover yet
+    assert_eq!(generate_missing_thumbnails(&albums_dir, "Artist", "Other").unwrap(), 0);
+
+    let ui = image::open(thumbnail_path(&albums_dir, "Artist", "Album", ThumbnailSize::Ui)).unwrap();
+    assert_eq!((ui.width(), ui.height()), (200, 133));
+    let device =
+        image::open(thumbnail_path(&albums_dir, "Artist", "Album", ThumbnailSize::Device)).unwrap();
+    assert_eq!(device.width(), 240);
+
+    // Small covers are not upscaled
+    write_cover(&albums_dir, "Tiny", "Cover", 100, 100);
+    generate_missing_thumbnails(&albums_dir, "Tiny", "Cover").unwrap();
+    let tiny = image::open(thumbnail_path(&albums_dir, "Tiny", "Cover", ThumbnailSize::Device)).unwrap();
+    assert_eq!((tiny.width(), tiny.height()), (100, 100));
+}
+
+#[test]
+fn test_thumbnails_generated_in_background() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let albums_dir = temp_dir.path().join("jp3").join("assets").join("albums");
+    write_cover(&albums_dir, "Artist", "Album", 500, 500);
+
+    let tasks = TaskManager::default();
+    queue_album_thumbnails(
+        &tasks,
+        temp_dir.path(),
+        vec![("Artist".to_string(), "Album".to_string())],
+    );
+
+    // Tasks run in order, so this one finishes after the thumbnails
+    let (done_tx, done_rx) = mpsc::channel();
+    tasks.submit("marker", move || {
+        done_tx.send(()).unwrap();
+        Ok(())
+    });
+    done_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+
+    for size in ThumbnailSize::ALL {
+        assert!(thumbnail_path(&albums_dir, "Artist", "Album", size).exists());
+    }
+}
@@ -0,0 +1,23 @@
+//! Tests for re-encoding helpers.
+//!
+//! Tests cover:
+//! - Preview clip ranges: default cap, end of song, invalid input
+
+use jp3_organiser_lib::services::transcode_service::{clip_range, MAX_CLIP_SECS};
+
+#[test]
+fn test_clip_range() {
+    assert_eq!(clip_range(45.0, 30.0, 200), Ok((45.0, 30.0)));
+
+    // Capped, and cut at the end of the song
+    assert_eq!(clip_range(0.0, 600.0, 200), Ok((0.0, MAX_CLIP_SECS)));
+    assert_eq!(clip_range(190.0, 30.0, 200), Ok((190.0, 10.0)));
+
+    // Unknown song length: only the cap applies
+    assert_eq!(clip_range(500.0, 30.0, 0), Ok((500.0, 30.0)));
+
+    assert!(clip_range(200.0, 30.0, 200).is_err());
+    assert!(clip_range(-1.0, 30.0, 200).is_err());
+    assert!(clip_range(0.0, 0.0, 200).is_err());
+    assert!(clip_range(f32::NAN, 30.0, 200).is_err());
+}
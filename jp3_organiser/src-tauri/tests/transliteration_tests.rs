@@ -0,0 +1,121 @@
+//! Tests for script detection and transliteration keys.
+//!
+//! Tests cover:
+//! - Detecting the dominant script of a string
+//! - Romanizing Cyrillic and kana, leaving Latin names alone
+//! - Script distribution in library stats
+//! - Enabling transliteration and user overrides (kanji readings)
+
+use jp3_organiser_lib::commands::enrichment::{
+    set_transliteration_enabled, set_transliteration_override,
+};
+use jp3_organiser_lib::commands::library::{
+    get_library_stats, initialize_library, load_library, save_to_library, FileToSave,
+};
+use jp3_organiser_lib::models::{AudioMetadata, MetadataSource, Script};
+use jp3_organiser_lib::services::transliteration_service::{detect_script, romanize};
+
+fn setup_library_with(songs: &[(&str, &str, &str)]) -> (tempfile::TempDir, String) {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+
+    let files = songs
+        .iter()
+        .enumerate()
+        .map(|(i, (title, artist, album))| {
+            let source = temp_dir.path().join(format!("{}.mp3", i));
+            std::fs::write(&source, format!("fake audio {}", i)).unwrap();
+            FileToSave {
+                source_path: source.to_string_lossy().to_string(),
+                metadata: AudioMetadata {
+                    title: Some(title.to_string()),
+                    artist: Some(artist.to_string()),
+                    album: Some(album.to_string()),
+                    year: Some(2000),
+                    track_number: Some(1),
+                    duration_secs: Some(200),
+                    release_mbid: None,
+                    artist_mbid: None,
+                    genre: None,
+                    album_artist: None,
+                    disc_number: None,
+                    rating: None,
+                    play_count: None,
+                },
+                metadata_source: MetadataSource::Unknown,
+                confidence: None,
+                fingerprint: None,
+            }
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+    (temp_dir, base_path)
+}
+
+#[test]
+fn test_detect_script_and_romanize() {
+    assert_eq!(detect_script("Sigur Rós"), Some(Script::Latin));
+    assert_eq!(detect_script("Кино"), Some(Script::Cyrillic));
+    assert_eq!(detect_script("椎名林檎"), Some(Script::Han));
+    assert_eq!(detect_script("ここでキスして。"), Some(Script::Kana));
+    assert_eq!(detect_script("無罪モラトリアム"), Some(Script::Kana));
+    assert_eq!(detect_script("방탄소년단"), Some(Script::Hangul));
+    assert_eq!(detect_script("1999"), None);
+
+    assert_eq!(romanize("Кино").as_deref(), Some("kino"));
+    assert_eq!(romanize("しいな").as_deref(), Some("shiina"));
+    // Hepburn combinations, small tsu and the long vowel mark
+    assert_eq!(romanize("きゃりーぱみゅぱみゅ").as_deref(), Some("kyaripamyupamyu"));
+    assert_eq!(romanize("ちょっと").as_deref(), Some("chotto"));
+    assert_eq!(romanize("ファンタジー").as_deref(), Some("fantaji"));
+    assert_eq!(romanize("Sigur Rós"), None);
+}
+
+#[test]
+fn test_stats_report_script_distribution() {
+    let (_temp_dir, base_path) = setup_library_with(&[
+        ("Группа крови", "Кино", "Группа крови"),
+        ("Hoppípolla", "Sigur Rós", "Takk..."),
+    ]);
+
+    let stats = get_library_stats(base_path).unwrap();
+    let counts: Vec<_> = stats
+        .script_distribution
+        .iter()
+        .map(|c| (c.script, c.count))
+        .collect();
+    // "Группа крови" is both a title and an album but one string
+    assert_eq!(counts, vec![(Script::Latin, 3), (Script::Cyrillic, 2)]);
+}
+
+#[test]
+fn test_transliteration_keys_and_overrides() {
+    let (_temp_dir, base_path) =
+        setup_library_with(&[("丸の内サディスティック", "椎名林檎", "無罪モラトリアム")]);
+
+    // Disabled by default: no keys
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.artists[0].romanized, None);
+
+    let result = set_transliteration_enabled(base_path.clone(), true).unwrap();
+    assert_eq!(result.keys_generated, 3);
+
+    // Kanji get a (Mandarin) reading until the user supplies the Japanese one
+    set_transliteration_override(
+        base_path.clone(),
+        "椎名林檎".to_string(),
+        Some("Shiina Ringo".to_string()),
+    )
+    .unwrap();
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    assert_eq!(library.artists[0].romanized.as_deref(), Some("shiina ringo"));
+    assert!(library.albums[0].romanized.is_some());
+    assert!(library.songs[0].romanized_title.is_some());
+
+    // Keys stay stored but are hidden while disabled
+    set_transliteration_enabled(base_path.clone(), false).unwrap();
+    let library = load_library(base_path, None).unwrap();
+    assert_eq!(library.artists[0].romanized, None);
+}
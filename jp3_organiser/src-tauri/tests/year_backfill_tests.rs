@@ -0,0 +1,95 @@
+//! Tests for album year backfill.
+//!
+//! Tests cover:
+//! - Patching album years in library.bin in place
+//! - Picking the earliest year from MusicBrainz dates
+//! - Caching known and unknown years
+
+use std::path::Path;
+
+use jp3_organiser_lib::commands::library::{
+    initialize_library, load_library, patch_album_years, save_to_library, FileToSave,
+};
+use jp3_organiser_lib::models::{AudioMetadata, MetadataSource};
+use jp3_organiser_lib::services::musicbrainz_service::{release_year, ReleaseSearchResult};
+use jp3_organiser_lib::services::year_backfill_service::{
+    cache_year, cached_year, earliest_year, load_year_cache, save_year_cache,
+};
+
+fn release(date: Option<&str>) -> ReleaseSearchResult {
+    ReleaseSearchResult {
+        release_mbid: "mbid".to_string(),
+        title: "Album".to_string(),
+        artist: None,
+        score: 100,
+        date: date.map(|d| d.to_string()),
+        country: None,
+        track_count: None,
+    }
+}
+
+#[test]
+fn test_patch_album_years_in_place() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+
+    let files: Vec<FileToSave> = [("One", "No Year"), ("Two", "Dated")]
+        .iter()
+        .map(|(title, album)| {
+            let source = temp_dir.path().join(format!("{}.mp3", title));
+            std::fs::write(&source, format!("fake audio data {}", title)).unwrap();
+            FileToSave {
+                source_path: source.to_string_lossy().to_string(),
+                metadata: AudioMetadata {
+                    title: Some(title.to_string()),
+                    artist: Some("Artist".to_string()),
+                    album: Some(album.to_string()),
+                    year: if *album == "Dated" { Some(1999) } else { None },
+                    ..Default::default()
+                },
+                metadata_source: MetadataSource::Unknown,
+                confidence: None,
+                fingerprint: None,
+            }
+        })
+        .collect();
+    save_to_library(base_path.clone(), files).unwrap();
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    let undated = library.albums.iter().find(|a| a.year == 0).unwrap().id;
+
+    assert_eq!(patch_album_years(&base_path, &[(undated, 1984), (99, 2000)]).unwrap(), 1);
+
+    let library = load_library(base_path.clone(), None).unwrap();
+    let years: Vec<_> = library.albums.iter().map(|a| (a.name.as_str(), a.year)).collect();
+    assert!(years.contains(&("No Year", 1984)));
+    assert!(years.contains(&("Dated", 1999)));
+    assert!(library.songs.iter().any(|s| s.title == "One" && s.year == 1984));
+}
+
+#[test]
+fn test_earliest_year_and_cache() {
+    assert_eq!(release_year("1979-11-30"), Some(1979));
+    assert_eq!(release_year("2001"), Some(2001));
+    assert_eq!(release_year(""), None);
+
+    let releases = vec![release(Some("2011-03")), release(None), release(Some("1979"))];
+    assert_eq!(earliest_year(&releases), Some(1979));
+    assert_eq!(earliest_year(&[release(None)]), None);
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_string_lossy().to_string();
+    initialize_library(base_path.clone()).unwrap();
+    let base = Path::new(&base_path);
+
+    let mut cache = load_year_cache(base).unwrap();
+    cache_year(&mut cache, "Artist", "Known", 1979);
+    cache_year(&mut cache, "Artist", "Unknown", 0);
+    save_year_cache(base, &cache).unwrap();
+
+    let cache = load_year_cache(base).unwrap();
+    assert_eq!(cached_year(&cache, "artist", "known"), Some(1979));
+    assert_eq!(cached_year(&cache, "Artist", "Unknown"), Some(0));
+    assert_eq!(cached_year(&cache, "Artist", "Never Looked Up"), None);
+}
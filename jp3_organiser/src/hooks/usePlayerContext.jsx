@@ -23,9 +23,11 @@ const PlayerContext = createContext(null);
 /**
  * Construct absolute path for audio files in the library.
  * Library structure: {libraryPath}/jp3/music/{relativePath}
+ * Songs adopted in place (scanAndAdopt) already have an absolute path.
  */
 function resolveAudioPath(libraryPath, relativePath) {
   if (!libraryPath || !relativePath) return null;
+  if (/^([a-zA-Z]:)?[\\/]/.test(relativePath)) return relativePath;
   // Handle both forward and back slashes for cross-platform
   const base = libraryPath.replace(/\\/g, '/').replace(/\/$/, '');
   const rel = relativePath.replace(/\\/g, '/').replace(/^\//, '');
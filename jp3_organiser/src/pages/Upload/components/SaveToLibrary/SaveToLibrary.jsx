@@ -181,6 +181,8 @@ export default function SaveToLibrary({ libraryPath, workflow, toast }) {
       const files = filesToSave.map(f => ({
         sourcePath: f.filePath,
         metadata: f.metadata,
+        metadataSource: f.metadataSource,
+        confidence: f.confidence,
       }));
 
       let message;
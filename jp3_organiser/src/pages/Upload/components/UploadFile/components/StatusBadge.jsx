@@ -8,6 +8,7 @@
  * - Automated: Has complete metadata from ID3/fingerprint, awaiting review
  * - Incomplete: Missing required fields
  * - Error: Failed to process
+ * - Protected: Copy-protected (DRM), can't be imported
  * - Pending: Waiting to be processed
  */
 
@@ -20,6 +21,7 @@ const STATUS_CONFIG = {
   automated: { className: styles.statusAutomated, label: 'Automated' },
   [MetadataStatus.INCOMPLETE]: { className: styles.statusIncomplete, label: 'Incomplete' },
   [MetadataStatus.ERROR]: { className: styles.statusError, label: 'Error' },
+  [MetadataStatus.PROTECTED_FILE]: { className: styles.statusError, label: 'Protected' },
   [MetadataStatus.PENDING]: { className: styles.statusPending, label: 'Pending' },
 };
 
@@ -27,6 +27,8 @@ export const MetadataStatus = {
   INCOMPLETE: 'incomplete',
   /** Failed to read file or parse metadata */
   ERROR: 'error',
+  /** Copy-protected (DRM); can't be played on the device or saved */
+  PROTECTED_FILE: 'protectedFile',
 };
 
 /**
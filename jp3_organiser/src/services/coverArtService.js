@@ -99,29 +99,70 @@ export async function fetchArtistCover(basePath, artist) {
  * Read album cover image bytes for displaying in frontend
  * 
  * Useful when you need raw image data for blob URLs.
- * Returns the image as a Uint8Array.
+ * Returns the image as an ArrayBuffer.
  * Uses artist+album hash for stable filename lookup.
  * 
  * @param {string} basePath - Library base path
  * @param {string} artist - Artist name
  * @param {string} album - Album name
- * @returns {Promise<Uint8Array>} Image bytes
+ * @returns {Promise<ArrayBuffer>} Image bytes
  * @throws {Error} If cover not found
  */
 export async function readAlbumCover(basePath, artist, album) {
   return await invoke('read_album_cover', { basePath, artist, album });
 }
 
+/**
+ * Read many album covers in one call (e.g. a page of the album grid)
+ * 
+ * Covers are keyed by hash, so duplicate requests are read once. Albums
+ * without a cover are listed in `missing`; once the batch's size cap is
+ * reached the rest are listed in `deferred` to request again.
+ * 
+ * @param {string} basePath - Library base path
+ * @param {Array<{artist: string, album: string, size?: 'ui'|'device'}>} requests - Albums to read, full cover unless a thumbnail size is given
+ * @returns {Promise<{covers: Map<string, Uint8Array>, missing: string[], deferred: string[]}>}
+ */
+export async function readAlbumCoversBatch(basePath, requests) {
+  const frame = await invoke('read_album_covers_batch', { basePath, requests });
+  return decodeCoversFrame(frame);
+}
+
+/**
+ * Decode the binary frame read_album_covers_batch returns
+ * 
+ * Layout: u32 (little-endian) index length, a JSON index
+ * ({covers: [{hash, length}], missing, deferred}), then the image bytes
+ * in index order.
+ * 
+ * @param {ArrayBuffer|Uint8Array} frame - Raw command response
+ * @returns {{covers: Map<string, Uint8Array>, missing: string[], deferred: string[]}}
+ */
+function decodeCoversFrame(frame) {
+  const bytes = frame instanceof Uint8Array ? frame : new Uint8Array(frame);
+  const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+  const indexLength = view.getUint32(0, true);
+  const index = JSON.parse(new TextDecoder().decode(bytes.subarray(4, 4 + indexLength)));
+
+  const covers = new Map();
+  let offset = 4 + indexLength;
+  for (const { hash, length } of index.covers) {
+    covers.set(hash, bytes.subarray(offset, offset + length));
+    offset += length;
+  }
+  return { covers, missing: index.missing, deferred: index.deferred };
+}
+
 /**
  * Read artist cover image bytes for displaying in frontend
  * 
  * Useful when you need raw image data for blob URLs.
- * Returns the image as a Uint8Array.
+ * Returns the image as an ArrayBuffer.
  * Uses artist hash for stable filename lookup.
  * 
  * @param {string} basePath - Library base path
  * @param {string} artist - Artist name
- * @returns {Promise<Uint8Array>} Image bytes
+ * @returns {Promise<ArrayBuffer>} Image bytes
  * @throws {Error} If cover not found
  */
 export async function readArtistCover(basePath, artist) {
@@ -171,15 +212,15 @@ export async function getArtistCoverBlobUrl(basePath, artist) {
 
 /**
  * Convert raw bytes to a blob URL
- * @param {Uint8Array|number[]} bytes - Image bytes
+ * @param {ArrayBuffer|Uint8Array|number[]} bytes - Image bytes
  * @returns {string|null} Blob URL or null if conversion fails
  */
 function bytesToBlobUrl(bytes) {
-  // Tauri returns an array of numbers, need to convert to Uint8Array
+  // Cover commands return a raw ArrayBuffer; older builds returned an array of numbers
   let uint8Array;
   if (bytes instanceof Uint8Array) {
     uint8Array = bytes;
-  } else if (Array.isArray(bytes)) {
+  } else if (bytes instanceof ArrayBuffer || Array.isArray(bytes)) {
     uint8Array = new Uint8Array(bytes);
   } else {
     console.log('[coverArtService] Unexpected bytes type:', typeof bytes);
@@ -202,3 +243,17 @@ function bytesToBlobUrl(bytes) {
 export async function clearCoverCache(basePath) {
   return await invoke('clear_cover_cache', { basePath });
 }
+
+/**
+ * Rename covers earlier builds saved under album/artist IDs to their hash names.
+ * 
+ * Runs once per library (the app also runs it at startup for the saved library);
+ * later calls return `alreadyMigrated: true`. If the library was compacted since
+ * the covers were written, nothing is renamed (`skippedAfterCompaction: true`).
+ * 
+ * @param {string} basePath - Library base path
+ * @returns {Promise<{alreadyMigrated: boolean, skippedAfterCompaction: boolean, albumsRenamed: number, artistsRenamed: number, duplicatesRemoved: number, unmatched: number, failed: number}>}
+ */
+export async function migrateLegacyCovers(basePath) {
+  return await invoke('migrate_legacy_covers', { basePath });
+}
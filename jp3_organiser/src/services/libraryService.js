@@ -9,7 +9,7 @@
  * {libraryPath}/
  *   jp3/
  *     music/
- *       00/  (buckets, max 256 songs each)
+ *       00/  (buckets, max 256 songs each by default; see setBucketLayout)
  *       01/
  *       ...
  *     metadata/
@@ -18,6 +18,18 @@
  */
 
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+
+/** Events the library commands emit whenever they change the library */
+export const LIBRARY_EVENTS = {
+  SONGS_ADDED: 'library://songs-added',
+  SONGS_DELETED: 'library://songs-deleted',
+  SONGS_UPDATED: 'library://songs-updated',
+  ALBUM_UPDATED: 'library://album-updated',
+  ARTIST_UPDATED: 'library://artist-updated',
+  PLAYLIST_CHANGED: 'library://playlist-changed',
+  COMPACTED: 'library://compacted',
+};
 
 /**
  * Get the saved library path from persistent storage
@@ -85,6 +97,9 @@ export async function getLibraryInfo(basePath) {
  * @typedef {Object} FileToSave
  * @property {string} sourcePath - Original file path
  * @property {Object} metadata - Metadata object with title, artist, album, etc.
+ * @property {string} [metadataSource] - Where the metadata came from (id3, fingerprint, manual, folderStructure)
+ * @property {Object} [confidence] - Match confidence (acoustidScore, acoustidSources, musicbrainzScore)
+ * @property {Array} [fingerprint] - [fingerprint, durationSecs] from fpcalc, for duplicate detection
  * 
  * @typedef {Object} SaveToLibraryResult
  * @property {number} filesSaved - Number of files copied
@@ -92,6 +107,9 @@ export async function getLibraryInfo(basePath) {
  * @property {number} albumsAdded - Number of albums in library
  * @property {number} songsAdded - Number of songs in library
  * @property {number} duplicatesSkipped - Number of duplicate songs skipped
+ * @property {number} protectedSkipped - Number of copy-protected (DRM) files not saved
+ * @property {number} missingSkipped - Number of files skipped because their source was gone
+ * @property {number[]} savedFiles - Positions (in `files`) of the files that were saved
  * @property {number[]} songIds - IDs of the newly saved songs
  * @property {number[]} duplicateSongIds - IDs of existing songs that were duplicates (for playlists)
  */
@@ -105,7 +123,11 @@ export async function saveToLibrary(basePath, files) {
  * This reads directly from the file on disk, parsing it
  * exactly as the ESP32 would (not from locally cached memory).
  * 
+ * Songs are in ID order unless a sort is given. Text keys ignore case,
+ * accents and a leading "The"/"A"/"An", matching the device's order.
+ * 
  * @param {string} basePath - The base library directory path
+ * @param {{key: 'title'|'artist'|'album'|'year'|'duration'|'dateAdded', descending?: boolean}|null} [sort] - Song order
  * @returns {Promise<ParsedLibrary>} Parsed library data
  * 
  * @typedef {Object} ParsedArtist
@@ -136,16 +158,129 @@ export async function saveToLibrary(basePath, files) {
  * @property {ParsedAlbum[]} albums - All albums
  * @property {ParsedSong[]} songs - All songs
  */
-export async function loadLibrary(basePath) {
-  return await invoke('load_library', { basePath });
+export async function loadLibrary(basePath, sort = null) {
+  return await invoke('load_library', { basePath, sort });
+}
+
+/**
+ * Load one page of a library list instead of the whole library.
+ * 
+ * Pass the returned snapshot back for later pages; if the library changed
+ * in between, the call fails with a SnapshotExpired error.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {'artists'|'albums'|'songs'|'recentlyAdded'} kind - Which list to page through
+ * @param {number} offset - Index of the first item
+ * @param {number} limit - Maximum items to return
+ * @param {string|null} [snapshot] - Snapshot from the first page
+ * @param {{artistId?: number, albumId?: number}|null} [filter] - Only this artist's or album's rows
+ * @returns {Promise<{snapshot: string, offset: number, total: number, artists: Array, albums: Array, songs: Array}>}
+ */
+export async function loadLibraryPage(basePath, kind, offset, limit, snapshot = null, filter = null) {
+  return await invoke('load_library_page', { basePath, kind, offset, limit, snapshot, filter });
+}
+
+/**
+ * Subscribe to library change events from any view.
+ * 
+ * The callback gets the event name (see LIBRARY_EVENTS) and its payload;
+ * `generation` can be passed to `loadLibraryDelta` to refresh.
+ * 
+ * @param {(event: string, payload: {basePath: string, generation: number, songIds: number[], albumIds: number[], artistIds: number[], playlistIds: number[]}) => void} callback
+ * @returns {Promise<() => void>} Unsubscribe function
+ */
+export async function onLibraryChange(callback) {
+  const unlisteners = await Promise.all(
+    Object.values(LIBRARY_EVENTS).map((event) =>
+      listen(event, ({ payload }) => callback(event, payload))
+    )
+  );
+  return () => unlisteners.forEach((unlisten) => unlisten());
+}
+
+/**
+ * Load only what changed since a library generation the frontend already has.
+ * 
+ * Pass the `generation` from the last `loadLibrary` (or delta). Replace
+ * entries by ID and drop the removed IDs; if `full` is set, the lists are
+ * the whole library instead.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number} sinceGeneration - Generation already loaded
+ * @returns {Promise<{generation: number, full: boolean, artists: Array, albums: Array, songs: Array, removedArtistIds: number[], removedAlbumIds: number[], removedSongIds: number[], genres: Array}>}
+ */
+export async function loadLibraryDelta(basePath, sinceGeneration) {
+  return await invoke('load_library_delta', { basePath, sinceGeneration });
+}
+
+/**
+ * Everything an album page shows, without loading the whole library.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number} albumId - Album ID
+ * @returns {Promise<{album: Object, songs: Array, totalDurationSec: number, coverPath: string|null, sizeBytes: number}>}
+ */
+export async function getAlbum(basePath, albumId) {
+  return await invoke('get_album', { basePath, albumId });
+}
+
+/**
+ * Everything an artist page shows, without loading the whole library.
+ * 
+ * Songs include other artists' tracks on this artist's albums, in album
+ * order (oldest first).
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number} artistId - Artist ID
+ * @returns {Promise<{artist: Object, albums: Array, songs: Array, firstYear: number|null, lastYear: number|null, totalDurationSec: number, coverPath: string|null, sizeBytes: number}>}
+ */
+export async function getArtist(basePath, artistId) {
+  return await invoke('get_artist', { basePath, artistId });
+}
+
+/**
+ * Search artists, albums and songs by name.
+ * 
+ * Matching ignores case and accents; songs also match on their artist and
+ * album. The backend keeps an index per library, so this is cheap to call
+ * on every keystroke.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {string} query - Search text
+ * @param {number|null} [limit] - Maximum results per category (default 5)
+ * @returns {Promise<{artists: Array, albums: Array, songs: Array, totalCount: number}>}
+ */
+export async function searchLibrary(basePath, query, limit = null) {
+  return await invoke('search_library', { basePath, query, limit });
+}
+
+/**
+ * Report what deleteSongs would do, without deleting anything.
+ * 
+ * Use it to warn before a delete that empties or shrinks playlists.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number[]} songIds - Song IDs about to be deleted
+ * @returns {Promise<DeletePreview>}
+ * 
+ * @typedef {Object} DeletePreview
+ * @property {number} songs - Songs that would be marked as deleted
+ * @property {number[]} notFound - IDs not in the library (or already deleted)
+ * @property {number} files - Audio files that would move to the trash
+ * @property {number} bytes - Size of those files
+ * @property {{playlistId: number, name: string, songsAffected: number, songsRemaining: number}[]} playlists - Playlists that would lose songs
+ */
+export async function previewDelete(basePath, songIds) {
+  return await invoke('preview_delete', { basePath, songIds });
 }
 
 /**
  * Soft delete songs by their IDs.
  * 
  * This marks the song as deleted in library.bin (minimal binary write) AND
- * deletes the actual audio file from music/ (frees disk space immediately).
- * Use `compactLibrary` to reclaim metadata space in library.bin.
+ * moves the audio file from music/ to the trash (jp3/.trash), where it stays
+ * for the library's retention period. Use `compactLibrary` to reclaim
+ * metadata space in library.bin.
  * 
  * @param {string} basePath - The base library directory path
  * @param {number[]} songIds - Array of song IDs to delete
@@ -154,16 +289,126 @@ export async function loadLibrary(basePath) {
  * @typedef {Object} DeleteSongsResult
  * @property {number} songsDeleted - Number of songs successfully marked as deleted
  * @property {number[]} notFound - Song IDs that were not found
- * @property {number} filesDeleted - Number of audio files deleted from music/
+ * @property {number} filesDeleted - Number of audio files moved to the trash
  */
 export async function deleteSongs(basePath, songIds) {
   return await invoke('delete_songs', { basePath, songIds });
 }
 
+/**
+ * Undo a soft delete, until compaction drops the songs.
+ * 
+ * Audio files are moved back from the trash; songs whose file has been
+ * purged from the trash stay deleted.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number[]} songIds - Array of song IDs to restore
+ * @returns {Promise<RestoreSongsResult>} Result with restore counts
+ * 
+ * @typedef {Object} RestoreSongsResult
+ * @property {number} songsRestored - Number of songs no longer marked as deleted
+ * @property {number[]} notDeleted - Song IDs that don't exist or aren't deleted
+ * @property {number[]} missingFiles - Deleted songs whose audio file is gone
+ */
+export async function restoreSongs(basePath, songIds) {
+  return await invoke('restore_songs', { basePath, songIds });
+}
+
+/**
+ * List audio files in the trash, most recently deleted first.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<TrashEntry[]>}
+ * 
+ * @typedef {Object} TrashEntry
+ * @property {string} id - Trash entry ID
+ * @property {string} originalPath - Where the file was, relative to jp3/music
+ * @property {string|null} title - Title of the song it belonged to
+ * @property {number} deletedAt - When it was deleted (Unix seconds)
+ * @property {number} sizeBytes - File size
+ */
+export async function listTrash(basePath) {
+  return await invoke('list_trash', { basePath });
+}
+
+/**
+ * Move trashed files back and undo the delete of their songs.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {string[]} entryIds - Trash entry IDs to restore
+ * @returns {Promise<{filesRestored: number, songsRestored: number, notRestored: string[]}>}
+ */
+export async function restoreFromTrash(basePath, entryIds) {
+  return await invoke('restore_from_trash', { basePath, entryIds });
+}
+
+/**
+ * Permanently remove everything in the trash.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<{filesRemoved: number, bytesFreed: number}>}
+ */
+export async function emptyTrash(basePath) {
+  return await invoke('empty_trash', { basePath });
+}
+
+/**
+ * Get how many days deleted audio stays in the trash.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<number|null>} Days (default 30), or null to keep until emptied
+ */
+export async function getTrashRetention(basePath) {
+  return await invoke('get_trash_retention', { basePath });
+}
+
+/**
+ * Set how many days deleted audio stays in the trash.
+ * 
+ * Expired files are purged the next time songs are deleted.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number|null} days - Days to keep, or null to keep until emptied
+ * @returns {Promise<void>}
+ */
+export async function setTrashRetention(basePath, days) {
+  return await invoke('set_trash_retention', { basePath, days });
+}
+
+/**
+ * Get how audio files are laid out in jp3/music.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<{filesPerBucket: number, twoLevel: boolean, fileNames: string}>} Default 256 files, one level, sequential names
+ */
+export async function getBucketLayout(basePath) {
+  return await invoke('get_bucket_layout', { basePath });
+}
+
+/**
+ * Set the bucket layout for songs saved from now on.
+ * 
+ * With twoLevel, buckets are grouped as 00/00/, 00/01/, ... (filesPerBucket
+ * buckets per group) for firmware or cards that handle few entries per
+ * folder. Existing songs move into the new layout when the library is
+ * compacted with file renumbering.
+ * 
+ * fileNames is 'sequential' (001.mp3), 'trackTitle' (01 - Title.mp3) or
+ * 'artistTitle' (Artist - Title.mp3). Song-based names are made safe for
+ * FAT cards, and keep their name when compaction renumbers files.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {{filesPerBucket: number, twoLevel: boolean, fileNames: string}} layout - filesPerBucket from 1 to 4096
+ * @returns {Promise<void>}
+ */
+export async function setBucketLayout(basePath, layout) {
+  return await invoke('set_bucket_layout', { basePath, layout });
+}
+
 /**
  * Delete all songs belonging to an album.
  * 
- * This soft-deletes all songs in the album AND deletes their audio files.
+ * This soft-deletes all songs in the album AND moves their audio files to the trash.
  * Use `compactLibrary` to clean up orphaned albums/artists afterward.
  * 
  * @param {string} basePath - The base library directory path
@@ -172,7 +417,7 @@ export async function deleteSongs(basePath, songIds) {
  * 
  * @typedef {Object} DeleteAlbumResult
  * @property {number} songsDeleted - Number of songs deleted
- * @property {number} filesDeleted - Number of audio files deleted
+ * @property {number} filesDeleted - Number of audio files moved to the trash
  * @property {string} albumName - Name of the deleted album
  * @property {string} artistName - Name of the album's artist
  */
@@ -183,7 +428,7 @@ export async function deleteAlbum(basePath, albumId) {
 /**
  * Delete all songs belonging to an artist.
  * 
- * This soft-deletes all songs by the artist AND deletes their audio files.
+ * This soft-deletes all songs by the artist AND moves their audio files to the trash.
  * Use `compactLibrary` to clean up orphaned albums/artists afterward.
  * 
  * @param {string} basePath - The base library directory path
@@ -192,7 +437,7 @@ export async function deleteAlbum(basePath, albumId) {
  * 
  * @typedef {Object} DeleteArtistResult
  * @property {number} songsDeleted - Number of songs deleted
- * @property {number} filesDeleted - Number of audio files deleted
+ * @property {number} filesDeleted - Number of audio files moved to the trash
  * @property {number} albumsAffected - Number of albums affected
  * @property {string} artistName - Name of the deleted artist
  */
@@ -216,18 +461,504 @@ export async function deleteArtist(basePath, artistId) {
  * @property {number} totalAlbums - Total albums
  * @property {number} totalStrings - Total strings in string table
  * @property {number} deletedPercentage - Percentage of deleted songs (0-100)
- * @property {boolean} shouldCompact - Recommended to compact (deleted > 20%)
+ * @property {number} deadStringBytes - Bytes of strings nothing kept by a compaction refers to
+ * @property {number} wastedBytes - Bytes compaction would reclaim
+ * @property {boolean} shouldCompact - Recommended to compact (a compaction threshold was crossed)
+ * @property {'deletedPercentage'|'deadStringBytes'|'wastedBytes'|null} compactReason - First threshold crossed
  * @property {number} fileSizeBytes - File size in bytes
  */
 export async function getLibraryStats(basePath) {
   return await invoke('get_library_stats', { basePath });
 }
 
+/**
+ * Check library.bin for damage without changing it.
+ * 
+ * Only a missing or unreadable library.bin rejects; everything else is
+ * reported.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<LibraryVerificationReport>}
+ * 
+ * @typedef {Object} LibraryVerificationReport
+ * @property {number|null} version - Format version
+ * @property {string|null} headerError - Why the header couldn't be used
+ * @property {boolean} checksumValid - Whether the table checksum matched
+ * @property {string[]} layoutErrors - Tables overlapping or past the end of the file
+ * @property {string|null} stringTableError - Why the string table couldn't be read to its end
+ * @property {number[]} invalidUtf8StringIds - Strings that are not valid UTF-8
+ * @property {{table: string, entryId: number, field: string, value: number}[]} badReferences - Out-of-range IDs
+ * @property {number[]} missingFileSongIds - Active songs whose audio file is missing
+ * @property {number[]} hashMismatchSongIds - Active songs whose audio file changed since it was saved
+ */
+export async function verifyLibrary(basePath) {
+  return await invoke('verify_library', { basePath });
+}
+
+/**
+ * Hash the audio of songs saved before content hashes were recorded.
+ * 
+ * Hashes (jp3/metadata/song_hashes.json) let saves skip audio that is
+ * already in the library under other tags, and let verifyLibrary spot
+ * changed files.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<number>} Number of songs hashed
+ */
+export async function backfillSongHashes(basePath) {
+  return await invoke('backfill_song_hashes', { basePath });
+}
+
+/**
+ * Inspect a song's audio file on disk, for the technical details panel.
+ * 
+ * Everything is read from the file itself, so this is what the device
+ * actually plays. Codec details are null for formats we can't parse.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number} songId - Song ID
+ * @returns {Promise<SongFileInfo>}
+ * 
+ * @typedef {Object} SongFileInfo
+ * @property {number} songId
+ * @property {string} path - Path relative to jp3/music
+ * @property {string|null} container - Format from the file header ('mp3', 'flac', 'mp4', ...)
+ * @property {{codec: string|null, bitrateKbps: number|null, variableBitrate: boolean, sampleRate: number|null, channels: number|null, bitsPerSample: number|null, durationSecs: number|null}} stream
+ * @property {number} sizeBytes
+ * @property {string} sha256
+ * @property {boolean|null} hashMatchesLibrary - Null if no hash was recorded at save time
+ */
+export async function getSongFileInfo(basePath, songId) {
+  return await invoke('get_song_file_info', { basePath, songId });
+}
+
+/**
+ * Encode a short MP3 clip of a song for sharing (requires ffmpeg).
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number} songId - Song to clip
+ * @param {number|null} start - Seconds into the song (default 0)
+ * @param {number|null} duration - Clip length in seconds (default 30, at most 60)
+ * @param {string} dest - Clip file path, or a folder to write "Artist - Title (preview).mp3" into
+ * @returns {Promise<{path: string, startSecs: number, durationSecs: number, sizeBytes: number}>}
+ */
+export async function exportPreviewClip(basePath, songId, start, duration, dest) {
+  return await invoke('export_preview_clip', { basePath, songId, start, duration, dest });
+}
+
+/**
+ * Measure ReplayGain (track and album gain) for library songs.
+ * 
+ * Each album's tracks are measured on their own and then together, so the
+ * device can level single tracks or keep an album's dynamics on full-album
+ * playback. Needs ffmpeg. Albums already measured are skipped unless forced.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {boolean} [force=false] - Re-measure every album
+ * @returns {Promise<{albumsAnalyzed: number, songsAnalyzed: number, upToDate: number, failed: number}>}
+ */
+export async function analyzeReplayGain(basePath, force = false) {
+  return await invoke('analyze_replay_gain', { basePath, force });
+}
+
+/**
+ * Rewrite the tags of library MP3s to a clean ID3v2.4 set.
+ * 
+ * Title, artist, album, track and year are taken from the library and the
+ * front cover is kept; comments, private frames, ID3v1/APE tags and padding
+ * are removed. Non-MP3 songs are skipped.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number[]|null} [songIds=null] - Songs to clean (default: all)
+ * @returns {Promise<{songsCleaned: number, alreadyClean: number, skipped: number, failed: number, bytesBefore: number, bytesAfter: number}>}
+ */
+export async function cleanLibraryTags(basePath, songIds = null) {
+  return await invoke('clean_library_tags', { basePath, songIds });
+}
+
+/**
+ * Rebuild a damaged library.bin from whatever can still be read.
+ * 
+ * Unreadable songs are dropped; audio files no song points at are added back
+ * from their tags. Playlists are remapped. The damaged files are backed up first.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<{backupId: string|null, songsKept: number, songsDropped: number, songsRelinked: number, playlistsUpdated: number}>}
+ */
+export async function repairLibrary(basePath) {
+  return await invoke('repair_library', { basePath });
+}
+
+/**
+ * Find files in jp3/music that no active song points at.
+ * 
+ * Saves cut off before library.bin was written leave their copies behind.
+ * With an action the files are then deleted, or the audio ones added back
+ * as songs from their tags.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {'delete'|'adopt'|null} [action=null] - What to do with the files (default: only report)
+ * @returns {Promise<{files: Array<{path: string, sizeBytes: number, isAudio: boolean}>, wastedBytes: number, filesDeleted: number, bytesFreed: number, songsAdopted: number, duplicatesSkipped: number, songIds: number[], failedFiles: string[]}>}
+ */
+export async function scanOrphanedFiles(basePath, action = null) {
+  return await invoke('scan_orphaned_files', { basePath, action });
+}
+
+/**
+ * Songs whose audio file no longer exists, e.g. after files were moved
+ * around on the card by hand. Fix each with `relinkSong`.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<Array>} Songs in the same shape as `loadLibrary` returns
+ */
+export async function scanMissingFiles(basePath) {
+  return await invoke('scan_missing_files', { basePath });
+}
+
+/**
+ * Point a song at the file it moved to, instead of re-importing it.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number} songId - The song to relink
+ * @param {string} newPath - The file, relative to jp3/music or absolute (files outside jp3/music are adopted in place)
+ * @returns {Promise<string>} The path stored for the song
+ */
+export async function relinkSong(basePath, songId, newPath) {
+  return await invoke('relink_song', { basePath, songId, newPath });
+}
+
+/**
+ * Size of the cache of device transcodes made by `exportSelection`.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<{fileCount: number, totalBytes: number, oldestUsedAt: number|null}>}
+ */
+export async function getTranscodeCacheStats(basePath) {
+  return await invoke('get_transcode_cache_stats', { basePath });
+}
+
+/**
+ * Free space taken by device transcodes. Transcodes unused for `unusedDays`
+ * go first, then the least recently used until the cache fits `maxBytes`.
+ * At least one of the two is required.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number|null} maxBytes - Size the cache may keep
+ * @param {number|null} unusedDays - Remove transcodes not used for this long
+ * @returns {Promise<{filesRemoved: number, bytesFreed: number}>}
+ */
+export async function pruneTranscodeCache(basePath, maxBytes = null, unusedDays = null) {
+  return await invoke('prune_transcode_cache', { basePath, maxBytes, unusedDays });
+}
+
+/**
+ * Pick songs whose audio fits in a byte budget, e.g. to fill a 16GB card.
+ * 
+ * Pass the returned songIds to `exportSelection`. Only audio is counted, so
+ * leave some room for covers and library.bin.
+ * 
+ * `mostPlayed` uses play counts imported with the songs; whole albums rank
+ * by their total plays, and by their best song otherwise.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number} maxBytes - Audio budget in bytes
+ * @param {{favoritesFirst?: boolean, priority?: 'libraryOrder'|'highestRated'|'recentlyAdded'|'mostPlayed', wholeAlbums?: boolean}|null} [preferences] - How songs are ranked
+ * @returns {Promise<{songIds: number[], excludedSongIds: number[], selectedBytes: number, excludedBytes: number}>}
+ */
+export async function selectSongsForSize(basePath, maxBytes, preferences = null) {
+  return await invoke('select_songs_for_size', { basePath, maxBytes, preferences });
+}
+
+/**
+ * Write a printable catalogue of the library to a file.
+ * 
+ * Albums are listed under their album artist, oldest first, with track
+ * numbers and durations. HTML is a standalone page styled for printing.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {string} dest - File to write (e.g. from a save dialog)
+ * @param {'html'|'markdown'} format - Report format
+ * @returns {Promise<{path: string, artistCount: number, albumCount: number, songCount: number}>}
+ */
+export async function exportLibraryReport(basePath, dest, format) {
+  return await invoke('export_library_report', { basePath, dest, format });
+}
+
+/**
+ * Export the library (songs, ratings, favorites, playlists) as JSON.
+ * 
+ * Song paths are relative to jp3/music, so the JSON can sit at the root of
+ * the old library and `importLibraryJson` will find the audio next to it.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {string} dest - JSON file to write
+ * @returns {Promise<{path: string, songCount: number, playlistCount: number}>}
+ */
+export async function exportLibraryJson(basePath, dest) {
+  return await invoke('export_library_json', { basePath, dest });
+}
+
+/**
+ * Recreate songs, favorites and playlists from an exported library JSON,
+ * copying the referenced audio into this library.
+ * 
+ * Songs already in the library are skipped; songs whose audio can't be
+ * found are listed in `filesMissing`.
+ * 
+ * @param {string} basePath - The library to import into (created if needed)
+ * @param {string} jsonPath - JSON written by `exportLibraryJson`
+ * @param {string|null} [sourcePath=null] - Library root holding the audio (defaults to the JSON's folder)
+ * @returns {Promise<{songsImported: number, duplicatesSkipped: number, filesMissing: string[], favoritesRestored: number, playlistsCreated: number}>}
+ */
+export async function importLibraryJson(basePath, jsonPath, sourcePath = null) {
+  return await invoke('import_library_json', { basePath, jsonPath, sourcePath });
+}
+
+/**
+ * Import music and playlists from an iTunes / Music.app Library.xml export.
+ * 
+ * Track files are copied with their iTunes metadata (track and disc numbers,
+ * years, ratings, play counts); loved tracks become favorites and user
+ * playlists are recreated. Podcasts, videos and cloud-only tracks are skipped.
+ * 
+ * @param {string} basePath - The library to import into (created if needed)
+ * @param {string} xmlPath - The exported Library.xml
+ * @param {string|null} [musicFolder=null] - Where the iTunes media folder is now, if it moved since the export
+ * @returns {Promise<{songsImported: number, duplicatesSkipped: number, tracksSkipped: number, filesMissing: string[], incompleteFiles: string[], favoritesRestored: number, playlistsCreated: number}>}
+ */
+export async function importItunesLibrary(basePath, xmlPath, musicFolder = null) {
+  return await invoke('import_itunes_library', { basePath, xmlPath, musicFolder });
+}
+
+/**
+ * Index a music folder into the library without copying it.
+ * 
+ * Songs are registered with the absolute path of their file, which stays
+ * where it is; delete, compaction and tag cleanup never touch it. Adopted
+ * songs can't be played on the device until copied in, either now in the
+ * background (`copyFiles`) or later with `copyAdoptedSongs`.
+ * 
+ * @param {string} basePath - The library to adopt into (created if needed)
+ * @param {string} path - The music folder to adopt
+ * @param {boolean} [copyFiles=false] - Queue copying the files into the library
+ * @returns {Promise<{songsAdopted: number, duplicatesSkipped: number, protectedSkipped: number, incompleteFiles: string[], skippedFiles: string[], songIds: number[], copyQueued: boolean}>}
+ */
+export async function scanAndAdopt(basePath, path, copyFiles = false) {
+  return await invoke('scan_and_adopt', { basePath, path, copyFiles });
+}
+
+/**
+ * Copy adopted songs' files into the music buckets. The originals stay.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number[]|null} [songIds=null] - Songs to copy (default: every adopted song)
+ * @returns {Promise<{filesCopied: number, songsUpdated: number, missingFiles: string[], failedFiles: string[]}>}
+ */
+export async function copyAdoptedSongs(basePath, songIds = null) {
+  return await invoke('copy_adopted_songs', { basePath, songIds });
+}
+
+/**
+ * Copy a selection of the library to another drive as a library of its own.
+ * 
+ * Songs in the chosen playlists are included. Songs, artists and albums get
+ * new IDs; only the covers the selection uses are copied, with thumbnails
+ * generated for any that lack them. With an encode profile (e.g. the target
+ * device's `encodeProfile`), songs that aren't MP3 are transcoded with
+ * ffmpeg; transcodes are cached for later exports.
+ * 
+ * A drive holding only the firmware's device marker is accepted; its limits
+ * are checked first (`FirmwareIncompatible`) and the marker is kept.
+ * library.bin is written in the newest version the firmware reads; pass
+ * `libraryVersion` for firmware that reads an older one but hasn't said so.
+ * 
+ * The library's auto-playlists are generated on the drive from the
+ * exported songs.
+ * 
+ * If the export is interrupted after songs were copied (card pulled, app
+ * closed), the partial library is kept; see `resumeSync`.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number[]} songIds - Songs to export
+ * @param {number[]} playlistIds - Playlists to export, with their songs
+ * @param {string} destPath - Drive or folder to create the new library in
+ * @param {{mp3VbrQuality: number}|null} [profile] - Re-encode non-MP3 songs (LAME -V quality)
+ * @param {number|null} [libraryVersion] - Older library.bin version to write (remembered by the card)
+ * @returns {Promise<{songsExported: number, songsSkipped: number, playlistsExported: number, songsTranscoded: number, coversExported: number, bytesCopied: number, songsVerified: number, autoPlaylistsExported: number}>}
+ */
+export async function exportSelection(basePath, songIds, playlistIds, destPath, profile = null, libraryVersion = null) {
+  return await invoke('export_selection', { basePath, songIds, playlistIds, destPath, profile, libraryVersion });
+}
+
+/**
+ * The export that was interrupted before it finished, if any.
+ * 
+ * Check on launch and offer `resumeSync` or `discardSync`; no new export
+ * can start while one is pending.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<{songIds: number[], playlistIds: number[], destPath: string, profile: {mp3VbrQuality: number}|null, libraryVersion: number|null, startedAt: number, copied: Object<string, string>}|null>}
+ */
+export async function getPendingSync(basePath) {
+  return await invoke('get_pending_sync', { basePath });
+}
+
+/**
+ * Finish an interrupted export.
+ * 
+ * Files already on the drive are checked by hash and kept if they match
+ * (counted in songsVerified); everything else is copied as usual.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<{songsExported: number, songsSkipped: number, playlistsExported: number, songsTranscoded: number, coversExported: number, bytesCopied: number, songsVerified: number, autoPlaylistsExported: number}>}
+ */
+export async function resumeSync(basePath) {
+  return await invoke('resume_sync', { basePath });
+}
+
+/**
+ * Abandon an interrupted export, removing its partial library if the drive
+ * is connected.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<boolean>} False if there was nothing to discard
+ */
+export async function discardSync(basePath) {
+  return await invoke('discard_sync', { basePath });
+}
+
+/**
+ * Preview an export without writing anything.
+ * 
+ * Lists each file the export would copy, delete or skip, with the reason
+ * (new, changedHash, alreadyCopied, orphanedOnDevice, excluded), plus totals.
+ * If an interrupted export to destPath is pending, previews resuming it.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number[]} songIds - Songs to export
+ * @param {number[]} playlistIds - Playlists to export (their songs are included)
+ * @param {string} destPath - Drive to export to
+ * @param {{mp3VbrQuality: number}|null} profile - Encode profile, or null to copy as-is
+ * @param {number|null} [libraryVersion] - Older library.bin version to write, as for exportSelection
+ * @returns {Promise<{destPath: string, resumesPending: boolean, files: {action: 'copy'|'delete'|'skip', reason: string, path: string, songId: number|null, bytes: number, transcode: boolean}[], filesToCopy: number, filesToDelete: number, filesSkipped: number, bytesToCopy: number, bytesToFree: number}>}
+ */
+export async function planDeviceSync(basePath, songIds, playlistIds, destPath, profile = null, libraryVersion = null) {
+  return await invoke('plan_device_sync', { basePath, songIds, playlistIds, destPath, profile, libraryVersion });
+}
+
+/**
+ * Save a plan from planDeviceSync as plain text (for bug reports).
+ * 
+ * @param {Object} report - The plan returned by planDeviceSync
+ * @param {string} dest - File to write
+ * @returns {Promise<void>}
+ */
+export async function exportSyncPlan(report, dest) {
+  return await invoke('export_sync_plan', { report, dest });
+}
+
+/**
+ * Past exports, most recent first.
+ * 
+ * Each finished export (or resumed one) is recorded with the drive's device
+ * ID, what was copied and whether the exported library passed verification.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {string|null} deviceId - Only exports to this device, or null for all
+ * @returns {Promise<{deviceId: string|null, destPath: string, syncedAt: number, resumed: boolean, songsExported: number, filesCopied: number, bytesCopied: number, verified: boolean}[]>}
+ */
+export async function getSyncHistory(basePath, deviceId = null) {
+  return await invoke('get_sync_history', { basePath, deviceId });
+}
+
+/** Emitted when closing the window was held back by running library writes */
+export const CLOSE_BLOCKED_EVENT = 'app://close-blocked';
+
+/**
+ * Subscribe to blocked window closes, to ask the user whether to quit anyway.
+ * 
+ * @param {(payload: {tasks: {name: string, io: boolean, running: boolean}[], compactionRunning: boolean}) => void} callback
+ * @returns {Promise<() => void>} Unsubscribe function
+ */
+export async function onCloseBlocked(callback) {
+  return await listen(CLOSE_BLOCKED_EVENT, ({ payload }) => callback(payload));
+}
+
+/**
+ * Cancel queued tasks and any compaction, wait for running work to stop,
+ * then exit the app.
+ * 
+ * An export cut short this way can be finished with `resumeSync`.
+ * 
+ * @returns {Promise<ShutdownReport>} What was stopped (the app exits right after)
+ * 
+ * @typedef {Object} ShutdownReport
+ * @property {number} shutDownAt - Unix seconds
+ * @property {string[]} cancelledTasks - Background tasks dropped before they started
+ * @property {boolean} compactionCancelled - A compaction was stopped
+ * @property {boolean} clean - Everything running stopped before exit
+ */
+export async function gracefulShutdown() {
+  return await invoke('graceful_shutdown');
+}
+
+/**
+ * What the last `gracefulShutdown` stopped, or null.
+ * 
+ * @returns {Promise<ShutdownReport|null>}
+ */
+export async function getLastShutdown() {
+  return await invoke('get_last_shutdown');
+}
+
+/**
+ * Check the library on a card against the limits its firmware declared.
+ * 
+ * Firmware declares its limits in jp3/metadata/device.json. Cards that
+ * declared none report no problems.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<{firmwareVersion: string|null, problems: {problem: string, fix: string}[]}>}
+ */
+export async function checkDeviceCompatibility(basePath) {
+  return await invoke('check_device_compatibility', { basePath });
+}
+
+/**
+ * Get the thresholds getLibraryStats uses to recommend compacting.
+ * 
+ * Stored per library in jp3/metadata/library_settings.json. A null
+ * threshold is not checked.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<CompactionThresholds>}
+ * 
+ * @typedef {Object} CompactionThresholds
+ * @property {number|null} deletedPercentage - Deleted songs percentage (default 20)
+ * @property {number|null} deadStringBytes - Bytes of dead strings
+ * @property {number|null} wastedBytes - Total bytes compaction would reclaim
+ */
+export async function getCompactionThresholds(basePath) {
+  return await invoke('get_compaction_thresholds', { basePath });
+}
+
+/**
+ * Set the thresholds getLibraryStats uses to recommend compacting.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {CompactionThresholds} thresholds - New thresholds
+ * @returns {Promise<void>}
+ */
+export async function setCompactionThresholds(basePath, thresholds) {
+  return await invoke('set_compaction_thresholds', { basePath, thresholds });
+}
+
 /**
  * Edit a song's metadata.
  * 
  * This soft-deletes the old song entry and creates a new one with updated metadata.
  * The audio file path is preserved. Use `compactLibrary` to clean up the old entry.
+ * When only the title changes, the entry is updated in place and keeps its ID.
  * 
  * Note: If the song is in playlists, those playlists will reference the old (deleted) ID
  * until compaction remaps them to the new ID.
@@ -239,6 +970,7 @@ export async function getLibraryStats(basePath) {
  * 
  * @typedef {Object} EditSongResult
  * @property {number} newSongId - The new song ID
+ * @property {boolean} inPlace - Title-only edit made in place (ID unchanged)
  * @property {boolean} artistCreated - Whether a new artist was created
  * @property {boolean} albumCreated - Whether a new album was created
  */
@@ -246,29 +978,73 @@ export async function editSongMetadata(basePath, songId, metadata) {
   return await invoke('edit_song_metadata', { basePath, songId, newMetadata: metadata });
 }
 
+/**
+ * Edit several songs' metadata at once.
+ * 
+ * Works like `editSongMetadata` for each song, but library.bin is written once
+ * and playlists are remapped once. A song may only appear once per batch.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {Array<{songId: number, metadata: Object}>} edits - New metadata per song
+ * @returns {Promise<EditSongsResult>} Result with the new song IDs
+ * 
+ * @typedef {Object} EditSongsResult
+ * @property {number[]} newSongIds - New song IDs, in the same order as the edits
+ * @property {number} editedInPlace - Title-only edits made in place (IDs unchanged)
+ * @property {number} artistsCreated - Number of artists created
+ * @property {number} albumsCreated - Number of albums created
+ * @property {number} playlistsUpdated - Number of playlists updated with new IDs
+ */
+export async function editSongsMetadata(basePath, edits) {
+  return await invoke('edit_songs_metadata', { basePath, edits });
+}
+
 /**
  * Edit an album's metadata (name, artist, year).
  * 
- * This updates the album entry and all songs in the album to reflect the changes.
- * If the artist changes, a new artist entry is created if needed.
+ * The album entry is updated in place; renaming it or changing its year
+ * leaves every song entry alone. If the artist changes, a new artist entry
+ * is created if needed and songs credited to the old album artist move to it.
  * 
  * @param {string} basePath - The base library directory path
  * @param {number} albumId - ID of the album to edit
  * @param {string} newName - New album name
- * @param {string} newArtistName - New artist name
+ * @param {string|null} newArtistName - New artist name (null keeps the current one)
  * @param {number|null} newYear - New year (optional)
  * @returns {Promise<EditAlbumResult>} Result with update info
  * 
  * @typedef {Object} EditAlbumResult
- * @property {number} songsUpdated - Number of songs updated
+ * @property {number} songsUpdated - Number of songs on the album
+ * @property {number} songsRewritten - Song entries rewritten because their artist moved
  * @property {boolean} artistCreated - Whether a new artist was created
  * @property {string} oldName - Previous album name
  * @property {string} newName - New album name
  */
-export async function editAlbum(basePath, albumId, newName, newArtistName, newYear = null) {
+export async function editAlbum(basePath, albumId, newName, newArtistName = null, newYear = null) {
   return await invoke('edit_album', { basePath, albumId, newName, newArtistName, newYear });
 }
 
+/**
+ * Merge albums split by inconsistent tagging (e.g. deluxe vs standard naming).
+ * 
+ * Songs on the source albums move to the target, keeping their IDs. The target
+ * takes over a source cover if it has none; other source covers are removed.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number} targetId - Album to keep
+ * @param {number[]} sourceIds - Albums to fold into the target
+ * @returns {Promise<MergeAlbumsResult>} Result with counts
+ * 
+ * @typedef {Object} MergeAlbumsResult
+ * @property {number} songsMoved - Songs moved onto the target album
+ * @property {number} albumsMerged - Source albums merged (empty until compaction)
+ * @property {boolean} coverAdopted - Whether the target took over a source cover
+ * @property {number} coversRemoved - Other source covers deleted
+ */
+export async function mergeAlbums(basePath, targetId, sourceIds) {
+  return await invoke('merge_albums', { basePath, targetId, sourceIds });
+}
+
 /**
  * Edit an artist's metadata (name only).
  * 
@@ -300,8 +1076,18 @@ export async function editArtist(basePath, artistId, newName) {
  * - Strings not referenced by any active entry
  * 
  * This is a full rewrite operation - use sparingly to minimize SD card wear.
+ * library.bin and the playlists are backed up first (see backupLibrary).
+ * 
+ * Progress is emitted as `library://compaction-progress` events
+ * ({ phase, done, total }). A cancelled compaction rejects with an error
+ * starting with "CompactionCancelled" and leaves the library unchanged.
+ * 
+ * With `renumberFiles`, audio files are also renumbered from 00/001,
+ * closing gaps left by deleted songs and merging underfilled buckets
+ * (this moves every file after the first gap, so it's opt-in).
  * 
  * @param {string} basePath - The base library directory path
+ * @param {boolean} [renumberFiles=false] - Renumber audio files and merge buckets
  * @returns {Promise<CompactResult>} Result with removal counts
  * 
  * @typedef {Object} CompactResult
@@ -312,9 +1098,150 @@ export async function editArtist(basePath, artistId, newName) {
  * @property {number} oldSizeBytes - Old file size
  * @property {number} newSizeBytes - New file size
  * @property {number} bytesSaved - Bytes saved
+ * @property {number} filesRenumbered - Audio files moved to close gaps (renumberFiles only)
+ * @property {number} bucketsRemoved - Empty bucket folders removed (renumberFiles only)
+ * @property {boolean} dryRun - Nothing was written (see previewCompaction)
+ */
+export async function compactLibrary(basePath, renumberFiles = false) {
+  return await invoke('compact_library_with_progress', { basePath, renumberFiles });
+}
+
+/**
+ * What compactLibrary would remove and save, without writing anything.
+ * 
+ * No backup is taken and no files move. `bucketsRemoved` is not predicted.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {boolean} [renumberFiles=false] - Also count files renumbering would move
+ * @returns {Promise<CompactResult>} Counts with `dryRun` set
+ */
+export async function previewCompaction(basePath, renumberFiles = false) {
+  return await invoke('compact_library', { basePath, renumberFiles, dryRun: true });
+}
+
+/**
+ * Back up library.bin and the playlists into jp3/backups/{id}/.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<BackupInfo>} The new backup
+ * 
+ * @typedef {Object} BackupInfo
+ * @property {string} id - Backup ID, passed to restoreLibrary
+ * @property {number} createdAt - Creation time (Unix seconds)
+ * @property {number} libraryBytes - Size of the backed-up library.bin
+ * @property {number} playlistCount - Playlist files in the backup
+ */
+export async function backupLibrary(basePath) {
+  return await invoke('backup_library', { basePath });
+}
+
+/**
+ * List the library's backups, newest first.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<BackupInfo[]>}
+ */
+export async function listBackups(basePath) {
+  return await invoke('list_backups', { basePath });
+}
+
+/**
+ * Restore library.bin and the playlists from a backup.
+ * 
+ * The current state is backed up first. Audio files are not part of a
+ * backup, so songs whose files were removed by a compaction stay unplayable.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {string} backupId - ID from listBackups
+ * @returns {Promise<BackupInfo>} The restored backup
  */
-export async function compactLibrary(basePath) {
-  return await invoke('compact_library', { basePath });
+export async function restoreLibrary(basePath, backupId) {
+  return await invoke('restore_library', { basePath, backupId });
+}
+
+/**
+ * Delete a backup.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {string} backupId - ID from listBackups
+ * @returns {Promise<void>}
+ */
+export async function deleteBackup(basePath, backupId) {
+  return await invoke('delete_backup', { basePath, backupId });
+}
+
+/**
+ * Get how many backups are kept.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<number|null>} Newest backups kept (default 10), or null to keep all
+ */
+export async function getBackupRetention(basePath) {
+  return await invoke('get_backup_retention', { basePath });
+}
+
+/**
+ * Set how many backups are kept.
+ * 
+ * Older backups are pruned the next time one is taken (by backupLibrary,
+ * a compaction or a restore). A backup being restored is never pruned.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {number|null} count - Backups to keep (at least 1), or null to keep all
+ * @returns {Promise<void>}
+ */
+export async function setBackupRetention(basePath, count) {
+  return await invoke('set_backup_retention', { basePath, count });
+}
+
+/**
+ * library.bin writes an earlier run started but never finished.
+ * 
+ * While any are listed, loadLibrary fails with an InterruptedWrite error.
+ * Check on startup and offer recoverInterruptedWrites.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<{writes: {operationId: string, operation: string, startedAt: number}[], libraryIntact: boolean}>}
+ */
+export async function getInterruptedWrites(basePath) {
+  return await invoke('get_interrupted_writes', { basePath });
+}
+
+/**
+ * Recover from interrupted library.bin writes and clear their markers.
+ * 
+ * With a backup ID that backup is restored; otherwise library.bin is kept
+ * if its checksum still passes, or repaired if not.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @param {string|null} [backupId] - Backup to restore instead
+ * @returns {Promise<'intact'|'restored'|'repaired'>}
+ */
+export async function recoverInterruptedWrites(basePath, backupId = null) {
+  return await invoke('recover_interrupted_writes', { basePath, backupId });
+}
+
+/**
+ * Cancel a running compaction (before it swaps in the new library.bin).
+ * 
+ * @returns {Promise<boolean>} False if no compaction was running
+ */
+export async function cancelCompaction() {
+  return await invoke('cancel_compaction');
+}
+
+/**
+ * Run one small idle-time compaction step.
+ * 
+ * Trims deleted songs off the end of the song table only. Writes are capped
+ * per library per day (the count is kept on the card, so restarting doesn't
+ * reset it); once the budget is spent, steps do nothing.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<{songsTrimmed: number, playlistsUpdated: number, bytesWritten: number, budgetRemaining: number, done: boolean}>}
+ */
+export async function compactLibraryIdleStep(basePath) {
+  return await invoke('compact_library_idle_step', { basePath });
 }
 
 // =============================================================================
@@ -452,3 +1379,17 @@ export async function removeSongsFromPlaylist(basePath, playlistId, songIds) {
 export async function renamePlaylist(basePath, playlistId, newName) {
   return await invoke('rename_playlist', { basePath, playlistId, newName });
 }
+
+/**
+ * Rewrite playlists the card's firmware can't read in the v1 layout.
+ * 
+ * Fix for the playlist problem checkDeviceCompatibility reports. Added
+ * dates and the manual order flag are dropped; playlists saved after the
+ * firmware declared its version are already written this way.
+ * 
+ * @param {string} basePath - The base library directory path
+ * @returns {Promise<number>} Number of playlists rewritten
+ */
+export async function convertPlaylistsForFirmware(basePath) {
+  return await invoke('convert_playlists_for_firmware', { basePath });
+}